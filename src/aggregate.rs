@@ -0,0 +1,677 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use crate::expression::DataContext;
+use crate::expression::Expression;
+use crate::query::Query;
+use crate::schema::ColumnNumber;
+use crate::value::Collation;
+use crate::value::ConstantValue;
+use crate::value::TypeAffinity;
+use crate::value::Value;
+use crate::value::ValueCmp;
+use crate::value::DEFAULT_COLLATION;
+
+#[derive(Debug)]
+pub enum Error {
+    IntegerOverflow,
+    ColumnNotInGroup,
+}
+
+impl std::error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IntegerOverflow => {
+                write!(f, "integer overflow")
+            }
+            Self::ColumnNotInGroup => {
+                write!(f, "column not in group")
+            }
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Built-in aggregate functions.
+///
+/// https://www.sqlite.org/lang_aggfunc.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Total,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    /// Look up the aggregate function by the lowercased name and the number
+    /// of arguments.
+    ///
+    /// min() and max() with more than 1 arguments are scalar functions.
+    pub fn lookup(name: &[u8], n_args: usize) -> Option<Self> {
+        match (name, n_args) {
+            (b"count", 0 | 1) => Some(Self::Count),
+            (b"sum", 1) => Some(Self::Sum),
+            (b"total", 1) => Some(Self::Total),
+            (b"avg", 1) => Some(Self::Avg),
+            (b"min", 1) => Some(Self::Min),
+            (b"max", 1) => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AggregateCall {
+    pub function: AggregateFunction,
+    /// [None] for `count(*)`.
+    pub arg: Option<Expression>,
+    /// Only the distinct values of the argument are fed to the function.
+    pub distinct: bool,
+}
+
+/// Aggregate functions and GROUP BY of a SELECT statement.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregation {
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    /// Aggregate function calls referred by [Expression::Aggregate].
+    pub calls: Vec<AggregateCall>,
+    /// Columns referred outside of aggregate functions.
+    pub columns: Vec<ColumnNumber>,
+}
+
+impl Aggregation {
+    pub fn new(group_by: Vec<Expression>) -> Self {
+        Self {
+            group_by,
+            ..Default::default()
+        }
+    }
+
+    pub fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        for expr in self.group_by.iter_mut() {
+            expr.bind_parameter(idx, value);
+        }
+        if let Some(having) = &mut self.having {
+            having.bind_parameter(idx, value);
+        }
+        for call in self.calls.iter_mut() {
+            if let Some(arg) = &mut call.arg {
+                arg.bind_parameter(idx, value);
+            }
+        }
+    }
+
+    /// Call the function for each column referred by GROUP BY, HAVING and the
+    /// arguments of the aggregate functions.
+    pub fn for_each_column<F: FnMut(&ColumnNumber)>(&self, f: &mut F) {
+        for expr in self.group_by.iter() {
+            expr.for_each_column(f);
+        }
+        if let Some(having) = &self.having {
+            having.for_each_column(f);
+        }
+        for call in self.calls.iter() {
+            if let Some(arg) = &call.arg {
+                arg.for_each_column(f);
+            }
+        }
+    }
+
+    /// Whether the query needs aggregation.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || !self.calls.is_empty()
+    }
+
+    /// Whether the aggregation is only `count(*)` without GROUP BY, HAVING and
+    /// bare columns, so the result depends only on the number of rows.
+    pub fn is_count_all(&self) -> bool {
+        self.group_by.is_empty()
+            && self.having.is_none()
+            && self.columns.is_empty()
+            && self
+                .calls
+                .iter()
+                .all(|call| call.function == AggregateFunction::Count && call.arg.is_none())
+    }
+
+    /// The result of [Self::run()] from the number of rows if
+    /// [Self::is_count_all()].
+    pub fn count_all(&self, n_rows: u64) -> GroupData {
+        assert!(self.is_count_all());
+        GroupData {
+            columns: Vec::new(),
+            aggregates: vec![Some(ConstantValue::Integer(n_rows as i64)); self.calls.len()],
+        }
+    }
+
+    /// Group all the rows from the query and return the groups in the order of
+    /// the group keys. Groups not matching HAVING are excluded.
+    ///
+    /// Without GROUP BY, all the rows make a single group even if there is no
+    /// row.
+    ///
+    /// TODO: Stream groups if rows are sorted by an index on the group keys.
+    pub fn run(&self, query: &mut Query) -> anyhow::Result<Vec<GroupData>> {
+        let mut groups: BTreeMap<GroupKey, (Vec<Option<ConstantValue>>, Vec<CallState>)> =
+            BTreeMap::new();
+        // The only min() or max() call.
+        let min_max_call = {
+            let mut min_max_calls = self.calls.iter().enumerate().filter(|(_, call)| {
+                matches!(
+                    call.function,
+                    AggregateFunction::Min | AggregateFunction::Max
+                )
+            });
+            match (min_max_calls.next(), min_max_calls.next()) {
+                (Some((i, _)), None) => Some(i),
+                _ => None,
+            }
+        };
+        while let Some(data) = query.next()? {
+            let mut key = Vec::with_capacity(self.group_by.len());
+            for expr in self.group_by.iter() {
+                let (value, _, collation) = expr.execute(Some(&data))?;
+                key.push((
+                    value.map(ConstantValue::copy_from),
+                    collation.map_or(DEFAULT_COLLATION.clone(), |(c, _)| c.clone()),
+                ));
+            }
+            let (columns, states) = groups
+                .entry(GroupKey(key))
+                .or_insert_with(|| (Vec::new(), self.new_call_states()));
+            let mut min_max_updated = false;
+            for (i, (call, state)) in self.calls.iter().zip(states.iter_mut()).enumerate() {
+                let updated = match &call.arg {
+                    Some(arg) => {
+                        let (value, _, collation) = arg.execute(Some(&data))?;
+                        let collation = collation.map_or(&DEFAULT_COLLATION, |(c, _)| c);
+                        // NULLs are ignored by all the functions anyway.
+                        if let (Some(seen), Some(value)) = (&mut state.seen, &value) {
+                            let key = GroupKey(vec![(
+                                Some(ConstantValue::copy_from(value.clone())),
+                                collation.clone(),
+                            )]);
+                            if !seen.insert(key) {
+                                continue;
+                            }
+                        }
+                        state.accumulator.step(value, collation)?
+                    }
+                    // count(*) counts all the rows.
+                    None => state
+                        .accumulator
+                        .step(Some(Value::Integer(1)), &DEFAULT_COLLATION)?,
+                };
+                min_max_updated |= min_max_call == Some(i) && updated;
+            }
+            // With only one min() or max() call, bare columns take the values of the row
+            // holding its result as SQLite does. Otherwise they take the values of the first
+            // row in the group. This matters for the group keys too because values equal by
+            // the collation may differ.
+            if columns.len() < self.columns.len() || min_max_updated {
+                columns.clear();
+                for column in self.columns.iter() {
+                    let value = data
+                        .get_column_value(column)
+                        .map_err(crate::expression::Error::FailGetColumn)?;
+                    columns.push(value.map(ConstantValue::copy_from));
+                }
+            }
+        }
+        if groups.is_empty() && self.group_by.is_empty() {
+            groups.insert(
+                GroupKey(Vec::new()),
+                (vec![None; self.columns.len()], self.new_call_states()),
+            );
+        }
+
+        let mut results = Vec::with_capacity(groups.len());
+        for (_, (columns, states)) in groups {
+            let group = GroupData {
+                columns: self.columns.iter().copied().zip(columns).collect(),
+                aggregates: states
+                    .into_iter()
+                    .map(|state| state.accumulator.finalize())
+                    .collect::<Result<Vec<_>>>()?,
+            };
+            if let Some(having) = &self.having {
+                let (value, _, _) = having.execute(Some(&group))?;
+                if !value.map(|v| v.as_bool()).unwrap_or(false) {
+                    continue;
+                }
+            }
+            results.push(group);
+        }
+        Ok(results)
+    }
+
+    fn new_call_states(&self) -> Vec<CallState> {
+        self.calls
+            .iter()
+            .map(|call| CallState {
+                accumulator: Accumulator::new(call.function),
+                seen: call.distinct.then(BTreeSet::new),
+            })
+            .collect()
+    }
+}
+
+/// The state of an aggregate function call in a group.
+struct CallState {
+    accumulator: Accumulator,
+    /// The values already fed if the call is DISTINCT. The values are compared
+    /// by the collation of the argument.
+    seen: Option<BTreeSet<GroupKey>>,
+}
+
+/// The group key values with their collations.
+///
+/// NULLs are in the same group.
+struct GroupKey(Vec<(Option<ConstantValue>, Collation)>);
+
+impl Ord for GroupKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        for ((v1, collation), (v2, _)) in self.0.iter().zip(other.0.iter()) {
+            let ordering = match (v1, v2) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(v1), Some(v2)) => {
+                    ValueCmp::new(&v1.as_value(), collation).compare(&v2.as_value())
+                }
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl PartialOrd for GroupKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for GroupKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GroupKey {}
+
+/// The values of a group to evaluate expressions after aggregation.
+pub struct GroupData {
+    columns: Vec<(ColumnNumber, Option<ConstantValue>)>,
+    aggregates: Vec<Option<ConstantValue>>,
+}
+
+impl DataContext for GroupData {
+    fn get_column_value(
+        &self,
+        column_idx: &ColumnNumber,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        match self.columns.iter().find(|(column, _)| column == column_idx) {
+            Some((_, value)) => Ok(value.as_ref().map(|v| v.as_value())),
+            None => Err(Box::new(Error::ColumnNotInGroup)),
+        }
+    }
+
+    fn get_aggregate_value(
+        &self,
+        idx: usize,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(self.aggregates[idx].as_ref().map(|v| v.as_value()))
+    }
+}
+
+fn to_real(value: Value) -> f64 {
+    match value.force_apply_type_affinity(TypeAffinity::Real) {
+        Value::Real(d) => d,
+        _ => unreachable!("real affinity is forcibly applied"),
+    }
+}
+
+/// The intermediate state of an aggregate function.
+#[derive(Debug)]
+pub enum Accumulator {
+    Count(i64),
+    /// sum() returns an integer if all the non-NULL inputs are integers.
+    Sum {
+        integer: i64,
+        real: f64,
+        approx: bool,
+        overflow: bool,
+        n: i64,
+    },
+    Total(f64),
+    Avg {
+        sum: f64,
+        n: i64,
+    },
+    MinMax {
+        value: Option<ConstantValue>,
+        max: bool,
+    },
+}
+
+impl Accumulator {
+    pub fn new(function: AggregateFunction) -> Self {
+        match function {
+            AggregateFunction::Count => Self::Count(0),
+            AggregateFunction::Sum => Self::Sum {
+                integer: 0,
+                real: 0.0,
+                approx: false,
+                overflow: false,
+                n: 0,
+            },
+            AggregateFunction::Total => Self::Total(0.0),
+            AggregateFunction::Avg => Self::Avg { sum: 0.0, n: 0 },
+            AggregateFunction::Min => Self::MinMax {
+                value: None,
+                max: false,
+            },
+            AggregateFunction::Max => Self::MinMax {
+                value: None,
+                max: true,
+            },
+        }
+    }
+
+    /// Feed a value. NULLs are ignored by all the functions.
+    ///
+    /// Returns whether the row holds the result of min() or max() so far. A
+    /// NULL is the result until a non-NULL value is fed.
+    pub fn step(&mut self, value: Option<Value>, collation: &Collation) -> Result<bool> {
+        let Some(value) = value else {
+            return Ok(matches!(self, Self::MinMax { value: None, .. }));
+        };
+        match self {
+            Self::Count(n) => *n += 1,
+            Self::Sum {
+                integer,
+                real,
+                approx,
+                overflow,
+                n,
+            } => {
+                *n += 1;
+                // A real value stays real even if it has no fractional part.
+                let value = match value {
+                    Value::Real(d) => Value::Real(d),
+                    value => value.apply_numeric_affinity(),
+                };
+                match value {
+                    Value::Integer(i) => {
+                        *real += i as f64;
+                        if !*approx && !*overflow {
+                            match integer.checked_add(i) {
+                                Some(v) => *integer = v,
+                                None => *overflow = true,
+                            }
+                        }
+                    }
+                    value => {
+                        *real += to_real(value);
+                        *approx = true;
+                    }
+                }
+            }
+            Self::Total(sum) => *sum += to_real(value),
+            Self::Avg { sum, n } => {
+                *sum += to_real(value);
+                *n += 1;
+            }
+            Self::MinMax {
+                value: current,
+                max,
+            } => {
+                let replace = match current {
+                    None => true,
+                    Some(current) => {
+                        let ordering =
+                            ValueCmp::new(&value, collation).compare(&current.as_value());
+                        if *max {
+                            ordering == Ordering::Greater
+                        } else {
+                            ordering == Ordering::Less
+                        }
+                    }
+                };
+                if replace {
+                    *current = Some(ConstantValue::copy_from(value));
+                }
+                return Ok(replace);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn finalize(self) -> Result<Option<ConstantValue>> {
+        match self {
+            Self::Count(n) => Ok(Some(ConstantValue::Integer(n))),
+            Self::Sum {
+                integer,
+                real,
+                approx,
+                overflow,
+                n,
+            } => {
+                if overflow {
+                    Err(Error::IntegerOverflow)
+                } else if n == 0 {
+                    Ok(None)
+                } else if approx {
+                    Ok(Some(ConstantValue::Real(real)))
+                } else {
+                    Ok(Some(ConstantValue::Integer(integer)))
+                }
+            }
+            Self::Total(sum) => Ok(Some(ConstantValue::Real(sum))),
+            Self::Avg { sum, n } => {
+                if n == 0 {
+                    Ok(None)
+                } else {
+                    Ok(Some(ConstantValue::Real(sum / n as f64)))
+                }
+            }
+            Self::MinMax { value, .. } => Ok(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(
+        function: AggregateFunction,
+        values: &[Option<Value>],
+    ) -> Result<Option<ConstantValue>> {
+        let mut accumulator = Accumulator::new(function);
+        for value in values {
+            accumulator.step(value.clone(), &DEFAULT_COLLATION)?;
+        }
+        accumulator.finalize()
+    }
+
+    fn text(s: &str) -> Option<Value<'_>> {
+        Some(Value::Text(s.as_bytes().into()))
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(
+            AggregateFunction::lookup(b"count", 0),
+            Some(AggregateFunction::Count)
+        );
+        assert_eq!(
+            AggregateFunction::lookup(b"count", 1),
+            Some(AggregateFunction::Count)
+        );
+        assert_eq!(
+            AggregateFunction::lookup(b"max", 1),
+            Some(AggregateFunction::Max)
+        );
+        assert_eq!(AggregateFunction::lookup(b"max", 2), None);
+        assert_eq!(AggregateFunction::lookup(b"sum", 0), None);
+        assert_eq!(AggregateFunction::lookup(b"unknown", 1), None);
+    }
+
+    #[test]
+    fn test_count() {
+        assert!(matches!(
+            aggregate(AggregateFunction::Count, &[]).unwrap(),
+            Some(ConstantValue::Integer(0))
+        ));
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Count,
+                &[Some(Value::Integer(1)), None, text("a")]
+            )
+            .unwrap(),
+            Some(ConstantValue::Integer(2))
+        ));
+    }
+
+    #[test]
+    fn test_sum() {
+        assert!(aggregate(AggregateFunction::Sum, &[]).unwrap().is_none());
+        assert!(aggregate(AggregateFunction::Sum, &[None])
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Sum,
+                &[Some(Value::Integer(1)), None, text("2")]
+            )
+            .unwrap(),
+            Some(ConstantValue::Integer(3))
+        ));
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Sum,
+                &[Some(Value::Integer(1)), Some(Value::Real(0.5))]
+            )
+            .unwrap(),
+            Some(ConstantValue::Real(d)) if d == 1.5
+        ));
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Sum,
+                &[Some(Value::Real(1.0)), Some(Value::Real(3.0))]
+            )
+            .unwrap(),
+            Some(ConstantValue::Real(d)) if d == 4.0
+        ));
+        // Non-numeric text is 0.0.
+        assert!(matches!(
+            aggregate(AggregateFunction::Sum, &[Some(Value::Integer(1)), text("a")]).unwrap(),
+            Some(ConstantValue::Real(d)) if d == 1.0
+        ));
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Sum,
+                &[Some(Value::Integer(i64::MAX)), Some(Value::Integer(1))]
+            ),
+            Err(Error::IntegerOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_total_avg() {
+        assert!(matches!(
+            aggregate(AggregateFunction::Total, &[]).unwrap(),
+            Some(ConstantValue::Real(d)) if d == 0.0
+        ));
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Total,
+                &[Some(Value::Integer(i64::MAX)), Some(Value::Integer(1))]
+            )
+            .unwrap(),
+            Some(ConstantValue::Real(d)) if d == i64::MAX as f64 + 1.0
+        ));
+        assert!(aggregate(AggregateFunction::Avg, &[None])
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            aggregate(
+                AggregateFunction::Avg,
+                &[Some(Value::Integer(1)), None, Some(Value::Integer(2))]
+            )
+            .unwrap(),
+            Some(ConstantValue::Real(d)) if d == 1.5
+        ));
+    }
+
+    #[test]
+    fn test_min_max() {
+        let values = [
+            text("b"),
+            None,
+            Some(Value::Integer(10)),
+            Some(Value::Blob(b"a".as_slice().into())),
+            Some(Value::Real(1.5)),
+            text("a"),
+        ];
+        assert!(matches!(
+            aggregate(AggregateFunction::Min, &values).unwrap(),
+            Some(ConstantValue::Real(d)) if d == 1.5
+        ));
+        assert!(matches!(
+            aggregate(AggregateFunction::Max, &values).unwrap(),
+            Some(ConstantValue::Blob(b)) if b == b"a"
+        ));
+        assert!(aggregate(AggregateFunction::Max, &[None])
+            .unwrap()
+            .is_none());
+
+        // NULLs hold the result until a non-NULL value is fed.
+        let mut accumulator = Accumulator::new(AggregateFunction::Min);
+        assert!(accumulator.step(None, &DEFAULT_COLLATION).unwrap());
+        assert!(accumulator
+            .step(Some(Value::Integer(1)), &DEFAULT_COLLATION)
+            .unwrap());
+        assert!(!accumulator.step(None, &DEFAULT_COLLATION).unwrap());
+        assert!(!accumulator
+            .step(Some(Value::Integer(1)), &DEFAULT_COLLATION)
+            .unwrap());
+
+        let mut accumulator = Accumulator::new(AggregateFunction::Max);
+        assert!(accumulator.step(text("a"), &Collation::NoCase).unwrap());
+        assert!(accumulator.step(text("B"), &Collation::NoCase).unwrap());
+        assert!(!accumulator.step(text("b"), &Collation::NoCase).unwrap());
+        assert!(!accumulator.step(None, &Collation::NoCase).unwrap());
+        assert!(matches!(
+            accumulator.finalize().unwrap(),
+            Some(ConstantValue::Text(t)) if t == b"B"
+        ));
+    }
+}
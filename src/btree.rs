@@ -33,6 +33,7 @@ pub const BTREE_PAGE_CELL_POINTER_SIZE: usize = 2;
 const LEAF_FLAG: u8 = 0x08;
 const INDEX_FLAG: u8 = 0x02;
 const TABLE_FLAG: u8 = 0x05;
+pub const BTREE_PAGE_TYPE_LEAF_TABLE: u8 = LEAF_FLAG | TABLE_FLAG;
 
 pub const BTREE_OVERFLOW_PAGE_ID_BYTES: usize = 4;
 
@@ -141,6 +142,16 @@ impl BtreeContext {
 pub struct BtreePageType(u8);
 
 impl BtreePageType {
+    /// The type of the leaf pages of table or index b-trees.
+    #[inline]
+    pub fn leaf(is_table: bool) -> Self {
+        if is_table {
+            Self(BTREE_PAGE_TYPE_LEAF_TABLE)
+        } else {
+            Self(LEAF_FLAG | INDEX_FLAG)
+        }
+    }
+
     #[inline]
     pub fn leaf_type(&self) -> Self {
         Self(self.0 | LEAF_FLAG)
@@ -335,6 +346,10 @@ impl<'a> BtreePageHeaderMut<'a> {
         self.0[7] += size;
     }
 
+    pub fn remove_fragmented_free_bytes(&mut self, size: u8) {
+        self.0[7] -= size;
+    }
+
     pub fn clear_fragmented_free_bytes(&mut self) {
         self.0[7] = 0;
     }
@@ -809,10 +824,125 @@ pub fn allocate_from_unallocated_space(
     new_cell_content_area_offset
 }
 
+/// Add the space to the freeblock list.
+///
+/// Freeblocks are kept in ascending order and merged with the adjacent
+/// freeblocks. The fragmented bytes between them are merged as well. If the
+/// space is at the beginning of the cell content area, the cell content area
+/// is shrunk instead of creating a new freeblock.
+///
+/// This comes from freeSpace() in btree.c of SQLite.
+pub fn free_space(
+    page: &MemPage,
+    buffer: &mut PageBufferMut,
+    offset: usize,
+    size: u16,
+) -> ParseResult<()> {
+    let page_header = BtreePageHeader::from_page_mut(page, buffer);
+    let cell_content_area_offset = page_header.cell_content_area_offset().get() as usize;
+    let fragmented_free_bytes = page_header.fragmented_free_bytes();
+
+    let mut start = offset;
+    let mut end = offset + size as usize;
+    check_corrupt!(end <= buffer.len(), "free space out of range");
+
+    // The offset of the pointer to the next freeblock. This is the first
+    // freeblock offset in the header or the previous freeblock.
+    let header_pointer_offset = page.header_offset + BTREE_FIRST_FREEBLOCK_OFFSET.start;
+    let mut pointer_offset = header_pointer_offset;
+    let mut next_freeblock_offset;
+    loop {
+        next_freeblock_offset = u16::from_be_bytes(
+            buffer[pointer_offset..pointer_offset + 2]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        if next_freeblock_offset == 0 || next_freeblock_offset >= start {
+            break;
+        }
+        check_corrupt!(
+            next_freeblock_offset > pointer_offset,
+            "freeblocks not in ascending order"
+        );
+        check_corrupt!(
+            next_freeblock_offset + 4 <= buffer.len(),
+            "freeblock offset out of range"
+        );
+        pointer_offset = next_freeblock_offset;
+    }
+    check_corrupt!(
+        next_freeblock_offset + 4 <= buffer.len(),
+        "freeblock offset out of range"
+    );
+
+    let mut n_fragmented = 0;
+    // Merge the next freeblock.
+    if next_freeblock_offset != 0 && end + 3 >= next_freeblock_offset {
+        check_corrupt!(end <= next_freeblock_offset, "freeblock overlaps");
+        n_fragmented += next_freeblock_offset - end;
+        let next_size = u16::from_be_bytes(
+            buffer[next_freeblock_offset + 2..next_freeblock_offset + 4]
+                .try_into()
+                .unwrap(),
+        );
+        end = next_freeblock_offset + next_size as usize;
+        check_corrupt!(end <= buffer.len(), "freeblock out of range");
+        next_freeblock_offset = u16::from_be_bytes(
+            buffer[next_freeblock_offset..next_freeblock_offset + 2]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+    }
+    // Merge the previous freeblock.
+    if pointer_offset != header_pointer_offset {
+        let previous_size = u16::from_be_bytes(
+            buffer[pointer_offset + 2..pointer_offset + 4]
+                .try_into()
+                .unwrap(),
+        );
+        let previous_end = pointer_offset + previous_size as usize;
+        if previous_end + 3 >= start {
+            check_corrupt!(previous_end <= start, "freeblock overlaps");
+            n_fragmented += start - previous_end;
+            start = pointer_offset;
+        }
+    }
+    check_corrupt!(
+        n_fragmented <= fragmented_free_bytes as usize,
+        "invalid fragmented free bytes"
+    );
+
+    if start <= cell_content_area_offset {
+        check_corrupt!(
+            start == cell_content_area_offset && pointer_offset == header_pointer_offset,
+            "freeblock before cell content area"
+        );
+        let mut page_header = BtreePageHeaderMut::from_page(page, buffer);
+        page_header.set_first_freeblock_offset(next_freeblock_offset as u16);
+        page_header.set_cell_content_area_offset(non_zero_to_u16(end as u32));
+        page_header.remove_fragmented_free_bytes(n_fragmented as u8);
+    } else {
+        set_u16(buffer, pointer_offset, start as u16);
+        set_u16(buffer, start, next_freeblock_offset as u16);
+        set_u16(buffer, start + 2, (end - start) as u16);
+        BtreePageHeaderMut::from_page(page, buffer)
+            .remove_fragmented_free_bytes(n_fragmented as u8);
+    }
+    Ok(())
+}
+
 /// Compute the free size of the page.
 ///
 /// n_cells is an argument because this is cached in cursor.
-pub fn compute_free_size(page: &MemPage, buffer: &PageBufferMut, n_cells: u16) -> ParseResult<u16> {
+///
+/// This also validates that the free spaces are out of the reserved space at
+/// the end of the page.
+pub fn compute_free_size(
+    ctx: &BtreeContext,
+    page: &MemPage,
+    buffer: &PageBufferMut,
+    n_cells: u16,
+) -> ParseResult<u16> {
     let page_header = BtreePageHeader::from_page_mut(page, buffer);
     let header_size = page_header.page_type().header_size();
     let first_freeblock_offset = page_header.first_freeblock_offset();
@@ -821,7 +951,8 @@ pub fn compute_free_size(page: &MemPage, buffer: &PageBufferMut, n_cells: u16) -
     let unallocated_space_offset = cell_pointer_offset(page, n_cells, header_size);
 
     check_corrupt!(
-        unallocated_space_offset <= cell_content_area_offset,
+        unallocated_space_offset <= cell_content_area_offset
+            && cell_content_area_offset <= ctx.usable_size as usize,
         "invalid cell content area offset"
     );
 
@@ -832,7 +963,8 @@ pub fn compute_free_size(page: &MemPage, buffer: &PageBufferMut, n_cells: u16) -
     let mut free_size = unallocated_size;
     for (freeblock_offset, size) in FreeblockIterator::new(first_freeblock_offset, buffer) {
         check_corrupt!(
-            freeblock_offset + 4 <= buffer.len(),
+            freeblock_offset + 4 <= buffer.len()
+                && freeblock_offset + size as usize <= ctx.usable_size as usize,
             "freeblock offset out of range"
         );
         check_corrupt!(
@@ -856,7 +988,6 @@ mod tests {
     const BTREE_PAGE_TYPE_INTERIOR_INDEX: u8 = INDEX_FLAG;
     const BTREE_PAGE_TYPE_INTERIOR_TABLE: u8 = TABLE_FLAG;
     const BTREE_PAGE_TYPE_LEAF_INDEX: u8 = LEAF_FLAG | INDEX_FLAG;
-    const BTREE_PAGE_TYPE_LEAF_TABLE: u8 = LEAF_FLAG | TABLE_FLAG;
 
     #[test]
     fn test_payload_size() {
@@ -1262,9 +1393,32 @@ mod tests {
         assert_eq!(&buffer[2400..2700], &[2; 300]);
     }
 
+    #[test]
+    fn test_n_local() {
+        for usable_size in [480, 4096, 65536] {
+            let ctx = BtreeContext::new(usable_size);
+            for is_table in [true, false] {
+                let max_local = ctx.max_local(is_table) as u64;
+                for payload_size in [max_local + 1, 100000, i32::MAX as u64 - 1, i32::MAX as u64] {
+                    let min_local = ctx.min_local as u64;
+                    let surplus = min_local + (payload_size - min_local) % (usable_size as u64 - 4);
+                    let expected = if surplus <= max_local {
+                        surplus
+                    } else {
+                        min_local
+                    };
+                    let n_local = ctx.n_local(is_table, payload_size.try_into().unwrap());
+                    assert_eq!(n_local as u64, expected, "{usable_size} {payload_size}");
+                    assert!(n_local as u64 <= max_local);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_compute_free_size() {
         let pager = create_empty_pager(&[], 2 * 4096, 2 * 4096);
+        let ctx = BtreeContext::new(2 * 4096);
         let page_type = BtreePageType(BTREE_PAGE_TYPE_LEAF_TABLE);
 
         let (page_id, page) = pager.allocate_page().unwrap();
@@ -1275,13 +1429,13 @@ mod tests {
         page_header.set_cell_content_area_offset(4096);
         page_header.set_first_freeblock_offset(0);
         page_header.clear_fragmented_free_bytes();
-        assert_eq!(compute_free_size(&page, &buffer, 0).unwrap(), 3988);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 0).unwrap(), 3988);
 
         let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
         page_header.set_cell_content_area_offset(4000);
         page_header.add_fragmented_free_bytes(3);
-        assert_eq!(compute_free_size(&page, &buffer, 0).unwrap(), 3895);
-        assert_eq!(compute_free_size(&page, &buffer, 10).unwrap(), 3875);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 0).unwrap(), 3895);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 10).unwrap(), 3875);
 
         let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
         page_header.set_cell_content_area_offset(2000);
@@ -1292,7 +1446,7 @@ mod tests {
         // freeblock 3100 ~ 3200
         set_u16(&mut buffer, 3100, 0);
         set_u16(&mut buffer, 3102, 100);
-        assert_eq!(compute_free_size(&page, &buffer, 10).unwrap(), 1985);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 10).unwrap(), 1985);
 
         let (page_id, page) = pager.allocate_page().unwrap();
         assert_ne!(page_id, PAGE_ID_1);
@@ -1302,13 +1456,13 @@ mod tests {
         page_header.set_cell_content_area_offset(4096);
         page_header.set_first_freeblock_offset(0);
         page_header.clear_fragmented_free_bytes();
-        assert_eq!(compute_free_size(&page, &buffer, 0).unwrap(), 4088);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 0).unwrap(), 4088);
 
         let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
         page_header.set_cell_content_area_offset(4000);
         page_header.add_fragmented_free_bytes(3);
-        assert_eq!(compute_free_size(&page, &buffer, 0).unwrap(), 3995);
-        assert_eq!(compute_free_size(&page, &buffer, 10).unwrap(), 3975);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 0).unwrap(), 3995);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 10).unwrap(), 3975);
 
         let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
         page_header.set_cell_content_area_offset(2000);
@@ -1319,6 +1473,77 @@ mod tests {
         // freeblock 3100 ~ 3200
         set_u16(&mut buffer, 3100, 0);
         set_u16(&mut buffer, 3102, 100);
-        assert_eq!(compute_free_size(&page, &buffer, 10).unwrap(), 2085);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 10).unwrap(), 2085);
+
+        // The free spaces must not overlap the reserved space.
+        let ctx = BtreeContext::new(3200);
+        assert_eq!(compute_free_size(&ctx, &page, &buffer, 10).unwrap(), 2085);
+        let ctx = BtreeContext::new(3150);
+        assert!(compute_free_size(&ctx, &page, &buffer, 10).is_err());
+        let ctx = BtreeContext::new(1999);
+        let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
+        page_header.set_first_freeblock_offset(0);
+        assert!(compute_free_size(&ctx, &page, &buffer, 10).is_err());
+    }
+
+    #[test]
+    fn test_free_space() {
+        let pager = create_empty_pager(&[], 2 * 4096, 2 * 4096);
+        let page_type = BtreePageType(BTREE_PAGE_TYPE_LEAF_TABLE);
+        let (page_id, _) = pager.allocate_page().unwrap();
+        assert_eq!(page_id, PAGE_ID_1);
+        let (_, page) = pager.allocate_page().unwrap();
+        let mut buffer = pager.make_page_mut(&page).unwrap();
+        let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
+        page_header.set_page_type(page_type);
+        page_header.set_cell_content_area_offset(2000);
+        page_header.set_first_freeblock_offset(0);
+        page_header.clear_fragmented_free_bytes();
+        page_header.add_fragmented_free_bytes(5);
+
+        let freeblocks = |buffer: &PageBufferMut| {
+            let page_header = BtreePageHeader::from_page_mut(&page, buffer);
+            FreeblockIterator::new(page_header.first_freeblock_offset(), buffer).collect::<Vec<_>>()
+        };
+
+        free_space(&page, &mut buffer, 3000, 10).unwrap();
+        assert_eq!(freeblocks(&buffer), vec![(3000, 10)]);
+        // Freeblocks are sorted.
+        free_space(&page, &mut buffer, 2500, 10).unwrap();
+        free_space(&page, &mut buffer, 3500, 10).unwrap();
+        assert_eq!(
+            freeblocks(&buffer),
+            vec![(2500, 10), (3000, 10), (3500, 10)]
+        );
+        // Merged with the previous freeblock.
+        free_space(&page, &mut buffer, 3010, 10).unwrap();
+        assert_eq!(
+            freeblocks(&buffer),
+            vec![(2500, 10), (3000, 20), (3500, 10)]
+        );
+        // Merged with the next freeblock.
+        free_space(&page, &mut buffer, 3490, 10).unwrap();
+        assert_eq!(
+            freeblocks(&buffer),
+            vec![(2500, 10), (3000, 20), (3490, 20)]
+        );
+        // Merged with both freeblocks and the fragmented bytes between them.
+        free_space(&page, &mut buffer, 2512, 486).unwrap();
+        assert_eq!(freeblocks(&buffer), vec![(2500, 520), (3490, 20)]);
+        assert_eq!(
+            BtreePageHeader::from_page_mut(&page, &buffer).fragmented_free_bytes(),
+            1
+        );
+        // Merged to the unallocated space.
+        free_space(&page, &mut buffer, 2000, 500).unwrap();
+        assert_eq!(freeblocks(&buffer), vec![(3490, 20)]);
+        let page_header = BtreePageHeader::from_page_mut(&page, &buffer);
+        assert_eq!(page_header.cell_content_area_offset().get(), 3020);
+        assert_eq!(page_header.fragmented_free_bytes(), 1);
+
+        // Overlapping freeblock.
+        assert!(free_space(&page, &mut buffer, 3485, 10).is_err());
+        // Before the cell content area.
+        assert!(free_space(&page, &mut buffer, 1000, 10).is_err());
     }
 }
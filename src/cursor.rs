@@ -13,12 +13,14 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt::Display;
 
 use crate::btree::allocate_from_freeblocks;
 use crate::btree::allocate_from_unallocated_space;
 use crate::btree::cell_pointer_offset;
 use crate::btree::compute_free_size;
+use crate::btree::free_space;
 use crate::btree::get_cell_offset;
 use crate::btree::non_zero_to_u16;
 use crate::btree::parse_btree_interior_cell_page_id;
@@ -33,7 +35,6 @@ use crate::btree::IndexCellKeyParser;
 use crate::btree::OverflowPage;
 use crate::btree::PayloadInfo;
 use crate::btree::TableCellKeyParser;
-use crate::btree::BTREE_FIRST_FREEBLOCK_OFFSET;
 use crate::btree::BTREE_OVERFLOW_PAGE_ID_BYTES;
 use crate::btree::BTREE_PAGE_CELL_POINTER_SIZE;
 use crate::btree::BTREE_RIGHT_PAGE_ID_OFFSET;
@@ -67,6 +68,8 @@ pub enum Error {
     Record(anyhow::Error),
     LoadPayload,
     IndexExists,
+    NotEmpty,
+    UnsortedKey,
 }
 
 impl std::error::Error for Error {
@@ -82,6 +85,8 @@ impl std::error::Error for Error {
             Self::Record(e) => e.source(),
             Self::LoadPayload => None,
             Self::IndexExists => None,
+            Self::NotEmpty => None,
+            Self::UnsortedKey => None,
         }
     }
 }
@@ -103,6 +108,8 @@ impl Display for Error {
             Self::Record(e) => f.write_fmt(format_args!("record: {}", e)),
             Self::LoadPayload => f.write_str("failed to load payload"),
             Self::IndexExists => f.write_str("index already exists"),
+            Self::NotEmpty => f.write_str("btree is not empty"),
+            Self::UnsortedKey => f.write_str("key is not larger than the previous key"),
         }
     }
 }
@@ -147,11 +154,20 @@ impl Payload<Error> for BtreePayload<'_> {
 
         let mut cur = payload.len();
         let mut overflow = self.payload_info.overflow;
+        // A corrupted chain may loop. It can not have more pages than the file.
+        let mut n_overflow_pages = 0;
         while !buf.is_empty() && cur < self.payload_info.payload_size.get() as usize {
             let overflow_page = overflow.ok_or_else(|| Error::FileCorrupt {
                 page_id: self.local_page_id,
                 e: FileCorrupt::new("no overflow page"),
             })?;
+            n_overflow_pages += 1;
+            if n_overflow_pages > self.pager.num_pages() {
+                return Err(Error::FileCorrupt {
+                    page_id: overflow_page.page_id(),
+                    e: FileCorrupt::new("overflow page chain loops"),
+                });
+            }
             let page = self
                 .pager
                 .get_page(overflow_page.page_id())
@@ -273,6 +289,20 @@ impl CursorPage {
     }
 }
 
+/// Allocate a new page as the root page of an empty b-tree.
+pub fn create_btree(pager: &Pager, btree_ctx: &BtreeContext, is_table: bool) -> Result<PageId> {
+    let (page_id, page) = pager.allocate_page().map_err(Error::AllocatePage)?;
+    // make_page_mut() must succeed for allocated pages.
+    let mut buffer = pager.make_page_mut(&page).unwrap();
+    let mut page_header = BtreePageHeaderMut::from_page(&page, &mut buffer);
+    page_header.set_page_type(BtreePageType::leaf(is_table));
+    page_header.set_first_freeblock_offset(0);
+    page_header.set_n_cells(0);
+    page_header.set_cell_content_area_offset(non_zero_to_u16(btree_ctx.usable_size));
+    page_header.clear_fragmented_free_bytes();
+    Ok(page_id)
+}
+
 /// The cursor of btree.
 ///
 /// [BtreeCursor::insert()] may fail to get a writable buffer from the pager if
@@ -283,6 +313,8 @@ pub struct BtreeCursor<'a> {
     current_page: CursorPage,
     parent_pages: Vec<CursorPage>,
     initialized: bool,
+    /// Whether each column of the index is sorted in descending order.
+    descending: Vec<bool>,
 }
 
 impl<'a> BtreeCursor<'a> {
@@ -302,9 +334,16 @@ impl<'a> BtreeCursor<'a> {
             current_page: page,
             parent_pages: Vec::new(),
             initialized: false,
+            descending: Vec::new(),
         })
     }
 
+    /// Set the sort order of the columns of the index b-tree. The columns are
+    /// ascending by default.
+    pub fn set_index_order(&mut self, descending: &[bool]) {
+        self.descending = descending.to_vec();
+    }
+
     pub fn is_initialized(&self) -> bool {
         self.initialized
     }
@@ -368,26 +407,49 @@ impl<'a> BtreeCursor<'a> {
     ///
     /// If it does not exist, move to the next cell and returns `false`.
     pub fn index_move_to(&mut self, comparators: &[Option<ValueCmp>]) -> Result<bool> {
-        let found = self.index_move_to_leaf(comparators)?;
-        if !found && self.current_page.page_type.is_leaf() {
-            // If the key is between the last key of the index leaf page and the parent key,
-            // we need to adjust the cursor to the parent cell.
-            if self.current_page.idx_cell == self.current_page.n_cells {
-                while self.back_to_parent()
-                    && self.current_page.idx_cell == self.current_page.n_cells
-                {}
-            }
+        let found = self.index_move_to_leaf(comparators, false)?;
+        if !found {
+            self.adjust_to_next_index_cell();
         }
         Ok(found)
     }
 
+    /// Move to the first btree index cell whose key is greater than or equal
+    /// to the keys. The keys may be a prefix of the index columns.
+    ///
+    /// Returns `false` if there is no such cell. The following cells are
+    /// visited by [Self::move_next()] in the key order, so a range scan
+    /// continues until the cell exceeds its upper bound.
+    pub fn index_seek_ge(&mut self, comparators: &[Option<ValueCmp>]) -> Result<bool> {
+        self.index_move_to_leaf(comparators, true)?;
+        self.adjust_to_next_index_cell();
+        Ok(self.current_page.idx_cell < self.current_page.n_cells)
+    }
+
+    /// If the key is between the last key of the index leaf page and the
+    /// parent key, adjust the cursor to the parent cell.
+    fn adjust_to_next_index_cell(&mut self) {
+        if self.current_page.page_type.is_leaf()
+            && self.current_page.idx_cell == self.current_page.n_cells
+        {
+            while self.back_to_parent() && self.current_page.idx_cell == self.current_page.n_cells {
+            }
+        }
+    }
+
     /// Move to the specified btree index cell with the key without adjustment.
     ///
-    /// Returns `true` if exact key is found.
+    /// Returns `true` if exact key is found. If `lower_bound` is `true`, this
+    /// keeps searching the first cell not less than the key instead and always
+    /// returns `false`.
     ///
     /// If it does not exist, the cursor points to a cell (or tail) of a leaf
     /// page and returns `false`.
-    fn index_move_to_leaf(&mut self, comparators: &[Option<ValueCmp>]) -> Result<bool> {
+    fn index_move_to_leaf(
+        &mut self,
+        comparators: &[Option<ValueCmp>],
+        lower_bound: bool,
+    ) -> Result<bool> {
         self.move_to_root();
         loop {
             if !self.current_page.page_type.is_index() {
@@ -415,10 +477,15 @@ impl<'a> BtreeCursor<'a> {
                     local_payload_buffer: self.current_page.mem.buffer(),
                     payload_info,
                 };
-                match compare_record(comparators, &key_payload).map_err(Error::Record)? {
+                match compare_record(comparators, &self.descending, &key_payload)
+                    .map_err(Error::Record)?
+                {
                     Ordering::Less => {
                         i_max = i_mid;
                     }
+                    Ordering::Equal if lower_bound => {
+                        i_max = i_mid;
+                    }
                     Ordering::Equal => {
                         self.current_page.idx_cell = i_mid as u16;
                         self.initialized = true;
@@ -489,6 +556,7 @@ impl<'a> BtreeCursor<'a> {
                 // Traversing completed for single leaf btree.
                 self.current_page.idx_cell += 1;
             } else {
+                self.prefetch_next_children()?;
                 loop {
                     self.current_page.idx_cell += 1;
                     if self.move_to_left_most()? {
@@ -531,6 +599,28 @@ impl<'a> BtreeCursor<'a> {
         Ok(())
     }
 
+    /// Start bulk-loading the empty table btree. See [TableBulkLoader].
+    pub fn into_table_bulk_loader(mut self) -> Result<TableBulkLoader<'a>> {
+        self.move_to_root();
+        if !self.current_page.page_type.is_table() {
+            return Err(Error::NotTable);
+        } else if !self.current_page.page_type.is_leaf() || self.current_page.n_cells != 0 {
+            return Err(Error::NotEmpty);
+        }
+        // The database header does not fit in the pages built beforehand.
+        assert_eq!(
+            self.current_page.mem.header_offset, 0,
+            "sqlite_schema can not be bulk loaded"
+        );
+        self.initialized = false;
+        Ok(TableBulkLoader {
+            cursor: self,
+            leaf: None,
+            children: Vec::new(),
+            last_key: None,
+        })
+    }
+
     /// Insert a new key to index.
     ///
     /// This fails if the key already exists. If you need to update the key for
@@ -540,7 +630,7 @@ impl<'a> BtreeCursor<'a> {
         comparators: &[Option<ValueCmp>],
         payload: &P,
     ) -> Result<()> {
-        if self.index_move_to_leaf(comparators)? {
+        if self.index_move_to_leaf(comparators, false)? {
             // index_insert() does not support updating a key.
             return Err(Error::IndexExists);
         }
@@ -575,27 +665,132 @@ impl<'a> BtreeCursor<'a> {
         assert!(self.current_page.page_type.is_table());
         assert!(self.current_page.page_type.is_leaf());
 
-        if let Some(current_cell_key) = current_cell_key {
-            if current_cell_key == key {
-                // TODO: Update the payload
-                todo!("update the payload");
-            }
-        }
+        let update = current_cell_key == Some(key);
+        let old_cell = if update {
+            Some(self.remove_overflow_of_current_cell()?)
+        } else {
+            None
+        };
 
         let mut cell_header_buf = [0; 18];
 
         let (cell_header, n_local, overflow_page_id) =
             self.pack_cell(cell_header_buf.as_mut_slice(), payload, Some(key))?;
+        let cell_payload = LeafCellPayload {
+            cell_header,
+            payload,
+            n_local,
+            overflow_page_id,
+        };
 
-        self.insert_cell(
-            self.parent_pages.len(),
-            &LeafCellPayload {
-                cell_header,
-                payload,
-                n_local,
-                overflow_page_id,
-            },
+        if let Some((cell_offset, cell_size)) = old_cell {
+            if self.overwrite_current_cell(cell_offset, cell_size, &cell_payload)? {
+                return Ok(());
+            }
+        }
+
+        self.insert_cell(self.parent_pages.len(), &cell_payload)
+    }
+
+    /// Free the overflow pages of the current cell.
+    ///
+    /// Returns the offset and the size of the cell.
+    fn remove_overflow_of_current_cell(&self) -> Result<(usize, u16)> {
+        let buffer = self.current_page.mem.buffer();
+        let cell_offset = get_cell_offset(
+            &self.current_page.mem,
+            &buffer,
+            self.current_page.idx_cell,
+            self.current_page.page_type.header_size(),
         )
+        .map_err(|e| Error::FileCorrupt {
+            page_id: self.current_page.page_id,
+            e,
+        })?;
+        let cell_size = self.current_page.page_type.compute_cell_size_fn()(
+            self.btree_ctx,
+            &buffer,
+            cell_offset,
+        )
+        .map_err(|e| Error::FileCorrupt {
+            page_id: self.current_page.page_id,
+            e,
+        })?;
+        let overflow_page = self.current_page.page_type.compute_overflow_page_fn()(
+            self.btree_ctx,
+            &buffer,
+            cell_offset,
+        )
+        .map_err(|e| Error::FileCorrupt {
+            page_id: self.current_page.page_id,
+            e,
+        })?;
+        drop(buffer);
+        self.free_overflow_pages(overflow_page)?;
+        Ok((cell_offset, cell_size))
+    }
+
+    /// Replace the current cell with the new cell.
+    ///
+    /// If the new cell fits in the space of the current cell, the new cell
+    /// overwrites it and the trailing space becomes a freeblock. Otherwise the
+    /// current cell is removed and this returns false. The caller must insert
+    /// the new cell at the current position with insert_cell().
+    fn overwrite_current_cell(
+        &mut self,
+        cell_offset: usize,
+        cell_size: u16,
+        cell_payload: &dyn CellPayload,
+    ) -> Result<bool> {
+        // Cells occupy at least 4 bytes. See insert_cell().
+        let cell_size = if cell_size < 4 { 4 } else { cell_size };
+        let new_cell_size = cell_payload.size();
+        let new_cell_size = if new_cell_size < 4 { 4 } else { new_cell_size };
+        let current_page = &mut self.current_page;
+        let mut buffer = self
+            .pager
+            .make_page_mut(&current_page.mem)
+            .map_err(|e| Error::Pager {
+                page_id: current_page.page_id,
+                e,
+            })?;
+        // The remaining space less than 4 bytes can not be a freeblock. Relocate the
+        // cell instead of increasing fragmented bytes.
+        if new_cell_size == cell_size || new_cell_size + 4 <= cell_size {
+            cell_payload.copy_all(&mut buffer[cell_offset..]);
+            if new_cell_size < cell_size {
+                free_space(
+                    &current_page.mem,
+                    &mut buffer,
+                    cell_offset + new_cell_size as usize,
+                    cell_size - new_cell_size,
+                )
+                .map_err(|e| Error::FileCorrupt {
+                    page_id: current_page.page_id,
+                    e,
+                })?;
+            }
+            return Ok(true);
+        }
+
+        let header_size = current_page.page_type.header_size();
+        let cell_pointers_tail_offset =
+            cell_pointer_offset(&current_page.mem, current_page.n_cells, header_size);
+        let cell_pointer_offset =
+            cell_pointer_offset(&current_page.mem, current_page.idx_cell, header_size);
+        buffer.copy_within(
+            cell_pointer_offset + 2..cell_pointers_tail_offset,
+            cell_pointer_offset,
+        );
+        Self::free_cell(&current_page.mem, &mut buffer, cell_offset, cell_size).map_err(|e| {
+            Error::FileCorrupt {
+                page_id: current_page.page_id,
+                e,
+            }
+        })?;
+        // The header value is updated in insert_cell().
+        current_page.n_cells -= 1;
+        Ok(false)
     }
 
     fn pack_cell<'b, P: CopiablePayload>(
@@ -706,11 +901,16 @@ impl<'a> BtreeCursor<'a> {
                     })?;
 
             // TODO: Cache free size.
-            let free_size = compute_free_size(&current_page.mem, &buffer, current_page.n_cells)
-                .map_err(|e| Error::FileCorrupt {
-                    page_id: current_page.page_id,
-                    e,
-                })?;
+            let free_size = compute_free_size(
+                self.btree_ctx,
+                &current_page.mem,
+                &buffer,
+                current_page.n_cells,
+            )
+            .map_err(|e| Error::FileCorrupt {
+                page_id: current_page.page_id,
+                e,
+            })?;
 
             if free_size >= new_cell_size + 2 {
                 let page_header = BtreePageHeader::from_page_mut(&current_page.mem, &buffer);
@@ -1027,9 +1227,6 @@ impl<'a> BtreeCursor<'a> {
                 let n_new_cells = n_moved_cells
                     + (!split_into_3) as u16
                     + (!split_into_3 && sub_interior_cell_len.is_some()) as u16;
-                let mut first_freeblock_offset =
-                    BtreePageHeader::from_page_mut(&current_page.mem, &buffer)
-                        .first_freeblock_offset();
                 // TODO: Does this assertion avoid boundary check of cells[i as usize]?
                 assert!(idx_cells.end as usize <= cells.len());
 
@@ -1057,11 +1254,12 @@ impl<'a> BtreeCursor<'a> {
                     cell_content_area_offset = new_cell_content_area_offset;
                     i_new += 1;
 
-                    // TODO: Merge freeblock to unallocated space if possible.
-                    // Put the cell to the freeblock list.
-                    set_u16(&mut buffer, offset, first_freeblock_offset);
-                    set_u16(&mut buffer, offset + 2, cell_size);
-                    first_freeblock_offset = offset as u16;
+                    Self::free_cell(&current_page.mem, &mut buffer, offset, cell_size).map_err(
+                        |e| Error::FileCorrupt {
+                            page_id: current_page.page_id,
+                            e,
+                        },
+                    )?;
                 }
 
                 // Insert the new cell.
@@ -1148,7 +1346,6 @@ impl<'a> BtreeCursor<'a> {
                 };
 
                 let mut page_header = BtreePageHeaderMut::from_page(&current_page.mem, &mut buffer);
-                page_header.set_first_freeblock_offset(first_freeblock_offset);
                 let n_current_cells = current_page.n_cells - n_moved_cells;
                 page_header.set_n_cells(n_current_cells);
 
@@ -1254,7 +1451,18 @@ impl<'a> BtreeCursor<'a> {
                 let is_table_leaf = current_page.page_type.is_table_leaf();
                 if !is_table_leaf {
                     // Remove the cell at the tail.
-                    // TODO: add the cell to freeblocks list or try not to copy the cell.
+                    // TODO: try not to copy the cell.
+                    let cell_size = compute_cell_size(self.btree_ctx, &left_buffer, cell_offset)
+                        .map_err(|e| Error::FileCorrupt {
+                            page_id: current_page.page_id,
+                            e,
+                        })?;
+                    // The left page is not page 1 because it is not the root page.
+                    Self::free_cell(&current_page.mem, &mut left_buffer, cell_offset, cell_size)
+                        .map_err(|e| Error::FileCorrupt {
+                            page_id: current_page.page_id,
+                            e,
+                        })?;
                     BtreePageHeaderMut::from_page(&current_page.mem, &mut left_buffer)
                         .set_n_cells(n_left_cells - 1);
                 }
@@ -1275,6 +1483,35 @@ impl<'a> BtreeCursor<'a> {
         }
     }
 
+    /// Count the entries in the tree from the number of cells of each page
+    /// without parsing the cells.
+    ///
+    /// The cursor is moved to the end of the tree.
+    pub fn count(&mut self) -> Result<u64> {
+        self.move_to_first()?;
+        let mut n_entries = 0;
+        loop {
+            if self.current_page.page_type.is_leaf() {
+                self.current_page.idx_cell = self.current_page.n_cells;
+                while self.current_page.idx_cell == self.current_page.n_cells {
+                    // The cells of interior index pages are entries as well.
+                    if self.current_page.page_type.is_leaf()
+                        || self.current_page.page_type.is_index()
+                    {
+                        n_entries += self.current_page.n_cells as u64;
+                    }
+                    if !self.back_to_parent() {
+                        // Same as the state after traversing completed by move_next().
+                        self.current_page.idx_cell = self.current_page.n_cells + 1;
+                        return Ok(n_entries);
+                    }
+                }
+                self.current_page.idx_cell += 1;
+            }
+            assert!(self.move_to_current_child()?);
+        }
+    }
+
     /// Delete all entries in the tree.
     ///
     /// Returns the number of entries deleted.
@@ -1440,7 +1677,12 @@ impl<'a> BtreeCursor<'a> {
                     cell_pointer_offset,
                 );
                 let page_id_buf: [u8; 4] = buffer[cell_offset..cell_offset + 4].try_into().unwrap();
-                Self::free_cell(&current_page.mem, &mut buffer, cell_offset, cell_size);
+                Self::free_cell(&current_page.mem, &mut buffer, cell_offset, cell_size).map_err(
+                    |e| Error::FileCorrupt {
+                        page_id: current_page.page_id,
+                        e,
+                    },
+                )?;
 
                 // Reduce the n_cells for insert_cell(). The header value is updated in
                 // insert_cell().
@@ -1492,6 +1734,8 @@ impl<'a> BtreeCursor<'a> {
 
         loop {
             if self.current_page.n_cells > self.current_page.page_type.is_leaf() as u16 {
+                let interior_page_depth =
+                    (!self.current_page.page_type.is_leaf()).then_some(self.parent_pages.len());
                 let header_size = self.current_page.page_type.header_size();
 
                 let mut buffer = self
@@ -1518,7 +1762,9 @@ impl<'a> BtreeCursor<'a> {
                         e,
                     })?;
 
-                    buffer.copy_within(cell_offset..cell_offset + 4, BTREE_RIGHT_PAGE_ID_OFFSET);
+                    let right_page_id_offset =
+                        self.current_page.mem.header_offset + BTREE_RIGHT_PAGE_ID_OFFSET;
+                    buffer.copy_within(cell_offset..cell_offset + 4, right_page_id_offset);
                     cell_offset
                 } else {
                     let cell_offset = get_cell_offset(
@@ -1562,7 +1808,11 @@ impl<'a> BtreeCursor<'a> {
                     e,
                 })?;
 
-                Self::free_cell(&self.current_page.mem, &mut buffer, cell_offset, cell_size);
+                Self::free_cell(&self.current_page.mem, &mut buffer, cell_offset, cell_size)
+                    .map_err(|e| Error::FileCorrupt {
+                        page_id: self.current_page.page_id,
+                        e,
+                    })?;
 
                 // Update page header.
                 self.current_page.n_cells -= 1;
@@ -1624,6 +1874,9 @@ impl<'a> BtreeCursor<'a> {
                 } else {
                     assert!(self.move_to_left_most()?);
                 }
+                if let Some(depth) = interior_page_depth {
+                    self.remove_empty_interior_page(depth)?;
+                }
                 break;
             } else {
                 let page_id = self.current_page.page_id;
@@ -1634,14 +1887,6 @@ impl<'a> BtreeCursor<'a> {
                         .map_err(|e| Error::Pager { page_id, e })?;
 
                     assert!(!self.current_page.page_type.is_leaf());
-                    if self.current_page.page_type.is_table()
-                        && self.current_page.idx_cell != self.current_page.n_cells
-                        && self.current_page.n_cells == 1
-                    {
-                        // TODO: Optimization: shrink the page level by
-                        // replacing the interior page with the right child
-                        // page.
-                    }
                 } else {
                     // Initialize the root page.
                     self.current_page.page_type = self.current_page.page_type.leaf_type();
@@ -1673,10 +1918,133 @@ impl<'a> BtreeCursor<'a> {
         Ok(())
     }
 
+    /// Remove the interior page at the depth if it has no cells.
+    ///
+    /// SQLite treats interior pages without cells as corrupted. The only child
+    /// page takes the place of the interior page.
+    ///
+    /// The cursor must point at a descendant of the interior page.
+    fn remove_empty_interior_page(&mut self, depth: usize) -> Result<()> {
+        assert!(depth < self.parent_pages.len());
+        let interior_page = &self.parent_pages[depth];
+        if interior_page.n_cells > 0 {
+            return Ok(());
+        }
+        assert!(!interior_page.page_type.is_leaf());
+        let interior_page_id = interior_page.page_id;
+        let child_page_id = if depth + 1 == self.parent_pages.len() {
+            self.current_page.page_id
+        } else {
+            self.parent_pages[depth + 1].page_id
+        };
+
+        if depth > 0 {
+            // Make the parent page point at the child page directly.
+            let parent_page = &self.parent_pages[depth - 1];
+            let mut buffer =
+                self.pager
+                    .make_page_mut(&parent_page.mem)
+                    .map_err(|e| Error::Pager {
+                        page_id: parent_page.page_id,
+                        e,
+                    })?;
+            if parent_page.idx_cell < parent_page.n_cells {
+                let cell_offset = get_cell_offset(
+                    &parent_page.mem,
+                    &buffer,
+                    parent_page.idx_cell,
+                    parent_page.page_type.header_size(),
+                )
+                .map_err(|e| Error::FileCorrupt {
+                    page_id: parent_page.page_id,
+                    e,
+                })?;
+                buffer[cell_offset..cell_offset + 4]
+                    .copy_from_slice(&child_page_id.get().to_be_bytes());
+            } else {
+                BtreePageHeaderMut::from_page(&parent_page.mem, &mut buffer)
+                    .set_right_page_id(child_page_id);
+            }
+            drop(buffer);
+            self.parent_pages.remove(depth);
+            self.pager
+                .delete_page(interior_page_id)
+                .map_err(|e| Error::Pager {
+                    page_id: interior_page_id,
+                    e,
+                })?;
+        } else {
+            // Copy the child page into the root page because the root page id must not
+            // change.
+            let child_page = if depth + 1 == self.parent_pages.len() {
+                &self.current_page
+            } else {
+                &self.parent_pages[depth + 1]
+            };
+            let interior_page = &self.parent_pages[depth];
+            let header_offset = interior_page.mem.header_offset;
+            let mut buffer =
+                self.pager
+                    .make_page_mut(&interior_page.mem)
+                    .map_err(|e| Error::Pager {
+                        page_id: interior_page_id,
+                        e,
+                    })?;
+            let child_buffer = child_page.mem.buffer();
+            let child_page_header = BtreePageHeader::from_page(&child_page.mem, &child_buffer);
+            let cell_content_area_offset =
+                child_page_header.cell_content_area_offset().get() as usize;
+            let cell_pointers_tail_offset = cell_pointer_offset(
+                &child_page.mem,
+                child_page.n_cells,
+                child_page.page_type.header_size(),
+            );
+            if header_offset + cell_pointers_tail_offset > cell_content_area_offset {
+                // The page 1 may not have enough space for the cell pointers after the
+                // database header. SQLite accepts the page 1 without cells.
+                return Ok(());
+            }
+            // The child page is not the page 1 and its page header is at the head of
+            // the page. Offsets of cells and freeblocks are still valid in the root page.
+            buffer[header_offset..header_offset + cell_pointers_tail_offset]
+                .copy_from_slice(&child_buffer[..cell_pointers_tail_offset]);
+            buffer[cell_content_area_offset..]
+                .copy_from_slice(&child_buffer[cell_content_area_offset..]);
+            drop(child_buffer);
+            drop(buffer);
+
+            let interior_page = self.parent_pages.remove(depth);
+            let child_page = if depth == self.parent_pages.len() {
+                &mut self.current_page
+            } else {
+                &mut self.parent_pages[depth]
+            };
+            child_page.page_id = interior_page.page_id;
+            child_page.mem = interior_page.mem;
+            self.pager
+                .delete_page(child_page_id)
+                .map_err(|e| Error::Pager {
+                    page_id: child_page_id,
+                    e,
+                })?;
+        }
+        Ok(())
+    }
+
     fn free_overflow_pages(&self, overflow_page: Option<OverflowPage>) -> Result<()> {
+        // Collect the whole chain first. A freed page is overwritten as a freelist
+        // page, and a looping chain would free the same page twice.
+        let mut page_ids = Vec::new();
+        let mut visited = HashSet::new();
         let mut overflow_page = overflow_page;
         while let Some(overflow_page_unwrap) = overflow_page {
             let next_page_id = overflow_page_unwrap.page_id();
+            if !visited.insert(next_page_id) {
+                return Err(Error::FileCorrupt {
+                    page_id: next_page_id,
+                    e: FileCorrupt::new("overflow page chain loops"),
+                });
+            }
             let next_page = self
                 .pager
                 .get_page(next_page_id)
@@ -1692,26 +2060,25 @@ impl<'a> BtreeCursor<'a> {
                     e,
                 })?;
             overflow_page = next_overflow_page;
-            drop(buffer);
-            self.pager
-                .delete_page(next_page_id)
-                .map_err(|e| Error::Pager {
-                    page_id: self.current_page.page_id,
-                    e,
-                })?;
+            page_ids.push(next_page_id);
+        }
+        for page_id in page_ids {
+            self.pager.delete_page(page_id).map_err(|e| Error::Pager {
+                page_id: self.current_page.page_id,
+                e,
+            })?;
         }
         Ok(())
     }
 
-    fn free_cell(page: &MemPage, buffer: &mut PageBufferMut, offset: usize, cell_size: u16) {
+    fn free_cell(
+        page: &MemPage,
+        buffer: &mut PageBufferMut,
+        offset: usize,
+        cell_size: u16,
+    ) -> std::result::Result<(), FileCorrupt> {
         let cell_size = if cell_size < 4 { 4 } else { cell_size };
-        buffer.copy_within(BTREE_FIRST_FREEBLOCK_OFFSET, offset);
-        set_u16(buffer, offset + 2, cell_size);
-        let mut page_header = BtreePageHeaderMut::from_page(page, buffer);
-        page_header.set_first_freeblock_offset(offset as u16);
-
-        // TODO: Merge freeblocks.
-        // TODO: Merge freeblock to unallocated space if possible.
+        free_space(page, buffer, offset, cell_size)
     }
 
     pub fn get_table_key(&self) -> Result<Option<i64>> {
@@ -1734,7 +2101,7 @@ impl<'a> BtreeCursor<'a> {
         Ok(Some(key))
     }
 
-    pub fn get_table_payload(&self) -> Result<Option<(i64, BtreePayload)>> {
+    pub fn get_table_payload(&self) -> Result<Option<(i64, BtreePayload<'_>)>> {
         if !self.initialized {
             return Err(Error::NotInitialized);
         } else if !self.current_page.page_type.is_table() {
@@ -1766,7 +2133,7 @@ impl<'a> BtreeCursor<'a> {
         )))
     }
 
-    pub fn get_index_payload(&self) -> Result<Option<BtreePayload>> {
+    pub fn get_index_payload(&self) -> Result<Option<BtreePayload<'_>>> {
         if !self.initialized {
             return Err(Error::NotInitialized);
         } else if !self.current_page.page_type.is_index() {
@@ -1877,6 +2244,41 @@ impl<'a> BtreeCursor<'a> {
         Ok(true)
     }
 
+    /// Read the child pages following the current cell of the interior page
+    /// into the cache if readahead is enabled on the pager.
+    fn prefetch_next_children(&self) -> Result<()> {
+        let n_pages = self.pager.readahead();
+        if n_pages == 0 {
+            return Ok(());
+        }
+        let page = &self.current_page;
+        let buffer = page.mem.buffer();
+        let start = page.idx_cell as u32 + 1;
+        // The right page is the child after the last cell.
+        let end = (start + n_pages).min(page.n_cells as u32 + 1);
+        let mut page_ids = Vec::with_capacity(end.saturating_sub(start) as usize);
+        for idx_cell in start..end {
+            let page_id = if idx_cell < page.n_cells as u32 {
+                parse_btree_interior_cell_page_id(&page.mem, &buffer, idx_cell as u16)
+            } else {
+                BtreePageHeader::from_page(&page.mem, &buffer).right_page_id()
+            }
+            .map_err(|e| Error::FileCorrupt {
+                page_id: page.page_id,
+                e,
+            })?;
+            page_ids.push(page_id);
+        }
+        drop(buffer);
+        if let Some(page_id) = page_ids.first() {
+            self.pager.prefetch(&page_ids).map_err(|e| Error::Pager {
+                page_id: *page_id,
+                e,
+            })?;
+        }
+        Ok(())
+    }
+
     fn back_to_parent(&mut self) -> bool {
         if let Some(page) = self.parent_pages.pop() {
             self.current_page = page;
@@ -1887,6 +2289,240 @@ impl<'a> BtreeCursor<'a> {
     }
 }
 
+/// A page which [TableBulkLoader] is filling.
+struct BulkPage {
+    page_id: PageId,
+    mem: MemPage,
+    n_cells: u16,
+    cell_content_area_offset: usize,
+}
+
+/// Builds a table btree from entries appended in strictly increasing key
+/// order.
+///
+/// Unlike [BtreeCursor::table_insert()], leaf pages are filled to capacity and
+/// never split. The interior pages are built bottom-up from the leaf pages on
+/// [TableBulkLoader::finish()]. The btree is incomplete until then.
+pub struct TableBulkLoader<'a> {
+    cursor: BtreeCursor<'a>,
+    leaf: Option<BulkPage>,
+    /// The filled pages of the lowest level and their largest keys.
+    children: Vec<(PageId, i64)>,
+    last_key: Option<i64>,
+}
+
+impl<'a> TableBulkLoader<'a> {
+    pub fn append<P: CopiablePayload>(&mut self, key: i64, payload: &P) -> Result<()> {
+        if self.last_key.is_some_and(|last_key| key <= last_key) {
+            return Err(Error::UnsortedKey);
+        }
+        let mut cell_header_buf = [0; 18];
+        let (cell_header, n_local, overflow_page_id) =
+            self.cursor
+                .pack_cell(cell_header_buf.as_mut_slice(), payload, Some(key))?;
+        let cell_payload = LeafCellPayload {
+            cell_header,
+            payload,
+            n_local,
+            overflow_page_id,
+        };
+        // Allocate 4 bytes or more as insert_cell() does.
+        let cell_size = cell_payload.size().max(4);
+
+        let page_type = BtreePageType::leaf(true);
+        let header_size = page_type.header_size();
+        let fits = self.leaf.as_ref().is_some_and(|leaf| {
+            cell_pointer_offset(&leaf.mem, leaf.n_cells + 1, header_size) + cell_size as usize
+                <= leaf.cell_content_area_offset
+        });
+        if !fits {
+            self.start_leaf()?;
+        }
+        let leaf = self.leaf.as_mut().unwrap();
+        let mut buffer = self
+            .cursor
+            .pager
+            .make_page_mut(&leaf.mem)
+            .map_err(|e| Error::Pager {
+                page_id: leaf.page_id,
+                e,
+            })?;
+        leaf.cell_content_area_offset = allocate_from_unallocated_space(
+            &leaf.mem,
+            &mut buffer,
+            header_size,
+            leaf.cell_content_area_offset,
+            leaf.n_cells,
+            cell_size,
+        );
+        cell_payload.copy_all(&mut buffer[leaf.cell_content_area_offset..]);
+        leaf.n_cells += 1;
+        let mut page_header = BtreePageHeaderMut::from_page(&leaf.mem, &mut buffer);
+        page_header.set_n_cells(leaf.n_cells);
+        // At least 1 cell is written. cell_content_area_offset must fit in u16.
+        page_header.set_cell_content_area_offset(leaf.cell_content_area_offset as u16);
+        self.last_key = Some(key);
+        Ok(())
+    }
+
+    /// Close the current leaf page and start a new one.
+    ///
+    /// The first leaf page is the root page. It is moved to a new page when the
+    /// second leaf page starts because the root page becomes an interior page.
+    fn start_leaf(&mut self) -> Result<()> {
+        let pager = self.cursor.pager;
+        let (page_id, mem) = match self.leaf.take() {
+            None => {
+                let page_id = self.cursor.current_page.page_id;
+                let mem = pager
+                    .get_page(page_id)
+                    .map_err(|e| Error::Pager { page_id, e })?;
+                (page_id, mem)
+            }
+            Some(leaf) => {
+                let last_key = self.last_key.expect("leaf page has cells");
+                if self.children.is_empty() {
+                    let (page_id, mem) = pager.allocate_page().map_err(Error::AllocatePage)?;
+                    // make_page_mut() must succeed for allocated pages.
+                    let mut buffer = pager.make_page_mut(&mem).unwrap();
+                    buffer.copy_from_slice(&leaf.mem.buffer());
+                    self.children.push((page_id, last_key));
+                } else {
+                    self.children.push((leaf.page_id, last_key));
+                }
+                pager.allocate_page().map_err(Error::AllocatePage)?
+            }
+        };
+        self.leaf = Some(self.init_page(page_id, mem, BtreePageType::leaf(true))?);
+        Ok(())
+    }
+
+    fn init_page(
+        &self,
+        page_id: PageId,
+        mem: MemPage,
+        page_type: BtreePageType,
+    ) -> Result<BulkPage> {
+        let usable_size = self.cursor.btree_ctx.usable_size;
+        let mut buffer = self
+            .cursor
+            .pager
+            .make_page_mut(&mem)
+            .map_err(|e| Error::Pager { page_id, e })?;
+        let mut page_header = BtreePageHeaderMut::from_page(&mem, &mut buffer);
+        page_header.set_page_type(page_type);
+        page_header.set_first_freeblock_offset(0);
+        page_header.set_n_cells(0);
+        page_header.set_cell_content_area_offset(non_zero_to_u16(usable_size));
+        page_header.clear_fragmented_free_bytes();
+        drop(buffer);
+        Ok(BulkPage {
+            page_id,
+            mem,
+            n_cells: 0,
+            cell_content_area_offset: usable_size as usize,
+        })
+    }
+
+    /// Build the interior pages over the leaf pages.
+    pub fn finish(mut self) -> Result<()> {
+        // The root page is still empty or the only leaf page.
+        let Some(leaf) = self.leaf.take() else {
+            return Ok(());
+        };
+        if self.children.is_empty() {
+            return Ok(());
+        }
+        self.children
+            .push((leaf.page_id, self.last_key.expect("leaf page has cells")));
+
+        let mut children = std::mem::take(&mut self.children);
+        while children.len() > 1 {
+            children = self.build_interior_level(&children)?;
+        }
+        Ok(())
+    }
+
+    /// Build a level of interior pages over the children. Each page has
+    /// children as many as fit and the last child is the right page.
+    ///
+    /// Returns the new pages and their largest keys. The root page is used if
+    /// all the children fit in a page.
+    fn build_interior_level(&self, children: &[(PageId, i64)]) -> Result<Vec<(PageId, i64)>> {
+        let page_type = BtreePageType::leaf(true).interior_type();
+        let header_size = page_type.header_size();
+        let capacity = self.cursor.btree_ctx.usable_size as usize - header_size as usize;
+        let mut key_buf = [0; 9];
+        let cell_size = |key: i64, key_buf: &mut [u8]| 4 + put_varint(key_buf, i64_to_u64(key));
+
+        let mut groups = Vec::new();
+        let mut start = 0;
+        while start < children.len() {
+            let mut end = start;
+            let mut used = 0;
+            while end + 1 < children.len() {
+                let size = cell_size(children[end].1, &mut key_buf) + BTREE_PAGE_CELL_POINTER_SIZE;
+                if used + size > capacity {
+                    break;
+                }
+                used += size;
+                end += 1;
+            }
+            groups.push(start..end + 1);
+            start = end + 1;
+        }
+        // Every page has at least one cell.
+        if groups.len() > 1 && groups.last().unwrap().len() == 1 {
+            let n_groups = groups.len();
+            groups[n_groups - 2].end -= 1;
+            groups[n_groups - 1].start -= 1;
+        }
+
+        let pager = self.cursor.pager;
+        let mut parents = Vec::with_capacity(groups.len());
+        for group in groups {
+            let (page_id, mem) = if parents.is_empty() && group.len() == children.len() {
+                let page_id = self.cursor.current_page.page_id;
+                let mem = pager
+                    .get_page(page_id)
+                    .map_err(|e| Error::Pager { page_id, e })?;
+                (page_id, mem)
+            } else {
+                pager.allocate_page().map_err(Error::AllocatePage)?
+            };
+            let mut page = self.init_page(page_id, mem, page_type)?;
+            let mut buffer = pager
+                .make_page_mut(&page.mem)
+                .map_err(|e| Error::Pager { page_id, e })?;
+            let (right_page_id, last_key) = children[group.end - 1];
+            for &(child_page_id, key) in &children[group.start..group.end - 1] {
+                let mut cell = [0; 13];
+                cell[..4].copy_from_slice(&child_page_id.get().to_be_bytes());
+                let size = cell_size(key, &mut cell[4..]);
+                page.cell_content_area_offset = allocate_from_unallocated_space(
+                    &page.mem,
+                    &mut buffer,
+                    header_size,
+                    page.cell_content_area_offset,
+                    page.n_cells,
+                    size as u16,
+                );
+                buffer[page.cell_content_area_offset..page.cell_content_area_offset + size]
+                    .copy_from_slice(&cell[..size]);
+                page.n_cells += 1;
+            }
+            let mut page_header = BtreePageHeaderMut::from_page(&page.mem, &mut buffer);
+            page_header.set_n_cells(page.n_cells);
+            page_header.set_cell_content_area_offset(non_zero_to_u16(
+                page.cell_content_area_offset as u32,
+            ));
+            page_header.set_right_page_id(right_page_id);
+            parents.push((page_id, last_key));
+        }
+        Ok(parents)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2290,6 +2926,63 @@ mod tests {
         drop(payload);
     }
 
+    #[test]
+    fn test_btree_cursor_count() {
+        let mut queries = vec![
+            "PRAGMA page_size = 512;".to_string(),
+            "CREATE TABLE example(col, buf);".to_string(),
+            "CREATE INDEX index1 ON example(buf);".to_string(),
+            "CREATE TABLE empty(col);".to_string(),
+            "CREATE INDEX index2 ON empty(col);".to_string(),
+        ];
+        for i in 0..2000 {
+            queries.push(format!(
+                "INSERT INTO example(col, buf) VALUES ({}, X'{:0100}');",
+                i, i
+            ));
+        }
+        let file = create_sqlite_database(&queries.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+
+        for name in ["example", "index1", "empty", "index2"] {
+            let is_index = name.starts_with("index");
+            let page_id = if is_index {
+                find_index_page_id(name, file.path())
+            } else {
+                find_table_page_id(name, file.path())
+            };
+            let has_entry = |cursor: &BtreeCursor| {
+                if is_index {
+                    cursor.get_index_payload().unwrap().is_some()
+                } else {
+                    cursor.get_table_key().unwrap().is_some()
+                }
+            };
+            let mut cursor = BtreeCursor::new(page_id, &pager, &bctx).unwrap();
+            cursor.move_to_first().unwrap();
+            let mut n_entries = 0;
+            while has_entry(&cursor) {
+                n_entries += 1;
+                cursor.move_next().unwrap();
+            }
+            let expected = if name == "example" || name == "index1" {
+                2000
+            } else {
+                0
+            };
+            assert_eq!(n_entries, expected, "{}", name);
+
+            let mut cursor = BtreeCursor::new(page_id, &pager, &bctx).unwrap();
+            assert_eq!(cursor.count().unwrap(), expected, "{}", name);
+            // The cursor is at the end.
+            assert!(!has_entry(&cursor));
+            cursor.move_next().unwrap();
+            assert!(!has_entry(&cursor));
+            assert_eq!(cursor.count().unwrap(), expected, "{}", name);
+        }
+    }
+
     #[test]
     fn test_overflow_payload() {
         let mut queries = vec![
@@ -2377,6 +3070,71 @@ mod tests {
         assert_eq!(n, 1);
     }
 
+    #[test]
+    fn test_overflow_payload_loop() {
+        let query = format!(
+            "INSERT INTO example(col) VALUES (X'{}');",
+            "11".repeat(2000)
+        );
+        let file = create_sqlite_database(&[
+            "PRAGMA page_size = 512;",
+            "CREATE TABLE example(col);",
+            &query,
+        ]);
+        let table_page_id = find_table_page_id("example", file.path());
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        cursor.move_to_first().unwrap();
+        let (_, payload) = cursor.get_table_payload().unwrap().unwrap();
+        let n_local = payload.buf().len();
+        let payload_size = payload.size().get() as usize;
+        drop(payload);
+        drop(cursor);
+        drop(pager);
+
+        // Make the first overflow page point to itself. The payload size is
+        // increased by multiples of the overflow page capacity so that the local
+        // size is not changed and the chain is longer than the file.
+        let mut buf = std::fs::read(file.path()).unwrap();
+        let page_offset = (table_page_id.get() as usize - 1) * 512;
+        let cell_offset =
+            page_offset + u16::from_be_bytes([buf[page_offset + 8], buf[page_offset + 9]]) as usize;
+        // 2 bytes payload size + 1 byte rowid.
+        assert_eq!(buf[cell_offset] & 0x80, 0x80);
+        assert_eq!(buf[cell_offset + 1] & 0x80, 0);
+        let overflow_offset = cell_offset + 3 + n_local;
+        let overflow_page_id = u32::from_be_bytes(
+            buf[overflow_offset..overflow_offset + 4]
+                .try_into()
+                .unwrap(),
+        );
+        let corrupted_size = payload_size + 508 * 28;
+        assert!(corrupted_size < 1 << 14);
+        buf[cell_offset] = 0x80 | (corrupted_size >> 7) as u8;
+        buf[cell_offset + 1] = (corrupted_size & 0x7f) as u8;
+        let overflow_page_offset = (overflow_page_id as usize - 1) * 512;
+        buf[overflow_page_offset..overflow_page_offset + 4]
+            .copy_from_slice(&overflow_page_id.to_be_bytes());
+        std::fs::write(file.path(), &buf).unwrap();
+
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        assert!(((corrupted_size - n_local) / 508) as u32 > pager.num_pages());
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        cursor.move_to_first().unwrap();
+        let (_, payload) = cursor.get_table_payload().unwrap().unwrap();
+        assert_eq!(payload.size().get() as usize, corrupted_size);
+        assert_eq!(payload.buf().len(), n_local);
+        let mut payload_buf = vec![0; corrupted_size];
+        assert!(matches!(
+            payload.load(0, &mut payload_buf),
+            Err(Error::FileCorrupt { page_id, .. }) if page_id.get() == overflow_page_id
+        ));
+        drop(payload);
+
+        assert!(matches!(cursor.delete(), Err(Error::FileCorrupt { .. })));
+    }
+
     #[test]
     fn test_table_move_to_in_single_page() {
         let file = create_sqlite_database(&[
@@ -2992,31 +3750,190 @@ mod tests {
             // Reset the cursor.
             cursor.move_to_first().unwrap();
 
-            assert!(cursor
-                .index_move_to(&[Some(ValueCmp::new(
-                    &Value::Integer(2 * i + 1),
-                    &Collation::Binary,
-                ))])
-                .unwrap());
-            let payload = cursor.get_index_payload().unwrap();
-            assert!(payload.is_some(), "i = {}", i);
-            let mut record = parse_record(payload.as_ref().unwrap()).unwrap();
-            assert_eq!(record.get(0).unwrap(), Some(Value::Integer(2 * i + 1)));
-            assert_eq!(record.get(2).unwrap(), Some(Value::Integer(i)));
-            drop(payload);
+            assert!(cursor
+                .index_move_to(&[Some(ValueCmp::new(
+                    &Value::Integer(2 * i + 1),
+                    &Collation::Binary,
+                ))])
+                .unwrap());
+            let payload = cursor.get_index_payload().unwrap();
+            assert!(payload.is_some(), "i = {}", i);
+            let mut record = parse_record(payload.as_ref().unwrap()).unwrap();
+            assert_eq!(record.get(0).unwrap(), Some(Value::Integer(2 * i + 1)));
+            assert_eq!(record.get(2).unwrap(), Some(Value::Integer(i)));
+            drop(payload);
+
+            // Reset the cursor.
+            cursor.move_to_first().unwrap();
+        }
+
+        assert!(!cursor
+            .index_move_to(&[Some(ValueCmp::new(
+                &Value::Integer(10000),
+                &Collation::Binary,
+            ))])
+            .unwrap());
+        let payload = cursor.get_index_payload().unwrap();
+        assert!(payload.is_none());
+    }
+
+    /// The first column and the rowid of the index entries from the cursor
+    /// position to the end.
+    fn collect_index_tail(cursor: &mut BtreeCursor) -> Vec<(i64, i64)> {
+        let mut entries = Vec::new();
+        loop {
+            let Some(payload) = cursor.get_index_payload().unwrap() else {
+                return entries;
+            };
+            let mut record = parse_record(&payload).unwrap();
+            let Some(Value::Integer(key)) = record.get(0).unwrap() else {
+                panic!("key is not integer");
+            };
+            let Some(Value::Integer(rowid)) = record.get(record.len() - 1).unwrap() else {
+                panic!("rowid is not integer");
+            };
+            entries.push((key, rowid));
+            drop(record);
+            drop(payload);
+            cursor.move_next().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_index_seek_ge_in_single_page() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "CREATE INDEX index1 ON example(col);",
+            "INSERT INTO example(rowid, col) VALUES (1, 1);",
+            "INSERT INTO example(rowid, col) VALUES (3, 3);",
+            "INSERT INTO example(rowid, col) VALUES (10, 10);",
+            "INSERT INTO example(rowid, col) VALUES (11, 10);",
+            "INSERT INTO example(rowid, col) VALUES (12, 10);",
+            "INSERT INTO example(rowid, col) VALUES (15, 11);",
+            "INSERT INTO example(rowid, col) VALUES (14, 11);",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let page_id = find_index_page_id("index1", file.path());
+
+        let mut cursor = BtreeCursor::new(page_id, &pager, &bctx).unwrap();
+
+        // Between existing entries.
+        assert!(cursor
+            .index_seek_ge(&[Some(ValueCmp::new(&Value::Integer(5), &Collation::Binary))])
+            .unwrap());
+        assert_eq!(
+            collect_index_tail(&mut cursor),
+            [(10, 10), (10, 11), (10, 12), (11, 14), (11, 15)]
+        );
+        // The first one of the duplicated keys.
+        assert!(cursor
+            .index_seek_ge(&[Some(ValueCmp::new(&Value::Integer(10), &Collation::Binary))])
+            .unwrap());
+        assert_eq!(
+            collect_index_tail(&mut cursor),
+            [(10, 10), (10, 11), (10, 12), (11, 14), (11, 15)]
+        );
+        // The rowid is the last column.
+        assert!(cursor
+            .index_seek_ge(&[
+                Some(ValueCmp::new(&Value::Integer(10), &Collation::Binary)),
+                Some(ValueCmp::new(&Value::Integer(11), &Collation::Binary)),
+            ])
+            .unwrap());
+        assert_eq!(
+            collect_index_tail(&mut cursor),
+            [(10, 11), (10, 12), (11, 14), (11, 15)]
+        );
+        // NULL is the smallest.
+        assert!(cursor.index_seek_ge(&[None]).unwrap());
+        assert_eq!(collect_index_tail(&mut cursor).len(), 7);
+        // After the last entry.
+        assert!(!cursor
+            .index_seek_ge(&[Some(ValueCmp::new(&Value::Integer(12), &Collation::Binary))])
+            .unwrap());
+        assert!(cursor.get_index_payload().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_index_seek_ge_empty_rows() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "CREATE INDEX index1 ON example(col);",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let page_id = find_index_page_id("index1", file.path());
+
+        let mut cursor = BtreeCursor::new(page_id, &pager, &bctx).unwrap();
+
+        assert!(!cursor
+            .index_seek_ge(&[Some(ValueCmp::new(&Value::Integer(1), &Collation::Binary))])
+            .unwrap());
+        assert!(cursor.get_index_payload().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_index_seek_ge_multiple_page() {
+        // Each key has 4 entries and each entry occupies a half page so that the
+        // duplicated keys span over interior pages.
+        let hex = buffer_to_hex(&[0; 2000]);
+        let mut inserts = Vec::new();
+        for i in 0..2000 {
+            inserts.push(format!(
+                "INSERT INTO example(rowid, id, col) VALUES ({}, {}, X'{}');",
+                i,
+                2 * (i / 4) + 1,
+                hex.as_str()
+            ));
+        }
+        let mut queries = vec![
+            "CREATE TABLE example(id, col);",
+            "CREATE INDEX index1 ON example(id, col);",
+        ];
+        queries.extend(inserts.iter().map(|s| s.as_str()));
+        let file = create_sqlite_database(&queries);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let page_id = find_index_page_id("index1", file.path());
+
+        let mut cursor = BtreeCursor::new(page_id, &pager, &bctx).unwrap();
 
-            // Reset the cursor.
-            cursor.move_to_first().unwrap();
+        for i in 0..500 {
+            for key in [2 * i, 2 * i + 1] {
+                assert!(cursor
+                    .index_seek_ge(&[Some(ValueCmp::new(
+                        &Value::Integer(key),
+                        &Collation::Binary,
+                    ))])
+                    .unwrap());
+                let payload = cursor.get_index_payload().unwrap().unwrap();
+                let mut record = parse_record(&payload).unwrap();
+                assert_eq!(record.get(0).unwrap(), Some(Value::Integer(2 * i + 1)));
+                assert_eq!(record.get(2).unwrap(), Some(Value::Integer(4 * i)));
+            }
         }
 
+        // Iterate the tail from the middle.
+        assert!(cursor
+            .index_seek_ge(&[Some(ValueCmp::new(
+                &Value::Integer(600),
+                &Collation::Binary
+            ))])
+            .unwrap());
+        let tail = collect_index_tail(&mut cursor);
+        assert_eq!(tail.len(), 2000 - 1200);
+        assert_eq!(tail[0], (601, 1200));
+        assert!(tail.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(tail[tail.len() - 1], (999, 1999));
+
         assert!(!cursor
-            .index_move_to(&[Some(ValueCmp::new(
-                &Value::Integer(10000),
-                &Collation::Binary,
+            .index_seek_ge(&[Some(ValueCmp::new(
+                &Value::Integer(1000),
+                &Collation::Binary
             ))])
             .unwrap());
-        let payload = cursor.get_index_payload().unwrap();
-        assert!(payload.is_none());
+        assert!(cursor.get_index_payload().unwrap().is_none());
     }
 
     #[test]
@@ -3319,7 +4236,7 @@ mod tests {
             let p_size = max_local + i;
             let p_size = (p_size as u64).try_into().unwrap();
             let n_local = bctx.n_local(true, p_size) as usize;
-            if (p_size.get() as usize - n_local) % (bctx.usable_size as usize - 4) == 0 {
+            if (p_size.get() as usize - n_local).is_multiple_of(bctx.usable_size as usize - 4) {
                 size_exact_overflow_page_size = Some(p_size);
                 break;
             }
@@ -3798,6 +4715,244 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_update_table_same_size() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(rowid, col) VALUES (1, x'01');",
+            "INSERT INTO example(rowid, col) VALUES (2, x'0202');",
+            "INSERT INTO example(rowid, col) VALUES (3, x'04');",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+
+        cursor
+            .table_insert(2, &SlicePayload::new(&[2, 16, 9, 9]).unwrap())
+            .unwrap();
+
+        cursor.move_to_first().unwrap();
+        assert_all_local_in_table_cursor(
+            &mut cursor,
+            &[(1, &[2, 14, 1]), (2, &[2, 16, 9, 9]), (3, &[2, 14, 4])],
+        );
+
+        let page = pager.get_page(table_page_id).unwrap();
+        let buffer = page.buffer();
+        let page_header = BtreePageHeader::from_page(&page, &buffer);
+        assert_eq!(page_header.n_cells(), 3);
+        assert_eq!(page_header.first_freeblock_offset(), 0);
+        assert_eq!(page_header.fragmented_free_bytes(), 0);
+        assert_eq!(page_header.cell_content_area_offset().get(), 4080);
+    }
+
+    #[test]
+    fn test_update_table_shorter() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(rowid, col) VALUES (1, x'01');",
+            "INSERT INTO example(rowid, col) VALUES (2, x'0202');",
+            "INSERT INTO example(rowid, col) VALUES (3, x'ffffffffffffffff');", // 8 bytes
+            "INSERT INTO example(rowid, col) VALUES (4, x'04');",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+
+        // The trailing 7 bytes become a freeblock.
+        cursor
+            .table_insert(3, &SlicePayload::new(&[2, 14, 3]).unwrap())
+            .unwrap();
+        // The trailing 2 bytes can not be a freeblock. The cell is relocated.
+        cursor
+            .table_insert(2, &SlicePayload::new(&[2]).unwrap())
+            .unwrap();
+
+        cursor.move_to_first().unwrap();
+        assert_all_local_in_table_cursor(
+            &mut cursor,
+            &[
+                (1, &[2, 14, 1]),
+                (2, &[2]),
+                (3, &[2, 14, 3]),
+                (4, &[2, 14, 4]),
+            ],
+        );
+
+        let page = pager.get_page(table_page_id).unwrap();
+        let buffer = page.buffer();
+        let page_header = BtreePageHeader::from_page(&page, &buffer);
+        assert_eq!(page_header.n_cells(), 4);
+        assert_eq!(page_header.fragmented_free_bytes(), 0);
+        assert_eq!(page_header.cell_content_area_offset().get(), 4068);
+
+        let mut freeblocks = FreeblockIterator::new(page_header.first_freeblock_offset(), &buffer);
+        // The new cell for rowid 2 is allocated from the freeblock of rowid 3.
+        assert_eq!(freeblocks.next(), Some((4082, 9)));
+        assert_eq!(freeblocks.next(), None);
+    }
+
+    #[test]
+    fn test_update_table_longer() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(rowid, col) VALUES (1, x'01');",
+            "INSERT INTO example(rowid, col) VALUES (2, x'0202');",
+            "INSERT INTO example(rowid, col) VALUES (3, x'03');",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+
+        cursor
+            .table_insert(
+                1,
+                &SlicePayload::new(&[2, 28, 1, 1, 1, 1, 1, 1, 1, 1]).unwrap(),
+            )
+            .unwrap();
+
+        cursor.move_to_first().unwrap();
+        assert_all_local_in_table_cursor(
+            &mut cursor,
+            &[
+                (1, &[2, 28, 1, 1, 1, 1, 1, 1, 1, 1]),
+                (2, &[2, 16, 2, 2]),
+                (3, &[2, 14, 3]),
+            ],
+        );
+
+        let page = pager.get_page(table_page_id).unwrap();
+        let buffer = page.buffer();
+        let page_header = BtreePageHeader::from_page(&page, &buffer);
+        assert_eq!(page_header.n_cells(), 3);
+        assert_eq!(page_header.fragmented_free_bytes(), 0);
+        assert_eq!(page_header.cell_content_area_offset().get(), 4068);
+
+        let mut freeblocks = FreeblockIterator::new(page_header.first_freeblock_offset(), &buffer);
+        assert_eq!(freeblocks.next(), Some((4091, 5)));
+        assert_eq!(freeblocks.next(), None);
+    }
+
+    #[test]
+    fn test_update_table_split() {
+        let file =
+            create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        let small = [1; 100];
+        let large = [2; 400];
+        for i in 0..4 {
+            cursor
+                .table_insert(i, &SlicePayload::new(&small).unwrap())
+                .unwrap();
+        }
+
+        cursor
+            .table_insert(1, &SlicePayload::new(&large).unwrap())
+            .unwrap();
+
+        let page = pager.get_page(table_page_id).unwrap();
+        let buffer = page.buffer();
+        let page_header = BtreePageHeader::from_page(&page, &buffer);
+        assert!(!page_header.page_type().is_leaf());
+        drop(buffer);
+
+        cursor.move_to_first().unwrap();
+        assert_all_local_in_table_cursor(
+            &mut cursor,
+            &[(0, &small), (1, &large), (2, &small), (3, &small)],
+        );
+    }
+
+    #[test]
+    fn test_update_table_overflow() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(rowid, col) VALUES (1, x'01');",
+            "INSERT INTO example(rowid, col) VALUES (2, x'02');",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+        let usable_size = bctx.usable_size as usize;
+
+        let mut data = Vec::with_capacity(usable_size * 2);
+        for _ in 0..usable_size * 2 {
+            data.push(rand::random::<u8>());
+        }
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        cursor
+            .table_insert(1, &SlicePayload::new(&data).unwrap())
+            .unwrap();
+        cursor.table_move_to(1).unwrap();
+        let (key, payload) = cursor.get_table_payload().unwrap().unwrap();
+        assert_eq!(key, 1);
+        assert_eq!(payload.size().get() as usize, data.len());
+        let mut buf = vec![0; data.len()];
+        assert_eq!(payload.load(0, &mut buf).unwrap(), data.len());
+        assert_eq!(buf, data);
+        drop(payload);
+
+        // Replace the overflowing payload with another overflowing payload.
+        data.reverse();
+        cursor
+            .table_insert(1, &SlicePayload::new(&data).unwrap())
+            .unwrap();
+        cursor.table_move_to(1).unwrap();
+        let (key, payload) = cursor.get_table_payload().unwrap().unwrap();
+        assert_eq!(key, 1);
+        assert_eq!(payload.size().get() as usize, data.len());
+        let mut buf = vec![0; data.len()];
+        assert_eq!(payload.load(0, &mut buf).unwrap(), data.len());
+        assert_eq!(buf, data);
+        drop(payload);
+
+        cursor
+            .table_insert(1, &SlicePayload::new(&[2, 14, 1]).unwrap())
+            .unwrap();
+
+        cursor.move_to_first().unwrap();
+        assert_all_local_in_table_cursor(&mut cursor, &[(1, &[2, 14, 1]), (2, &[2, 14, 2])]);
+    }
+
+    #[test]
+    fn test_create_btree() {
+        let file =
+            create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+
+        let table_page_id = create_btree(&pager, &bctx, true).unwrap();
+        let index_page_id = create_btree(&pager, &bctx, false).unwrap();
+        assert_ne!(table_page_id, index_page_id);
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        cursor.move_to_first().unwrap();
+        assert!(cursor.get_table_payload().unwrap().is_none());
+        for i in 0..100 {
+            cursor
+                .table_insert(i, &SlicePayload::new(&[1; 100]).unwrap())
+                .unwrap();
+        }
+        cursor.move_to_last().unwrap();
+        assert_eq!(cursor.get_table_key().unwrap(), Some(99));
+
+        let mut cursor = BtreeCursor::new(index_page_id, &pager, &bctx).unwrap();
+        cursor.move_to_first().unwrap();
+        assert!(cursor.get_index_payload().unwrap().is_none());
+        assert!(cursor.get_table_payload().is_err());
+    }
+
     #[test]
     fn test_insert_table_split() {
         let file =
@@ -4326,7 +5481,7 @@ mod tests {
             let p_size = max_local + i;
             let p_size = (p_size as u64).try_into().unwrap();
             let n_local = bctx.n_local(false, p_size) as usize;
-            if (p_size.get() as usize - n_local) % (bctx.usable_size as usize - 4) == 0 {
+            if (p_size.get() as usize - n_local).is_multiple_of(bctx.usable_size as usize - 4) {
                 size_exact_overflow_page_size = Some(p_size);
                 break;
             }
@@ -5439,36 +6594,37 @@ mod tests {
         }
         assert!(cursor.get_index_payload().unwrap().is_none());
 
-        // Delete cell in the root page.
-        for _ in 0..6 {
+        // Delete cells in the root page until the root page has no cells. The root
+        // page is replaced with its child page then.
+        cursor.move_to_first().unwrap();
+        let depth = cursor.parent_pages.len();
+        let mut n_entries = 22;
+        loop {
             cursor.move_to_first().unwrap();
-            assert!(cursor.parent_pages.len() >= 2);
+            assert!(cursor.parent_pages.iter().all(|page| page.n_cells > 0));
+            if cursor.parent_pages.len() < depth {
+                break;
+            }
             while !cursor.parent_pages.is_empty() {
                 cursor.move_next().unwrap();
             }
             cursor.delete().unwrap();
+            n_entries -= 1;
         }
-        cursor.move_to_first().unwrap();
-        // Root page is empty.
-        assert_eq!(cursor.parent_pages[0].n_cells, 0);
+        assert_eq!(cursor.parent_pages.len(), depth - 1);
 
-        // Delete cell in the second level page.
-        for _ in 0..12 {
-            cursor.move_to_first().unwrap();
-            assert!(cursor.parent_pages.len() >= 2);
-            while cursor.parent_pages.len() != 1 {
-                cursor.move_next().unwrap();
-            }
-            cursor.delete().unwrap();
+        let mut remaining = payloads.iter();
+        for _ in 0..n_entries {
+            let payload = cursor.get_index_payload().unwrap().unwrap();
+            assert!(remaining.any(|expected| payload.buf() == expected));
+            drop(payload);
+            cursor.move_next().unwrap();
         }
+        assert!(cursor.get_index_payload().unwrap().is_none());
 
-        cursor.move_to_first().unwrap();
-        assert_eq!(cursor.parent_pages[0].n_cells, 0);
-        // Second level page is empty.
-        assert_eq!(cursor.parent_pages[1].n_cells, 0);
-
-        for _ in 0..4 {
+        for _ in 0..n_entries {
             cursor.move_to_first().unwrap();
+            assert!(cursor.parent_pages.iter().all(|page| page.n_cells > 0));
             cursor.delete().unwrap();
         }
         assert!(cursor.get_index_payload().unwrap().is_none());
@@ -5478,4 +6634,84 @@ mod tests {
 
         assert_eq!(pager.num_pages() - pager.num_free_pages(), initial_pages);
     }
+
+    #[test]
+    fn test_table_bulk_loader() {
+        let file = create_sqlite_database(&[
+            "PRAGMA page_size = 512;",
+            "CREATE TABLE example(col);",
+            "CREATE TABLE example2(col);",
+        ]);
+        let pager = create_pager(file.as_file().try_clone().unwrap()).unwrap();
+        let bctx = load_btree_context(file.as_file()).unwrap();
+        let table_page_id = find_table_page_id("example", file.path());
+        let table2_page_id = find_table_page_id("example2", file.path());
+        let n_pages_before = pager.num_pages();
+
+        let payload = |key: i64| {
+            let mut buf = vec![key.rem_euclid(256) as u8; 1 + key.rem_euclid(20) as usize];
+            // The payloads of some entries overflow.
+            if key % 10000 == 0 {
+                buf.resize(2000, 1);
+            }
+            buf
+        };
+        let keys = (1..=100000).map(|i| i * 3 - 100).collect::<Vec<_>>();
+        let n_compared_keys = 10000;
+
+        let cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        let mut loader = cursor.into_table_bulk_loader().unwrap();
+        let mut n_bulk_pages = 0;
+        for (i, key) in keys.iter().enumerate() {
+            if i == n_compared_keys {
+                n_bulk_pages = pager.num_pages() - n_pages_before;
+            }
+            loader
+                .append(*key, &SlicePayload::new(&payload(*key)).unwrap())
+                .unwrap();
+        }
+        assert!(matches!(
+            loader.append(keys[keys.len() - 1], &SlicePayload::new(&[1]).unwrap()),
+            Err(Error::UnsortedKey)
+        ));
+        loader.finish().unwrap();
+
+        let mut cursor = BtreeCursor::new(table_page_id, &pager, &bctx).unwrap();
+        cursor.move_to_first().unwrap();
+        for key in &keys {
+            let (k, p) = cursor.get_table_payload().unwrap().unwrap();
+            assert_eq!(k, *key);
+            let mut buf = vec![0; p.size().get() as usize];
+            assert_eq!(p.load(0, &mut buf).unwrap(), buf.len());
+            assert_eq!(buf, payload(*key));
+            drop(p);
+            cursor.move_next().unwrap();
+        }
+        assert!(cursor.get_table_payload().unwrap().is_none());
+        assert_eq!(cursor.count().unwrap(), keys.len() as u64);
+        assert_eq!(cursor.table_move_to(keys[5000]).unwrap(), Some(keys[5000]));
+
+        assert!(matches!(
+            BtreeCursor::new(table_page_id, &pager, &bctx)
+                .unwrap()
+                .into_table_bulk_loader(),
+            Err(Error::NotEmpty)
+        ));
+
+        // The leaf pages are filled to capacity unlike inserting one by one.
+        let n_pages_before = pager.num_pages();
+        let mut cursor = BtreeCursor::new(table2_page_id, &pager, &bctx).unwrap();
+        for key in &keys[..n_compared_keys] {
+            cursor
+                .table_insert(*key, &SlicePayload::new(&payload(*key)).unwrap())
+                .unwrap();
+        }
+        let n_insert_pages = pager.num_pages() - n_pages_before;
+        assert!(
+            n_bulk_pages * 3 < n_insert_pages * 2,
+            "bulk: {}, insert: {}",
+            n_bulk_pages,
+            n_insert_pages
+        );
+    }
 }
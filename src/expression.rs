@@ -14,15 +14,33 @@
 
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::rc::Rc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
+use crate::aggregate::AggregateCall;
+use crate::aggregate::AggregateFunction;
+use crate::aggregate::Aggregation;
+use crate::function;
+use crate::function::ScalarFunction;
+use crate::function::UserFunction;
+use crate::function::UserFunctions;
 use crate::parser::BinaryOp;
 use crate::parser::CompareOp;
+use crate::parser::CurrentTime;
 use crate::parser::Expr;
+use crate::parser::PatternOp;
+use crate::parser::Select;
 use crate::parser::UnaryOp;
+use crate::pattern::escape_char;
+use crate::pattern::glob;
+use crate::pattern::like;
+use crate::query::RowData;
 use crate::schema::calc_collation;
 use crate::schema::calc_type_affinity;
 use crate::schema::ColumnNumber;
 use crate::schema::Table;
+use crate::utils::CaseInsensitiveBytes;
 use crate::value::Buffer;
 use crate::value::Collation;
 use crate::value::ConstantValue;
@@ -35,8 +53,17 @@ use crate::value::DEFAULT_COLLATION;
 pub enum Error {
     CollationNotFound,
     ColumnNotFound,
+    AmbiguousColumn,
     NoTableContext,
+    FunctionNotFound,
+    MisuseAggregateFunction,
+    InvalidEscape,
+    IntegerOverflow,
+    UnsupportedSubquery,
+    TooManySubqueryRows,
     FailGetColumn(Box<dyn std::error::Error + Sync + Send>),
+    FailGetAggregate(Box<dyn std::error::Error + Sync + Send>),
+    FailUserFunction(anyhow::Error),
 }
 
 impl std::error::Error for Error {
@@ -44,8 +71,17 @@ impl std::error::Error for Error {
         match self {
             Self::CollationNotFound => None,
             Self::ColumnNotFound => None,
+            Self::AmbiguousColumn => None,
             Self::NoTableContext => None,
+            Self::FunctionNotFound => None,
+            Self::MisuseAggregateFunction => None,
+            Self::InvalidEscape => None,
+            Self::IntegerOverflow => None,
+            Self::UnsupportedSubquery => None,
+            Self::TooManySubqueryRows => None,
             Self::FailGetColumn(e) => Some(e.as_ref()),
+            Self::FailGetAggregate(e) => Some(e.as_ref()),
+            Self::FailUserFunction(e) => Some(e.as_ref()),
         }
     }
 }
@@ -59,12 +95,39 @@ impl Display for Error {
             Self::ColumnNotFound => {
                 write!(f, "column not found")
             }
+            Self::AmbiguousColumn => {
+                write!(f, "ambiguous column name")
+            }
             Self::NoTableContext => {
                 write!(f, "no table context")
             }
+            Self::FunctionNotFound => {
+                write!(f, "function not found")
+            }
+            Self::MisuseAggregateFunction => {
+                write!(f, "misuse of aggregate function")
+            }
+            Self::InvalidEscape => {
+                write!(f, "ESCAPE expression must be a single character")
+            }
+            Self::IntegerOverflow => {
+                write!(f, "integer overflow")
+            }
+            Self::UnsupportedSubquery => {
+                write!(f, "subquery is not supported here")
+            }
+            Self::TooManySubqueryRows => {
+                write!(f, "scalar subquery returns more than one row")
+            }
             Self::FailGetColumn(e) => {
                 write!(f, "fail to get column: {}", e)
             }
+            Self::FailGetAggregate(e) => {
+                write!(f, "fail to get aggregate value: {}", e)
+            }
+            Self::FailUserFunction(e) => {
+                write!(f, "user function failed: {}", e)
+            }
         }
     }
 }
@@ -91,16 +154,194 @@ fn filter_expression_collation(
     }
 }
 
+/// Choose the collation sequence used by a binary comparison.
+///
+/// https://www.sqlite.org/datatype3.html#assigning_collating_sequences_from_sql
+fn merge_collation<'a>(
+    left: Option<(&'a Collation, CollateOrigin)>,
+    right: Option<(&'a Collation, CollateOrigin)>,
+) -> Option<(&'a Collation, CollateOrigin)> {
+    match (left, right) {
+        (None, _) => right,
+        (Some((_, CollateOrigin::Column)), Some((_, CollateOrigin::Expression))) => right,
+        _ => left,
+    }
+}
+
+/// Compare 2 non-NULL values.
+///
+/// Type Conversions Prior To Comparison are applied to the values.
+///
+/// https://www.sqlite.org/datatype3.html#type_conversions_prior_to_comparison
+fn compare_values(
+    mut left_value: Value,
+    left_affinity: Option<TypeAffinity>,
+    mut right_value: Value,
+    right_affinity: Option<TypeAffinity>,
+    collation: Option<(&Collation, CollateOrigin)>,
+) -> Ordering {
+    match (left_affinity, right_affinity) {
+        (
+            Some(TypeAffinity::Integer) | Some(TypeAffinity::Real) | Some(TypeAffinity::Numeric),
+            Some(TypeAffinity::Text) | Some(TypeAffinity::Blob) | None,
+        ) => {
+            right_value = right_value.apply_numeric_affinity();
+        }
+        (
+            Some(TypeAffinity::Text) | Some(TypeAffinity::Blob) | None,
+            Some(TypeAffinity::Integer) | Some(TypeAffinity::Real) | Some(TypeAffinity::Numeric),
+        ) => {
+            left_value = left_value.apply_numeric_affinity();
+        }
+        (Some(TypeAffinity::Text), None) => {
+            right_value = right_value.apply_text_affinity();
+        }
+        (None, Some(TypeAffinity::Text)) => {
+            left_value = left_value.apply_text_affinity();
+        }
+        _ => {}
+    }
+
+    ValueCmp::new(
+        &left_value,
+        collation.map(|(c, _)| c).unwrap_or(&DEFAULT_COLLATION),
+    )
+    .compare(&right_value)
+}
+
+/// Evaluate an arithmetic operator on 2 non-NULL values.
+///
+/// Integer results which overflow are computed as real values. Returns [None]
+/// on division by zero or if the result is NaN.
+///
+/// This comes from OP_Add, OP_Subtract, OP_Multiply, OP_Divide and
+/// OP_Remainder of SQLite.
+fn arithmetic(operator: BinaryOp, left: Value, right: Value) -> Option<Value<'static>> {
+    let left = left.force_numeric();
+    let right = right.force_numeric();
+    if let (Value::Integer(l), Value::Integer(r)) = (&left, &right) {
+        let (l, r) = (*l, *r);
+        let result = match operator {
+            BinaryOp::Add => l.checked_add(r),
+            BinaryOp::Sub => l.checked_sub(r),
+            BinaryOp::Mul => l.checked_mul(r),
+            BinaryOp::Div if r == 0 => return None,
+            // i64::MIN / -1 overflows.
+            BinaryOp::Div => l.checked_div(r),
+            BinaryOp::Rem if r == 0 => return None,
+            // i64::MIN % -1 overflows.
+            BinaryOp::Rem => Some(l % if r == -1 { 1 } else { r }),
+            _ => unreachable!("not an arithmetic operator"),
+        };
+        if let Some(result) = result {
+            return Some(Value::Integer(result));
+        }
+    }
+    let result = match operator {
+        BinaryOp::Add => left.as_real() + right.as_real(),
+        BinaryOp::Sub => left.as_real() - right.as_real(),
+        BinaryOp::Mul => left.as_real() * right.as_real(),
+        BinaryOp::Div => {
+            let r = right.as_real();
+            if r == 0.0 {
+                return None;
+            }
+            left.as_real() / r
+        }
+        BinaryOp::Rem => {
+            // The operands are truncated to integers.
+            let r = right.as_integer();
+            if r == 0 {
+                return None;
+            }
+            (left.as_integer() % if r == -1 { 1 } else { r }) as f64
+        }
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    if result.is_nan() {
+        None
+    } else {
+        Some(Value::Real(result))
+    }
+}
+
+/// Evaluate a bitwise operator on 2 non-NULL values.
+///
+/// Shifting by a negative amount shifts in the other direction. Right shifts
+/// are arithmetic.
+///
+/// This comes from OP_BitAnd, OP_BitOr, OP_ShiftLeft and OP_ShiftRight of
+/// SQLite.
+fn bitwise(mut operator: BinaryOp, left: &Value, right: &Value) -> i64 {
+    let value = left.as_integer();
+    let mut amount = right.as_integer();
+    match operator {
+        BinaryOp::BitAnd => return value & amount,
+        BinaryOp::BitOr => return value | amount,
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => {}
+        _ => unreachable!("not a bitwise operator"),
+    }
+    if amount < 0 {
+        operator = if operator == BinaryOp::ShiftLeft {
+            BinaryOp::ShiftRight
+        } else {
+            BinaryOp::ShiftLeft
+        };
+        amount = amount.checked_neg().unwrap_or(64);
+    }
+    if amount >= 64 {
+        if value >= 0 || operator == BinaryOp::ShiftLeft {
+            0
+        } else {
+            -1
+        }
+    } else if operator == BinaryOp::ShiftLeft {
+        ((value as u64) << amount) as i64
+    } else {
+        value >> amount
+    }
+}
+
+#[inline]
+fn bool_to_value(b: bool) -> Value<'static> {
+    if b {
+        Value::Integer(1)
+    } else {
+        Value::Integer(0)
+    }
+}
+
 pub trait DataContext {
     fn get_column_value(
         &self,
         column_idx: &ColumnNumber,
-    ) -> std::result::Result<Option<Value>, Box<dyn std::error::Error + Sync + Send>>;
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>>;
+
+    /// Get the value of the column of the outer table of a join referred by
+    /// [Expression::OuterColumn].
+    fn get_outer_column_value(
+        &self,
+        _column_idx: &ColumnNumber,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        Err(Box::new(Error::NoTableContext))
+    }
+
+    /// Get the result of the aggregate function referred by
+    /// [Expression::Aggregate].
+    fn get_aggregate_value(
+        &self,
+        _idx: usize,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        Err(Box::new(Error::MisuseAggregateFunction))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     Column((ColumnNumber, TypeAffinity, Collation)),
+    /// A column of the outer table of a join. [Self::Column] is a column of
+    /// the inner table.
+    OuterColumn((ColumnNumber, TypeAffinity, Collation)),
     UnaryOperator {
         operator: UnaryOp,
         expr: Box<Expression>,
@@ -118,32 +359,238 @@ pub enum Expression {
         expr: Box<Expression>,
         type_affinity: TypeAffinity,
     },
+    InList {
+        expr: Box<Expression>,
+        list: Vec<Expression>,
+    },
+    /// `expr IN (SELECT ...)`. The values are bound by
+    /// [Self::bind_subquery()] before execution.
+    InSubquery {
+        expr: Box<Expression>,
+        idx: usize,
+        values: Option<Rc<SubqueryValues>>,
+    },
+    /// A scalar subquery. The value is bound by [Self::bind_subquery()]
+    /// before execution.
+    Subquery {
+        idx: usize,
+        value: Option<ConstantValue>,
+        affinity: Option<TypeAffinity>,
+    },
+    Between {
+        expr: Box<Expression>,
+        lower: Box<Expression>,
+        upper: Box<Expression>,
+    },
+    Pattern {
+        operator: PatternOp,
+        expr: Box<Expression>,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+    },
+    Function {
+        function: ScalarFunction,
+        args: Vec<Expression>,
+    },
+    UserFunction {
+        function: Rc<UserFunction>,
+        args: Vec<Expression>,
+    },
+    Case {
+        operand: Option<Box<Expression>>,
+        /// Pairs of WHEN and THEN expressions.
+        branches: Vec<(Expression, Expression)>,
+        else_expr: Option<Box<Expression>>,
+    },
+    /// The index of [Aggregation::calls].
+    Aggregate(usize),
+    CurrentTime(CurrentTime),
+    /// The 1-based index and the bound value. Unbound parameters are NULL.
+    Parameter {
+        idx: usize,
+        value: Option<ConstantValue>,
+    },
     Null,
     Const(ConstantValue),
 }
 
+/// The result of the subquery of IN, sorted to look up values by binary
+/// search.
+#[derive(Debug)]
+pub struct SubqueryValues {
+    /// The affinity applied to both sides of the comparison.
+    affinity: Option<TypeAffinity>,
+    collation: Collation,
+    /// Distinct non-NULL values.
+    values: Vec<ConstantValue>,
+    has_null: bool,
+}
+
+impl SubqueryValues {
+    /// `left` is the left operand of IN and `column` is the result column of
+    /// the subquery.
+    fn new(left: &Expression, column: &Expression, rows: &[Vec<Option<ConstantValue>>]) -> Self {
+        let is_numeric = |affinity| {
+            matches!(
+                affinity,
+                TypeAffinity::Integer | TypeAffinity::Real | TypeAffinity::Numeric
+            )
+        };
+        let affinity = match (left.affinity(), column.affinity()) {
+            (Some(left), Some(right)) if is_numeric(left) || is_numeric(right) => {
+                Some(TypeAffinity::Numeric)
+            }
+            (Some(_), Some(_)) => None,
+            (left, right) => left.or(right),
+        };
+        let collation = merge_collation(left.static_collation(), column.static_collation())
+            .map_or(DEFAULT_COLLATION.clone(), |(collation, _)| {
+                collation.clone()
+            });
+
+        let mut result = Self {
+            affinity,
+            collation,
+            values: Vec::with_capacity(rows.len()),
+            has_null: false,
+        };
+        for row in rows {
+            match &row[0] {
+                Some(value) => {
+                    let value = result.apply_affinity(value.as_value());
+                    result.values.push(ConstantValue::copy_from(value));
+                }
+                None => result.has_null = true,
+            }
+        }
+        let collation = &result.collation;
+        result
+            .values
+            .sort_by(|v1, v2| ValueCmp::new(&v1.as_value(), collation).compare(&v2.as_value()));
+        result.values.dedup_by(|v1, v2| {
+            ValueCmp::new(&v1.as_value(), collation).compare(&v2.as_value()) == Ordering::Equal
+        });
+        result
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.is_empty() && !self.has_null
+    }
+
+    fn apply_affinity<'a>(&self, value: Value<'a>) -> Value<'a> {
+        match self.affinity {
+            Some(TypeAffinity::Integer | TypeAffinity::Real | TypeAffinity::Numeric) => {
+                value.apply_numeric_affinity()
+            }
+            Some(TypeAffinity::Text) => value.apply_text_affinity(),
+            Some(TypeAffinity::Blob) | None => value,
+        }
+    }
+
+    fn contains(&self, value: Value) -> bool {
+        let value = self.apply_affinity(value);
+        self.values
+            .binary_search_by(|v| ValueCmp::new(&v.as_value(), &self.collation).compare(&value))
+            .is_ok()
+    }
+}
+
 impl Expression {
     #[inline]
     pub fn one() -> Self {
         Self::Const(ConstantValue::Integer(1))
     }
 
-    pub fn from(expr: Expr, table: Option<&Table>) -> Result<Self> {
+    /// Convert the expression referring to the columns of the tables.
+    ///
+    /// Each table is paired with its dequoted name. The tables are the outer
+    /// table and the inner table for a join.
+    pub fn from(expr: Expr, tables: &[(&[u8], &Table)], functions: &UserFunctions) -> Result<Self> {
+        let mut subqueries = Vec::new();
+        let expr = Self::convert(expr, tables, functions, None, &mut subqueries)?;
+        if !subqueries.is_empty() {
+            return Err(Error::UnsupportedSubquery);
+        }
+        Ok(expr)
+    }
+
+    /// Convert the expression which may contain subqueries.
+    ///
+    /// The subqueries are moved to `subqueries` and referred by their indexes.
+    pub fn from_with_subqueries<'a>(
+        expr: Expr<'a>,
+        tables: &[(&[u8], &Table)],
+        functions: &UserFunctions,
+        subqueries: &mut Vec<Select<'a>>,
+    ) -> Result<Self> {
+        Self::convert(expr, tables, functions, None, subqueries)
+    }
+
+    /// Convert the expression which may contain aggregate functions.
+    ///
+    /// The aggregate function calls and the columns referred outside of them
+    /// are registered to the [Aggregation].
+    pub fn from_aggregate(
+        expr: Expr,
+        tables: &[(&[u8], &Table)],
+        functions: &UserFunctions,
+        aggregation: &mut Aggregation,
+    ) -> Result<Self> {
+        let mut subqueries = Vec::new();
+        let expr = Self::convert(expr, tables, functions, Some(aggregation), &mut subqueries)?;
+        if !subqueries.is_empty() {
+            return Err(Error::UnsupportedSubquery);
+        }
+        Ok(expr)
+    }
+
+    fn convert<'a>(
+        expr: Expr<'a>,
+        tables: &[(&[u8], &Table)],
+        functions: &UserFunctions,
+        aggregation: Option<&mut Aggregation>,
+        subqueries: &mut Vec<Select<'a>>,
+    ) -> Result<Self> {
+        Self::convert_node(expr, tables, functions, aggregation, subqueries)
+            .map(Self::fold_constant)
+    }
+
+    fn convert_node<'a>(
+        expr: Expr<'a>,
+        tables: &[(&[u8], &Table)],
+        functions: &UserFunctions,
+        mut aggregation: Option<&mut Aggregation>,
+        subqueries: &mut Vec<Select<'a>>,
+    ) -> Result<Self> {
         match expr {
             Expr::Null => Ok(Self::Null),
+            Expr::CurrentTime(kind) => Ok(Self::CurrentTime(kind)),
             Expr::Integer(i) => Ok(Self::Const(ConstantValue::Integer(i))),
             Expr::Real(f) => Ok(Self::Const(ConstantValue::Real(f))),
             Expr::Text(text) => Ok(Self::Const(ConstantValue::Text(text.dequote()))),
             Expr::Blob(hex) => Ok(Self::Const(ConstantValue::Blob(hex.decode()))),
+            Expr::Parameter(idx) => Ok(Self::Parameter { idx, value: None }),
             Expr::UnaryOperator { operator, expr } => Ok(Self::UnaryOperator {
                 operator,
-                expr: Box::new(Self::from(*expr, table)?),
+                expr: Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
             }),
             Expr::Collate {
                 expr,
                 collation_name,
             } => Ok(Self::Collate {
-                expr: Box::new(Self::from(*expr, table)?),
+                expr: Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
                 collation: calc_collation(&collation_name).ok_or(Error::CollationNotFound)?,
             }),
             Expr::BinaryOperator {
@@ -152,36 +599,639 @@ impl Expression {
                 right,
             } => Ok(Self::BinaryOperator {
                 operator,
-                left: Box::new(Self::from(*left, table)?),
-                right: Box::new(Self::from(*right, table)?),
+                left: Box::new(Self::convert(
+                    *left,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+                right: Box::new(Self::convert(
+                    *right,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
             }),
             Expr::Column(column_name) => {
-                if let Some(table) = table {
-                    let column_name = column_name.dequote();
-                    table
-                        .get_column(&column_name)
-                        .map(Self::Column)
-                        .ok_or(Error::ColumnNotFound)
-                } else {
-                    Err(Error::NoTableContext)
-                }
+                Self::convert_column(tables, None, &column_name.dequote(), aggregation)
             }
+            Expr::QualifiedColumn {
+                table_name,
+                column_name,
+            } => Self::convert_column(
+                tables,
+                Some(&table_name.dequote()),
+                &column_name.dequote(),
+                aggregation,
+            ),
             Expr::Cast { expr, type_name } => Ok(Self::Cast {
-                expr: Box::new(Self::from(*expr, table)?),
+                expr: Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
                 type_affinity: calc_type_affinity(&type_name),
             }),
+            Expr::InList { expr, list } => {
+                let expr = Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?);
+                let mut converted_list = Vec::with_capacity(list.len());
+                for item in list {
+                    converted_list.push(Self::convert(
+                        item,
+                        tables,
+                        functions,
+                        aggregation.as_deref_mut(),
+                        subqueries,
+                    )?);
+                }
+                Ok(Self::InList {
+                    expr,
+                    list: converted_list,
+                })
+            }
+            Expr::InSelect { expr, select } => {
+                let expr = Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?);
+                subqueries.push(*select);
+                Ok(Self::InSubquery {
+                    expr,
+                    idx: subqueries.len() - 1,
+                    values: None,
+                })
+            }
+            Expr::Subquery(select) => {
+                subqueries.push(*select);
+                Ok(Self::Subquery {
+                    idx: subqueries.len() - 1,
+                    value: None,
+                    affinity: None,
+                })
+            }
+            Expr::Between { expr, lower, upper } => Ok(Self::Between {
+                expr: Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+                lower: Box::new(Self::convert(
+                    *lower,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+                upper: Box::new(Self::convert(
+                    *upper,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+            }),
+            Expr::Pattern {
+                operator,
+                expr,
+                pattern,
+                escape,
+            } => Ok(Self::Pattern {
+                operator,
+                expr: Box::new(Self::convert(
+                    *expr,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+                pattern: Box::new(Self::convert(
+                    *pattern,
+                    tables,
+                    functions,
+                    aggregation.as_deref_mut(),
+                    subqueries,
+                )?),
+                escape: match escape {
+                    Some(escape) => Some(Box::new(Self::convert(
+                        *escape,
+                        tables,
+                        functions,
+                        aggregation.as_deref_mut(),
+                        subqueries,
+                    )?)),
+                    None => None,
+                },
+            }),
+            Expr::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some(Box::new(Self::convert(
+                        *operand,
+                        tables,
+                        functions,
+                        aggregation.as_deref_mut(),
+                        subqueries,
+                    )?)),
+                    None => None,
+                };
+                let mut converted_branches = Vec::with_capacity(branches.len());
+                for (when, then) in branches {
+                    converted_branches.push((
+                        Self::convert(
+                            when,
+                            tables,
+                            functions,
+                            aggregation.as_deref_mut(),
+                            subqueries,
+                        )?,
+                        Self::convert(
+                            then,
+                            tables,
+                            functions,
+                            aggregation.as_deref_mut(),
+                            subqueries,
+                        )?,
+                    ));
+                }
+                let else_expr = match else_expr {
+                    Some(else_expr) => Some(Box::new(Self::convert(
+                        *else_expr,
+                        tables,
+                        functions,
+                        aggregation.as_deref_mut(),
+                        subqueries,
+                    )?)),
+                    None => None,
+                };
+                Ok(Self::Case {
+                    operand,
+                    branches: converted_branches,
+                    else_expr,
+                })
+            }
+            Expr::Function {
+                name,
+                mut args,
+                distinct,
+            } => {
+                let name = name.dequote().to_ascii_lowercase();
+                // DISTINCT is ignored by scalar functions as SQLite does.
+                // User defined functions override the built-in functions.
+                if let Some(function) = functions.lookup(&name, args.len()) {
+                    let mut converted_args = Vec::with_capacity(args.len());
+                    for arg in args {
+                        converted_args.push(Self::convert(
+                            arg,
+                            tables,
+                            functions,
+                            aggregation.as_deref_mut(),
+                            subqueries,
+                        )?);
+                    }
+                    return Ok(Self::UserFunction {
+                        function: function.clone(),
+                        args: converted_args,
+                    });
+                }
+                if let Some(function) = ScalarFunction::lookup(&name, args.len()) {
+                    let mut converted_args = Vec::with_capacity(args.len());
+                    for arg in args {
+                        converted_args.push(Self::convert(
+                            arg,
+                            tables,
+                            functions,
+                            aggregation.as_deref_mut(),
+                            subqueries,
+                        )?);
+                    }
+                    return Ok(Self::Function {
+                        function,
+                        args: converted_args,
+                    });
+                }
+                let function =
+                    AggregateFunction::lookup(&name, args.len()).ok_or(Error::FunctionNotFound)?;
+                let Some(aggregation) = aggregation else {
+                    return Err(Error::MisuseAggregateFunction);
+                };
+                // Aggregate functions can not be nested.
+                let arg = args
+                    .pop()
+                    .map(|arg| Self::convert(arg, tables, functions, None, subqueries))
+                    .transpose()?;
+                aggregation.calls.push(AggregateCall {
+                    function,
+                    arg,
+                    distinct,
+                });
+                Ok(Self::Aggregate(aggregation.calls.len() - 1))
+            }
+        }
+    }
+
+    /// Find the column by the name, qualified by the table name if any.
+    ///
+    /// The last table is the inner table. An unqualified name must match only
+    /// one table.
+    fn convert_column(
+        tables: &[(&[u8], &Table)],
+        table_name: Option<&[u8]>,
+        column_name: &[u8],
+        aggregation: Option<&mut Aggregation>,
+    ) -> Result<Self> {
+        if tables.is_empty() {
+            return Err(Error::NoTableContext);
+        }
+        let mut found = None;
+        for (i, (name, table)) in tables.iter().enumerate() {
+            if let Some(table_name) = table_name {
+                if CaseInsensitiveBytes::from(table_name) != CaseInsensitiveBytes::from(*name) {
+                    continue;
+                }
+            }
+            if let Some(column) = table.get_column(column_name) {
+                if found.is_some() {
+                    return Err(Error::AmbiguousColumn);
+                }
+                found = Some((i, column));
+            }
+        }
+        let (i, column) = found.ok_or(Error::ColumnNotFound)?;
+        if i + 1 < tables.len() {
+            return Ok(Self::OuterColumn(column));
+        }
+        if let Some(aggregation) = aggregation {
+            if !aggregation.columns.contains(&column.0) {
+                aggregation.columns.push(column.0);
+            }
+        }
+        Ok(Self::Column(column))
+    }
+
+    /// Replace the expression with its result if all the operands are
+    /// constants.
+    ///
+    /// The expression is kept as is if the result has a type affinity or a
+    /// collation, or if the evaluation fails so that the error is reported on
+    /// execution.
+    fn fold_constant(self) -> Self {
+        let is_const = |expr: &Self| matches!(expr, Self::Const(_) | Self::Null);
+        let foldable = match &self {
+            Self::UnaryOperator { expr, .. } => is_const(expr),
+            Self::BinaryOperator { left, right, .. } => is_const(left) && is_const(right),
+            Self::InList { expr, list } => is_const(expr) && list.iter().all(is_const),
+            Self::Between { expr, lower, upper } => {
+                is_const(expr) && is_const(lower) && is_const(upper)
+            }
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => is_const(expr) && is_const(pattern) && escape.as_deref().is_none_or(is_const),
+            Self::Function { args, .. } => args.iter().all(is_const),
+            Self::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                operand.as_deref().is_none_or(is_const)
+                    && branches
+                        .iter()
+                        .all(|(when, then)| is_const(when) && is_const(then))
+                    && else_expr.as_deref().is_none_or(is_const)
+            }
+            // User defined functions may not be deterministic.
+            Self::UserFunction { .. }
+            | Self::Column(_)
+            | Self::OuterColumn(_)
+            | Self::Collate { .. }
+            | Self::Cast { .. }
+            | Self::Aggregate(_)
+            | Self::CurrentTime(_)
+            | Self::Parameter { .. }
+            | Self::InSubquery { .. }
+            | Self::Subquery { .. }
+            | Self::Null
+            | Self::Const(_) => false,
+        };
+        if !foldable {
+            return self;
+        }
+        match self.execute::<RowData>(None) {
+            Ok((Some(value), None, None)) => Self::Const(ConstantValue::copy_from(value)),
+            Ok((None, None, None)) => Self::Null,
+            _ => self,
+        }
+    }
+
+    /// The collation of the result which does not depend on rows.
+    ///
+    /// Only columns, COLLATE and CAST carry collations as the result columns
+    /// of compound SELECTs in SQLite.
+    pub fn collation(&self) -> &Collation {
+        self.static_collation()
+            .map_or(&DEFAULT_COLLATION, |(collation, _)| collation)
+    }
+
+    fn static_collation(&self) -> Option<(&Collation, CollateOrigin)> {
+        match self {
+            Self::Column((_, _, collation)) | Self::OuterColumn((_, _, collation)) => {
+                Some((collation, CollateOrigin::Column))
+            }
+            Self::Collate { collation, .. } => Some((collation, CollateOrigin::Expression)),
+            Self::Cast { expr, .. } => expr.static_collation(),
+            _ => None,
+        }
+    }
+
+    /// The type affinity of the result which does not depend on rows.
+    pub fn affinity(&self) -> Option<TypeAffinity> {
+        match self {
+            Self::Column((_, affinity, _)) | Self::OuterColumn((_, affinity, _)) => Some(*affinity),
+            Self::Cast { type_affinity, .. } => Some(*type_affinity),
+            Self::Collate { expr, .. } => expr.affinity(),
+            Self::Subquery { affinity, .. } => *affinity,
+            _ => None,
+        }
+    }
+
+    /// Call the function for each column referred by the expression.
+    ///
+    /// The arguments of aggregate functions and the columns of the outer table
+    /// of a join are not visited.
+    pub fn for_each_column<F: FnMut(&ColumnNumber)>(&self, f: &mut F) {
+        match self {
+            Self::Column((column_number, _, _)) => f(column_number),
+            Self::UnaryOperator { expr, .. }
+            | Self::Collate { expr, .. }
+            | Self::Cast { expr, .. } => expr.for_each_column(f),
+            Self::BinaryOperator { left, right, .. } => {
+                left.for_each_column(f);
+                right.for_each_column(f);
+            }
+            Self::InSubquery { expr, .. } => expr.for_each_column(f),
+            Self::InList { expr, list } => {
+                expr.for_each_column(f);
+                for item in list {
+                    item.for_each_column(f);
+                }
+            }
+            Self::Function { args, .. } | Self::UserFunction { args, .. } => {
+                for arg in args {
+                    arg.for_each_column(f);
+                }
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                if let Some(operand) = operand {
+                    operand.for_each_column(f);
+                }
+                for (when, then) in branches {
+                    when.for_each_column(f);
+                    then.for_each_column(f);
+                }
+                if let Some(else_expr) = else_expr {
+                    else_expr.for_each_column(f);
+                }
+            }
+            Self::Between { expr, lower, upper } => {
+                expr.for_each_column(f);
+                lower.for_each_column(f);
+                upper.for_each_column(f);
+            }
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                expr.for_each_column(f);
+                pattern.for_each_column(f);
+                if let Some(escape) = escape {
+                    escape.for_each_column(f);
+                }
+            }
+            Self::OuterColumn(_)
+            | Self::Aggregate(_)
+            | Self::CurrentTime(_)
+            | Self::Parameter { .. }
+            | Self::Subquery { .. }
+            | Self::Null
+            | Self::Const(_) => {}
         }
     }
 
+    /// Set the value to the parameters of the index in the expression.
+    pub fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        match self {
+            Self::Parameter {
+                idx: parameter_idx,
+                value: parameter_value,
+            } => {
+                if *parameter_idx == idx {
+                    *parameter_value = value.clone();
+                }
+            }
+            Self::UnaryOperator { expr, .. }
+            | Self::Collate { expr, .. }
+            | Self::Cast { expr, .. }
+            | Self::InSubquery { expr, .. } => expr.bind_parameter(idx, value),
+            Self::BinaryOperator { left, right, .. } => {
+                left.bind_parameter(idx, value);
+                right.bind_parameter(idx, value);
+            }
+            Self::InList { expr, list } => {
+                expr.bind_parameter(idx, value);
+                for item in list.iter_mut() {
+                    item.bind_parameter(idx, value);
+                }
+            }
+            Self::Function { args, .. } | Self::UserFunction { args, .. } => {
+                for arg in args.iter_mut() {
+                    arg.bind_parameter(idx, value);
+                }
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                if let Some(operand) = operand {
+                    operand.bind_parameter(idx, value);
+                }
+                for (when, then) in branches.iter_mut() {
+                    when.bind_parameter(idx, value);
+                    then.bind_parameter(idx, value);
+                }
+                if let Some(else_expr) = else_expr {
+                    else_expr.bind_parameter(idx, value);
+                }
+            }
+            Self::Between { expr, lower, upper } => {
+                expr.bind_parameter(idx, value);
+                lower.bind_parameter(idx, value);
+                upper.bind_parameter(idx, value);
+            }
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                expr.bind_parameter(idx, value);
+                pattern.bind_parameter(idx, value);
+                if let Some(escape) = escape {
+                    escape.bind_parameter(idx, value);
+                }
+            }
+            Self::Column(_)
+            | Self::OuterColumn(_)
+            | Self::Aggregate(_)
+            | Self::CurrentTime(_)
+            | Self::Subquery { .. }
+            | Self::Null
+            | Self::Const(_) => {}
+        }
+    }
+
+    /// Set the result rows of the subquery of the index in the expression.
+    ///
+    /// `column` is the result column of the subquery.
+    pub fn bind_subquery(
+        &mut self,
+        idx: usize,
+        column: &Expression,
+        rows: &[Vec<Option<ConstantValue>>],
+    ) -> Result<()> {
+        match self {
+            Self::Subquery {
+                idx: subquery_idx,
+                value,
+                affinity,
+            } => {
+                if *subquery_idx == idx {
+                    if rows.len() > 1 {
+                        return Err(Error::TooManySubqueryRows);
+                    }
+                    // An empty result is NULL.
+                    *value = rows.first().and_then(|row| row[0].clone());
+                    *affinity = column.affinity();
+                }
+            }
+            Self::InSubquery {
+                expr,
+                idx: subquery_idx,
+                values,
+            } => {
+                expr.bind_subquery(idx, column, rows)?;
+                if *subquery_idx == idx {
+                    *values = Some(Rc::new(SubqueryValues::new(expr, column, rows)));
+                }
+            }
+            Self::UnaryOperator { expr, .. }
+            | Self::Collate { expr, .. }
+            | Self::Cast { expr, .. } => expr.bind_subquery(idx, column, rows)?,
+            Self::BinaryOperator { left, right, .. } => {
+                left.bind_subquery(idx, column, rows)?;
+                right.bind_subquery(idx, column, rows)?;
+            }
+            Self::InList { expr, list } => {
+                expr.bind_subquery(idx, column, rows)?;
+                for item in list.iter_mut() {
+                    item.bind_subquery(idx, column, rows)?;
+                }
+            }
+            Self::Function { args, .. } | Self::UserFunction { args, .. } => {
+                for arg in args.iter_mut() {
+                    arg.bind_subquery(idx, column, rows)?;
+                }
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                if let Some(operand) = operand {
+                    operand.bind_subquery(idx, column, rows)?;
+                }
+                for (when, then) in branches.iter_mut() {
+                    when.bind_subquery(idx, column, rows)?;
+                    then.bind_subquery(idx, column, rows)?;
+                }
+                if let Some(else_expr) = else_expr {
+                    else_expr.bind_subquery(idx, column, rows)?;
+                }
+            }
+            Self::Between { expr, lower, upper } => {
+                expr.bind_subquery(idx, column, rows)?;
+                lower.bind_subquery(idx, column, rows)?;
+                upper.bind_subquery(idx, column, rows)?;
+            }
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                expr.bind_subquery(idx, column, rows)?;
+                pattern.bind_subquery(idx, column, rows)?;
+                if let Some(escape) = escape {
+                    escape.bind_subquery(idx, column, rows)?;
+                }
+            }
+            Self::Column(_)
+            | Self::OuterColumn(_)
+            | Self::Aggregate(_)
+            | Self::CurrentTime(_)
+            | Self::Parameter { .. }
+            | Self::Null
+            | Self::Const(_) => {}
+        }
+        Ok(())
+    }
+
     /// Execute the expression and return the result.
     ///
     /// TODO: The row should be a context object.
     pub fn execute<'a, D: DataContext>(&'a self, row: Option<&'a D>) -> ExecutionResult<'a> {
         match self {
-            Self::Column((idx, affinity, collation)) => {
+            Self::Column((idx, affinity, collation))
+            | Self::OuterColumn((idx, affinity, collation)) => {
                 if let Some(row) = row {
+                    let mut value = if let Self::Column(_) = self {
+                        row.get_column_value(idx)
+                    } else {
+                        row.get_outer_column_value(idx)
+                    }
+                    .map_err(Error::FailGetColumn)?;
+                    // SQLite may store a real value as an integer on disk to save space.
+                    if let (TypeAffinity::Real, Some(Value::Integer(i))) = (affinity, &value) {
+                        value = Some(Value::Real(*i as f64));
+                    }
                     Ok((
-                        row.get_column_value(idx).map_err(Error::FailGetColumn)?,
+                        value,
                         Some(*affinity),
                         Some((collation, CollateOrigin::Column)),
                     ))
@@ -193,11 +1243,11 @@ impl Expression {
                 let (value, _, collation) = expr.execute(row)?;
                 let value = match operator {
                     UnaryOp::BitNot => value.map(|v| Value::Integer(!v.as_integer())),
-                    UnaryOp::Minus => value.map(|v| match v {
-                        Value::Integer(i) => Value::Integer(-i),
-                        Value::Real(d) => Value::Real(-d),
-                        Value::Text(_) | Value::Blob(_) => Value::Integer(0),
-                    }),
+                    // -x is evaluated as 0 - x.
+                    UnaryOp::Minus => {
+                        value.and_then(|v| arithmetic(BinaryOp::Sub, Value::Integer(0), v))
+                    }
+                    UnaryOp::Not => value.map(|v| bool_to_value(!v.as_bool())),
                 };
                 Ok((value, None, filter_expression_collation(collation)))
             }
@@ -210,6 +1260,31 @@ impl Expression {
                     Some((collation, CollateOrigin::Expression)),
                 ))
             }
+            Self::BinaryOperator {
+                operator: operator @ (BinaryOp::And | BinaryOp::Or),
+                left,
+                right,
+            } => {
+                let (left_value, _, _) = left.execute(row)?;
+                let left = left_value.map(|v| v.as_bool());
+                // The right operand is not evaluated if the left operand decides the result.
+                let result = match (operator, left) {
+                    (BinaryOp::And, Some(false)) => Some(false),
+                    (BinaryOp::Or, Some(true)) => Some(true),
+                    _ => {
+                        let (right_value, _, _) = right.execute(row)?;
+                        let right = right_value.map(|v| v.as_bool());
+                        match (operator, left, right) {
+                            (BinaryOp::And, _, Some(false)) => Some(false),
+                            (BinaryOp::And, Some(true), Some(true)) => Some(true),
+                            (BinaryOp::Or, _, Some(true)) => Some(true),
+                            (BinaryOp::Or, Some(false), Some(false)) => Some(false),
+                            _ => None,
+                        }
+                    }
+                };
+                Ok((result.map(bool_to_value), None, None))
+            }
             Self::BinaryOperator {
                 operator,
                 left,
@@ -219,69 +1294,67 @@ impl Expression {
                 let (right_value, right_affinity, right_collation) = right.execute(row)?;
 
                 // TODO: Confirm whether collation is preserved after NULL.
-                let (mut left_value, mut right_value) = match (left_value, right_value) {
-                    (None, _) | (_, None) => return Ok((None, None, None)),
+                let (left_value, right_value) = match (left_value, right_value) {
                     (Some(left_value), Some(right_value)) => (left_value, right_value),
-                };
-
-                let collation = match (left_collation, right_collation) {
-                    (None, _) => right_collation,
-                    (Some((_, CollateOrigin::Column)), Some((_, CollateOrigin::Expression))) => {
-                        right_collation
+                    (left_value, right_value) => {
+                        // IS and IS NOT treat NULL as a value equal only to NULL.
+                        let both_null = left_value.is_none() && right_value.is_none();
+                        let value = match operator {
+                            BinaryOp::Compare(CompareOp::Is) => Some(bool_to_value(both_null)),
+                            BinaryOp::Compare(CompareOp::IsNot) => Some(bool_to_value(!both_null)),
+                            _ => None,
+                        };
+                        return Ok((value, None, None));
                     }
-                    _ => left_collation,
                 };
+
+                let collation = merge_collation(left_collation, right_collation);
                 let next_collation = filter_expression_collation(collation);
 
                 match operator {
                     BinaryOp::Compare(compare_op) => {
-                        // Type Conversions Prior To Comparison
-                        match (left_affinity, right_affinity) {
-                            (
-                                Some(TypeAffinity::Integer)
-                                | Some(TypeAffinity::Real)
-                                | Some(TypeAffinity::Numeric),
-                                Some(TypeAffinity::Text) | Some(TypeAffinity::Blob) | None,
-                            ) => {
-                                right_value = right_value.apply_numeric_affinity();
-                            }
-                            (
-                                Some(TypeAffinity::Text) | Some(TypeAffinity::Blob) | None,
-                                Some(TypeAffinity::Integer)
-                                | Some(TypeAffinity::Real)
-                                | Some(TypeAffinity::Numeric),
-                            ) => {
-                                left_value = left_value.apply_numeric_affinity();
-                            }
-                            (Some(TypeAffinity::Text), None) => {
-                                right_value = right_value.apply_text_affinity();
-                            }
-                            (None, Some(TypeAffinity::Text)) => {
-                                left_value = left_value.apply_text_affinity();
-                            }
-                            _ => {}
-                        }
-
-                        let cmp = ValueCmp::new(
-                            &left_value,
-                            collation.map(|(c, _)| c).unwrap_or(&DEFAULT_COLLATION),
-                        )
-                        .compare(&right_value);
+                        let cmp = compare_values(
+                            left_value,
+                            left_affinity,
+                            right_value,
+                            right_affinity,
+                            collation,
+                        );
 
                         let result = match compare_op {
-                            CompareOp::Eq => cmp == Ordering::Equal,
-                            CompareOp::Ne => cmp != Ordering::Equal,
+                            CompareOp::Eq | CompareOp::Is => cmp == Ordering::Equal,
+                            CompareOp::Ne | CompareOp::IsNot => cmp != Ordering::Equal,
                             CompareOp::Lt => cmp == Ordering::Less,
                             CompareOp::Le => cmp != Ordering::Greater,
                             CompareOp::Gt => cmp == Ordering::Greater,
                             CompareOp::Ge => cmp != Ordering::Less,
                         };
-                        if result {
-                            Ok((Some(Value::Integer(1)), None, next_collation))
-                        } else {
-                            Ok((Some(Value::Integer(0)), None, next_collation))
-                        }
+                        Ok((Some(bool_to_value(result)), None, next_collation))
                     }
+                    BinaryOp::And | BinaryOp::Or => {
+                        unreachable!("logical operators are handled above")
+                    }
+                    BinaryOp::BitAnd
+                    | BinaryOp::BitOr
+                    | BinaryOp::ShiftLeft
+                    | BinaryOp::ShiftRight => Ok((
+                        Some(Value::Integer(bitwise(
+                            *operator,
+                            &left_value,
+                            &right_value,
+                        ))),
+                        None,
+                        next_collation,
+                    )),
+                    BinaryOp::Add
+                    | BinaryOp::Sub
+                    | BinaryOp::Mul
+                    | BinaryOp::Div
+                    | BinaryOp::Rem => Ok((
+                        arithmetic(*operator, left_value, right_value),
+                        None,
+                        next_collation,
+                    )),
                     BinaryOp::Concat => {
                         // Both operands are forcibly converted to text before concatination. Both
                         // are not null.
@@ -315,6 +1388,354 @@ impl Expression {
                     collation,
                 ))
             }
+            Self::InList { expr, list } => {
+                // x IN () is always false even if x is NULL.
+                if list.is_empty() {
+                    return Ok((Some(Value::Integer(0)), None, None));
+                }
+                let (value, affinity, collation) = expr.execute(row)?;
+                let Some(value) = value else {
+                    return Ok((None, None, None));
+                };
+                let next_collation = filter_expression_collation(collation);
+                let mut has_null = false;
+                for item in list {
+                    let (item_value, _, item_collation) = item.execute(row)?;
+                    let Some(item_value) = item_value else {
+                        has_null = true;
+                        continue;
+                    };
+                    // The values in the list are considered to have no affinity.
+                    let cmp = compare_values(
+                        value.clone(),
+                        affinity,
+                        item_value,
+                        None,
+                        merge_collation(collation, item_collation),
+                    );
+                    if cmp == Ordering::Equal {
+                        return Ok((Some(Value::Integer(1)), None, next_collation));
+                    }
+                }
+                // If no match is found and the list contains NULL, the result is NULL.
+                if has_null {
+                    Ok((None, None, None))
+                } else {
+                    Ok((Some(Value::Integer(0)), None, next_collation))
+                }
+            }
+            Self::InSubquery { expr, values, .. } => {
+                // x IN (SELECT ...) is always false if the subquery returns no row.
+                let Some(values) = values.as_deref().filter(|values| !values.is_empty()) else {
+                    return Ok((Some(Value::Integer(0)), None, None));
+                };
+                let (value, _, collation) = expr.execute(row)?;
+                let Some(value) = value else {
+                    return Ok((None, None, None));
+                };
+                let next_collation = filter_expression_collation(collation);
+                if values.contains(value) {
+                    Ok((Some(Value::Integer(1)), None, next_collation))
+                } else if values.has_null {
+                    // If no match is found and the result contains NULL, the result is NULL.
+                    Ok((None, None, None))
+                } else {
+                    Ok((Some(Value::Integer(0)), None, next_collation))
+                }
+            }
+            Self::Subquery {
+                value, affinity, ..
+            } => Ok((value.as_ref().map(ConstantValue::as_value), *affinity, None)),
+            Self::Between { expr, lower, upper } => {
+                // x BETWEEN y AND z is equivalent to x >= y AND x <= z.
+                let (value, affinity, collation) = expr.execute(row)?;
+                let (lower_value, lower_affinity, lower_collation) = lower.execute(row)?;
+                let (upper_value, upper_affinity, upper_collation) = upper.execute(row)?;
+                let Some(value) = value else {
+                    return Ok((None, None, None));
+                };
+                let next_collation = filter_expression_collation(collation);
+                let ge_lower = lower_value.map(|lower_value| {
+                    compare_values(
+                        value.clone(),
+                        affinity,
+                        lower_value,
+                        lower_affinity,
+                        merge_collation(collation, lower_collation),
+                    ) != Ordering::Less
+                });
+                let le_upper = upper_value.map(|upper_value| {
+                    compare_values(
+                        value,
+                        affinity,
+                        upper_value,
+                        upper_affinity,
+                        merge_collation(collation, upper_collation),
+                    ) != Ordering::Greater
+                });
+                match (ge_lower, le_upper) {
+                    (Some(false), _) | (_, Some(false)) => {
+                        Ok((Some(Value::Integer(0)), None, next_collation))
+                    }
+                    (None, _) | (_, None) => Ok((None, None, None)),
+                    (Some(true), Some(true)) => Ok((Some(Value::Integer(1)), None, next_collation)),
+                }
+            }
+            Self::Pattern {
+                operator,
+                expr,
+                pattern,
+                escape,
+            } => {
+                let (value, _, _) = expr.execute(row)?;
+                let (pattern_value, _, _) = pattern.execute(row)?;
+                let (Some(value), Some(pattern_value)) = (value, pattern_value) else {
+                    return Ok((None, None, None));
+                };
+                let escape = match escape {
+                    Some(escape) => {
+                        let (Some(escape), _, _) = escape.execute(row)? else {
+                            return Ok((None, None, None));
+                        };
+                        Some(escape_char(&escape.force_text_buffer()).ok_or(Error::InvalidEscape)?)
+                    }
+                    None => None,
+                };
+                let value = value.force_text_buffer();
+                let pattern = pattern_value.force_text_buffer();
+                let matched = match operator {
+                    PatternOp::Like => like(&pattern, &value, escape),
+                    PatternOp::Glob => glob(&pattern, &value),
+                };
+                Ok((Some(bool_to_value(matched)), None, None))
+            }
+            Self::Function { function, args } => match function {
+                ScalarFunction::Abs => {
+                    let (value, _, _) = args[0].execute(row)?;
+                    match value {
+                        Some(value) => Ok((
+                            Some(function::abs(value).ok_or(Error::IntegerOverflow)?),
+                            None,
+                            None,
+                        )),
+                        None => Ok((None, None, None)),
+                    }
+                }
+                ScalarFunction::Min | ScalarFunction::Max => {
+                    // The values are compared without type conversions in the order of
+                    // NULL, numbers, text and blobs. The result is NULL if any argument is
+                    // NULL. The collation is the first one found in the arguments.
+                    let mut values = Vec::with_capacity(args.len());
+                    let mut collation = None;
+                    for arg in args {
+                        let (Some(value), _, arg_collation) = arg.execute(row)? else {
+                            return Ok((None, None, None));
+                        };
+                        collation = collation.or(arg_collation);
+                        values.push(value);
+                    }
+                    let collation = collation.map(|(c, _)| c).unwrap_or(&DEFAULT_COLLATION);
+                    let mut values = values.into_iter();
+                    let mut result = values.next().expect("2 or more arguments");
+                    for value in values {
+                        // Like minmaxFunc() in func.c of SQLite, min() takes the last one of
+                        // the equal values and max() takes the first one.
+                        let ordering = ValueCmp::new(&result, collation).compare(&value);
+                        let replace = match function {
+                            ScalarFunction::Min => ordering.is_ge(),
+                            _ => ordering.is_lt(),
+                        };
+                        if replace {
+                            result = value;
+                        }
+                    }
+                    Ok((Some(result), None, None))
+                }
+                ScalarFunction::Round => {
+                    let n = match args.get(1) {
+                        Some(arg) => {
+                            let (Some(n), _, _) = arg.execute(row)? else {
+                                return Ok((None, None, None));
+                            };
+                            n.as_integer()
+                        }
+                        None => 0,
+                    };
+                    let (value, _, _) = args[0].execute(row)?;
+                    let value =
+                        value.map(|v| match v.force_apply_type_affinity(TypeAffinity::Real) {
+                            Value::Real(d) => Value::Real(function::round(d, n)),
+                            _ => unreachable!("real affinity must produce a real value"),
+                        });
+                    Ok((value, None, None))
+                }
+                ScalarFunction::Coalesce | ScalarFunction::IfNull => {
+                    // Later arguments are not evaluated once a non-NULL value is found.
+                    for arg in args {
+                        let (value, _, _) = arg.execute(row)?;
+                        if value.is_some() {
+                            return Ok((value, None, None));
+                        }
+                    }
+                    Ok((None, None, None))
+                }
+                ScalarFunction::NullIf => {
+                    let (value, _, collation) = args[0].execute(row)?;
+                    let (other, _, other_collation) = args[1].execute(row)?;
+                    let Some(value) = value else {
+                        return Ok((None, None, None));
+                    };
+                    // The values are compared without type conversions. The collation is
+                    // the first one found in the arguments.
+                    let collation = collation
+                        .or(other_collation)
+                        .map(|(c, _)| c)
+                        .unwrap_or(&DEFAULT_COLLATION);
+                    match other {
+                        Some(other) if ValueCmp::new(&value, collation).compare(&other).is_eq() => {
+                            Ok((None, None, None))
+                        }
+                        _ => Ok((Some(value), None, None)),
+                    }
+                }
+                ScalarFunction::TypeOf => {
+                    let (value, _, _) = args[0].execute(row)?;
+                    Ok((
+                        Some(Value::Text(function::type_of(value.as_ref()).into())),
+                        None,
+                        None,
+                    ))
+                }
+                ScalarFunction::Quote => {
+                    let (value, _, _) = args[0].execute(row)?;
+                    Ok((Some(Value::Text(function::quote(value).into())), None, None))
+                }
+                ScalarFunction::Hex => {
+                    let (value, _, _) = args[0].execute(row)?;
+                    let buf = value.map(|v| v.force_text_buffer());
+                    let hex = function::hex(buf.as_deref().unwrap_or_default());
+                    Ok((Some(Value::Text(hex.into())), None, None))
+                }
+                ScalarFunction::Unhex => {
+                    let (Some(value), _, _) = args[0].execute(row)? else {
+                        return Ok((None, None, None));
+                    };
+                    let separators = match args.get(1) {
+                        Some(arg) => {
+                            let (Some(separators), _, _) = arg.execute(row)? else {
+                                return Ok((None, None, None));
+                            };
+                            Some(separators.force_text_buffer())
+                        }
+                        None => None,
+                    };
+                    let blob = function::unhex(
+                        &value.force_text_buffer(),
+                        separators.as_deref().unwrap_or_default(),
+                    );
+                    Ok((blob.map(|blob| Value::Blob(blob.into())), None, None))
+                }
+                ScalarFunction::Lower | ScalarFunction::Upper => {
+                    // Only ASCII characters are converted as SQLite without ICU does.
+                    let (value, _, _) = args[0].execute(row)?;
+                    let value = value.map(|v| {
+                        let mut text = v.force_text_buffer().into_vec();
+                        if matches!(function, ScalarFunction::Lower) {
+                            text.make_ascii_lowercase();
+                        } else {
+                            text.make_ascii_uppercase();
+                        }
+                        Value::Text(text.into())
+                    });
+                    Ok((value, None, None))
+                }
+                ScalarFunction::Char => {
+                    // NULL is converted to 0.
+                    let mut code_points = Vec::with_capacity(args.len());
+                    for arg in args {
+                        let (value, _, _) = arg.execute(row)?;
+                        code_points.push(value.map_or(0, |v| v.as_integer()));
+                    }
+                    Ok((
+                        Some(Value::Text(
+                            function::char_from_code_points(code_points.into_iter()).into(),
+                        )),
+                        None,
+                        None,
+                    ))
+                }
+            },
+            Self::UserFunction { function, args } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    let (value, _, _) = arg.execute(row)?;
+                    values.push(value);
+                }
+                let value = function.call(&values).map_err(Error::FailUserFunction)?;
+                Ok((value, None, None))
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_expr,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some(operand.execute(row)?),
+                    None => None,
+                };
+                // Only the THEN expression of the first matching branch is evaluated.
+                for (when, then) in branches {
+                    let (when_value, when_affinity, when_collation) = when.execute(row)?;
+                    let matched = match (&operand, when_value) {
+                        // CASE x WHEN y is equivalent to x = y. NULL never matches.
+                        (Some((Some(value), affinity, collation)), Some(when_value)) => {
+                            compare_values(
+                                value.clone(),
+                                *affinity,
+                                when_value,
+                                when_affinity,
+                                merge_collation(*collation, when_collation),
+                            ) == Ordering::Equal
+                        }
+                        (Some(_), _) => false,
+                        (None, when_value) => when_value.map(|v| v.as_bool()).unwrap_or(false),
+                    };
+                    if matched {
+                        let (value, _, _) = then.execute(row)?;
+                        return Ok((value, None, None));
+                    }
+                }
+                match else_expr {
+                    Some(else_expr) => {
+                        let (value, _, _) = else_expr.execute(row)?;
+                        Ok((value, None, None))
+                    }
+                    None => Ok((None, None, None)),
+                }
+            }
+            Self::Aggregate(idx) => {
+                if let Some(row) = row {
+                    Ok((
+                        row.get_aggregate_value(*idx)
+                            .map_err(Error::FailGetAggregate)?,
+                        None,
+                        None,
+                    ))
+                } else {
+                    Err(Error::NoTableContext)
+                }
+            }
+            Self::Parameter { value, .. } => Ok((value.as_ref().map(|v| v.as_value()), None, None)),
+            Self::CurrentTime(kind) => {
+                let unix_time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs() as i64);
+                Ok((
+                    Some(Value::Text(function::current_time(*kind, unix_time).into())),
+                    None,
+                    None,
+                ))
+            }
             Self::Null => Ok((None, None, None)),
             Self::Const(value) => Ok((Some(value.as_value()), None, None)),
         }
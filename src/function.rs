@@ -0,0 +1,562 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::parser::CurrentTime;
+use crate::pattern::read_utf8;
+use crate::utils::hex_to_int;
+use crate::utils::upper_to_lower;
+use crate::value::TypeAffinity;
+use crate::value::Value;
+
+/// Built-in scalar functions.
+///
+/// https://www.sqlite.org/lang_corefunc.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarFunction {
+    Abs,
+    /// min(X, Y, ...) with 2 or more arguments. min(X) is the aggregate
+    /// function.
+    Min,
+    /// max(X, Y, ...) with 2 or more arguments. max(X) is the aggregate
+    /// function.
+    Max,
+    Round,
+    Coalesce,
+    IfNull,
+    NullIf,
+    TypeOf,
+    Quote,
+    Hex,
+    Unhex,
+    Char,
+    Lower,
+    Upper,
+}
+
+impl ScalarFunction {
+    /// Look up the scalar function by the lowercased name and the number of
+    /// arguments.
+    pub fn lookup(name: &[u8], n_args: usize) -> Option<Self> {
+        match (name, n_args) {
+            (b"abs", 1) => Some(Self::Abs),
+            (b"min", 2..) => Some(Self::Min),
+            (b"max", 2..) => Some(Self::Max),
+            (b"round", 1 | 2) => Some(Self::Round),
+            (b"coalesce", 2..) => Some(Self::Coalesce),
+            (b"ifnull", 2) => Some(Self::IfNull),
+            (b"nullif", 2) => Some(Self::NullIf),
+            (b"typeof", 1) => Some(Self::TypeOf),
+            (b"quote", 1) => Some(Self::Quote),
+            (b"hex", 1) => Some(Self::Hex),
+            (b"unhex", 1 | 2) => Some(Self::Unhex),
+            (b"char", _) => Some(Self::Char),
+            (b"lower", 1) => Some(Self::Lower),
+            (b"upper", 1) => Some(Self::Upper),
+            _ => None,
+        }
+    }
+}
+
+type UserFunctionImpl = dyn Fn(&[Option<Value>]) -> anyhow::Result<Option<Value<'static>>>;
+
+/// A scalar function registered by [crate::Connection::create_function()].
+pub struct UserFunction {
+    name: String,
+    func: Box<UserFunctionImpl>,
+}
+
+impl UserFunction {
+    /// Call the function. NULL arguments and the NULL result are [None].
+    pub fn call(&self, args: &[Option<Value>]) -> anyhow::Result<Option<Value<'static>>> {
+        (self.func)(args)
+    }
+}
+
+impl Debug for UserFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserFunction")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// The registry of user defined scalar functions keyed by the lowercased
+/// name and the number of arguments.
+#[derive(Default)]
+pub struct UserFunctions(HashMap<(Vec<u8>, usize), Rc<UserFunction>>);
+
+impl UserFunctions {
+    /// Register the function. The function with the same name and the same
+    /// number of arguments is replaced.
+    pub fn insert(&mut self, name: &str, n_args: usize, func: Box<UserFunctionImpl>) {
+        let mut key = name.as_bytes().to_vec();
+        upper_to_lower(&mut key);
+        self.0.insert(
+            (key, n_args),
+            Rc::new(UserFunction {
+                name: name.to_string(),
+                func,
+            }),
+        );
+    }
+
+    /// Look up the function by the lowercased name and the number of
+    /// arguments.
+    pub fn lookup(&self, name: &[u8], n_args: usize) -> Option<&Rc<UserFunction>> {
+        // TODO: Look up without copying the name.
+        self.0.get(&(name.to_vec(), n_args))
+    }
+}
+
+/// abs(X). Returns [None] if X is the minimum integer.
+///
+/// Text and blob values are converted to a real value.
+pub fn abs(value: Value) -> Option<Value<'static>> {
+    match value {
+        Value::Integer(i) => i.checked_abs().map(Value::Integer),
+        Value::Real(d) => Some(Value::Real(d.abs())),
+        Value::Text(_) | Value::Blob(_) => {
+            match value.force_apply_type_affinity(TypeAffinity::Real) {
+                Value::Real(d) => Some(Value::Real(d.abs())),
+                _ => unreachable!("real affinity must produce a real value"),
+            }
+        }
+    }
+}
+
+/// typeof(X). Returns the storage class of the value.
+pub fn type_of(value: Option<&Value>) -> &'static [u8] {
+    match value {
+        None => b"null",
+        Some(Value::Integer(_)) => b"integer",
+        Some(Value::Real(_)) => b"real",
+        Some(Value::Text(_)) => b"text",
+        Some(Value::Blob(_)) => b"blob",
+    }
+}
+
+/// quote(X). Returns the SQL literal of the value.
+///
+/// Text is truncated at the first NUL character as SQLite does. The real
+/// value is formatted as [Value::force_text_buffer()] does, which keeps the
+/// decimal point so that the literal is read back as a real value.
+pub fn quote(value: Option<Value>) -> Vec<u8> {
+    match value {
+        None => b"NULL".to_vec(),
+        Some(Value::Integer(i)) => i.to_string().into_bytes(),
+        Some(Value::Real(d)) => Value::Real(d).force_text_buffer().into_vec(),
+        Some(Value::Text(text)) => {
+            let text = match text.iter().position(|&b| b == 0) {
+                Some(i) => &text[..i],
+                None => &text,
+            };
+            let mut buf = Vec::with_capacity(text.len() + 2);
+            buf.push(b'\'');
+            for &b in text {
+                if b == b'\'' {
+                    buf.push(b'\'');
+                }
+                buf.push(b);
+            }
+            buf.push(b'\'');
+            buf
+        }
+        Some(Value::Blob(blob)) => {
+            let mut buf = Vec::with_capacity(blob.len() * 2 + 3);
+            buf.extend_from_slice(b"X'");
+            buf.extend(hex(&blob));
+            buf.push(b'\'');
+            buf
+        }
+    }
+}
+
+/// hex(X). Returns the uppercase hexadecimal digits of the bytes.
+pub fn hex(buf: &[u8]) -> Vec<u8> {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    let mut hex = Vec::with_capacity(buf.len() * 2);
+    for &b in buf {
+        hex.push(DIGITS[(b >> 4) as usize]);
+        hex.push(DIGITS[(b & 0x0f) as usize]);
+    }
+    hex
+}
+
+/// unhex(X, Y). Returns [None] if X is not pairs of hexadecimal digits
+/// separated by the characters in Y.
+///
+/// X ends at the first NUL character.
+///
+/// This comes from unhexFunc() in func.c of SQLite.
+pub fn unhex(hex: &[u8], separators: &[u8]) -> Option<Vec<u8>> {
+    let mut blob = Vec::with_capacity(hex.len() / 2);
+    let mut cursor = 0;
+    while let Some(&c) = hex.get(cursor) {
+        if c == 0 {
+            break;
+        }
+        if !c.is_ascii_hexdigit() {
+            let c = read_utf8(hex, &mut cursor);
+            let mut separator_cursor = 0;
+            loop {
+                if separator_cursor >= separators.len() {
+                    return None;
+                } else if read_utf8(separators, &mut separator_cursor) == c {
+                    break;
+                }
+            }
+            continue;
+        }
+        // The separators are not allowed between the digits of a byte.
+        let d = *hex.get(cursor + 1)?;
+        if !d.is_ascii_hexdigit() {
+            return None;
+        }
+        blob.push((hex_to_int(c) << 4) | hex_to_int(d));
+        cursor += 2;
+    }
+    Some(blob)
+}
+
+/// char(X1, X2, ..., XN). Returns the UTF-8 text of the code points.
+///
+/// Code points out of the unicode range are replaced with U+FFFD. Surrogates
+/// are encoded as they are.
+pub fn char_from_code_points(code_points: impl Iterator<Item = i64>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for c in code_points {
+        let c = if (0..=0x10ffff).contains(&c) {
+            c as u32
+        } else {
+            0xfffd
+        };
+        if c < 0x80 {
+            buf.push(c as u8);
+        } else if c < 0x800 {
+            buf.push(0xc0 | (c >> 6) as u8);
+            buf.push(0x80 | (c & 0x3f) as u8);
+        } else if c < 0x10000 {
+            buf.push(0xe0 | (c >> 12) as u8);
+            buf.push(0x80 | ((c >> 6) & 0x3f) as u8);
+            buf.push(0x80 | (c & 0x3f) as u8);
+        } else {
+            buf.push(0xf0 | (c >> 18) as u8);
+            buf.push(0x80 | ((c >> 12) & 0x3f) as u8);
+            buf.push(0x80 | ((c >> 6) & 0x3f) as u8);
+            buf.push(0x80 | (c & 0x3f) as u8);
+        }
+    }
+    buf
+}
+
+/// round(X, Y). Y is clamped between 0 and 30.
+///
+/// Halfway cases are rounded away from zero, using the 16 significant digits
+/// of X as SQLite does. A result rounded to zero is positive zero.
+///
+/// This comes from roundFunc() in func.c of SQLite.
+pub fn round(d: f64, n: i64) -> f64 {
+    let n = n.clamp(0, 30);
+    if d == 0.0 {
+        return 0.0;
+    }
+    if n == 0 && d.abs() < (i64::MAX - 1) as f64 {
+        let rounded = (d.abs() + 0.5) as i64 as f64;
+        return if d < 0.0 && rounded != 0.0 {
+            -rounded
+        } else {
+            rounded
+        };
+    }
+    // "d.ddddddddddddddde<exp>"
+    let text = format!("{:.15e}", d.abs());
+    let (mantissa, exp) = text.split_once('e').unwrap();
+    let exp: i64 = exp.parse().unwrap();
+    let mut digits: Vec<u8> = mantissa.bytes().filter(u8::is_ascii_digit).collect();
+    // The number of digits before the decimal point.
+    let mut n_integer_digits = exp + 1;
+    let n_keep = n_integer_digits + n;
+    if n_keep >= digits.len() as i64 {
+        return d;
+    } else if n_keep < 0 {
+        return 0.0;
+    }
+    let n_keep = n_keep as usize;
+    let round_up = digits[n_keep] >= b'5';
+    digits.truncate(n_keep);
+    if round_up {
+        let mut carry = true;
+        for digit in digits.iter_mut().rev() {
+            if *digit == b'9' {
+                *digit = b'0';
+            } else {
+                *digit += 1;
+                carry = false;
+                break;
+            }
+        }
+        if carry {
+            digits.insert(0, b'1');
+            n_integer_digits += 1;
+        }
+    }
+    if digits.is_empty() {
+        return 0.0;
+    }
+    let text = format!(
+        "0.{}e{}",
+        std::str::from_utf8(&digits).unwrap(),
+        n_integer_digits
+    );
+    let rounded: f64 = text.parse().unwrap();
+    rounded.copysign(d)
+}
+
+/// CURRENT_TIME, CURRENT_DATE and CURRENT_TIMESTAMP in UTC.
+///
+/// `unix_time` is the number of seconds since the Unix epoch.
+pub fn current_time(kind: CurrentTime, unix_time: i64) -> Vec<u8> {
+    let (year, month, day) = civil_from_days(unix_time.div_euclid(86400));
+    let secs = unix_time.rem_euclid(86400);
+    let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let text = match kind {
+        CurrentTime::Time => format!("{:02}:{:02}:{:02}", hour, minute, second),
+        CurrentTime::Date => format!("{:04}-{:02}-{:02}", year, month, day),
+        CurrentTime::Timestamp => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        ),
+    };
+    text.into_bytes()
+}
+
+/// Convert the number of days since the Unix epoch to (year, month, day).
+///
+/// This comes from civil_from_days() in
+/// http://howardhinnant.github.io/date_algorithms.html.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(ScalarFunction::lookup(b"abs", 1), Some(ScalarFunction::Abs));
+        assert_eq!(ScalarFunction::lookup(b"abs", 2), None);
+        assert_eq!(ScalarFunction::lookup(b"min", 1), None);
+        assert_eq!(ScalarFunction::lookup(b"min", 2), Some(ScalarFunction::Min));
+        assert_eq!(ScalarFunction::lookup(b"max", 3), Some(ScalarFunction::Max));
+        assert_eq!(
+            ScalarFunction::lookup(b"round", 1),
+            Some(ScalarFunction::Round)
+        );
+        assert_eq!(
+            ScalarFunction::lookup(b"round", 2),
+            Some(ScalarFunction::Round)
+        );
+        assert_eq!(ScalarFunction::lookup(b"coalesce", 1), None);
+        assert_eq!(
+            ScalarFunction::lookup(b"coalesce", 5),
+            Some(ScalarFunction::Coalesce)
+        );
+        assert_eq!(ScalarFunction::lookup(b"ifnull", 3), None);
+        assert_eq!(
+            ScalarFunction::lookup(b"nullif", 2),
+            Some(ScalarFunction::NullIf)
+        );
+        assert_eq!(
+            ScalarFunction::lookup(b"typeof", 1),
+            Some(ScalarFunction::TypeOf)
+        );
+        assert_eq!(ScalarFunction::lookup(b"typeof", 0), None);
+        assert_eq!(
+            ScalarFunction::lookup(b"quote", 1),
+            Some(ScalarFunction::Quote)
+        );
+        assert_eq!(ScalarFunction::lookup(b"hex", 2), None);
+        assert_eq!(
+            ScalarFunction::lookup(b"unhex", 2),
+            Some(ScalarFunction::Unhex)
+        );
+        assert_eq!(ScalarFunction::lookup(b"unhex", 3), None);
+        assert_eq!(
+            ScalarFunction::lookup(b"char", 0),
+            Some(ScalarFunction::Char)
+        );
+        assert_eq!(
+            ScalarFunction::lookup(b"lower", 1),
+            Some(ScalarFunction::Lower)
+        );
+        assert_eq!(
+            ScalarFunction::lookup(b"upper", 1),
+            Some(ScalarFunction::Upper)
+        );
+        assert_eq!(ScalarFunction::lookup(b"upper", 2), None);
+        assert_eq!(ScalarFunction::lookup(b"count", 1), None);
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(abs(Value::Integer(-3)), Some(Value::Integer(3)));
+        assert_eq!(
+            abs(Value::Integer(i64::MAX)),
+            Some(Value::Integer(i64::MAX))
+        );
+        assert_eq!(abs(Value::Integer(i64::MIN)), None);
+        assert_eq!(abs(Value::Real(-1.5)), Some(Value::Real(1.5)));
+        assert_eq!(
+            abs(Value::Text(b"-12".as_slice().into())),
+            Some(Value::Real(12.0))
+        );
+        assert_eq!(
+            abs(Value::Blob(b"abc".as_slice().into())),
+            Some(Value::Real(0.0))
+        );
+    }
+
+    #[test]
+    fn test_type_of() {
+        assert_eq!(type_of(None), b"null");
+        assert_eq!(type_of(Some(&Value::Integer(1))), b"integer");
+        assert_eq!(type_of(Some(&Value::Real(1.0))), b"real");
+        assert_eq!(type_of(Some(&Value::Text(b"1".as_slice().into()))), b"text");
+        assert_eq!(type_of(Some(&Value::Blob(b"1".as_slice().into()))), b"blob");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(None), b"NULL");
+        assert_eq!(quote(Some(Value::Integer(-12))), b"-12");
+        assert_eq!(quote(Some(Value::Real(1.5))), b"1.5");
+        assert_eq!(quote(Some(Value::Real(-2.0))), b"-2.0");
+        assert_eq!(quote(Some(Value::Real(1e20))), b"1.0e+20");
+        assert_eq!(
+            quote(Some(Value::Text(b"it's".as_slice().into()))),
+            b"'it''s'"
+        );
+        assert_eq!(quote(Some(Value::Text(b"a\0b".as_slice().into()))), b"'a'");
+        assert_eq!(
+            quote(Some(Value::Blob(b"'a\0'".as_slice().into()))),
+            b"X'27610027'"
+        );
+        assert_eq!(quote(Some(Value::Blob(b"".as_slice().into()))), b"X''");
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(b""), b"");
+        assert_eq!(hex(b"\x00\x01\x7f\x80\xab\xff"), b"00017F80ABFF");
+        assert_eq!(hex("é".as_bytes()), b"C3A9");
+    }
+
+    #[test]
+    fn test_unhex() {
+        assert_eq!(unhex(b"", b""), Some(Vec::new()));
+        assert_eq!(
+            unhex(b"00017f80aBFF", b""),
+            Some(b"\x00\x01\x7f\x80\xab\xff".to_vec())
+        );
+        assert_eq!(unhex(b"414", b""), None);
+        assert_eq!(unhex(b"41 42", b""), None);
+        assert_eq!(unhex(b"41 42", b" "), Some(b"AB".to_vec()));
+        assert_eq!(unhex(b" 41-42 ", b"- "), Some(b"AB".to_vec()));
+        assert_eq!(unhex(b"4 1", b" "), None);
+        assert_eq!(
+            unhex("41é42".as_bytes(), "é".as_bytes()),
+            Some(b"AB".to_vec())
+        );
+        assert_eq!(unhex("41è42".as_bytes(), "é".as_bytes()), None);
+        assert_eq!(unhex(b"41\x0042", b""), Some(b"A".to_vec()));
+        assert_eq!(unhex(b"4\x0042", b""), None);
+        assert_eq!(unhex(b"4g", b"g"), None);
+    }
+
+    #[test]
+    fn test_char_from_code_points() {
+        assert_eq!(char_from_code_points([].into_iter()), b"");
+        assert_eq!(char_from_code_points([65, 0, 66].into_iter()), b"A\0B");
+        assert_eq!(
+            char_from_code_points(
+                [0x7f, 0x80, 0x7ff, 0x800, 0xffff, 0x10000, 0x10ffff].into_iter()
+            ),
+            "\u{7f}\u{80}\u{7ff}\u{800}\u{ffff}\u{10000}\u{10ffff}".as_bytes()
+        );
+        assert_eq!(
+            char_from_code_points([-1, 0x110000].into_iter()),
+            "\u{fffd}\u{fffd}".as_bytes()
+        );
+        assert_eq!(char_from_code_points([0xd800].into_iter()), b"\xed\xa0\x80");
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(round(2.5, 0), 3.0);
+        assert_eq!(round(-2.5, 0), -3.0);
+        assert_eq!(round(3.5, 0), 4.0);
+        assert_eq!(round(2.4, 0), 2.0);
+        assert_eq!(round(0.125, 2), 0.13);
+        assert_eq!(round(-0.125, 2), -0.13);
+        assert_eq!(round(1.005, 2), 1.01);
+        assert_eq!(round(9.96, 1), 10.0);
+        assert_eq!(round(99.5, 0), 100.0);
+        assert_eq!(round(0.0004, 2), 0.0);
+        assert_eq!(round(1.23456, 3), 1.235);
+        assert_eq!(round(123.456, -1), 123.0);
+        assert_eq!(round(1.25, 100), 1.25);
+        assert_eq!(round(1e300, 2), 1e300);
+        assert_eq!(round(1e-300, 2), 0.0);
+        // Negative values rounded to zero are positive zero.
+        for (d, n) in [(-0.4, 0), (-0.004, 2), (-1e-300, 2), (-0.0, 0), (-0.0, 20)] {
+            let rounded = round(d, n);
+            assert_eq!(rounded, 0.0);
+            assert!(rounded.is_sign_positive(), "round({}, {})", d, n);
+        }
+    }
+
+    #[test]
+    fn test_current_time() {
+        assert_eq!(current_time(CurrentTime::Time, 0), b"00:00:00");
+        assert_eq!(current_time(CurrentTime::Date, 0), b"1970-01-01");
+        assert_eq!(
+            current_time(CurrentTime::Timestamp, 1_700_000_000),
+            b"2023-11-14 22:13:20"
+        );
+        // Leap day.
+        assert_eq!(
+            current_time(CurrentTime::Timestamp, 951_825_599),
+            b"2000-02-29 11:59:59"
+        );
+        assert_eq!(
+            current_time(CurrentTime::Timestamp, -1),
+            b"1969-12-31 23:59:59"
+        );
+    }
+}
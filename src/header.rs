@@ -17,6 +17,17 @@ use crate::pager::MAX_PAGE_SIZE;
 
 const MAGIC_HEADER: &[u8; 16] = b"SQLite format 3\0";
 pub const DATABASE_HEADER_SIZE: usize = 100;
+/// The SQLite version written to new databases.
+const SQLITE_VERSION_NUMBER: u32 = 3041002;
+
+/// The text encoding of the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
 pub struct DatabaseHeader<'a>(&'a [u8; DATABASE_HEADER_SIZE]);
 
 impl<'a> DatabaseHeader<'a> {
@@ -34,7 +45,7 @@ impl<'a> DatabaseHeader<'a> {
         Ok(())
     }
 
-    fn validate_magic_header(&self) -> bool {
+    pub fn validate_magic_header(&self) -> bool {
         let magic_header: &[u8; 16] = self.0[0..16].try_into().unwrap();
         magic_header == MAGIC_HEADER
     }
@@ -53,10 +64,23 @@ impl<'a> DatabaseHeader<'a> {
         self.0[20]
     }
 
+    /// The file change counter which is incremented on each commit.
+    pub fn change_counter(&self) -> u32 {
+        u32::from_be_bytes(self.0[24..28].try_into().unwrap())
+    }
+
     pub fn n_pages(&self) -> u32 {
         u32::from_be_bytes(self.0[28..32].try_into().unwrap())
     }
 
+    /// Whether the database size in the header is valid.
+    ///
+    /// Legacy writers do not update the database size. The size is valid only
+    /// if the version-valid-for number matches the change counter.
+    pub fn is_n_pages_valid(&self) -> bool {
+        self.n_pages() != 0 && self.0[24..28] == self.0[92..96]
+    }
+
     pub fn first_freelist_trunk_page_id(&self) -> Option<PageId> {
         PageId::new(u32::from_be_bytes(self.0[32..36].try_into().unwrap()))
     }
@@ -64,6 +88,37 @@ impl<'a> DatabaseHeader<'a> {
     pub fn n_freelist_pages(&self) -> u32 {
         u32::from_be_bytes(self.0[36..40].try_into().unwrap())
     }
+
+    /// The schema cookie which is incremented on each schema change.
+    pub fn schema_cookie(&self) -> u32 {
+        u32::from_be_bytes(self.0[40..44].try_into().unwrap())
+    }
+
+    /// The user version which SQLite does not use.
+    pub fn user_version(&self) -> i32 {
+        i32::from_be_bytes(self.0[60..64].try_into().unwrap())
+    }
+
+    /// The text encoding. [None] if the value is unknown.
+    pub fn text_encoding(&self) -> Option<TextEncoding> {
+        match u32::from_be_bytes(self.0[56..60].try_into().unwrap()) {
+            1 => Some(TextEncoding::Utf8),
+            2 => Some(TextEncoding::Utf16le),
+            3 => Some(TextEncoding::Utf16be),
+            _ => None,
+        }
+    }
+
+    /// The application ID set by `PRAGMA application_id`.
+    pub fn application_id(&self) -> i32 {
+        i32::from_be_bytes(self.0[68..72].try_into().unwrap())
+    }
+
+    /// The version number of the SQLite library which most recently modified
+    /// the database.
+    pub fn sqlite_version_number(&self) -> u32 {
+        u32::from_be_bytes(self.0[96..100].try_into().unwrap())
+    }
 }
 
 pub struct DatabaseHeaderMut<'a>(&'a mut [u8; DATABASE_HEADER_SIZE]);
@@ -73,6 +128,43 @@ impl<'a> DatabaseHeaderMut<'a> {
         Self(buf)
     }
 
+    /// Initialize the header of a new database which has only page 1.
+    pub fn initialize(&mut self, pagesize: u32) {
+        self.0.fill(0);
+        self.0[0..16].copy_from_slice(MAGIC_HEADER);
+        // 65536 is stored as 1.
+        let pagesize = (pagesize as u16) | (pagesize >> 16) as u16;
+        self.0[16..18].copy_from_slice(&pagesize.to_be_bytes());
+        // File format write/read version: legacy.
+        self.0[18] = 1;
+        self.0[19] = 1;
+        // Maximum/minimum embedded payload fraction and leaf payload fraction.
+        self.0[21] = 64;
+        self.0[22] = 32;
+        self.0[23] = 32;
+        // File change counter.
+        self.0[24..28].copy_from_slice(&1_u32.to_be_bytes());
+        self.set_n_pages(1);
+        // Schema format number.
+        self.0[44..48].copy_from_slice(&4_u32.to_be_bytes());
+        // Text encoding: UTF-8.
+        self.0[56..60].copy_from_slice(&1_u32.to_be_bytes());
+        // Version-valid-for number matching the file change counter.
+        self.0[92..96].copy_from_slice(&1_u32.to_be_bytes());
+        self.0[96..100].copy_from_slice(&SQLITE_VERSION_NUMBER.to_be_bytes());
+    }
+
+    /// Increment the file change counter on commit.
+    ///
+    /// The version-valid-for number and the SQLite version number are updated
+    /// together so that other SQLite clients trust the database size.
+    pub fn increment_change_counter(&mut self) {
+        let counter = u32::from_be_bytes(self.0[24..28].try_into().unwrap()).wrapping_add(1);
+        self.0[24..28].copy_from_slice(&counter.to_be_bytes());
+        self.0[92..96].copy_from_slice(&counter.to_be_bytes());
+        self.0[96..100].copy_from_slice(&SQLITE_VERSION_NUMBER.to_be_bytes());
+    }
+
     pub fn set_n_pages(&mut self, n_pages: u32) {
         self.0[28..32].copy_from_slice(&n_pages.to_be_bytes());
     }
@@ -89,6 +181,14 @@ impl<'a> DatabaseHeaderMut<'a> {
     pub fn set_n_freelist_pages(&mut self, pages: u32) {
         self.0[36..40].copy_from_slice(&pages.to_be_bytes());
     }
+
+    pub fn set_schema_cookie(&mut self, cookie: u32) {
+        self.0[40..44].copy_from_slice(&cookie.to_be_bytes());
+    }
+
+    pub fn set_user_version(&mut self, user_version: i32) {
+        self.0[60..64].copy_from_slice(&user_version.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -146,6 +246,94 @@ mod tests {
         assert_eq!(header.n_pages(), 3);
     }
 
+    #[test]
+    fn change_counter() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(col) VALUES (1);",
+        ]);
+        let mut buf = fs::read(file.path()).unwrap();
+
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        let counter = header.change_counter();
+        assert!(counter > 0);
+        assert!(header.is_n_pages_valid());
+
+        let mut header =
+            DatabaseHeaderMut::from((&mut buf[0..DATABASE_HEADER_SIZE]).try_into().unwrap());
+        header.increment_change_counter();
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.change_counter(), counter + 1);
+        assert!(header.is_n_pages_valid());
+        assert_eq!(header.sqlite_version_number(), SQLITE_VERSION_NUMBER);
+
+        // The version-valid-for number does not match.
+        buf[92..96].copy_from_slice(&counter.to_be_bytes());
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert!(!header.is_n_pages_valid());
+        // The database size is missing.
+        buf[92..96].copy_from_slice(&(counter + 1).to_be_bytes());
+        buf[28..32].copy_from_slice(&0_u32.to_be_bytes());
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert!(!header.is_n_pages_valid());
+    }
+
+    #[test]
+    fn schema_cookie() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "CREATE INDEX index1 ON example(col);",
+        ]);
+        let mut buf = fs::read(file.path()).unwrap();
+
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.schema_cookie(), 2);
+
+        let mut header =
+            DatabaseHeaderMut::from((&mut buf[0..DATABASE_HEADER_SIZE]).try_into().unwrap());
+        header.set_schema_cookie(3);
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.schema_cookie(), 3);
+    }
+
+    #[test]
+    fn user_version() {
+        let file = create_sqlite_database(&["PRAGMA user_version = -2;"]);
+        let mut buf = fs::read(file.path()).unwrap();
+
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.user_version(), -2);
+
+        let mut header =
+            DatabaseHeaderMut::from((&mut buf[0..DATABASE_HEADER_SIZE]).try_into().unwrap());
+        header.set_user_version(5);
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.user_version(), 5);
+    }
+
+    #[test]
+    fn application_id_and_text_encoding() {
+        let file =
+            create_sqlite_database(&["PRAGMA application_id = -3;", "CREATE TABLE example(col);"]);
+        let buf = fs::read(file.path()).unwrap();
+
+        let header = DatabaseHeader::from(buf[0..DATABASE_HEADER_SIZE].try_into().unwrap());
+        assert_eq!(header.application_id(), -3);
+        assert_eq!(header.text_encoding(), Some(TextEncoding::Utf8));
+        assert!(header.sqlite_version_number() >= 3000000);
+
+        let mut buf = [0_u8; DATABASE_HEADER_SIZE];
+        for (value, expected) in [
+            (2, Some(TextEncoding::Utf16le)),
+            (3, Some(TextEncoding::Utf16be)),
+            (0, None),
+            (4, None),
+        ] {
+            buf[56..60].copy_from_slice(&(value as u32).to_be_bytes());
+            assert_eq!(DatabaseHeader::from(&buf).text_encoding(), expected);
+        }
+    }
+
     #[test]
     fn validate_database_header() {
         let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
@@ -157,4 +345,38 @@ mod tests {
         assert_eq!(header.pagesize(), 4096);
         assert!(header.validate_pagesize());
     }
+
+    #[test]
+    fn initialize() {
+        for pagesize in [512, 4096, 65536] {
+            let mut buf = vec![0_u8; pagesize as usize];
+            let mut header =
+                DatabaseHeaderMut::from((&mut buf[..DATABASE_HEADER_SIZE]).try_into().unwrap());
+            header.initialize(pagesize);
+            // The empty table leaf page of sqlite_schema.
+            buf[DATABASE_HEADER_SIZE] = 0x0D;
+            buf[DATABASE_HEADER_SIZE + 5..DATABASE_HEADER_SIZE + 7]
+                .copy_from_slice(&(pagesize as u16).to_be_bytes());
+
+            let header = DatabaseHeader::from(buf[..DATABASE_HEADER_SIZE].try_into().unwrap());
+            assert!(header.validate().is_ok());
+            assert_eq!(header.pagesize(), pagesize);
+            assert_eq!(header.reserved(), 0);
+            assert_eq!(header.n_pages(), 1);
+            assert_eq!(header.first_freelist_trunk_page_id(), None);
+            assert_eq!(header.n_freelist_pages(), 0);
+
+            let file = tempfile::NamedTempFile::new().unwrap();
+            fs::write(file.path(), &buf).unwrap();
+            let conn = rusqlite::Connection::open(file.path()).unwrap();
+            let result: String = conn
+                .query_row("PRAGMA integrity_check;", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(result, "ok");
+            let n_tables: i64 = conn
+                .query_row("SELECT count(*) FROM sqlite_schema;", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(n_tables, 0);
+        }
+    }
 }
@@ -0,0 +1,461 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rollback journal.
+//!
+//! The format is compatible with the rollback journal of SQLite.
+//! https://www.sqlite.org/fileformat2.html#the_rollback_journal
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::pager::PageId;
+use crate::pager::Storage;
+use crate::pager::MAX_PAGE_SIZE;
+
+const JOURNAL_MAGIC: [u8; 8] = [0xd9, 0xd5, 0x05, 0xf9, 0x20, 0xa1, 0x63, 0xd7];
+/// The journal header is padded to the sector size.
+const SECTOR_SIZE: u32 = 512;
+const JOURNAL_HEADER_SIZE: usize = 28;
+
+/// The device and inode numbers of the database files written by connections
+/// in this process. A file appears once per writing connection.
+///
+/// This plays the role of the RESERVED lock of SQLite only within the process.
+static WRITING_FILES: Mutex<Vec<(u64, u64)>> = Mutex::new(Vec::new());
+
+/// The rollback journal file "<db>-journal" of a database file.
+pub struct Journal {
+    path: PathBuf,
+    /// The device and inode numbers of the database file.
+    db_id: Option<(u64, u64)>,
+}
+
+impl Journal {
+    pub fn new(db_path: &Path) -> Self {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push("-journal");
+        let db_id = fs::metadata(db_path)
+            .ok()
+            .map(|metadata| (metadata.dev(), metadata.ino()));
+        Self {
+            path: path.into(),
+            db_id,
+        }
+    }
+
+    /// Mark the database file as being written until the returned lock is
+    /// dropped.
+    pub fn lock_writer(&self) -> WriterLock {
+        if let Some(db_id) = self.db_id {
+            WRITING_FILES.lock().unwrap().push(db_id);
+        }
+        WriterLock(self.db_id)
+    }
+
+    /// Whether the hot journal exists. The journal of the file which a
+    /// connection in this process is writing to is not hot.
+    pub fn is_hot(&self) -> io::Result<bool> {
+        let writing_files = WRITING_FILES.lock().unwrap();
+        if self
+            .db_id
+            .is_some_and(|db_id| writing_files.contains(&db_id))
+        {
+            return Ok(false);
+        }
+        self.exists()
+    }
+
+    /// Roll back the hot journal. See [Journal::is_hot()] and
+    /// [Journal::rollback()].
+    pub fn rollback_hot(&self, storage: &dyn Storage) -> io::Result<bool> {
+        // No connection in this process starts writing until the rollback
+        // finishes.
+        let writing_files = WRITING_FILES.lock().unwrap();
+        if self
+            .db_id
+            .is_some_and(|db_id| writing_files.contains(&db_id))
+        {
+            return Ok(false);
+        }
+        self.rollback(storage)
+    }
+
+    /// This is used by test only.
+    #[allow(dead_code)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the journal exists.
+    pub fn exists(&self) -> io::Result<bool> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => Ok(metadata.len() > 0),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a new journal file.
+    ///
+    /// `n_pages` is the number of pages in the database before the commit.
+    pub fn create(&self, pagesize: u32, n_pages: u32) -> io::Result<JournalWriter> {
+        let nonce = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0)
+            ^ std::process::id();
+        let file = File::create(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        // The number of records is filled on finish.
+        let mut header = [0; SECTOR_SIZE as usize];
+        header[..8].copy_from_slice(&JOURNAL_MAGIC);
+        header[12..16].copy_from_slice(&nonce.to_be_bytes());
+        header[16..20].copy_from_slice(&n_pages.to_be_bytes());
+        header[20..24].copy_from_slice(&SECTOR_SIZE.to_be_bytes());
+        header[24..28].copy_from_slice(&pagesize.to_be_bytes());
+        writer.write_all(&header)?;
+        Ok(JournalWriter {
+            writer,
+            nonce,
+            n_records: 0,
+        })
+    }
+
+    /// Delete the journal file. This commits the transaction.
+    pub fn delete(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restore the original pages in the journal to the database and delete
+    /// the journal.
+    ///
+    /// Returns whether the database is rolled back. The journal which was not
+    /// completely written is just deleted because the database file is not
+    /// modified yet.
+    pub fn rollback(&self, storage: &dyn Storage) -> io::Result<bool> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let file_size = file.metadata()?.len();
+        let mut header = [0; JOURNAL_HEADER_SIZE];
+        if file_size < JOURNAL_HEADER_SIZE as u64
+            || file.read_exact_at(&mut header, 0).is_err()
+            || header[..8] != JOURNAL_MAGIC
+        {
+            self.delete()?;
+            return Ok(false);
+        }
+        let n_records = u32::from_be_bytes(header[8..12].try_into().unwrap());
+        let nonce = u32::from_be_bytes(header[12..16].try_into().unwrap());
+        let n_pages = u32::from_be_bytes(header[16..20].try_into().unwrap());
+        let sector_size = u32::from_be_bytes(header[20..24].try_into().unwrap());
+        let pagesize = u32::from_be_bytes(header[24..28].try_into().unwrap());
+        if !pagesize.is_power_of_two()
+            || !(512..=MAX_PAGE_SIZE as u32).contains(&pagesize)
+            || !sector_size.is_power_of_two()
+            || !(32..=MAX_PAGE_SIZE as u32).contains(&sector_size)
+        {
+            self.delete()?;
+            return Ok(false);
+        }
+        let record_size = pagesize as u64 + 8;
+        let n_records = if n_records == u32::MAX {
+            // The journal is written without syncing the number of records.
+            (file_size.saturating_sub(sector_size as u64) / record_size) as u32
+        } else {
+            n_records
+        };
+
+        let mut record = vec![0; record_size as usize];
+        let mut offset = sector_size as u64;
+        for _ in 0..n_records {
+            // A torn record at the tail is ignored.
+            if file.read_exact_at(&mut record, offset).is_err() {
+                break;
+            }
+            offset += record_size;
+            let page_id = u32::from_be_bytes(record[..4].try_into().unwrap());
+            let page = &record[4..4 + pagesize as usize];
+            let checksum = u32::from_be_bytes(record[4 + pagesize as usize..].try_into().unwrap());
+            if page_id == 0 || checksum != compute_checksum(nonce, page) {
+                break;
+            }
+            if page_id <= n_pages {
+                storage.write(page, (page_id - 1) as u64 * pagesize as u64)?;
+            }
+        }
+        storage.truncate(n_pages as u64 * pagesize as u64)?;
+        storage.sync()?;
+        self.delete()?;
+        Ok(true)
+    }
+}
+
+/// Returned by [Journal::lock_writer()].
+pub struct WriterLock(Option<(u64, u64)>);
+
+impl Drop for WriterLock {
+    fn drop(&mut self) {
+        if let Some(db_id) = self.0 {
+            let mut files = WRITING_FILES.lock().unwrap();
+            if let Some(i) = files.iter().position(|id| *id == db_id) {
+                files.swap_remove(i);
+            }
+        }
+    }
+}
+
+/// Writes the original pages to a new journal file.
+pub struct JournalWriter {
+    writer: BufWriter<File>,
+    nonce: u32,
+    n_records: u32,
+}
+
+impl JournalWriter {
+    pub fn append(&mut self, page_id: PageId, page: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&page_id.get().to_be_bytes())?;
+        self.writer.write_all(page)?;
+        self.writer
+            .write_all(&compute_checksum(self.nonce, page).to_be_bytes())?;
+        self.n_records += 1;
+        Ok(())
+    }
+
+    /// Write the number of records to the header and sync the journal file.
+    ///
+    /// The database file must not be modified until this succeeds.
+    pub fn finish(self) -> io::Result<()> {
+        let file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.write_all_at(&self.n_records.to_be_bytes(), 8)?;
+        file.sync_all()
+    }
+}
+
+/// The checksum of a journal record samples every 200th bytes of the page.
+fn compute_checksum(nonce: u32, page: &[u8]) -> u32 {
+    let mut checksum = nonce;
+    let mut i = page.len() as isize - 200;
+    while i > 0 {
+        checksum = checksum.wrapping_add(page[i as usize] as u32);
+        i -= 200;
+    }
+    checksum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils::*;
+    use crate::value::Value;
+    use crate::Connection;
+
+    fn read_file(path: &Path) -> Vec<u8> {
+        fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_rollback_hot_journal() {
+        let file = create_sqlite_database(&[
+            "PRAGMA page_size = 512;",
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(col) VALUES (1);",
+            "INSERT INTO example(col) VALUES (2);",
+        ]);
+        let original = read_file(file.path());
+        assert_eq!(original.len(), 1024);
+
+        // Simulate a crash while writing pages after the journal is synced.
+        let journal = Journal::new(file.path());
+        let mut writer = journal.create(512, 2).unwrap();
+        for page_id in [2, 1] {
+            let offset = (page_id - 1) * 512;
+            writer
+                .append(
+                    PageId::new(page_id as u32).unwrap(),
+                    &original[offset..offset + 512],
+                )
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        file.as_file().write_all_at(&[0xff; 512], 512).unwrap();
+        file.as_file().write_all_at(&[0xff; 1024], 1024).unwrap();
+        assert!(journal.exists().unwrap());
+
+        let conn = Connection::open(file.path()).unwrap();
+        assert!(!journal.exists().unwrap());
+        assert_eq!(read_file(file.path()), original);
+        let stmt = conn.prepare("SELECT col FROM example;").unwrap();
+        let mut rows = stmt.query().unwrap();
+        let mut values = Vec::new();
+        while let Some(row) = rows.next_row().unwrap() {
+            let columns = row.parse().unwrap();
+            let Some(&Value::Integer(value)) = columns.get(0) else {
+                panic!("unexpected value");
+            };
+            values.push(value);
+        }
+        assert_eq!(values, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_keep_journal_of_live_writer() {
+        let file = create_sqlite_database(&[
+            "PRAGMA page_size = 512;",
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(col) VALUES (1);",
+        ]);
+        let original = read_file(file.path());
+        let writer = Connection::open(file.path()).unwrap();
+        let txn = writer.begin().unwrap();
+        writer
+            .prepare("INSERT INTO example(col) VALUES (2);")
+            .unwrap()
+            .execute()
+            .unwrap();
+
+        // The journal of the writer in the middle of committing.
+        let journal = Journal::new(file.path());
+        let mut journal_writer = journal.create(512, 2).unwrap();
+        journal_writer
+            .append(PageId::new(2).unwrap(), &[0xff; 512])
+            .unwrap();
+        journal_writer.finish().unwrap();
+
+        let conn = Connection::open(file.path()).unwrap();
+        Connection::open_readonly(file.path()).unwrap();
+        assert!(journal.exists().unwrap());
+        assert_eq!(read_file(file.path()), original);
+        drop(conn);
+
+        txn.commit().unwrap();
+        assert!(!journal.exists().unwrap());
+        let conn = Connection::open(file.path()).unwrap();
+        let stmt = conn.prepare("SELECT col FROM example;").unwrap();
+        let mut rows = stmt.query().unwrap();
+        let mut values = Vec::new();
+        while let Some(row) = rows.next_row().unwrap() {
+            let columns = row.parse().unwrap();
+            let Some(&Value::Integer(value)) = columns.get(0) else {
+                panic!("unexpected value");
+            };
+            values.push(value);
+        }
+        assert_eq!(values, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_rollback_torn_record() {
+        let file =
+            create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+        let original = read_file(file.path());
+
+        let journal = Journal::new(file.path());
+        let mut writer = journal.create(512, 2).unwrap();
+        writer
+            .append(PageId::new(2).unwrap(), &original[512..])
+            .unwrap();
+        writer
+            .append(PageId::new(1).unwrap(), &[0xff; 512])
+            .unwrap();
+        writer.finish().unwrap();
+        // Break the page of the second record at the byte covered by the checksum.
+        fs::OpenOptions::new()
+            .write(true)
+            .open(journal.path())
+            .unwrap()
+            .write_all_at(&[0], 512 + 520 + 4 + 312)
+            .unwrap();
+        file.as_file().write_all_at(&[0xff; 512], 512).unwrap();
+
+        assert!(journal.rollback(file.as_file()).unwrap());
+        assert!(!journal.exists().unwrap());
+        // Page 2 is restored and the broken record for page 1 is ignored.
+        assert_eq!(read_file(file.path()), original);
+    }
+
+    #[test]
+    fn test_rollback_incomplete_journal() {
+        let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+        let original = read_file(file.path());
+        let journal = Journal::new(file.path());
+
+        // No journal.
+        assert!(!journal.rollback(file.as_file()).unwrap());
+
+        // The header is not written.
+        fs::write(journal.path(), [0; 100]).unwrap();
+        assert!(!journal.rollback(file.as_file()).unwrap());
+        assert!(!journal.exists().unwrap());
+        assert_eq!(read_file(file.path()), original);
+    }
+
+    #[test]
+    fn test_commit_deletes_journal() {
+        let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+        let journal = Journal::new(file.path());
+        let conn = Connection::open(file.path()).unwrap();
+        let stmt = conn
+            .prepare("INSERT INTO example(col) VALUES (1);")
+            .unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+        assert!(!journal.exists().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_rollback_journal() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col);",
+            "INSERT INTO example(col) VALUES (1);",
+        ]);
+        let original = read_file(file.path());
+        let pagesize = 4096;
+
+        let journal = Journal::new(file.path());
+        let mut writer = journal.create(pagesize as u32, 2).unwrap();
+        writer
+            .append(PageId::new(2).unwrap(), &original[pagesize..])
+            .unwrap();
+        writer.finish().unwrap();
+        file.as_file()
+            .write_all_at(&vec![0xff; pagesize], pagesize as u64)
+            .unwrap();
+
+        // SQLite rolls back the journal written by prsqlite.
+        let conn = rusqlite::Connection::open(file.path()).unwrap();
+        let n: i64 = conn
+            .query_row("SELECT count(*) FROM example;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(n, 1);
+        drop(conn);
+        assert!(!journal.exists().unwrap());
+        assert_eq!(read_file(file.path()), original);
+    }
+}
@@ -12,12 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod aggregate;
 mod btree;
 mod cursor;
+#[cfg(feature = "serde")]
+mod de;
 mod expression;
+mod function;
 mod header;
+mod journal;
 mod pager;
 mod parser;
+mod pattern;
 mod payload;
 mod query;
 mod record;
@@ -28,61 +34,139 @@ mod token;
 mod utils;
 mod value;
 
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::fs::File;
 use std::fs::OpenOptions;
-use std::os::unix::fs::FileExt;
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::Arc;
+use std::sync::Weak;
 
+use aggregate::Aggregation;
 use anyhow::bail;
 use anyhow::Context;
+use btree::non_zero_to_u16;
 use btree::BtreeContext;
+use btree::BTREE_PAGE_TYPE_LEAF_TABLE;
+use cursor::create_btree;
 use cursor::BtreeCursor;
+#[cfg(feature = "serde")]
+pub use de::Error as DeserializeError;
 use expression::DataContext;
 use expression::Expression;
+use function::UserFunctions;
 use header::DatabaseHeader;
 use header::DatabaseHeaderMut;
+pub use header::TextEncoding;
 use header::DATABASE_HEADER_SIZE;
+use journal::Journal;
+pub use pager::CacheStats;
+use pager::MemoryStorage;
 use pager::PageId;
 use pager::Pager;
+use pager::Storage;
 use pager::PAGE_ID_1;
 use parser::expect_no_more_token;
 use parser::expect_semicolon;
 use parser::parse_sql;
+use parser::CompoundOperator;
+use parser::CompoundSelect;
+use parser::CreateIndex;
+use parser::CreateTable;
 use parser::Delete;
+use parser::ForeignKeyAction;
 use parser::Insert;
+use parser::OnConflict;
 use parser::Parser;
+use parser::Pragma;
 use parser::ResultColumn;
 use parser::Select;
 use parser::Stmt;
+use parser::Update;
+use payload::Payload;
+use payload::SlicePayload;
+pub use query::AccessMethod;
+use query::JoinPlan;
+use query::JoinQuery;
 use query::Query;
 use query::QueryPlan;
 use query::RowData;
+use record::parse_record;
 use record::RecordPayload;
+use schema::calc_collation;
 use schema::ColumnNumber;
+use schema::ForeignKey;
 use schema::Index;
 use schema::Schema;
+pub use schema::SchemaEntry;
 use schema::Table;
+use utils::random_u64;
+use utils::u64_to_i64;
+use utils::upper_to_lower;
+use utils::CaseInsensitiveBytes;
+use utils::MaybeQuotedBytes;
 pub use value::Buffer;
 use value::Collation;
+use value::ConstantValue;
 use value::TypeAffinity;
 pub use value::Value;
 use value::ValueCmp;
 use value::DEFAULT_COLLATION;
 
-// Original SQLite support both 32-bit or 64-bit rowid. prsqlite only support
-// 64-bit rowid.
+// SQLite can be built with 32-bit rowid (SQLITE_32BIT_ROWID), but the file
+// format does not record it. prsqlite only supports the default 64-bit rowid.
 const MAX_ROWID: i64 = i64::MAX;
+const SEQUENCE_TABLE_NAME: &[u8] = b"sqlite_sequence";
+/// The number of random rowids to try before giving up.
+const MAX_RANDOM_ROWID_TRIES: usize = 100;
+const MEMORY_DATABASE_NAME: &str = ":memory:";
+/// The page size of new databases.
+const DEFAULT_PAGESIZE: u32 = 4096;
+/// The default maximum number of statements in [StatementCache].
+const DEFAULT_STATEMENT_CACHE_SIZE: usize = 16;
 
 #[derive(Debug)]
 pub enum Error<'a> {
     Parse(parser::Error<'a>),
     Cursor(cursor::Error),
     Expression(expression::Error),
-    Query(query::Error),
     UniqueConstraintViolation,
+    NotNullConstraintViolation,
+    /// The value does not conform to the datatype of the column in a STRICT
+    /// table.
+    DataTypeConstraintViolation,
+    /// The CHECK constraint of the table is false for the row.
+    CheckConstraintViolation,
+    /// The parent key of a foreign key does not exist, or the parent key to
+    /// delete or update is referred to by child rows.
+    ForeignKeyConstraintViolation,
     DataTypeMismatch,
+    DatabaseFull,
+    /// The schema changed after the statement was prepared.
+    SchemaChanged,
+    /// Reading or writing the database file failed.
+    Io(std::io::Error),
+    /// The database file is malformed.
+    Corrupt(Box<dyn std::error::Error + Send + Sync>),
+    /// The file is not a database. The file is too short or the database
+    /// header is invalid.
+    NotADatabase,
+    /// The database is opened read-only.
+    ReadOnly,
+    /// Other statements or a transaction on the connection conflict.
+    Busy,
+    /// A statement in the transaction failed and the transaction can not be
+    /// committed.
+    TransactionAborted,
     Unsupported(&'static str),
     Other(anyhow::Error),
 }
@@ -95,7 +179,37 @@ impl<'a> From<parser::Error<'a>> for Error<'a> {
 
 impl From<cursor::Error> for Error<'_> {
     fn from(e: cursor::Error) -> Self {
-        Self::Cursor(e)
+        match e {
+            cursor::Error::Pager { e, .. } | cursor::Error::AllocatePage(e)
+                if !matches!(e, pager::Error::InvalidPageId) =>
+            {
+                e.into()
+            }
+            cursor::Error::FileCorrupt { .. }
+            | cursor::Error::Pager { .. }
+            | cursor::Error::NotTable
+            | cursor::Error::NotIndex
+            | cursor::Error::Record(_)
+            | cursor::Error::LoadPayload => Self::Corrupt(Box::new(e)),
+            _ => Self::Cursor(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error<'_> {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<pager::Error> for Error<'_> {
+    fn from(e: pager::Error) -> Self {
+        match e {
+            pager::Error::Io(e) => Self::Io(e),
+            pager::Error::NoSpace => Self::DatabaseFull,
+            pager::Error::InvalidFile | pager::Error::InvalidPageId => Self::Corrupt(Box::new(e)),
+            pager::Error::RemainingReference => Self::Other(e.into()),
+        }
     }
 }
 
@@ -107,13 +221,29 @@ impl From<expression::Error> for Error<'_> {
 
 impl From<query::Error> for Error<'_> {
     fn from(e: query::Error) -> Self {
-        Self::Query(e)
+        match e {
+            query::Error::Cursor(e) => e.into(),
+            query::Error::Expression(e) => Self::Expression(e),
+            query::Error::Record(_) => Self::Corrupt(Box::new(e)),
+        }
     }
 }
 
+/// Errors from the internal modules are classified by their root cause.
 impl From<anyhow::Error> for Error<'_> {
     fn from(e: anyhow::Error) -> Self {
-        Self::Other(e)
+        let e = match e.downcast::<cursor::Error>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<pager::Error>() {
+            Ok(e) => return e.into(),
+            Err(e) => e,
+        };
+        match e.downcast::<std::io::Error>() {
+            Ok(e) => Self::Io(e),
+            Err(e) => Self::Other(e),
+        }
     }
 }
 
@@ -131,15 +261,48 @@ impl Display for Error<'_> {
             Error::Expression(e) => {
                 write!(f, "expression error: {}", e)
             }
-            Error::Query(e) => {
-                write!(f, "query error: {}", e)
-            }
             Error::DataTypeMismatch => {
                 write!(f, "data type mismatch")
             }
             Error::UniqueConstraintViolation => {
                 write!(f, "unique constraint violation")
             }
+            Error::NotNullConstraintViolation => {
+                write!(f, "not null constraint violation")
+            }
+            Error::DataTypeConstraintViolation => {
+                write!(f, "datatype constraint violation")
+            }
+            Error::CheckConstraintViolation => {
+                write!(f, "check constraint violation")
+            }
+            Error::ForeignKeyConstraintViolation => {
+                write!(f, "foreign key constraint violation")
+            }
+            Error::DatabaseFull => {
+                write!(f, "database or disk is full")
+            }
+            Error::SchemaChanged => {
+                write!(f, "database schema has changed")
+            }
+            Error::Io(e) => {
+                write!(f, "io error: {}", e)
+            }
+            Error::Corrupt(e) => {
+                write!(f, "database disk image is malformed: {}", e)
+            }
+            Error::NotADatabase => {
+                write!(f, "file is not a database")
+            }
+            Error::ReadOnly => {
+                write!(f, "database opened read-only")
+            }
+            Error::Busy => {
+                write!(f, "other statements running")
+            }
+            Error::TransactionAborted => {
+                write!(f, "transaction aborted")
+            }
             Error::Unsupported(msg) => {
                 write!(f, "unsupported: {}", msg)
             }
@@ -154,35 +317,164 @@ pub struct Connection {
     pager: Pager,
     btree_ctx: BtreeContext,
     schema: RefCell<Option<Schema>>,
+    /// The schema cookie in the database header when [Connection::schema] was
+    /// loaded.
+    schema_cookie: Cell<u32>,
+    functions: RefCell<UserFunctions>,
+    statement_cache: RefCell<StatementCache>,
+    /// Whether foreign key constraints are enforced. This is set by PRAGMA
+    /// foreign_keys.
+    foreign_keys: Cell<bool>,
     /// Number of running read or write.
     ///
-    /// > 0 : read(s) running
-    /// 0   : no read/write
-    /// -1  : write running
+    /// * > 0 : read(s) running
+    /// * 0   : no read/write
+    /// * -1  : write running
     ref_count: Cell<i64>,
+    /// The state of the transaction started by [Connection::begin()].
+    transaction: Cell<TransactionState>,
+    /// The rowid of the last row inserted by INSERT.
+    last_insert_rowid: Cell<i64>,
+    /// Number of rows changed by the last INSERT, UPDATE or DELETE.
+    changes: Cell<u64>,
+    /// Number of rows changed by INSERT, UPDATE or DELETE since the connection
+    /// was opened.
+    total_changes: Cell<u64>,
+    read_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransactionState {
+    None,
+    Active,
+    /// A statement in the transaction failed and all changes are discarded.
+    Aborted,
 }
 
 impl Connection {
-    pub fn open(filename: &Path) -> anyhow::Result<Self> {
-        // TODO: support read only mode.
+    /// Open the database file.
+    ///
+    /// The filename ":memory:" opens a new in-memory database. An empty file is
+    /// initialized as a new empty database.
+    ///
+    /// The hot journal left by a crashed write is rolled back unless another
+    /// connection in this process is writing to the file. Connections in other
+    /// processes are not detected because the file is not locked, so a
+    /// database file must not be opened by multiple processes while one of
+    /// them writes to it.
+    pub fn open(filename: &Path) -> Result<'static, Self> {
+        if filename == Path::new(MEMORY_DATABASE_NAME) {
+            return Self::open_memory();
+        }
+        Self::open_with_mode(filename, false)
+    }
+
+    /// Open a new empty database which lives only in memory.
+    pub fn open_memory() -> Result<'static, Self> {
+        Self::from_storage(
+            MemoryStorage::new(new_database_page1(DEFAULT_PAGESIZE)),
+            false,
+        )
+    }
+
+    /// Open the database file without write permission.
+    ///
+    /// Any write statement on the connection fails.
+    pub fn open_readonly(filename: &Path) -> Result<'static, Self> {
+        Self::open_with_mode(filename, true)
+    }
+
+    /// Create a new database file with the options set on the returned
+    /// [CreateOptions].
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("example.db");
+    /// let conn = prsqlite::Connection::create(&path)
+    ///     .page_size(512)
+    ///     .open()
+    ///     .unwrap();
+    /// ```
+    pub fn create(filename: &Path) -> CreateOptions<'_> {
+        CreateOptions {
+            filename,
+            pagesize: DEFAULT_PAGESIZE,
+        }
+    }
+
+    fn open_with_mode(filename: &Path, read_only: bool) -> Result<'static, Self> {
         let file = OpenOptions::new()
             .read(true)
-            .write(true)
+            .write(!read_only)
             .open(filename)
-            .with_context(|| format!("failed to open file: {:?}", filename))?;
+            .map_err(|e| open_error(filename, e))?;
+        Self::from_file(file, filename, read_only, DEFAULT_PAGESIZE)
+    }
+
+    /// Open the database file. The file is initialized with the page size if it
+    /// is empty.
+    fn from_file(
+        file: File,
+        filename: &Path,
+        read_only: bool,
+        pagesize: u32,
+    ) -> Result<'static, Self> {
+        // Roll back the transaction interrupted by a crash.
+        let journal = Journal::new(filename);
+        if read_only {
+            // The hot journal can not be rolled back without write permission.
+            if journal.is_hot()? {
+                return Err(Error::ReadOnly);
+            }
+        } else {
+            journal
+                .rollback_hot(&file)
+                .context("failed to roll back hot journal")?;
+        }
+        if Storage::size(&file)? == 0 {
+            if read_only {
+                return Err(Error::NotADatabase);
+            }
+            Storage::write(&file, &new_database_page1(pagesize), 0)?;
+            Storage::sync(&file)?;
+        }
+        let mut conn = Self::from_storage(file, read_only)?;
+        conn.pager.set_journal(journal);
+        Ok(conn)
+    }
+
+    fn from_storage<S: Storage + 'static>(storage: S, read_only: bool) -> Result<'static, Self> {
         let mut buf = [0; DATABASE_HEADER_SIZE];
-        file.read_exact_at(&mut buf, 0)?;
+        storage.read(&mut buf, 0).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                Error::NotADatabase
+            } else {
+                Error::Io(e)
+            }
+        })?;
         let header = DatabaseHeader::from(&buf);
+        if !header.validate_magic_header() {
+            return Err(Error::NotADatabase);
+        }
         header
             .validate()
-            .map_err(|e| anyhow::anyhow!("database header invalid: {e}"))?;
+            .map_err(|e| Error::Corrupt(format!("database header invalid: {e}").into()))?;
         let pagesize = header.pagesize();
         // pagesize is bigger than or equal to 512.
         // reserved is smaller than or equal to 255.
         let usable_size = pagesize - header.reserved() as u32;
+        // The database size in the header is not reliable if a legacy writer
+        // modified the file. It is computed from the file size instead.
+        let n_pages = if header.is_n_pages_valid() {
+            header.n_pages()
+        } else {
+            (storage.size()? / pagesize as u64)
+                .try_into()
+                .map_err(|_| Error::Corrupt("database file too large".into()))?
+        };
         let pager = Pager::new(
-            file,
-            header.n_pages(),
+            storage,
+            n_pages,
             pagesize,
             usable_size,
             header.first_freelist_trunk_page_id(),
@@ -192,88 +484,699 @@ impl Connection {
             pager,
             btree_ctx: BtreeContext::new(usable_size),
             schema: RefCell::new(None),
+            schema_cookie: Cell::new(0),
+            functions: RefCell::new(UserFunctions::default()),
+            statement_cache: RefCell::new(StatementCache::new(DEFAULT_STATEMENT_CACHE_SIZE)),
+            foreign_keys: Cell::new(false),
             ref_count: Cell::new(0),
+            transaction: Cell::new(TransactionState::None),
+            last_insert_rowid: Cell::new(0),
+            changes: Cell::new(0),
+            total_changes: Cell::new(0),
+            read_only,
         })
     }
 
+    /// Register a scalar function which takes `n_args` arguments.
+    ///
+    /// NULL arguments and the NULL result are [None]. The function overrides
+    /// the built-in function with the same name and the same number of
+    /// arguments. Statements prepared before the registration are not
+    /// affected. The statement cache is cleared so that the following
+    /// [Connection::prepare()] uses the function.
+    pub fn create_function<F>(&self, name: &str, n_args: usize, func: F) -> Result<'static, ()>
+    where
+        F: Fn(&[Option<Value>]) -> anyhow::Result<Option<Value<'static>>> + 'static,
+    {
+        if name.is_empty() || name.len() > 255 {
+            return Err(Error::Other(anyhow::anyhow!(
+                "invalid function name: {:?}",
+                name
+            )));
+        } else if n_args > 127 {
+            return Err(Error::Other(anyhow::anyhow!(
+                "too many arguments: {}",
+                n_args
+            )));
+        }
+        self.functions
+            .borrow_mut()
+            .insert(name, n_args, Box::new(func));
+        self.clear_statement_cache();
+        Ok(())
+    }
+
+    /// The tables in the database in the order of sqlite_schema.
+    ///
+    /// sqlite_schema itself is not included.
+    pub fn tables(&self) -> Result<'static, Vec<SchemaEntry>> {
+        self.schema_entries(b"table")
+    }
+
+    /// The indexes in the database in the order of sqlite_schema.
+    ///
+    /// Indexes created for PRIMARY KEY and UNIQUE constraints have no SQL.
+    pub fn indexes(&self) -> Result<'static, Vec<SchemaEntry>> {
+        self.schema_entries(b"index")
+    }
+
+    fn schema_entries(&self, type_: &[u8]) -> Result<'static, Vec<SchemaEntry>> {
+        if self.schema_cookie()? != self.schema_cookie.get() {
+            *self.schema.borrow_mut() = None;
+        }
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        Ok(schema
+            .entries()
+            .iter()
+            .filter(|entry| entry.type_.as_bytes() == type_)
+            .cloned()
+            .collect())
+    }
+
+    /// Query all the columns of the rows of the table whose rowid is in the
+    /// range, in the rowid order.
+    ///
+    /// The cursor seeks the start of the range instead of scanning the table
+    /// from the first row.
+    pub fn query_rowid_range(
+        &self,
+        table_name: &str,
+        rowids: RangeInclusive<i64>,
+    ) -> Result<'static, Rows<'_>> {
+        if self.schema_cookie()? != self.schema_cookie.get() {
+            *self.schema.borrow_mut() = None;
+        }
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let table = schema
+            .get_table(table_name.as_bytes())
+            .ok_or(anyhow::anyhow!("table not found: {:?}", table_name))?;
+        if table.without_rowid {
+            return Err(Error::Other(anyhow::anyhow!(
+                "table has no rowid: {:?}",
+                table_name
+            )));
+        }
+        let columns = table
+            .get_all_columns()
+            .map(Expression::Column)
+            .collect::<Vec<_>>();
+        let column_names = table
+            .get_all_columns()
+            .map(|column| String::from_utf8_lossy(table.get_column_name(&column.0)).into_owned())
+            .collect();
+        let statement = SelectStatement::new(
+            table.root_page_id,
+            columns,
+            column_names,
+            Expression::one(),
+            QueryPlan::RowIdRange(*rowids.start(), *rowids.end()),
+            Vec::new(),
+            None,
+        );
+        drop(schema_cell);
+        statement.query(self)
+    }
+
+    /// Prepare the statement.
+    ///
+    /// The statements prepared recently are cached by their SQL and a copy of
+    /// the cached plan is returned for the same SQL without parsing it again.
+    /// The cached plans are discarded when the schema changes.
     pub fn prepare<'a, 'conn>(&'conn self, sql: &'a str) -> Result<'a, Statement<'conn>> {
+        let schema_cookie = self.schema_cookie()?;
+        if let Some(cached) = self.statement_cache.borrow_mut().get(sql, schema_cookie) {
+            return Ok(cached.into_statement(self));
+        }
         let input = sql.as_bytes();
         let mut parser = Parser::new(input);
         let statement = parse_sql(&mut parser)?;
         expect_semicolon(&mut parser)?;
         expect_no_more_token(&parser)?;
+        let statement = self.prepare_statement(statement, &parser)?;
+        self.statement_cache
+            .borrow_mut()
+            .insert(sql, CachedStatement::from(&statement));
+        Ok(statement)
+    }
 
-        match statement {
-            Stmt::Select(select) => Ok(Statement::Query(self.prepare_select(select)?)),
-            Stmt::Insert(insert) => {
-                Ok(Statement::Execution(Box::new(self.prepare_insert(insert)?)))
+    /// Set the maximum number of statements kept in the statement cache. 0
+    /// disables the cache.
+    pub fn set_statement_cache_size(&self, n_statements: usize) {
+        self.statement_cache.borrow_mut().set_capacity(n_statements);
+    }
+
+    /// Discard all the cached statements.
+    pub fn clear_statement_cache(&self) {
+        self.statement_cache.borrow_mut().clear();
+    }
+
+    /// Statistics of the statement cache lookups by [Connection::prepare()].
+    pub fn statement_cache_stats(&self) -> CacheStats {
+        self.statement_cache.borrow().stats
+    }
+
+    /// Run the `;`-separated statements in order within a single transaction.
+    ///
+    /// Stops at the first error and returns it. Parameters are NULL and the
+    /// rows of queries are discarded.
+    ///
+    /// If a transaction started by [Connection::begin()] is running, the
+    /// statements run in it and are committed or rolled back with it. A
+    /// failed write aborts the transaction as it does when executed alone.
+    /// Otherwise the batch commits its own transaction, and all the changes
+    /// made by the batch are rolled back on error.
+    pub fn execute_batch<'a>(&self, sql: &'a str) -> Result<'a, ()> {
+        let txn = if self.transaction.get() == TransactionState::None {
+            Some(self.begin()?)
+        } else {
+            None
+        };
+        let mut input = sql.as_bytes();
+        loop {
+            let mut parser = Parser::new(input);
+            if expect_no_more_token(&parser).is_ok() {
+                break;
+            }
+            let statement = parse_sql(&mut parser)?;
+            expect_semicolon(&mut parser)?;
+            // Each statement is prepared after the previous one is executed so
+            // that it sees the schema changes made by the batch.
+            let stmt = self.prepare_statement(statement, &parser)?;
+            if stmt.is_query() {
+                for row in stmt.query()? {
+                    row?;
+                }
+            } else {
+                stmt.execute()?;
             }
-            Stmt::Delete(delete) => Ok(Statement::Execution(self.prepare_delete(delete)?)),
+            input = &input[parser.n_consumed()..];
+        }
+        if let Some(txn) = txn {
+            txn.commit()?;
+        }
+        Ok(())
+    }
+
+    fn prepare_statement<'a>(
+        &self,
+        statement: Stmt<'a>,
+        parser: &Parser<'a>,
+    ) -> Result<'a, Statement<'_>> {
+        // Reload the schema if it was changed.
+        let schema_cookie = self.schema_cookie()?;
+        if schema_cookie != self.schema_cookie.get() {
+            *self.schema.borrow_mut() = None;
         }
+
+        let (n_parameters, parameter_names) = parser.parameters();
+        let parameter_names = parameter_names
+            .iter()
+            .map(|(name, idx)| (name.to_vec(), *idx))
+            .collect();
+
+        let kind = match statement {
+            Stmt::Select(select) => StatementKind::Query(self.prepare_select(select)?),
+            Stmt::CompoundSelect(compound) => {
+                StatementKind::CompoundQuery(self.prepare_compound_select(compound)?)
+            }
+            Stmt::Insert(insert) => {
+                StatementKind::Execution(Box::new(self.prepare_insert(insert)?))
+            }
+            Stmt::Delete(delete) => StatementKind::Execution(self.prepare_delete(delete)?),
+            Stmt::Update(update) => {
+                StatementKind::Execution(Box::new(self.prepare_update(update)?))
+            }
+            Stmt::CreateTable(create_table, sql) => {
+                StatementKind::Execution(Box::new(self.prepare_create_table(create_table, sql)?))
+            }
+            Stmt::CreateIndex(create_index, sql) => {
+                StatementKind::Execution(Box::new(self.prepare_create_index(create_index, sql)?))
+            }
+            Stmt::Pragma(pragma) => self.prepare_pragma(pragma)?,
+            Stmt::Vacuum => StatementKind::Execution(Box::new(VacuumStatement)),
+        };
+        Ok(Statement {
+            conn: self,
+            kind,
+            schema_cookie,
+            n_parameters,
+            parameter_names,
+        })
     }
 
     fn load_schema(&self) -> anyhow::Result<()> {
+        self.schema_cookie.set(self.schema_cookie()?);
         let schema_table = Schema::schema_table();
         let columns = schema_table
             .get_all_columns()
             .map(Expression::Column)
             .collect::<Vec<_>>();
         *self.schema.borrow_mut() = Some(Schema::generate(
+            self,
             SelectStatement::new(
-                self,
                 schema_table.root_page_id,
                 columns,
+                Rc::new([]),
                 Expression::one(),
                 QueryPlan::FullScan,
+                Vec::new(),
+                None,
             ),
             schema_table,
         )?);
         Ok(())
     }
 
+    /// Read the schema cookie from the database header.
+    fn schema_cookie(&self) -> anyhow::Result<u32> {
+        self.refresh()?;
+        self.read_header(|header| header.schema_cookie())
+    }
+
+    /// Drop the cached pages if another connection committed to the database
+    /// file, so that a schema change made by it is detected by the schema
+    /// cookie.
+    ///
+    /// The cache is kept while a statement or a transaction is running on the
+    /// connection because its pages may be modified or referenced.
+    fn refresh(&self) -> anyhow::Result<()> {
+        if self.ref_count.get() == 0 && self.transaction.get() == TransactionState::None {
+            self.pager.refresh()?;
+        }
+        Ok(())
+    }
+
+    fn read_header<T>(&self, f: impl FnOnce(&DatabaseHeader) -> T) -> anyhow::Result<T> {
+        let page1 = self.pager.get_page(PAGE_ID_1)?;
+        let buffer = page1.buffer();
+        let header = DatabaseHeader::from(buffer[..DATABASE_HEADER_SIZE].try_into().unwrap());
+        Ok(f(&header))
+    }
+
+    /// Increment the schema cookie to invalidate the prepared statements.
+    fn increment_schema_cookie(&self) -> anyhow::Result<()> {
+        let page1 = self.pager.get_page(PAGE_ID_1)?;
+        let mut buffer = self.pager.make_page_mut(&page1)?;
+        let header_buf = &mut buffer[..DATABASE_HEADER_SIZE];
+        let cookie = DatabaseHeader::from((&*header_buf).try_into().unwrap()).schema_cookie();
+        DatabaseHeaderMut::from(header_buf.try_into().unwrap())
+            .set_schema_cookie(cookie.wrapping_add(1));
+        Ok(())
+    }
+
+    fn set_user_version(&self, user_version: i32) -> anyhow::Result<()> {
+        let page1 = self.pager.get_page(PAGE_ID_1)?;
+        let mut buffer = self.pager.make_page_mut(&page1)?;
+        DatabaseHeaderMut::from((&mut buffer[..DATABASE_HEADER_SIZE]).try_into().unwrap())
+            .set_user_version(user_version);
+        Ok(())
+    }
+
     fn prepare_select<'a>(&self, select: Select<'a>) -> Result<'a, SelectStatement> {
         if self.schema.borrow().is_none() {
             self.load_schema()?;
         }
         let schema_cell = self.schema.borrow();
         let schema = schema_cell.as_ref().unwrap();
+        let functions = self.functions.borrow();
         let table_name = select.table_name.dequote();
         let table = schema.get_table(&table_name).ok_or(anyhow::anyhow!(
             "table not found: {:?}",
             std::str::from_utf8(&table_name).unwrap_or_default()
         ))?;
+        // The FROM table is the outer table of a join.
+        let mut tables = vec![(table_name.as_slice(), table)];
+        let inner_table_name;
+        let mut join_constraint = None;
+        if let Some(join) = select.join {
+            inner_table_name = join.table_name.dequote();
+            let inner_table = schema.get_table(&inner_table_name).ok_or(anyhow::anyhow!(
+                "table not found: {:?}",
+                std::str::from_utf8(&inner_table_name).unwrap_or_default()
+            ))?;
+            tables.push((inner_table_name.as_slice(), inner_table));
+            join_constraint = Some((join.constraint, join.left));
+        }
+
+        // GROUP BY is set after the result columns it may refer to.
+        let mut aggregation = Aggregation::new(Vec::new());
 
         let mut columns = Vec::new();
+        let mut column_names = Vec::new();
+        // The aliases and the indexes of the result columns they name.
+        let mut aliases = Vec::new();
+        // The indexes of the result columns calling aggregate functions.
+        let mut aggregate_columns = Vec::new();
         for column in select.columns {
             match column {
                 ResultColumn::All => {
-                    columns.extend(table.get_all_columns().map(Expression::Column));
+                    for i in 0..tables.len() {
+                        add_all_columns(
+                            &tables,
+                            i,
+                            &mut columns,
+                            &mut column_names,
+                            &mut aggregation,
+                        );
+                    }
                 }
-                ResultColumn::Expr((expr, _alias)) => {
-                    // TODO: consider alias.
-                    columns.push(Expression::from(expr, Some(table))?);
+                ResultColumn::Expr { expr, alias, text } => {
+                    let n_calls = aggregation.calls.len();
+                    let expr =
+                        Expression::from_aggregate(expr, &tables, &functions, &mut aggregation)?;
+                    if aggregation.calls.len() != n_calls {
+                        aggregate_columns.push(columns.len());
+                    }
+                    // The alias only changes the name of the column. A bare column is named after
+                    // the column definition.
+                    column_names.push(match (alias, &expr) {
+                        (Some(alias), _) => {
+                            let alias = alias.dequote();
+                            aliases.push((alias.clone(), columns.len()));
+                            Cow::Owned(alias)
+                        }
+                        (None, Expression::Column((column_number, _, _))) => {
+                            Cow::Borrowed(tables[tables.len() - 1].1.get_column_name(column_number))
+                        }
+                        (None, Expression::OuterColumn((column_number, _, _))) => {
+                            Cow::Borrowed(table.get_column_name(column_number))
+                        }
+                        (None, _) => Cow::Borrowed(text),
+                    });
+                    columns.push(expr);
                 }
-                ResultColumn::AllOfTable(_table_name) => {
-                    todo!("ResultColumn::AllOfTable");
+                ResultColumn::AllOfTable(name) => {
+                    let name = name.dequote();
+                    let Some(i) = tables.iter().position(|(table_name, _)| {
+                        CaseInsensitiveBytes::from(*table_name)
+                            == CaseInsensitiveBytes::from(name.as_slice())
+                    }) else {
+                        return Err(Error::Other(anyhow::anyhow!(
+                            "no such table: {}",
+                            String::from_utf8_lossy(&name)
+                        )));
+                    };
+                    add_all_columns(
+                        &tables,
+                        i,
+                        &mut columns,
+                        &mut column_names,
+                        &mut aggregation,
+                    );
                 }
             }
         }
 
+        let find_alias = |name: &MaybeQuotedBytes| {
+            let name = name.dequote();
+            aliases
+                .iter()
+                .find(|(alias, _)| {
+                    CaseInsensitiveBytes::from(alias) == CaseInsensitiveBytes::from(&name)
+                })
+                .map(|(_, i)| *i)
+        };
+
+        // GROUP BY terms refer to the result columns by integers or aliases
+        // like ORDER BY terms, but the table columns take precedence over the
+        // aliases.
+        let mut group_by = Vec::with_capacity(select.group_by.len());
+        for expr in select.group_by {
+            let result_column = match &expr {
+                parser::Expr::Column(name)
+                    if tables
+                        .iter()
+                        .all(|(_, table)| table.get_column(&name.dequote()).is_none()) =>
+                {
+                    find_alias(name)
+                }
+                parser::Expr::Integer(i) => {
+                    if *i < 1 || *i as usize > columns.len() {
+                        return Err(Error::Other(anyhow::anyhow!(
+                            "GROUP BY term out of range: {}",
+                            i
+                        )));
+                    }
+                    Some(*i as usize - 1)
+                }
+                _ => None,
+            };
+            group_by.push(match result_column {
+                Some(i) if aggregate_columns.contains(&i) => {
+                    return Err(Error::Other(anyhow::anyhow!(
+                        "aggregate functions are not allowed in the GROUP BY clause"
+                    )));
+                }
+                Some(i) => columns[i].clone(),
+                None => Expression::from(expr, &tables, &functions)?,
+            });
+        }
+        aggregation.group_by = group_by;
+
+        let mut subqueries = Vec::new();
         let filter = select
             .filter
-            .map(|expr| Expression::from(expr, Some(table)))
+            .map(|expr| {
+                Expression::from_with_subqueries(expr, &tables, &functions, &mut subqueries)
+            })
             .transpose()?
             .unwrap_or(Expression::one());
+        let subqueries = subqueries
+            .into_iter()
+            .map(|select| {
+                let statement = self.prepare_select(select)?;
+                if statement.columns.len() != 1 {
+                    return Err(Error::Other(anyhow::anyhow!(
+                        "sub-select returns {} columns - expected 1",
+                        statement.columns.len()
+                    )));
+                }
+                Ok(statement)
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-        let query_plan = QueryPlan::generate(table, &filter);
+        let (mut query_plan, join) = if let Some((constraint, left)) = join_constraint {
+            let constraint = Expression::from(constraint, &tables, &functions)?;
+            let plan = JoinPlan::generate(table, tables[1].1, &constraint, left);
+            // TODO: Narrow down the outer rows by the WHERE clause.
+            (
+                QueryPlan::generate(table, &Expression::one()),
+                Some((Rc::new(plan), Rc::new(constraint))),
+            )
+        } else {
+            (QueryPlan::generate(table, &filter), None)
+        };
 
-        Ok(SelectStatement::new(
-            self,
+        aggregation.having = select
+            .having
+            .map(|expr| Expression::from_aggregate(expr, &tables, &functions, &mut aggregation))
+            .transpose()?;
+
+        let mut order_by = Vec::with_capacity(select.order_by.len());
+        for term in select.order_by {
+            // An alias of a result column takes precedence over the table columns.
+            let alias_column = match &term.expr {
+                parser::Expr::Column(name) => find_alias(name),
+                _ => None,
+            };
+            let expr = match (term.expr, alias_column) {
+                (_, Some(i)) => columns[i].clone(),
+                // An integer refers to the result column.
+                (parser::Expr::Integer(i), None) => {
+                    if i < 1 || i as usize > columns.len() {
+                        return Err(Error::Other(anyhow::anyhow!(
+                            "ORDER BY term out of range: {}",
+                            i
+                        )));
+                    }
+                    columns[i as usize - 1].clone()
+                }
+                (expr, None) => {
+                    Expression::from_aggregate(expr, &tables, &functions, &mut aggregation)?
+                }
+            };
+            order_by.push(SortKey {
+                expr,
+                desc: term.desc,
+                // NULL is the smallest value by default.
+                nulls_first: term.nulls_first.unwrap_or(!term.desc),
+            });
+        }
+
+        let aggregation = if aggregation.is_aggregate() {
+            if join.is_some() {
+                return Err(Error::Unsupported("aggregate on join"));
+            }
+            Some(Box::new(aggregation))
+        } else if aggregation.having.is_some() {
+            return Err(Error::Other(anyhow::anyhow!(
+                "HAVING clause on a non-aggregate query"
+            )));
+        } else {
+            None
+        };
+
+        let mut used_columns = Vec::new();
+        let mut add_column = |column: &ColumnNumber| {
+            if !used_columns.contains(column) {
+                used_columns.push(*column);
+            }
+        };
+        for expr in columns
+            .iter()
+            .chain([&filter])
+            .chain(order_by.iter().map(|key| &key.expr))
+        {
+            expr.for_each_column(&mut add_column);
+        }
+        if let Some(aggregation) = &aggregation {
+            aggregation.for_each_column(&mut add_column);
+        }
+        query_plan.use_covering_index(&used_columns);
+
+        // The table is scanned in the rowid order.
+        if aggregation.is_none()
+            && join.is_none()
+            && matches!(
+                query_plan,
+                QueryPlan::FullScan | QueryPlan::RowId(_) | QueryPlan::RowIdRange(..)
+            )
+            && matches!(
+                order_by.as_slice(),
+                [SortKey {
+                    expr: Expression::Column((ColumnNumber::RowId, _, _)),
+                    desc: false,
+                    ..
+                }]
+            )
+        {
+            order_by.clear();
+        }
+
+        let column_names = column_names
+            .into_iter()
+            .map(|name| String::from_utf8_lossy(&name).into_owned())
+            .collect();
+        let distinct = select.distinct.then(|| {
+            columns
+                .iter()
+                .map(|expr| expr.collation().clone())
+                .collect::<Vec<_>>()
+        });
+
+        let mut statement = SelectStatement::new(
             table.root_page_id,
             columns,
+            column_names,
             filter,
             query_plan,
-        ))
+            order_by,
+            aggregation,
+        );
+        statement.distinct = distinct;
+        statement.join = join;
+        statement.subqueries = subqueries;
+        Ok(statement)
+    }
+
+    fn prepare_compound_select<'a>(
+        &self,
+        mut compound: CompoundSelect<'a>,
+    ) -> Result<'a, CompoundSelectStatement> {
+        // The ORDER BY clause after the last SELECT sorts the whole result.
+        let terms = compound
+            .rest
+            .last_mut()
+            .map(|(_, select)| std::mem::take(&mut select.order_by))
+            .unwrap_or_default();
+        let first = self.prepare_select(compound.first)?;
+        let mut rest = Vec::with_capacity(compound.rest.len());
+        for (operator, select) in compound.rest {
+            let select = self.prepare_select(select)?;
+            if select.columns.len() != first.columns.len() {
+                return Err(Error::Other(anyhow::anyhow!(
+                    "SELECTs to the left and right of {} do not have the same number of result columns",
+                    operator.as_str()
+                )));
+            }
+            rest.push((operator, select));
+        }
+        let collations = first
+            .columns
+            .iter()
+            .map(|expr| expr.collation().clone())
+            .collect::<Vec<_>>();
+
+        // An ORDER BY term is an ordinal or the name of a result column of any of
+        // the SELECTs, optionally with COLLATE.
+        let mut order_by = Vec::with_capacity(terms.len());
+        for (i, term) in terms.into_iter().enumerate() {
+            let (expr, collation) = match term.expr {
+                parser::Expr::Collate {
+                    expr,
+                    collation_name,
+                } => (
+                    *expr,
+                    Some(
+                        calc_collation(&collation_name)
+                            .ok_or(expression::Error::CollationNotFound)?,
+                    ),
+                ),
+                expr => (expr, None),
+            };
+            let column = match expr {
+                parser::Expr::Integer(n) => {
+                    if n < 1 || n as usize > collations.len() {
+                        return Err(Error::Other(anyhow::anyhow!(
+                            "ORDER BY term out of range: {}",
+                            n
+                        )));
+                    }
+                    Some(n as usize - 1)
+                }
+                parser::Expr::Column(name) => {
+                    let name = name.dequote();
+                    [&first]
+                        .into_iter()
+                        .chain(rest.iter().map(|(_, select)| select))
+                        .find_map(|select| {
+                            select.column_names.iter().position(|column_name| {
+                                CaseInsensitiveBytes::from(column_name.as_bytes())
+                                    == CaseInsensitiveBytes::from(&name)
+                            })
+                        })
+                }
+                _ => None,
+            };
+            let Some(column) = column else {
+                return Err(Error::Other(anyhow::anyhow!(
+                    "ORDER BY term {} does not match any column in the result set",
+                    i + 1
+                )));
+            };
+            order_by.push(CompoundSortKey {
+                column,
+                collation: collation.unwrap_or_else(|| collations[column].clone()),
+                desc: term.desc,
+                nulls_first: term.nulls_first.unwrap_or(!term.desc),
+            });
+        }
+
+        Ok(CompoundSelectStatement {
+            column_names: first.column_names.clone(),
+            collations,
+            order_by,
+            first,
+            rest,
+        })
     }
 
     fn prepare_insert<'a>(&self, insert: Insert<'a>) -> Result<'a, InsertStatement> {
@@ -282,13 +1185,22 @@ impl Connection {
         }
         let schema_cell = self.schema.borrow();
         let schema = schema_cell.as_ref().unwrap();
+        let functions = self.functions.borrow();
         let table_name = insert.table_name.dequote();
         let table = schema.get_table(&table_name).ok_or(anyhow::anyhow!(
             "table not found: {:?}",
             std::str::from_utf8(&table_name).unwrap_or_default()
         ))?;
+        if table.without_rowid {
+            return Err(Error::Unsupported("write to without rowid table"));
+        } else if table.has_unsupported_index() {
+            return Err(Error::Unsupported("write to table with unsupported index"));
+        }
 
         let mut columns_idx = Vec::with_capacity(insert.columns.len());
+        if insert.columns.is_empty() {
+            columns_idx.extend((0..table.columns.len()).map(|i| table.column_number(i)));
+        }
         for column in insert.columns {
             let column_name = column.dequote();
             if let Some((column_idx, _, _)) = table.get_column(&column_name) {
@@ -301,12 +1213,26 @@ impl Connection {
             }
         }
 
+        // Omitted columns take the DEFAULT value or NULL. The rowid alias column
+        // is always stored as NULL in the record.
+        let mut defaults = Vec::with_capacity(table.columns.len());
+        let rowid_alias = table.rowid_alias();
+        for (i, column) in table.columns.iter().enumerate() {
+            let default = if rowid_alias == Some(i) {
+                None
+            } else {
+                column.default_expression()?
+            };
+            defaults.push(default.unwrap_or(Expression::Null));
+        }
+
         let mut records = Vec::with_capacity(insert.values.len());
         for column_values in insert.values {
             let mut columns = table
                 .columns
                 .iter()
-                .map(|column| (Expression::Null, column.type_affinity))
+                .zip(defaults.iter())
+                .map(|(column, default)| (default.clone(), column.type_affinity))
                 .collect::<Vec<_>>();
             let mut rowid = None;
             if column_values.len() != columns_idx.len() {
@@ -319,10 +1245,10 @@ impl Connection {
             for (column, expr) in columns_idx.iter().zip(column_values) {
                 match column {
                     ColumnNumber::RowId => {
-                        rowid = Some(Expression::from(expr, None)?);
+                        rowid = Some(Expression::from(expr, &[], &functions)?);
                     }
                     ColumnNumber::Column(column_idx) => {
-                        columns[*column_idx].0 = Expression::from(expr, None)?;
+                        columns[*column_idx].0 = Expression::from(expr, &[], &functions)?;
                     }
                 }
             }
@@ -333,100 +1259,638 @@ impl Connection {
         let mut indexes = Vec::new();
         let mut index = table.indexes.as_ref();
         while let Some(idx) = index {
-            indexes.push(IndexSchema::create(table, idx));
+            indexes.push(IndexSchema::create(idx));
             index = idx.next.as_ref();
         }
+        let sequence = if table.autoincrement {
+            let sequence_table = schema
+                .get_table(SEQUENCE_TABLE_NAME)
+                .ok_or(anyhow::anyhow!("sqlite_sequence not found"))?;
+            Some(Sequence {
+                root_page_id: sequence_table.root_page_id,
+                // sqlite_sequence has the table name as declared.
+                table_name: schema
+                    .get_name_by_root_page(table_page_id)
+                    .unwrap_or_default()
+                    .as_bytes()
+                    .to_vec(),
+            })
+        } else {
+            None
+        };
         Ok(InsertStatement {
-            conn: self,
             table_page_id,
             records,
             indexes,
+            sequence,
+            not_null_columns: table.not_null_columns(),
+            datatypes: table.datatypes(),
+            checks: table.check_expressions(&table_name)?,
+            foreign_keys: table
+                .foreign_keys
+                .iter()
+                .map(|foreign_key| ForeignKeySchema::create(schema, table, foreign_key))
+                .collect(),
+            on_conflict: insert.on_conflict,
         })
     }
 
-    fn prepare_delete<'a, 'conn>(
-        &'conn self,
-        delete: Delete<'a>,
-    ) -> Result<'a, Box<dyn ExecutionStatement + 'conn>> {
+    fn prepare_delete<'a>(&self, delete: Delete<'a>) -> Result<'a, Box<dyn ExecutionStatement>> {
         if self.schema.borrow().is_none() {
             self.load_schema()?;
         }
         let schema_cell = self.schema.borrow();
         let schema = schema_cell.as_ref().unwrap();
+        let functions = self.functions.borrow();
         let table_name = delete.table_name.dequote();
         let table = schema.get_table(&table_name).ok_or(anyhow::anyhow!(
             "table not found: {:?}",
             std::str::from_utf8(&table_name).unwrap_or_default()
         ))?;
+        if table.without_rowid {
+            return Err(Error::Unsupported("write to without rowid table"));
+        } else if table.has_unsupported_index() {
+            return Err(Error::Unsupported("write to table with unsupported index"));
+        }
 
         let filter = delete
             .filter
-            .map(|expr| Expression::from(expr, Some(table)))
+            .map(|expr| Expression::from(expr, &[(table_name.as_slice(), table)], &functions))
             .transpose()?;
 
         let table_page_id = table.root_page_id;
-        if let Some(filter) = filter {
-            let query_plan = QueryPlan::generate(table, &filter);
-
-            let query_index_page_id = query_plan.index_page_id();
-            let mut indexes = Vec::new();
-            let mut index = table.indexes.as_ref();
-            while let Some(idx) = index {
-                if Some(idx.root_page_id) != query_index_page_id {
-                    indexes.push(IndexSchema::create(table, idx));
+        let child_foreign_keys = schema
+            .get_child_foreign_keys(&table_name)
+            .into_iter()
+            .map(|(child, foreign_key)| ForeignKeySchema::create(schema, child, foreign_key))
+            .collect::<Vec<_>>();
+        let filter = match filter {
+            Some(filter) => filter,
+            // Rows referred to by foreign keys are deleted one by one to check
+            // the child rows.
+            None if !child_foreign_keys.is_empty() => Expression::one(),
+            None => {
+                let mut index_page_ids = Vec::new();
+                let mut index_schema = table.indexes.clone();
+                while let Some(index) = index_schema {
+                    index_page_ids.push(index.root_page_id);
+                    index_schema = index.next.clone();
                 }
-                index = idx.next.as_ref();
+                return Ok(Box::new(ClearStatement {
+                    table_page_id,
+                    index_page_ids,
+                }));
             }
+        };
+        let query_plan = QueryPlan::generate(table, &filter);
 
-            Ok(Box::new(DeleteStatement {
-                conn: self,
-                table_page_id,
-                indexes,
-                filter,
-                query_plan,
-            }))
-        } else {
-            let mut index_page_ids = Vec::new();
-            let mut index_schema = table.indexes.clone();
-            while let Some(index) = index_schema {
-                index_page_ids.push(index.root_page_id);
-                index_schema = index.next.clone();
+        let query_index_page_id = query_plan.index_page_id();
+        let mut indexes = Vec::new();
+        let mut index = table.indexes.as_ref();
+        while let Some(idx) = index {
+            if Some(idx.root_page_id) != query_index_page_id {
+                indexes.push(IndexSchema::create(idx));
             }
-            Ok(Box::new(ClearStatement {
-                conn: self,
-                table_page_id,
-                index_page_ids,
-            }))
+            index = idx.next.as_ref();
         }
-    }
 
-    fn start_read(&self) -> anyhow::Result<ReadTransaction> {
-        // TODO: Lock across processes
-        let ref_count = self.ref_count.get();
-        if ref_count >= 0 {
-            self.ref_count.set(ref_count + 1);
-            Ok(ReadTransaction(self))
-        } else {
-            bail!("write statment running");
-        }
+        Ok(Box::new(DeleteStatement {
+            table_page_id,
+            indexes,
+            child_foreign_keys,
+            filter: Rc::new(filter),
+            query_plan,
+        }))
     }
 
-    fn start_write(&self) -> anyhow::Result<WriteTransaction> {
-        // TODO: Lock across processes
-        if self.ref_count.get() == 0 {
-            self.ref_count.set(-1);
-            Ok(WriteTransaction {
-                conn: self,
-                do_commit: false,
-            })
-        } else {
-            bail!("other statments running");
+    fn prepare_update<'a>(&self, update: Update<'a>) -> Result<'a, UpdateStatement> {
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let functions = self.functions.borrow();
+        let table_name = update.table_name.dequote();
+        let table = schema.get_table(&table_name).ok_or(anyhow::anyhow!(
+            "table not found: {:?}",
+            std::str::from_utf8(&table_name).unwrap_or_default()
+        ))?;
+        if table.without_rowid {
+            return Err(Error::Unsupported("write to without rowid table"));
+        } else if table.has_unsupported_index() {
+            return Err(Error::Unsupported("write to table with unsupported index"));
+        }
+
+        let mut assignments = Vec::with_capacity(update.assignments.len());
+        let mut rowid = None;
+        for (column, expr) in update.assignments {
+            let column_name = column.dequote();
+            match table.get_column(&column_name) {
+                Some((ColumnNumber::Column(column_idx), type_affinity, _)) => {
+                    let expr =
+                        Expression::from(expr, &[(table_name.as_slice(), table)], &functions)?;
+                    // The last assignment wins if the same column is assigned twice.
+                    assignments.retain(|(idx, _, _)| *idx != column_idx);
+                    assignments.push((column_idx, expr, type_affinity));
+                }
+                Some((ColumnNumber::RowId, _, _)) => {
+                    rowid = Some(Expression::from(
+                        expr,
+                        &[(table_name.as_slice(), table)],
+                        &functions,
+                    )?);
+                }
+                None => {
+                    return Err(Error::Other(anyhow::anyhow!(
+                        "column not found: {:?}",
+                        std::str::from_utf8(&column_name).unwrap_or_default()
+                    )));
+                }
+            }
+        }
+
+        let filter = update
+            .filter
+            .map(|expr| Expression::from(expr, &[(table_name.as_slice(), table)], &functions))
+            .transpose()?
+            .unwrap_or(Expression::one());
+        let query_plan = QueryPlan::generate(table, &filter);
+
+        // Only indexes containing updated columns, or whose key expressions or WHERE
+        // clause refer to them, need to be updated. All the index entries refer to
+        // the rowid.
+        let mut indexes = Vec::new();
+        let mut index = table.indexes.as_ref();
+        while let Some(idx) = index {
+            let is_updated = |column_number: &ColumnNumber| {
+                assignments
+                    .iter()
+                    .any(|(column_idx, _, _)| *column_number == ColumnNumber::Column(*column_idx))
+            };
+            let mut expression_updated = false;
+            for expression in idx.expressions.iter().flatten().chain(&idx.predicate) {
+                expression.expr.for_each_column(&mut |column_number| {
+                    expression_updated |= is_updated(column_number);
+                });
+            }
+            if rowid.is_some()
+                || expression_updated
+                || idx
+                    .columns
+                    .iter()
+                    .any(|(column_number, _)| is_updated(column_number))
+            {
+                indexes.push(IndexSchema::create(idx));
+            }
+            index = idx.next.as_ref();
+        }
+
+        Ok(UpdateStatement {
+            table_page_id: table.root_page_id,
+            n_columns: table.columns.len(),
+            assignments,
+            rowid,
+            indexes,
+            not_null_columns: table.not_null_columns(),
+            datatypes: table.datatypes(),
+            checks: table.check_expressions(&table_name)?,
+            foreign_keys: table
+                .foreign_keys
+                .iter()
+                .map(|foreign_key| ForeignKeySchema::create(schema, table, foreign_key))
+                .collect(),
+            child_foreign_keys: schema
+                .get_child_foreign_keys(&table_name)
+                .into_iter()
+                .map(|(child, foreign_key)| ForeignKeySchema::create(schema, child, foreign_key))
+                .collect(),
+            filter: Rc::new(filter),
+            query_plan,
+        })
+    }
+
+    fn prepare_create_table<'a>(
+        &self,
+        create_table: CreateTable<'a>,
+        sql: &'a [u8],
+    ) -> Result<'a, CreateTableStatement> {
+        // The root page is allocated on execution.
+        let (table_name, table) = Table::new(create_table, PAGE_ID_1)?;
+        if is_reserved_name(&table_name) {
+            return Err(Error::Other(anyhow::anyhow!(
+                "object name reserved for internal use: {:?}",
+                std::str::from_utf8(&table_name).unwrap_or_default()
+            )));
+        }
+        // The primary key of WITHOUT ROWID tables takes a number of autoindexes
+        // but the table itself is the index.
+        let autoindexes = (0..table.autoindexes.len())
+            .filter(|i| !table.without_rowid || Some(*i) != table.primary_key_autoindex)
+            .map(|i| i + 1)
+            .collect();
+        Ok(CreateTableStatement {
+            table_name,
+            sql: sql.to_vec(),
+            without_rowid: table.without_rowid,
+            autoincrement: table.autoincrement,
+            autoindexes,
+        })
+    }
+
+    fn prepare_pragma<'a>(&self, pragma: Pragma<'a>) -> Result<'a, StatementKind> {
+        let mut name = pragma.name.dequote();
+        upper_to_lower(&mut name);
+        let (pragma, column_names): (_, &[&str]) = match (name.as_slice(), pragma.value) {
+            (b"user_version", None) => (PragmaQuery::UserVersion, &["user_version"]),
+            (b"user_version", Some(value)) => {
+                // SQLite truncates the value to 32 bits.
+                let user_version = eval_pragma_integer(value)? as i32;
+                return Ok(StatementKind::Execution(Box::new(UserVersionStatement {
+                    user_version,
+                })));
+            }
+            (b"page_size", None) => (PragmaQuery::PageSize, &["page_size"]),
+            (b"foreign_keys", None) => (PragmaQuery::ForeignKeys, &["foreign_keys"]),
+            (b"foreign_keys", Some(value)) => {
+                let enabled = eval_pragma_boolean(value)?;
+                return Ok(StatementKind::Execution(Box::new(ForeignKeysStatement {
+                    enabled,
+                })));
+            }
+            (b"page_size", Some(_)) => return Err(Error::Unsupported("changing page_size")),
+            (b"table_info", Some(value)) => {
+                let table_name = pragma_name_argument(value)?;
+                (
+                    PragmaQuery::Rows(self.table_info(&table_name)?),
+                    &["cid", "name", "type", "notnull", "dflt_value", "pk"],
+                )
+            }
+            (b"index_list", Some(value)) => {
+                let table_name = pragma_name_argument(value)?;
+                (
+                    PragmaQuery::Rows(self.index_list(&table_name)?),
+                    &["seq", "name", "unique", "origin", "partial"],
+                )
+            }
+            (b"index_info", Some(value)) => {
+                let index_name = pragma_name_argument(value)?;
+                (
+                    PragmaQuery::Rows(self.index_info(&index_name)?),
+                    &["seqno", "cid", "name"],
+                )
+            }
+            _ => return Err(Error::Unsupported("pragma")),
+        };
+        Ok(StatementKind::Pragma(PragmaStatement {
+            pragma,
+            column_names: column_names.iter().map(|name| name.to_string()).collect(),
+        }))
+    }
+
+    /// The rows of PRAGMA table_info.
+    ///
+    /// Returns no rows if the table does not exist.
+    fn table_info(&self, table_name: &[u8]) -> anyhow::Result<Vec<Vec<Option<ConstantValue>>>> {
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let Some(table) = schema.get_table(table_name) else {
+            return Ok(Vec::new());
+        };
+        Ok(table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                // The primary key of WITHOUT ROWID tables is implicitly NOT NULL.
+                let not_null = column.not_null || (table.without_rowid && column.primary_key);
+                let pk = table
+                    .primary_key
+                    .iter()
+                    .position(|pk| *pk == i)
+                    .map_or(0, |position| position + 1);
+                vec![
+                    Some(ConstantValue::Integer(i as i64)),
+                    Some(ConstantValue::Text(column.name.clone())),
+                    Some(ConstantValue::Text(column.declared_type.clone())),
+                    Some(ConstantValue::Integer(not_null as i64)),
+                    column.default.clone().map(ConstantValue::Text),
+                    Some(ConstantValue::Integer(pk as i64)),
+                ]
+            })
+            .collect())
+    }
+
+    /// The rows of PRAGMA index_list.
+    ///
+    /// The indexes are listed from the newest one like SQLite.
+    ///
+    /// TODO: List the primary key of WITHOUT ROWID tables which has no index
+    /// entry in sqlite_schema.
+    fn index_list(&self, table_name: &[u8]) -> anyhow::Result<Vec<Vec<Option<ConstantValue>>>> {
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let Some(table) = schema.get_table(table_name) else {
+            return Ok(Vec::new());
+        };
+        let primary_key = table
+            .primary_key
+            .iter()
+            .map(|i| table.column_number(*i))
+            .collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        let mut next = table.indexes.as_ref();
+        while let Some(index) = next {
+            let origin = if !index.name.starts_with(b"sqlite_autoindex_") {
+                "c"
+            } else if index
+                .columns
+                .iter()
+                .map(|(column_number, _)| *column_number)
+                .eq(primary_key.iter().copied())
+            {
+                "pk"
+            } else {
+                "u"
+            };
+            rows.push(vec![
+                Some(ConstantValue::Integer(rows.len() as i64)),
+                Some(ConstantValue::Text(index.name.clone())),
+                Some(ConstantValue::Integer(index.unique as i64)),
+                Some(ConstantValue::Text(origin.as_bytes().to_vec())),
+                Some(ConstantValue::Integer(index.predicate.is_some() as i64)),
+            ]);
+            next = index.next.as_ref();
+        }
+        Ok(rows)
+    }
+
+    /// The rows of PRAGMA index_info.
+    ///
+    /// Returns no rows if the index does not exist.
+    fn index_info(&self, index_name: &[u8]) -> anyhow::Result<Vec<Vec<Option<ConstantValue>>>> {
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let Some((table, index)) = schema.get_index_with_table(index_name) else {
+            return Ok(Vec::new());
+        };
+        Ok(index
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(seqno, (column_number, _))| {
+                // Expression keys have the cid -2 and no name like SQLite.
+                if index.expressions[seqno].is_some() {
+                    return vec![
+                        Some(ConstantValue::Integer(seqno as i64)),
+                        Some(ConstantValue::Integer(-2)),
+                        None,
+                    ];
+                }
+                let column_index = table.column_index(column_number);
+                vec![
+                    Some(ConstantValue::Integer(seqno as i64)),
+                    Some(ConstantValue::Integer(
+                        column_index.map_or(-1, |i| i as i64),
+                    )),
+                    column_index.map(|i| ConstantValue::Text(table.columns[i].name.clone())),
+                ]
+            })
+            .collect())
+    }
+
+    fn prepare_create_index<'a>(
+        &self,
+        create_index: CreateIndex<'a>,
+        sql: &'a [u8],
+    ) -> Result<'a, CreateIndexStatement> {
+        if self.schema.borrow().is_none() {
+            self.load_schema()?;
+        }
+        let schema_cell = self.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+        let index_name = create_index.index_name.dequote();
+        if is_reserved_name(&index_name) {
+            return Err(Error::Other(anyhow::anyhow!(
+                "object name reserved for internal use: {:?}",
+                std::str::from_utf8(&index_name).unwrap_or_default()
+            )));
+        }
+        let table_name = create_index.table_name.dequote();
+        let table = schema.get_table(&table_name).ok_or(anyhow::anyhow!(
+            "table not found: {:?}",
+            std::str::from_utf8(&table_name).unwrap_or_default()
+        ))?;
+        // sqlite_schema and the internal tables like sqlite_sequence and
+        // sqlite_stat1 are maintained by the database itself.
+        if is_reserved_name(&table_name) {
+            return Err(Error::Other(anyhow::anyhow!(
+                "table may not be indexed: {:?}",
+                std::str::from_utf8(&table_name).unwrap_or_default()
+            )));
+        } else if table.without_rowid {
+            return Err(Error::Unsupported("index on without rowid table"));
+        }
+        // The root page is allocated on execution.
+        let index = IndexSchema::create(&Index::new(&create_index, PAGE_ID_1, table)?);
+
+        Ok(CreateIndexStatement {
+            index_name,
+            table_name,
+            sql: sql.to_vec(),
+            table_page_id: table.root_page_id,
+            n_columns: table.columns.len(),
+            columns: index.columns,
+            expressions: index.expressions,
+            descending: index.descending,
+            unique: index.unique,
+            predicate: index.predicate,
+        })
+    }
+
+    /// Set the maximum number of pages kept in the page cache.
+    ///
+    /// Dirty pages are kept until commit even if the cache is full.
+    pub fn set_cache_size(&self, n_pages: usize) {
+        self.pager.set_cache_size(n_pages);
+    }
+
+    /// Statistics of the page cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.pager.cache_stats()
+    }
+
+    /// Set the number of leaf pages to read ahead when a full table scan moves
+    /// to the next leaf page. The pages are read in a batch into the page
+    /// cache. 0 disables readahead, which is the default.
+    pub fn set_readahead(&self, n_pages: u32) {
+        self.pager.set_readahead(n_pages);
+    }
+
+    /// The rowid of the last row inserted by INSERT on the connection, or 0 if
+    /// no row has been inserted.
+    pub fn last_insert_rowid(&self) -> i64 {
+        self.last_insert_rowid.get()
+    }
+
+    /// Number of rows inserted, updated or deleted by the last INSERT, UPDATE
+    /// or DELETE statement.
+    ///
+    /// The counter is restored to the value before [Connection::begin()] if the
+    /// transaction is rolled back.
+    pub fn changes(&self) -> u64 {
+        self.changes.get()
+    }
+
+    /// Number of rows inserted, updated or deleted since the connection was
+    /// opened.
+    ///
+    /// Changes made in a transaction which is rolled back are not counted.
+    pub fn total_changes(&self) -> u64 {
+        self.total_changes.get()
+    }
+
+    /// The application ID set by `PRAGMA application_id`.
+    pub fn application_id(&self) -> Result<'static, i32> {
+        let _read_txn = self.start_read()?;
+        Ok(self.read_header(|header| header.application_id())?)
+    }
+
+    /// The text encoding of the database.
+    pub fn text_encoding(&self) -> Result<'static, TextEncoding> {
+        let _read_txn = self.start_read()?;
+        self.read_header(|header| header.text_encoding())?
+            .ok_or_else(|| Error::Other(anyhow::anyhow!("unknown text encoding")))
+    }
+
+    /// The file change counter of the database, which is incremented on each
+    /// commit that modifies the database.
+    pub fn change_counter(&self) -> Result<'static, u32> {
+        let _read_txn = self.start_read()?;
+        Ok(self.read_header(|header| header.change_counter())?)
+    }
+
+    /// The version number of the SQLite library which most recently modified
+    /// the database (e.g. 3041002 for 3.41.2).
+    pub fn sqlite_version_number(&self) -> Result<'static, u32> {
+        let _read_txn = self.start_read()?;
+        Ok(self.read_header(|header| header.sqlite_version_number())?)
+    }
+
+    /// Record the number of rows changed by a completed statement.
+    fn record_changes(&self, n: u64) {
+        self.changes.set(n);
+        self.total_changes.set(self.total_changes.get() + n);
+    }
+
+    /// Start a read transaction which lasts until the returned [ReadSnapshot]
+    /// is dropped.
+    ///
+    /// All queries run while the snapshot is alive see the same data because
+    /// no write can start on the connection.
+    pub fn begin_read(&self) -> Result<'static, ReadSnapshot<'_>> {
+        Ok(ReadSnapshot {
+            _read_txn: self.start_read()?,
+        })
+    }
+
+    /// Start a write transaction which lasts until [Transaction::commit()] or
+    /// [Transaction::rollback()] is called, or the returned [Transaction] is
+    /// dropped.
+    ///
+    /// Statements executed while the transaction is alive do not commit their
+    /// changes on their own. If one of them fails, all the changes in the
+    /// transaction are discarded and the transaction can not be committed.
+    pub fn begin(&self) -> Result<'static, Transaction<'_>> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        if self.transaction.get() != TransactionState::None {
+            // Nested transactions are not supported.
+            return Err(Error::Busy);
+        }
+        if self.ref_count.get() != 0 {
+            return Err(Error::Busy);
+        }
+        self.refresh()?;
+        self.pager.lock_writer();
+        self.transaction.set(TransactionState::Active);
+        Ok(Transaction {
+            conn: self,
+            done: false,
+            changes: self.changes.get(),
+            total_changes: self.total_changes.get(),
+        })
+    }
+
+    fn start_read(&self) -> Result<'static, ReadTransaction<'_>> {
+        // TODO: Lock across processes
+        self.refresh()?;
+        let ref_count = self.ref_count.get();
+        if ref_count >= 0 {
+            self.ref_count.set(ref_count + 1);
+            Ok(ReadTransaction(self))
+        } else {
+            // A write statement is running.
+            Err(Error::Busy)
+        }
+    }
+
+    fn commit_pages(&self) -> Result<'static, ()> {
+        if self.pager.is_modified()? {
+            let page1 = self.pager.get_page(PAGE_ID_1)?;
+            let mut buffer = self.pager.make_page_mut(&page1)?;
+            let header_buf = &mut buffer[..DATABASE_HEADER_SIZE];
+            let mut header = DatabaseHeaderMut::from(header_buf.try_into().unwrap());
+            header.increment_change_counter();
+            header.set_n_pages(self.pager.num_pages());
+            drop(buffer);
+            drop(page1);
+        }
+
+        self.pager.commit()?;
+        Ok(())
+    }
+
+    fn start_write(&self) -> Result<'static, WriteTransaction<'_>> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        if self.transaction.get() == TransactionState::Aborted {
+            return Err(Error::TransactionAborted);
+        }
+        // TODO: Lock across processes
+        self.refresh()?;
+        if self.ref_count.get() == 0 {
+            self.ref_count.set(-1);
+            self.pager.lock_writer();
+            Ok(WriteTransaction {
+                conn: self,
+                do_commit: false,
+            })
+        } else {
+            Err(Error::Busy)
         }
     }
 }
 
 struct ReadTransaction<'a>(&'a Connection);
 
+/// A read transaction held across multiple queries.
+///
+/// The read transaction is released on drop.
+pub struct ReadSnapshot<'conn> {
+    _read_txn: ReadTransaction<'conn>,
+}
+
 impl Drop for ReadTransaction<'_> {
     fn drop(&mut self) {
         self.0.ref_count.set(self.0.ref_count.get() - 1);
@@ -439,372 +1903,2992 @@ struct WriteTransaction<'a> {
 }
 
 impl WriteTransaction<'_> {
-    fn commit(mut self) -> anyhow::Result<()> {
-        if self.conn.pager.is_file_size_changed() {
-            let page1 = self.conn.pager.get_page(PAGE_ID_1)?;
-            let mut buffer = self.conn.pager.make_page_mut(&page1)?;
-            let header_buf = &mut buffer[..DATABASE_HEADER_SIZE];
-            let mut header = DatabaseHeaderMut::from(header_buf.try_into().unwrap());
-            header.set_n_pages(self.conn.pager.num_pages());
-            drop(buffer);
-            drop(page1);
+    /// Commit the changes. The commit is deferred until [Transaction::commit()]
+    /// if a [Transaction] is running.
+    fn commit(mut self) -> Result<'static, ()> {
+        if self.conn.transaction.get() == TransactionState::None {
+            self.conn.commit_pages()?;
         }
-
-        self.conn.pager.commit()?;
         self.do_commit = true;
         Ok(())
     }
 }
 
-impl Drop for WriteTransaction<'_> {
+impl Drop for WriteTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.do_commit {
+            self.conn.pager.abort();
+            if self.conn.transaction.get() == TransactionState::Active {
+                self.conn.transaction.set(TransactionState::Aborted);
+            }
+        }
+        self.conn.ref_count.set(0);
+    }
+}
+
+/// A write transaction started by [Connection::begin()].
+///
+/// The changes are discarded on drop unless [Transaction::commit()] is called.
+pub struct Transaction<'conn> {
+    conn: &'conn Connection,
+    done: bool,
+    /// The change counters of the connection when the transaction started.
+    changes: u64,
+    total_changes: u64,
+}
+
+impl Transaction<'_> {
+    /// Write all the changes made in the transaction to the database file.
+    ///
+    /// The changes are discarded if this fails.
+    pub fn commit(mut self) -> Result<'static, ()> {
+        if self.conn.ref_count.get() != 0 {
+            return Err(Error::Busy);
+        }
+        if self.conn.transaction.get() == TransactionState::Aborted {
+            return Err(Error::TransactionAborted);
+        }
+        let result = self.conn.commit_pages();
+        if result.is_ok() {
+            self.done = true;
+            self.conn.transaction.set(TransactionState::None);
+        }
+        result
+    }
+
+    /// Discard all the changes made in the transaction.
+    pub fn rollback(self) {
+        // Dropping the transaction discards the changes.
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.conn.pager.abort();
+            self.conn.transaction.set(TransactionState::None);
+            self.conn.changes.set(self.changes);
+            self.conn.total_changes.set(self.total_changes);
+        }
+    }
+}
+
+pub trait ExecutionStatement {
+    fn execute(&self, conn: &Connection) -> Result<'static, u64>;
+
+    /// Set the value to the parameters of the 1-based index.
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>);
+
+    fn clone_box(&self) -> Box<dyn ExecutionStatement>;
+}
+
+impl Clone for Box<dyn ExecutionStatement> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone)]
+enum StatementKind {
+    Query(SelectStatement),
+    CompoundQuery(CompoundSelectStatement),
+    Pragma(PragmaStatement),
+    Execution(Box<dyn ExecutionStatement>),
+}
+
+/// A prepared statement.
+///
+/// A [Statement] borrows only the [Connection], and so do the [Rows] returned
+/// by [Statement::query]. The rows do not borrow the statement, so a statement
+/// can be kept in a struct and queried from its methods.
+///
+/// ```
+/// use prsqlite::Connection;
+/// use prsqlite::Rows;
+/// use prsqlite::Statement;
+/// use prsqlite::Value;
+///
+/// struct Lookup<'conn> {
+///     stmt: Statement<'conn>,
+/// }
+///
+/// impl<'conn> Lookup<'conn> {
+///     fn find(&mut self, min: i64) -> Rows<'conn> {
+///         self.stmt.bind(1, Some(Value::Integer(min))).unwrap();
+///         self.stmt.query().unwrap()
+///     }
+/// }
+///
+/// let conn = Connection::open_memory().unwrap();
+/// conn.execute_batch(
+///     "CREATE TABLE example(col); INSERT INTO example(col) VALUES (1), (2), (3);",
+/// )
+/// .unwrap();
+/// let mut lookup = Lookup {
+///     stmt: conn.prepare("SELECT col FROM example WHERE col >= ?;").unwrap(),
+/// };
+/// let rows = lookup.find(2);
+/// // Rebinding does not affect the rows already returned.
+/// let other_rows = lookup.find(3);
+/// drop(lookup);
+/// let values = rows
+///     .map(|row| row.unwrap().get(0).cloned())
+///     .collect::<Vec<_>>();
+/// assert_eq!(values, [Some(Value::Integer(2)), Some(Value::Integer(3))]);
+/// assert_eq!(other_rows.count(), 1);
+/// ```
+pub struct Statement<'conn> {
+    conn: &'conn Connection,
+    kind: StatementKind,
+    /// The schema cookie when the statement was prepared.
+    schema_cookie: u32,
+    n_parameters: usize,
+    /// Named parameters including the prefix and their indexes.
+    parameter_names: Vec<(Vec<u8>, usize)>,
+}
+
+impl<'conn> Statement<'conn> {
+    /// Whether the statement returns rows.
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self.kind,
+            StatementKind::Query(_) | StatementKind::CompoundQuery(_) | StatementKind::Pragma(_)
+        )
+    }
+
+    /// Run the query from the beginning.
+    ///
+    /// The plan built on prepare is reused. The returned [Rows] shares the plan
+    /// with the statement and outlives the statement. Parameters bound after
+    /// this call do not affect the returned [Rows]. The [Rows] holds a read
+    /// transaction until it is dropped, so writes on the same connection fail
+    /// while it is alive.
+    pub fn query(&self) -> Result<'static, Rows<'conn>> {
+        self.check_schema()?;
+        match &self.kind {
+            StatementKind::Query(stmt) => stmt.query(self.conn),
+            StatementKind::CompoundQuery(stmt) => stmt.query(self.conn),
+            StatementKind::Pragma(stmt) => stmt.query(self.conn),
+            StatementKind::Execution(_) => {
+                Err(Error::Unsupported("execute statement not support query"))
+            }
+        }
+    }
+
+    /// Execute the statement. This can be called multiple times.
+    pub fn execute(&self) -> Result<'static, u64> {
+        self.check_schema()?;
+        match &self.kind {
+            StatementKind::Query(_)
+            | StatementKind::CompoundQuery(_)
+            | StatementKind::Pragma(_) => {
+                Err(Error::Unsupported("select statement not support execute"))
+            }
+            StatementKind::Execution(stmt) => stmt.execute(self.conn),
+        }
+    }
+
+    /// Fail if the schema changed after the statement was prepared because the
+    /// statement may refer to stale root pages.
+    fn check_schema(&self) -> Result<'static, ()> {
+        if self.conn.schema_cookie()? != self.schema_cookie {
+            return Err(Error::SchemaChanged);
+        }
+        Ok(())
+    }
+
+    /// The names of the result columns. Empty unless the statement is a query.
+    pub fn column_names(&self) -> &[String] {
+        match &self.kind {
+            StatementKind::Query(stmt) => &stmt.column_names,
+            StatementKind::CompoundQuery(stmt) => &stmt.column_names,
+            StatementKind::Pragma(stmt) => &stmt.column_names,
+            StatementKind::Execution(_) => &[],
+        }
+    }
+
+    /// Describe the plan of the query chosen on prepare.
+    ///
+    /// This is for diagnostics and not compatible with EXPLAIN of SQLite.
+    pub fn explain(&self) -> Result<'static, PlanDescription> {
+        self.check_schema()?;
+        match &self.kind {
+            StatementKind::Query(stmt) => stmt.explain(self.conn),
+            StatementKind::CompoundQuery(_) => Err(Error::Unsupported("explain compound select")),
+            StatementKind::Pragma(_) | StatementKind::Execution(_) => {
+                Err(Error::Unsupported("explain non-select statement"))
+            }
+        }
+    }
+
+    /// The largest index of the parameters in the statement.
+    pub fn parameter_count(&self) -> usize {
+        self.n_parameters
+    }
+
+    /// The index of the named parameter. The name includes the prefix (e.g.
+    /// `:name`).
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameter_names
+            .iter()
+            .find(|(parameter_name, _)| parameter_name == name.as_bytes())
+            .map(|(_, idx)| *idx)
+    }
+
+    /// Bind the value to the parameter of the 1-based index.
+    ///
+    /// Parameters not bound are NULL. [None] binds NULL.
+    pub fn bind(&mut self, idx: usize, value: Option<Value>) -> Result<'static, ()> {
+        if idx < 1 || idx > self.n_parameters {
+            return Err(Error::Other(anyhow::anyhow!(
+                "parameter index out of range: {}",
+                idx
+            )));
+        }
+        self.bind_parameter(idx, &value.map(ConstantValue::copy_from));
+        Ok(())
+    }
+
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        match &mut self.kind {
+            StatementKind::Query(stmt) => stmt.bind_parameter(idx, value),
+            StatementKind::CompoundQuery(stmt) => stmt.bind_parameter(idx, value),
+            // PRAGMA has no parameters.
+            StatementKind::Pragma(_) => {}
+            StatementKind::Execution(stmt) => stmt.bind_parameter(idx, value),
+        }
+    }
+
+    /// Reset the statement so that the next run starts from the beginning.
+    /// The bound parameters are kept. Use [Statement::clear_bindings()] to
+    /// unbind them.
+    ///
+    /// The statement keeps no execution state because each
+    /// [Statement::query()] and [Statement::execute()] starts from the
+    /// beginning with new cursors. The [Rows] returned by [Statement::query()]
+    /// own their cursors and the read transaction, which this does not end.
+    /// Writes on the connection fail with [Error::Busy] until the [Rows] are
+    /// dropped.
+    pub fn reset(&mut self) {}
+
+    /// Unbind all the parameters to NULL.
+    pub fn clear_bindings(&mut self) {
+        for idx in 1..=self.n_parameters {
+            self.bind_parameter(idx, &None);
+        }
+    }
+
+    /// Bind the value to the named parameter.
+    pub fn bind_named(&mut self, name: &str, value: Option<Value>) -> Result<'static, ()> {
+        let Some(idx) = self.parameter_index(name) else {
+            return Err(Error::Other(anyhow::anyhow!(
+                "parameter not found: {}",
+                name
+            )));
+        };
+        self.bind(idx, value)
+    }
+}
+
+/// A [Statement] without the [Connection] kept in [StatementCache].
+#[derive(Clone)]
+struct CachedStatement {
+    kind: StatementKind,
+    schema_cookie: u32,
+    n_parameters: usize,
+    parameter_names: Vec<(Vec<u8>, usize)>,
+}
+
+impl CachedStatement {
+    fn into_statement(self, conn: &Connection) -> Statement<'_> {
+        Statement {
+            conn,
+            kind: self.kind,
+            schema_cookie: self.schema_cookie,
+            n_parameters: self.n_parameters,
+            parameter_names: self.parameter_names,
+        }
+    }
+}
+
+impl From<&Statement<'_>> for CachedStatement {
+    fn from(statement: &Statement) -> Self {
+        Self {
+            kind: statement.kind.clone(),
+            schema_cookie: statement.schema_cookie,
+            n_parameters: statement.n_parameters,
+            parameter_names: statement.parameter_names.clone(),
+        }
+    }
+}
+
+/// Prepared statements keyed by their SQL with LRU eviction.
+struct StatementCache {
+    /// The statements and the time they were last used.
+    statements: HashMap<String, (CachedStatement, u64)>,
+    clock: u64,
+    capacity: usize,
+    stats: CacheStats,
+}
+
+impl StatementCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            statements: HashMap::new(),
+            clock: 0,
+            capacity,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Get a copy of the cached statement. The statement prepared with another
+    /// schema is discarded.
+    fn get(&mut self, sql: &str, schema_cookie: u32) -> Option<CachedStatement> {
+        if self.capacity == 0 {
+            return None;
+        }
+        self.clock += 1;
+        match self.statements.get_mut(sql) {
+            Some((statement, last_used)) if statement.schema_cookie == schema_cookie => {
+                self.stats.hits += 1;
+                *last_used = self.clock;
+                Some(statement.clone())
+            }
+            Some(_) => {
+                self.stats.misses += 1;
+                self.statements.remove(sql);
+                None
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, sql: &str, statement: CachedStatement) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.statements
+            .insert(sql.to_string(), (statement, self.clock));
+        self.evict();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Evict least recently used statements until the number of statements
+    /// fits in the capacity.
+    fn evict(&mut self) {
+        while self.statements.len() > self.capacity {
+            let oldest = self
+                .statements
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(sql, _)| sql.clone())
+                .unwrap();
+            self.statements.remove(&oldest);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.statements.clear();
+    }
+}
+
+/// Expand `*` or `table.*` to the columns of the `i`th table.
+///
+/// The last table is the inner table of a join and the others are outer.
+fn add_all_columns<'a>(
+    tables: &[(&[u8], &'a Table)],
+    i: usize,
+    columns: &mut Vec<Expression>,
+    column_names: &mut Vec<Cow<'a, [u8]>>,
+    aggregation: &mut Aggregation,
+) {
+    let table = tables[i].1;
+    let is_outer = i + 1 < tables.len();
+    for column in table.get_all_columns() {
+        column_names.push(Cow::Borrowed(table.get_column_name(&column.0)));
+        if is_outer {
+            columns.push(Expression::OuterColumn(column));
+        } else {
+            if !aggregation.columns.contains(&column.0) {
+                aggregation.columns.push(column.0);
+            }
+            columns.push(Expression::Column(column));
+        }
+    }
+}
+
+/// An ORDER BY term.
+#[derive(Clone)]
+struct SortKey {
+    expr: Expression,
+    desc: bool,
+    nulls_first: bool,
+}
+
+/// An ORDER BY term of a compound SELECT which refers to a result column.
+#[derive(Clone)]
+struct CompoundSortKey {
+    column: usize,
+    collation: Collation,
+    desc: bool,
+    nulls_first: bool,
+}
+
+/// Compare the values of an ORDER BY term.
+///
+/// DESC reverses the order of non-NULL values. NULLs are placed first or last
+/// regardless of the direction.
+fn compare_sort_values(
+    v1: &Option<ConstantValue>,
+    v2: &Option<ConstantValue>,
+    collation: &Collation,
+    desc: bool,
+    nulls_first: bool,
+) -> Ordering {
+    match (v1, v2) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) if nulls_first => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) if nulls_first => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(v1), Some(v2)) => {
+            let ordering = ValueCmp::new(&v1.as_value(), collation).compare(&v2.as_value());
+            if desc {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+    }
+}
+
+/// Compare rows column by column. NULLs are equal to each other and smaller
+/// than any other value.
+fn compare_rows(
+    row1: &[Option<ConstantValue>],
+    row2: &[Option<ConstantValue>],
+    collations: &[Collation],
+) -> Ordering {
+    for ((v1, v2), collation) in row1.iter().zip(row2.iter()).zip(collations.iter()) {
+        let ordering = compare_sort_values(v1, v2, collation, false, true);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// The values of the ORDER BY terms and the result columns of a row.
+type EvaluatedRow = (Vec<Option<ConstantValue>>, Vec<Option<ConstantValue>>);
+
+/// The plan of a query returned by [Statement::explain()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanDescription {
+    /// The tables in the order of the nested loops. The first one is the
+    /// outermost.
+    pub tables: Vec<TablePlan>,
+    /// Whether the rows are sorted for ORDER BY after the scan.
+    pub sort: bool,
+    pub aggregate: bool,
+    /// The plans of the subqueries in the WHERE clause.
+    pub subqueries: Vec<PlanDescription>,
+}
+
+/// How a table is read by a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TablePlan {
+    pub table_name: String,
+    pub root_page: u32,
+    /// The inner table of a join is read by this for each outer row.
+    pub access: AccessMethod,
+}
+
+#[derive(Clone)]
+pub struct SelectStatement {
+    table_page_id: PageId,
+    columns: Rc<[Expression]>,
+    column_names: Rc<[String]>,
+    filter: Rc<Expression>,
+    query_plan: QueryPlan,
+    /// Empty if no sort is needed.
+    order_by: Vec<SortKey>,
+    /// The collations of the result columns to remove duplicated rows for
+    /// SELECT DISTINCT. [None] without DISTINCT.
+    distinct: Option<Vec<Collation>>,
+    aggregation: Option<Box<Aggregation>>,
+    /// The plan of the inner table and the ON clause if the query is a join.
+    join: Option<(Rc<JoinPlan>, Rc<Expression>)>,
+    /// The subqueries in the WHERE clause referred by their indexes.
+    subqueries: Vec<SelectStatement>,
+}
+
+impl SelectStatement {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        table_page_id: PageId,
+        columns: Vec<Expression>,
+        column_names: Rc<[String]>,
+        filter: Expression,
+        query_plan: QueryPlan,
+        order_by: Vec<SortKey>,
+        aggregation: Option<Box<Aggregation>>,
+    ) -> Self {
+        Self {
+            table_page_id,
+            columns: columns.into(),
+            column_names,
+            filter: Rc::new(filter),
+            query_plan,
+            order_by,
+            distinct: None,
+            aggregation,
+            join: None,
+            subqueries: Vec::new(),
+        }
+    }
+
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        // Rows already returned keep the values bound when they started.
+        for expr in Rc::make_mut(&mut self.columns).iter_mut() {
+            expr.bind_parameter(idx, value);
+        }
+        Rc::make_mut(&mut self.filter).bind_parameter(idx, value);
+        if let Some((_, constraint)) = &mut self.join {
+            Rc::make_mut(constraint).bind_parameter(idx, value);
+        }
+        for subquery in self.subqueries.iter_mut() {
+            subquery.bind_parameter(idx, value);
+        }
+        for key in self.order_by.iter_mut() {
+            key.expr.bind_parameter(idx, value);
+        }
+        if let Some(aggregation) = &mut self.aggregation {
+            aggregation.bind_parameter(idx, value);
+        }
+    }
+
+    pub fn query<'conn>(&self, conn: &'conn Connection) -> Result<'static, Rows<'conn>> {
+        let read_txn = conn.start_read()?;
+        let filter = self.bind_subqueries(conn)?;
+
+        if let Some((plan, constraint)) = &self.join {
+            let outer = Query::new(
+                self.table_page_id,
+                &conn.pager,
+                &conn.btree_ctx,
+                &self.query_plan,
+                Rc::new(Expression::one()),
+            )?;
+            let mut join = JoinQuery::new(
+                outer,
+                plan.clone(),
+                constraint.clone(),
+                filter,
+                &conn.pager,
+                &conn.btree_ctx,
+            );
+            let buffered_rows = if self.order_by.is_empty() && self.distinct.is_none() {
+                None
+            } else {
+                let mut collations = Vec::with_capacity(self.order_by.len());
+                let mut rows = Vec::new();
+                while let Some(row) = join.next(|row| Ok(self.evaluate(row, &mut collations)?))? {
+                    rows.push(row);
+                }
+                Some(self.sort(rows, &collations).into_iter())
+            };
+            return Ok(Rows {
+                _read_txn: read_txn,
+                columns: self.columns.clone(),
+                column_names: self.column_names.clone(),
+                query: None,
+                join: buffered_rows.is_none().then_some(join),
+                buffered_rows,
+                compound: Vec::new(),
+            });
+        }
+
+        let mut query = Query::new(
+            self.table_page_id,
+            &conn.pager,
+            &conn.btree_ctx,
+            &self.query_plan,
+            filter.clone(),
+        )?;
+
+        let buffered_rows = if let Some(aggregation) = &self.aggregation {
+            let groups = if aggregation.is_count_all()
+                && matches!(
+                    filter.as_ref(),
+                    Expression::Const(ConstantValue::Integer(1))
+                ) {
+                // Count the cells of the table b-tree without parsing the records.
+                let mut cursor =
+                    BtreeCursor::new(self.table_page_id, &conn.pager, &conn.btree_ctx)?;
+                vec![aggregation.count_all(cursor.count()?)]
+            } else {
+                aggregation.run(&mut query)?
+            };
+            let mut collations = Vec::with_capacity(self.order_by.len());
+            let mut rows = Vec::with_capacity(groups.len());
+            for group in groups.iter() {
+                rows.push(self.evaluate(group, &mut collations)?);
+            }
+            Some(self.sort(rows, &collations).into_iter())
+        } else if self.order_by.is_empty() && self.distinct.is_none() {
+            None
+        } else {
+            let mut collations = Vec::with_capacity(self.order_by.len());
+            let mut rows = Vec::new();
+            while let Some(data) = query.next()? {
+                rows.push(self.evaluate(&data, &mut collations)?);
+            }
+            Some(self.sort(rows, &collations).into_iter())
+        };
+
+        Ok(Rows {
+            _read_txn: read_txn,
+            columns: self.columns.clone(),
+            column_names: self.column_names.clone(),
+            query: Some(query),
+            buffered_rows,
+            join: None,
+            compound: Vec::new(),
+        })
+    }
+
+    fn explain(&self, conn: &Connection) -> Result<'static, PlanDescription> {
+        if conn.schema.borrow().is_none() {
+            conn.load_schema()?;
+        }
+        let schema_cell = conn.schema.borrow();
+        Ok(self.describe(schema_cell.as_ref().unwrap()))
+    }
+
+    fn describe(&self, schema: &Schema) -> PlanDescription {
+        let get_name = |page_id| {
+            schema
+                .get_name_by_root_page(page_id)
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let mut tables = vec![TablePlan {
+            table_name: get_name(self.table_page_id),
+            root_page: self.table_page_id.get(),
+            access: self.query_plan.access_method(get_name),
+        }];
+        if let Some((plan, _)) = &self.join {
+            tables.push(TablePlan {
+                table_name: get_name(plan.inner_page_id()),
+                root_page: plan.inner_page_id().get(),
+                access: plan.access_method(get_name),
+            });
+        }
+        PlanDescription {
+            tables,
+            sort: !self.order_by.is_empty(),
+            aggregate: self.aggregation.is_some(),
+            subqueries: self
+                .subqueries
+                .iter()
+                .map(|subquery| subquery.describe(schema))
+                .collect(),
+        }
+    }
+
+    /// Run the subqueries and bind their results to the WHERE clause.
+    ///
+    /// The subqueries do not refer to the outer query, so each of them runs
+    /// only once.
+    fn bind_subqueries(&self, conn: &Connection) -> Result<'static, Rc<Expression>> {
+        if self.subqueries.is_empty() {
+            return Ok(self.filter.clone());
+        }
+        let mut filter = self.filter.as_ref().clone();
+        for (idx, subquery) in self.subqueries.iter().enumerate() {
+            let rows = collect_rows(subquery.query(conn)?)?;
+            filter.bind_subquery(idx, &subquery.columns[0], &rows)?;
+        }
+        Ok(Rc::new(filter))
+    }
+
+    /// Evaluate the ORDER BY terms and the result columns.
+    ///
+    /// The collation of each ORDER BY term does not depend on rows and is
+    /// taken from the first row.
+    fn evaluate<D: DataContext>(
+        &self,
+        data: &D,
+        collations: &mut Vec<Collation>,
+    ) -> expression::Result<EvaluatedRow> {
+        let mut keys = Vec::with_capacity(self.order_by.len());
+        for key in self.order_by.iter() {
+            let (value, _, collation) = key.expr.execute(Some(data))?;
+            keys.push(value.map(ConstantValue::copy_from));
+            if collations.len() < self.order_by.len() {
+                collations.push(
+                    collation.map_or(DEFAULT_COLLATION.clone(), |(collation, _)| {
+                        collation.clone()
+                    }),
+                );
+            }
+        }
+        let mut columns = Vec::with_capacity(self.columns.len());
+        for expr in self.columns.iter() {
+            let (value, _, _) = expr.execute(Some(data))?;
+            columns.push(value.map(ConstantValue::copy_from));
+        }
+        Ok((keys, columns))
+    }
+
+    /// Remove the duplicated rows for DISTINCT, sort the rows by the ORDER BY
+    /// terms and return the result columns.
+    fn sort(
+        &self,
+        mut rows: Vec<EvaluatedRow>,
+        collations: &[Collation],
+    ) -> Vec<Vec<Option<ConstantValue>>> {
+        if let Some(distinct) = &self.distinct {
+            // The first one of equal rows is kept in the original order.
+            let mut order = (0..rows.len()).collect::<Vec<_>>();
+            order.sort_by(|i, j| compare_rows(&rows[*i].1, &rows[*j].1, distinct));
+            let mut duplicated = vec![false; rows.len()];
+            for pair in order.windows(2) {
+                duplicated[pair[1]] =
+                    compare_rows(&rows[pair[0]].1, &rows[pair[1]].1, distinct) == Ordering::Equal;
+            }
+            let mut duplicated = duplicated.into_iter();
+            rows.retain(|_| !duplicated.next().unwrap());
+        }
+        if !self.order_by.is_empty() {
+            rows.sort_by(|(keys1, _), (keys2, _)| {
+                for (((key1, key2), collation), key) in keys1
+                    .iter()
+                    .zip(keys2.iter())
+                    .zip(collations.iter())
+                    .zip(self.order_by.iter())
+                {
+                    let ordering =
+                        compare_sort_values(key1, key2, collation, key.desc, key.nulls_first);
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                Ordering::Equal
+            });
+        }
+        rows.into_iter().map(|(_, columns)| columns).collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct CompoundSelectStatement {
+    /// The names of the result columns of the first SELECT.
+    column_names: Rc<[String]>,
+    /// The collations of the result columns of the first SELECT to compare
+    /// rows.
+    collations: Vec<Collation>,
+    /// The ORDER BY terms after the last SELECT. Empty if no sort is needed.
+    order_by: Vec<CompoundSortKey>,
+    first: SelectStatement,
+    rest: Vec<(CompoundOperator, SelectStatement)>,
+}
+
+impl CompoundSelectStatement {
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        self.first.bind_parameter(idx, value);
+        for (_, select) in self.rest.iter_mut() {
+            select.bind_parameter(idx, value);
+        }
+    }
+
+    /// Run the SELECTs.
+    ///
+    /// UNION, INTERSECT and EXCEPT return the distinct rows sorted in
+    /// ascending order of the result columns. NULLs come first. UNION ALL
+    /// appends the rows of the right SELECT as they are. ORDER BY sorts the
+    /// whole result at the end.
+    pub fn query<'conn>(&self, conn: &'conn Connection) -> Result<'static, Rows<'conn>> {
+        let read_txn = conn.start_read()?;
+        // The SELECTs up to the last operator which removes duplicates are
+        // evaluated in advance. The following SELECTs are streamed.
+        let n_buffered = self
+            .rest
+            .iter()
+            .rposition(|(operator, _)| *operator != CompoundOperator::UnionAll)
+            .map_or(0, |i| i + 1);
+        let mut compound = Vec::with_capacity(self.rest.len() + 1);
+        if n_buffered == 0 {
+            compound.push(self.first.query(conn)?);
+        } else {
+            let mut rows = collect_rows(self.first.query(conn)?)?;
+            for (operator, select) in self.rest[..n_buffered].iter() {
+                let mut right = collect_rows(select.query(conn)?)?;
+                match operator {
+                    CompoundOperator::UnionAll => rows.append(&mut right),
+                    CompoundOperator::Union => {
+                        rows.append(&mut right);
+                        self.sort_distinct(&mut rows);
+                    }
+                    CompoundOperator::Intersect | CompoundOperator::Except => {
+                        self.sort_distinct(&mut rows);
+                        self.sort_distinct(&mut right);
+                        let intersect = *operator == CompoundOperator::Intersect;
+                        rows.retain(|row| {
+                            right
+                                .binary_search_by(|right_row| {
+                                    compare_rows(right_row, row, &self.collations)
+                                })
+                                .is_ok()
+                                == intersect
+                        });
+                    }
+                }
+            }
+            compound.push(Rows {
+                _read_txn: conn.start_read()?,
+                columns: Rc::new([]),
+                column_names: self.column_names.clone(),
+                query: None,
+                buffered_rows: Some(rows.into_iter()),
+                join: None,
+                compound: Vec::new(),
+            });
+        }
+        for (_, select) in self.rest[n_buffered..].iter() {
+            compound.push(select.query(conn)?);
+        }
+        compound.reverse();
+        let rows = Rows {
+            _read_txn: read_txn,
+            columns: Rc::new([]),
+            column_names: self.column_names.clone(),
+            query: None,
+            buffered_rows: None,
+            join: None,
+            compound,
+        };
+        if self.order_by.is_empty() {
+            return Ok(rows);
+        }
+
+        let mut rows = collect_rows(rows)?;
+        rows.sort_by(|row1, row2| {
+            for key in self.order_by.iter() {
+                let ordering = compare_sort_values(
+                    &row1[key.column],
+                    &row2[key.column],
+                    &key.collation,
+                    key.desc,
+                    key.nulls_first,
+                );
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        Ok(Rows {
+            _read_txn: conn.start_read()?,
+            columns: Rc::new([]),
+            column_names: self.column_names.clone(),
+            query: None,
+            buffered_rows: Some(rows.into_iter()),
+            join: None,
+            compound: Vec::new(),
+        })
+    }
+
+    /// Sort the rows and remove duplicates. The last one of equal rows is kept
+    /// as SQLite does.
+    fn sort_distinct(&self, rows: &mut Vec<Vec<Option<ConstantValue>>>) {
+        rows.reverse();
+        rows.sort_by(|row1, row2| compare_rows(row1, row2, &self.collations));
+        rows.dedup_by(|row, prev| compare_rows(prev, row, &self.collations) == Ordering::Equal);
+    }
+}
+
+/// Copy the values of all the rows.
+fn collect_rows(mut rows: Rows) -> Result<'static, Vec<Vec<Option<ConstantValue>>>> {
+    let mut values = Vec::new();
+    while let Some(row) = rows.next_row()? {
+        values.push(row.copy_values()?);
+    }
+    Ok(values)
+}
+
+/// The rows of a query. This borrows the [Connection] but not the [Statement].
+pub struct Rows<'conn> {
+    _read_txn: ReadTransaction<'conn>,
+    columns: Rc<[Expression]>,
+    column_names: Rc<[String]>,
+    /// None if all the rows are buffered or the rows are of a join or a
+    /// compound SELECT.
+    query: Option<Query<'conn>>,
+    buffered_rows: Option<std::vec::IntoIter<Vec<Option<ConstantValue>>>>,
+    join: Option<JoinQuery<'conn>>,
+    /// The rows of each SELECT of a compound SELECT in reverse order.
+    compound: Vec<Rows<'conn>>,
+}
+
+impl<'conn> Rows<'conn> {
+    pub fn next_row(&mut self) -> Result<'static, Option<Row<'_>>> {
+        if let Some(join) = &mut self.join {
+            // The result columns are evaluated while the inner row is available.
+            let columns = &self.columns;
+            let values = join.next(|row| {
+                let mut values = Vec::with_capacity(columns.len());
+                for expr in columns.iter() {
+                    let (value, _, _) = expr.execute(Some(row))?;
+                    values.push(value.map(ConstantValue::copy_from));
+                }
+                Ok(values)
+            })?;
+            return Ok(values.map(|values| Row {
+                columns: &self.columns,
+                column_names: &self.column_names,
+                data: RowSource::Buffered(values),
+            }));
+        }
+        if self.query.is_none() && self.buffered_rows.is_none() {
+            // The values are copied because the row of a SELECT in the compound
+            // can not be returned after the previous SELECT is exhausted.
+            while let Some(rows) = self.compound.last_mut() {
+                if let Some(row) = rows.next_row()? {
+                    let columns = row.copy_values()?;
+                    return Ok(Some(Row {
+                        columns: &self.columns,
+                        column_names: &self.column_names,
+                        data: RowSource::Buffered(columns),
+                    }));
+                }
+                self.compound.pop();
+            }
+            return Ok(None);
+        }
+        if let Some(buffered_rows) = &mut self.buffered_rows {
+            return Ok(buffered_rows.next().map(|columns| Row {
+                columns: &self.columns,
+                column_names: &self.column_names,
+                data: RowSource::Buffered(columns),
+            }));
+        }
+        if let Some(data) = self.query.as_mut().unwrap().next()? {
+            Ok(Some(Row {
+                columns: &self.columns,
+                column_names: &self.column_names,
+                data: RowSource::Data(data),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Iterates rows with their values copied out so that each row outlives the
+/// iteration step.
+impl Iterator for Rows<'_> {
+    type Item = Result<'static, OwnedRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_row() {
+            Ok(Some(row)) => Some(row.parse().map(Columns::into_owned)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+enum RowSource<'a> {
+    Data(RowData<'a>),
+    /// The result columns evaluated in advance for sorting or aggregation.
+    Buffered(Vec<Option<ConstantValue>>),
+}
+
+pub struct Row<'a> {
+    columns: &'a [Expression],
+    column_names: &'a Rc<[String]>,
+    data: RowSource<'a>,
+}
+
+impl<'a> Row<'a> {
+    /// Evaluate the result columns of the row.
+    ///
+    /// Text and blob values read from the table reference the page buffer, or
+    /// the buffer of the row if the payload spans overflow pages. Use
+    /// [Columns::into_owned()] to keep them after the [Rows] advances.
+    pub fn parse(&self) -> Result<'static, Columns<'_>> {
+        match &self.data {
+            RowSource::Data(data) => {
+                let mut values = Vec::with_capacity(self.columns.len());
+                for expr in self.columns.iter() {
+                    let (value, _, _) = expr.execute(Some(data))?;
+                    values.push(value);
+                }
+                Ok(Columns {
+                    values,
+                    names: self.column_names.clone(),
+                })
+            }
+            RowSource::Buffered(columns) => Ok(Columns {
+                values: columns
+                    .iter()
+                    .map(|v| v.as_ref().map(|v| v.as_value()))
+                    .collect(),
+                names: self.column_names.clone(),
+            }),
+        }
+    }
+
+    fn copy_values(&self) -> Result<'static, Vec<Option<ConstantValue>>> {
+        Ok(self
+            .parse()?
+            .values
+            .into_iter()
+            .map(|v| v.map(ConstantValue::copy_from))
+            .collect())
+    }
+
+    /// Deserialize the row into `T` using the column names as the field names.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<'static, T> {
+        let columns = self.parse()?;
+        columns
+            .deserialize()
+            .map_err(|e| Error::Other(anyhow::Error::from(e)))
+    }
+}
+
+pub struct Columns<'a> {
+    values: Vec<Option<Value<'a>>>,
+    names: Rc<[String]>,
+}
+
+/// Columns which own their values.
+pub type OwnedRow = Columns<'static>;
+
+impl<'a> Columns<'a> {
+    /// Copy the borrowed text and blob values. Values which are already owned
+    /// (e.g. results of functions) are moved without copying.
+    pub fn into_owned(self) -> OwnedRow {
+        Columns {
+            values: self
+                .values
+                .into_iter()
+                .map(|v| v.map(Value::into_owned))
+                .collect(),
+            names: self.names,
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&Value<'a>> {
+        if let Some(Some(v)) = self.values.get(i) {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// Get the bytes of a text or blob column without copying.
+    ///
+    /// Values stored in the page of the row reference the page buffer
+    /// directly. A copy is unavoidable only when the row spans overflow pages;
+    /// then the bytes reference the buffer of the row which the content is
+    /// loaded into. Returns [None] for NULL, integer and real values.
+    pub fn get_bytes(&self, i: usize) -> Option<&[u8]> {
+        match self.get(i)? {
+            Value::Text(buf) | Value::Blob(buf) => Some(buf),
+            _ => None,
+        }
+    }
+
+    /// Get the value of the first column with the name.
+    ///
+    /// The name is compared case-insensitively for ASCII characters.
+    pub fn get_by_name(&self, name: &str) -> Option<&Value<'a>> {
+        self.names
+            .iter()
+            .position(|column_name| column_name.eq_ignore_ascii_case(name))
+            .and_then(|i| self.get(i))
+    }
+
+    pub fn column_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Option<Value<'a>>> {
+        self.values.iter()
+    }
+
+    /// Deserialize the columns into `T` using the column names as the field
+    /// names.
+    ///
+    /// Text and blob values can be borrowed by `T`.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<'de, T: serde::Deserialize<'de>>(
+        &'de self,
+    ) -> std::result::Result<T, DeserializeError> {
+        T::deserialize(de::ColumnsDeserializer::new(self))
+    }
+}
+
+#[derive(Clone)]
+struct InsertRecord {
+    rowid: Option<Expression>,
+    columns: Vec<(Expression, TypeAffinity)>,
+}
+
+/// The row of sqlite_sequence for an AUTOINCREMENT table.
+#[derive(Clone)]
+struct Sequence {
+    /// The root page of sqlite_sequence.
+    root_page_id: PageId,
+    table_name: Vec<u8>,
+}
+
+impl Sequence {
+    /// Returns the rowid of the row in sqlite_sequence and the largest rowid
+    /// ever used by the table. The largest rowid is 0 if there is no row.
+    fn load(&self, conn: &Connection) -> Result<'static, (Option<i64>, i64)> {
+        let mut cursor = BtreeCursor::new(self.root_page_id, &conn.pager, &conn.btree_ctx)?;
+        cursor.move_to_first()?;
+        loop {
+            let seq = {
+                let Some((rowid, payload)) = cursor.get_table_payload()? else {
+                    return Ok((None, 0));
+                };
+                let mut record = parse_record(&payload)?;
+                match record.get(0)? {
+                    Some(Value::Text(name)) if *name == *self.table_name => {
+                        Some((Some(rowid), record.get(1)?.map_or(0, |v| v.as_integer())))
+                    }
+                    _ => None,
+                }
+            };
+            if let Some(seq) = seq {
+                return Ok(seq);
+            }
+            cursor.move_next()?;
+        }
+    }
+
+    /// Record the largest rowid. A new row is appended if `rowid` is [None].
+    fn save(&self, conn: &Connection, rowid: Option<i64>, seq: i64) -> Result<'static, ()> {
+        let mut cursor = BtreeCursor::new(self.root_page_id, &conn.pager, &conn.btree_ctx)?;
+        let rowid = match rowid {
+            Some(rowid) => rowid,
+            None => {
+                cursor.move_to_last()?;
+                cursor.get_table_key()?.unwrap_or(0) + 1
+            }
+        };
+        let name = Value::Text(self.table_name.as_slice().into());
+        let seq = Value::Integer(seq);
+        cursor.table_insert(rowid, &RecordPayload::new(&[Some(&name), Some(&seq)])?)?;
+        Ok(())
+    }
+}
+
+/// The values of a row being written.
+struct RowValues<'a> {
+    rowid: i64,
+    columns: &'a [Option<Value<'a>>],
+}
+
+impl DataContext for RowValues<'_> {
+    fn get_column_value(
+        &self,
+        column_idx: &ColumnNumber,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        match column_idx {
+            ColumnNumber::RowId => Ok(Some(Value::Integer(self.rowid))),
+            ColumnNumber::Column(idx) => Ok(self.columns.get(*idx).cloned().flatten()),
+        }
+    }
+}
+
+/// Fail if any CHECK constraint is false for the row. NULL passes as in
+/// SQLite.
+fn check_constraints(checks: &[Expression], row: &RowValues) -> Result<'static, ()> {
+    for check in checks {
+        if matches!(check.execute(Some(row))?.0, Some(v) if !v.as_bool()) {
+            return Err(Error::CheckConstraintViolation);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct IndexSchema {
+    root_page_id: PageId,
+    /// The key columns followed by the rowid.
+    columns: Vec<(ColumnNumber, Collation)>,
+    /// The expression of each key if it is not a column.
+    expressions: Vec<Option<Expression>>,
+    /// Whether each key column is sorted in descending order.
+    descending: Vec<bool>,
+    unique: bool,
+    /// The WHERE clause of a partial index.
+    predicate: Option<Expression>,
+}
+
+impl IndexSchema {
+    /// Whether the row has an entry in the index. Rows not satisfying the
+    /// WHERE clause of a partial index are not indexed.
+    fn contains<D: DataContext>(&self, row: &D) -> Result<'static, bool> {
+        match &self.predicate {
+            Some(predicate) => Ok(matches!(
+                predicate.execute(Some(row))?.0,
+                Some(v) if v.as_bool()
+            )),
+            None => Ok(true),
+        }
+    }
+
+    /// Open a cursor of the index b-tree.
+    fn open_cursor<'a>(&self, conn: &'a Connection) -> Result<'static, BtreeCursor<'a>> {
+        let mut cursor = BtreeCursor::new(self.root_page_id, &conn.pager, &conn.btree_ctx)?;
+        cursor.set_index_order(&self.descending);
+        Ok(cursor)
+    }
+
+    /// Values of the index key for the row followed by the rowid.
+    fn keys<'a, D: DataContext>(&self, row: &'a D) -> Result<'static, Vec<Option<Value<'a>>>> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, (column_number, _))| match self.expressions.get(i) {
+                Some(Some(expr)) => Ok(expr.execute(Some(row))?.0.map(Value::into_owned)),
+                _ => Ok(row
+                    .get_column_value(column_number)
+                    .map_err(expression::Error::FailGetColumn)?),
+            })
+            .collect()
+    }
+
+    fn comparators<'a>(&'a self, keys: &'a [Option<Value<'a>>]) -> Vec<Option<ValueCmp<'a>>> {
+        self.columns
+            .iter()
+            .zip(keys.iter())
+            .map(|((_, collation), v)| v.as_ref().map(|v| ValueCmp::new(v, collation)))
+            .collect()
+    }
+
+    /// Find the rowid of the entry conflicting with the keys in the UNIQUE
+    /// index.
+    ///
+    /// Keys containing NULL never conflict because NULLs are distinct.
+    fn find_conflict(
+        &self,
+        cursor: &mut BtreeCursor,
+        keys: &[Option<Value>],
+    ) -> Result<'static, Option<i64>> {
+        // Exclude the rowid.
+        let keys = &keys[..keys.len() - 1];
+        if !self.unique
+            || keys.iter().any(Option::is_none)
+            || !cursor.index_move_to(&self.comparators(keys))?
+        {
+            return Ok(None);
+        }
+        let Some(payload) = cursor.get_index_payload()? else {
+            unreachable!("index_move_to() found the entry");
+        };
+        let mut record = parse_record(&payload)?;
+        match record.get(record.len() - 1)? {
+            Some(Value::Integer(rowid)) => Ok(Some(rowid)),
+            _ => Err(Error::Other(anyhow::anyhow!(
+                "rowid in index is not integer"
+            ))),
+        }
+    }
+
+    fn create(index: &Index) -> Self {
+        let mut columns = index.columns.clone();
+        columns.push((ColumnNumber::RowId, DEFAULT_COLLATION.clone()));
+
+        IndexSchema {
+            root_page_id: index.root_page_id,
+            columns,
+            expressions: index
+                .expressions
+                .iter()
+                .map(|expression| {
+                    expression
+                        .as_ref()
+                        .map(|expression| expression.expr.clone())
+                })
+                .collect(),
+            descending: index.descending.clone(),
+            unique: index.unique,
+            predicate: index
+                .predicate
+                .as_ref()
+                .map(|predicate| predicate.expr.clone()),
+        }
+    }
+}
+
+/// How the parent key of a foreign key is looked up in the parent table.
+#[derive(Clone)]
+enum ParentKey {
+    /// The parent key is the rowid of the parent table.
+    RowId,
+    /// The UNIQUE index on the parent key. `order` is the position in the
+    /// parent key of each index key.
+    Index {
+        index: IndexSchema,
+        order: Vec<usize>,
+    },
+    /// The parent table or a PRIMARY KEY or UNIQUE index on the parent key
+    /// does not exist.
+    Mismatch,
+}
+
+/// A foreign key constraint resolved against the child and parent tables.
+#[derive(Clone)]
+struct ForeignKeySchema {
+    child_page_id: PageId,
+    child_without_rowid: bool,
+    /// The child key columns.
+    child_columns: Vec<ColumnNumber>,
+    parent_page_id: PageId,
+    /// The parent key columns in the order of the child key columns.
+    parent_columns: Vec<(ColumnNumber, TypeAffinity, Collation)>,
+    parent_key: ParentKey,
+    on_delete: ForeignKeyAction,
+    on_update: ForeignKeyAction,
+}
+
+impl ForeignKeySchema {
+    fn create(schema: &Schema, child: &Table, foreign_key: &ForeignKey) -> Self {
+        let mut foreign_key_schema = Self {
+            child_page_id: child.root_page_id,
+            child_without_rowid: child.without_rowid,
+            child_columns: foreign_key
+                .columns
+                .iter()
+                .map(|i| child.column_number(*i))
+                .collect(),
+            parent_page_id: PAGE_ID_1,
+            parent_columns: Vec::new(),
+            parent_key: ParentKey::Mismatch,
+            on_delete: foreign_key.on_delete,
+            on_update: foreign_key.on_update,
+        };
+        let Some(parent) = schema.get_table(&foreign_key.parent_table) else {
+            return foreign_key_schema;
+        };
+        let parent_columns = if foreign_key.parent_columns.is_empty() {
+            Some(
+                parent
+                    .primary_key
+                    .iter()
+                    .map(|i| {
+                        let column = &parent.columns[*i];
+                        (
+                            parent.column_number(*i),
+                            column.type_affinity,
+                            column.collation.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            foreign_key
+                .parent_columns
+                .iter()
+                .map(|name| parent.get_column(name))
+                .collect::<Option<Vec<_>>>()
+        };
+        let Some(parent_columns) = parent_columns else {
+            return foreign_key_schema;
+        };
+        if parent_columns.len() != foreign_key_schema.child_columns.len() || parent.without_rowid {
+            return foreign_key_schema;
+        }
+
+        let parent_key = if let [(ColumnNumber::RowId, _, _)] = parent_columns.as_slice() {
+            ParentKey::RowId
+        } else {
+            let mut parent_key = ParentKey::Mismatch;
+            let mut index = parent.indexes.as_ref();
+            while let Some(idx) = index {
+                index = idx.next.as_ref();
+                if !idx.unique
+                    || idx.predicate.is_some()
+                    || idx.columns.len() != parent_columns.len()
+                {
+                    continue;
+                }
+                // The index must consist of the parent key columns in any order
+                // with the same collations.
+                let order = idx
+                    .columns
+                    .iter()
+                    .zip(idx.expressions.iter())
+                    .map(|((column_number, collation), expression)| {
+                        parent_columns
+                            .iter()
+                            .position(|(parent_column, _, parent_collation)| {
+                                expression.is_none()
+                                    && parent_column == column_number
+                                    && parent_collation == collation
+                            })
+                    })
+                    .collect::<Option<Vec<_>>>();
+                if let Some(order) = order {
+                    parent_key = ParentKey::Index {
+                        index: IndexSchema::create(idx),
+                        order,
+                    };
+                    break;
+                }
+            }
+            parent_key
+        };
+        foreign_key_schema.parent_page_id = parent.root_page_id;
+        foreign_key_schema.parent_columns = parent_columns;
+        foreign_key_schema.parent_key = parent_key;
+        foreign_key_schema
+    }
+
+    /// Whether the parent row referred to by the child row exists.
+    ///
+    /// The child key containing NULL refers to no parent and always passes.
+    fn parent_exists<D: DataContext>(&self, conn: &Connection, row: &D) -> Result<'static, bool> {
+        let mut keys = Vec::with_capacity(self.child_columns.len());
+        for child_column in self.child_columns.iter() {
+            let Some(value) = row
+                .get_column_value(child_column)
+                .map_err(expression::Error::FailGetColumn)?
+            else {
+                return Ok(true);
+            };
+            keys.push(value);
+        }
+        if let ParentKey::Mismatch = self.parent_key {
+            return Err(Error::Other(anyhow::anyhow!("foreign key mismatch")));
+        }
+        // The affinity of the parent key is applied to the child key.
+        let keys = keys
+            .into_iter()
+            .zip(self.parent_columns.iter())
+            .map(|(value, (_, type_affinity, _))| value.apply_affinity(*type_affinity))
+            .collect::<Vec<_>>();
+        match &self.parent_key {
+            ParentKey::RowId => {
+                let Value::Integer(rowid) = keys[0] else {
+                    return Ok(false);
+                };
+                let mut cursor =
+                    BtreeCursor::new(self.parent_page_id, &conn.pager, &conn.btree_ctx)?;
+                Ok(cursor.table_move_to(rowid)? == Some(rowid))
+            }
+            ParentKey::Index { index, order } => {
+                let mut index_keys = order
+                    .iter()
+                    .map(|i| Some(keys[*i].clone()))
+                    .collect::<Vec<_>>();
+                // find_conflict() ignores the rowid at the tail.
+                index_keys.push(None);
+                let mut cursor = index.open_cursor(conn)?;
+                Ok(index.find_conflict(&mut cursor, &index_keys)?.is_some())
+            }
+            ParentKey::Mismatch => unreachable!("mismatch is checked above"),
+        }
+    }
+
+    /// Whether any child row refers to the parent key of the parent row.
+    ///
+    /// TODO: Use the index on the child key instead of scanning the child
+    /// table.
+    fn child_exists<D: DataContext>(&self, conn: &Connection, row: &D) -> Result<'static, bool> {
+        if let ParentKey::Mismatch = self.parent_key {
+            return Err(Error::Other(anyhow::anyhow!("foreign key mismatch")));
+        }
+        let Some(keys) = self.parent_keys(row)? else {
+            return Ok(false);
+        };
+        let plan = if self.child_without_rowid {
+            QueryPlan::WithoutRowidScan
+        } else {
+            QueryPlan::FullScan
+        };
+        let mut query = Query::new(
+            self.child_page_id,
+            &conn.pager,
+            &conn.btree_ctx,
+            &plan,
+            Rc::new(Expression::one()),
+        )?;
+        while let Some(data) = query.next()? {
+            let mut matched = true;
+            for (child_column, (key, (_, type_affinity, collation))) in self
+                .child_columns
+                .iter()
+                .zip(keys.iter().zip(self.parent_columns.iter()))
+            {
+                let value = data
+                    .get_column_value(child_column)
+                    .map_err(expression::Error::FailGetColumn)?;
+                matched = match value {
+                    Some(value) => {
+                        let value = value.apply_affinity(*type_affinity);
+                        ValueCmp::new(key, collation).compare(&value) == Ordering::Equal
+                    }
+                    None => false,
+                };
+                if !matched {
+                    break;
+                }
+            }
+            if matched {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The values of the parent key of the parent row. [None] if any of them
+    /// is NULL.
+    fn parent_keys<'a, D: DataContext>(
+        &self,
+        row: &'a D,
+    ) -> Result<'static, Option<Vec<Value<'a>>>> {
+        let mut keys = Vec::with_capacity(self.parent_columns.len());
+        for (parent_column, _, _) in self.parent_columns.iter() {
+            let Some(value) = row
+                .get_column_value(parent_column)
+                .map_err(expression::Error::FailGetColumn)?
+            else {
+                return Ok(None);
+            };
+            keys.push(value);
+        }
+        Ok(Some(keys))
+    }
+
+    fn child_key_changed<D: DataContext>(&self, old: &D, new: &D) -> Result<'static, bool> {
+        key_changed(self.child_columns.iter(), old, new)
+    }
+
+    fn parent_key_changed<D: DataContext>(&self, old: &D, new: &D) -> Result<'static, bool> {
+        key_changed(
+            self.parent_columns.iter().map(|(column, _, _)| column),
+            old,
+            new,
+        )
+    }
+
+    /// Fail if any child row refers to the parent key of the parent row being
+    /// deleted or updated.
+    fn check_no_child<D: DataContext>(
+        &self,
+        conn: &Connection,
+        row: &D,
+        action: ForeignKeyAction,
+    ) -> Result<'static, ()> {
+        if !self.child_exists(conn, row)? {
+            return Ok(());
+        }
+        match action {
+            ForeignKeyAction::NoAction | ForeignKeyAction::Restrict => {
+                Err(Error::ForeignKeyConstraintViolation)
+            }
+            ForeignKeyAction::SetNull
+            | ForeignKeyAction::SetDefault
+            | ForeignKeyAction::Cascade => Err(Error::Unsupported("foreign key action")),
+        }
+    }
+}
+
+/// Whether any of the columns has different values in the two rows.
+fn key_changed<'a, D: DataContext>(
+    columns: impl Iterator<Item = &'a ColumnNumber>,
+    old: &D,
+    new: &D,
+) -> Result<'static, bool> {
+    for column in columns {
+        let old_value = old
+            .get_column_value(column)
+            .map_err(expression::Error::FailGetColumn)?;
+        let new_value = new
+            .get_column_value(column)
+            .map_err(expression::Error::FailGetColumn)?;
+        if old_value != new_value {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Apply the type affinity of the `i`th column to the value to store.
+///
+/// For STRICT tables, `datatypes` are the datatypes of the columns and the
+/// value must conform to the datatype.
+fn convert_column_value<'a>(
+    value: Value<'a>,
+    type_affinity: TypeAffinity,
+    datatypes: &[Option<TypeAffinity>],
+    i: usize,
+) -> Result<'static, Value<'a>> {
+    match datatypes.get(i) {
+        Some(Some(datatype)) => value
+            .apply_strict(*datatype)
+            .ok_or(Error::DataTypeConstraintViolation),
+        _ => Ok(value.apply_affinity(type_affinity)),
+    }
+}
+
+#[derive(Clone)]
+pub struct InsertStatement {
+    table_page_id: PageId,
+    records: Vec<InsertRecord>,
+    indexes: Vec<IndexSchema>,
+    /// The row of sqlite_sequence if the table is AUTOINCREMENT.
+    sequence: Option<Sequence>,
+    not_null_columns: Vec<usize>,
+    /// The datatypes of the columns if the table is STRICT.
+    datatypes: Vec<Option<TypeAffinity>>,
+    checks: Vec<Expression>,
+    foreign_keys: Vec<ForeignKeySchema>,
+    on_conflict: OnConflict,
+}
+
+impl InsertStatement {
+    /// Delete the row and its index entries to replace it.
+    ///
+    /// `index_cursors` are the cursors of [Self::indexes].
+    fn delete_row(
+        &self,
+        cursor: &mut BtreeCursor,
+        index_cursors: &mut [BtreeCursor],
+        rowid: i64,
+        n_columns: usize,
+    ) -> Result<'static, ()> {
+        if cursor.table_move_to(rowid)? != Some(rowid) {
+            return Ok(());
+        }
+        let columns = {
+            let Some((_, payload)) = cursor.get_table_payload()? else {
+                unreachable!("table_move_to() found the row");
+            };
+            let mut record = parse_record(&payload)?;
+            // The record may have less columns than the table.
+            (0..n_columns.min(record.len()))
+                .map(|i| record.get(i).map(|v| v.map(ConstantValue::copy_from)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        let mut columns = columns
+            .iter()
+            .map(|v| v.as_ref().map(ConstantValue::as_value))
+            .collect::<Vec<_>>();
+        columns.resize(n_columns, None);
+
+        let row = RowValues {
+            rowid,
+            columns: &columns,
+        };
+        for (index, index_cursor) in self.indexes.iter().zip(index_cursors.iter_mut()) {
+            if !index.contains(&row)? {
+                continue;
+            }
+            let keys = index.keys(&row)?;
+            index_cursor.index_move_to(&index.comparators(&keys))?;
+            index_cursor.delete()?;
+        }
+
+        cursor.table_move_to(rowid)?;
+        cursor.delete()?;
+        Ok(())
+    }
+}
+
+impl ExecutionStatement for InsertStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
+
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        let write_txn = conn.start_write()?;
+
+        // The cursors are reused for all the rows. Each insertion seeks from the root.
+        let mut cursor = BtreeCursor::new(self.table_page_id, &conn.pager, &conn.btree_ctx)?;
+        let mut index_cursors = self
+            .indexes
+            .iter()
+            .map(|index| index.open_cursor(conn))
+            .collect::<Result<Vec<_>>>()?;
+        let mut n = 0;
+        let mut last_rowid = None;
+        // The largest rowid in the table. It is read from the b-tree once and kept up
+        // to date by the insertions until rows are deleted.
+        let mut max_table_rowid = None;
+        let (sequence_rowid, mut max_rowid) = match &self.sequence {
+            Some(sequence) => {
+                let (sequence_rowid, max_rowid) = sequence.load(conn)?;
+                (sequence_rowid, Some(max_rowid))
+            }
+            None => (None, None),
+        };
+        let initial_max_rowid = max_rowid;
+        for record in self.records.iter() {
+            let mut rowid = None;
+            if let Some(rowid_expr) = &record.rowid {
+                let (rowid_value, _, _) = rowid_expr.execute::<RowData>(None)?;
+                // NULL then fallback to generate new rowid.
+                if let Some(rowid_value) = rowid_value {
+                    match rowid_value.apply_numeric_affinity() {
+                        Value::Integer(rowid_value) => {
+                            rowid = Some(rowid_value);
+                        }
+                        _ => return Err(Error::DataTypeMismatch),
+                    }
+                }
+            }
+            let rowid = if let Some(rowid) = rowid {
+                rowid
+            } else {
+                let last_rowid = match max_table_rowid {
+                    Some(max_table_rowid) => max_table_rowid,
+                    None => {
+                        cursor.move_to_last()?;
+                        cursor.get_table_key()?.unwrap_or(0)
+                    }
+                };
+                max_table_rowid = Some(last_rowid);
+                match max_rowid {
+                    // AUTOINCREMENT never reuses rowids even if unused ones exist.
+                    Some(max_rowid) if last_rowid.max(max_rowid) == MAX_ROWID => {
+                        return Err(Error::DatabaseFull);
+                    }
+                    Some(max_rowid) => last_rowid.max(max_rowid) + 1,
+                    None if last_rowid == MAX_ROWID => find_unused_rowid(&mut cursor)?,
+                    None => last_rowid + 1,
+                }
+            };
+
+            let mut columns = Vec::with_capacity(record.columns.len());
+            for (i, (expr, type_affinity)) in record.columns.iter().enumerate() {
+                let (value, _, _) = expr.execute::<RowData>(None)?;
+                let value = value
+                    .map(|v| convert_column_value(v, *type_affinity, &self.datatypes, i))
+                    .transpose()?;
+                columns.push(value);
+            }
+            if self
+                .not_null_columns
+                .iter()
+                .any(|column_idx| columns[*column_idx].is_none())
+            {
+                return Err(Error::NotNullConstraintViolation);
+            }
+            check_constraints(
+                &self.checks,
+                &RowValues {
+                    rowid,
+                    columns: &columns,
+                },
+            )?;
+
+            // Check rowid conflict
+            let current_rowid = cursor.table_move_to(rowid)?;
+            if current_rowid.is_some() && current_rowid.unwrap() == rowid {
+                match self.on_conflict {
+                    OnConflict::Abort => return Err(Error::UniqueConstraintViolation),
+                    OnConflict::Ignore => continue,
+                    OnConflict::Replace => {
+                        self.delete_row(&mut cursor, &mut index_cursors, rowid, columns.len())?;
+                        max_table_rowid = None;
+                    }
+                }
+            }
+
+            let row = RowValues {
+                rowid,
+                columns: &columns,
+            };
+            let mut ignored = false;
+            for (i, index) in self.indexes.iter().enumerate() {
+                if !index.contains(&row)? {
+                    continue;
+                }
+                let index_columns = index.keys(&row)?;
+                let Some(conflict_rowid) =
+                    index.find_conflict(&mut index_cursors[i], &index_columns)?
+                else {
+                    continue;
+                };
+                match self.on_conflict {
+                    OnConflict::Abort => return Err(Error::UniqueConstraintViolation),
+                    OnConflict::Ignore => {
+                        ignored = true;
+                        break;
+                    }
+                    OnConflict::Replace => {
+                        self.delete_row(
+                            &mut cursor,
+                            &mut index_cursors,
+                            conflict_rowid,
+                            columns.len(),
+                        )?;
+                        max_table_rowid = None;
+                    }
+                }
+            }
+            if ignored {
+                continue;
+            }
+
+            cursor.table_insert(
+                rowid,
+                &RecordPayload::new(&columns.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
+            )?;
+
+            for (index, index_cursor) in self.indexes.iter().zip(index_cursors.iter_mut()) {
+                if !index.contains(&row)? {
+                    continue;
+                }
+                let index_columns = index.keys(&row)?;
+                index_cursor.index_insert(
+                    &index.comparators(&index_columns),
+                    &RecordPayload::new(
+                        &index_columns.iter().map(|v| v.as_ref()).collect::<Vec<_>>(),
+                    )?,
+                )?;
+            }
+
+            // The parent is checked after the row is inserted because the row
+            // may refer to itself.
+            if conn.foreign_keys.get() {
+                for foreign_key in &self.foreign_keys {
+                    if !foreign_key.parent_exists(conn, &row)? {
+                        return Err(Error::ForeignKeyConstraintViolation);
+                    }
+                }
+            }
+
+            last_rowid = Some(rowid);
+            max_table_rowid = max_table_rowid.map(|max_table_rowid| rowid.max(max_table_rowid));
+            if let Some(max_rowid) = &mut max_rowid {
+                *max_rowid = rowid.max(*max_rowid);
+            }
+            n += 1;
+        }
+
+        if let (Some(sequence), Some(max_rowid)) = (&self.sequence, max_rowid) {
+            if Some(max_rowid) != initial_max_rowid {
+                sequence.save(conn, sequence_rowid, max_rowid)?;
+            }
+        }
+
+        write_txn.commit()?;
+
+        if let Some(rowid) = last_rowid {
+            conn.last_insert_rowid.set(rowid);
+        }
+        conn.record_changes(n);
+        Ok(n)
+    }
+
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        for record in self.records.iter_mut() {
+            if let Some(rowid) = &mut record.rowid {
+                rowid.bind_parameter(idx, value);
+            }
+            for (expr, _) in record.columns.iter_mut() {
+                expr.bind_parameter(idx, value);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClearStatement {
+    table_page_id: PageId,
+    index_page_ids: Vec<PageId>,
+}
+
+impl ExecutionStatement for ClearStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
+
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        let write_txn = conn.start_write()?;
+
+        let mut cursor = BtreeCursor::new(self.table_page_id, &conn.pager, &conn.btree_ctx)?;
+
+        let n_deleted = cursor.clear()?;
+
+        for index_page_id in self.index_page_ids.iter() {
+            let mut cursor = BtreeCursor::new(*index_page_id, &conn.pager, &conn.btree_ctx)?;
+            let n = cursor.clear()?;
+            if n != n_deleted {
+                return Err(Error::Other(anyhow::anyhow!(
+                    "number of deleted rows in table and index does not match"
+                )));
+            }
+        }
+
+        write_txn.commit()?;
+
+        conn.record_changes(n_deleted);
+        Ok(n_deleted)
+    }
+
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // DELETE without WHERE has no parameters.
+    }
+}
+
+#[derive(Clone)]
+pub struct DeleteStatement {
+    table_page_id: PageId,
+    indexes: Vec<IndexSchema>,
+    /// The foreign keys of the child tables referring to the table.
+    child_foreign_keys: Vec<ForeignKeySchema>,
+    filter: Rc<Expression>,
+    query_plan: QueryPlan,
+}
+
+impl ExecutionStatement for DeleteStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
+
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        let write_txn = conn.start_write()?;
+
+        let mut query = Query::new(
+            self.table_page_id,
+            &conn.pager,
+            &conn.btree_ctx,
+            &self.query_plan,
+            self.filter.clone(),
+        )?;
+
+        let mut n_deleted = 0;
+
+        loop {
+            let Some(data) = query.next()? else {
+                break;
+            };
+
+            if conn.foreign_keys.get() {
+                for foreign_key in &self.child_foreign_keys {
+                    foreign_key.check_no_child(conn, &data, foreign_key.on_delete)?;
+                }
+            }
+
+            // Delete from index
+            for index in &self.indexes {
+                if !index.contains(&data)? {
+                    continue;
+                }
+                let keys = index.keys(&data)?;
+                let mut index_cursor = index.open_cursor(conn)?;
+                index_cursor.index_move_to(&index.comparators(&keys))?;
+                index_cursor.delete()?;
+            }
+
+            drop(data);
+            query.delete()?;
+
+            n_deleted += 1;
+        }
+
+        write_txn.commit()?;
+
+        conn.record_changes(n_deleted);
+        Ok(n_deleted)
+    }
+
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        Rc::make_mut(&mut self.filter).bind_parameter(idx, value);
+    }
+}
+
+/// Find an unused rowid randomly when the largest rowid is already used.
+///
+/// This comes from sqlite3VdbeExec() OP_NewRowid of SQLite.
+fn find_unused_rowid(cursor: &mut BtreeCursor) -> Result<'static, i64> {
+    for _ in 0..MAX_RANDOM_ROWID_TRIES {
+        // The range is 1..=(MAX_ROWID >> 1) + 1.
+        let rowid = (u64_to_i64(random_u64()) & (MAX_ROWID >> 1)) + 1;
+        if cursor.table_move_to(rowid)? != Some(rowid) {
+            return Ok(rowid);
+        }
+    }
+    Err(Error::DatabaseFull)
+}
+
+#[derive(Clone)]
+pub struct UpdateStatement {
+    table_page_id: PageId,
+    n_columns: usize,
+    assignments: Vec<(usize, Expression, TypeAffinity)>,
+    /// The new rowid if the rowid or its alias column is assigned.
+    rowid: Option<Expression>,
+    indexes: Vec<IndexSchema>,
+    not_null_columns: Vec<usize>,
+    /// The datatypes of the columns if the table is STRICT.
+    datatypes: Vec<Option<TypeAffinity>>,
+    checks: Vec<Expression>,
+    foreign_keys: Vec<ForeignKeySchema>,
+    /// The foreign keys of the child tables referring to the table.
+    child_foreign_keys: Vec<ForeignKeySchema>,
+    filter: Rc<Expression>,
+    query_plan: QueryPlan,
+}
+
+struct UpdateRecord {
+    rowid: i64,
+    new_rowid: i64,
+    old_columns: Vec<Option<ConstantValue>>,
+    new_columns: Vec<Option<ConstantValue>>,
+}
+
+impl ExecutionStatement for UpdateStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
+
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        let write_txn = conn.start_write()?;
+
+        // Collect the new records before modifying the btrees. Otherwise the query
+        // may visit the updated rows again via the index.
+        let mut records = Vec::new();
+        {
+            let mut query = Query::new(
+                self.table_page_id,
+                &conn.pager,
+                &conn.btree_ctx,
+                &self.query_plan,
+                self.filter.clone(),
+            )?;
+            while let Some(data) = query.next()? {
+                let Some(Value::Integer(rowid)) = data
+                    .get_column_value(&ColumnNumber::RowId)
+                    .map_err(expression::Error::FailGetColumn)?
+                else {
+                    unreachable!("rowid must be integer");
+                };
+                let old_columns = (0..self.n_columns)
+                    .map(|column_idx| {
+                        let value = data
+                            .get_column_value(&ColumnNumber::Column(column_idx))
+                            .map_err(expression::Error::FailGetColumn)?;
+                        Ok(value.map(ConstantValue::copy_from))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let new_rowid = match &self.rowid {
+                    Some(expr) => {
+                        let (value, _, _) = expr.execute(Some(&data))?;
+                        // Unlike INSERT, NULL does not generate a new rowid.
+                        match value.map(Value::apply_numeric_affinity) {
+                            Some(Value::Integer(new_rowid)) => new_rowid,
+                            _ => return Err(Error::DataTypeMismatch),
+                        }
+                    }
+                    None => rowid,
+                };
+                let mut new_columns = old_columns.clone();
+                for (column_idx, expr, type_affinity) in self.assignments.iter() {
+                    let (value, _, _) = expr.execute(Some(&data))?;
+                    new_columns[*column_idx] = value
+                        .map(|v| {
+                            convert_column_value(v, *type_affinity, &self.datatypes, *column_idx)
+                                .map(ConstantValue::copy_from)
+                        })
+                        .transpose()?;
+                }
+                if self
+                    .not_null_columns
+                    .iter()
+                    .any(|column_idx| new_columns[*column_idx].is_none())
+                {
+                    return Err(Error::NotNullConstraintViolation);
+                }
+                if !self.checks.is_empty() {
+                    let values = new_columns
+                        .iter()
+                        .map(|v| v.as_ref().map(ConstantValue::as_value))
+                        .collect::<Vec<_>>();
+                    check_constraints(
+                        &self.checks,
+                        &RowValues {
+                            rowid: new_rowid,
+                            columns: &values,
+                        },
+                    )?;
+                }
+                records.push(UpdateRecord {
+                    rowid,
+                    new_rowid,
+                    old_columns,
+                    new_columns,
+                });
+            }
+        }
+
+        let mut cursor = BtreeCursor::new(self.table_page_id, &conn.pager, &conn.btree_ctx)?;
+        for record in records.iter() {
+            let old_columns = record
+                .old_columns
+                .iter()
+                .map(|v| v.as_ref().map(ConstantValue::as_value))
+                .collect::<Vec<_>>();
+            let new_columns = record
+                .new_columns
+                .iter()
+                .map(|v| v.as_ref().map(ConstantValue::as_value))
+                .collect::<Vec<_>>();
+
+            let old_row = RowValues {
+                rowid: record.rowid,
+                columns: &old_columns,
+            };
+            let new_row = RowValues {
+                rowid: record.new_rowid,
+                columns: &new_columns,
+            };
+            for index in self.indexes.iter() {
+                let mut index_cursor = index.open_cursor(conn)?;
+
+                if index.contains(&old_row)? {
+                    let old_keys = index.keys(&old_row)?;
+                    index_cursor.index_move_to(&index.comparators(&old_keys))?;
+                    index_cursor.delete()?;
+                }
+
+                if !index.contains(&new_row)? {
+                    continue;
+                }
+                let new_keys = index.keys(&new_row)?;
+                if index.find_conflict(&mut index_cursor, &new_keys)?.is_some() {
+                    return Err(Error::UniqueConstraintViolation);
+                }
+                index_cursor.index_insert(
+                    &index.comparators(&new_keys),
+                    &RecordPayload::new(&new_keys.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
+                )?;
+            }
+
+            if record.new_rowid != record.rowid {
+                // The row moves to the new rowid. The rows are moved one by one as
+                // SQLite does, so the new rowid may conflict with a row not moved yet.
+                if cursor.table_move_to(record.rowid)? == Some(record.rowid) {
+                    cursor.delete()?;
+                }
+                if cursor.table_move_to(record.new_rowid)? == Some(record.new_rowid) {
+                    return Err(Error::UniqueConstraintViolation);
+                }
+            }
+            cursor.table_insert(
+                record.new_rowid,
+                &RecordPayload::new(&new_columns.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
+            )?;
+
+            if conn.foreign_keys.get() {
+                for foreign_key in &self.foreign_keys {
+                    if foreign_key.child_key_changed(&old_row, &new_row)?
+                        && !foreign_key.parent_exists(conn, &new_row)?
+                    {
+                        return Err(Error::ForeignKeyConstraintViolation);
+                    }
+                }
+                for foreign_key in &self.child_foreign_keys {
+                    if foreign_key.parent_key_changed(&old_row, &new_row)? {
+                        foreign_key.check_no_child(conn, &old_row, foreign_key.on_update)?;
+                    }
+                }
+            }
+        }
+
+        write_txn.commit()?;
+
+        let n = records.len() as u64;
+        conn.record_changes(n);
+        Ok(n)
+    }
+
+    fn bind_parameter(&mut self, idx: usize, value: &Option<ConstantValue>) {
+        for (_, expr, _) in self.assignments.iter_mut() {
+            expr.bind_parameter(idx, value);
+        }
+        if let Some(expr) = &mut self.rowid {
+            expr.bind_parameter(idx, value);
+        }
+        Rc::make_mut(&mut self.filter).bind_parameter(idx, value);
+    }
+}
+
+/// Options to create a new database. See [Connection::create()].
+pub struct CreateOptions<'a> {
+    filename: &'a Path,
+    pagesize: u32,
+}
+
+impl CreateOptions<'_> {
+    /// Set the page size in bytes. It must be a power of two between 512 and
+    /// 65536. The default is 4096.
+    pub fn page_size(mut self, pagesize: u32) -> Self {
+        self.pagesize = pagesize;
+        self
+    }
+
+    /// Create the database and open it.
+    ///
+    /// The file is created if it does not exist. This fails if the file is not
+    /// empty.
+    pub fn open(self) -> Result<'static, Connection> {
+        if !(512..=65536).contains(&self.pagesize) || !self.pagesize.is_power_of_two() {
+            return Err(Error::Unsupported(
+                "page size must be a power of two between 512 and 65536",
+            ));
+        }
+        if self.filename == Path::new(MEMORY_DATABASE_NAME) {
+            return Connection::from_storage(
+                MemoryStorage::new(new_database_page1(self.pagesize)),
+                false,
+            );
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.filename)
+            .map_err(|e| open_error(self.filename, e))?;
+        if Storage::size(&file)? != 0 {
+            return Err(open_error(
+                self.filename,
+                std::io::ErrorKind::AlreadyExists.into(),
+            ));
+        }
+        Connection::from_file(file, self.filename, false, self.pagesize)
+    }
+}
+
+static NEXT_SHARED_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The liveness of a [SharedConnection] and its connection of a thread.
+type SharedConnectionEntry = (Weak<()>, Rc<Connection>);
+
+thread_local! {
+    /// The read-only connections of the current thread for each
+    /// [SharedConnection].
+    static SHARED_CONNECTIONS: RefCell<HashMap<u64, SharedConnectionEntry>> =
+        RefCell::new(HashMap::new());
+}
+
+/// A read-only handle to a database file which can be shared across threads
+/// (e.g. in an [std::sync::Arc]).
+///
+/// [Connection] is neither [Send] nor [Sync]. Instead of synchronizing the
+/// pager, each thread using the handle opens its own read-only [Connection] on
+/// the first use and reuses it for the following queries. The schema and the
+/// page cache are loaded once per thread.
+///
+/// The connection of a thread is closed when:
+///
+/// * [Self::close()] is called on the thread,
+/// * the handle is dropped on the thread,
+/// * the thread uses any handle after the handle is dropped on another
+///   thread,
+/// * or the thread exits.
+pub struct SharedConnection {
+    id: u64,
+    filename: PathBuf,
+    /// The connections of other threads refer to this weakly so that they are
+    /// pruned after the handle is dropped.
+    alive: Arc<()>,
+}
+
+impl SharedConnection {
+    /// Open the database file without write permission.
+    pub fn open(filename: &Path) -> Result<'static, Self> {
+        let shared = Self {
+            id: NEXT_SHARED_CONNECTION_ID.fetch_add(1, AtomicOrdering::Relaxed),
+            filename: filename.to_path_buf(),
+            alive: Arc::new(()),
+        };
+        // Validate the file and keep the connection for the current thread.
+        shared.with(|_| ())?;
+        Ok(shared)
+    }
+
+    /// Run `f` with the read-only connection of the current thread.
+    ///
+    /// ```
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("example.db");
+    /// # let conn = prsqlite::Connection::create(&path).open().unwrap();
+    /// # conn.prepare("CREATE TABLE example(col);").unwrap().execute().unwrap();
+    /// let shared = std::sync::Arc::new(prsqlite::SharedConnection::open(&path).unwrap());
+    /// let handle = std::thread::spawn({
+    ///     let shared = shared.clone();
+    ///     move || {
+    ///         shared
+    ///             .with(|conn| conn.prepare("SELECT col FROM example;").is_ok())
+    ///             .unwrap()
+    ///     }
+    /// });
+    /// assert!(handle.join().unwrap());
+    /// ```
+    pub fn with<T>(&self, f: impl FnOnce(&Connection) -> T) -> Result<'static, T> {
+        let conn = SHARED_CONNECTIONS.with(|connections| {
+            let mut connections = connections.borrow_mut();
+            // Close the connections of the handles dropped on other threads.
+            connections.retain(|_, (alive, _)| alive.strong_count() > 0);
+            if let Some((_, conn)) = connections.get(&self.id) {
+                return Ok(conn.clone());
+            }
+            let conn = Rc::new(Connection::open_readonly(&self.filename)?);
+            connections.insert(self.id, (Arc::downgrade(&self.alive), conn.clone()));
+            Ok::<_, Error>(conn)
+        })?;
+        // The connection is not borrowed from the map so that `f` can use
+        // other handles.
+        Ok(f(&conn))
+    }
+
+    /// Close the connection of the current thread.
+    ///
+    /// The next [Self::with()] on the thread opens a new connection.
+    pub fn close(&self) {
+        // The thread local storage may be already destroyed on thread exit.
+        let _ = SHARED_CONNECTIONS.try_with(|connections| {
+            connections.borrow_mut().remove(&self.id);
+        });
+    }
+}
+
+impl Drop for SharedConnection {
     fn drop(&mut self) {
-        if !self.do_commit {
-            self.conn.pager.abort();
-        }
-        self.conn.ref_count.set(0);
+        self.close();
     }
 }
 
-pub trait ExecutionStatement {
-    fn execute(&self) -> Result<u64>;
+fn open_error(filename: &Path, e: std::io::Error) -> Error<'static> {
+    Error::Io(std::io::Error::new(
+        e.kind(),
+        format!("failed to open file {:?}: {}", filename, e),
+    ))
 }
 
-pub enum Statement<'conn> {
-    Query(SelectStatement<'conn>),
-    Execution(Box<dyn ExecutionStatement + 'conn>),
+/// Build page 1 of a new database which has no tables.
+fn new_database_page1(pagesize: u32) -> Vec<u8> {
+    let mut page1 = vec![0; pagesize as usize];
+    DatabaseHeaderMut::from((&mut page1[..DATABASE_HEADER_SIZE]).try_into().unwrap())
+        .initialize(pagesize);
+    // The empty table leaf page of sqlite_schema.
+    let btree_header = &mut page1[DATABASE_HEADER_SIZE..];
+    btree_header[0] = BTREE_PAGE_TYPE_LEAF_TABLE;
+    btree::set_u16(btree_header, 5, non_zero_to_u16(pagesize));
+    page1
 }
 
-impl<'conn> Statement<'conn> {
-    pub fn query(&'conn self) -> anyhow::Result<Rows<'conn>> {
-        match self {
-            Self::Query(stmt) => stmt.query(),
-            Self::Execution(_) => bail!("execute statement not support query"),
-        }
-    }
+/// Whether the name of a table or an index starts with "sqlite_".
+fn is_reserved_name(name: &[u8]) -> bool {
+    name.len() >= 7 && name[..7].eq_ignore_ascii_case(b"sqlite_")
+}
 
-    pub fn execute(&'conn self) -> Result<u64> {
-        match self {
-            Self::Query(_) => Err(Error::Unsupported("select statement not support execute")),
-            Self::Execution(stmt) => stmt.execute(),
-        }
+/// Append a row to sqlite_schema.
+fn insert_schema_record(
+    cursor: &mut BtreeCursor,
+    type_: &[u8],
+    name: &[u8],
+    table_name: &[u8],
+    root_page_id: PageId,
+    sql: Option<&[u8]>,
+) -> Result<'static, ()> {
+    cursor.move_to_last()?;
+    let rowid = cursor.get_table_key()?.unwrap_or(0) + 1;
+    let type_ = Value::Text(type_.into());
+    let name = Value::Text(name.into());
+    let table_name = Value::Text(table_name.into());
+    let root_page_id = Value::Integer(root_page_id.get() as i64);
+    let sql = sql.map(|sql| Value::Text(sql.into()));
+    cursor.table_insert(
+        rowid,
+        &RecordPayload::new(&[
+            Some(&type_),
+            Some(&name),
+            Some(&table_name),
+            Some(&root_page_id),
+            sql.as_ref(),
+        ])?,
+    )?;
+    Ok(())
+}
+
+/// Fail if a table or an index with the name already exists.
+fn check_name_conflict(conn: &Connection, name: &[u8]) -> Result<'static, ()> {
+    if conn.schema.borrow().is_none() {
+        conn.load_schema()?;
+    }
+    let schema_cell = conn.schema.borrow();
+    let schema = schema_cell.as_ref().unwrap();
+    if schema.get_table(name).is_some() || schema.get_index(name).is_some() {
+        return Err(Error::Other(anyhow::anyhow!(
+            "{:?} already exists",
+            std::str::from_utf8(name).unwrap_or_default()
+        )));
     }
+    Ok(())
 }
 
-pub struct SelectStatement<'conn> {
-    conn: &'conn Connection,
-    table_page_id: PageId,
-    columns: Vec<Expression>,
-    filter: Expression,
-    query_plan: QueryPlan,
+#[derive(Clone)]
+pub struct CreateTableStatement {
+    table_name: Vec<u8>,
+    sql: Vec<u8>,
+    without_rowid: bool,
+    autoincrement: bool,
+    /// The numbers of the autoindexes which need their own b-tree.
+    autoindexes: Vec<usize>,
 }
 
-impl<'conn> SelectStatement<'conn> {
-    pub(crate) fn new(
-        conn: &'conn Connection,
-        table_page_id: PageId,
-        columns: Vec<Expression>,
-        filter: Expression,
-        query_plan: QueryPlan,
-    ) -> Self {
-        Self {
-            conn,
-            table_page_id,
-            columns,
-            filter,
-            query_plan,
-        }
+impl ExecutionStatement for CreateTableStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
     }
 
-    pub fn query(&'conn self) -> anyhow::Result<Rows<'conn>> {
-        let read_txn = self.conn.start_read()?;
-        // TODO: check schema version.
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        check_name_conflict(conn, &self.table_name)?;
+        // The first AUTOINCREMENT table creates sqlite_sequence.
+        let create_sequence = self.autoincrement
+            && conn
+                .schema
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .get_table(SEQUENCE_TABLE_NAME)
+                .is_none();
 
-        let query = Query::new(
-            self.table_page_id,
-            &self.conn.pager,
-            &self.conn.btree_ctx,
-            &self.query_plan,
-            &self.filter,
+        let write_txn = conn.start_write()?;
+
+        let root_page_id = create_btree(&conn.pager, &conn.btree_ctx, !self.without_rowid)?;
+        let mut cursor = BtreeCursor::new(PAGE_ID_1, &conn.pager, &conn.btree_ctx)?;
+        insert_schema_record(
+            &mut cursor,
+            b"table",
+            &self.table_name,
+            &self.table_name,
+            root_page_id,
+            Some(&self.sql),
         )?;
+        for n in self.autoindexes.iter() {
+            let mut index_name = b"sqlite_autoindex_".to_vec();
+            index_name.extend_from_slice(&self.table_name);
+            index_name.extend_from_slice(format!("_{}", n).as_bytes());
+            let index_page_id = create_btree(&conn.pager, &conn.btree_ctx, false)?;
+            insert_schema_record(
+                &mut cursor,
+                b"index",
+                &index_name,
+                &self.table_name,
+                index_page_id,
+                None,
+            )?;
+        }
+        if create_sequence {
+            let sequence_page_id = create_btree(&conn.pager, &conn.btree_ctx, true)?;
+            insert_schema_record(
+                &mut cursor,
+                b"table",
+                SEQUENCE_TABLE_NAME,
+                SEQUENCE_TABLE_NAME,
+                sequence_page_id,
+                Some(b"CREATE TABLE sqlite_sequence(name,seq)"),
+            )?;
+        }
+        drop(cursor);
+        conn.increment_schema_cookie()?;
 
-        Ok(Rows {
-            _read_txn: read_txn,
-            stmt: self,
-            query,
-        })
-    }
-}
+        write_txn.commit()?;
 
-pub struct Rows<'conn> {
-    _read_txn: ReadTransaction<'conn>,
-    stmt: &'conn SelectStatement<'conn>,
-    query: Query<'conn>,
-}
+        Ok(0)
+    }
 
-impl<'conn> Rows<'conn> {
-    pub fn next_row(&mut self) -> Result<Option<Row<'_>>> {
-        if let Some(data) = self.query.next()? {
-            Ok(Some(Row {
-                stmt: self.stmt,
-                data,
-            }))
-        } else {
-            Ok(None)
-        }
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // CREATE TABLE has no parameters.
     }
 }
 
-pub struct Row<'a> {
-    stmt: &'a SelectStatement<'a>,
-    data: RowData<'a>,
+#[derive(Clone)]
+pub struct CreateIndexStatement {
+    index_name: Vec<u8>,
+    table_name: Vec<u8>,
+    sql: Vec<u8>,
+    table_page_id: PageId,
+    n_columns: usize,
+    /// The key columns followed by the rowid.
+    columns: Vec<(ColumnNumber, Collation)>,
+    expressions: Vec<Option<Expression>>,
+    descending: Vec<bool>,
+    unique: bool,
+    predicate: Option<Expression>,
 }
 
-impl<'a> Row<'a> {
-    pub fn parse(&self) -> Result<Columns<'_>> {
-        let mut columns = Vec::with_capacity(self.stmt.columns.len());
-        for expr in self.stmt.columns.iter() {
-            let (value, _, _) = expr.execute(Some(&self.data))?;
-            columns.push(value);
-        }
-        Ok(Columns(columns))
+impl ExecutionStatement for CreateIndexStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
     }
-}
 
-pub struct Columns<'a>(Vec<Option<Value<'a>>>);
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        check_name_conflict(conn, &self.index_name)?;
 
-impl<'a> Columns<'a> {
-    pub fn get(&self, i: usize) -> Option<&Value<'a>> {
-        if let Some(Some(v)) = self.0.get(i) {
-            Some(v)
-        } else {
-            None
+        let write_txn = conn.start_write()?;
+
+        let index_page_id = create_btree(&conn.pager, &conn.btree_ctx, false)?;
+        let index = IndexSchema {
+            root_page_id: index_page_id,
+            columns: self.columns.clone(),
+            expressions: self.expressions.clone(),
+            descending: self.descending.clone(),
+            unique: self.unique,
+            predicate: self.predicate.clone(),
+        };
+        let mut cursor = BtreeCursor::new(self.table_page_id, &conn.pager, &conn.btree_ctx)?;
+        let mut index_cursor = index.open_cursor(conn)?;
+        cursor.move_to_first()?;
+        loop {
+            let (rowid, columns) = {
+                let Some((rowid, payload)) = cursor.get_table_payload()? else {
+                    break;
+                };
+                let mut record = parse_record(&payload)?;
+                // The record may have less columns than the table.
+                let columns = (0..self.n_columns.min(record.len()))
+                    .map(|i| record.get(i).map(|v| v.map(ConstantValue::copy_from)))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                (rowid, columns)
+            };
+            let mut columns = columns
+                .iter()
+                .map(|v| v.as_ref().map(ConstantValue::as_value))
+                .collect::<Vec<_>>();
+            columns.resize(self.n_columns, None);
+
+            let row = RowValues {
+                rowid,
+                columns: &columns,
+            };
+            if !index.contains(&row)? {
+                cursor.move_next()?;
+                continue;
+            }
+            let keys = index.keys(&row)?;
+            if index.find_conflict(&mut index_cursor, &keys)?.is_some() {
+                return Err(Error::UniqueConstraintViolation);
+            }
+            index_cursor.index_insert(
+                &index.comparators(&keys),
+                &RecordPayload::new(&keys.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
+            )?;
+
+            cursor.move_next()?;
         }
-    }
+        drop(index_cursor);
+        drop(cursor);
 
-    pub fn len(&self) -> usize {
-        self.0.len()
-    }
+        let mut cursor = BtreeCursor::new(PAGE_ID_1, &conn.pager, &conn.btree_ctx)?;
+        insert_schema_record(
+            &mut cursor,
+            b"index",
+            &self.index_name,
+            &self.table_name,
+            index_page_id,
+            Some(&self.sql),
+        )?;
+        drop(cursor);
+        conn.increment_schema_cookie()?;
 
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        write_txn.commit()?;
+
+        Ok(0)
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &Option<Value<'a>>> {
-        self.0.iter()
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // CREATE INDEX has no parameters.
     }
 }
 
-struct InsertRecord {
-    rowid: Option<Expression>,
-    columns: Vec<(Expression, TypeAffinity)>,
+/// The pragmas which return rows.
+#[derive(Clone)]
+enum PragmaQuery {
+    UserVersion,
+    PageSize,
+    ForeignKeys,
+    /// The rows generated from the schema on prepare. The schema cookie
+    /// invalidates the statement if the schema changes.
+    Rows(Vec<Vec<Option<ConstantValue>>>),
 }
 
-struct IndexSchema {
-    root_page_id: PageId,
-    columns: Vec<(ColumnNumber, Collation)>,
+#[derive(Clone)]
+pub struct PragmaStatement {
+    pragma: PragmaQuery,
+    column_names: Rc<[String]>,
 }
 
-impl IndexSchema {
-    fn create(table: &Table, index: &Index) -> Self {
-        let mut columns = index
-            .columns
-            .iter()
-            .map(|column_number| {
-                let collation = if let ColumnNumber::Column(column_idx) = column_number {
-                    &table.columns[*column_idx].collation
-                } else {
-                    &DEFAULT_COLLATION
-                };
-                (*column_number, collation.clone())
-            })
-            .collect::<Vec<_>>();
-        columns.push((ColumnNumber::RowId, DEFAULT_COLLATION.clone()));
+impl PragmaStatement {
+    pub fn query<'conn>(&self, conn: &'conn Connection) -> Result<'static, Rows<'conn>> {
+        let read_txn = conn.start_read()?;
 
-        IndexSchema {
-            root_page_id: index.root_page_id,
-            columns,
-        }
+        let page1 = conn.pager.get_page(PAGE_ID_1)?;
+        let buffer = page1.buffer();
+        let header = DatabaseHeader::from(buffer[..DATABASE_HEADER_SIZE].try_into().unwrap());
+        let rows = match &self.pragma {
+            PragmaQuery::UserVersion => {
+                vec![vec![Some(ConstantValue::Integer(
+                    header.user_version() as i64
+                ))]]
+            }
+            PragmaQuery::PageSize => {
+                vec![vec![Some(ConstantValue::Integer(header.pagesize() as i64))]]
+            }
+            PragmaQuery::ForeignKeys => {
+                vec![vec![Some(ConstantValue::Integer(
+                    conn.foreign_keys.get() as i64
+                ))]]
+            }
+            PragmaQuery::Rows(rows) => rows.clone(),
+        };
+
+        Ok(Rows {
+            _read_txn: read_txn,
+            columns: Rc::new([]),
+            column_names: self.column_names.clone(),
+            query: None,
+            buffered_rows: Some(rows.into_iter()),
+            join: None,
+            compound: Vec::new(),
+        })
     }
 }
 
-pub struct InsertStatement<'conn> {
-    conn: &'conn Connection,
-    table_page_id: PageId,
-    records: Vec<InsertRecord>,
-    indexes: Vec<IndexSchema>,
+/// Evaluate the value of a pragma as an integer.
+fn eval_pragma_integer(value: parser::Expr) -> Result<i64> {
+    let expr = Expression::from(value, &[], &UserFunctions::default())?;
+    let (value, _, _) = expr.execute::<RowData>(None)?;
+    Ok(value.map_or(0, |v| v.as_integer()))
 }
 
-impl<'conn> ExecutionStatement for InsertStatement<'conn> {
-    fn execute(&self) -> Result<u64> {
-        let write_txn = self.conn.start_write()?;
-
-        let mut cursor =
-            BtreeCursor::new(self.table_page_id, &self.conn.pager, &self.conn.btree_ctx)?;
-        let mut n = 0;
-        for record in self.records.iter() {
-            let mut rowid = None;
-            if let Some(rowid_expr) = &record.rowid {
-                let (rowid_value, _, _) = rowid_expr.execute::<RowData>(None)?;
-                // NULL then fallback to generate new rowid.
-                if let Some(rowid_value) = rowid_value {
-                    match rowid_value.apply_numeric_affinity() {
-                        Value::Integer(rowid_value) => {
-                            rowid = Some(rowid_value);
-                        }
-                        _ => return Err(Error::DataTypeMismatch),
-                    }
-                }
-            }
-            let rowid = if let Some(rowid) = rowid {
-                rowid
-            } else {
-                cursor.move_to_last()?;
-                let last_rowid = cursor.get_table_key()?.unwrap_or(0);
-                // TODO: 32-bit rowid support.
-                if last_rowid == MAX_ROWID {
-                    todo!("find unused rowid randomly");
-                } else {
-                    last_rowid + 1
-                }
-            };
+/// Evaluate the value of a pragma as a boolean.
+///
+/// ON, YES and TRUE are true and OFF, NO and FALSE are false in addition to
+/// integers.
+fn eval_pragma_boolean(value: parser::Expr) -> Result<bool> {
+    if let parser::Expr::Column(name) | parser::Expr::Text(name) = &value {
+        let name = name.dequote();
+        let name = CaseInsensitiveBytes::from(name.as_slice());
+        if [b"on".as_slice(), b"yes", b"true"]
+            .iter()
+            .any(|keyword| name.equal_to_lower_bytes(keyword))
+        {
+            return Ok(true);
+        } else if [b"off".as_slice(), b"no", b"false"]
+            .iter()
+            .any(|keyword| name.equal_to_lower_bytes(keyword))
+        {
+            return Ok(false);
+        }
+    }
+    Ok(eval_pragma_integer(value)? != 0)
+}
 
-            // Check rowid conflict
-            let current_rowid = cursor.table_move_to(rowid)?;
-            if current_rowid.is_some() && current_rowid.unwrap() == rowid {
-                return Err(Error::UniqueConstraintViolation);
-            }
+/// The table or index name given to a pragma as an identifier or a string.
+fn pragma_name_argument(value: parser::Expr) -> Result<Vec<u8>> {
+    match value {
+        parser::Expr::Column(name) => Ok(name.dequote()),
+        parser::Expr::Text(text) => Ok(text.dequote()),
+        _ => Err(Error::Other(anyhow::anyhow!("invalid pragma argument"))),
+    }
+}
 
-            let mut columns = Vec::with_capacity(record.columns.len());
-            for (expr, type_affinity) in record.columns.iter() {
-                let (value, _, _) = expr.execute::<RowData>(None)?;
-                let value = value.map(|v| v.apply_affinity(*type_affinity));
-                columns.push(value);
-            }
+#[derive(Clone)]
+pub struct UserVersionStatement {
+    user_version: i32,
+}
 
-            cursor.table_insert(
-                rowid,
-                &RecordPayload::new(&columns.iter().map(|v| v.as_ref()).collect::<Vec<_>>())?,
-            )?;
+impl ExecutionStatement for UserVersionStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
 
-            let row_id = Value::Integer(rowid);
-            for index in self.indexes.iter() {
-                let index_columns = index
-                    .columns
-                    .iter()
-                    .map(|(column_number, _)| match column_number {
-                        ColumnNumber::RowId => Some(&row_id),
-                        ColumnNumber::Column(column_idx) => columns[*column_idx].as_ref(),
-                    })
-                    .collect::<Vec<_>>();
-                let comparators = index
-                    .columns
-                    .iter()
-                    .zip(index_columns.iter())
-                    .map(|((_, collation), v)| v.map(|v| ValueCmp::new(v, collation)))
-                    .collect::<Vec<_>>();
-                let mut index_cursor =
-                    BtreeCursor::new(index.root_page_id, &self.conn.pager, &self.conn.btree_ctx)?;
-                index_cursor.index_insert(&comparators, &RecordPayload::new(&index_columns)?)?;
-            }
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        let write_txn = conn.start_write()?;
 
-            n += 1;
-        }
+        conn.set_user_version(self.user_version)?;
 
         write_txn.commit()?;
 
-        Ok(n)
+        Ok(0)
+    }
+
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // PRAGMA has no parameters.
     }
 }
 
-pub struct ClearStatement<'conn> {
-    conn: &'conn Connection,
-    table_page_id: PageId,
-    index_page_ids: Vec<PageId>,
+/// PRAGMA foreign_keys = <boolean>.
+#[derive(Clone)]
+pub struct ForeignKeysStatement {
+    enabled: bool,
 }
 
-impl<'conn> ExecutionStatement for ClearStatement<'conn> {
-    fn execute(&self) -> Result<u64> {
-        let write_txn = self.conn.start_write()?;
+impl ExecutionStatement for ForeignKeysStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
 
-        let mut cursor =
-            BtreeCursor::new(self.table_page_id, &self.conn.pager, &self.conn.btree_ctx)?;
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        conn.foreign_keys.set(self.enabled);
+        Ok(0)
+    }
 
-        let n_deleted = cursor.clear()?;
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // PRAGMA has no parameters.
+    }
+}
 
-        for index_page_id in self.index_page_ids.iter() {
-            let mut cursor =
-                BtreeCursor::new(*index_page_id, &self.conn.pager, &self.conn.btree_ctx)?;
-            let n = cursor.clear()?;
-            if n != n_deleted {
-                return Err(Error::Other(anyhow::anyhow!(
-                    "number of deleted rows in table and index does not match"
-                )));
-            }
+/// Rebuild the database into a fresh image without free pages.
+#[derive(Clone)]
+pub struct VacuumStatement;
+
+impl ExecutionStatement for VacuumStatement {
+    fn clone_box(&self) -> Box<dyn ExecutionStatement> {
+        Box::new(self.clone())
+    }
+
+    fn execute(&self, conn: &Connection) -> Result<'static, u64> {
+        if conn.transaction.get() != TransactionState::None {
+            return Err(Error::Other(anyhow::anyhow!(
+                "cannot VACUUM from within a transaction"
+            )));
         }
+        if conn.schema.borrow().is_none() {
+            conn.load_schema()?;
+        }
+        let schema_cell = conn.schema.borrow();
+        let schema = schema_cell.as_ref().unwrap();
+
+        let write_txn = conn.start_write()?;
+
+        let image = self.build_image(conn, schema)?;
+        conn.pager
+            .replace_with(&image)
+            .map_err(anyhow::Error::from)?;
+        // The root pages of the tables and indexes are changed.
+        conn.increment_schema_cookie()?;
 
         write_txn.commit()?;
 
-        Ok(n_deleted)
+        Ok(0)
     }
-}
 
-pub struct DeleteStatement<'conn> {
-    conn: &'conn Connection,
-    table_page_id: PageId,
-    indexes: Vec<IndexSchema>,
-    filter: Expression,
-    query_plan: QueryPlan,
+    fn bind_parameter(&mut self, _idx: usize, _value: &Option<ConstantValue>) {
+        // VACUUM has no parameters.
+    }
 }
 
-impl<'conn> ExecutionStatement for DeleteStatement<'conn> {
-    fn execute(&self) -> Result<u64> {
-        let write_txn = self.conn.start_write()?;
-
-        let mut query = Query::new(
-            self.table_page_id,
-            &self.conn.pager,
-            &self.conn.btree_ctx,
-            &self.query_plan,
-            &self.filter,
+impl VacuumStatement {
+    /// Copy all the tables and indexes to a new in-memory database image.
+    fn build_image(&self, conn: &Connection, schema: &Schema) -> anyhow::Result<Pager> {
+        let page1 = conn.pager.get_page(PAGE_ID_1)?;
+        let mut image_page1 = conn.pager.allocate_tmp_page().to_vec();
+        image_page1[..DATABASE_HEADER_SIZE]
+            .copy_from_slice(&page1.buffer()[..DATABASE_HEADER_SIZE]);
+        drop(page1);
+        let header = DatabaseHeader::from(image_page1[..DATABASE_HEADER_SIZE].try_into().unwrap());
+        let pagesize = header.pagesize();
+        let usable_size = pagesize - header.reserved() as u32;
+        let mut header = DatabaseHeaderMut::from(
+            (&mut image_page1[..DATABASE_HEADER_SIZE])
+                .try_into()
+                .unwrap(),
+        );
+        header.set_n_pages(1);
+        header.set_first_freelist_trunk_page_id(None);
+        header.set_n_freelist_pages(0);
+        // The empty table leaf page of sqlite_schema.
+        let btree_header = &mut image_page1[DATABASE_HEADER_SIZE..];
+        btree_header[0] = BTREE_PAGE_TYPE_LEAF_TABLE;
+        btree::set_u16(btree_header, 5, non_zero_to_u16(usable_size));
+        let image = Pager::new(
+            MemoryStorage::new(image_page1),
+            1,
+            pagesize,
+            usable_size,
+            None,
+            0,
         )?;
+        let btree_ctx = &conn.btree_ctx;
 
-        let mut n_deleted = 0;
-
+        let mut schema_cursor = BtreeCursor::new(PAGE_ID_1, &conn.pager, btree_ctx)?;
+        let mut image_schema_cursor = BtreeCursor::new(PAGE_ID_1, &image, btree_ctx)?;
+        schema_cursor.move_to_first()?;
         loop {
-            let Some(data) = query.next()? else {
-                break;
+            let (rowid, mut columns) = {
+                let Some((rowid, payload)) = schema_cursor.get_table_payload()? else {
+                    break;
+                };
+                let mut record = parse_record(&payload)?;
+                let columns = (0..record.len())
+                    .map(|i| record.get(i).map(|v| v.map(ConstantValue::copy_from)))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                (rowid, columns)
             };
-
-            // Delete from index
-            for index in &self.indexes {
-                let tmp_keys = index
-                    .columns
-                    .iter()
-                    .map(|(column_idx, collation)| {
-                        let value = data
-                            .get_column_value(column_idx)
-                            .map_err(expression::Error::FailGetColumn)?;
-                        Ok((value, collation))
-                    })
-                    .collect::<Result<Vec<_>>>()?;
-                let comparators = tmp_keys
-                    .iter()
-                    .map(|(v, c)| v.as_ref().map(|v| ValueCmp::new(v, c)))
-                    .collect::<Vec<_>>();
-                let mut index_cursor =
-                    BtreeCursor::new(index.root_page_id, &self.conn.pager, &self.conn.btree_ctx)?;
-                index_cursor.index_move_to(&comparators)?;
-                index_cursor.delete()?;
+            let (Some(Some(ConstantValue::Text(type_))), Some(Some(ConstantValue::Text(name)))) =
+                (columns.first(), columns.get(1))
+            else {
+                bail!("invalid schema record");
+            };
+            if let Some(Some(ConstantValue::Integer(root_page_id))) = columns.get(3) {
+                // Views and triggers have no b-tree.
+                if let Some(root_page_id) = u32::try_from(*root_page_id).ok().and_then(PageId::new)
+                {
+                    // The collations of the keys if the b-tree is an index.
+                    let mut descending = Vec::new();
+                    let key_collations = match type_.as_slice() {
+                        b"table" => {
+                            let table = schema.get_table(name).context("table not found")?;
+                            match table.primary_key_autoindex {
+                                Some(n) if table.without_rowid => {
+                                    let key = &table.autoindexes[n];
+                                    descending.clone_from(&key.descending);
+                                    Some(
+                                        key.columns
+                                            .iter()
+                                            .map(|(_, collation)| collation.clone())
+                                            .collect::<Vec<_>>(),
+                                    )
+                                }
+                                _ => None,
+                            }
+                        }
+                        b"index" => {
+                            let index = schema.get_index(name).context("index not found")?;
+                            descending.clone_from(&index.descending);
+                            let mut collations = index
+                                .columns
+                                .iter()
+                                .map(|(_, collation)| collation.clone())
+                                .collect::<Vec<_>>();
+                            collations.push(DEFAULT_COLLATION.clone());
+                            Some(collations)
+                        }
+                        _ => bail!("invalid schema type"),
+                    };
+                    let new_root_page_id =
+                        create_btree(&image, btree_ctx, key_collations.is_none())?;
+                    let mut cursor = BtreeCursor::new(root_page_id, &conn.pager, btree_ctx)?;
+                    let mut image_cursor = BtreeCursor::new(new_root_page_id, &image, btree_ctx)?;
+                    image_cursor.set_index_order(&descending);
+                    if let Some(key_collations) = key_collations {
+                        copy_index(&mut cursor, &mut image_cursor, &key_collations)?;
+                    } else {
+                        copy_table(&mut cursor, image_cursor)?;
+                    }
+                    columns[3] = Some(ConstantValue::Integer(new_root_page_id.get() as i64));
+                }
             }
+            let columns = columns
+                .iter()
+                .map(|v| v.as_ref().map(ConstantValue::as_value))
+                .collect::<Vec<_>>();
+            let columns = columns.iter().map(Option::as_ref).collect::<Vec<_>>();
+            image_schema_cursor.table_insert(rowid, &RecordPayload::new(&columns)?)?;
+            schema_cursor.move_next()?;
+        }
+        drop(image_schema_cursor);
+        drop(schema_cursor);
 
-            drop(data);
-            query.delete()?;
+        let page1 = image.get_page(PAGE_ID_1)?;
+        let mut buffer = image.make_page_mut(&page1)?;
+        DatabaseHeaderMut::from((&mut buffer[..DATABASE_HEADER_SIZE]).try_into().unwrap())
+            .set_n_pages(image.num_pages());
+        drop(buffer);
+        drop(page1);
 
-            n_deleted += 1;
-        }
+        Ok(image)
+    }
+}
 
-        write_txn.commit()?;
+/// Copy all the entries of the table b-tree in rowid order.
+///
+/// The entries are bulk loaded into the empty table b-tree because they are
+/// sorted by rowid.
+fn copy_table(cursor: &mut BtreeCursor, dst_cursor: BtreeCursor) -> anyhow::Result<()> {
+    let mut loader = dst_cursor.into_table_bulk_loader()?;
+    let mut buf = Vec::new();
+    cursor.move_to_first()?;
+    loop {
+        let rowid = {
+            let Some((rowid, payload)) = cursor.get_table_payload()? else {
+                break;
+            };
+            buf.resize(payload.size().get() as usize, 0);
+            payload.load(0, &mut buf)?;
+            rowid
+        };
+        loader.append(rowid, &SlicePayload::new(&buf)?)?;
+        cursor.move_next()?;
+    }
+    loader.finish()?;
+    Ok(())
+}
 
-        Ok(n_deleted)
+/// Copy all the entries of the index b-tree in key order.
+fn copy_index(
+    cursor: &mut BtreeCursor,
+    dst_cursor: &mut BtreeCursor,
+    key_collations: &[Collation],
+) -> anyhow::Result<()> {
+    let mut buf = Vec::new();
+    cursor.move_to_first()?;
+    loop {
+        let keys = {
+            let Some(payload) = cursor.get_index_payload()? else {
+                break;
+            };
+            buf.resize(payload.size().get() as usize, 0);
+            payload.load(0, &mut buf)?;
+            let mut record = parse_record(&payload)?;
+            (0..key_collations.len().min(record.len()))
+                .map(|i| record.get(i).map(|v| v.map(ConstantValue::copy_from)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        let keys = keys
+            .iter()
+            .map(|v| v.as_ref().map(ConstantValue::as_value))
+            .collect::<Vec<_>>();
+        let comparators = keys
+            .iter()
+            .zip(key_collations)
+            .map(|(v, collation)| v.as_ref().map(|v| ValueCmp::new(v, collation)))
+            .collect::<Vec<_>>();
+        dst_cursor.index_insert(&comparators, &SlicePayload::new(&buf)?)?;
+        cursor.move_next()?;
     }
+    Ok(())
 }
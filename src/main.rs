@@ -4,7 +4,6 @@ use std::io::Write;
 use std::path::Path;
 
 use prsqlite::Connection;
-use prsqlite::Statement;
 
 fn main() {
     let mut args = env::args();
@@ -36,35 +35,30 @@ fn main() {
                         continue;
                     }
                 };
-                match stmt {
-                    Statement::Query(stmt) => {
-                        let mut rows = stmt.query().expect("execute statement");
-                        loop {
-                            let row = match rows.next_row() {
-                                Ok(Some(row)) => row,
-                                Ok(None) => break,
-                                Err(e) => {
-                                    eprintln!("{e}");
-                                    break;
-                                }
-                            };
-                            let columns = row.parse().expect("parse row");
-                            for i in 0..columns.len() {
-                                if i > 0 {
-                                    print!("|");
-                                }
-                                if let Some(v) = columns.get(i) {
-                                    v.display(&mut stdout).expect("display column");
-                                }
+                if stmt.is_query() {
+                    let mut rows = stmt.query().expect("execute statement");
+                    loop {
+                        let row = match rows.next_row() {
+                            Ok(Some(row)) => row,
+                            Ok(None) => break,
+                            Err(e) => {
+                                eprintln!("{e}");
+                                break;
+                            }
+                        };
+                        let columns = row.parse().expect("parse row");
+                        for i in 0..columns.len() {
+                            if i > 0 {
+                                print!("|");
+                            }
+                            if let Some(v) = columns.get(i) {
+                                v.display(&mut stdout).expect("display column");
                             }
-                            println!();
-                        }
-                    }
-                    Statement::Execution(stmt) => {
-                        if let Err(e) = stmt.execute() {
-                            eprintln!("{e}");
                         }
+                        println!();
                     }
+                } else if let Err(e) = stmt.execute() {
+                    eprintln!("{e}");
                 }
             }
         }
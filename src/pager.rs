@@ -19,6 +19,7 @@ use std::cell::Ref;
 use std::cell::RefCell;
 use std::cell::RefMut;
 use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs::File;
@@ -32,6 +33,8 @@ use std::rc::Rc;
 use crate::header::DatabaseHeader;
 use crate::header::DatabaseHeaderMut;
 use crate::header::DATABASE_HEADER_SIZE;
+use crate::journal::Journal;
+use crate::journal::WriterLock;
 use crate::payload::CopiablePayload;
 use crate::payload::PayloadSize;
 
@@ -150,19 +153,143 @@ pub fn swap_page_buffer(a: &mut PageBufferMut, b: &mut PageBufferMut) {
     std::mem::swap(&mut a.0.buf, &mut b.0.buf);
 }
 
+/// The backing store of the database pages.
+pub trait Storage {
+    /// Read exactly `buf.len()` bytes at the offset.
+    fn read(&self, buf: &mut [u8], offset: u64) -> io::Result<()>;
+    /// Write the whole buffer at the offset, growing the storage if needed.
+    fn write(&self, buf: &[u8], offset: u64) -> io::Result<()>;
+    /// The size of the storage in bytes.
+    fn size(&self) -> io::Result<u64>;
+    /// Truncate or extend the storage to the size.
+    fn truncate(&self, size: u64) -> io::Result<()>;
+    /// Flush the written data to the durable storage.
+    fn sync(&self) -> io::Result<()>;
+}
+
+impl Storage for File {
+    fn read(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        FileExt::read_exact_at(self, buf, offset)
+    }
+
+    fn write(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+        FileExt::write_all_at(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn truncate(&self, size: u64) -> io::Result<()> {
+        self.set_len(size)
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        self.sync_all()
+    }
+}
+
+impl<S: Storage + ?Sized> Storage for Rc<S> {
+    fn read(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        self.as_ref().read(buf, offset)
+    }
+
+    fn write(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+        self.as_ref().write(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.as_ref().size()
+    }
+
+    fn truncate(&self, size: u64) -> io::Result<()> {
+        self.as_ref().truncate(size)
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        self.as_ref().sync()
+    }
+}
+
+/// [Storage] backed by a buffer in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStorage(RefCell<Vec<u8>>);
+
+impl MemoryStorage {
+    pub fn new(buf: Vec<u8>) -> Self {
+        Self(RefCell::new(buf))
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        let data = self.0.borrow();
+        let (start, end) = memory_range(offset, buf.len())?;
+        match data.get(start..end) {
+            Some(src) => {
+                buf.copy_from_slice(src);
+                Ok(())
+            }
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    fn write(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+        let mut data = self.0.borrow_mut();
+        let (start, end) = memory_range(offset, buf.len())?;
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.0.borrow().len() as u64)
+    }
+
+    fn truncate(&self, size: u64) -> io::Result<()> {
+        self.0.borrow_mut().resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The range of the buffer at the file offset in memory.
+///
+/// The offset of a large database may not fit in `usize` on 32-bit targets.
+fn memory_range(offset: u64, len: usize) -> io::Result<(usize, usize)> {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| Some((start, start.checked_add(len)?)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "offset too large"))
+}
+
 pub struct Pager {
-    file: File,
+    file: Box<dyn Storage>,
+    journal: Option<Journal>,
+    /// Held from [Pager::lock_writer()] until [Pager::commit()] or
+    /// [Pager::abort()].
+    writer_lock: RefCell<Option<WriterLock>>,
     cache: PageCache,
     n_pages: Cell<u32>,
     n_pages_stable: Cell<u32>,
     first_freelist_trunk_page_id: Cell<Option<PageId>>,
     n_freelist_pages: Cell<u32>,
     usable_size: u32,
+    /// The number of pages to read ahead on sequential scans.
+    readahead: Cell<u32>,
+    /// The change counter and the schema cookie of the file when the cached
+    /// pages were read or committed. [None] until [Pager::refresh()] is called.
+    file_version: Cell<Option<(u32, u32)>>,
 }
 
 impl Pager {
-    pub fn new(
-        file: File,
+    pub fn new<S: Storage + 'static>(
+        file: S,
         n_pages: u32,
         pagesize: u32,
         usable_size: u32,
@@ -172,23 +299,142 @@ impl Pager {
         if n_pages > MAX_PAGE_ID {
             return Err(Error::InvalidFile);
         }
-        let file_len = file.metadata()?.len();
+        let file_len = file.size()?;
         if file_len % pagesize as u64 != 0 {
             todo!("file size mismatch");
         } else if file_len / (pagesize as u64) != n_pages as u64 {
             todo!("file size mismatch");
         }
         Ok(Self {
-            file,
+            file: Box::new(file),
+            journal: None,
+            writer_lock: RefCell::new(None),
             cache: PageCache::new(pagesize),
             n_pages: Cell::new(n_pages),
             n_pages_stable: Cell::new(n_pages),
             first_freelist_trunk_page_id: Cell::new(first_freelist_trunk_page_id),
             n_freelist_pages: Cell::new(n_freelist_pages),
             usable_size,
+            readahead: Cell::new(0),
+            file_version: Cell::new(None),
         })
     }
 
+    /// Drop all the cached pages if another connection committed to the file
+    /// since they were read. Returns whether the cache was dropped.
+    ///
+    /// The database header is read from the file bypassing the cache. This
+    /// must be called only while no page is modified or referenced.
+    pub fn refresh(&self) -> Result<bool> {
+        let mut buf = [0; DATABASE_HEADER_SIZE];
+        self.file.read(&mut buf, 0)?;
+        let header = DatabaseHeader::from(&buf);
+        let file_version = (header.change_counter(), header.schema_cookie());
+        if Some(file_version) == self.file_version.get() {
+            return Ok(false);
+        }
+        if header.pagesize() != self.cache.pagesize {
+            return Err(Error::InvalidFile);
+        }
+        let n_pages = if header.is_n_pages_valid() {
+            header.n_pages()
+        } else {
+            u32::try_from(self.file.size()? / self.cache.pagesize as u64)
+                .map_err(|_| Error::InvalidFile)?
+        };
+        if n_pages > MAX_PAGE_ID {
+            return Err(Error::InvalidFile);
+        }
+        self.cache.clear();
+        self.n_pages.set(n_pages);
+        self.n_pages_stable.set(n_pages);
+        self.first_freelist_trunk_page_id
+            .set(header.first_freelist_trunk_page_id());
+        self.n_freelist_pages.set(header.n_freelist_pages());
+        self.file_version.set(Some(file_version));
+        Ok(true)
+    }
+
+    /// Use the rollback journal on commit.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
+    /// Tell other connections in this process that the file is being written
+    /// until the changes are committed or aborted.
+    ///
+    /// They do not roll back the journal of the file meanwhile.
+    pub fn lock_writer(&self) {
+        if let Some(journal) = &self.journal {
+            let mut writer_lock = self.writer_lock.borrow_mut();
+            if writer_lock.is_none() {
+                *writer_lock = Some(journal.lock_writer());
+            }
+        }
+    }
+
+    /// Set the maximum number of pages kept in the page cache.
+    pub fn set_cache_size(&self, n_pages: usize) {
+        self.cache.set_capacity(n_pages);
+    }
+
+    /// Statistics of the page cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats.get()
+    }
+
+    /// Set the number of pages to read ahead on sequential scans. 0 disables
+    /// readahead.
+    pub fn set_readahead(&self, n_pages: u32) {
+        self.readahead.set(n_pages);
+    }
+
+    pub fn readahead(&self) -> u32 {
+        self.readahead.get()
+    }
+
+    /// Read the pages into the cache in advance.
+    ///
+    /// Nothing is read if the first page is already cached so that callers
+    /// sliding a window over the pages read them in batches. Contiguous pages
+    /// are read at once. Prefetching does not count as cache lookups.
+    pub fn prefetch(&self, page_ids: &[PageId]) -> Result<()> {
+        if page_ids.is_empty() || self.cache.contains(page_ids[0]) {
+            return Ok(());
+        }
+        // Pages beyond the file are not written yet.
+        let mut pages = page_ids
+            .iter()
+            .filter(|page_id| page_id.get() <= self.n_pages_stable.get())
+            .filter_map(|page_id| {
+                self.cache
+                    .insert_page(*page_id)
+                    .map(|page| (*page_id, page))
+            })
+            .collect::<Vec<_>>();
+        pages.sort_by_key(|(page_id, _)| *page_id);
+
+        let pagesize = self.cache.pagesize as usize;
+        let mut buf = Vec::new();
+        let mut result = Ok(());
+        for run in pages.chunk_by(|(a, _), (b, _)| a.get() + 1 == b.get()) {
+            buf.resize(run.len() * pagesize, 0);
+            if let Err(e) = self.file.read(&mut buf, self.page_offset(run[0].0)) {
+                result = Err(e.into());
+                break;
+            }
+            for ((_, page), chunk) in run.iter().zip(buf.chunks_exact(pagesize)) {
+                page.borrow_mut().buf.copy_from_slice(chunk);
+            }
+        }
+        if result.is_err() {
+            for (page_id, _) in pages {
+                self.cache.delete_page(page_id);
+            }
+        }
+        result
+    }
+
     pub fn allocate_page(&self) -> Result<(PageId, MemPage)> {
         let page_id = if let Some(page_id) = self.allocate_from_freelist()? {
             page_id
@@ -202,8 +448,7 @@ impl Pager {
         };
 
         let (page, _) = self.cache.get_page(page_id);
-        page.try_borrow_mut()?.is_dirty = true;
-        // TODO: setup journal
+        self.cache.mark_dirty(&mut *page.try_borrow_mut()?);
 
         let header_offset = if page_id == PAGE_ID_1 {
             DATABASE_HEADER_SIZE
@@ -224,12 +469,12 @@ impl Pager {
             let (page1, is_new) = self.cache.get_page(PAGE_ID_1);
             let mut page1 = if is_new {
                 let mut page1 = page1.borrow_mut();
-                self.file.read_exact_at(&mut page1.buf, 0)?;
+                self.file.read(&mut page1.buf, 0)?;
                 page1
             } else {
                 page1.try_borrow_mut()?
             };
-            page1.is_dirty = true;
+            self.cache.mark_dirty(&mut page1);
             let buffer = &mut page1.buf;
             let mut header =
                 DatabaseHeaderMut::from((&mut buffer[..DATABASE_HEADER_SIZE]).try_into().unwrap());
@@ -238,7 +483,7 @@ impl Pager {
             let mut trunk_page = if is_new {
                 let mut trunk_page = trunk_page.borrow_mut();
                 self.file
-                    .read_exact_at(&mut trunk_page.buf, self.page_offset(first_page_id))?;
+                    .read(&mut trunk_page.buf, self.page_offset(first_page_id))?;
                 trunk_page
             } else {
                 trunk_page.try_borrow_mut()?
@@ -249,12 +494,19 @@ impl Pager {
             if n_pages > self.usable_size / 4 - 2 {
                 return Err(Error::InvalidFile);
             }
+            // The freelist must have as many pages as the header says. Otherwise a
+            // looping trunk chain could hand out the same page again and again.
+            if self.n_freelist_pages.get() == 0 {
+                return Err(Error::InvalidFile);
+            }
 
             let page_id = if n_pages == 0 {
-                self.first_freelist_trunk_page_id
-                    .set(PageId::new(u32::from_be_bytes(
-                        trunk_buffer[0..4].try_into().unwrap(),
-                    )));
+                let next_trunk_page_id =
+                    PageId::new(u32::from_be_bytes(trunk_buffer[0..4].try_into().unwrap()));
+                if next_trunk_page_id == Some(first_page_id) {
+                    return Err(Error::InvalidFile);
+                }
+                self.first_freelist_trunk_page_id.set(next_trunk_page_id);
                 header.set_first_freelist_trunk_page_id_raw(trunk_buffer[0..4].try_into().unwrap());
                 first_page_id
             } else {
@@ -262,8 +514,10 @@ impl Pager {
                 let next_page_id = PageId::new(u32::from_be_bytes(
                     trunk_buffer[offset..offset + 4].try_into().unwrap(),
                 ))
-                .unwrap();
+                .ok_or(Error::InvalidFile)?;
                 trunk_buffer[4..8].copy_from_slice(&(n_pages - 1).to_be_bytes());
+                // The trunk page must be written on commit as well as the header.
+                self.cache.mark_dirty(&mut trunk_page);
                 next_page_id
             };
 
@@ -281,6 +535,30 @@ impl Pager {
         }
     }
 
+    /// Replace all the pages with the pages of the other database image.
+    ///
+    /// The file is truncated on commit if the image has less pages. The page
+    /// sizes must be the same.
+    pub fn replace_with(&self, image: &Pager) -> Result<()> {
+        assert_eq!(self.cache.pagesize, image.cache.pagesize);
+        for page_id in 1..=image.num_pages() {
+            let page_id = PageId::new(page_id).unwrap();
+            let src = image.get_page(page_id)?;
+            let (page, _) = self.cache.get_page(page_id);
+            let mut page = page.try_borrow_mut()?;
+            page.buf.copy_from_slice(&src.buffer());
+            self.cache.mark_dirty(&mut page);
+        }
+        for page_id in image.num_pages() + 1..=self.n_pages.get() {
+            self.cache.delete_page(PageId::new(page_id).unwrap());
+        }
+        self.n_pages.set(image.num_pages());
+        self.first_freelist_trunk_page_id
+            .set(image.first_freelist_trunk_page_id.get());
+        self.n_freelist_pages.set(image.n_freelist_pages.get());
+        Ok(())
+    }
+
     pub fn allocate_tmp_page(&self) -> TemporaryPage {
         TemporaryPage(vec![0_u8; self.cache.pagesize as usize])
     }
@@ -293,7 +571,7 @@ impl Pager {
         if is_new {
             let mut raw_page = page.borrow_mut();
             self.file
-                .read_exact_at(&mut raw_page.buf, self.page_offset(page_id))?;
+                .read(&mut raw_page.buf, self.page_offset(page_id))?;
         }
         let header_offset = if page_id == PAGE_ID_1 {
             DATABASE_HEADER_SIZE
@@ -310,7 +588,7 @@ impl Pager {
         let mut raw_page = page.page.try_borrow_mut()?;
 
         if !raw_page.is_dirty {
-            raw_page.is_dirty = true;
+            self.cache.mark_dirty(&mut raw_page);
             // TODO: setup journal
         }
 
@@ -327,12 +605,12 @@ impl Pager {
         let (page1, is_new) = self.cache.get_page(PAGE_ID_1);
         let mut page1 = if is_new {
             let mut page1 = page1.borrow_mut();
-            self.file.read_exact_at(&mut page1.buf, 0)?;
+            self.file.read(&mut page1.buf, 0)?;
             page1
         } else {
             page1.try_borrow_mut()?
         };
-        page1.is_dirty = true;
+        self.cache.mark_dirty(&mut page1);
         let buffer = &mut page1.buf;
         let mut header =
             DatabaseHeaderMut::from((&mut buffer[..DATABASE_HEADER_SIZE]).try_into().unwrap());
@@ -348,7 +626,7 @@ impl Pager {
             let mut trunk_page = if is_new {
                 let mut trunk_page = trunk_page.borrow_mut();
                 self.file
-                    .read_exact_at(&mut trunk_page.buf, self.page_offset(first_page_id))?;
+                    .read(&mut trunk_page.buf, self.page_offset(first_page_id))?;
                 trunk_page
             } else {
                 trunk_page.try_borrow_mut()?
@@ -368,7 +646,7 @@ impl Pager {
                 buffer[4..8].copy_from_slice(&(n_pages + 1).to_be_bytes());
                 let offset = (n_pages * 4) as usize + 8;
                 buffer[offset..offset + 4].copy_from_slice(&page_id.get().to_be_bytes());
-                trunk_page.is_dirty = true;
+                self.cache.mark_dirty(&mut trunk_page);
                 self.cache.delete_page(page_id);
                 return Ok(());
             } else if n_pages > max_freelist_pages {
@@ -382,7 +660,7 @@ impl Pager {
         // Use the deleted page as a new freelist trunk page.
         let (deleted_page, _) = self.cache.get_page(page_id);
         let mut deleted_page = deleted_page.try_borrow_mut()?;
-        deleted_page.is_dirty = true;
+        self.cache.mark_dirty(&mut deleted_page);
         deleted_page.buf[..4].copy_from_slice(
             &self
                 .first_freelist_trunk_page_id
@@ -403,27 +681,94 @@ impl Pager {
     ///
     /// No reference to buffers of any dirty pages must be kept when commiting.
     pub fn commit(&self) -> Result<()> {
-        for (page_id, page) in self.cache.map.borrow().iter() {
-            let raw_page = page.try_borrow()?;
-            if raw_page.is_dirty {
-                self.file
-                    .write_all_at(&raw_page.buf, self.page_offset(*page_id))?;
-                drop(raw_page);
-                page.try_borrow_mut()?.is_dirty = false;
+        let map = self.cache.map.borrow();
+        let mut dirty_pages = Vec::new();
+        for (page_id, entry) in map.iter() {
+            if entry.page.try_borrow()?.is_dirty {
+                dirty_pages.push((*page_id, &entry.page));
             }
         }
+        dirty_pages.sort_by_key(|(page_id, _)| *page_id);
+
+        if let Some(journal) = &self.journal {
+            if !dirty_pages.is_empty() || self.n_pages.get() < self.n_pages_stable.get() {
+                self.write_journal(journal, &dirty_pages)?;
+                if let Err(e) = self.write_pages(&dirty_pages) {
+                    // Restore the pages partially written.
+                    journal.rollback(self.file.as_ref())?;
+                    return Err(e);
+                }
+                journal.delete()?;
+            }
+        } else {
+            self.write_pages(&dirty_pages)?;
+        }
+
+        for (page_id, page) in &dirty_pages {
+            let mut page = page.try_borrow_mut()?;
+            if *page_id == PAGE_ID_1 {
+                let header =
+                    DatabaseHeader::from(page.buf[..DATABASE_HEADER_SIZE].try_into().unwrap());
+                self.file_version
+                    .set(Some((header.change_counter(), header.schema_cookie())));
+            }
+            page.is_dirty = false;
+        }
+        let page_ids = dirty_pages
+            .iter()
+            .map(|(page_id, _)| *page_id)
+            .collect::<Vec<_>>();
+        drop(dirty_pages);
+        drop(map);
+        self.cache.mark_clean(&page_ids);
         self.n_pages_stable.set(self.n_pages.get());
+        self.writer_lock.take();
+        Ok(())
+    }
+
+    /// Save the original contents of the dirty pages to the journal.
+    fn write_journal(
+        &self,
+        journal: &Journal,
+        dirty_pages: &[(PageId, &Rc<RefCell<RawPage>>)],
+    ) -> Result<()> {
+        let mut writer = journal.create(self.cache.pagesize, self.n_pages_stable.get())?;
+        let mut buf = self.allocate_tmp_page();
+        for (page_id, _) in dirty_pages {
+            // New pages are discarded by truncating the file on rollback.
+            if page_id.get() <= self.n_pages_stable.get() {
+                self.file.read(&mut buf, self.page_offset(*page_id))?;
+                writer.append(*page_id, &buf)?;
+            }
+        }
+        // Pages truncated from the file.
+        for page_id in self.n_pages.get() + 1..=self.n_pages_stable.get() {
+            let page_id = PageId::new(page_id).unwrap();
+            self.file.read(&mut buf, self.page_offset(page_id))?;
+            writer.append(page_id, &buf)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn write_pages(&self, dirty_pages: &[(PageId, &Rc<RefCell<RawPage>>)]) -> Result<()> {
+        for (page_id, page) in dirty_pages {
+            self.file
+                .write(&page.try_borrow()?.buf, self.page_offset(*page_id))?;
+        }
+        if self.n_pages.get() < self.n_pages_stable.get() {
+            self.file
+                .truncate(self.n_pages.get() as u64 * self.cache.pagesize as u64)?;
+        }
+        self.file.sync()?;
         Ok(())
     }
 
     pub fn abort(&self) {
+        self.writer_lock.take();
         self.n_pages.set(self.n_pages_stable.get());
 
-        // Drop all dirty pages.
-        self.cache
-            .map
-            .borrow_mut()
-            .retain(|_, page| !page.borrow().is_dirty);
+        self.cache.drop_dirty_pages();
 
         // Invalidate cached database header.
         if self.n_pages.get() > 0 {
@@ -431,7 +776,7 @@ impl Pager {
             if is_new {
                 let mut page1 = page1.borrow_mut();
                 self.file
-                    .read_exact_at(&mut page1.buf, 0)
+                    .read(&mut page1.buf, 0)
                     .expect("read page 1 must succeed");
             }
             let buffer = &page1.borrow().buf;
@@ -443,14 +788,26 @@ impl Pager {
             self.first_freelist_trunk_page_id.set(None);
             self.n_freelist_pages.set(0);
         }
-
-        // TODO: rollback journal
     }
 
     pub fn is_file_size_changed(&self) -> bool {
         self.n_pages.get() != self.n_pages_stable.get()
     }
 
+    /// Whether any page is modified or the file size is changed since the last
+    /// commit.
+    pub fn is_modified(&self) -> Result<bool> {
+        if self.is_file_size_changed() {
+            return Ok(true);
+        }
+        for entry in self.cache.map.borrow().values() {
+            if entry.page.try_borrow()?.is_dirty {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     pub fn num_pages(&self) -> u32 {
         self.n_pages.get()
     }
@@ -476,7 +833,7 @@ pub struct MemPage {
 
 impl MemPage {
     #[inline(always)]
-    pub fn buffer(&self) -> PageBuffer {
+    pub fn buffer(&self) -> PageBuffer<'_> {
         PageBuffer(self.page.borrow())
     }
 }
@@ -512,21 +869,62 @@ impl CopiablePayload for PagePayload {
 }
 
 struct RawPage {
+    id: PageId,
     buf: Vec<u8>,
+    /// Set by [PageCache::mark_dirty()].
     is_dirty: bool,
 }
 
 impl RawPage {
-    fn new(pagesize: u32) -> Self {
+    fn new(id: PageId, pagesize: u32) -> Self {
         Self {
+            id,
             buf: vec![0_u8; pagesize as usize],
             is_dirty: false,
         }
     }
 }
 
+/// The default maximum number of pages in [PageCache].
+pub const DEFAULT_CACHE_SIZE: usize = 2000;
+
+/// Statistics of the lookups of the page cache or the statement cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The ratio of hits to all lookups. Returns 0 if there is no lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+struct CacheEntry {
+    page: Rc<RefCell<RawPage>>,
+    /// The key in [PageCache::lru]. [None] if the page is dirty.
+    last_used: Option<u64>,
+}
+
+/// Page cache with LRU eviction.
+///
+/// Only clean pages which no one refers to are evicted. The cache may exceed
+/// the capacity if there are many dirty or referenced pages.
 struct PageCache {
-    map: RefCell<HashMap<PageId, Rc<RefCell<RawPage>>>>,
+    map: RefCell<HashMap<PageId, CacheEntry>>,
+    /// Clean pages ordered by the last used time. Dirty pages are kept out of
+    /// the list until they are committed so that eviction does not scan them.
+    lru: RefCell<BTreeMap<u64, PageId>>,
+    clock: Cell<u64>,
+    capacity: Cell<usize>,
+    stats: Cell<CacheStats>,
     pagesize: u32,
 }
 
@@ -534,23 +932,160 @@ impl PageCache {
     fn new(pagesize: u32) -> Self {
         Self {
             map: RefCell::new(HashMap::new()),
+            lru: RefCell::new(BTreeMap::new()),
+            clock: Cell::new(0),
+            capacity: Cell::new(DEFAULT_CACHE_SIZE),
+            stats: Cell::new(CacheStats::default()),
             pagesize,
         }
     }
 
     fn get_page(&self, id: PageId) -> (Rc<RefCell<RawPage>>, bool) {
-        match self.map.borrow_mut().entry(id) {
-            Entry::Occupied(entry) => (entry.get().clone(), false),
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+        let mut stats = self.stats.get();
+        let mut map = self.map.borrow_mut();
+        let mut lru = self.lru.borrow_mut();
+        let result = match map.entry(id) {
+            Entry::Occupied(mut entry) => {
+                stats.hits += 1;
+                let entry = entry.get_mut();
+                if let Some(last_used) = entry.last_used {
+                    lru.remove(&last_used);
+                    lru.insert(now, id);
+                    entry.last_used = Some(now);
+                }
+                (entry.page.clone(), false)
+            }
             Entry::Vacant(entry) => {
-                let page = Rc::new(RefCell::new(RawPage::new(self.pagesize)));
-                entry.insert(page.clone());
+                stats.misses += 1;
+                let page = Rc::new(RefCell::new(RawPage::new(id, self.pagesize)));
+                entry.insert(CacheEntry {
+                    page: page.clone(),
+                    last_used: Some(now),
+                });
+                lru.insert(now, id);
                 (page, true)
             }
+        };
+        self.stats.set(stats);
+        if result.1 {
+            Self::evict(&mut map, &mut lru, self.capacity.get());
         }
+        result
+    }
+
+    /// Evict least recently used pages until the number of pages fits in the
+    /// capacity.
+    fn evict(
+        map: &mut HashMap<PageId, CacheEntry>,
+        lru: &mut BTreeMap<u64, PageId>,
+        capacity: usize,
+    ) {
+        if map.len() <= capacity {
+            return;
+        }
+        let n_evict = map.len() - capacity;
+        let evicted = lru
+            .iter()
+            // The page referred by others must be kept.
+            .filter(|(_, id)| Rc::strong_count(&map.get(id).unwrap().page) == 1)
+            .take(n_evict)
+            .map(|(last_used, id)| (*last_used, *id))
+            .collect::<Vec<_>>();
+        for (last_used, id) in evicted {
+            lru.remove(&last_used);
+            map.remove(&id);
+        }
+    }
+
+    fn contains(&self, id: PageId) -> bool {
+        self.map.borrow().contains_key(&id)
+    }
+
+    /// Insert a new page without counting a lookup. Returns [None] if the page
+    /// is already cached.
+    fn insert_page(&self, id: PageId) -> Option<Rc<RefCell<RawPage>>> {
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+        let mut map = self.map.borrow_mut();
+        let mut lru = self.lru.borrow_mut();
+        let Entry::Vacant(entry) = map.entry(id) else {
+            return None;
+        };
+        let page = Rc::new(RefCell::new(RawPage::new(id, self.pagesize)));
+        entry.insert(CacheEntry {
+            page: page.clone(),
+            last_used: Some(now),
+        });
+        lru.insert(now, id);
+        Self::evict(&mut map, &mut lru, self.capacity.get());
+        Some(page)
+    }
+
+    fn set_capacity(&self, capacity: usize) {
+        self.capacity.set(capacity);
+        Self::evict(
+            &mut self.map.borrow_mut(),
+            &mut self.lru.borrow_mut(),
+            capacity,
+        );
     }
 
     fn delete_page(&self, id: PageId) {
-        self.map.borrow_mut().remove(&id);
+        if let Some(CacheEntry {
+            last_used: Some(last_used),
+            ..
+        }) = self.map.borrow_mut().remove(&id)
+        {
+            self.lru.borrow_mut().remove(&last_used);
+        }
+    }
+
+    /// Mark the page as dirty and remove it from the LRU list.
+    fn mark_dirty(&self, page: &mut RawPage) {
+        if page.is_dirty {
+            return;
+        }
+        page.is_dirty = true;
+        if let Some(entry) = self.map.borrow_mut().get_mut(&page.id) {
+            if let Some(last_used) = entry.last_used.take() {
+                self.lru.borrow_mut().remove(&last_used);
+            }
+        }
+    }
+
+    /// Put the committed pages back to the LRU list as the most recently used
+    /// pages.
+    fn mark_clean(&self, ids: &[PageId]) {
+        let mut map = self.map.borrow_mut();
+        let mut lru = self.lru.borrow_mut();
+        for id in ids {
+            let Some(entry) = map.get_mut(id) else {
+                continue;
+            };
+            if entry.last_used.is_none() {
+                let now = self.clock.get() + 1;
+                self.clock.set(now);
+                lru.insert(now, *id);
+                entry.last_used = Some(now);
+            }
+        }
+        Self::evict(&mut map, &mut lru, self.capacity.get());
+    }
+
+    /// Drop all pages.
+    fn clear(&self) {
+        self.map.borrow_mut().clear();
+        self.lru.borrow_mut().clear();
+    }
+
+    /// Drop all dirty pages.
+    fn drop_dirty_pages(&self) {
+        // Dirty pages are not in the LRU list.
+        self.map
+            .borrow_mut()
+            .retain(|_, entry| entry.last_used.is_some());
     }
 }
 
@@ -603,6 +1138,149 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_page_cache_eviction() {
+        let file = tempfile::tempfile().unwrap();
+        for i in 0..4 {
+            file.write_all_at(&[i + 1; 4096], 4096 * i as u64).unwrap();
+        }
+        let pager = Pager::new(file, 4, 4096, 4096, None, 0).unwrap();
+        pager.set_cache_size(2);
+        let page_ids = (1..=4).map(|i| PageId::new(i).unwrap()).collect::<Vec<_>>();
+
+        for page_id in &page_ids[..3] {
+            pager.get_page(*page_id).unwrap();
+        }
+        assert_eq!(pager.cache.map.borrow().len(), 2);
+        assert_eq!(pager.cache_stats(), CacheStats { hits: 0, misses: 3 });
+        // Page 3 is still cached and page 1 was evicted.
+        pager.get_page(page_ids[2]).unwrap();
+        assert_eq!(pager.cache_stats(), CacheStats { hits: 1, misses: 3 });
+        pager.get_page(page_ids[0]).unwrap();
+        assert_eq!(pager.cache_stats(), CacheStats { hits: 1, misses: 4 });
+
+        // Dirty pages and referenced pages are not evicted.
+        let page = pager.get_page(page_ids[1]).unwrap();
+        pager.make_page_mut(&page).unwrap().fill(10);
+        drop(page);
+        let page3 = pager.get_page(page_ids[2]).unwrap();
+        let page4 = pager.get_page(page_ids[3]).unwrap();
+        assert_eq!(pager.cache.map.borrow().len(), 3);
+        // The dirty page is out of the LRU list.
+        assert_eq!(pager.cache.lru.borrow().len(), 2);
+        assert_eq!(
+            pager.get_page(page_ids[1]).unwrap().buffer().deref(),
+            [10; 4096].as_slice()
+        );
+        assert_eq!(page3.buffer().deref(), [3; 4096].as_slice());
+        assert_eq!(page4.buffer().deref(), [4; 4096].as_slice());
+
+        // Committed pages can be evicted.
+        drop(page3);
+        drop(page4);
+        pager.commit().unwrap();
+        pager.set_cache_size(1);
+        assert_eq!(pager.cache.map.borrow().len(), 1);
+        assert_eq!(pager.cache.lru.borrow().len(), 1);
+        let stats = pager.cache_stats();
+        assert_eq!(
+            pager.get_page(page_ids[1]).unwrap().buffer().deref(),
+            [10; 4096].as_slice()
+        );
+        assert_eq!(pager.cache_stats().hits, stats.hits + 1);
+    }
+
+    #[test]
+    fn test_prefetch() {
+        struct CountingStorage {
+            storage: MemoryStorage,
+            n_reads: Cell<usize>,
+        }
+
+        impl Storage for CountingStorage {
+            fn read(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+                self.n_reads.set(self.n_reads.get() + 1);
+                self.storage.read(buf, offset)
+            }
+
+            fn write(&self, buf: &[u8], offset: u64) -> io::Result<()> {
+                self.storage.write(buf, offset)
+            }
+
+            fn size(&self) -> io::Result<u64> {
+                self.storage.size()
+            }
+
+            fn truncate(&self, size: u64) -> io::Result<()> {
+                self.storage.truncate(size)
+            }
+
+            fn sync(&self) -> io::Result<()> {
+                self.storage.sync()
+            }
+        }
+
+        let mut buf = Vec::new();
+        for i in 0..8 {
+            buf.extend_from_slice(&[i + 1; 512]);
+        }
+        let storage = Rc::new(CountingStorage {
+            storage: MemoryStorage::new(buf),
+            n_reads: Cell::new(0),
+        });
+        let pager = Pager::new(storage.clone(), 8, 512, 512, None, 0).unwrap();
+        let page_ids = (1..=8).map(|i| PageId::new(i).unwrap()).collect::<Vec<_>>();
+
+        // Pages 2, 3, 4 and pages 6, 7 are contiguous.
+        pager
+            .prefetch(&[
+                page_ids[1],
+                page_ids[3],
+                page_ids[2],
+                page_ids[6],
+                page_ids[5],
+            ])
+            .unwrap();
+        assert_eq!(storage.n_reads.get(), 2);
+        assert_eq!(pager.cache_stats(), CacheStats::default());
+        for i in [1, 2, 3, 5, 6] {
+            assert_eq!(
+                pager.get_page(page_ids[i]).unwrap().buffer().deref(),
+                [i as u8 + 1; 512].as_slice()
+            );
+        }
+        assert_eq!(storage.n_reads.get(), 2);
+        assert_eq!(pager.cache_stats(), CacheStats { hits: 5, misses: 0 });
+
+        // Nothing is read if the first page is cached.
+        pager.prefetch(&[page_ids[1], page_ids[7]]).unwrap();
+        assert_eq!(storage.n_reads.get(), 2);
+
+        // Cached pages are skipped.
+        pager
+            .prefetch(&[page_ids[0], page_ids[1], page_ids[7]])
+            .unwrap();
+        assert_eq!(storage.n_reads.get(), 4);
+        assert_eq!(
+            pager.get_page(page_ids[7]).unwrap().buffer().deref(),
+            [8; 512].as_slice()
+        );
+        assert_eq!(storage.n_reads.get(), 4);
+
+        // Pages beyond the file are ignored.
+        pager.allocate_page().unwrap();
+        pager
+            .prefetch(&[page_ids[4], PageId::new(9).unwrap()])
+            .unwrap();
+        assert_eq!(storage.n_reads.get(), 5);
+    }
+
+    #[test]
+    fn test_cache_stats_hit_rate() {
+        assert_eq!(CacheStats::default().hit_rate(), 0.0);
+        assert_eq!(CacheStats { hits: 3, misses: 1 }.hit_rate(), 0.75);
+    }
+
     #[test]
     fn test_make_page_mut() {
         let file = tempfile::tempfile().unwrap();
@@ -717,6 +1395,78 @@ mod tests {
         assert_eq!(buf[4096..], [4_u8; 4096]);
     }
 
+    #[test]
+    fn test_page_offset_beyond_4gb() {
+        // 65537 pages of 64KiB. The last page starts at 4GiB which does not fit
+        // in u32. The file is sparse.
+        let pagesize = 65536;
+        let n_pages = 65537;
+        let last_offset = 65536 * 65536;
+        assert!(last_offset > u32::MAX as u64);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        file.as_file()
+            .set_len(n_pages as u64 * pagesize as u64)
+            .unwrap();
+        file.as_file()
+            .write_all_at(&[1_u8; 65536], last_offset)
+            .unwrap();
+        let pager =
+            Pager::new(file.reopen().unwrap(), n_pages, pagesize, pagesize, None, 0).unwrap();
+
+        let last_page_id = PageId::new(n_pages).unwrap();
+        assert_eq!(pager.page_offset(last_page_id), last_offset);
+        let page = pager.get_page(last_page_id).unwrap();
+        assert_eq!(page.buffer().deref(), [1_u8; 65536].as_slice());
+        let mut buffer = pager.make_page_mut(&page).unwrap();
+        buffer.fill(2);
+        drop(buffer);
+        drop(page);
+        pager.commit().unwrap();
+
+        let mut buf = vec![0; 65536];
+        file.as_file().read_exact_at(&mut buf, last_offset).unwrap();
+        assert_eq!(buf, [2_u8; 65536]);
+        // The page at 4GiB must not wrap around to the first page.
+        file.as_file().read_exact_at(&mut buf, 0).unwrap();
+        assert_eq!(buf, [0_u8; 65536]);
+    }
+
+    #[test]
+    fn test_replace_with() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..3 {
+            file.as_file()
+                .write_all_at(&[i as u8 + 1; 4096], i * 4096)
+                .unwrap();
+        }
+        let pager = Pager::new(file.reopen().unwrap(), 3, 4096, 4096, None, 0).unwrap();
+        let image = Pager::new(MemoryStorage::new(vec![5; 4096]), 1, 4096, 4096, None, 0).unwrap();
+
+        pager.replace_with(&image).unwrap();
+        assert_eq!(pager.num_pages(), 1);
+        assert_eq!(
+            pager.get_page(PAGE_ID_1).unwrap().buffer().deref(),
+            [5; 4096].as_slice()
+        );
+        assert!(pager.get_page(PageId::new(2).unwrap()).is_err());
+
+        // The file is restored on abort.
+        pager.abort();
+        assert_eq!(pager.num_pages(), 3);
+        assert_eq!(
+            pager.get_page(PAGE_ID_1).unwrap().buffer().deref(),
+            [1; 4096].as_slice()
+        );
+
+        // The file is truncated on commit.
+        pager.replace_with(&image).unwrap();
+        pager.commit().unwrap();
+        let mut buf = [0; 4096];
+        file.as_file().read_exact_at(&mut buf, 0).unwrap();
+        assert_eq!(buf, [5; 4096]);
+        assert_eq!(file.as_file().metadata().unwrap().len(), 4096);
+    }
+
     #[test]
     fn test_commit_failure() {
         let file = tempfile::tempfile().unwrap();
@@ -766,6 +1516,124 @@ mod tests {
         assert_eq!(pager.n_freelist_pages.get(), 1);
     }
 
+    #[test]
+    fn test_memory_storage() {
+        let storage = MemoryStorage::default();
+        assert_eq!(storage.size().unwrap(), 0);
+        let mut buf = [0; 2];
+        assert_eq!(
+            storage.read(&mut buf, 0).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        storage.write(&[1, 2, 3], 2).unwrap();
+        assert_eq!(storage.size().unwrap(), 5);
+        storage.read(&mut buf, 0).unwrap();
+        assert_eq!(buf, [0, 0]);
+        storage.read(&mut buf, 3).unwrap();
+        assert_eq!(buf, [2, 3]);
+        assert!(storage.read(&mut buf, 4).is_err());
+
+        storage.write(&[4], 0).unwrap();
+        assert_eq!(storage.size().unwrap(), 5);
+        storage.read(&mut buf, 0).unwrap();
+        assert_eq!(buf, [4, 0]);
+
+        assert_eq!(
+            storage.read(&mut buf, u64::MAX).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            storage.write(&[1], u64::MAX).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(storage.size().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_memory_storage_commit_abort() {
+        let storage = Rc::new(MemoryStorage::new(vec![1_u8; 4096]));
+        let pager = Pager::new(storage.clone(), 1, 4096, 4096, None, 0).unwrap();
+
+        let page = pager.get_page(PAGE_ID_1).unwrap();
+        let mut buffer = pager.make_page_mut(&page).unwrap();
+        buffer.fill(2);
+        drop(buffer);
+        let (page_id, page) = pager.allocate_page().unwrap();
+        assert_eq!(page_id.get(), 2);
+        let mut buffer = pager.make_page_mut(&page).unwrap();
+        buffer.fill(3);
+        drop(buffer);
+        pager.commit().unwrap();
+
+        assert_eq!(storage.size().unwrap(), 4096 * 2);
+        let mut buf = [0; 4096 * 2];
+        storage.read(&mut buf, 0).unwrap();
+        assert_eq!(buf[..4096], [2_u8; 4096]);
+        assert_eq!(buf[4096..], [3_u8; 4096]);
+
+        let page = pager.get_page(PAGE_ID_1).unwrap();
+        let mut buffer = pager.make_page_mut(&page).unwrap();
+        buffer.fill(4);
+        drop(buffer);
+        let (page_id, _) = pager.allocate_page().unwrap();
+        assert_eq!(page_id.get(), 3);
+        pager.abort();
+
+        assert_eq!(pager.num_pages(), 2);
+        assert_eq!(
+            pager.get_page(PAGE_ID_1).unwrap().buffer().deref(),
+            [2_u8; 4096].as_slice()
+        );
+        assert_eq!(storage.size().unwrap(), 4096 * 2);
+        storage.read(&mut buf, 0).unwrap();
+        assert_eq!(buf[..4096], [2_u8; 4096]);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut content = vec![0_u8; 512 * 2];
+        // The page size is 512.
+        content[16..18].copy_from_slice(&[2, 0]);
+        let storage = Rc::new(MemoryStorage::new(content));
+        let pager = Pager::new(storage.clone(), 2, 512, 512, None, 0).unwrap();
+        let other_pager = Pager::new(storage.clone(), 2, 512, 512, None, 0).unwrap();
+        let page_id = PageId::new(2).unwrap();
+        // The first refresh drops the pages read before.
+        assert!(pager.refresh().unwrap());
+        assert!(!pager.refresh().unwrap());
+        assert_eq!(pager.get_page(page_id).unwrap().buffer()[0], 0);
+
+        // Commit without updating the change counter.
+        let page = other_pager.get_page(page_id).unwrap();
+        other_pager.make_page_mut(&page).unwrap().fill(1);
+        drop(page);
+        other_pager.commit().unwrap();
+        assert!(!pager.refresh().unwrap());
+        assert_eq!(pager.get_page(page_id).unwrap().buffer()[0], 0);
+
+        let page1 = other_pager.get_page(PAGE_ID_1).unwrap();
+        let mut buffer = other_pager.make_page_mut(&page1).unwrap();
+        DatabaseHeaderMut::from((&mut buffer[..DATABASE_HEADER_SIZE]).try_into().unwrap())
+            .increment_change_counter();
+        drop(buffer);
+        drop(page1);
+        let (_, page) = other_pager.allocate_page().unwrap();
+        other_pager.make_page_mut(&page).unwrap().fill(2);
+        drop(page);
+        other_pager.commit().unwrap();
+        assert!(!other_pager.refresh().unwrap());
+
+        assert!(pager.refresh().unwrap());
+        assert_eq!(pager.num_pages(), 3);
+        assert_eq!(pager.get_page(page_id).unwrap().buffer()[0], 1);
+        assert_eq!(
+            pager.get_page(PageId::new(3).unwrap()).unwrap().buffer()[0],
+            2
+        );
+        assert!(!pager.refresh().unwrap());
+    }
+
     #[test]
     fn test_allocate_page() {
         let file = tempfile::NamedTempFile::new().unwrap();
@@ -840,6 +1708,36 @@ mod tests {
         assert_eq!(buf[4096 * 2..], [3_u8; 4096]);
     }
 
+    #[test]
+    fn test_allocate_page_freelist_loop() {
+        // The freelist trunk page 2 points to itself.
+        let file = tempfile::NamedTempFile::new().unwrap();
+        file.as_file().write_all_at(&[0; 4096 * 3], 0).unwrap();
+        file.as_file()
+            .write_all_at(&2_u32.to_be_bytes(), 4096)
+            .unwrap();
+        let pager = Pager::new(file.reopen().unwrap(), 3, 4096, 4096, PageId::new(2), 2).unwrap();
+        assert!(matches!(
+            pager.allocate_page().err().unwrap(),
+            Error::InvalidFile
+        ));
+
+        // The freelist trunk pages 2 and 3 point to each other.
+        file.as_file()
+            .write_all_at(&3_u32.to_be_bytes(), 4096)
+            .unwrap();
+        file.as_file()
+            .write_all_at(&2_u32.to_be_bytes(), 4096 * 2)
+            .unwrap();
+        let pager = Pager::new(file.reopen().unwrap(), 3, 4096, 4096, PageId::new(2), 2).unwrap();
+        assert_eq!(pager.allocate_page().unwrap().0.get(), 2);
+        assert_eq!(pager.allocate_page().unwrap().0.get(), 3);
+        assert!(matches!(
+            pager.allocate_page().err().unwrap(),
+            Error::InvalidFile
+        ));
+    }
+
     #[test]
     fn test_allocate_page_failure() {
         let file = tempfile::NamedTempFile::new().unwrap();
@@ -937,6 +1835,40 @@ mod tests {
         assert_eq!(pager.num_pages(), 8);
     }
 
+    #[test]
+    fn test_allocate_page_from_freelist_commit() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut header_buf = [1; 4096];
+        let mut header = DatabaseHeaderMut::from(
+            (&mut header_buf[..DATABASE_HEADER_SIZE])
+                .try_into()
+                .unwrap(),
+        );
+        header.set_first_freelist_trunk_page_id(PageId::new(2));
+        header.set_n_freelist_pages(3);
+        file.as_file().write_all_at(&header_buf, 0).unwrap();
+        file.as_file()
+            .write_all_at(&[0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4], 4096)
+            .unwrap();
+        file.as_file()
+            .write_all_at(&[2; 4096 * 2], 4096 * 2)
+            .unwrap();
+        let pager = Pager::new(file.reopen().unwrap(), 4, 4096, 4096, PageId::new(2), 3).unwrap();
+
+        let (page_id, _) = pager.allocate_page().unwrap();
+        assert_eq!(page_id.get(), 4);
+        pager.commit().unwrap();
+
+        // The trunk page is written together with the header.
+        let pager = Pager::new(file.reopen().unwrap(), 4, 4096, 4096, PageId::new(2), 2).unwrap();
+        assert_freelist_header(&pager, PageId::new(2), 2);
+        let (page_id, _) = pager.allocate_page().unwrap();
+        assert_eq!(page_id.get(), 3);
+        let (page_id, _) = pager.allocate_page().unwrap();
+        assert_eq!(page_id.get(), 2);
+        assert_freelist_header(&pager, None, 0);
+    }
+
     #[test]
     fn test_allocate_page_from_freelist_tail() {
         let file = tempfile::NamedTempFile::new().unwrap();
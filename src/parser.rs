@@ -17,7 +17,9 @@ use std::fmt::Display;
 use crate::token::get_token;
 use crate::token::Token;
 use crate::utils::parse_float;
+use crate::utils::parse_hex_integer;
 use crate::utils::parse_integer;
+use crate::utils::CaseInsensitiveBytes;
 use crate::utils::HexedBytes;
 use crate::utils::MaybeQuotedBytes;
 use crate::utils::ParseIntegerResult;
@@ -73,12 +75,21 @@ pub type Result<'a, T> = std::result::Result<T, Error<'a>>;
 
 static NULL_BYTES: &[u8] = b"null";
 
+/// The maximum index of parameters.
+///
+/// This is the same as the default SQLITE_MAX_VARIABLE_NUMBER.
+const MAX_PARAMETER_INDEX: usize = 32766;
+
 #[derive(Debug, Clone)]
 pub struct Parser<'a> {
     input: &'a [u8],
     cursor: usize,
     token: Option<Token<'a>>,
     token_size: usize,
+    /// The end of the last consumed token.
+    consumed_end: usize,
+    n_parameters: usize,
+    parameter_names: Vec<(&'a [u8], usize)>,
 }
 
 impl<'a> Parser<'a> {
@@ -88,35 +99,84 @@ impl<'a> Parser<'a> {
             cursor: 0,
             token: None,
             token_size: 0,
+            consumed_end: 0,
+            n_parameters: 0,
+            parameter_names: Vec::new(),
         };
         parser.next();
         parser
     }
 
-    /// Return the number of bytes consumed by the parser.
+    /// Return the largest parameter index and the named parameters with their
+    /// indexes.
+    pub fn parameters(&self) -> (usize, &[(&'a [u8], usize)]) {
+        (self.n_parameters, &self.parameter_names)
+    }
+
+    /// Assign the 1-based index to the parameter.
     ///
-    /// This is used for testing.
-    #[allow(dead_code)]
+    /// `?` takes the next index of the largest index so far. `?NNN` uses NNN.
+    /// Named parameters with the same name share the same index.
+    fn assign_parameter_index(&mut self, variable: &'a [u8]) -> Result<'a, usize> {
+        if variable[0] == b'?' {
+            if variable.len() == 1 {
+                self.n_parameters += 1;
+                return Ok(self.n_parameters);
+            }
+            let idx = match parse_integer(&variable[1..]) {
+                (true, ParseIntegerResult::Integer(idx))
+                    if idx >= 1 && idx as usize <= MAX_PARAMETER_INDEX =>
+                {
+                    idx as usize
+                }
+                _ => return Err(self.error("parameter index out of range")),
+            };
+            self.n_parameters = self.n_parameters.max(idx);
+            Ok(idx)
+        } else if let Some((_, idx)) = self
+            .parameter_names
+            .iter()
+            .find(|(name, _)| *name == variable)
+        {
+            Ok(*idx)
+        } else {
+            self.n_parameters += 1;
+            self.parameter_names.push((variable, self.n_parameters));
+            Ok(self.n_parameters)
+        }
+    }
+
+    /// Return the number of bytes consumed by the parser.
     pub fn n_consumed(&self) -> usize {
         self.cursor
     }
 
     fn next<'b>(&'b mut self) -> Option<&'b Token<'a>> {
         self.cursor += self.token_size;
-        if let Some((n, token)) = get_token(&self.input[self.cursor..]) {
-            self.token_size = n;
-            if token == Token::Space {
-                return self.next();
+        self.consumed_end = self.cursor;
+        loop {
+            if let Some((n, token)) = get_token(&self.input[self.cursor..]) {
+                if token == Token::Space {
+                    self.cursor += n;
+                    continue;
+                }
+                self.token_size = n;
+                self.token = Some(token);
+                return self.token.as_ref();
+            } else {
+                self.token_size = 0;
+                self.token = None;
+                return None;
             }
-            self.token = Some(token);
-            self.token.as_ref()
-        } else {
-            self.token_size = 0;
-            self.token = None;
-            None
         }
     }
 
+    /// Return the input from the start position to the end of the last
+    /// consumed token.
+    fn consumed_since(&self, start: usize) -> &'a [u8] {
+        &self.input[start..self.consumed_end.max(start)]
+    }
+
     fn peek<'b>(&'b self) -> Option<&'b Token<'a>> {
         self.token.as_ref()
     }
@@ -133,15 +193,28 @@ impl<'a> Parser<'a> {
 
 pub enum Stmt<'a> {
     Select(Select<'a>),
+    CompoundSelect(CompoundSelect<'a>),
     Insert(Insert<'a>),
     Delete(Delete<'a>),
+    Update(Update<'a>),
+    /// CREATE TABLE statement with its SQL text without the semicolon.
+    CreateTable(CreateTable<'a>, &'a [u8]),
+    /// CREATE INDEX statement with its SQL text without the semicolon.
+    CreateIndex(CreateIndex<'a>, &'a [u8]),
+    Pragma(Pragma<'a>),
+    Vacuum,
 }
 
 pub fn parse_sql<'a>(p: &mut Parser<'a>) -> Result<'a, Stmt<'a>> {
     match p.peek() {
         Some(Token::Select) => {
             let select = parse_select(p)?;
-            Ok(Stmt::Select(select))
+            if let Some(Token::Union | Token::Intersect | Token::Except) = p.peek() {
+                let compound = parse_compound_select(p, select)?;
+                Ok(Stmt::CompoundSelect(compound))
+            } else {
+                Ok(Stmt::Select(select))
+            }
         }
         Some(Token::Insert) => {
             let select = parse_insert(p)?;
@@ -151,20 +224,52 @@ pub fn parse_sql<'a>(p: &mut Parser<'a>) -> Result<'a, Stmt<'a>> {
             let delete = parse_delete(p)?;
             Ok(Stmt::Delete(delete))
         }
+        Some(Token::Update) => {
+            let update = parse_update(p)?;
+            Ok(Stmt::Update(update))
+        }
+        Some(Token::Create) => {
+            let start = p.cursor;
+            let mut lookahead = p.clone();
+            if let Some(Token::Table) = lookahead.next() {
+                let create_table = parse_create_table(p)?;
+                Ok(Stmt::CreateTable(create_table, p.consumed_since(start)))
+            } else {
+                let create_index = parse_create_index(p)?;
+                Ok(Stmt::CreateIndex(create_index, p.consumed_since(start)))
+            }
+        }
+        Some(Token::Pragma) => {
+            let pragma = parse_pragma(p)?;
+            Ok(Stmt::Pragma(pragma))
+        }
+        Some(Token::Vacuum) => {
+            p.next();
+            Ok(Stmt::Vacuum)
+        }
         _ => Err(p.error("no statement")),
     }
 }
 
 /// Assert that the next token is a semicolon.
+///
+/// The end of input right after a comment also ends the statement as SQLite
+/// does.
 pub fn expect_semicolon<'a>(p: &mut Parser<'a>) -> Result<'a, ()> {
     match p.peek() {
         Some(Token::Semicolon) => {}
+        None if has_comment(&p.input[p.consumed_end..p.cursor]) => return Ok(()),
         _ => return Err(p.error("no semicolon")),
     }
     p.next();
     Ok(())
 }
 
+/// Whether the skipped input between tokens contains a comment.
+fn has_comment(skipped: &[u8]) -> bool {
+    skipped.windows(2).any(|w| w == b"--" || w == b"/*")
+}
+
 /// Assert that there is no token except spaces.
 ///
 /// Uses mutable [Parser] to unify the interface with other expect functions.
@@ -180,17 +285,275 @@ pub fn expect_no_more_token<'a>(p: &Parser<'a>) -> Result<'a, ()> {
 pub struct CreateTable<'a> {
     pub table_name: MaybeQuotedBytes<'a>,
     pub columns: Vec<ColumnDef<'a>>,
+    pub constraints: Vec<TableConstraint<'a>>,
+    pub without_rowid: bool,
+    /// The table enforces the declared types of the columns.
+    pub strict: bool,
+}
+
+/// Constraint of a table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TableConstraint<'a> {
+    PrimaryKey(Vec<IndexedColumn<'a>>),
+    Unique(Vec<IndexedColumn<'a>>),
+    /// The raw text of the CHECK expression without the parentheses.
+    Check(&'a [u8]),
+    ForeignKey {
+        columns: Vec<MaybeQuotedBytes<'a>>,
+        clause: ForeignKeyClause<'a>,
+    },
+}
+
+/// The action on the child rows when the parent key is deleted or updated.
+///
+/// https://www.sqlite.org/foreignkeys.html#fk_actions
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ForeignKeyAction {
+    NoAction,
+    Restrict,
+    SetNull,
+    SetDefault,
+    Cascade,
+}
+
+/// REFERENCES clause of a foreign key constraint.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ForeignKeyClause<'a> {
+    pub foreign_table: MaybeQuotedBytes<'a>,
+    /// The parent key columns. Empty if the parent key is the primary key of
+    /// the foreign table.
+    pub columns: Vec<MaybeQuotedBytes<'a>>,
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+/// Parse the column names in the parentheses.
+///
+/// The current token is the left paren.
+fn parse_column_names<'a>(p: &mut Parser<'a>) -> Result<'a, Vec<MaybeQuotedBytes<'a>>> {
+    let mut columns = Vec::new();
+    loop {
+        let Some(Token::Identifier(name)) = p.next() else {
+            return Err(p.error("no column name"));
+        };
+        columns.push(*name);
+        match p.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RightParen) => break,
+            _ => return Err(p.error("no right paren")),
+        }
+    }
+    p.next();
+    Ok(columns)
+}
+
+/// Whether the token is the unquoted keyword which is not a token on its own.
+fn is_keyword(token: Option<&Token>, keyword: &[u8]) -> bool {
+    matches!(token, Some(Token::Identifier(id))
+        if CaseInsensitiveBytes::from(id.raw()).equal_to_lower_bytes(keyword))
+}
+
+/// https://www.sqlite.org/syntax/foreign-key-clause.html
+///
+/// The current token is REFERENCES. MATCH and DEFERRABLE are parsed but
+/// ignored. NOT DEFERRABLE is not supported yet.
+fn parse_foreign_key_clause<'a>(p: &mut Parser<'a>) -> Result<'a, ForeignKeyClause<'a>> {
+    let Some(Token::Identifier(foreign_table)) = p.next() else {
+        return Err(p.error("no foreign table name"));
+    };
+    let foreign_table = *foreign_table;
+    let columns = if let Some(Token::LeftParen) = p.next() {
+        parse_column_names(p)?
+    } else {
+        Vec::new()
+    };
+    let mut on_delete = ForeignKeyAction::NoAction;
+    let mut on_update = ForeignKeyAction::NoAction;
+    loop {
+        match p.peek() {
+            Some(Token::On) => {
+                let delete = match p.next() {
+                    Some(Token::Delete) => true,
+                    Some(Token::Update) => false,
+                    _ => return Err(p.error("no delete or update after on")),
+                };
+                let action = match p.next() {
+                    Some(Token::Set) => match p.next() {
+                        Some(Token::Null) => ForeignKeyAction::SetNull,
+                        Some(Token::Default) => ForeignKeyAction::SetDefault,
+                        _ => return Err(p.error("no null or default after set")),
+                    },
+                    token if is_keyword(token, b"cascade") => ForeignKeyAction::Cascade,
+                    token if is_keyword(token, b"restrict") => ForeignKeyAction::Restrict,
+                    token if is_keyword(token, b"no") => {
+                        if !is_keyword(p.next(), b"action") {
+                            return Err(p.error("no action after no"));
+                        }
+                        ForeignKeyAction::NoAction
+                    }
+                    _ => return Err(p.error("no foreign key action")),
+                };
+                p.next();
+                if delete {
+                    on_delete = action;
+                } else {
+                    on_update = action;
+                }
+            }
+            token if is_keyword(token, b"match") => {
+                let Some(Token::Identifier(_)) = p.next() else {
+                    return Err(p.error("no name after match"));
+                };
+                p.next();
+            }
+            token if is_keyword(token, b"deferrable") => {
+                // INITIALLY DEFERRED or INITIALLY IMMEDIATE.
+                if is_keyword(p.next(), b"initially") {
+                    let token = p.next();
+                    if !is_keyword(token, b"deferred") && !is_keyword(token, b"immediate") {
+                        return Err(p.error("no deferred or immediate after initially"));
+                    }
+                    p.next();
+                }
+            }
+            _ => break,
+        }
+    }
+    Ok(ForeignKeyClause {
+        foreign_table,
+        columns,
+        on_delete,
+        on_update,
+    })
+}
+
+/// Parse the expression of a CHECK constraint.
+///
+/// The current token is CHECK. Returns the raw text of the expression.
+fn parse_check<'a>(p: &mut Parser<'a>) -> Result<'a, &'a [u8]> {
+    let Some(Token::LeftParen) = p.next() else {
+        return Err(p.error("no check left paren"));
+    };
+    p.next();
+    let n_parameters = p.n_parameters;
+    let start = p.cursor;
+    parse_expr(p)?;
+    let text = p.consumed_since(start);
+    let Some(Token::RightParen) = p.peek() else {
+        return Err(p.error("no check right paren"));
+    };
+    p.next();
+    if p.n_parameters != n_parameters {
+        return Err(p.error("parameters prohibited in check constraint"));
+    }
+    Ok(text)
+}
+
+/// Skip the name of the constraint if any.
+///
+/// The current token is CONSTRAINT or the first token of the constraint.
+fn skip_constraint_name<'a>(p: &mut Parser<'a>) -> Result<'a, ()> {
+    if is_keyword(p.peek(), b"constraint") {
+        let Some(Token::Identifier(_)) = p.next() else {
+            return Err(p.error("no constraint name"));
+        };
+        p.next();
+    }
+    Ok(())
+}
+
+/// Skip the ON CONFLICT clause if any.
+///
+/// The conflict resolution algorithm of the constraints is not supported and
+/// ABORT is always used.
+///
+/// https://www.sqlite.org/syntax/conflict-clause.html
+fn skip_conflict_clause<'a>(p: &mut Parser<'a>) -> Result<'a, ()> {
+    let Some(Token::On) = p.peek() else {
+        return Ok(());
+    };
+    if !is_keyword(p.next(), b"conflict") {
+        return Err(p.error("no conflict after on"));
+    }
+    match p.next() {
+        Some(Token::Ignore) | Some(Token::Replace) => {}
+        token
+            if is_keyword(token, b"rollback")
+                || is_keyword(token, b"abort")
+                || is_keyword(token, b"fail") => {}
+        _ => return Err(p.error("no conflict resolution")),
+    }
+    p.next();
+    Ok(())
+}
+
+/// https://www.sqlite.org/syntax/table-constraint.html
+///
+/// The current token is CONSTRAINT, PRIMARY, UNIQUE, CHECK or FOREIGN.
+fn parse_table_constraint<'a>(p: &mut Parser<'a>) -> Result<'a, TableConstraint<'a>> {
+    skip_constraint_name(p)?;
+    let primary_key = match p.peek() {
+        Some(Token::Check) => return Ok(TableConstraint::Check(parse_check(p)?)),
+        Some(Token::Foreign) => {
+            let Some(Token::Key) = p.next() else {
+                return Err(p.error("no key after foreign"));
+            };
+            let Some(Token::LeftParen) = p.next() else {
+                return Err(p.error("no left paren"));
+            };
+            let columns = parse_column_names(p)?;
+            let Some(Token::References) = p.peek() else {
+                return Err(p.error("no references"));
+            };
+            let clause = parse_foreign_key_clause(p)?;
+            return Ok(TableConstraint::ForeignKey { columns, clause });
+        }
+        Some(Token::Primary) => {
+            let Some(Token::Key) = p.next() else {
+                return Err(p.error("no key after primary"));
+            };
+            true
+        }
+        Some(Token::Unique) => false,
+        _ => return Err(p.error("no table constraint")),
+    };
+    let Some(Token::LeftParen) = p.next() else {
+        return Err(p.error("no left paren"));
+    };
+    let columns = parse_indexed_columns(p)?;
+    if columns.iter().any(|column| column.expr.is_some()) {
+        return Err(p.error("expressions prohibited in primary key and unique constraints"));
+    }
+    skip_conflict_clause(p)?;
+    if primary_key {
+        Ok(TableConstraint::PrimaryKey(columns))
+    } else {
+        Ok(TableConstraint::Unique(columns))
+    }
 }
 
 /// Constraint of a column in a table.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ColumnConstraint<'a> {
     Collate(MaybeQuotedBytes<'a>),
-    PrinaryKey,
+    PrinaryKey {
+        /// Whether the key is sorted in descending order.
+        desc: bool,
+        autoincrement: bool,
+    },
+    NotNull,
+    Unique,
+    /// The raw text of the default expression without the parentheses.
+    Default(&'a [u8]),
+    /// The raw text of the CHECK expression without the parentheses.
+    Check(&'a [u8]),
+    References(ForeignKeyClause<'a>),
 }
 
 /// https://www.sqlite.org/syntax/column-constraint.html
 fn parse_column_constraint<'a>(p: &mut Parser<'a>) -> Result<'a, Option<ColumnConstraint<'a>>> {
+    let named = is_keyword(p.peek(), b"constraint");
+    skip_constraint_name(p)?;
     match p.peek() {
         Some(Token::Collate) => {
             let Some(Token::Identifier(collation)) = p.next() else {
@@ -204,9 +567,74 @@ fn parse_column_constraint<'a>(p: &mut Parser<'a>) -> Result<'a, Option<ColumnCo
             let Some(Token::Key) = p.next() else {
                 return Err(p.error("no key after primary"));
             };
+            let desc = match p.next() {
+                Some(Token::Asc) => {
+                    p.next();
+                    false
+                }
+                Some(Token::Desc) => {
+                    p.next();
+                    true
+                }
+                _ => false,
+            };
+            skip_conflict_clause(p)?;
+            let autoincrement = if let Some(Token::Autoincrement) = p.peek() {
+                p.next();
+                true
+            } else {
+                false
+            };
+            Ok(Some(ColumnConstraint::PrinaryKey {
+                desc,
+                autoincrement,
+            }))
+        }
+        Some(Token::Not) => {
+            let Some(Token::Null) = p.next() else {
+                return Err(p.error("no null after not"));
+            };
+            p.next();
+            skip_conflict_clause(p)?;
+            Ok(Some(ColumnConstraint::NotNull))
+        }
+        Some(Token::Unique) => {
             p.next();
-            Ok(Some(ColumnConstraint::PrinaryKey))
+            skip_conflict_clause(p)?;
+            Ok(Some(ColumnConstraint::Unique))
+        }
+        Some(Token::Check) => Ok(Some(ColumnConstraint::Check(parse_check(p)?))),
+        Some(Token::References) => Ok(Some(ColumnConstraint::References(
+            parse_foreign_key_clause(p)?,
+        ))),
+        Some(Token::Default) => {
+            let n_parameters = p.n_parameters;
+            let text = match p.next() {
+                Some(Token::LeftParen) => {
+                    p.next();
+                    let start = p.cursor;
+                    parse_expr(p)?;
+                    let text = p.consumed_since(start);
+                    let Some(Token::RightParen) = p.peek() else {
+                        return Err(p.error("no default right paren"));
+                    };
+                    p.next();
+                    text
+                }
+                _ => {
+                    // Only literals and signed numbers are allowed without
+                    // parentheses.
+                    let start = p.cursor;
+                    parse_expr_unary(p)?;
+                    p.consumed_since(start)
+                }
+            };
+            if p.n_parameters != n_parameters {
+                return Err(p.error("default value is not constant"));
+            }
+            Ok(Some(ColumnConstraint::Default(text)))
         }
+        _ if named => Err(p.error("no constraint after constraint name")),
         _ => Ok(None),
     }
 }
@@ -216,6 +644,8 @@ fn parse_column_constraint<'a>(p: &mut Parser<'a>) -> Result<'a, Option<ColumnCo
 pub struct ColumnDef<'a> {
     pub name: MaybeQuotedBytes<'a>,
     pub type_name: Vec<MaybeQuotedBytes<'a>>,
+    /// The raw text of the type name.
+    pub declared_type: &'a [u8],
     pub constraints: Vec<ColumnConstraint<'a>>,
 }
 
@@ -224,7 +654,10 @@ fn skip_signed_number<'a>(p: &mut Parser<'a>) -> Result<'a, ()> {
     if matches!(p.peek(), Some(Token::Plus) | Some(Token::Minus)) {
         p.next();
     }
-    if !matches!(p.peek(), Some(Token::Integer(_)) | Some(Token::Float(_))) {
+    if !matches!(
+        p.peek(),
+        Some(Token::Integer(_)) | Some(Token::HexInteger(_)) | Some(Token::Float(_))
+    ) {
         return Err(p.error("no signed number"));
     }
     p.next();
@@ -241,6 +674,8 @@ fn parse_type_name<'a>(p: &mut Parser<'a>) -> Result<'a, Vec<MaybeQuotedBytes<'a
         Some(Token::Null) => {
             type_name.push(NULL_BYTES.into());
         }
+        // CONSTRAINT starts a named column constraint.
+        token if is_keyword(token, b"constraint") => return Ok(Vec::new()),
         Some(Token::Identifier(id)) => {
             type_name.push(*id);
         }
@@ -252,6 +687,7 @@ fn parse_type_name<'a>(p: &mut Parser<'a>) -> Result<'a, Vec<MaybeQuotedBytes<'a
             Some(Token::Null) => {
                 type_name.push(NULL_BYTES.into());
             }
+            token if is_keyword(token, b"constraint") => break,
             Some(Token::Identifier(id)) => {
                 type_name.push(*id);
             }
@@ -302,26 +738,40 @@ pub fn parse_create_table<'a>(p: &mut Parser<'a>) -> Result<'a, CreateTable<'a>>
     };
 
     let mut columns = Vec::new();
+    let mut constraints = Vec::new();
     loop {
-        // Parse ColumnDef.
-        let Some(Token::Identifier(name)) = p.next() else {
-            return Err(p.error("no column name"));
-        };
-        let name = *name;
-        p.next();
+        match p.next() {
+            Some(Token::Primary) | Some(Token::Unique) | Some(Token::Check)
+            | Some(Token::Foreign) => {
+                constraints.push(parse_table_constraint(p)?);
+            }
+            token if is_keyword(token, b"constraint") => {
+                constraints.push(parse_table_constraint(p)?);
+            }
+            // Table constraints must follow all the column definitions.
+            Some(Token::Identifier(name)) if constraints.is_empty() => {
+                // Parse ColumnDef.
+                let name = *name;
+                p.next();
 
-        let type_name = parse_type_name(p)?;
+                let start = p.cursor;
+                let type_name = parse_type_name(p)?;
+                let declared_type = p.consumed_since(start);
 
-        let mut constraints = Vec::new();
-        while let Some(constraint) = parse_column_constraint(p)? {
-            constraints.push(constraint);
-        }
+                let mut column_constraints = Vec::new();
+                while let Some(constraint) = parse_column_constraint(p)? {
+                    column_constraints.push(constraint);
+                }
 
-        columns.push(ColumnDef {
-            name,
-            type_name,
-            constraints,
-        });
+                columns.push(ColumnDef {
+                    name,
+                    type_name,
+                    declared_type,
+                    constraints: column_constraints,
+                });
+            }
+            _ => return Err(p.error("no column name")),
+        }
 
         // Parser contains a peekable token after parse_column_constraint().
         match p.peek() {
@@ -330,26 +780,174 @@ pub fn parse_create_table<'a>(p: &mut Parser<'a>) -> Result<'a, CreateTable<'a>>
             _ => return Err(p.error("no right paren")),
         }
     }
-    p.next();
+
+    // Table options are separated by commas.
+    let mut without_rowid = false;
+    let mut strict = false;
+    let mut token = p.next();
+    loop {
+        match token {
+            Some(Token::Without) => {
+                match p.next() {
+                    Some(Token::Identifier(id))
+                        if CaseInsensitiveBytes::from(id.raw()).equal_to_lower_bytes(b"rowid") => {}
+                    _ => return Err(p.error("no rowid after without")),
+                }
+                without_rowid = true;
+            }
+            Some(Token::Identifier(id))
+                if CaseInsensitiveBytes::from(id.raw()).equal_to_lower_bytes(b"strict") =>
+            {
+                strict = true;
+            }
+            _ if !without_rowid && !strict => break,
+            _ => return Err(p.error("no table option")),
+        }
+        if let Some(Token::Comma) = p.next() {
+            token = p.next();
+        } else {
+            break;
+        }
+    }
 
     Ok(CreateTable {
         table_name,
         columns,
+        constraints,
+        without_rowid,
+        strict,
     })
 }
 
+/// PRAGMA statement.
+#[derive(Debug, PartialEq)]
+pub struct Pragma<'a> {
+    pub name: MaybeQuotedBytes<'a>,
+    /// The value after `=` or in the parentheses.
+    pub value: Option<Expr<'a>>,
+}
+
+/// Parse PRAGMA statement.
+///
+/// https://www.sqlite.org/pragma.html#syntax
+pub fn parse_pragma<'a>(p: &mut Parser<'a>) -> Result<'a, Pragma<'a>> {
+    let Some(Token::Pragma) = p.peek() else {
+        return Err(p.error("no pragma"));
+    };
+
+    let Some(Token::Identifier(name)) = p.next() else {
+        return Err(p.error("no pragma name"));
+    };
+    let name = *name;
+
+    // ON is a keyword but is allowed as a value.
+    let parse_value = |p: &mut Parser<'a>| {
+        if let Some(Token::On) = p.peek() {
+            p.next();
+            Ok(Expr::Column(b"on".as_slice().into()))
+        } else {
+            parse_expr(p)
+        }
+    };
+    let value = match p.next() {
+        Some(Token::Eq) => {
+            p.next();
+            Some(parse_value(p)?)
+        }
+        Some(Token::LeftParen) => {
+            p.next();
+            let value = parse_value(p)?;
+            let Some(Token::RightParen) = p.peek() else {
+                return Err(p.error("no right paren"));
+            };
+            p.next();
+            Some(value)
+        }
+        _ => None,
+    };
+
+    Ok(Pragma { name, value })
+}
+
 /// CREATE INDEX statement.
 #[derive(Debug, PartialEq, Eq)]
 pub struct CreateIndex<'a> {
+    pub unique: bool,
     pub index_name: MaybeQuotedBytes<'a>,
     pub table_name: MaybeQuotedBytes<'a>,
     pub columns: Vec<IndexedColumn<'a>>,
+    /// The raw text of the WHERE expression of a partial index.
+    pub where_clause: Option<&'a [u8]>,
 }
 
 /// Definition of a column in a index.
 #[derive(Debug, PartialEq, Eq)]
 pub struct IndexedColumn<'a> {
+    /// The column name. Empty if the key is an expression.
     pub name: MaybeQuotedBytes<'a>,
+    /// The raw text of the key expression of an index on an expression.
+    pub expr: Option<&'a [u8]>,
+    pub collation: Option<MaybeQuotedBytes<'a>>,
+    /// Whether the column is sorted in descending order.
+    pub desc: bool,
+}
+
+/// Parse the indexed columns in the parentheses.
+///
+/// The current token is the left paren.
+///
+/// https://www.sqlite.org/syntax/indexed-column.html
+fn parse_indexed_columns<'a>(p: &mut Parser<'a>) -> Result<'a, Vec<IndexedColumn<'a>>> {
+    let mut columns = Vec::new();
+    loop {
+        let n_parameters = p.n_parameters;
+        p.next();
+        let start = p.cursor;
+        let key = parse_expr(p)?;
+        let raw_expr = p.consumed_since(start);
+        // A column with a collation is parsed as a COLLATE expression.
+        let (name, expr, collation) = match key {
+            Expr::Column(name) => (name, None, None),
+            Expr::Collate {
+                expr,
+                collation_name,
+            } => match *expr {
+                Expr::Column(name) => (name, None, Some(collation_name)),
+                _ => (b"".as_slice().into(), Some(raw_expr), None),
+            },
+            _ => (b"".as_slice().into(), Some(raw_expr), None),
+        };
+        if p.n_parameters != n_parameters {
+            return Err(p.error("parameters prohibited in index expression"));
+        }
+
+        let desc = match p.peek() {
+            Some(Token::Asc) => {
+                p.next();
+                false
+            }
+            Some(Token::Desc) => {
+                p.next();
+                true
+            }
+            _ => false,
+        };
+
+        columns.push(IndexedColumn {
+            name,
+            expr,
+            collation,
+            desc,
+        });
+
+        match p.peek() {
+            Some(Token::Comma) => continue,
+            Some(Token::RightParen) => break,
+            _ => return Err(p.error("no right paren")),
+        }
+    }
+    p.next();
+    Ok(columns)
 }
 
 /// Parse CREATE INDEX statement.
@@ -360,7 +958,14 @@ pub fn parse_create_index<'a>(p: &mut Parser<'a>) -> Result<'a, CreateIndex<'a>>
         return Err(p.error("no create"));
     };
 
-    let Some(Token::Index) = p.next() else {
+    let unique = if let Some(Token::Unique) = p.next() {
+        p.next();
+        true
+    } else {
+        false
+    };
+
+    let Some(Token::Index) = p.peek() else {
         return Err(p.error("no index"));
     };
 
@@ -382,35 +987,41 @@ pub fn parse_create_index<'a>(p: &mut Parser<'a>) -> Result<'a, CreateIndex<'a>>
         return Err(p.error("no left paren"));
     };
 
-    let mut columns = Vec::new();
-    loop {
-        let Some(Token::Identifier(name)) = p.next() else {
-            return Err(p.error("no column name"));
-        };
-        let name = *name;
-
-        columns.push(IndexedColumn { name });
+    let columns = parse_indexed_columns(p)?;
 
-        match p.next() {
-            Some(Token::Comma) => continue,
-            Some(Token::RightParen) => break,
-            _ => return Err(p.error("no right paren")),
+    let where_clause = if let Some(Token::Where) = p.peek() {
+        let n_parameters = p.n_parameters;
+        p.next();
+        let start = p.cursor;
+        parse_expr(p)?;
+        if p.n_parameters != n_parameters {
+            return Err(p.error("parameters prohibited in partial index"));
         }
-    }
-    p.next();
+        Some(p.consumed_since(start))
+    } else {
+        None
+    };
 
     Ok(CreateIndex {
+        unique,
         index_name,
         table_name,
         columns,
+        where_clause,
     })
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Select<'a> {
+    /// SELECT DISTINCT removes the duplicated result rows.
+    pub distinct: bool,
     pub table_name: MaybeQuotedBytes<'a>,
+    pub join: Option<Join<'a>>,
     pub columns: Vec<ResultColumn<'a>>,
     pub filter: Option<Expr<'a>>,
+    pub group_by: Vec<Expr<'a>>,
+    pub having: Option<Expr<'a>>,
+    pub order_by: Vec<OrderingTerm<'a>>,
 }
 
 // Parse SELECT statement.
@@ -420,7 +1031,17 @@ pub fn parse_select<'a>(p: &mut Parser<'a>) -> Result<'a, Select<'a>> {
     let Some(Token::Select) = p.peek() else {
         return Err(p.error("no select"));
     };
-    p.next();
+    let distinct = match p.next() {
+        Some(Token::Distinct) => {
+            p.next();
+            true
+        }
+        Some(Token::All) => {
+            p.next();
+            false
+        }
+        _ => false,
+    };
 
     let result_column = parse_result_column(p)?;
 
@@ -443,7 +1064,10 @@ pub fn parse_select<'a>(p: &mut Parser<'a>) -> Result<'a, Select<'a>> {
     };
     let table_name = *table_name;
 
-    let filter = if let Some(Token::Where) = p.next() {
+    p.next();
+    let join = parse_join(p)?;
+
+    let filter = if let Some(Token::Where) = p.peek() {
         p.next();
         let expr = parse_expr(p)?;
         Some(expr)
@@ -451,10 +1075,234 @@ pub fn parse_select<'a>(p: &mut Parser<'a>) -> Result<'a, Select<'a>> {
         None
     };
 
+    let group_by = if let Some(Token::Group) = p.peek() {
+        let Some(Token::By) = p.next() else {
+            return Err(p.error("no by"));
+        };
+        let mut group_by = Vec::new();
+        loop {
+            p.next();
+            group_by.push(parse_expr(p)?);
+            let Some(Token::Comma) = p.peek() else {
+                break;
+            };
+        }
+        group_by
+    } else {
+        Vec::new()
+    };
+
+    let having = if let Some(Token::Having) = p.peek() {
+        p.next();
+        Some(parse_expr(p)?)
+    } else {
+        None
+    };
+
+    let order_by = if let Some(Token::Order) = p.peek() {
+        let Some(Token::By) = p.next() else {
+            return Err(p.error("no by"));
+        };
+        let mut order_by = Vec::new();
+        loop {
+            p.next();
+            order_by.push(parse_ordering_term(p)?);
+            let Some(Token::Comma) = p.peek() else {
+                break;
+            };
+        }
+        order_by
+    } else {
+        Vec::new()
+    };
+
     Ok(Select {
+        distinct,
         table_name,
+        join,
         columns,
         filter,
+        group_by,
+        having,
+        order_by,
+    })
+}
+
+/// `[INNER | LEFT [OUTER]] JOIN table_name ON constraint`.
+#[derive(Debug, PartialEq)]
+pub struct Join<'a> {
+    /// Whether the outer rows without any matching inner row are returned.
+    pub left: bool,
+    pub table_name: MaybeQuotedBytes<'a>,
+    pub constraint: Expr<'a>,
+}
+
+/// Parse the join clause following the first table.
+///
+/// https://www.sqlite.org/syntax/join-clause.html
+fn parse_join<'a>(p: &mut Parser<'a>) -> Result<'a, Option<Join<'a>>> {
+    let left = match p.peek() {
+        Some(Token::Inner) => {
+            let Some(Token::Join) = p.next() else {
+                return Err(p.error("no join after inner"));
+            };
+            false
+        }
+        Some(Token::Left) => {
+            let mut token = p.next();
+            if let Some(Token::Outer) = token {
+                token = p.next();
+            }
+            let Some(Token::Join) = token else {
+                return Err(p.error("no join after left"));
+            };
+            true
+        }
+        Some(Token::Join) => false,
+        _ => return Ok(None),
+    };
+    let Some(Token::Identifier(table_name)) = p.next() else {
+        return Err(p.error("no join table_name"));
+    };
+    let table_name = *table_name;
+    let Some(Token::On) = p.next() else {
+        return Err(p.error("no on"));
+    };
+    p.next();
+    let constraint = parse_expr(p)?;
+    Ok(Some(Join {
+        left,
+        table_name,
+        constraint,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompoundOperator {
+    UnionAll,
+    Union,
+    Intersect,
+    Except,
+}
+
+impl CompoundOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnionAll => "UNION ALL",
+            Self::Union => "UNION",
+            Self::Intersect => "INTERSECT",
+            Self::Except => "EXCEPT",
+        }
+    }
+}
+
+/// SELECT statements joined by compound operators.
+///
+/// The operators are left-associative.
+#[derive(Debug)]
+pub struct CompoundSelect<'a> {
+    pub first: Select<'a>,
+    pub rest: Vec<(CompoundOperator, Select<'a>)>,
+}
+
+/// Parse the compound operators and the following SELECT statements.
+///
+/// https://www.sqlite.org/syntax/compound-operator.html
+fn parse_compound_select<'a>(
+    p: &mut Parser<'a>,
+    first: Select<'a>,
+) -> Result<'a, CompoundSelect<'a>> {
+    let mut has_order_by = !first.order_by.is_empty();
+    let mut rest = Vec::new();
+    loop {
+        if has_order_by
+            && matches!(
+                p.peek(),
+                Some(Token::Union | Token::Intersect | Token::Except)
+            )
+        {
+            return Err(p.error("ORDER BY clause should come after the last SELECT"));
+        }
+        let operator = match p.peek() {
+            Some(Token::Union) => {
+                if let Some(Token::All) = p.next() {
+                    p.next();
+                    CompoundOperator::UnionAll
+                } else {
+                    CompoundOperator::Union
+                }
+            }
+            Some(Token::Intersect) => {
+                p.next();
+                CompoundOperator::Intersect
+            }
+            Some(Token::Except) => {
+                p.next();
+                CompoundOperator::Except
+            }
+            _ => break,
+        };
+        let select = parse_select(p)?;
+        has_order_by = !select.order_by.is_empty();
+        rest.push((operator, select));
+    }
+    Ok(CompoundSelect { first, rest })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OrderingTerm<'a> {
+    pub expr: Expr<'a>,
+    pub desc: bool,
+    /// Whether NULLS FIRST (`Some(true)`) or NULLS LAST (`Some(false)`) is
+    /// specified.
+    pub nulls_first: Option<bool>,
+}
+
+/// Parse ordering term.
+///
+/// The collation is parsed as a part of the expression.
+///
+/// https://www.sqlite.org/syntax/ordering-term.html
+fn parse_ordering_term<'a>(p: &mut Parser<'a>) -> Result<'a, OrderingTerm<'a>> {
+    let expr = parse_expr(p)?;
+    let desc = match p.peek() {
+        Some(Token::Asc) => {
+            p.next();
+            false
+        }
+        Some(Token::Desc) => {
+            p.next();
+            true
+        }
+        _ => false,
+    };
+    // NULLS, FIRST and LAST are not keywords so that they can be used as names.
+    let nulls_first = match p.peek() {
+        Some(Token::Identifier(id))
+            if CaseInsensitiveBytes::from(id.raw()).equal_to_lower_bytes(b"nulls") =>
+        {
+            let nulls_first = match p.next() {
+                Some(Token::Identifier(id)) => {
+                    let id = CaseInsensitiveBytes::from(id.raw());
+                    if id.equal_to_lower_bytes(b"first") {
+                        true
+                    } else if id.equal_to_lower_bytes(b"last") {
+                        false
+                    } else {
+                        return Err(p.error("no first or last after nulls"));
+                    }
+                }
+                _ => return Err(p.error("no first or last after nulls")),
+            };
+            p.next();
+            Some(nulls_first)
+        }
+        _ => None,
+    };
+    Ok(OrderingTerm {
+        expr,
+        desc,
+        nulls_first,
     })
 }
 
@@ -462,7 +1310,12 @@ pub fn parse_select<'a>(p: &mut Parser<'a>) -> Result<'a, Select<'a>> {
 pub enum ResultColumn<'a> {
     All,
     AllOfTable(MaybeQuotedBytes<'a>),
-    Expr((Expr<'a>, Option<MaybeQuotedBytes<'a>>)),
+    Expr {
+        expr: Expr<'a>,
+        alias: Option<MaybeQuotedBytes<'a>>,
+        /// The source text of the expression.
+        text: &'a [u8],
+    },
 }
 
 /// Parse result column.
@@ -489,12 +1342,14 @@ fn parse_result_column<'a>(p: &mut Parser<'a>) -> Result<'a, ResultColumn<'a>> {
         }
         _ => {}
     }
+    let start = p.cursor;
     let expr = parse_expr(p)?;
-    match p.peek() {
+    let text = p.consumed_since(start);
+    let alias = match p.peek() {
         Some(Token::Identifier(alias)) => {
             let alias = *alias;
             p.next();
-            Ok(ResultColumn::Expr((expr, Some(alias))))
+            Some(alias)
         }
         Some(Token::As) => {
             let Some(Token::Identifier(alias)) = p.next() else {
@@ -502,15 +1357,28 @@ fn parse_result_column<'a>(p: &mut Parser<'a>) -> Result<'a, ResultColumn<'a>> {
             };
             let alias = *alias;
             p.next();
-            Ok(ResultColumn::Expr((expr, Some(alias))))
+            Some(alias)
         }
-        _ => Ok(ResultColumn::Expr((expr, None))),
-    }
+        _ => None,
+    };
+    Ok(ResultColumn::Expr { expr, alias, text })
+}
+
+/// The conflict resolution algorithm of INSERT.
+///
+/// https://www.sqlite.org/lang_conflict.html
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OnConflict {
+    Abort,
+    Ignore,
+    Replace,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Insert<'a> {
+    pub on_conflict: OnConflict,
     pub table_name: MaybeQuotedBytes<'a>,
+    /// Empty if the column list is omitted, which means all the columns.
     pub columns: Vec<MaybeQuotedBytes<'a>>,
     pub values: Vec<Vec<Expr<'a>>>,
 }
@@ -522,7 +1390,18 @@ pub fn parse_insert<'a>(p: &mut Parser<'a>) -> Result<'a, Insert<'a>> {
     let Some(Token::Insert) = p.peek() else {
         return Err(p.error("no insert"));
     };
-    let Some(Token::Into) = p.next() else {
+    let on_conflict = if let Some(Token::Or) = p.next() {
+        let on_conflict = match p.next() {
+            Some(Token::Ignore) => OnConflict::Ignore,
+            Some(Token::Replace) => OnConflict::Replace,
+            _ => return Err(p.error("no conflict resolution")),
+        };
+        p.next();
+        on_conflict
+    } else {
+        OnConflict::Abort
+    };
+    let Some(Token::Into) = p.peek() else {
         return Err(p.error("no into"));
     };
     let Some(Token::Identifier(table_name)) = p.next() else {
@@ -530,23 +1409,22 @@ pub fn parse_insert<'a>(p: &mut Parser<'a>) -> Result<'a, Insert<'a>> {
     };
     let table_name = *table_name;
 
-    let Some(Token::LeftParen) = p.next() else {
-        return Err(p.error("no left paren"));
-    };
     let mut columns = Vec::new();
-
-    loop {
-        let Some(Token::Identifier(column_name)) = p.next() else {
-            return Err(p.error("no column_name"));
-        };
-        columns.push(*column_name);
-        match p.next() {
-            Some(Token::Comma) => continue,
-            Some(Token::RightParen) => break,
-            _ => return Err(p.error("no right paren")),
+    if let Some(Token::LeftParen) = p.next() {
+        loop {
+            let Some(Token::Identifier(column_name)) = p.next() else {
+                return Err(p.error("no column_name"));
+            };
+            columns.push(*column_name);
+            match p.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RightParen) => break,
+                _ => return Err(p.error("no right paren")),
+            }
         }
+        p.next();
     }
-    let Some(Token::Values) = p.next() else {
+    let Some(Token::Values) = p.peek() else {
         return Err(p.error("no values"));
     };
 
@@ -578,6 +1456,7 @@ pub fn parse_insert<'a>(p: &mut Parser<'a>) -> Result<'a, Insert<'a>> {
     }
 
     Ok(Insert {
+        on_conflict,
         table_name,
         columns,
         values,
@@ -616,17 +1495,90 @@ pub fn parse_delete<'a>(p: &mut Parser<'a>) -> Result<'a, Delete<'a>> {
     Ok(Delete { table_name, filter })
 }
 
+#[derive(Debug)]
+pub struct Update<'a> {
+    pub table_name: MaybeQuotedBytes<'a>,
+    pub assignments: Vec<(MaybeQuotedBytes<'a>, Expr<'a>)>,
+    pub filter: Option<Expr<'a>>,
+}
+
+// Parse UPDATE statement.
+//
+// https://www.sqlite.org/lang_update.html
+pub fn parse_update<'a>(p: &mut Parser<'a>) -> Result<'a, Update<'a>> {
+    let Some(Token::Update) = p.peek() else {
+        return Err(p.error("no update"));
+    };
+    let Some(Token::Identifier(table_name)) = p.next() else {
+        return Err(p.error("no table_name"));
+    };
+    let table_name = *table_name;
+    let Some(Token::Set) = p.next() else {
+        return Err(p.error("no set"));
+    };
+
+    let mut assignments = Vec::new();
+    loop {
+        let Some(Token::Identifier(column_name)) = p.next() else {
+            return Err(p.error("no column_name"));
+        };
+        let column_name = *column_name;
+        let Some(Token::Eq) = p.next() else {
+            return Err(p.error("no eq"));
+        };
+        p.next();
+        let expr = parse_expr(p)?;
+        assignments.push((column_name, expr));
+        let Some(Token::Comma) = p.peek() else {
+            break;
+        };
+    }
+
+    let filter = if let Some(Token::Where) = p.peek() {
+        p.next();
+        let expr = parse_expr(p)?;
+        Some(expr)
+    } else {
+        None
+    };
+
+    Ok(Update {
+        table_name,
+        assignments,
+        filter,
+    })
+}
+
+/// CURRENT_TIME, CURRENT_DATE and CURRENT_TIMESTAMP.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CurrentTime {
+    Time,
+    Date,
+    Timestamp,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum UnaryOp {
     BitNot,
     Minus,
+    Not,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BinaryOp {
+    And,
+    Or,
     Compare(CompareOp),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    ShiftLeft,
+    ShiftRight,
     Concat,
-    // TODO: BitOr
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -643,11 +1595,26 @@ pub enum CompareOp {
     Lt,
     /// Less than or equal to
     Le,
+    /// Equal to, where NULL is equal to NULL
+    Is,
+    /// Not equal to, where NULL is equal to NULL
+    IsNot,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PatternOp {
+    Like,
+    Glob,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Expr<'a> {
     Column(MaybeQuotedBytes<'a>),
+    /// `table_name.column_name`.
+    QualifiedColumn {
+        table_name: MaybeQuotedBytes<'a>,
+        column_name: MaybeQuotedBytes<'a>,
+    },
     UnaryOperator {
         operator: UnaryOp,
         expr: Box<Expr<'a>>,
@@ -665,6 +1632,46 @@ pub enum Expr<'a> {
         expr: Box<Expr<'a>>,
         type_name: Vec<MaybeQuotedBytes<'a>>,
     },
+    InList {
+        expr: Box<Expr<'a>>,
+        list: Vec<Expr<'a>>,
+    },
+    /// `expr IN (SELECT ...)`.
+    InSelect {
+        expr: Box<Expr<'a>>,
+        select: Box<Select<'a>>,
+    },
+    Between {
+        expr: Box<Expr<'a>>,
+        lower: Box<Expr<'a>>,
+        upper: Box<Expr<'a>>,
+    },
+    Pattern {
+        operator: PatternOp,
+        expr: Box<Expr<'a>>,
+        pattern: Box<Expr<'a>>,
+        /// The escape character for LIKE.
+        escape: Option<Box<Expr<'a>>>,
+    },
+    /// `CASE [operand] WHEN ... THEN ... [ELSE ...] END`.
+    Case {
+        operand: Option<Box<Expr<'a>>>,
+        /// Pairs of WHEN and THEN expressions.
+        branches: Vec<(Expr<'a>, Expr<'a>)>,
+        else_expr: Option<Box<Expr<'a>>>,
+    },
+    /// The 1-based index of the parameter.
+    Parameter(usize),
+    /// Scalar subquery `(SELECT ...)`.
+    Subquery(Box<Select<'a>>),
+    /// Function call. The arguments are empty for `count(*)`.
+    Function {
+        name: MaybeQuotedBytes<'a>,
+        args: Vec<Expr<'a>>,
+        /// The arguments are preceded by DISTINCT.
+        distinct: bool,
+    },
+    CurrentTime(CurrentTime),
     Null,
     Integer(i64),
     Real(f64),
@@ -675,22 +1682,31 @@ pub enum Expr<'a> {
 /// Parse expression.
 ///
 /// https://www.sqlite.org/syntax/expr.html
-fn parse_expr<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
-    parse_expr_eq(p)
+pub fn parse_expr<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    parse_expr_or(p)
 }
 
-fn parse_expr_eq<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
-    let mut expr = parse_expr_compare(p)?;
-    loop {
-        let operator = match p.peek() {
-            Some(Token::Eq) => BinaryOp::Compare(CompareOp::Eq),
-            Some(Token::Ne) => BinaryOp::Compare(CompareOp::Ne),
-            _ => break,
+fn parse_expr_or<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_and(p)?;
+    while let Some(Token::Or) = p.peek() {
+        p.next();
+        let right = parse_expr_and(p)?;
+        expr = Expr::BinaryOperator {
+            operator: BinaryOp::Or,
+            left: Box::new(expr),
+            right: Box::new(right),
         };
+    }
+    Ok(expr)
+}
+
+fn parse_expr_and<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_not(p)?;
+    while let Some(Token::And) = p.peek() {
         p.next();
-        let right = parse_expr_compare(p)?;
+        let right = parse_expr_not(p)?;
         expr = Expr::BinaryOperator {
-            operator,
+            operator: BinaryOp::And,
             left: Box::new(expr),
             right: Box::new(right),
         };
@@ -698,8 +1714,175 @@ fn parse_expr_eq<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
     Ok(expr)
 }
 
+fn parse_expr_not<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    if let Some(Token::Not) = p.peek() {
+        p.next();
+        let expr = parse_expr_not(p)?;
+        Ok(Expr::UnaryOperator {
+            operator: UnaryOp::Not,
+            expr: Box::new(expr),
+        })
+    } else {
+        parse_expr_eq(p)
+    }
+}
+
+fn parse_expr_eq<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_compare(p)?;
+    loop {
+        let mut negated = false;
+        if let Some(Token::Not) = p.peek() {
+            // NOT here is a part of NOT IN, NOT LIKE, NOT GLOB, NOT BETWEEN or NOT NULL.
+            let mut cloned_parser = p.clone();
+            if !matches!(
+                cloned_parser.next(),
+                Some(Token::In)
+                    | Some(Token::Like)
+                    | Some(Token::Glob)
+                    | Some(Token::Between)
+                    | Some(Token::Null)
+            ) {
+                break;
+            }
+            *p = cloned_parser;
+            negated = true;
+        }
+        expr = match p.peek() {
+            Some(Token::Is) => {
+                let operator = if let Some(Token::Not) = p.next() {
+                    p.next();
+                    BinaryOp::Compare(CompareOp::IsNot)
+                } else {
+                    BinaryOp::Compare(CompareOp::Is)
+                };
+                let right = parse_expr_compare(p)?;
+                Expr::BinaryOperator {
+                    operator,
+                    left: Box::new(expr),
+                    right: Box::new(right),
+                }
+            }
+            Some(Token::Null) if !negated => break,
+            // "x ISNULL" is "x IS NULL". "x NOTNULL" and "x NOT NULL" are "x IS NOT NULL".
+            Some(Token::Isnull) | Some(Token::Notnull) | Some(Token::Null) => {
+                let operator = if let Some(Token::Isnull) = p.peek() {
+                    BinaryOp::Compare(CompareOp::Is)
+                } else {
+                    negated = false;
+                    BinaryOp::Compare(CompareOp::IsNot)
+                };
+                p.next();
+                Expr::BinaryOperator {
+                    operator,
+                    left: Box::new(expr),
+                    right: Box::new(Expr::Null),
+                }
+            }
+            Some(Token::Eq) | Some(Token::Ne) => {
+                let operator = if let Some(Token::Eq) = p.peek() {
+                    BinaryOp::Compare(CompareOp::Eq)
+                } else {
+                    BinaryOp::Compare(CompareOp::Ne)
+                };
+                p.next();
+                let right = parse_expr_compare(p)?;
+                Expr::BinaryOperator {
+                    operator,
+                    left: Box::new(expr),
+                    right: Box::new(right),
+                }
+            }
+            Some(Token::In) => {
+                let Some(Token::LeftParen) = p.next() else {
+                    return Err(p.error("no in left paren"));
+                };
+                let expr = match p.next() {
+                    Some(Token::Select) => {
+                        let select = parse_select(p)?;
+                        let Some(Token::RightParen) = p.peek() else {
+                            return Err(p.error("no in right paren"));
+                        };
+                        Expr::InSelect {
+                            expr: Box::new(expr),
+                            select: Box::new(select),
+                        }
+                    }
+                    Some(Token::RightParen) => Expr::InList {
+                        expr: Box::new(expr),
+                        list: Vec::new(),
+                    },
+                    _ => {
+                        let mut list = Vec::new();
+                        loop {
+                            list.push(parse_expr(p)?);
+                            match p.peek() {
+                                Some(Token::Comma) => {
+                                    p.next();
+                                }
+                                Some(Token::RightParen) => break,
+                                _ => return Err(p.error("no in right paren")),
+                            }
+                        }
+                        Expr::InList {
+                            expr: Box::new(expr),
+                            list,
+                        }
+                    }
+                };
+                p.next();
+                expr
+            }
+            Some(Token::Like) | Some(Token::Glob) => {
+                let operator = if let Some(Token::Like) = p.peek() {
+                    PatternOp::Like
+                } else {
+                    PatternOp::Glob
+                };
+                p.next();
+                let pattern = parse_expr_compare(p)?;
+                let escape = if operator == PatternOp::Like && p.peek() == Some(&Token::Escape) {
+                    p.next();
+                    Some(Box::new(parse_expr_compare(p)?))
+                } else {
+                    None
+                };
+                Expr::Pattern {
+                    operator,
+                    expr: Box::new(expr),
+                    pattern: Box::new(pattern),
+                    escape,
+                }
+            }
+            Some(Token::Between) => {
+                p.next();
+                // The lower bound can be any expression which does not contain AND.
+                let lower = parse_expr_not(p)?;
+                let Some(Token::And) = p.peek() else {
+                    return Err(p.error("no between and"));
+                };
+                p.next();
+                let upper = parse_expr_compare(p)?;
+                Expr::Between {
+                    expr: Box::new(expr),
+                    lower: Box::new(lower),
+                    upper: Box::new(upper),
+                }
+            }
+            _ => break,
+        };
+        if negated {
+            // x NOT IN (...) is equivalent to NOT (x IN (...)) even for NULL.
+            expr = Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(expr),
+            };
+        }
+    }
+    Ok(expr)
+}
+
 fn parse_expr_compare<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
-    let mut expr = parse_expr_concat(p)?;
+    let mut expr = parse_expr_bitwise(p)?;
     loop {
         let operator = match p.peek() {
             Some(Token::Gt) => BinaryOp::Compare(CompareOp::Gt),
@@ -709,7 +1892,7 @@ fn parse_expr_compare<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
             _ => break,
         };
         p.next();
-        let right = parse_expr_concat(p)?;
+        let right = parse_expr_bitwise(p)?;
         expr = Expr::BinaryOperator {
             operator,
             left: Box::new(expr),
@@ -719,17 +1902,18 @@ fn parse_expr_compare<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
     Ok(expr)
 }
 
-fn parse_expr_concat<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
-    let mut expr = parse_expr_collate(p)?;
-    // TODO: -> ->> will be added in the future.
-    #[allow(clippy::while_let_loop)]
+fn parse_expr_bitwise<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_add(p)?;
     loop {
         let operator = match p.peek() {
-            Some(Token::Concat) => BinaryOp::Concat,
+            Some(Token::BitAnd) => BinaryOp::BitAnd,
+            Some(Token::BitOr) => BinaryOp::BitOr,
+            Some(Token::ShiftLeft) => BinaryOp::ShiftLeft,
+            Some(Token::ShiftRight) => BinaryOp::ShiftRight,
             _ => break,
         };
         p.next();
-        let right = parse_expr_collate(p)?;
+        let right = parse_expr_add(p)?;
         expr = Expr::BinaryOperator {
             operator,
             left: Box::new(expr),
@@ -739,10 +1923,69 @@ fn parse_expr_concat<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
     Ok(expr)
 }
 
-fn parse_expr_collate<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
-    let expr = parse_expr_unary(p)?;
-    let mut collation = None;
-    while let Some(Token::Collate) = p.peek() {
+fn parse_expr_add<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_mul(p)?;
+    loop {
+        let operator = match p.peek() {
+            Some(Token::Plus) => BinaryOp::Add,
+            Some(Token::Minus) => BinaryOp::Sub,
+            _ => break,
+        };
+        p.next();
+        let right = parse_expr_mul(p)?;
+        expr = Expr::BinaryOperator {
+            operator,
+            left: Box::new(expr),
+            right: Box::new(right),
+        };
+    }
+    Ok(expr)
+}
+
+fn parse_expr_mul<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_concat(p)?;
+    loop {
+        let operator = match p.peek() {
+            Some(Token::Asterisk) => BinaryOp::Mul,
+            Some(Token::Slash) => BinaryOp::Div,
+            Some(Token::Percent) => BinaryOp::Rem,
+            _ => break,
+        };
+        p.next();
+        let right = parse_expr_concat(p)?;
+        expr = Expr::BinaryOperator {
+            operator,
+            left: Box::new(expr),
+            right: Box::new(right),
+        };
+    }
+    Ok(expr)
+}
+
+fn parse_expr_concat<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let mut expr = parse_expr_collate(p)?;
+    // TODO: -> ->> will be added in the future.
+    #[allow(clippy::while_let_loop)]
+    loop {
+        let operator = match p.peek() {
+            Some(Token::Concat) => BinaryOp::Concat,
+            _ => break,
+        };
+        p.next();
+        let right = parse_expr_collate(p)?;
+        expr = Expr::BinaryOperator {
+            operator,
+            left: Box::new(expr),
+            right: Box::new(right),
+        };
+    }
+    Ok(expr)
+}
+
+fn parse_expr_collate<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
+    let expr = parse_expr_unary(p)?;
+    let mut collation = None;
+    while let Some(Token::Collate) = p.peek() {
         let Some(Token::Identifier(collation_name)) = p.next() else {
             return Err(p.error("no collation name"));
         };
@@ -816,7 +2059,33 @@ fn parse_expr_unary<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
 
 fn parse_expr_primitive<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
     let expr = match p.peek() {
-        Some(Token::Identifier(id)) => Expr::Column(*id),
+        Some(Token::Identifier(id)) => {
+            let id = *id;
+            if let Some(Token::LeftParen) = p.clone().next() {
+                p.next();
+                return parse_function_args(p, id);
+            }
+            // CURRENT_* are too long to be keywords. Quoted names are columns.
+            let name = CaseInsensitiveBytes::from(id.raw());
+            if name.equal_to_lower_bytes(b"current_time") {
+                Expr::CurrentTime(CurrentTime::Time)
+            } else if name.equal_to_lower_bytes(b"current_date") {
+                Expr::CurrentTime(CurrentTime::Date)
+            } else if name.equal_to_lower_bytes(b"current_timestamp") {
+                Expr::CurrentTime(CurrentTime::Timestamp)
+            } else if let Some(Token::Dot) = p.clone().next() {
+                p.next();
+                let Some(Token::Identifier(column_name)) = p.next() else {
+                    return Err(p.error("no column name after dot"));
+                };
+                Expr::QualifiedColumn {
+                    table_name: id,
+                    column_name: *column_name,
+                }
+            } else {
+                Expr::Column(id)
+            }
+        }
         Some(Token::Cast) => {
             let Some(Token::LeftParen) = p.next() else {
                 return Err(p.error("no cast left paren"));
@@ -841,6 +2110,59 @@ fn parse_expr_primitive<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
                 type_name,
             }
         }
+        Some(Token::Case) => {
+            p.next();
+            let operand = match p.peek() {
+                Some(Token::When) => None,
+                _ => Some(Box::new(parse_expr(p)?)),
+            };
+            let mut branches = Vec::new();
+            while let Some(Token::When) = p.peek() {
+                p.next();
+                let when = parse_expr(p)?;
+                let Some(Token::Then) = p.peek() else {
+                    return Err(p.error("no case then"));
+                };
+                p.next();
+                let then = parse_expr(p)?;
+                branches.push((when, then));
+            }
+            if branches.is_empty() {
+                return Err(p.error("no case when"));
+            }
+            let else_expr = match p.peek() {
+                Some(Token::Else) => {
+                    p.next();
+                    Some(Box::new(parse_expr(p)?))
+                }
+                _ => None,
+            };
+            let Some(Token::End) = p.peek() else {
+                return Err(p.error("no case end"));
+            };
+
+            Expr::Case {
+                operand,
+                branches,
+                else_expr,
+            }
+        }
+        Some(Token::LeftParen) => {
+            if let Some(Token::Select) = p.next() {
+                let select = parse_select(p)?;
+                let Some(Token::RightParen) = p.peek() else {
+                    return Err(p.error("no subquery right paren"));
+                };
+                Expr::Subquery(Box::new(select))
+            } else {
+                // The parentheses only group the expression.
+                let expr = parse_expr(p)?;
+                let Some(Token::RightParen) = p.peek() else {
+                    return Err(p.error("no right paren"));
+                };
+                expr
+            }
+        }
         Some(Token::Null) => Expr::Null,
         Some(Token::Integer(buf)) => {
             let (valid, parsed_int) = parse_integer(buf);
@@ -858,6 +2180,12 @@ fn parse_expr_primitive<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
                 }
             }
         }
+        Some(Token::HexInteger(buf)) => {
+            let Some(v) = parse_hex_integer(buf) else {
+                return Err(p.error("hex literal too big"));
+            };
+            Expr::Integer(v)
+        }
         Some(Token::Float(buf)) => {
             let (valid, pure_integer, d) = parse_float(buf);
             assert!(valid);
@@ -866,6 +2194,12 @@ fn parse_expr_primitive<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
         }
         Some(Token::String(text)) => Expr::Text(*text),
         Some(Token::Blob(hex)) => Expr::Blob(*hex),
+        Some(Token::Variable(variable)) => {
+            let variable = *variable;
+            Expr::Parameter(p.assign_parameter_index(variable)?)
+        }
+        // e.g. an unterminated string or a blob literal with invalid hex digits.
+        Some(Token::Illegal) => return Err(p.error("unrecognized token")),
         _ => return Err(p.error("no expr")),
     };
     p.next();
@@ -873,6 +2207,46 @@ fn parse_expr_primitive<'a>(p: &mut Parser<'a>) -> Result<'a, Expr<'a>> {
     Ok(expr)
 }
 
+/// Parse the arguments of a function call.
+///
+/// The current token is the left paren.
+fn parse_function_args<'a>(p: &mut Parser<'a>, name: MaybeQuotedBytes<'a>) -> Result<'a, Expr<'a>> {
+    let mut args = Vec::new();
+    // DISTINCT or ALL must be followed by the arguments.
+    let distinct = match p.next() {
+        Some(Token::Distinct) => Some(true),
+        Some(Token::All) => Some(false),
+        _ => None,
+    };
+    if distinct.is_some() {
+        p.next();
+    }
+    match p.peek() {
+        Some(Token::Asterisk) if distinct.is_none() => {
+            p.next();
+        }
+        Some(Token::RightParen) if distinct.is_none() => {}
+        _ => loop {
+            args.push(parse_expr(p)?);
+            match p.peek() {
+                Some(Token::Comma) => {
+                    p.next();
+                }
+                _ => break,
+            }
+        },
+    }
+    let Some(Token::RightParen) = p.peek() else {
+        return Err(p.error("no function right paren"));
+    };
+    p.next();
+    Ok(Expr::Function {
+        name,
+        args,
+        distinct: distinct.unwrap_or(false),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -902,6 +2276,14 @@ mod tests {
         let r = expect_semicolon(&mut parser);
         assert!(r.is_err());
         assert_eq!(r.unwrap_err().cursor(), 0);
+
+        assert_parser!(expect_semicolon, b" -- comment", 11, ());
+        assert_parser!(expect_semicolon, b" /* comment", 11, ());
+        assert_parser!(expect_semicolon, b"/**/", 4, ());
+        let mut parser = Parser::new(b" - ");
+        assert!(expect_semicolon(&mut parser).is_err());
+        let mut parser = Parser::new(b" / * ");
+        assert!(expect_semicolon(&mut parser).is_err());
     }
 
     #[test]
@@ -922,6 +2304,18 @@ mod tests {
         assert_eq!(r.unwrap_err().cursor(), 4);
     }
 
+    #[test]
+    fn test_parse_sql_create_table() {
+        let input = b"  CREATE TABLE foo (id, name) WITHOUT ROWID ;";
+        let mut parser = Parser::new(input);
+        let Stmt::CreateTable(create_table, sql) = parse_sql(&mut parser).unwrap() else {
+            panic!("not create table");
+        };
+        assert_eq!(create_table.table_name, b"foo".as_slice().into());
+        assert_eq!(sql, b"CREATE TABLE foo (id, name) WITHOUT ROWID");
+        assert!(expect_semicolon(&mut parser).is_ok());
+    }
+
     #[test]
     fn test_parse_create_table() {
         let input = b"create table foo (id integer primary key, name text, real real, \"blob\" blob, `empty` null,[no_type])";
@@ -935,31 +2329,40 @@ mod tests {
                 ColumnDef {
                     name: b"id".as_slice().into(),
                     type_name: vec![b"integer".as_slice().into()],
-                    constraints: vec![ColumnConstraint::PrinaryKey],
+                    declared_type: b"integer",
+                    constraints: vec![ColumnConstraint::PrinaryKey {
+                        desc: false,
+                        autoincrement: false
+                    }],
                 },
                 ColumnDef {
                     name: b"name".as_slice().into(),
                     type_name: vec![b"text".as_slice().into()],
+                    declared_type: b"text",
                     constraints: vec![],
                 },
                 ColumnDef {
                     name: b"real".as_slice().into(),
                     type_name: vec![b"real".as_slice().into()],
+                    declared_type: b"real",
                     constraints: vec![],
                 },
                 ColumnDef {
                     name: b"\"blob\"".as_slice().into(),
                     type_name: vec![b"blob".as_slice().into()],
+                    declared_type: b"blob",
                     constraints: vec![],
                 },
                 ColumnDef {
                     name: b"`empty`".as_slice().into(),
                     type_name: vec![b"null".as_slice().into()],
+                    declared_type: b"null",
                     constraints: vec![],
                 },
                 ColumnDef {
-                    name: b"no_type".as_slice().into(),
+                    name: b"[no_type]".as_slice().into(),
                     type_name: vec![],
+                    declared_type: b"",
                     constraints: vec![],
                 },
             ]
@@ -984,7 +2387,7 @@ mod tests {
         );
         assert_eq!(
             create_table.columns[2].type_name,
-            vec![b"Float".as_slice().into()]
+            vec![b"[Float]".as_slice().into()]
         );
         assert_eq!(
             create_table.columns[3].type_name,
@@ -998,11 +2401,14 @@ mod tests {
             create_table.columns[5].type_name,
             vec![b"`blob```".as_slice().into()]
         );
+        assert_eq!(create_table.columns[0].declared_type, b"type type");
+        assert_eq!(create_table.columns[3].declared_type, b"\"test\"(-10.0)");
+        assert_eq!(create_table.columns[5].declared_type, b"`blob```(1,+2)");
     }
 
     #[test]
     fn test_parse_create_table_constraints() {
-        let input = b"create table foo (col1 type type collate binary primary key collate nocase, col2 collate rtrim, col3 collate \"RTRIM\")";
+        let input = b"create table foo (col1 type type collate binary primary key collate nocase, col2 collate rtrim, col3 collate \"RTRIM\", col4 integer not null collate nocase, col5 unique not null)";
         let mut parser = Parser::new(input);
         let create_table = parse_create_table(&mut parser).unwrap();
         assert_eq!(parser.n_consumed(), input.len());
@@ -1010,7 +2416,10 @@ mod tests {
             create_table.columns[0].constraints,
             vec![
                 ColumnConstraint::Collate(b"binary".as_slice().into()),
-                ColumnConstraint::PrinaryKey,
+                ColumnConstraint::PrinaryKey {
+                    desc: false,
+                    autoincrement: false
+                },
                 ColumnConstraint::Collate(b"nocase".as_slice().into())
             ]
         );
@@ -1022,6 +2431,327 @@ mod tests {
             create_table.columns[2].constraints,
             vec![ColumnConstraint::Collate(b"\"RTRIM\"".as_slice().into()),]
         );
+        assert_eq!(
+            create_table.columns[3].constraints,
+            vec![
+                ColumnConstraint::NotNull,
+                ColumnConstraint::Collate(b"nocase".as_slice().into())
+            ]
+        );
+        assert_eq!(
+            create_table.columns[4].constraints,
+            vec![ColumnConstraint::Unique, ColumnConstraint::NotNull]
+        );
+
+        let input = b"create table foo (id integer primary key autoincrement not null)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![
+                ColumnConstraint::PrinaryKey {
+                    desc: false,
+                    autoincrement: true
+                },
+                ColumnConstraint::NotNull
+            ]
+        );
+
+        let mut parser = Parser::new(b"create table foo (col1 not)");
+        assert!(parse_create_table(&mut parser).is_err());
+        let mut parser = Parser::new(b"create table foo (col1 autoincrement)");
+        assert!(parse_create_table(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_table_table_constraints() {
+        let input = b"create table foo (col1, col2 unique, col3, primary key (col3, col1), unique(col2)) without rowid";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(create_table.columns.len(), 3);
+        assert_eq!(
+            create_table.constraints,
+            vec![
+                TableConstraint::PrimaryKey(vec![
+                    IndexedColumn {
+                        name: b"col3".as_slice().into(),
+                        expr: None,
+                        collation: None,
+                        desc: false,
+                    },
+                    IndexedColumn {
+                        name: b"col1".as_slice().into(),
+                        expr: None,
+                        collation: None,
+                        desc: false,
+                    },
+                ]),
+                TableConstraint::Unique(vec![IndexedColumn {
+                    name: b"col2".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
+                }]),
+            ]
+        );
+        assert!(create_table.without_rowid);
+
+        let input = b"create table foo (col1, col2) WITHOUT ROWID";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(create_table.constraints.is_empty());
+        assert!(create_table.without_rowid);
+
+        let input = b"create table foo (col1, col2)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert!(!create_table.without_rowid);
+        assert!(!create_table.strict);
+
+        let input = b"create table foo (col1 integer primary key) strict, without rowid";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(create_table.without_rowid);
+        assert!(create_table.strict);
+
+        let input = b"create table foo (col1 integer) STRICT";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(!create_table.without_rowid);
+        assert!(create_table.strict);
+
+        for input in [
+            b"create table foo (col1, primary key (col1), col2)".as_slice(),
+            b"create table foo (col1, primary (col1))",
+            b"create table foo (col1, unique ())",
+            b"create table foo (col1, unique (col1)",
+            b"create table foo (col1) without",
+            b"create table foo (col1) without oid",
+            b"create table foo (col1) strict,",
+            b"create table foo (col1) strict, foo",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_table(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table_constraint_options() {
+        let input = b"create table foo (col1 integer constraint pk primary key desc on conflict replace autoincrement, col2 not null on conflict ignore constraint u unique on conflict fail, col3, constraint pk2 primary key (col3 desc, col1 asc) on conflict rollback, unique (col2 collate nocase, col3) on conflict abort, constraint c check (col1 > 0))";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(create_table.columns[0].declared_type, b"integer");
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![ColumnConstraint::PrinaryKey {
+                desc: true,
+                autoincrement: true
+            }]
+        );
+        assert_eq!(
+            create_table.columns[1].constraints,
+            vec![ColumnConstraint::NotNull, ColumnConstraint::Unique]
+        );
+        assert_eq!(
+            create_table.constraints,
+            vec![
+                TableConstraint::PrimaryKey(vec![
+                    IndexedColumn {
+                        name: b"col3".as_slice().into(),
+                        expr: None,
+                        collation: None,
+                        desc: true,
+                    },
+                    IndexedColumn {
+                        name: b"col1".as_slice().into(),
+                        expr: None,
+                        collation: None,
+                        desc: false,
+                    },
+                ]),
+                TableConstraint::Unique(vec![
+                    IndexedColumn {
+                        name: b"col2".as_slice().into(),
+                        expr: None,
+                        collation: Some(b"nocase".as_slice().into()),
+                        desc: false,
+                    },
+                    IndexedColumn {
+                        name: b"col3".as_slice().into(),
+                        expr: None,
+                        collation: None,
+                        desc: false,
+                    },
+                ]),
+                TableConstraint::Check(b"col1 > 0"),
+            ]
+        );
+
+        let input = b"create table foo (col1 primary key asc)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![ColumnConstraint::PrinaryKey {
+                desc: false,
+                autoincrement: false
+            }]
+        );
+
+        for input in [
+            b"create table foo (col1 constraint)".as_slice(),
+            b"create table foo (col1 constraint c)",
+            b"create table foo (col1, constraint c)",
+            b"create table foo (col1, constraint primary key (col1))",
+            b"create table foo (col1 unique on conflict)",
+            b"create table foo (col1 unique on replace)",
+            b"create table foo (col1 unique on conflict delete)",
+            b"create table foo (col1, unique (col1 + 1))",
+            b"create table foo (col1, primary key (lower(col1)))",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_table(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table_default() {
+        let input = b"create table foo (col1 default 10, col2 default - 1.5 not null, col3 default 'a''b', col4 default ( 1 +  2 ), col5 default null, col6 default current_timestamp)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        let defaults = [
+            b"10".as_slice(),
+            b"- 1.5",
+            b"'a''b'",
+            b"1 +  2",
+            b"null",
+            b"current_timestamp",
+        ];
+        for (column, default) in create_table.columns.iter().zip(defaults) {
+            assert_eq!(column.constraints[0], ColumnConstraint::Default(default));
+        }
+        assert_eq!(
+            create_table.columns[1].constraints[1],
+            ColumnConstraint::NotNull
+        );
+
+        for input in [
+            b"create table foo (col1 default)".as_slice(),
+            b"create table foo (col1 default (1)",
+            b"create table foo (col1 default (1 + 2)",
+            b"create table foo (col1 default ?)",
+            b"create table foo (col1 default (1 + ?))",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_table(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table_check() {
+        let input =
+            b"create table foo (col1 check (col1 >= 0) not null, col2, check ( col1 < col2 ))";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![
+                ColumnConstraint::Check(b"col1 >= 0"),
+                ColumnConstraint::NotNull
+            ]
+        );
+        assert_eq!(
+            create_table.constraints,
+            vec![TableConstraint::Check(b"col1 < col2")]
+        );
+
+        for input in [
+            b"create table foo (col1 check)".as_slice(),
+            b"create table foo (col1 check col1 > 0)",
+            b"create table foo (col1 check (col1 > 0)",
+            b"create table foo (col1 check (col1 > ?))",
+            b"create table foo (col1, check (col1 > 0), col2)",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_table(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_create_table_foreign_key() {
+        let input = b"create table foo (col1 references bar, col2 references \"Bar\" (id) on delete cascade on update set null, col3, foreign key (col3, col1) references baz(a, b) match simple deferrable initially deferred)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![ColumnConstraint::References(ForeignKeyClause {
+                foreign_table: b"bar".as_slice().into(),
+                columns: Vec::new(),
+                on_delete: ForeignKeyAction::NoAction,
+                on_update: ForeignKeyAction::NoAction,
+            })]
+        );
+        assert_eq!(
+            create_table.columns[1].constraints,
+            vec![ColumnConstraint::References(ForeignKeyClause {
+                foreign_table: b"\"Bar\"".as_slice().into(),
+                columns: vec![b"id".as_slice().into()],
+                on_delete: ForeignKeyAction::Cascade,
+                on_update: ForeignKeyAction::SetNull,
+            })]
+        );
+        assert_eq!(
+            create_table.constraints,
+            vec![TableConstraint::ForeignKey {
+                columns: vec![b"col3".as_slice().into(), b"col1".as_slice().into()],
+                clause: ForeignKeyClause {
+                    foreign_table: b"baz".as_slice().into(),
+                    columns: vec![b"a".as_slice().into(), b"b".as_slice().into()],
+                    on_delete: ForeignKeyAction::NoAction,
+                    on_update: ForeignKeyAction::NoAction,
+                },
+            }]
+        );
+
+        let input = b"create table foo (col1 references bar on delete restrict on update no action not null)";
+        let mut parser = Parser::new(input);
+        let create_table = parse_create_table(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_table.columns[0].constraints,
+            vec![
+                ColumnConstraint::References(ForeignKeyClause {
+                    foreign_table: b"bar".as_slice().into(),
+                    columns: Vec::new(),
+                    on_delete: ForeignKeyAction::Restrict,
+                    on_update: ForeignKeyAction::NoAction,
+                }),
+                ColumnConstraint::NotNull
+            ]
+        );
+
+        for input in [
+            b"create table foo (col1 references)".as_slice(),
+            b"create table foo (col1 references bar ())",
+            b"create table foo (col1 references bar on insert cascade)",
+            b"create table foo (col1 references bar on delete no)",
+            b"create table foo (col1 references bar on delete set)",
+            b"create table foo (col1, foreign key col1 references bar)",
+            b"create table foo (col1, foreign key (col1))",
+            b"create table foo (col1, foreign (col1) references bar)",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_table(&mut parser).is_err(), "{:?}", input);
+        }
     }
 
     #[test]
@@ -1037,11 +2767,13 @@ mod tests {
                 ColumnDef {
                     name: b"Id".as_slice().into(),
                     type_name: Vec::new(),
+                    declared_type: b"",
                     constraints: vec![],
                 },
                 ColumnDef {
                     name: b"Name".as_slice().into(),
                     type_name: Vec::new(),
+                    declared_type: b"",
                     constraints: vec![],
                 }
             ]
@@ -1074,24 +2806,47 @@ mod tests {
         let mut parser = Parser::new(input);
         let create_index = parse_create_index(&mut parser).unwrap();
         assert_eq!(parser.n_consumed(), input.len());
+        assert!(!create_index.unique);
         assert_eq!(create_index.index_name, b"foo".as_slice().into());
         assert_eq!(create_index.table_name, b"bar".as_slice().into());
         assert_eq!(
             create_index.columns,
             vec![
                 IndexedColumn {
-                    name: b"col1".as_slice().into()
+                    name: b"col1".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
                 },
                 IndexedColumn {
-                    name: b"col2".as_slice().into()
+                    name: b"col2".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
                 },
                 IndexedColumn {
-                    name: b"col3".as_slice().into()
+                    name: b"col3".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
                 },
             ]
         );
     }
 
+    #[test]
+    fn test_parse_create_unique_index() {
+        let input = b"create unique index foo on bar (col1)";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(create_index.unique);
+        assert_eq!(create_index.index_name, b"foo".as_slice().into());
+
+        let mut parser = Parser::new(b"create unique foo on bar (col1)");
+        assert!(parse_create_index(&mut parser).is_err());
+    }
+
     #[test]
     fn test_parse_create_index_with_extra() {
         let input = b"create index fOo on bAR (Col1,cOL2)abc ";
@@ -1104,26 +2859,223 @@ mod tests {
             create_index.columns,
             vec![
                 IndexedColumn {
-                    name: b"Col1".as_slice().into()
+                    name: b"Col1".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
                 },
                 IndexedColumn {
-                    name: b"cOL2".as_slice().into()
+                    name: b"cOL2".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
                 },
             ]
         );
     }
 
     #[test]
-    fn test_parse_create_index_fail() {
-        // no right paren.
-        let r = parse_create_index(&mut Parser::new(b"create index foo on bar (id, name "));
-        assert!(r.is_err());
-        assert_eq!(r.unwrap_err().cursor(), 34);
-    }
-
-    #[test]
-    fn test_parse_select_all() {
-        let input = b"select * from foo";
+    fn test_parse_create_index_collate() {
+        let input = b"create index foo on bar (col1 collate nocase, col2)";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_index.columns,
+            vec![
+                IndexedColumn {
+                    name: b"col1".as_slice().into(),
+                    expr: None,
+                    collation: Some(b"nocase".as_slice().into()),
+                    desc: false,
+                },
+                IndexedColumn {
+                    name: b"col2".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
+                },
+            ]
+        );
+
+        let mut parser = Parser::new(b"create index foo on bar (col1 collate)");
+        assert!(parse_create_index(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_index_order() {
+        let input = b"create index foo on bar (col1 desc, col2 ASC, col3 collate nocase DESC)";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_index.columns,
+            vec![
+                IndexedColumn {
+                    name: b"col1".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: true,
+                },
+                IndexedColumn {
+                    name: b"col2".as_slice().into(),
+                    expr: None,
+                    collation: None,
+                    desc: false,
+                },
+                IndexedColumn {
+                    name: b"col3".as_slice().into(),
+                    expr: None,
+                    collation: Some(b"nocase".as_slice().into()),
+                    desc: true,
+                },
+            ]
+        );
+
+        let mut parser = Parser::new(b"create index foo on bar (col1 desc collate nocase)");
+        assert!(parse_create_index(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_index_expression() {
+        let input = b"create index foo on bar (abs(col1) desc, col2 collate nocase, lower(col3) collate binary)";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_index.columns,
+            vec![
+                IndexedColumn {
+                    name: b"".as_slice().into(),
+                    expr: Some(b"abs(col1)".as_slice()),
+                    collation: None,
+                    desc: true,
+                },
+                IndexedColumn {
+                    name: b"col2".as_slice().into(),
+                    expr: None,
+                    collation: Some(b"nocase".as_slice().into()),
+                    desc: false,
+                },
+                IndexedColumn {
+                    name: b"".as_slice().into(),
+                    expr: Some(b"lower(col3) collate binary".as_slice()),
+                    collation: None,
+                    desc: false,
+                },
+            ]
+        );
+
+        let mut parser = Parser::new(b"create index foo on bar (abs(?))");
+        assert!(parse_create_index(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_partial_index() {
+        let input = b"create index foo on bar (col1) where col2 > 10 and col3 is not null";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            create_index.where_clause,
+            Some(b"col2 > 10 and col3 is not null".as_slice())
+        );
+
+        let input = b"create index foo on bar (col1) where col2 = 1;";
+        let mut parser = Parser::new(input);
+        let create_index = parse_create_index(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len() - 1);
+        assert_eq!(create_index.where_clause, Some(b"col2 = 1".as_slice()));
+
+        let mut parser = Parser::new(b"create index foo on bar (col1)");
+        assert_eq!(parse_create_index(&mut parser).unwrap().where_clause, None);
+
+        for input in [
+            b"create index foo on bar (col1) where".as_slice(),
+            b"create index foo on bar (col1) where col2 = ?",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_create_index(&mut parser).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_sql_create_index() {
+        let input = b"CREATE UNIQUE INDEX foo ON bar(col1);";
+        let mut parser = Parser::new(input);
+        let Stmt::CreateIndex(create_index, sql) = parse_sql(&mut parser).unwrap() else {
+            panic!("not create index");
+        };
+        assert!(create_index.unique);
+        assert_eq!(sql, b"CREATE UNIQUE INDEX foo ON bar(col1)");
+    }
+
+    #[test]
+    fn test_parse_pragma() {
+        assert_parser!(
+            parse_pragma,
+            b"pragma user_version",
+            19,
+            Pragma {
+                name: b"user_version".as_slice().into(),
+                value: None,
+            }
+        );
+        assert_parser!(
+            parse_pragma,
+            b"PRAGMA user_version = -10",
+            25,
+            Pragma {
+                name: b"user_version".as_slice().into(),
+                value: Some(Expr::Integer(-10)),
+            }
+        );
+        assert_parser!(
+            parse_pragma,
+            b"PRAGMA table_info(foo)",
+            22,
+            Pragma {
+                name: b"table_info".as_slice().into(),
+                value: Some(Expr::Column(b"foo".as_slice().into())),
+            }
+        );
+        assert_parser!(
+            parse_pragma,
+            b"PRAGMA foreign_keys = ON",
+            24,
+            Pragma {
+                name: b"foreign_keys".as_slice().into(),
+                value: Some(Expr::Column(b"on".as_slice().into())),
+            }
+        );
+
+        assert!(parse_pragma(&mut Parser::new(b"PRAGMA table_info(foo")).is_err());
+        assert!(parse_pragma(&mut Parser::new(b"PRAGMA = 1")).is_err());
+    }
+
+    #[test]
+    fn test_parse_sql_vacuum() {
+        let mut parser = Parser::new(b"vacuum;");
+        assert!(matches!(parse_sql(&mut parser).unwrap(), Stmt::Vacuum));
+        assert!(expect_semicolon(&mut parser).is_ok());
+
+        // VACUUM INTO is not supported.
+        let mut parser = Parser::new(b"VACUUM INTO 'foo';");
+        assert!(matches!(parse_sql(&mut parser).unwrap(), Stmt::Vacuum));
+        assert!(expect_semicolon(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_index_fail() {
+        // no right paren.
+        let r = parse_create_index(&mut Parser::new(b"create index foo on bar (id, name "));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 34);
+    }
+
+    #[test]
+    fn test_parse_select_all() {
+        let input = b"select * from foo";
         let mut parser = Parser::new(input);
         let select = parse_select(&mut parser).unwrap();
         assert_eq!(parser.n_consumed(), input.len());
@@ -1141,40 +3093,71 @@ mod tests {
         assert_eq!(
             select.columns,
             vec![
-                ResultColumn::Expr((Expr::Column(b"id".as_slice().into()), None)),
-                ResultColumn::Expr((Expr::Column(b"name".as_slice().into()), None)),
+                ResultColumn::Expr {
+                    expr: Expr::Column(b"id".as_slice().into()),
+                    alias: None,
+                    text: b"id",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::Column(b"name".as_slice().into()),
+                    alias: None,
+                    text: b"name",
+                },
                 ResultColumn::All,
-                ResultColumn::Expr((
-                    Expr::Column(b"col".as_slice().into()),
-                    Some(b"col2".as_slice().into())
-                )),
-                ResultColumn::Expr((
-                    Expr::Column(b"col3".as_slice().into()),
-                    Some(b"col4".as_slice().into())
-                )),
-                ResultColumn::Expr((Expr::Integer(10), None)),
-                ResultColumn::Expr((
-                    Expr::Text(b"'text'".as_slice().into()),
-                    Some(b"col5".as_slice().into())
-                )),
-                ResultColumn::Expr((
-                    Expr::BinaryOperator {
+                ResultColumn::Expr {
+                    expr: Expr::Column(b"col".as_slice().into()),
+                    alias: Some(b"col2".as_slice().into()),
+                    text: b"col",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::Column(b"col3".as_slice().into()),
+                    alias: Some(b"col4".as_slice().into()),
+                    text: b"col3",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::Integer(10),
+                    alias: None,
+                    text: b"10",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::Text(b"'text'".as_slice().into()),
+                    alias: Some(b"col5".as_slice().into()),
+                    text: b"'text'",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::BinaryOperator {
                         operator: BinaryOp::Compare(CompareOp::Eq),
                         left: Box::new(Expr::Column(b"col".as_slice().into())),
                         right: Box::new(Expr::Integer(11)),
                     },
-                    None
-                )),
-                ResultColumn::Expr((
-                    Expr::BinaryOperator {
+                    alias: None,
+                    text: b"col = 11",
+                },
+                ResultColumn::Expr {
+                    expr: Expr::BinaryOperator {
                         operator: BinaryOp::Compare(CompareOp::Lt),
                         left: Box::new(Expr::Column(b"col2".as_slice().into())),
                         right: Box::new(Expr::Column(b"col3".as_slice().into())),
                     },
-                    Some(b"col6".as_slice().into())
-                ))
+                    alias: Some(b"col6".as_slice().into()),
+                    text: b"col2 < col3",
+                }
             ]
         );
+
+        // The source text of the expression excludes the surrounding spaces.
+        let input = b"select   count( * )  , a||b from foo";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        let texts = select
+            .columns
+            .iter()
+            .map(|column| match column {
+                ResultColumn::Expr { text, .. } => *text,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec![b"count( * )".as_slice(), b"a||b".as_slice()]);
     }
 
     #[test]
@@ -1209,6 +3192,320 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_select_join() {
+        let input =
+            b"select foo.id, bar.* from foo inner join \"bar\" on foo.id = bar.foo_id where id = 5";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(select.table_name, b"foo".as_slice().into());
+        assert_eq!(
+            select.columns,
+            vec![
+                ResultColumn::Expr {
+                    expr: Expr::QualifiedColumn {
+                        table_name: b"foo".as_slice().into(),
+                        column_name: b"id".as_slice().into(),
+                    },
+                    alias: None,
+                    text: b"foo.id",
+                },
+                ResultColumn::AllOfTable(b"bar".as_slice().into()),
+            ]
+        );
+        assert_eq!(
+            select.join,
+            Some(Join {
+                left: false,
+                table_name: b"\"bar\"".as_slice().into(),
+                constraint: Expr::BinaryOperator {
+                    operator: BinaryOp::Compare(CompareOp::Eq),
+                    left: Box::new(Expr::QualifiedColumn {
+                        table_name: b"foo".as_slice().into(),
+                        column_name: b"id".as_slice().into(),
+                    }),
+                    right: Box::new(Expr::QualifiedColumn {
+                        table_name: b"bar".as_slice().into(),
+                        column_name: b"foo_id".as_slice().into(),
+                    }),
+                },
+            })
+        );
+        assert!(select.filter.is_some());
+
+        let select = parse_select(&mut Parser::new(b"select * from foo join bar on 1")).unwrap();
+        assert!(matches!(select.join, Some(Join { left: false, .. })));
+        let select =
+            parse_select(&mut Parser::new(b"select * from foo left join bar on 1")).unwrap();
+        assert!(matches!(select.join, Some(Join { left: true, .. })));
+        let select = parse_select(&mut Parser::new(
+            b"select * from foo left outer join bar on 1",
+        ))
+        .unwrap();
+        assert!(matches!(select.join, Some(Join { left: true, .. })));
+
+        // no on.
+        let r = parse_select(&mut Parser::new(b"select * from foo join bar where 1"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 27);
+        // no join after inner.
+        let r = parse_select(&mut Parser::new(b"select * from foo inner bar on 1"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 24);
+        // no join after outer.
+        let r = parse_select(&mut Parser::new(b"select * from foo left outer bar on 1"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 29);
+    }
+
+    #[test]
+    fn test_parse_select_subquery() {
+        let input = b"select * from foo where col = (select max(col) from bar)";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        let subquery = parse_select(&mut Parser::new(b"select max(col) from bar")).unwrap();
+        assert_eq!(
+            select.filter,
+            Some(Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Eq),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Subquery(Box::new(subquery))),
+            })
+        );
+
+        let input = b"select * from foo where col not in (select col from bar where id > 1)";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        let subquery = parse_select(&mut Parser::new(b"select col from bar where id > 1")).unwrap();
+        assert_eq!(
+            select.filter,
+            Some(Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::InSelect {
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    select: Box::new(subquery),
+                }),
+            })
+        );
+
+        // Parenthesized expressions are not subqueries.
+        let select =
+            parse_select(&mut Parser::new(b"select * from foo where col in (1, 2)")).unwrap();
+        assert!(matches!(select.filter, Some(Expr::InList { .. })));
+
+        // no right paren.
+        let r = parse_select(&mut Parser::new(b"select * from foo where col = (1"));
+        assert!(r.is_err());
+        let r = parse_select(&mut Parser::new(
+            b"select * from foo where col = (select 1 from bar",
+        ));
+        assert!(r.is_err());
+        let r = parse_select(&mut Parser::new(
+            b"select * from foo where col in (select 1 from bar",
+        ));
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_parse_select_order_by() {
+        let input =
+            b"select * from foo where id = 5 order by col1, col2 asc, col3 collate nocase desc";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(select.filter.is_some());
+        assert_eq!(
+            select.order_by,
+            vec![
+                OrderingTerm {
+                    expr: Expr::Column(b"col1".as_slice().into()),
+                    desc: false,
+                    nulls_first: None,
+                },
+                OrderingTerm {
+                    expr: Expr::Column(b"col2".as_slice().into()),
+                    desc: false,
+                    nulls_first: None,
+                },
+                OrderingTerm {
+                    expr: Expr::Collate {
+                        expr: Box::new(Expr::Column(b"col3".as_slice().into())),
+                        collation_name: b"nocase".as_slice().into(),
+                    },
+                    desc: true,
+                    nulls_first: None,
+                },
+            ]
+        );
+
+        let input = b"select col from foo order by col || 'a' desc";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(select.filter.is_none());
+        assert_eq!(
+            select.order_by,
+            vec![OrderingTerm {
+                expr: Expr::BinaryOperator {
+                    operator: BinaryOp::Concat,
+                    left: Box::new(Expr::Column(b"col".as_slice().into())),
+                    right: Box::new(Expr::Text(b"'a'".as_slice().into())),
+                },
+                desc: true,
+                nulls_first: None,
+            }]
+        );
+        let input = b"select * from foo order by col1 nulls first, col2 desc NULLS Last, nulls";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            select.order_by,
+            vec![
+                OrderingTerm {
+                    expr: Expr::Column(b"col1".as_slice().into()),
+                    desc: false,
+                    nulls_first: Some(true),
+                },
+                OrderingTerm {
+                    expr: Expr::Column(b"col2".as_slice().into()),
+                    desc: true,
+                    nulls_first: Some(false),
+                },
+                OrderingTerm {
+                    expr: Expr::Column(b"nulls".as_slice().into()),
+                    desc: false,
+                    nulls_first: None,
+                },
+            ]
+        );
+
+        assert!(parse_select(&mut Parser::new(b"select * from foo order by col nulls")).is_err());
+        assert!(parse_select(&mut Parser::new(
+            b"select * from foo order by col nulls middle"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_compound_select() {
+        let input = b"select col1 from foo where col1 = 1 union all select col2 from bar union all select * from foo;";
+        let mut parser = Parser::new(input);
+        let Stmt::CompoundSelect(compound) = parse_sql(&mut parser).unwrap() else {
+            panic!("not compound select");
+        };
+        assert_eq!(parser.n_consumed(), input.len() - 1);
+        assert_eq!(compound.first.table_name, b"foo".as_slice().into());
+        assert!(compound.first.filter.is_some());
+        assert_eq!(compound.rest.len(), 2);
+        assert_eq!(compound.rest[0].0, CompoundOperator::UnionAll);
+        assert_eq!(compound.rest[0].1.table_name, b"bar".as_slice().into());
+        assert_eq!(compound.rest[1].0, CompoundOperator::UnionAll);
+        assert_eq!(compound.rest[1].1.columns, vec![ResultColumn::All]);
+
+        // ORDER BY is only allowed on the last SELECT.
+        let input = b"select col from foo union all select col from bar order by col;";
+        let Stmt::CompoundSelect(compound) = parse_sql(&mut Parser::new(input)).unwrap() else {
+            panic!("not compound select");
+        };
+        assert!(compound.first.order_by.is_empty());
+        assert_eq!(compound.rest[0].1.order_by.len(), 1);
+        assert!(parse_sql(&mut Parser::new(
+            b"select col from foo order by col union all select col from bar;"
+        ))
+        .is_err());
+
+        let input = b"select col from foo union select col from bar intersect select col from baz except select col from foo;";
+        let Stmt::CompoundSelect(compound) = parse_sql(&mut Parser::new(input)).unwrap() else {
+            panic!("not compound select");
+        };
+        assert_eq!(
+            compound
+                .rest
+                .iter()
+                .map(|(operator, select)| (*operator, select.table_name))
+                .collect::<Vec<_>>(),
+            vec![
+                (CompoundOperator::Union, b"bar".as_slice().into()),
+                (CompoundOperator::Intersect, b"baz".as_slice().into()),
+                (CompoundOperator::Except, b"foo".as_slice().into()),
+            ]
+        );
+
+        assert!(parse_sql(&mut Parser::new(b"select col from foo union all;")).is_err());
+        assert!(parse_sql(&mut Parser::new(b"select col from foo union;")).is_err());
+        assert!(parse_sql(&mut Parser::new(
+            b"select col from foo except all select col from bar;"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_select_distinct() {
+        let input = b"select distinct col1, col2 from foo";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(select.distinct);
+        assert_eq!(select.columns.len(), 2);
+
+        let input = b"select all col1 from foo";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(!select.distinct);
+        assert_eq!(select.columns.len(), 1);
+
+        let select = parse_select(&mut Parser::new(b"select col1 from foo")).unwrap();
+        assert!(!select.distinct);
+
+        assert!(parse_select(&mut Parser::new(b"select distinct from foo")).is_err());
+        assert!(parse_select(&mut Parser::new(b"select distinct all col1 from foo")).is_err());
+    }
+
+    #[test]
+    fn test_parse_select_group_by() {
+        let input = b"select col1, count(*) from foo where col3 = 1 group by col1, col2 having count(*) > 1 order by 1";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert!(select.filter.is_some());
+        assert_eq!(
+            select.group_by,
+            vec![
+                Expr::Column(b"col1".as_slice().into()),
+                Expr::Column(b"col2".as_slice().into()),
+            ]
+        );
+        assert_eq!(
+            select.having,
+            Some(Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Gt),
+                left: Box::new(Expr::Function {
+                    name: b"count".as_slice().into(),
+                    args: Vec::new(),
+                    distinct: false,
+                }),
+                right: Box::new(Expr::Integer(1)),
+            })
+        );
+        assert_eq!(select.order_by.len(), 1);
+
+        let input = b"select col from foo group by col";
+        let mut parser = Parser::new(input);
+        let select = parse_select(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(
+            select.group_by,
+            vec![Expr::Column(b"col".as_slice().into())]
+        );
+        assert!(select.having.is_none());
+        assert!(select.order_by.is_empty());
+    }
+
     #[test]
     fn test_parse_select_fail() {
         // no expr after comma.
@@ -1219,6 +3516,24 @@ mod tests {
         let r = parse_select(&mut Parser::new(b"select col from ;"));
         assert!(r.is_err());
         assert_eq!(r.unwrap_err().cursor(), 16);
+        // no by.
+        let r = parse_select(&mut Parser::new(b"select col from foo order col;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 26);
+        // no ordering term.
+        let r = parse_select(&mut Parser::new(b"select col from foo order by col,;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 33);
+        // no by after group.
+        let r = parse_select(&mut Parser::new(b"select col from foo group col;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 26);
+        // no having expr.
+        let r = parse_select(&mut Parser::new(
+            b"select col from foo group by col having;",
+        ));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 39);
     }
 
     #[test]
@@ -1228,6 +3543,7 @@ mod tests {
             b"insert into example (col) values (1)",
             36,
             Insert {
+                on_conflict: OnConflict::Abort,
                 table_name: b"example".as_slice().into(),
                 columns: vec![b"col".as_slice().into()],
                 values: vec![vec![Expr::Integer(1)]],
@@ -1238,6 +3554,7 @@ mod tests {
             b"insert into example2 (col, col2) values (1, 2)a",
             46,
             Insert {
+                on_conflict: OnConflict::Abort,
                 table_name: b"example2".as_slice().into(),
                 columns: vec![b"col".as_slice().into(), b"col2".as_slice().into()],
                 values: vec![vec![Expr::Integer(1), Expr::Integer(2)]],
@@ -1248,6 +3565,7 @@ mod tests {
             b"insert into example2 (col, col2) values (1, 2), (3, 4)a",
             54,
             Insert {
+                on_conflict: OnConflict::Abort,
                 table_name: b"example2".as_slice().into(),
                 columns: vec![b"col".as_slice().into(), b"col2".as_slice().into()],
                 values: vec![
@@ -1256,6 +3574,46 @@ mod tests {
                 ],
             }
         );
+        assert_parser!(
+            parse_insert,
+            b"insert or ignore into example (col) values (1)",
+            46,
+            Insert {
+                on_conflict: OnConflict::Ignore,
+                table_name: b"example".as_slice().into(),
+                columns: vec![b"col".as_slice().into()],
+                values: vec![vec![Expr::Integer(1)]],
+            }
+        );
+        assert_parser!(
+            parse_insert,
+            b"INSERT OR REPLACE INTO example (col) VALUES (1)",
+            47,
+            Insert {
+                on_conflict: OnConflict::Replace,
+                table_name: b"example".as_slice().into(),
+                columns: vec![b"col".as_slice().into()],
+                values: vec![vec![Expr::Integer(1)]],
+            }
+        );
+        assert_parser!(
+            parse_insert,
+            b"insert into example values (-1, +2.5, not 0)",
+            44,
+            Insert {
+                on_conflict: OnConflict::Abort,
+                table_name: b"example".as_slice().into(),
+                columns: Vec::new(),
+                values: vec![vec![
+                    Expr::Integer(-1),
+                    Expr::Real(2.5),
+                    Expr::UnaryOperator {
+                        operator: UnaryOp::Not,
+                        expr: Box::new(Expr::Integer(0)),
+                    }
+                ]],
+            }
+        );
     }
 
     #[test]
@@ -1268,6 +3626,19 @@ mod tests {
         let r = parse_insert(&mut Parser::new(b"insert into (col) values (1)"));
         assert!(r.is_err());
         assert_eq!(r.unwrap_err().cursor(), 12);
+        // unsupported conflict resolution.
+        let r = parse_insert(&mut Parser::new(
+            b"insert or fail into example (col) values (1)",
+        ));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 10);
+        // no values.
+        let r = parse_insert(&mut Parser::new(b"insert into example (col) (1)"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 26);
+        let r = parse_insert(&mut Parser::new(b"insert into example"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 19);
     }
 
     #[test]
@@ -1306,15 +3677,104 @@ mod tests {
         assert_eq!(r.unwrap_err().cursor(), 12);
     }
 
+    #[test]
+    fn test_parse_update() {
+        let input = b"update foo set col1 = 1, col2 = col1 || 'a'";
+        let mut parser = Parser::new(input);
+        let update = parse_update(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(update.table_name, b"foo".as_slice().into());
+        assert_eq!(
+            update.assignments,
+            vec![
+                (b"col1".as_slice().into(), Expr::Integer(1)),
+                (
+                    b"col2".as_slice().into(),
+                    Expr::BinaryOperator {
+                        operator: BinaryOp::Concat,
+                        left: Box::new(Expr::Column(b"col1".as_slice().into())),
+                        right: Box::new(Expr::Text(b"'a'".as_slice().into())),
+                    }
+                ),
+            ]
+        );
+        assert!(update.filter.is_none());
+    }
+
+    #[test]
+    fn test_parse_update_where() {
+        let input = b"update foo set col = 1 where id = 5";
+        let mut parser = Parser::new(input);
+        let update = parse_update(&mut parser).unwrap();
+        assert_eq!(parser.n_consumed(), input.len());
+        assert_eq!(update.table_name, b"foo".as_slice().into());
+        assert_eq!(
+            update.assignments,
+            vec![(b"col".as_slice().into(), Expr::Integer(1))]
+        );
+        assert_eq!(
+            update.filter.unwrap(),
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Eq),
+                left: Box::new(Expr::Column(b"id".as_slice().into())),
+                right: Box::new(Expr::Integer(5)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_update_fail() {
+        // no set.
+        let r = parse_update(&mut Parser::new(b"update foo col = 1;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 11);
+
+        // no assignment.
+        let r = parse_update(&mut Parser::new(b"update foo set where id = 1;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 15);
+
+        // no expr after comma.
+        let r = parse_update(&mut Parser::new(b"update foo set col = 1, ;"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 24);
+    }
+
     #[test]
     fn test_parse_expr_literal_value() {
         // Parse null
         assert_parser!(parse_expr, b"null", 4, Expr::Null);
 
+        // Parse CURRENT_*
+        assert_parser!(
+            parse_expr,
+            b"current_time",
+            12,
+            Expr::CurrentTime(CurrentTime::Time)
+        );
+        assert_parser!(
+            parse_expr,
+            b"Current_Date",
+            12,
+            Expr::CurrentTime(CurrentTime::Date)
+        );
+        assert_parser!(
+            parse_expr,
+            b"CURRENT_TIMESTAMP",
+            17,
+            Expr::CurrentTime(CurrentTime::Timestamp)
+        );
+        assert_parser!(
+            parse_expr,
+            b"\"current_time\"",
+            14,
+            Expr::Column(b"\"current_time\"".as_slice().into())
+        );
+
         // Parse integer
-        assert_parser!(parse_expr, b"123456789a", 9, Expr::Integer(123456789));
-        assert_parser!(parse_expr, b"00123456789a", 11, Expr::Integer(123456789));
-        assert_parser!(parse_expr, b"00000000000000000001a", 20, Expr::Integer(1));
+        assert_parser!(parse_expr, b"123456789,", 9, Expr::Integer(123456789));
+        assert_parser!(parse_expr, b"00123456789,", 11, Expr::Integer(123456789));
+        assert_parser!(parse_expr, b"00000000000000000001,", 20, Expr::Integer(1));
         assert_parser!(
             parse_expr,
             b"9223372036854775807",
@@ -1342,17 +3802,48 @@ mod tests {
         );
         assert_parser!(
             parse_expr,
-            b"99999999999999999999a",
+            b"99999999999999999999,",
             20,
             Expr::Real(99999999999999999999.0)
         );
 
+        // Parse hex integer
+        assert_parser!(parse_expr, b"0xFF", 4, Expr::Integer(255));
+        assert_parser!(
+            parse_expr,
+            b"0x7fffffffffffffff",
+            18,
+            Expr::Integer(i64::MAX)
+        );
+        assert_parser!(parse_expr, b"0xFFFFFFFFFFFFFFFF", 18, Expr::Integer(-1));
+        assert_parser!(
+            parse_expr,
+            b"-0x10",
+            5,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Minus,
+                expr: Box::new(Expr::Integer(16)),
+            }
+        );
+        let r = parse_expr(&mut Parser::new(b"0x10000000000000000"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 0);
+        // 0x without hexadecimal digits.
+        for input in [b"0x".as_slice(), b"0X", b"0xg", b"0x1g"] {
+            assert!(parse_expr(&mut Parser::new(input)).is_err(), "{:?}", input);
+        }
+        // A numeric literal directly followed by identifier characters.
+        for input in [b"12abc".as_slice(), b"1.5x", b".5e1_", b"1$"] {
+            assert!(parse_expr(&mut Parser::new(input)).is_err(), "{:?}", input);
+        }
+
         // Parse float
         assert_parser!(parse_expr, b".1", 2, Expr::Real(0.1));
         assert_parser!(parse_expr, b"1.", 2, Expr::Real(1.0));
         assert_parser!(parse_expr, b"1.01", 4, Expr::Real(1.01));
         assert_parser!(parse_expr, b"1e1", 3, Expr::Real(10.0));
         assert_parser!(parse_expr, b"1e-1", 4, Expr::Real(0.1));
+        assert_parser!(parse_expr, b"1.5e3", 5, Expr::Real(1500.0));
 
         // Parse string
         assert_parser!(
@@ -1381,6 +3872,24 @@ mod tests {
             19,
             Expr::Blob(b"0123456789abcdef".as_slice().into())
         );
+        assert_parser!(
+            parse_expr,
+            b"x'aBcDeF'",
+            9,
+            Expr::Blob(b"aBcDeF".as_slice().into())
+        );
+
+        // Illegal literals
+        for input in [b"x'abc'".as_slice(), b"x'0g'", b"x'00", b"'it''s"] {
+            let r = parse_expr(&mut Parser::new(input));
+            assert!(r.is_err());
+            let e = r.unwrap_err();
+            assert_eq!(e.msg, "unrecognized token");
+            assert_eq!(e.cursor(), 0);
+        }
+        let r = parse_expr(&mut Parser::new(b"1 + x'0'"));
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().cursor(), 4);
     }
 
     #[test]
@@ -1403,8 +3912,8 @@ mod tests {
     fn test_parse_expr_cast() {
         assert_parser!(
             parse_expr,
-            b"cast(100as text)",
-            16,
+            b"cast(100 as text)",
+            17,
             Expr::Cast {
                 expr: Box::new(Expr::Integer(100)),
                 type_name: vec![b"text".as_slice().into()],
@@ -1434,6 +3943,142 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_expr_case() {
+        assert_parser!(
+            parse_expr,
+            b"case when a then 1 when b then 2 else 3 end",
+            43,
+            Expr::Case {
+                operand: None,
+                branches: vec![
+                    (Expr::Column(b"a".as_slice().into()), Expr::Integer(1)),
+                    (Expr::Column(b"b".as_slice().into()), Expr::Integer(2)),
+                ],
+                else_expr: Some(Box::new(Expr::Integer(3))),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"CASE col WHEN 1 THEN 'a' END = 'a'",
+            34,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Eq),
+                left: Box::new(Expr::Case {
+                    operand: Some(Box::new(Expr::Column(b"col".as_slice().into()))),
+                    branches: vec![(Expr::Integer(1), Expr::Text(b"'a'".as_slice().into()))],
+                    else_expr: None,
+                }),
+                right: Box::new(Expr::Text(b"'a'".as_slice().into())),
+            }
+        );
+
+        assert!(parse_expr(&mut Parser::new(b"case end")).is_err());
+        assert!(parse_expr(&mut Parser::new(b"case col else 1 end")).is_err());
+        assert!(parse_expr(&mut Parser::new(b"case when 1 then 2")).is_err());
+        assert!(parse_expr(&mut Parser::new(b"case when 1 2 end")).is_err());
+    }
+
+    #[test]
+    fn test_parse_expr_parameter() {
+        let mut parser = Parser::new(b"? ?5 ? :a @b ?2 :a $c ?");
+        let mut indexes = Vec::new();
+        while parser.peek().is_some() {
+            let Expr::Parameter(idx) = parse_expr(&mut parser).unwrap() else {
+                panic!("not parameter");
+            };
+            indexes.push(idx);
+        }
+        assert_eq!(indexes, vec![1, 5, 6, 7, 8, 2, 7, 9, 10]);
+        let (n, names) = parser.parameters();
+        assert_eq!(n, 10);
+        assert_eq!(
+            names,
+            &[
+                (b":a".as_slice(), 7),
+                (b"@b".as_slice(), 8),
+                (b"$c".as_slice(), 9)
+            ]
+        );
+
+        assert!(parse_expr(&mut Parser::new(b"?0")).is_err());
+        assert!(parse_expr(&mut Parser::new(b"?32767")).is_err());
+        assert!(parse_expr(&mut Parser::new(b"?32766")).is_ok());
+    }
+
+    #[test]
+    fn test_parse_expr_function() {
+        assert_parser!(
+            parse_expr,
+            b"count(*)",
+            8,
+            Expr::Function {
+                name: b"count".as_slice().into(),
+                args: Vec::new(),
+                distinct: false,
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"max ( col, 'a' || col )",
+            23,
+            Expr::Function {
+                name: b"max".as_slice().into(),
+                args: vec![
+                    Expr::Column(b"col".as_slice().into()),
+                    Expr::BinaryOperator {
+                        operator: BinaryOp::Concat,
+                        left: Box::new(Expr::Text(b"'a'".as_slice().into())),
+                        right: Box::new(Expr::Column(b"col".as_slice().into())),
+                    },
+                ],
+                distinct: false,
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"random()",
+            8,
+            Expr::Function {
+                name: b"random".as_slice().into(),
+                args: Vec::new(),
+                distinct: false,
+            }
+        );
+
+        assert_parser!(
+            parse_expr,
+            b"count(DISTINCT col)",
+            19,
+            Expr::Function {
+                name: b"count".as_slice().into(),
+                args: vec![Expr::Column(b"col".as_slice().into())],
+                distinct: true,
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"sum(all col)",
+            12,
+            Expr::Function {
+                name: b"sum".as_slice().into(),
+                args: vec![Expr::Column(b"col".as_slice().into())],
+                distinct: false,
+            }
+        );
+
+        let r = parse_expr(&mut Parser::new(b"sum(col"));
+        assert!(r.is_err());
+        let r = parse_expr(&mut Parser::new(b"sum(col,)"));
+        assert!(r.is_err());
+        let r = parse_expr(&mut Parser::new(b"count(distinct *)"));
+        assert!(r.is_err());
+        let r = parse_expr(&mut Parser::new(b"count(distinct)"));
+        assert!(r.is_err());
+        let r = parse_expr(&mut Parser::new(b"count(all)"));
+        assert!(r.is_err());
+    }
+
     #[test]
     fn test_parse_expr_unary_operator() {
         assert_parser!(
@@ -1596,35 +4241,122 @@ mod tests {
                 collation_name: b"rtrim".as_slice().into()
             }
         );
-    }
-
-    #[test]
-    fn test_parse_expr_concat() {
+    }
+
+    #[test]
+    fn test_parse_expr_concat() {
+        assert_parser!(
+            parse_expr,
+            b"'abc' || 2",
+            10,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Concat,
+                left: Box::new(Expr::Text(b"'abc'".as_slice().into())),
+                right: Box::new(Expr::Integer(2)),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"-1 || 2 || -abc",
+            15,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Concat,
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Concat,
+                    left: Box::new(Expr::Integer(-1)),
+                    right: Box::new(Expr::Integer(2)),
+                }),
+                right: Box::new(Expr::UnaryOperator {
+                    operator: UnaryOp::Minus,
+                    expr: Box::new(Expr::Column(b"abc".as_slice().into()))
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_expr_arithmetic() {
+        // * binds tighter than + and || binds tighter than *.
+        assert_parser!(
+            parse_expr,
+            b"1 + 2 * a || 'b' - 3",
+            20,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Sub,
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Add,
+                    left: Box::new(Expr::Integer(1)),
+                    right: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Mul,
+                        left: Box::new(Expr::Integer(2)),
+                        right: Box::new(Expr::BinaryOperator {
+                            operator: BinaryOp::Concat,
+                            left: Box::new(Expr::Column(b"a".as_slice().into())),
+                            right: Box::new(Expr::Text(b"'b'".as_slice().into())),
+                        }),
+                    }),
+                }),
+                right: Box::new(Expr::Integer(3)),
+            }
+        );
         assert_parser!(
             parse_expr,
-            b"'abc' || 2",
-            10,
+            b"4/2%-1 < 5-2",
+            12,
             Expr::BinaryOperator {
-                operator: BinaryOp::Concat,
-                left: Box::new(Expr::Text(b"'abc'".as_slice().into())),
-                right: Box::new(Expr::Integer(2)),
+                operator: BinaryOp::Compare(CompareOp::Lt),
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Rem,
+                    left: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Div,
+                        left: Box::new(Expr::Integer(4)),
+                        right: Box::new(Expr::Integer(2)),
+                    }),
+                    right: Box::new(Expr::Integer(-1)),
+                }),
+                right: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Sub,
+                    left: Box::new(Expr::Integer(5)),
+                    right: Box::new(Expr::Integer(2)),
+                }),
             }
         );
+    }
+
+    #[test]
+    fn test_parse_expr_bitwise() {
+        // The bitwise operators have the same precedence, lower than +.
         assert_parser!(
             parse_expr,
-            b"-1 || 2 || -abc",
-            15,
+            b"1 << 2 + 3 & ~a | 4 >> 1 > 0",
+            28,
             Expr::BinaryOperator {
-                operator: BinaryOp::Concat,
+                operator: BinaryOp::Compare(CompareOp::Gt),
                 left: Box::new(Expr::BinaryOperator {
-                    operator: BinaryOp::Concat,
-                    left: Box::new(Expr::Integer(-1)),
-                    right: Box::new(Expr::Integer(2)),
-                }),
-                right: Box::new(Expr::UnaryOperator {
-                    operator: UnaryOp::Minus,
-                    expr: Box::new(Expr::Column(b"abc".as_slice().into()))
+                    operator: BinaryOp::ShiftRight,
+                    left: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::BitOr,
+                        left: Box::new(Expr::BinaryOperator {
+                            operator: BinaryOp::BitAnd,
+                            left: Box::new(Expr::BinaryOperator {
+                                operator: BinaryOp::ShiftLeft,
+                                left: Box::new(Expr::Integer(1)),
+                                right: Box::new(Expr::BinaryOperator {
+                                    operator: BinaryOp::Add,
+                                    left: Box::new(Expr::Integer(2)),
+                                    right: Box::new(Expr::Integer(3)),
+                                }),
+                            }),
+                            right: Box::new(Expr::UnaryOperator {
+                                operator: UnaryOp::BitNot,
+                                expr: Box::new(Expr::Column(b"a".as_slice().into())),
+                            }),
+                        }),
+                        right: Box::new(Expr::Integer(4)),
+                    }),
+                    right: Box::new(Expr::Integer(1)),
                 }),
+                right: Box::new(Expr::Integer(0)),
             }
         );
     }
@@ -1748,6 +4480,408 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_expr_not() {
+        assert_parser!(
+            parse_expr,
+            b"not 1",
+            5,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::Integer(1)),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"NOT NOT col = 1",
+            15,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::UnaryOperator {
+                    operator: UnaryOp::Not,
+                    expr: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::Eq),
+                        left: Box::new(Expr::Column(b"col".as_slice().into())),
+                        right: Box::new(Expr::Integer(1)),
+                    }),
+                }),
+            }
+        );
+        // NOT which is not followed by IN, LIKE, GLOB or BETWEEN is left.
+        assert_parser!(parse_expr, b"1 not 2", 2, Expr::Integer(1));
+    }
+
+    #[test]
+    fn test_parse_expr_in() {
+        assert_parser!(
+            parse_expr,
+            b"col in (1, null, 'a')",
+            21,
+            Expr::InList {
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                list: vec![
+                    Expr::Integer(1),
+                    Expr::Null,
+                    Expr::Text(b"'a'".as_slice().into())
+                ],
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col IN ()",
+            9,
+            Expr::InList {
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                list: vec![],
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col NOT IN (1 = 1)",
+            18,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::InList {
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    list: vec![Expr::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::Eq),
+                        left: Box::new(Expr::Integer(1)),
+                        right: Box::new(Expr::Integer(1)),
+                    }],
+                }),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"1 = col in (1)",
+            14,
+            Expr::InList {
+                expr: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Compare(CompareOp::Eq),
+                    left: Box::new(Expr::Integer(1)),
+                    right: Box::new(Expr::Column(b"col".as_slice().into())),
+                }),
+                list: vec![Expr::Integer(1)],
+            }
+        );
+
+        for input in [
+            b"col in".as_slice(),
+            b"col in 1",
+            b"col in (",
+            b"col in (1",
+            b"col in (1,)",
+            b"col not in 1",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_expr(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_expr_is() {
+        assert_parser!(
+            parse_expr,
+            b"col IS 1",
+            8,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Is),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Integer(1)),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col is not null",
+            15,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::IsNot),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Null),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col IS NOT 1 > 2",
+            16,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::IsNot),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Compare(CompareOp::Gt),
+                    left: Box::new(Expr::Integer(1)),
+                    right: Box::new(Expr::Integer(2)),
+                }),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col ISNULL",
+            10,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Is),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Null),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col NOTNULL",
+            11,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::IsNot),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Null),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col NOT NULL",
+            12,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::IsNot),
+                left: Box::new(Expr::Column(b"col".as_slice().into())),
+                right: Box::new(Expr::Null),
+            }
+        );
+        // NULL after an expression is not a part of it.
+        assert_parser!(
+            parse_expr,
+            b"col NULL",
+            4,
+            Expr::Column(b"col".as_slice().into())
+        );
+    }
+
+    #[test]
+    fn test_parse_expr_and_or() {
+        assert_parser!(
+            parse_expr,
+            b"a OR b AND NOT c OR d",
+            21,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Or,
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Or,
+                    left: Box::new(Expr::Column(b"a".as_slice().into())),
+                    right: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::And,
+                        left: Box::new(Expr::Column(b"b".as_slice().into())),
+                        right: Box::new(Expr::UnaryOperator {
+                            operator: UnaryOp::Not,
+                            expr: Box::new(Expr::Column(b"c".as_slice().into())),
+                        }),
+                    }),
+                }),
+                right: Box::new(Expr::Column(b"d".as_slice().into())),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"a BETWEEN 1 AND 2 AND b = 3",
+            27,
+            Expr::BinaryOperator {
+                operator: BinaryOp::And,
+                left: Box::new(Expr::Between {
+                    expr: Box::new(Expr::Column(b"a".as_slice().into())),
+                    lower: Box::new(Expr::Integer(1)),
+                    upper: Box::new(Expr::Integer(2)),
+                }),
+                right: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Compare(CompareOp::Eq),
+                    left: Box::new(Expr::Column(b"b".as_slice().into())),
+                    right: Box::new(Expr::Integer(3)),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_expr_paren() {
+        // The parentheses override the precedence.
+        assert_parser!(
+            parse_expr,
+            b"(a = 1 OR a = 2) AND b = 'x'",
+            28,
+            Expr::BinaryOperator {
+                operator: BinaryOp::And,
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Or,
+                    left: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::Eq),
+                        left: Box::new(Expr::Column(b"a".as_slice().into())),
+                        right: Box::new(Expr::Integer(1)),
+                    }),
+                    right: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::Eq),
+                        left: Box::new(Expr::Column(b"a".as_slice().into())),
+                        right: Box::new(Expr::Integer(2)),
+                    }),
+                }),
+                right: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Compare(CompareOp::Eq),
+                    left: Box::new(Expr::Column(b"b".as_slice().into())),
+                    right: Box::new(Expr::Text(b"'x'".as_slice().into())),
+                }),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"(1 + 2) * 3",
+            11,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Mul,
+                left: Box::new(Expr::BinaryOperator {
+                    operator: BinaryOp::Add,
+                    left: Box::new(Expr::Integer(1)),
+                    right: Box::new(Expr::Integer(2)),
+                }),
+                right: Box::new(Expr::Integer(3)),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"-((a))",
+            6,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Minus,
+                expr: Box::new(Expr::Column(b"a".as_slice().into())),
+            }
+        );
+
+        let mut parser = Parser::new(b"(a + 1");
+        assert!(parse_expr(&mut parser).is_err());
+        let mut parser = Parser::new(b"()");
+        assert!(parse_expr(&mut parser).is_err());
+        let mut parser = Parser::new(b"(a, b)");
+        assert!(parse_expr(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_parse_expr_between() {
+        assert_parser!(
+            parse_expr,
+            b"col between 1 and 2",
+            19,
+            Expr::Between {
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                lower: Box::new(Expr::Integer(1)),
+                upper: Box::new(Expr::Integer(2)),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col NOT BETWEEN 1 = 2 AND 'a' || 'b'",
+            36,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::Between {
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    lower: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::Eq),
+                        left: Box::new(Expr::Integer(1)),
+                        right: Box::new(Expr::Integer(2)),
+                    }),
+                    upper: Box::new(Expr::BinaryOperator {
+                        operator: BinaryOp::Concat,
+                        left: Box::new(Expr::Text(b"'a'".as_slice().into())),
+                        right: Box::new(Expr::Text(b"'b'".as_slice().into())),
+                    }),
+                }),
+            }
+        );
+
+        assert_parser!(
+            parse_expr,
+            b"col between 1 and 2 = 0",
+            23,
+            Expr::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Eq),
+                left: Box::new(Expr::Between {
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    lower: Box::new(Expr::Integer(1)),
+                    upper: Box::new(Expr::Integer(2)),
+                }),
+                right: Box::new(Expr::Integer(0)),
+            }
+        );
+
+        for input in [
+            b"col between".as_slice(),
+            b"col between 1",
+            b"col between 1 and",
+            b"col not between 1, 2",
+        ] {
+            let mut parser = Parser::new(input);
+            assert!(parse_expr(&mut parser).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_expr_pattern() {
+        assert_parser!(
+            parse_expr,
+            b"col like 'a%'",
+            13,
+            Expr::Pattern {
+                operator: PatternOp::Like,
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                pattern: Box::new(Expr::Text(b"'a%'".as_slice().into())),
+                escape: None,
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col GLOB 'a*'",
+            13,
+            Expr::Pattern {
+                operator: PatternOp::Glob,
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                pattern: Box::new(Expr::Text(b"'a*'".as_slice().into())),
+                escape: None,
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col not like 'a%'",
+            17,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::Pattern {
+                    operator: PatternOp::Like,
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    pattern: Box::new(Expr::Text(b"'a%'".as_slice().into())),
+                    escape: None,
+                }),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col NOT GLOB 'a*'",
+            17,
+            Expr::UnaryOperator {
+                operator: UnaryOp::Not,
+                expr: Box::new(Expr::Pattern {
+                    operator: PatternOp::Glob,
+                    expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                    pattern: Box::new(Expr::Text(b"'a*'".as_slice().into())),
+                    escape: None,
+                }),
+            }
+        );
+        assert_parser!(
+            parse_expr,
+            b"col LIKE 'a!%' ESCAPE '!'",
+            25,
+            Expr::Pattern {
+                operator: PatternOp::Like,
+                expr: Box::new(Expr::Column(b"col".as_slice().into())),
+                pattern: Box::new(Expr::Text(b"'a!%'".as_slice().into())),
+                escape: Some(Box::new(Expr::Text(b"'!'".as_slice().into()))),
+            }
+        );
+    }
+
     #[test]
     fn test_parse_expr_operators() {
         assert_parser!(
@@ -0,0 +1,433 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pattern matching for LIKE and GLOB operators.
+//!
+//! This is a port of patternCompare() in func.c of SQLite.
+
+use crate::utils::UPPER_TO_LOWER;
+
+const REPLACEMENT_CHARACTER: u32 = 0xFFFD;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum MatchResult {
+    Match,
+    NoMatch,
+    /// No match and the rest of the pattern never matches. The caller can stop
+    /// trying other positions.
+    NoWildcardMatch,
+}
+
+struct CompareInfo {
+    match_all: u32,
+    match_one: u32,
+    /// The escape character for LIKE or '[' for GLOB.
+    match_other: u32,
+    match_set: bool,
+    no_case: bool,
+}
+
+const GLOB_INFO: CompareInfo = CompareInfo {
+    match_all: b'*' as u32,
+    match_one: b'?' as u32,
+    match_other: b'[' as u32,
+    match_set: true,
+    no_case: false,
+};
+
+/// Whether the input matches the LIKE pattern.
+///
+/// The comparison is case insensitive only for ASCII characters.
+///
+/// https://www.sqlite.org/lang_expr.html#the_like_glob_regexp_match_and_extract_operators
+pub fn like(pattern: &[u8], input: &[u8], escape: Option<u32>) -> bool {
+    let info = CompareInfo {
+        match_all: b'%' as u32,
+        match_one: b'_' as u32,
+        // 0 never matches because it terminates the pattern.
+        match_other: escape.unwrap_or(0),
+        match_set: false,
+        no_case: true,
+    };
+    pattern_compare(truncate_nul(pattern), truncate_nul(input), &info) == MatchResult::Match
+}
+
+/// Whether the input matches the GLOB pattern.
+///
+/// https://www.sqlite.org/lang_expr.html#the_like_glob_regexp_match_and_extract_operators
+pub fn glob(pattern: &[u8], input: &[u8]) -> bool {
+    pattern_compare(truncate_nul(pattern), truncate_nul(input), &GLOB_INFO) == MatchResult::Match
+}
+
+/// The prefix of the LIKE pattern without ESCAPE before the first wildcard.
+///
+/// Every input matching the pattern starts with the prefix ignoring the case
+/// of ASCII characters.
+pub fn like_prefix(pattern: &[u8]) -> &[u8] {
+    literal_prefix(pattern, b"%_")
+}
+
+/// The prefix of the GLOB pattern before the first wildcard.
+///
+/// Every input matching the pattern starts with the prefix.
+pub fn glob_prefix(pattern: &[u8]) -> &[u8] {
+    literal_prefix(pattern, b"*?[")
+}
+
+/// The prefix stops at non-ASCII characters because invalid UTF-8 sequences
+/// may match different bytes.
+fn literal_prefix<'a>(pattern: &'a [u8], wildcards: &[u8]) -> &'a [u8] {
+    let pattern = truncate_nul(pattern);
+    let len = pattern
+        .iter()
+        .position(|b| !b.is_ascii() || wildcards.contains(b))
+        .unwrap_or(pattern.len());
+    &pattern[..len]
+}
+
+/// Parse the escape character of LIKE.
+///
+/// Returns [None] unless the text is exactly one character.
+pub fn escape_char(buf: &[u8]) -> Option<u32> {
+    let buf = truncate_nul(buf);
+    let mut cursor = 0;
+    let c = read_utf8(buf, &mut cursor);
+    if c == 0 || cursor != buf.len() {
+        None
+    } else {
+        Some(c)
+    }
+}
+
+/// Text in SQLite is NUL terminated when it is passed to the pattern matcher.
+fn truncate_nul(buf: &[u8]) -> &[u8] {
+    match buf.iter().position(|&b| b == 0) {
+        Some(i) => &buf[..i],
+        None => buf,
+    }
+}
+
+/// Read a UTF-8 character and move the cursor.
+///
+/// Returns 0 at the end of the buffer. Invalid UTF-8 sequences are decoded
+/// loosely as sqlite3Utf8Read() in utf.c of SQLite does.
+pub fn read_utf8(buf: &[u8], cursor: &mut usize) -> u32 {
+    let Some(&byte) = buf.get(*cursor) else {
+        return 0;
+    };
+    *cursor += 1;
+    let mut c = byte as u32;
+    if c >= 0xc0 {
+        c = match c {
+            0xc0..=0xdf => c & 0x1f,
+            0xe0..=0xef => c & 0x0f,
+            0xf0..=0xf7 => c & 0x07,
+            _ => c & 0x03,
+        };
+        while let Some(&byte) = buf.get(*cursor) {
+            if byte & 0xc0 != 0x80 {
+                break;
+            }
+            c = c.wrapping_shl(6).wrapping_add((byte & 0x3f) as u32);
+            *cursor += 1;
+        }
+        if c < 0x80 || (c & 0xFFFFF800) == 0xD800 || (c & 0xFFFFFFFE) == 0xFFFE {
+            c = REPLACEMENT_CHARACTER;
+        }
+    }
+    c
+}
+
+#[inline]
+fn to_lower(c: u32) -> u32 {
+    if c < 0x80 {
+        UPPER_TO_LOWER[c as usize] as u32
+    } else {
+        c
+    }
+}
+
+fn pattern_compare(pattern: &[u8], input: &[u8], info: &CompareInfo) -> MatchResult {
+    let mut p = 0;
+    let mut s = 0;
+    // The position in the pattern right after the last escaped character.
+    let mut escaped = None;
+    loop {
+        let mut c = read_utf8(pattern, &mut p);
+        if c == 0 {
+            break;
+        }
+        if c == info.match_all {
+            loop {
+                c = read_utf8(pattern, &mut p);
+                if c == info.match_all {
+                    continue;
+                } else if c == info.match_one {
+                    if read_utf8(input, &mut s) == 0 {
+                        return MatchResult::NoWildcardMatch;
+                    }
+                } else {
+                    break;
+                }
+            }
+            if c == 0 {
+                return MatchResult::Match;
+            } else if c == info.match_other {
+                if info.match_set {
+                    // "[...]" immediately follows the "*". Try all the positions of the
+                    // input.
+                    // '[' is a single-byte character.
+                    let set_pattern = &pattern[p - 1..];
+                    while s < input.len() {
+                        let result = pattern_compare(set_pattern, &input[s..], info);
+                        if result != MatchResult::NoMatch {
+                            return result;
+                        }
+                        read_utf8(input, &mut s);
+                    }
+                    return MatchResult::NoWildcardMatch;
+                } else {
+                    c = read_utf8(pattern, &mut p);
+                    if c == 0 {
+                        return MatchResult::NoWildcardMatch;
+                    }
+                }
+            }
+            // c is the first character of the pattern after the wildcard. Search the
+            // input for the character and continue the match from there.
+            while s < input.len() {
+                let c2 = read_utf8(input, &mut s);
+                if c2 != c && !(info.no_case && c < 0x80 && to_lower(c) == to_lower(c2)) {
+                    continue;
+                }
+                let result = pattern_compare(&pattern[p..], &input[s..], info);
+                if result != MatchResult::NoMatch {
+                    return result;
+                }
+            }
+            return MatchResult::NoWildcardMatch;
+        }
+        if c == info.match_other {
+            if info.match_set {
+                let c = read_utf8(input, &mut s);
+                if c == 0 {
+                    return MatchResult::NoMatch;
+                }
+                let mut prior_c = 0;
+                let mut seen = false;
+                let mut invert = false;
+                let mut c2 = read_utf8(pattern, &mut p);
+                if c2 == b'^' as u32 {
+                    invert = true;
+                    c2 = read_utf8(pattern, &mut p);
+                }
+                if c2 == b']' as u32 {
+                    if c == b']' as u32 {
+                        seen = true;
+                    }
+                    c2 = read_utf8(pattern, &mut p);
+                }
+                while c2 != 0 && c2 != b']' as u32 {
+                    if c2 == b'-' as u32
+                        && !matches!(pattern.get(p), Some(b']') | None)
+                        && prior_c > 0
+                    {
+                        c2 = read_utf8(pattern, &mut p);
+                        if c >= prior_c && c <= c2 {
+                            seen = true;
+                        }
+                        prior_c = 0;
+                    } else {
+                        if c == c2 {
+                            seen = true;
+                        }
+                        prior_c = c2;
+                    }
+                    c2 = read_utf8(pattern, &mut p);
+                }
+                if c2 == 0 || seen == invert {
+                    return MatchResult::NoMatch;
+                }
+                continue;
+            } else {
+                c = read_utf8(pattern, &mut p);
+                if c == 0 {
+                    return MatchResult::NoMatch;
+                }
+                escaped = Some(p);
+            }
+        }
+        let c2 = read_utf8(input, &mut s);
+        if c == c2 {
+            continue;
+        }
+        if info.no_case && c < 0x80 && c2 < 0x80 && to_lower(c) == to_lower(c2) {
+            continue;
+        }
+        if c == info.match_one && escaped != Some(p) && c2 != 0 {
+            continue;
+        }
+        return MatchResult::NoMatch;
+    }
+    if s == input.len() {
+        MatchResult::Match
+    } else {
+        MatchResult::NoMatch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like() {
+        for (pattern, input, expected) in [
+            ("", "", true),
+            ("", "a", false),
+            ("abc", "abc", true),
+            ("abc", "ABC", true),
+            ("ABC", "abc", true),
+            ("abc", "abcd", false),
+            ("abcd", "abc", false),
+            ("a_c", "abc", true),
+            ("a_c", "ac", false),
+            ("a__", "abc", true),
+            ("%", "", true),
+            ("%", "abc", true),
+            ("a%", "abc", true),
+            ("a%", "bac", false),
+            ("%c", "abc", true),
+            ("%b%", "abc", true),
+            ("%B%", "abc", true),
+            ("%d%", "abc", false),
+            ("%_", "", false),
+            ("%_", "a", true),
+            ("%%_%", "ab", true),
+            ("a%c%e", "abcde", true),
+            ("a%c%e", "abcdf", false),
+            ("*", "abc", false),
+            ("?", "a", false),
+            // Non-ASCII characters are compared case sensitively.
+            ("\u{3042}", "\u{3042}", true),
+            ("_", "\u{3042}", true),
+            ("__", "\u{3042}", false),
+            ("%\u{3042}", "a\u{3042}", true),
+            ("\u{00e9}", "\u{00c9}", false),
+        ] {
+            assert_eq!(
+                like(pattern.as_bytes(), input.as_bytes(), None),
+                expected,
+                "pattern: {}, input: {}",
+                pattern,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_like_escape() {
+        let escape = Some(b'\\' as u32);
+        for (pattern, input, expected) in [
+            ("a\\%", "a%", true),
+            ("a\\%", "ab", false),
+            ("a\\_", "a_", true),
+            ("a\\_", "ab", false),
+            ("%\\%%", "10% off", true),
+            ("%\\%%", "10 off", false),
+            ("a\\\\", "a\\", true),
+            ("a\\", "a", false),
+        ] {
+            assert_eq!(
+                like(pattern.as_bytes(), input.as_bytes(), escape),
+                expected,
+                "pattern: {}, input: {}",
+                pattern,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_escape_char() {
+        assert_eq!(escape_char(b"\\"), Some(b'\\' as u32));
+        assert_eq!(escape_char("\u{3042}".as_bytes()), Some(0x3042));
+        assert_eq!(escape_char(b""), None);
+        assert_eq!(escape_char(b"ab"), None);
+        assert_eq!(escape_char(b"a\0b"), Some(b'a' as u32));
+    }
+
+    #[test]
+    fn test_glob() {
+        for (pattern, input, expected) in [
+            ("", "", true),
+            ("abc", "abc", true),
+            ("abc", "ABC", false),
+            ("a?c", "abc", true),
+            ("a?c", "ac", false),
+            ("*", "", true),
+            ("*", "abc", true),
+            ("a*", "abc", true),
+            ("*c", "abc", true),
+            ("*B*", "abc", false),
+            ("[abc]", "b", true),
+            ("[abc]", "d", false),
+            ("[^abc]", "d", true),
+            ("[^abc]", "a", false),
+            ("[a-c]x", "bx", true),
+            ("[a-c]x", "dx", false),
+            ("[]]", "]", true),
+            ("[]a]", "a", true),
+            ("[a-]", "-", true),
+            ("[abc", "a", false),
+            ("*[0-9]", "abc1", true),
+            ("*[0-9]", "abc", false),
+            ("*[0-9]*", "a1b", true),
+            ("%", "abc", false),
+            ("_", "a", false),
+            ("?", "\u{3042}", true),
+            ("[\u{3041}-\u{3043}]", "\u{3042}", true),
+        ] {
+            assert_eq!(
+                glob(pattern.as_bytes(), input.as_bytes()),
+                expected,
+                "pattern: {}, input: {}",
+                pattern,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_nul_terminated() {
+        assert!(like(b"abc", b"abc\0def", None));
+        assert!(like(b"abc\0def", b"abc", None));
+        assert!(glob(b"abc", b"abc\0def"));
+    }
+
+    #[test]
+    fn test_pattern_prefix() {
+        assert_eq!(like_prefix(b"abc%"), b"abc");
+        assert_eq!(like_prefix(b"ab_d%"), b"ab");
+        assert_eq!(like_prefix(b"abc"), b"abc");
+        assert_eq!(like_prefix(b"%abc"), b"");
+        assert_eq!(like_prefix(b"a*?[b%"), b"a*?[b");
+        assert_eq!(like_prefix("ab\u{3042}c%".as_bytes()), b"ab");
+        assert_eq!(like_prefix(b"ab\0c%"), b"ab");
+        assert_eq!(glob_prefix(b"abc*"), b"abc");
+        assert_eq!(glob_prefix(b"ab?c"), b"ab");
+        assert_eq!(glob_prefix(b"ab[c]"), b"ab");
+        assert_eq!(glob_prefix(b"a%_b*"), b"a%_b");
+    }
+}
@@ -43,7 +43,6 @@ pub struct SlicePayload<'a> {
 }
 
 impl<'a> SlicePayload<'a> {
-    #[allow(dead_code)]
     pub fn new(buf: &'a [u8]) -> anyhow::Result<Self> {
         let size = PayloadSize::try_from(buf.len() as u64)
             .map_err(|_| anyhow::anyhow!("payload size too large"))?;
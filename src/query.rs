@@ -14,6 +14,7 @@
 
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::rc::Rc;
 
 use crate::btree::BtreeContext;
 use crate::cursor::BtreeCursor;
@@ -24,12 +25,16 @@ use crate::pager::PageId;
 use crate::pager::Pager;
 use crate::parser::BinaryOp;
 use crate::parser::CompareOp;
+use crate::parser::PatternOp;
+use crate::pattern::glob_prefix;
+use crate::pattern::like_prefix;
 use crate::payload::LocalPayload;
 use crate::payload::Payload;
 use crate::record::parse_record;
 use crate::record::parse_record_header;
 use crate::record::SerialType;
 use crate::schema::ColumnNumber;
+use crate::schema::Index;
 use crate::schema::Table;
 use crate::value::Collation;
 use crate::value::ConstantValue;
@@ -78,97 +83,781 @@ impl Display for Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// How the rows of a table are read by a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessMethod {
+    /// Scan all the rows of the table.
+    FullScan,
+    /// Look up the row by the rowid.
+    RowId,
+    /// Seek the range of the rowid.
+    RowIdRange,
+    /// Seek the ranges of an index.
+    IndexSeek {
+        index_name: String,
+        index_root_page: u32,
+        /// The number of the ranges of the first index column to scan.
+        n_ranges: usize,
+        /// Whether the rows are read from the index entries without the
+        /// table.
+        covering: bool,
+    },
+}
+
+#[derive(Clone)]
 pub enum QueryPlan {
     FullScan,
-    IndexScan(IndexInfo),
+    IndexScan(Rc<IndexInfo>),
     RowId(i64),
+    /// Scan the rows whose rowid is between the bounds inclusive.
+    RowIdRange(i64, i64),
+    /// Full scan of a WITHOUT ROWID table, which is an index b-tree keyed by
+    /// the primary key.
+    WithoutRowidScan,
 }
 
 impl QueryPlan {
     pub fn generate(table: &Table, filter: &Expression) -> Self {
+        // TODO: Use the primary key and indexes of WITHOUT ROWID tables.
+        if table.without_rowid {
+            return Self::WithoutRowidScan;
+        }
+        Self::generate_term(table, filter, filter)
+    }
+
+    /// Generate the plan narrowing down the rows by the term of the filter.
+    fn generate_term(table: &Table, term: &Expression, filter: &Expression) -> Self {
         let mut plan = Self::FullScan;
 
-        if let Expression::BinaryOperator {
-            operator: BinaryOp::Compare(CompareOp::Eq),
-            left,
-            right,
-        } = filter
-        {
-            match (left.as_ref(), right.as_ref()) {
-                (
-                    Expression::Column((ColumnNumber::RowId, _, _)),
-                    Expression::Const(ConstantValue::Integer(value)),
-                )
-                | (
-                    Expression::Const(ConstantValue::Integer(value)),
-                    Expression::Column((ColumnNumber::RowId, _, _)),
-                ) => plan = Self::RowId(*value),
-                (
-                    Expression::Column((column_number, type_affinity, collation)),
-                    Expression::Const(const_value),
-                )
-                | (
-                    Expression::Const(const_value),
-                    Expression::Column((column_number, type_affinity, collation)),
-                ) => {
-                    let mut next_index = table.indexes.as_ref();
-                    while let Some(index) = next_index {
-                        if index.columns[0] == *column_number {
-                            break;
+        match term {
+            Expression::BinaryOperator {
+                operator: BinaryOp::And,
+                left,
+                right,
+            } => {
+                // The whole filter is evaluated for each row anyway, so either side can narrow
+                // down the rows to scan.
+                let left = Self::generate_term(table, left, filter);
+                let right = Self::generate_term(table, right, filter);
+                plan = match (left, right) {
+                    (plan @ Self::RowId(_), _) | (_, plan @ Self::RowId(_)) => plan,
+                    (plan @ Self::IndexScan(_), _) | (_, plan @ Self::IndexScan(_)) => plan,
+                    (plan, _) => plan,
+                };
+            }
+            Expression::BinaryOperator {
+                operator: BinaryOp::Or,
+                left,
+                right,
+            } => {
+                // Both sides must be narrowed down by the same index. The rows are scanned by the
+                // union of the ranges and the whole filter is evaluated for each row.
+                if let (Self::IndexScan(left), Self::IndexScan(right)) = (
+                    Self::generate_term(table, left, filter),
+                    Self::generate_term(table, right, filter),
+                ) {
+                    // The index determines the collation.
+                    if left.page_id == right.page_id {
+                        let mut index_info = Rc::unwrap_or_clone(left);
+                        let right = Rc::unwrap_or_clone(right);
+                        index_info.ranges.extend(right.ranges);
+                        index_info.ranges =
+                            IndexRange::union(index_info.ranges, &index_info.collation);
+                        plan = Self::IndexScan(Rc::new(index_info));
+                    }
+                }
+            }
+            Expression::BinaryOperator {
+                operator: BinaryOp::Compare(compare_op),
+                left,
+                right,
+            } => {
+                // Normalize to "column <op> value".
+                let (column, value, compare_op) = match (left.as_ref(), right.as_ref()) {
+                    (Expression::Column(column), value) => (column, value, *compare_op),
+                    (value, Expression::Column(column)) => {
+                        (column, value, swap_operands(*compare_op))
+                    }
+                    _ => {
+                        return Self::generate_expression_term(
+                            table,
+                            left,
+                            right,
+                            *compare_op,
+                            filter,
+                        )
+                    }
+                };
+                let (column_number, type_affinity, collation) = column;
+                match (compare_op, column_number, value) {
+                    (
+                        CompareOp::Eq | CompareOp::Is,
+                        ColumnNumber::RowId,
+                        Expression::Const(value),
+                    ) => {
+                        // The rowid has INTEGER affinity, e.g. '5' and 5.0 are 5. Other values
+                        // never equal to rowids and the whole table is scanned by the filter.
+                        if let Value::Integer(rowid) = value.as_value().apply_numeric_affinity() {
+                            plan = Self::RowId(rowid);
                         }
-                        next_index = index.next.as_ref();
                     }
-                    if let Some(index) = next_index {
-                        let value = match type_affinity {
-                            TypeAffinity::Integer | TypeAffinity::Real | TypeAffinity::Numeric => {
-                                ConstantValue::copy_from(
-                                    const_value.as_value().apply_numeric_affinity(),
-                                )
-                            }
-                            TypeAffinity::Text => ConstantValue::copy_from(
-                                const_value.as_value().apply_text_affinity(),
+                    _ => {
+                        let range = match value {
+                            // TODO: Consider collation of constant value.
+                            Expression::Const(const_value) => IndexRange::from_compare(
+                                compare_op,
+                                apply_index_affinity(const_value, *type_affinity),
                             ),
-                            TypeAffinity::Blob => ConstantValue::copy_from(const_value.as_value()),
+                            Expression::Null if compare_op == CompareOp::Is => {
+                                Some(IndexRange::Null)
+                            }
+                            Expression::Null if compare_op == CompareOp::IsNot => {
+                                Some(IndexRange::Values {
+                                    lower: None,
+                                    upper: None,
+                                })
+                            }
+                            _ => None,
                         };
-
-                        // TODO: Consider collation of constant value.
-                        plan = Self::IndexScan(IndexInfo {
+                        if let (Some(range), Some(index)) =
+                            (range, find_index(table, column_number, collation, filter))
+                        {
+                            plan = Self::IndexScan(Rc::new(IndexInfo {
+                                page_id: index.root_page_id,
+                                collation: collation.clone(),
+                                ranges: vec![range],
+                                columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                                desc: index.descending[0],
+                                covering: false,
+                            }));
+                        }
+                    }
+                }
+            }
+            Expression::InList { expr, list } => {
+                if let Expression::Column((column_number, type_affinity, collation)) = expr.as_ref()
+                {
+                    if let Some(index) = find_index(table, column_number, collation, filter) {
+                        let mut keys = Vec::with_capacity(list.len());
+                        let mut all_const = true;
+                        for item in list {
+                            match item {
+                                Expression::Const(const_value) => {
+                                    keys.push(apply_index_affinity(const_value, *type_affinity));
+                                }
+                                // NULL never matches.
+                                Expression::Null => {}
+                                _ => {
+                                    all_const = false;
+                                    break;
+                                }
+                            }
+                        }
+                        if all_const {
+                            // Look up the index in the key order and skip duplicated keys so
+                            // that each row is returned only once.
+                            keys.sort_by(|k1, k2| {
+                                ValueCmp::new(&k1.as_value(), collation).compare(&k2.as_value())
+                            });
+                            keys.dedup_by(|k1, k2| {
+                                ValueCmp::new(&k1.as_value(), collation).compare(&k2.as_value())
+                                    == Ordering::Equal
+                            });
+                            plan = Self::IndexScan(Rc::new(IndexInfo {
+                                page_id: index.root_page_id,
+                                collation: collation.clone(),
+                                ranges: keys.into_iter().map(IndexRange::point).collect(),
+                                columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                                desc: index.descending[0],
+                                covering: false,
+                            }));
+                        }
+                    }
+                }
+            }
+            Expression::Between { expr, lower, upper } => {
+                if let (
+                    Expression::Column((column_number, type_affinity, collation)),
+                    Expression::Const(lower),
+                    Expression::Const(upper),
+                ) = (expr.as_ref(), lower.as_ref(), upper.as_ref())
+                {
+                    if let Some(index) = find_index(table, column_number, collation, filter) {
+                        plan = Self::IndexScan(Rc::new(IndexInfo {
                             page_id: index.root_page_id,
-                            keys: vec![(value, collation.clone())],
-                            n_extra: index.columns.len() - 1,
-                        });
+                            collation: collation.clone(),
+                            ranges: vec![IndexRange::Values {
+                                lower: Some((apply_index_affinity(lower, *type_affinity), true)),
+                                upper: Some((apply_index_affinity(upper, *type_affinity), true)),
+                            }],
+                            columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                            desc: index.descending[0],
+                            covering: false,
+                        }));
                     }
                 }
-                _ => {}
-            };
-        };
+            }
+            Expression::Pattern {
+                operator,
+                expr,
+                pattern,
+                escape: None,
+            } => {
+                // Non-text values in the column may match the pattern after being converted to
+                // text, but they are not in the ranges of the prefix.
+                if let (
+                    Expression::Column((column_number, TypeAffinity::Text, _)),
+                    Expression::Const(ConstantValue::Text(pattern)),
+                ) = (expr.as_ref(), pattern.as_ref())
+                {
+                    // LIKE ignores the case of ASCII characters as NOCASE does.
+                    let (prefix, collation) = match operator {
+                        PatternOp::Like => (like_prefix(pattern), Collation::NoCase),
+                        PatternOp::Glob => (glob_prefix(pattern), Collation::Binary),
+                    };
+                    if !prefix.is_empty() {
+                        if let Some(index) = find_index(table, column_number, &collation, filter) {
+                            plan = Self::IndexScan(Rc::new(IndexInfo {
+                                page_id: index.root_page_id,
+                                ranges: IndexRange::prefix(prefix, &collation),
+                                collation,
+                                columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                                desc: index.descending[0],
+                                covering: false,
+                            }));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
         plan
     }
 
+    /// Generate the plan for "expr <op> value" by the index on the expression.
+    fn generate_expression_term(
+        table: &Table,
+        left: &Expression,
+        right: &Expression,
+        compare_op: CompareOp,
+        filter: &Expression,
+    ) -> Self {
+        let (expr, value, compare_op) = match (left, right) {
+            (expr, Expression::Const(value)) => (expr, value, compare_op),
+            (Expression::Const(value), expr) => (expr, value, swap_operands(compare_op)),
+            _ => return Self::FullScan,
+        };
+        // Expressions without affinity do not convert the constant.
+        let type_affinity = expr.affinity().unwrap_or(TypeAffinity::Blob);
+        let range =
+            IndexRange::from_compare(compare_op, apply_index_affinity(value, type_affinity));
+        match (range, find_expression_index(table, expr, filter)) {
+            (Some(range), Some(index)) => Self::IndexScan(Rc::new(IndexInfo {
+                page_id: index.root_page_id,
+                collation: index.columns[0].1.clone(),
+                ranges: vec![range],
+                columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                desc: index.descending[0],
+                covering: false,
+            })),
+            _ => Self::FullScan,
+        }
+    }
+
+    /// Read the rows from the index entries without the table if the index
+    /// has all the columns used by the query.
+    pub fn use_covering_index(&mut self, used_columns: &[ColumnNumber]) {
+        if let Self::IndexScan(index_info) = self {
+            if used_columns
+                .iter()
+                .all(|column| *column == ColumnNumber::RowId || index_info.columns.contains(column))
+            {
+                Rc::make_mut(index_info).covering = true;
+            }
+        }
+    }
+
+    /// Describe the plan. `get_name` resolves the name of the index.
+    pub fn access_method(&self, get_name: impl Fn(PageId) -> String) -> AccessMethod {
+        match self {
+            Self::FullScan | Self::WithoutRowidScan => AccessMethod::FullScan,
+            Self::RowId(_) => AccessMethod::RowId,
+            Self::RowIdRange(..) => AccessMethod::RowIdRange,
+            Self::IndexScan(index_info) => index_info.access_method(get_name),
+        }
+    }
+
     pub fn index_page_id(&self) -> Option<PageId> {
         match self {
-            Self::FullScan | Self::RowId(_) => None,
+            Self::FullScan | Self::RowId(_) | Self::RowIdRange(..) | Self::WithoutRowidScan => None,
             Self::IndexScan(index_info) => Some(index_info.page_id),
         }
     }
 }
 
+/// Find the index whose first column is the column.
+///
+/// The index must use the same collation as the comparison. A partial index
+/// is used only if the filter implies its WHERE clause.
+fn find_index<'a>(
+    table: &'a Table,
+    column_number: &ColumnNumber,
+    collation: &Collation,
+    filter: &Expression,
+) -> Option<&'a Index> {
+    let mut next_index = table.indexes.as_ref();
+    while let Some(index) = next_index {
+        let (first_column, first_collation) = &index.columns[0];
+        if !index.unsupported
+            && index.expressions[0].is_none()
+            && first_column == column_number
+            && first_collation == collation
+            && index
+                .predicate
+                .as_ref()
+                .is_none_or(|predicate| implies(filter, &predicate.expr))
+        {
+            return Some(index);
+        }
+        next_index = index.next.as_ref();
+    }
+    None
+}
+
+/// Find the index whose first key is the same expression.
+///
+/// The collation of the key is the one of the expression.
+fn find_expression_index<'a>(
+    table: &'a Table,
+    expr: &Expression,
+    filter: &Expression,
+) -> Option<&'a Index> {
+    let mut next_index = table.indexes.as_ref();
+    while let Some(index) = next_index {
+        if !index.unsupported
+            && index.expressions[0]
+                .as_ref()
+                .is_some_and(|key| is_same_expression(&key.expr, expr))
+            && index
+                .predicate
+                .as_ref()
+                .is_none_or(|predicate| implies(filter, &predicate.expr))
+        {
+            return Some(index);
+        }
+        next_index = index.next.as_ref();
+    }
+    None
+}
+
+/// The operator of the comparison with swapped operands. e.g. "value <
+/// column" is the same as "column > value".
+fn swap_operands(compare_op: CompareOp) -> CompareOp {
+    match compare_op {
+        CompareOp::Gt => CompareOp::Lt,
+        CompareOp::Ge => CompareOp::Le,
+        CompareOp::Lt => CompareOp::Gt,
+        CompareOp::Le => CompareOp::Ge,
+        op => op,
+    }
+}
+
+/// Whether the rows satisfying the filter always satisfy the predicate.
+///
+/// Each AND-connected term of the predicate must appear in the filter. `x IS
+/// NOT NULL` is also implied by comparisons of `x` other than IS and IS NOT.
+fn implies(filter: &Expression, predicate: &Expression) -> bool {
+    let mut filter_terms = Vec::new();
+    collect_and_terms(filter, &mut filter_terms);
+    let mut predicate_terms = Vec::new();
+    collect_and_terms(predicate, &mut predicate_terms);
+    predicate_terms.iter().all(|predicate_term| {
+        filter_terms.iter().any(|filter_term| {
+            is_same_expression(filter_term, predicate_term)
+                || matches!(
+                    predicate_term,
+                    Expression::BinaryOperator {
+                        operator: BinaryOp::Compare(CompareOp::IsNot),
+                        left,
+                        right,
+                    } if matches!(right.as_ref(), Expression::Null)
+                        && is_not_null_by(filter_term, left)
+                )
+        })
+    })
+}
+
+fn collect_and_terms<'a>(expr: &'a Expression, terms: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::BinaryOperator {
+            operator: BinaryOp::And,
+            left,
+            right,
+        } => {
+            collect_and_terms(left, terms);
+            collect_and_terms(right, terms);
+        }
+        expr => terms.push(expr),
+    }
+}
+
+/// Whether the term is never true if the column is NULL.
+fn is_not_null_by(term: &Expression, column: &Expression) -> bool {
+    if !matches!(column, Expression::Column(_)) {
+        return false;
+    }
+    match term {
+        Expression::BinaryOperator {
+            operator: BinaryOp::Compare(compare_op),
+            left,
+            right,
+        } if !matches!(compare_op, CompareOp::Is | CompareOp::IsNot) => {
+            is_same_expression(left, column) || is_same_expression(right, column)
+        }
+        Expression::Between { expr, .. } | Expression::InList { expr, .. } => {
+            is_same_expression(expr, column)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the expressions are structurally the same.
+///
+/// This is conservative and returns `false` for unsupported expressions.
+fn is_same_expression(e1: &Expression, e2: &Expression) -> bool {
+    match (e1, e2) {
+        (Expression::Column(c1), Expression::Column(c2)) => c1 == c2,
+        (Expression::Null, Expression::Null) => true,
+        (Expression::Const(v1), Expression::Const(v2)) => v1.as_value() == v2.as_value(),
+        (
+            Expression::UnaryOperator {
+                operator: op1,
+                expr: e1,
+            },
+            Expression::UnaryOperator {
+                operator: op2,
+                expr: e2,
+            },
+        ) => op1 == op2 && is_same_expression(e1, e2),
+        (
+            Expression::BinaryOperator {
+                operator: op1,
+                left: l1,
+                right: r1,
+            },
+            Expression::BinaryOperator {
+                operator: op2,
+                left: l2,
+                right: r2,
+            },
+        ) => op1 == op2 && is_same_expression(l1, l2) && is_same_expression(r1, r2),
+        (
+            Expression::Collate {
+                expr: e1,
+                collation: c1,
+            },
+            Expression::Collate {
+                expr: e2,
+                collation: c2,
+            },
+        ) => c1 == c2 && is_same_expression(e1, e2),
+        (
+            Expression::Cast {
+                expr: e1,
+                type_affinity: t1,
+            },
+            Expression::Cast {
+                expr: e2,
+                type_affinity: t2,
+            },
+        ) => t1 == t2 && is_same_expression(e1, e2),
+        (
+            Expression::Between {
+                expr: e1,
+                lower: lower1,
+                upper: upper1,
+            },
+            Expression::Between {
+                expr: e2,
+                lower: lower2,
+                upper: upper2,
+            },
+        ) => {
+            is_same_expression(e1, e2)
+                && is_same_expression(lower1, lower2)
+                && is_same_expression(upper1, upper2)
+        }
+        (
+            Expression::Function {
+                function: f1,
+                args: args1,
+            },
+            Expression::Function {
+                function: f2,
+                args: args2,
+            },
+        ) => {
+            f1 == f2
+                && args1.len() == args2.len()
+                && args1
+                    .iter()
+                    .zip(args2)
+                    .all(|(e1, e2)| is_same_expression(e1, e2))
+        }
+        (
+            Expression::InList {
+                expr: e1,
+                list: list1,
+            },
+            Expression::InList {
+                expr: e2,
+                list: list2,
+            },
+        ) => {
+            is_same_expression(e1, e2)
+                && list1.len() == list2.len()
+                && list1
+                    .iter()
+                    .zip(list2)
+                    .all(|(e1, e2)| is_same_expression(e1, e2))
+        }
+        _ => false,
+    }
+}
+
+/// Convert the constant value to compare with the values in the index.
+fn apply_index_affinity(value: &ConstantValue, type_affinity: TypeAffinity) -> ConstantValue {
+    match type_affinity {
+        TypeAffinity::Integer | TypeAffinity::Real | TypeAffinity::Numeric => {
+            ConstantValue::copy_from(value.as_value().apply_numeric_affinity())
+        }
+        TypeAffinity::Text => ConstantValue::copy_from(value.as_value().apply_text_affinity()),
+        TypeAffinity::Blob => value.clone(),
+    }
+}
+
+#[derive(Clone)]
 pub struct IndexInfo {
     page_id: PageId,
-    keys: Vec<(ConstantValue, Collation)>,
-    n_extra: usize,
+    /// The collation of the first column of the index.
+    collation: Collation,
+    /// Whether the first column of the index is sorted in descending order.
+    desc: bool,
+    /// Ranges of the first column to scan in order. They must not overlap.
+    ranges: Vec<IndexRange>,
+    /// The columns of the index except the rowid at the end.
+    columns: Vec<ColumnNumber>,
+    /// Whether the index has all the columns used by the query so that the
+    /// table is not read.
+    covering: bool,
+}
+
+impl IndexInfo {
+    fn access_method(&self, get_name: impl Fn(PageId) -> String) -> AccessMethod {
+        AccessMethod::IndexSeek {
+            index_name: get_name(self.page_id),
+            index_root_page: self.page_id.get(),
+            n_ranges: self.ranges.len(),
+            covering: self.covering,
+        }
+    }
+}
+
+/// A range of the values of the first column of an index.
+#[derive(Debug, Clone)]
+enum IndexRange {
+    /// NULL values.
+    Null,
+    /// Non-NULL values between the bounds.
+    ///
+    /// Each bound holds the value and whether it is inclusive. [None] means
+    /// unbounded.
+    Values {
+        lower: Option<(ConstantValue, bool)>,
+        upper: Option<(ConstantValue, bool)>,
+    },
+}
+
+impl IndexRange {
+    fn point(key: ConstantValue) -> Self {
+        Self::Values {
+            lower: Some((key.clone(), true)),
+            upper: Some((key, true)),
+        }
+    }
+
+    /// The range of the column values satisfying "column <op> key".
+    fn from_compare(op: CompareOp, key: ConstantValue) -> Option<Self> {
+        let (lower, upper) = match op {
+            CompareOp::Eq | CompareOp::Is => return Some(Self::point(key)),
+            CompareOp::Ne | CompareOp::IsNot => return None,
+            CompareOp::Gt => (Some((key, false)), None),
+            CompareOp::Ge => (Some((key, true)), None),
+            CompareOp::Lt => (None, Some((key, false))),
+            CompareOp::Le => (None, Some((key, true))),
+        };
+        Some(Self::Values { lower, upper })
+    }
+
+    /// The ranges of the column values which may start with the prefix.
+    ///
+    /// The text values starting with the prefix are between the prefix and its
+    /// successor. BLOBs are compared as text by LIKE and GLOB and are all
+    /// scanned because they are sorted after text values.
+    fn prefix(prefix: &[u8], collation: &Collation) -> Vec<Self> {
+        let mut lower = prefix.to_vec();
+        if *collation == Collation::NoCase {
+            lower.make_ascii_lowercase();
+        }
+        let mut upper = lower.clone();
+        while upper.last() == Some(&0xff) {
+            upper.pop();
+        }
+        let upper = match upper.last_mut() {
+            Some(last) => {
+                *last += 1;
+                Some((ConstantValue::Text(upper), false))
+            }
+            None => None,
+        };
+        let ranges = vec![
+            Self::Values {
+                lower: Some((ConstantValue::Text(lower), true)),
+                upper,
+            },
+            Self::Values {
+                lower: Some((ConstantValue::Blob(Vec::new()), true)),
+                upper: None,
+            },
+        ];
+        Self::union(ranges, collation)
+    }
+
+    /// Merge the ranges into non-overlapping ranges in the index order.
+    fn union(mut ranges: Vec<Self>, collation: &Collation) -> Vec<Self> {
+        // NULLs come first, then the ranges ordered by their lower bounds.
+        ranges.sort_by(|r1, r2| match (r1, r2) {
+            (Self::Null, Self::Null) => Ordering::Equal,
+            (Self::Null, Self::Values { .. }) => Ordering::Less,
+            (Self::Values { .. }, Self::Null) => Ordering::Greater,
+            (Self::Values { lower: lower1, .. }, Self::Values { lower: lower2, .. }) => {
+                match (lower1, lower2) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Less,
+                    (Some(_), None) => Ordering::Greater,
+                    (Some((key1, inclusive1)), Some((key2, inclusive2))) => {
+                        ValueCmp::new(&key1.as_value(), collation)
+                            .compare(&key2.as_value())
+                            // An inclusive bound starts before an exclusive one.
+                            .then(inclusive2.cmp(inclusive1))
+                    }
+                }
+            }
+        });
+        let mut merged: Vec<Self> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match (merged.last_mut(), range) {
+                (Some(Self::Null), Self::Null) => {}
+                (
+                    Some(Self::Values { upper, .. }),
+                    Self::Values {
+                        lower: next_lower,
+                        upper: next_upper,
+                    },
+                ) if Self::is_connected(upper, &next_lower, collation) => {
+                    let extends = match (&upper, &next_upper) {
+                        (None, _) => false,
+                        (Some(_), None) => true,
+                        (Some((key, inclusive)), Some((next_key, next_inclusive))) => {
+                            match ValueCmp::new(&key.as_value(), collation)
+                                .compare(&next_key.as_value())
+                            {
+                                Ordering::Less => true,
+                                Ordering::Equal => *next_inclusive && !inclusive,
+                                Ordering::Greater => false,
+                            }
+                        }
+                    };
+                    if extends {
+                        *upper = next_upper;
+                    }
+                }
+                (_, range) => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// Whether the range which ends at `upper` overlaps or touches the
+    /// following range which starts at `lower`.
+    fn is_connected(
+        upper: &Option<(ConstantValue, bool)>,
+        lower: &Option<(ConstantValue, bool)>,
+        collation: &Collation,
+    ) -> bool {
+        match (upper, lower) {
+            (None, _) | (_, None) => true,
+            (Some((upper, upper_inclusive)), Some((lower, lower_inclusive))) => {
+                match ValueCmp::new(&upper.as_value(), collation).compare(&lower.as_value()) {
+                    Ordering::Less => false,
+                    Ordering::Equal => *upper_inclusive || *lower_inclusive,
+                    Ordering::Greater => true,
+                }
+            }
+        }
+    }
+
+    /// Compare the value of the first column of an index entry with the range.
+    ///
+    /// Returns [Ordering::Less] if the value is before the range and
+    /// [Ordering::Greater] if after.
+    fn compare(&self, value: Option<&Value>, collation: &Collation) -> Ordering {
+        let (lower, upper, value) = match (self, value) {
+            // NULLs are the smallest values in the index.
+            (Self::Null, None) => return Ordering::Equal,
+            (Self::Null, Some(_)) => return Ordering::Greater,
+            (Self::Values { .. }, None) => return Ordering::Less,
+            (Self::Values { lower, upper }, Some(value)) => (lower, upper, value),
+        };
+        if let Some((key, inclusive)) = lower {
+            match ValueCmp::new(&key.as_value(), collation).compare(value) {
+                Ordering::Greater => return Ordering::Less,
+                Ordering::Equal if !inclusive => return Ordering::Less,
+                _ => {}
+            }
+        }
+        if let Some((key, inclusive)) = upper {
+            match ValueCmp::new(&key.as_value(), collation).compare(value) {
+                Ordering::Less => return Ordering::Greater,
+                Ordering::Equal if !inclusive => return Ordering::Greater,
+                _ => {}
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// The position of an index entry relative to an [IndexRange].
+enum RangePosition {
+    Before,
+    In(i64),
+    After,
 }
 
 enum PlanExecutor<'a> {
     Full,
     Index(IndexCursor<'a>),
     RowId(Option<i64>),
+    /// The lower bound is [None] once the cursor is positioned.
+    RowIdRange(Option<i64>, i64),
+    WithoutRowid,
 }
 
 pub struct Query<'a> {
-    cursor: BtreeCursor<'a>,
+    /// The cursor of the table. [None] if the index covers the query.
+    cursor: Option<BtreeCursor<'a>>,
     plan: PlanExecutor<'a>,
-    filter: &'a Expression,
+    filter: Rc<Expression>,
     deleted: bool,
 }
 
@@ -177,22 +866,31 @@ impl<'a> Query<'a> {
         table_page_id: PageId,
         pager: &'a Pager,
         bctx: &'a BtreeContext,
-        plan: &'a QueryPlan,
-        filter: &'a Expression,
+        plan: &QueryPlan,
+        filter: Rc<Expression>,
     ) -> Result<Self> {
+        let covering = matches!(plan, QueryPlan::IndexScan(index_info) if index_info.covering);
         let plan = match plan {
             QueryPlan::FullScan => PlanExecutor::Full,
             QueryPlan::IndexScan(index_info) => PlanExecutor::Index(IndexCursor::new(
                 index_info.page_id,
                 pager,
                 bctx,
-                index_info,
+                index_info.clone(),
             )?),
             QueryPlan::RowId(rowid) => PlanExecutor::RowId(Some(*rowid)),
+            QueryPlan::RowIdRange(lower, upper) => PlanExecutor::RowIdRange(Some(*lower), *upper),
+            QueryPlan::WithoutRowidScan => PlanExecutor::WithoutRowid,
+        };
+
+        let cursor = if covering {
+            None
+        } else {
+            Some(BtreeCursor::new(table_page_id, pager, bctx)?)
         };
 
         Ok(Self {
-            cursor: BtreeCursor::new(table_page_id, pager, bctx)?,
+            cursor,
             plan,
             filter,
             deleted: false,
@@ -207,11 +905,12 @@ impl<'a> Query<'a> {
 
         loop {
             match &mut self.plan {
-                PlanExecutor::Full => {
-                    if !self.cursor.is_initialized() {
-                        self.cursor.move_to_first()?;
+                PlanExecutor::Full | PlanExecutor::WithoutRowid => {
+                    let cursor = self.cursor.as_mut().expect("table cursor");
+                    if !cursor.is_initialized() {
+                        cursor.move_to_first()?;
                     } else if !self.deleted {
-                        self.cursor.move_next()?;
+                        cursor.move_next()?;
                     } else {
                         self.deleted = false;
                     }
@@ -219,24 +918,45 @@ impl<'a> Query<'a> {
                 PlanExecutor::Index(index_cursor) => {
                     let rowid = index_cursor.next(self.deleted)?;
                     self.deleted = false;
-                    if let Some(rowid) = rowid {
-                        self.cursor.table_move_to(rowid)?;
-                    } else {
+                    let Some(rowid) = rowid else {
                         return Ok(None);
+                    };
+                    // The row is read from the index entry if the index covers the query.
+                    if let Some(cursor) = &mut self.cursor {
+                        cursor.table_move_to(rowid)?;
                     }
                 }
                 PlanExecutor::RowId(rowid) => {
                     if let Some(rowid) = rowid.take() {
-                        self.cursor.table_move_to(rowid)?;
+                        self.cursor
+                            .as_mut()
+                            .expect("table cursor")
+                            .table_move_to(rowid)?;
                     } else {
                         return Ok(None);
                     }
                 }
+                PlanExecutor::RowIdRange(lower, _) => {
+                    let cursor = self.cursor.as_mut().expect("table cursor");
+                    if let Some(lower) = lower.take() {
+                        // Cells below the lower bound are not read.
+                        cursor.table_move_to(lower)?;
+                    } else if !self.deleted {
+                        cursor.move_next()?;
+                    } else {
+                        self.deleted = false;
+                    }
+                }
             }
 
-            let Some((rowid, payload)) = self.cursor.get_table_payload()? else {
+            let Some((rowid, payload)) = self.current_row()? else {
                 return Ok(None);
             };
+            if let PlanExecutor::RowIdRange(_, upper) = &self.plan {
+                if rowid.is_some_and(|rowid| rowid > *upper) {
+                    return Ok(None);
+                }
+            }
 
             headers = parse_record_header(&payload).map_err(Error::Record)?;
             assert!(!headers.is_empty());
@@ -255,18 +975,20 @@ impl<'a> Query<'a> {
 
             let data = RowData {
                 rowid,
+                index_columns: self.index_columns(),
                 payload,
                 tmp_buf,
                 headers,
                 use_local_buffer,
                 content_offset,
             };
-            let skip = matches!(
+            let skip = !matches!(
                 self.filter.execute(Some(&data))?.0,
-                None | Some(Value::Integer(0))
+                Some(v) if v.as_bool()
             );
             RowData {
                 rowid: _,
+                index_columns: _,
                 payload: _,
                 tmp_buf,
                 headers,
@@ -278,13 +1000,14 @@ impl<'a> Query<'a> {
             }
         }
 
-        let Some((rowid, payload)) = self.cursor.get_table_payload()? else {
+        let Some((rowid, payload)) = self.current_row()? else {
             unreachable!("cursor must point to a valid row");
         };
 
         Ok(Some(RowData {
             headers,
             rowid,
+            index_columns: self.index_columns(),
             payload,
             content_offset,
             use_local_buffer,
@@ -292,8 +1015,37 @@ impl<'a> Query<'a> {
         }))
     }
 
+    /// The rowid and the payload of the row the cursor points to.
+    ///
+    /// Rows of WITHOUT ROWID tables have no rowid. The rowid of the index
+    /// entry of a covering index is in the payload.
+    fn current_row(&self) -> Result<Option<(Option<i64>, BtreePayload<'_>)>> {
+        match (&self.cursor, &self.plan) {
+            (Some(cursor), PlanExecutor::WithoutRowid) => {
+                Ok(cursor.get_index_payload()?.map(|payload| (None, payload)))
+            }
+            (Some(cursor), _) => Ok(cursor
+                .get_table_payload()?
+                .map(|(rowid, payload)| (Some(rowid), payload))),
+            (None, PlanExecutor::Index(index_cursor)) => Ok(index_cursor
+                .cursor
+                .get_index_payload()?
+                .map(|payload| (None, payload))),
+            (None, _) => unreachable!("only index scans have no table cursor"),
+        }
+    }
+
+    /// The columns of the payload from [Self::current_row()] if it is an index
+    /// entry of a covering index.
+    fn index_columns(&self) -> Option<&[ColumnNumber]> {
+        match (&self.cursor, &self.plan) {
+            (None, PlanExecutor::Index(index_cursor)) => Some(&index_cursor.index.columns),
+            _ => None,
+        }
+    }
+
     pub fn delete(&mut self) -> Result<()> {
-        self.cursor.delete()?;
+        self.cursor.as_mut().expect("table cursor").delete()?;
         if let PlanExecutor::Index(index_cursor) = &mut self.plan {
             index_cursor.cursor.delete()?;
         }
@@ -304,7 +1056,10 @@ impl<'a> Query<'a> {
 
 struct IndexCursor<'a> {
     cursor: BtreeCursor<'a>,
-    index: &'a IndexInfo,
+    index: Rc<IndexInfo>,
+    /// The position of the range being scanned in the index order. The ranges
+    /// are scanned in reverse if the first column is descending.
+    range: Option<usize>,
 }
 
 impl<'a> IndexCursor<'a> {
@@ -312,48 +1067,103 @@ impl<'a> IndexCursor<'a> {
         index_page_id: PageId,
         pager: &'a Pager,
         bctx: &'a BtreeContext,
-        index: &'a IndexInfo,
+        index: Rc<IndexInfo>,
     ) -> Result<Self> {
+        let mut cursor = BtreeCursor::new(index_page_id, pager, bctx)?;
+        cursor.set_index_order(&[index.desc]);
         Ok(Self {
-            cursor: BtreeCursor::new(index_page_id, pager, bctx)?,
+            cursor,
             index,
+            range: None,
         })
     }
 
+    /// The range at the position in the scan order.
+    fn range_at(&self, range: usize) -> &IndexRange {
+        if self.index.desc {
+            &self.index.ranges[self.index.ranges.len() - 1 - range]
+        } else {
+            &self.index.ranges[range]
+        }
+    }
+
+    fn move_to_range(&mut self, range: usize) -> Result<()> {
+        // The scan starts from the upper bound if the column is descending.
+        let start = match (self.range_at(range), self.index.desc) {
+            (IndexRange::Values { lower: bound, .. }, false)
+            | (IndexRange::Values { upper: bound, .. }, true) => {
+                bound.as_ref().map(|(key, _)| Some(key.clone()))
+            }
+            // NULLs are at the beginning of the index, or at the end if descending.
+            (IndexRange::Null, false) => None,
+            (IndexRange::Null, true) => Some(None),
+        };
+        match start {
+            Some(key) => {
+                // TODO: IndexInfo should hold ValueCmp instead of ConstantValue.
+                let key = key.as_ref().map(ConstantValue::as_value);
+                self.cursor.index_seek_ge(&[key
+                    .as_ref()
+                    .map(|key| ValueCmp::new(key, &self.index.collation))])?;
+            }
+            None => self.cursor.move_to_first()?,
+        }
+        self.range = Some(range);
+        Ok(())
+    }
+
     fn next(&mut self, deleted: bool) -> Result<Option<i64>> {
-        if !self.cursor.is_initialized() {
-            // TODO: IndexInfo should hold ValueCmp instead of ConstantValue.
-            let tmp_keys = self
-                .index
-                .keys
-                .iter()
-                .map(|(v, c)| (v.as_value(), c))
-                .collect::<Vec<_>>();
-            let mut comparators =
-                Vec::with_capacity(self.index.keys.len() + self.index.n_extra + 1);
-            comparators.extend(tmp_keys.iter().map(|(v, c)| Some(ValueCmp::new(v, c))));
-            // +1 for rowid
-            comparators.extend((0..self.index.n_extra + 1).map(|_| None));
-            self.cursor.index_move_to(&comparators)?;
-        } else if !deleted {
-            self.cursor.move_next()?;
+        match self.range {
+            None => {
+                if self.index.ranges.is_empty() {
+                    return Ok(None);
+                }
+                self.move_to_range(0)?;
+            }
+            Some(_) => {
+                if !deleted {
+                    self.cursor.move_next()?;
+                }
+            }
+        }
+
+        loop {
+            let range = self.range.expect("range is set");
+            match self.current_position(self.range_at(range))? {
+                RangePosition::In(rowid) => return Ok(Some(rowid)),
+                RangePosition::Before => self.cursor.move_next()?,
+                RangePosition::After => {
+                    if range + 1 < self.index.ranges.len() {
+                        self.move_to_range(range + 1)?;
+                    } else {
+                        return Ok(None);
+                    }
+                }
+            }
         }
+    }
 
+    /// Locate the current index entry relative to the range.
+    ///
+    /// Returns the rowid of the entry if it is in the range. The end of the
+    /// index is after any range.
+    fn current_position(&self, range: &IndexRange) -> Result<RangePosition> {
         let Some(index_payload) = self.cursor.get_index_payload()? else {
-            return Ok(None);
+            return Ok(RangePosition::After);
         };
         let mut record = parse_record(&index_payload).map_err(Error::Record)?;
-        let keys = self.index.keys.as_slice();
-        if record.len() < keys.len() {
+        if record.len() < 2 {
             return Err(Error::Record(anyhow::anyhow!("index payload is too short")));
         }
-        for (i, (key, collation)) in keys.iter().enumerate() {
-            if let Some(value) = record.get(i).map_err(Error::Record)? {
-                if ValueCmp::new(&key.as_value(), collation).compare(&value) == Ordering::Equal {
-                    continue;
-                }
-            }
-            return Ok(None);
+        let value = record.get(0).map_err(Error::Record)?;
+        let mut ordering = range.compare(value.as_ref(), &self.index.collation);
+        if self.index.desc {
+            ordering = ordering.reverse();
+        }
+        match ordering {
+            Ordering::Less => return Ok(RangePosition::Before),
+            Ordering::Greater => return Ok(RangePosition::After),
+            Ordering::Equal => {}
         }
         let Some(Value::Integer(rowid)) = record.get(record.len() - 1).map_err(Error::Record)?
         else {
@@ -362,16 +1172,23 @@ impl<'a> IndexCursor<'a> {
             )));
         };
 
-        Ok(Some(rowid))
+        Ok(RangePosition::In(rowid))
     }
 }
 
 pub struct RowData<'a> {
-    rowid: i64,
+    rowid: Option<i64>,
+    /// The columns of the index if the payload is an index entry. The rowid
+    /// follows them.
+    index_columns: Option<&'a [ColumnNumber]>,
     payload: BtreePayload<'a>,
     headers: Vec<(SerialType, usize)>,
     content_offset: usize,
+    /// Whether the whole content is in the page. Text and blob values then
+    /// reference the page buffer without copying.
     use_local_buffer: bool,
+    /// The content copied from the overflow pages. Empty unless the payload
+    /// overflows.
     tmp_buf: Vec<u8>,
 }
 
@@ -379,27 +1196,950 @@ impl<'a> DataContext for RowData<'a> {
     fn get_column_value(
         &self,
         column_idx: &ColumnNumber,
-    ) -> std::result::Result<Option<Value>, Box<dyn std::error::Error + Sync + Send>> {
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        let idx =
+            match (column_idx, self.index_columns) {
+                (ColumnNumber::Column(idx), None) => *idx,
+                (ColumnNumber::RowId, None) => return Ok(self.rowid.map(Value::Integer)),
+                (ColumnNumber::RowId, Some(columns)) => columns.len(),
+                (column_idx, Some(columns)) => columns
+                    .iter()
+                    .position(|column| column == column_idx)
+                    .ok_or_else(|| anyhow::anyhow!("column is not in the index"))?,
+            };
+        if let Some((serial_type, offset)) = self.headers.get(idx) {
+            let contents_buffer = if self.use_local_buffer {
+                &self.payload.buf()[self.content_offset..]
+            } else {
+                &self.tmp_buf
+            };
+            let offset = offset - self.content_offset;
+            if contents_buffer.len() < offset
+                || contents_buffer.len() - offset < serial_type.content_size() as usize
+            {
+                return Err(anyhow::anyhow!("payload does not have enough size").into());
+            }
+            Ok(serial_type.parse(&contents_buffer[offset..]))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// How to find the rows of the inner table of a join for each row of the
+/// outer table.
+pub struct JoinPlan {
+    /// The number of the columns of the outer table.
+    n_outer_columns: usize,
+    inner_page_id: PageId,
+    lookup: JoinLookup,
+    /// Whether the outer rows without any matching inner row are returned
+    /// with NULLs for the inner columns.
+    left: bool,
+}
+
+enum JoinLookup {
+    /// Scan the whole inner table for each outer row.
+    Scan(QueryPlan),
+    /// Look up the rowid equal to the outer column.
+    RowId(ColumnNumber),
+    /// Look up the index whose first column equals to the outer column.
+    Index {
+        outer: ColumnNumber,
+        type_affinity: TypeAffinity,
+        index_info: IndexInfo,
+    },
+}
+
+impl JoinPlan {
+    /// Use the rowid or an index of the inner table if the join constraint
+    /// has an equality between an inner column and an outer column.
+    pub fn generate(
+        outer_table: &Table,
+        inner_table: &Table,
+        constraint: &Expression,
+        left: bool,
+    ) -> Self {
+        let lookup =
+            Self::generate_lookup(inner_table, constraint, constraint).unwrap_or_else(|| {
+                JoinLookup::Scan(QueryPlan::generate(inner_table, &Expression::one()))
+            });
+        Self {
+            n_outer_columns: outer_table.columns.len(),
+            inner_page_id: inner_table.root_page_id,
+            lookup,
+            left,
+        }
+    }
+
+    /// `term` is a term of the whole `constraint`.
+    fn generate_lookup(
+        inner_table: &Table,
+        term: &Expression,
+        constraint: &Expression,
+    ) -> Option<JoinLookup> {
+        // TODO: Use the primary key and indexes of WITHOUT ROWID tables.
+        if inner_table.without_rowid {
+            return None;
+        }
+        match term {
+            Expression::BinaryOperator {
+                operator: BinaryOp::And,
+                left,
+                right,
+            } => match (
+                Self::generate_lookup(inner_table, left, constraint),
+                Self::generate_lookup(inner_table, right, constraint),
+            ) {
+                (Some(lookup @ JoinLookup::RowId(_)), _)
+                | (_, Some(lookup @ JoinLookup::RowId(_))) => Some(lookup),
+                (left, right) => left.or(right),
+            },
+            Expression::BinaryOperator {
+                operator: BinaryOp::Compare(CompareOp::Eq),
+                left,
+                right,
+            } => {
+                let ((inner, inner_affinity, _), (outer, outer_affinity, _)) =
+                    match (left.as_ref(), right.as_ref()) {
+                        (Expression::Column(inner), Expression::OuterColumn(outer))
+                        | (Expression::OuterColumn(outer), Expression::Column(inner)) => {
+                            (inner, outer)
+                        }
+                        _ => return None,
+                    };
+                // The collation of the left operand is used for the comparison.
+                let (_, _, collation) = match left.as_ref() {
+                    Expression::Column(column) | Expression::OuterColumn(column) => column,
+                    _ => unreachable!(),
+                };
+                if *inner == ColumnNumber::RowId {
+                    return Some(JoinLookup::RowId(*outer));
+                }
+                // The key converted by the affinity of the inner column must be the same as
+                // the operand of the comparison.
+                let same_affinity = match inner_affinity {
+                    TypeAffinity::Integer | TypeAffinity::Real | TypeAffinity::Numeric => true,
+                    TypeAffinity::Text => {
+                        matches!(outer_affinity, TypeAffinity::Text | TypeAffinity::Blob)
+                    }
+                    TypeAffinity::Blob => *outer_affinity == TypeAffinity::Blob,
+                };
+                if !same_affinity {
+                    return None;
+                }
+                let index = find_index(inner_table, inner, collation, constraint)?;
+                Some(JoinLookup::Index {
+                    outer: *outer,
+                    type_affinity: *inner_affinity,
+                    index_info: IndexInfo {
+                        page_id: index.root_page_id,
+                        collation: collation.clone(),
+                        ranges: Vec::new(),
+                        columns: index.columns.iter().map(|(column, _)| *column).collect(),
+                        desc: index.descending[0],
+                        covering: false,
+                    },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn inner_page_id(&self) -> PageId {
+        self.inner_page_id
+    }
+
+    /// Describe how the inner table is read for each outer row.
+    pub fn access_method(&self, get_name: impl Fn(PageId) -> String) -> AccessMethod {
+        match &self.lookup {
+            JoinLookup::Scan(plan) => plan.access_method(get_name),
+            JoinLookup::RowId(_) => AccessMethod::RowId,
+            // The index is looked up by the single key of the outer row.
+            JoinLookup::Index { index_info, .. } => AccessMethod::IndexSeek {
+                index_name: get_name(index_info.page_id),
+                index_root_page: index_info.page_id.get(),
+                n_ranges: 1,
+                covering: index_info.covering,
+            },
+        }
+    }
+
+    /// The plan to scan the inner table for the outer row.
+    ///
+    /// Returns [None] if no inner row can match.
+    fn inner_plan(&self, outer_row: &OuterRow) -> Option<QueryPlan> {
+        match &self.lookup {
+            JoinLookup::Scan(QueryPlan::WithoutRowidScan) => Some(QueryPlan::WithoutRowidScan),
+            JoinLookup::Scan(_) => Some(QueryPlan::FullScan),
+            JoinLookup::RowId(outer) => match outer_row.get(outer)?.apply_numeric_affinity() {
+                Value::Integer(rowid) => Some(QueryPlan::RowId(rowid)),
+                // Non-integer values never equal to rowids.
+                _ => None,
+            },
+            JoinLookup::Index {
+                outer,
+                type_affinity,
+                index_info,
+            } => {
+                // NULL never matches.
+                let key = ConstantValue::copy_from(outer_row.get(outer)?);
+                let mut index_info = index_info.clone();
+                index_info.ranges = vec![IndexRange::point(apply_index_affinity(
+                    &key,
+                    *type_affinity,
+                ))];
+                Some(QueryPlan::IndexScan(Rc::new(index_info)))
+            }
+        }
+    }
+}
+
+/// The values of the current row of the outer table of a join.
+struct OuterRow {
+    rowid: Option<i64>,
+    values: Vec<Option<ConstantValue>>,
+}
+
+impl OuterRow {
+    fn get(&self, column_idx: &ColumnNumber) -> Option<Value<'_>> {
         match column_idx {
-            ColumnNumber::Column(idx) => {
-                if let Some((serial_type, offset)) = self.headers.get(*idx) {
-                    let contents_buffer = if self.use_local_buffer {
-                        &self.payload.buf()[self.content_offset..]
-                    } else {
-                        &self.tmp_buf
+            ColumnNumber::RowId => self.rowid.map(Value::Integer),
+            ColumnNumber::Column(idx) => self
+                .values
+                .get(*idx)
+                .and_then(|value| value.as_ref().map(|value| value.as_value())),
+        }
+    }
+}
+
+/// A row of the inner table of a join with the row of the outer table.
+pub struct JoinedRow<'a, 'b> {
+    outer: &'a OuterRow,
+    /// [None] if the row is the outer row extended with NULLs by LEFT JOIN.
+    inner: Option<&'a RowData<'b>>,
+}
+
+impl<'a, 'b> DataContext for JoinedRow<'a, 'b> {
+    fn get_column_value(
+        &self,
+        column_idx: &ColumnNumber,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        match self.inner {
+            Some(inner) => inner.get_column_value(column_idx),
+            None => Ok(None),
+        }
+    }
+
+    fn get_outer_column_value(
+        &self,
+        column_idx: &ColumnNumber,
+    ) -> std::result::Result<Option<Value<'_>>, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(self.outer.get(column_idx))
+    }
+}
+
+/// Nested loop join which scans the inner table for each row of the outer
+/// table.
+pub struct JoinQuery<'a> {
+    outer: Query<'a>,
+    outer_row: OuterRow,
+    inner: Option<Query<'a>>,
+    /// Whether the current outer row is still to be returned with NULLs
+    /// because no inner row has matched it yet.
+    unmatched: bool,
+    plan: Rc<JoinPlan>,
+    /// The ON clause, which selects the inner rows to join.
+    constraint: Rc<Expression>,
+    /// The WHERE clause, which is applied to the joined rows.
+    filter: Rc<Expression>,
+    pager: &'a Pager,
+    bctx: &'a BtreeContext,
+}
+
+impl<'a> JoinQuery<'a> {
+    pub fn new(
+        outer: Query<'a>,
+        plan: Rc<JoinPlan>,
+        constraint: Rc<Expression>,
+        filter: Rc<Expression>,
+        pager: &'a Pager,
+        bctx: &'a BtreeContext,
+    ) -> Self {
+        Self {
+            outer,
+            outer_row: OuterRow {
+                rowid: None,
+                values: Vec::with_capacity(plan.n_outer_columns),
+            },
+            inner: None,
+            unmatched: false,
+            plan,
+            constraint,
+            filter,
+            pager,
+            bctx,
+        }
+    }
+
+    /// Find the next joined row satisfying the filter and pass it to `f`.
+    pub fn next<T>(&mut self, f: impl FnOnce(&JoinedRow) -> Result<T>) -> Result<Option<T>> {
+        loop {
+            if let Some(inner) = &mut self.inner {
+                while let Some(inner_row) = inner.next()? {
+                    let row = JoinedRow {
+                        outer: &self.outer_row,
+                        inner: Some(&inner_row),
                     };
-                    let offset = offset - self.content_offset;
-                    if contents_buffer.len() < offset
-                        || contents_buffer.len() - offset < serial_type.content_size() as usize
-                    {
-                        return Err(anyhow::anyhow!("payload does not have enough size").into());
+                    if !matches!(self.constraint.execute(Some(&row))?.0, Some(v) if v.as_bool()) {
+                        continue;
                     }
-                    Ok(serial_type.parse(&contents_buffer[offset..]))
-                } else {
-                    Ok(None)
+                    self.unmatched = false;
+                    if matches!(self.filter.execute(Some(&row))?.0, Some(v) if v.as_bool()) {
+                        return f(&row).map(Some);
+                    }
+                }
+                self.inner = None;
+            }
+            if self.unmatched {
+                self.unmatched = false;
+                let row = JoinedRow {
+                    outer: &self.outer_row,
+                    inner: None,
+                };
+                if matches!(self.filter.execute(Some(&row))?.0, Some(v) if v.as_bool()) {
+                    return f(&row).map(Some);
                 }
             }
-            ColumnNumber::RowId => Ok(Some(Value::Integer(self.rowid))),
+
+            let Some(outer_row) = self.outer.next()? else {
+                return Ok(None);
+            };
+            self.outer_row.rowid = outer_row.rowid;
+            self.outer_row.values.clear();
+            for i in 0..self.plan.n_outer_columns {
+                let value = outer_row
+                    .get_column_value(&ColumnNumber::Column(i))
+                    .map_err(crate::expression::Error::FailGetColumn)?;
+                self.outer_row
+                    .values
+                    .push(value.map(ConstantValue::copy_from));
+            }
+            drop(outer_row);
+            self.unmatched = self.plan.left;
+            if let Some(plan) = self.plan.inner_plan(&self.outer_row) {
+                self.inner = Some(Query::new(
+                    self.plan.inner_page_id,
+                    self.pager,
+                    self.bctx,
+                    &plan,
+                    Rc::new(Expression::one()),
+                )?);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils::*;
+    use crate::value::DEFAULT_COLLATION;
+    use crate::Connection;
+    use crate::StatementKind;
+
+    /// The index ranges if the query uses an index.
+    fn query_plan_ranges(conn: &Connection, sql: &str) -> Option<Vec<IndexRange>> {
+        let stmt = conn.prepare(sql).unwrap();
+        let StatementKind::Query(select) = &stmt.kind else {
+            panic!("not select statement");
+        };
+        match &select.query_plan {
+            QueryPlan::IndexScan(index_info) => Some(index_info.ranges.clone()),
+            _ => None,
+        }
+    }
+
+    /// The keys of the index lookups if all the index ranges are single keys.
+    fn query_plan_probes(conn: &Connection, sql: &str) -> Option<Vec<ConstantValue>> {
+        query_plan_ranges(conn, sql).map(|ranges| {
+            ranges
+                .into_iter()
+                .map(|range| {
+                    let IndexRange::Values {
+                        lower: Some((lower, true)),
+                        upper: Some((upper, true)),
+                    } = range
+                    else {
+                        panic!("not a single key: {}", sql);
+                    };
+                    assert_eq!(
+                        ValueCmp::new(&lower.as_value(), &DEFAULT_COLLATION)
+                            .compare(&upper.as_value()),
+                        Ordering::Equal
+                    );
+                    lower
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_generate_partial_index_scan() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2, col3);",
+            "CREATE INDEX index1 ON example(col1) WHERE col2 > 10 AND col3 IS NOT NULL;",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        for query in [
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 10 AND col3 IS NOT NULL;",
+            "SELECT * FROM example WHERE col3 IS NOT NULL AND col2 > 10 AND col1 = 1;",
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 10 AND col3 = 5;",
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 10 AND col3 IN (1, 2);",
+        ] {
+            assert!(query_plan_ranges(&conn, query).is_some(), "{}", query);
+        }
+        for query in [
+            "SELECT * FROM example WHERE col1 = 1;",
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 10;",
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 11 AND col3 IS NOT NULL;",
+            "SELECT * FROM example WHERE col1 = 1 AND col2 > 10 AND col3 IS 5;",
+        ] {
+            assert!(query_plan_ranges(&conn, query).is_none(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_generate_index_scan() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE col1 = 1;").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Integer(1)]));
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE col2 = '2';").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Integer(2)]));
+        assert!(query_plan_probes(&conn, "SELECT * FROM example WHERE col3 = 1;").is_none());
+    }
+
+    #[test]
+    fn test_generate_index_scan_in() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        // Keys are sorted and deduplicated. NULLs are removed.
+        let probes = query_plan_probes(
+            &conn,
+            "SELECT * FROM example WHERE col2 IN (3, NULL, '1', 1, 2.0);",
+        )
+        .unwrap();
+        assert!(matches!(
+            probes.as_slice(),
+            [
+                ConstantValue::Integer(1),
+                ConstantValue::Integer(2),
+                ConstantValue::Integer(3)
+            ]
+        ));
+        let probes =
+            query_plan_probes(&conn, "SELECT * FROM example WHERE col1 IN ('1', 1);").unwrap();
+        assert!(matches!(
+            probes.as_slice(),
+            [ConstantValue::Integer(1), ConstantValue::Text(_)]
+        ));
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE col1 IN ();").unwrap();
+        assert!(probes.is_empty());
+
+        // Not constant.
+        assert!(
+            query_plan_probes(&conn, "SELECT * FROM example WHERE col1 IN (1, col2);").is_none()
+        );
+        // Not indexed.
+        assert!(query_plan_probes(&conn, "SELECT * FROM example WHERE col3 IN (1, 2);").is_none());
+        assert!(
+            query_plan_probes(&conn, "SELECT * FROM example WHERE col1 NOT IN (1, 2);").is_none()
+        );
+    }
+
+    #[test]
+    fn test_generate_index_scan_between() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 BETWEEN 1 AND 'a';")
+                .unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Integer(1), true)),
+                upper: Some((ConstantValue::Text(upper), true)),
+            }] if upper == b"a"
+        ));
+        // The column affinity is applied to the bounds.
+        let ranges = query_plan_ranges(
+            &conn,
+            "SELECT * FROM example WHERE col2 BETWEEN '3' AND 2.0;",
+        )
+        .unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Integer(3), true)),
+                upper: Some((ConstantValue::Integer(2), true)),
+            }]
+        ));
+
+        // Not constant.
+        assert!(query_plan_ranges(
+            &conn,
+            "SELECT * FROM example WHERE col1 BETWEEN 1 AND col2;"
+        )
+        .is_none());
+        assert!(query_plan_ranges(
+            &conn,
+            "SELECT * FROM example WHERE col1 BETWEEN NULL AND 2;"
+        )
+        .is_none());
+        // Not indexed.
+        assert!(
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col3 BETWEEN 1 AND 2;").is_none()
+        );
+        assert!(query_plan_ranges(
+            &conn,
+            "SELECT * FROM example WHERE col1 NOT BETWEEN 1 AND 2;"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_generate_index_scan_pattern() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1 text, col2 text, col3, col4 text);",
+            "CREATE INDEX index1 ON example(col1 COLLATE NOCASE);",
+            "CREATE INDEX index2 ON example(col2);",
+            "CREATE INDEX index3 ON example(col3 COLLATE NOCASE);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 LIKE 'aBz%';").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Text(lower), true)),
+                upper: Some((ConstantValue::Text(upper), false)),
+            }, IndexRange::Values {
+                lower: Some((ConstantValue::Blob(blob), true)),
+                upper: None,
+            }] if lower == b"abz" && upper == b"ab{" && blob.is_empty()
+        ));
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col2 GLOB 'aBc*d';").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Text(lower), true)),
+                upper: Some((ConstantValue::Text(upper), false)),
+            }, IndexRange::Values {
+                lower: Some((ConstantValue::Blob(_), true)),
+                upper: None,
+            }] if lower == b"aBc" && upper == b"aBd"
+        ));
+
+        for sql in [
+            // Starts with a wildcard.
+            "SELECT * FROM example WHERE col1 LIKE '%abc';",
+            "SELECT * FROM example WHERE col1 LIKE '_abc';",
+            "SELECT * FROM example WHERE col2 GLOB '*abc';",
+            // The collation of the index does not match.
+            "SELECT * FROM example WHERE col1 GLOB 'abc*';",
+            "SELECT * FROM example WHERE col2 LIKE 'abc%';",
+            // The column does not have TEXT affinity.
+            "SELECT * FROM example WHERE col3 LIKE 'abc%';",
+            // Not indexed.
+            "SELECT * FROM example WHERE col4 LIKE 'abc%';",
+            // Not constant.
+            "SELECT * FROM example WHERE col1 LIKE col2;",
+            "SELECT * FROM example WHERE col1 LIKE 'abc%' ESCAPE '!';",
+            "SELECT * FROM example WHERE col1 NOT LIKE 'abc%';",
+        ] {
+            assert!(query_plan_ranges(&conn, sql).is_none(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_generate_index_scan_compare() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 > 100;").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Integer(100), false)),
+                upper: None,
+            }]
+        ));
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE col2 <= '3';").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: None,
+                upper: Some((ConstantValue::Integer(3), true)),
+            }]
+        ));
+        // The constant on the left side.
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE 1 < col1;").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: Some((ConstantValue::Integer(1), false)),
+                upper: None,
+            }]
+        ));
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE 1 >= col1;").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: None,
+                upper: Some((ConstantValue::Integer(1), true)),
+            }]
+        ));
+
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 != 1;").is_none());
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 > col2;").is_none());
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col3 > 1;").is_none());
+    }
+
+    #[test]
+    fn test_generate_index_scan_is() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 IS NULL;").unwrap();
+        assert!(matches!(ranges.as_slice(), [IndexRange::Null]));
+        let ranges = query_plan_ranges(&conn, "SELECT * FROM example WHERE NULL IS col2;").unwrap();
+        assert!(matches!(ranges.as_slice(), [IndexRange::Null]));
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE col2 IS '1';").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Integer(1)]));
+
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 = NULL;").is_none());
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 IS NOT NULL;").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: None,
+                upper: None,
+            }]
+        ));
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 IS NOT 1;").is_none());
+        assert!(query_plan_ranges(&conn, "SELECT * FROM example WHERE col3 IS NULL;").is_none());
+    }
+
+    #[test]
+    fn test_generate_index_scan_folded_constant() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE col1 = 2 + 3;").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Integer(5)]));
+        let probes = query_plan_probes(&conn, "SELECT * FROM example WHERE -1 = col1;").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Integer(-1)]));
+        let probes =
+            query_plan_probes(&conn, "SELECT * FROM example WHERE col1 = 'a' || 'b';").unwrap();
+        assert!(matches!(probes.as_slice(), [ConstantValue::Text(text)] if text == b"ab"));
+        let probes = query_plan_probes(
+            &conn,
+            "SELECT * FROM example WHERE col2 IN (1 + 1, abs(-3), '4' * 1);",
+        )
+        .unwrap();
+        assert!(matches!(
+            probes.as_slice(),
+            [
+                ConstantValue::Integer(2),
+                ConstantValue::Integer(3),
+                ConstantValue::Integer(4)
+            ]
+        ));
+
+        // 1 / 0 is NULL which never matches.
+        let probes =
+            query_plan_probes(&conn, "SELECT * FROM example WHERE col1 IN (1 / 0, 1 % 0);")
+                .unwrap();
+        assert!(probes.is_empty());
+
+        // Not constant.
+        assert!(query_plan_probes(&conn, "SELECT * FROM example WHERE col1 = col2 + 3;").is_none());
+        assert!(query_plan_probes(&conn, "SELECT * FROM example WHERE col1 = ? + 3;").is_none());
+    }
+
+    #[test]
+    fn test_generate_or() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2 integer, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+            "CREATE INDEX index2 ON example(col2, col3);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        // The keys are sorted and deduplicated.
+        let probes = query_plan_probes(
+            &conn,
+            "SELECT * FROM example WHERE col1 = 3 OR col1 = 1 OR col1 IN (2, 3);",
+        )
+        .unwrap();
+        assert!(matches!(
+            probes.as_slice(),
+            [
+                ConstantValue::Integer(1),
+                ConstantValue::Integer(2),
+                ConstantValue::Integer(3)
+            ]
+        ));
+        let probes = query_plan_probes(
+            &conn,
+            "SELECT * FROM example WHERE col2 = '2' AND col3 = 1 OR col2 = 1;",
+        )
+        .unwrap();
+        assert!(matches!(
+            probes.as_slice(),
+            [ConstantValue::Integer(1), ConstantValue::Integer(2)]
+        ));
+
+        // Overlapping ranges are merged.
+        let ranges = query_plan_ranges(
+            &conn,
+            "SELECT * FROM example WHERE col1 IS NULL OR col1 > 5 OR col1 BETWEEN 1 AND 3 OR col1 = 5 OR col1 = 2;",
+        )
+        .unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [
+                IndexRange::Null,
+                IndexRange::Values {
+                    lower: Some((ConstantValue::Integer(1), true)),
+                    upper: Some((ConstantValue::Integer(3), true)),
+                },
+                IndexRange::Values {
+                    lower: Some((ConstantValue::Integer(5), true)),
+                    upper: None,
+                },
+            ]
+        ));
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 < 5 OR col1 > 5;").unwrap();
+        assert_eq!(ranges.len(), 2);
+        let ranges =
+            query_plan_ranges(&conn, "SELECT * FROM example WHERE col1 < 5 OR col1 >= 5;").unwrap();
+        assert!(matches!(
+            ranges.as_slice(),
+            [IndexRange::Values {
+                lower: None,
+                upper: None
+            }]
+        ));
+
+        // Each side must use the same index.
+        for sql in [
+            "SELECT * FROM example WHERE col1 = 1 OR col3 = 2;",
+            "SELECT * FROM example WHERE col1 = 1 OR col2 = 2;",
+            "SELECT * FROM example WHERE col1 = 1 OR rowid = 2;",
+            "SELECT * FROM example WHERE col1 = 1 OR col1 = col2;",
+        ] {
+            assert!(query_plan_ranges(&conn, sql).is_none(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_covering_index() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(id integer primary key, col1, col2, col3);",
+            "CREATE INDEX index1 ON example(col1, col2);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        for (sql, expected) in [
+            ("SELECT col1 FROM example WHERE col1 = 1;", true),
+            ("SELECT col2, rowid, id FROM example WHERE col1 > 1;", true),
+            (
+                "SELECT col1 FROM example WHERE col1 = 1 AND col2 = 2 ORDER BY col2;",
+                true,
+            ),
+            (
+                "SELECT count(*), sum(col2) FROM example WHERE col1 = 1 GROUP BY col2;",
+                true,
+            ),
+            ("SELECT col3 FROM example WHERE col1 = 1;", false),
+            ("SELECT * FROM example WHERE col1 = 1;", false),
+            (
+                "SELECT col1 FROM example WHERE col1 = 1 AND col3 = 2;",
+                false,
+            ),
+            (
+                "SELECT col1 FROM example WHERE col1 = 1 ORDER BY col3;",
+                false,
+            ),
+            (
+                "SELECT col1 FROM example WHERE col1 = 1 GROUP BY col3;",
+                false,
+            ),
+            ("SELECT max(col3) FROM example WHERE col1 = 1;", false),
+        ] {
+            let stmt = conn.prepare(sql).unwrap();
+            let StatementKind::Query(select) = &stmt.kind else {
+                panic!("not select statement");
+            };
+            let QueryPlan::IndexScan(index_info) = &select.query_plan else {
+                panic!("not index scan: {}", sql);
+            };
+            assert_eq!(index_info.covering, expected, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_generate_rowid() {
+        let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        for (filter, expected) in [
+            ("rowid = 5", Some(5)),
+            ("rowid = '5'", Some(5)),
+            ("rowid = 5.0", Some(5)),
+            ("rowid = '5.0'", Some(5)),
+            ("rowid IS -5.0", Some(-5)),
+            ("5 = rowid", Some(5)),
+            ("rowid = 5.5", None),
+            ("rowid = '5.5'", None),
+            ("rowid = 'abc'", None),
+            ("rowid = x'35'", None),
+            ("rowid = NULL", None),
+        ] {
+            let sql = format!("SELECT * FROM example WHERE {};", filter);
+            let stmt = conn.prepare(&sql).unwrap();
+            let StatementKind::Query(select) = &stmt.kind else {
+                panic!("not select statement");
+            };
+            let rowid = match &select.query_plan {
+                QueryPlan::RowId(rowid) => Some(*rowid),
+                _ => None,
+            };
+            assert_eq!(rowid, expected, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_generate_and() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(col1, col2, col3);",
+            "CREATE INDEX index1 ON example(col1);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        for (sql, expected) in [
+            ("SELECT * FROM example WHERE col1 = 1 AND col2 = 2;", true),
+            ("SELECT * FROM example WHERE col2 = 2 AND col1 = 1;", true),
+            (
+                "SELECT * FROM example WHERE col2 = 2 AND col3 = 3 AND col1 > 1;",
+                true,
+            ),
+            ("SELECT * FROM example WHERE col2 = 2 AND col3 = 3;", false),
+            ("SELECT * FROM example WHERE col1 = 1 OR col2 = 2;", false),
+            ("SELECT * FROM example WHERE col1 = 1 OR col1 = 2;", true),
+        ] {
+            assert_eq!(query_plan_ranges(&conn, sql).is_some(), expected, "{}", sql);
+        }
+
+        // rowid is preferred.
+        let stmt = conn
+            .prepare("SELECT * FROM example WHERE col1 = 1 AND rowid = 2;")
+            .unwrap();
+        let StatementKind::Query(select) = &stmt.kind else {
+            panic!("not select statement");
+        };
+        assert!(matches!(select.query_plan, QueryPlan::RowId(2)));
+    }
+
+    #[test]
+    fn test_generate_join() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE parent(id integer primary key, name text);",
+            "CREATE TABLE child(parent_id integer, name text, col);",
+            "CREATE INDEX index1 ON child(parent_id);",
+            "CREATE INDEX index2 ON child(name COLLATE NOCASE);",
+            "CREATE INDEX index3 ON child(col);",
+        ]);
+        let conn = Connection::open(file.path()).unwrap();
+
+        for (sql, expected) in [
+            (
+                "SELECT * FROM parent JOIN child ON parent.id = child.parent_id;",
+                "index",
+            ),
+            (
+                "SELECT * FROM parent JOIN child ON child.parent_id = parent.id AND child.col = 1;",
+                "index",
+            ),
+            (
+                "SELECT * FROM parent JOIN child ON parent.id > child.parent_id;",
+                "scan",
+            ),
+            (
+                "SELECT * FROM child JOIN parent ON child.col = parent.id;",
+                "rowid",
+            ),
+            // The collation of the comparison differs from the index.
+            (
+                "SELECT * FROM parent JOIN child ON child.name = parent.name;",
+                "scan",
+            ),
+            (
+                "SELECT * FROM parent JOIN child ON child.name = parent.name COLLATE NOCASE;",
+                "scan",
+            ),
+            // The text affinity is not applied to the values of the column.
+            (
+                "SELECT * FROM parent JOIN child ON parent.name = child.col;",
+                "scan",
+            ),
+        ] {
+            let stmt = conn.prepare(sql).unwrap();
+            let StatementKind::Query(select) = &stmt.kind else {
+                panic!("not select statement");
+            };
+            let (plan, _) = select.join.as_ref().unwrap();
+            let lookup = match &plan.lookup {
+                JoinLookup::Scan(_) => "scan",
+                JoinLookup::RowId(_) => "rowid",
+                JoinLookup::Index { .. } => "index",
+            };
+            assert_eq!(lookup, expected, "{}", sql);
         }
     }
 }
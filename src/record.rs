@@ -30,8 +30,13 @@ use crate::value::Buffer;
 use crate::value::Value;
 use crate::value::ValueCmp;
 
+/// Compare the keys with the record of an index entry.
+///
+/// `descending` holds whether each column is sorted in descending order in
+/// the index. Missing entries are ascending.
 pub fn compare_record(
     comparators: &[Option<ValueCmp<'_>>],
+    descending: &[bool],
     payload: &BtreePayload,
 ) -> anyhow::Result<Ordering> {
     let mut record = parse_record(payload)?;
@@ -40,15 +45,20 @@ pub fn compare_record(
     }
     for (i, cmp) in comparators.iter().enumerate() {
         let index_value = record.get(i)?;
-        match (cmp, index_value) {
+        let ordering = match (cmp, index_value) {
             (None, None) => continue,
-            (None, Some(_)) => return Ok(Ordering::Less),
-            (Some(_), None) => return Ok(Ordering::Greater),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
             (Some(cmp), Some(index_value)) => match cmp.compare(&index_value) {
                 Ordering::Equal => continue,
-                o => return Ok(o),
+                o => o,
             },
-        }
+        };
+        return Ok(if descending.get(i) == Some(&true) {
+            ordering.reverse()
+        } else {
+            ordering
+        });
     }
     Ok(Ordering::Equal)
 }
@@ -146,7 +156,7 @@ pub struct Record<'a, P: LocalPayload<E>, E> {
 #[inline]
 pub fn parse_record<'a>(
     payload: &'a BtreePayload<'a>,
-) -> anyhow::Result<Record<BtreePayload<'a>, crate::cursor::Error>> {
+) -> anyhow::Result<Record<'a, BtreePayload<'a>, crate::cursor::Error>> {
     Record::parse(payload)
 }
 
@@ -294,11 +304,14 @@ impl<'a> RecordPayload<'a> {
                     7
                 }
                 Some(Value::Text(buf)) | Some(Value::Blob(buf)) => {
-                    let serial_type =
-                        (buf.len() << 1) + 12 + (matches!(value, Some(Value::Text(_))) as usize);
-                    // TODO: how to guarantee serial_type <= u32::MAX?
-                    assert!(serial_type <= u32::MAX as usize);
-                    let serial_type = serial_type as u32;
+                    let serial_type = ((buf.len() as u64) << 1)
+                        + 12
+                        + (matches!(value, Some(Value::Text(_))) as u64);
+                    // The serial type fits in u32 if the payload size is within the limit of
+                    // i32::MAX.
+                    let serial_type: u32 = serial_type
+                        .try_into()
+                        .map_err(|_| anyhow::anyhow!("payload size too large"))?;
                     header_size += len_varint(serial_type as u64);
                     data_size += buf.len();
                     serial_type
@@ -19,14 +19,22 @@ use std::rc::Rc;
 use anyhow::bail;
 use anyhow::Context;
 
-pub use crate::btree::*;
+use crate::expression::Expression;
+use crate::function::UserFunctions;
 use crate::pager::PageId;
 use crate::pager::PAGE_ID_1;
 use crate::parser::expect_no_more_token;
 use crate::parser::parse_create_index;
 use crate::parser::parse_create_table;
+use crate::parser::parse_expr;
 use crate::parser::ColumnConstraint;
+use crate::parser::CreateIndex;
+use crate::parser::CreateTable;
+use crate::parser::Expr;
+use crate::parser::ForeignKeyAction;
+use crate::parser::ForeignKeyClause;
 use crate::parser::Parser;
+use crate::parser::TableConstraint;
 use crate::utils::upper_to_lower;
 use crate::utils::CaseInsensitiveBytes;
 use crate::utils::MaybeQuotedBytes;
@@ -36,13 +44,15 @@ use crate::value::TypeAffinity;
 use crate::value::Value;
 use crate::value::DEFAULT_COLLATION;
 use crate::Columns;
+use crate::Connection;
 use crate::SelectStatement;
 
 struct SchemaRecord<'a> {
     type_: &'a [u8],
     name: &'a [u8],
     table_name: &'a [u8],
-    root_page_id: PageId,
+    /// Views and triggers have no b-tree and their root page id is zero.
+    root_page_id: Option<PageId>,
     sql: Option<&'a [u8]>,
 }
 
@@ -67,8 +77,7 @@ impl<'a> SchemaRecord<'a> {
             (*root_page_id)
                 .try_into()
                 .context("root_page_id not fit u32")?,
-        )
-        .ok_or_else(|| anyhow::anyhow!("root_page_id is zero"))?;
+        );
 
         let sql: Option<&[u8]> = match columns.get(4) {
             None => None,
@@ -85,8 +94,37 @@ impl<'a> SchemaRecord<'a> {
     }
 }
 
+/// An entry of sqlite_schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaEntry {
+    /// "table", "index", "view" or "trigger".
+    pub type_: String,
+    pub name: String,
+    /// The table which the entry belongs to. The same as `name` for tables.
+    pub table_name: String,
+    pub root_page: u32,
+    /// [None] for automatically created indexes.
+    pub sql: Option<String>,
+}
+
+impl SchemaEntry {
+    fn new(record: &SchemaRecord) -> Self {
+        Self {
+            type_: String::from_utf8_lossy(record.type_).into_owned(),
+            name: String::from_utf8_lossy(record.name).into_owned(),
+            table_name: String::from_utf8_lossy(record.table_name).into_owned(),
+            root_page: record.root_page_id.map_or(0, |page_id| page_id.get()),
+            sql: record
+                .sql
+                .map(|sql| String::from_utf8_lossy(sql).into_owned()),
+        }
+    }
+}
+
 pub struct Schema {
     schema_table: Table,
+    /// All entries in the order of sqlite_schema.
+    entries: Vec<SchemaEntry>,
     // TODO: Use the reference of table name in the value as the key.
     tables: HashMap<Vec<u8>, Table>,
     indexes: HashMap<Vec<u8>, Rc<Index>>,
@@ -99,52 +137,80 @@ impl Schema {
             columns: vec![
                 Column {
                     name: b"type".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"name".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"tbl_name".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"rootpage".to_vec(),
+                    declared_type: b"INT".to_vec(),
                     type_affinity: TypeAffinity::Integer,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"sql".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
             ],
             indexes: None,
+            autoindexes: Vec::new(),
+            primary_key: Vec::new(),
+            primary_key_autoindex: None,
+            without_rowid: false,
+            autoincrement: false,
+            strict: false,
+            checks: Vec::new(),
+            foreign_keys: Vec::new(),
         }
     }
 
-    pub fn generate(stmt: SelectStatement, schema_table: Table) -> anyhow::Result<Schema> {
-        let stmt = stmt;
+    pub fn generate(
+        conn: &Connection,
+        stmt: SelectStatement,
+        schema_table: Table,
+    ) -> anyhow::Result<Schema> {
         let mut rows = stmt
-            .query()
+            .query(conn)
             .map_err(|e| anyhow::anyhow!("query: {:?}", e))?;
         let mut tables = HashMap::new();
         let mut indexes = HashMap::new();
+        let mut entries = Vec::new();
         while let Some(row) = rows
             .next_row()
             .map_err(|e| anyhow::anyhow!("next row: {:?}", e))?
         {
             let columns = row.parse().map_err(|e| anyhow::anyhow!("parse: {:?}", e))?;
             let schema = SchemaRecord::parse(&columns)?;
+            entries.push(SchemaEntry::new(&schema));
             match schema.type_ {
                 b"table" => {
                     if schema.name != schema.table_name {
@@ -158,7 +224,7 @@ impl Schema {
                         schema
                             .sql
                             .ok_or(anyhow::anyhow!("no sql for table schema"))?,
-                        schema.root_page_id,
+                        schema.root_page_id.context("root_page_id is zero")?,
                     )
                     .context("parse create table sql")?;
                     if table_name != schema.name {
@@ -178,15 +244,15 @@ impl Schema {
                     let table = tables
                         .get_mut(&table_name)
                         .context("index table not found")?;
+                    let root_page_id = schema.root_page_id.context("root_page_id is zero")?;
                     // TODO: validate the schema.table is equal to table.name.
-                    if let Some(sql) = schema.sql {
-                        let (mut index_name, parsed_table_name, mut index) =
-                            Index::parse(sql, schema.root_page_id, table)?;
-                        if index_name != schema.name {
+                    let mut index = if let Some(sql) = schema.sql {
+                        let (parsed_table_name, index) = Index::parse(sql, root_page_id, table)?;
+                        if index.name != schema.name {
                             bail!(
                                 "index name does not match: index_name={:?}, parsed_index_name={:?}",
                                 schema.name,
-                                index_name
+                                index.name
                             );
                         } else if !table_name.as_slice().iter().eq(parsed_table_name
                             .dequote_iter()
@@ -198,16 +264,17 @@ impl Schema {
                                 parsed_table_name
                             );
                         }
-                        index.next = table.indexes.clone();
-                        let index = Rc::new(index);
-                        table.indexes = Some(index.clone());
-
-                        upper_to_lower(&mut index_name);
-                        indexes.insert(index_name, index);
+                        index
                     } else {
-                        // TODO: support autoindex
-                        eprintln!("no sql for index: {:?}", schema.name);
-                    }
+                        Index::parse_autoindex(schema.name, root_page_id, table)?
+                    };
+                    index.next = table.indexes.clone();
+                    let index = Rc::new(index);
+                    table.indexes = Some(index.clone());
+
+                    let mut index_name = schema.name.to_vec();
+                    upper_to_lower(&mut index_name);
+                    indexes.insert(index_name, index);
                 }
                 b"view" => {
                     // TODO: support view
@@ -220,11 +287,27 @@ impl Schema {
         }
         Ok(Self {
             schema_table,
+            entries,
             tables,
             indexes,
         })
     }
 
+    pub fn entries(&self) -> &[SchemaEntry] {
+        &self.entries
+    }
+
+    /// The name of the table or the index whose root page is the page.
+    pub fn get_name_by_root_page(&self, page_id: PageId) -> Option<&str> {
+        if page_id == PAGE_ID_1 {
+            return Some("sqlite_schema");
+        }
+        self.entries
+            .iter()
+            .find(|entry| entry.root_page == page_id.get())
+            .map(|entry| entry.name.as_str())
+    }
+
     pub fn get_table(&self, table: &[u8]) -> Option<&Table> {
         // TODO: use the reference of given table name.
         let mut key = table.to_vec();
@@ -236,28 +319,92 @@ impl Schema {
         }
     }
 
-    #[allow(unused)]
+    /// The foreign keys referring to the table and their child tables.
+    pub fn get_child_foreign_keys(&self, table: &[u8]) -> Vec<(&Table, &ForeignKey)> {
+        let table = CaseInsensitiveBytes::from(table);
+        self.tables
+            .values()
+            .flat_map(|child| {
+                child
+                    .foreign_keys
+                    .iter()
+                    .filter(|foreign_key| {
+                        CaseInsensitiveBytes::from(&foreign_key.parent_table) == table
+                    })
+                    .map(move |foreign_key| (child, foreign_key))
+            })
+            .collect()
+    }
+
     pub fn get_index(&self, index: &[u8]) -> Option<&Rc<Index>> {
         // TODO: use the reference of given index name.
         let mut key = index.to_vec();
         upper_to_lower(&mut key);
         self.indexes.get(&key)
     }
+
+    /// Find the index and the table which the index belongs to.
+    pub fn get_index_with_table(&self, index: &[u8]) -> Option<(&Table, &Index)> {
+        let index = self.get_index(index)?;
+        self.tables.values().find_map(|table| {
+            let mut next = table.indexes.as_ref();
+            while let Some(table_index) = next {
+                if Rc::ptr_eq(table_index, index) {
+                    return Some((table, index.as_ref()));
+                }
+                next = table_index.next.as_ref();
+            }
+            None
+        })
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Index {
+    /// The dequoted name of the index.
+    pub name: Vec<u8>,
     pub root_page_id: PageId,
-    pub columns: Vec<ColumnNumber>,
+    /// The key columns and their collations. The column of an expression key
+    /// is [ColumnNumber::RowId] and the expression is in [Self::expressions].
+    pub columns: Vec<(ColumnNumber, Collation)>,
+    /// The expression of each key if it is not a column.
+    pub expressions: Vec<Option<IndexExpression>>,
+    /// Whether each key column is sorted in descending order.
+    pub descending: Vec<bool>,
+    /// Whether the keys must be unique. NULLs are distinct from each other.
+    pub unique: bool,
+    /// The WHERE clause of a partial index. Only the rows satisfying it are
+    /// in the index.
+    pub predicate: Option<IndexExpression>,
+    /// An expression of the index uses a function which is not supported. The
+    /// index is not used by queries and its table can not be written.
+    pub unsupported: bool,
     pub next: Option<Rc<Index>>,
 }
 
+/// An expression in CREATE INDEX: a key expression or the WHERE clause of a
+/// partial index.
+#[derive(Debug)]
+pub struct IndexExpression {
+    /// The raw text of the expression.
+    pub sql: Vec<u8>,
+    pub expr: Expression,
+}
+
+impl PartialEq for IndexExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.sql == other.sql
+    }
+}
+
+impl Eq for IndexExpression {}
+
 impl Index {
     fn parse<'a>(
         sql: &'a [u8],
         root_page_id: PageId,
         table: &Table,
-    ) -> anyhow::Result<(Vec<u8>, MaybeQuotedBytes<'a>, Self)> {
+    ) -> anyhow::Result<(MaybeQuotedBytes<'a>, Self)> {
         let mut parser = Parser::new(sql);
         let create_index = parse_create_index(&mut parser)
             .map_err(|e| anyhow::anyhow!("parse create index sql: {:?}", e))?;
@@ -267,37 +414,167 @@ impl Index {
                 sql
             );
         }
+        // An index which SQLite accepted must not fail the schema loading even
+        // if its expressions are not supported.
+        let index = Self::build(&create_index, root_page_id, table, true)?;
+        Ok((create_index.table_name, index))
+    }
+
+    /// Build the index from the parsed CREATE INDEX statement.
+    pub fn new(
+        create_index: &CreateIndex,
+        root_page_id: PageId,
+        table: &Table,
+    ) -> anyhow::Result<Self> {
+        Self::build(create_index, root_page_id, table, false)
+    }
+
+    /// Build the index. If `allow_unsupported` is true, an expression which
+    /// can not be evaluated marks the index as [Self::unsupported] instead of
+    /// failing.
+    fn build(
+        create_index: &CreateIndex,
+        root_page_id: PageId,
+        table: &Table,
+        allow_unsupported: bool,
+    ) -> anyhow::Result<Self> {
+        let table_name = create_index.table_name.dequote();
+        let mut unsupported = false;
+        // Functions in the schema must be built-in ones.
+        let mut parse_expression = |sql: &[u8]| -> anyhow::Result<IndexExpression> {
+            let mut parser = Parser::new(sql);
+            let expr = parse_expr(&mut parser)
+                .map_err(|e| anyhow::anyhow!("parse index expression: {:?}", e))?;
+            // The collation of an unsupported expression is only used to read
+            // the index b-tree.
+            let collation = match &expr {
+                Expr::Collate { collation_name, .. } => calc_collation(collation_name),
+                _ => None,
+            };
+            let expr = match Expression::from(
+                expr,
+                &[(table_name.as_slice(), table)],
+                &UserFunctions::default(),
+            ) {
+                Ok(expr) => expr,
+                Err(crate::expression::Error::FunctionNotFound) if allow_unsupported => {
+                    unsupported = true;
+                    match collation {
+                        Some(collation) => Expression::Collate {
+                            expr: Box::new(Expression::Null),
+                            collation,
+                        },
+                        None => Expression::Null,
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            };
+            Ok(IndexExpression {
+                sql: sql.to_vec(),
+                expr,
+            })
+        };
         let mut columns = Vec::with_capacity(create_index.columns.len());
+        let mut expressions = Vec::with_capacity(create_index.columns.len());
         for column in &create_index.columns {
+            if let Some(sql) = column.expr {
+                let expression = parse_expression(sql).context("invalid index expression")?;
+                columns.push((ColumnNumber::RowId, expression.expr.collation().clone()));
+                expressions.push(Some(expression));
+                continue;
+            }
             // TODO: use the reference of given column name.
             let column_name = column.name.dequote();
-            let Some((column_number, _, _)) = table.get_column(&column_name) else {
+            let Some((column_number, _, collation)) = table.get_column(&column_name) else {
                 bail!(
                     "column {:?} in create index sql is not found in table {:?}",
                     column.name,
                     table
                 );
             };
-            columns.push(column_number);
+            let collation = match &column.collation {
+                Some(collation_name) => calc_collation(collation_name)
+                    .ok_or_else(|| anyhow::anyhow!("collation is not found"))?,
+                None => collation,
+            };
+            columns.push((column_number, collation));
+            expressions.push(None);
         }
-        Ok((
-            create_index.index_name.dequote(),
-            create_index.table_name,
-            Self {
-                root_page_id,
-                columns,
-                next: None,
-            },
-        ))
+        let predicate = create_index
+            .where_clause
+            .map(|sql| parse_expression(sql).context("invalid partial index where clause"))
+            .transpose()?;
+        Ok(Self {
+            name: create_index.index_name.dequote(),
+            root_page_id,
+            columns,
+            expressions,
+            descending: create_index.columns.iter().map(|c| c.desc).collect(),
+            unique: create_index.unique,
+            predicate,
+            unsupported,
+            next: None,
+        })
+    }
+
+    /// Build the index which SQLite automatically creates for UNIQUE and
+    /// PRIMARY KEY constraints.
+    ///
+    /// The name is "sqlite_autoindex_<table>_<N>" where N is the 1-based order
+    /// of the constraints in the table.
+    fn parse_autoindex(name: &[u8], root_page_id: PageId, table: &Table) -> anyhow::Result<Self> {
+        let n = name
+            .strip_prefix(b"sqlite_autoindex_")
+            .and_then(|name| name.rsplit(|&c| c == b'_').next())
+            .and_then(|n| std::str::from_utf8(n).ok())
+            .and_then(|n| n.parse::<usize>().ok())
+            .with_context(|| format!("invalid autoindex name: {:?}", name))?;
+        let key = n
+            .checked_sub(1)
+            .and_then(|i| table.autoindexes.get(i))
+            .with_context(|| format!("no constraint for autoindex: {:?}", name))?;
+        Ok(Self {
+            name: name.to_vec(),
+            root_page_id,
+            columns: key.columns.clone(),
+            expressions: key.columns.iter().map(|_| None).collect(),
+            descending: key.descending.clone(),
+            unique: true,
+            predicate: None,
+            unsupported: false,
+            next: None,
+        })
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Column {
     pub name: Vec<u8>,
+    /// The declared type shown by PRAGMA table_info.
+    pub declared_type: Vec<u8>,
     pub type_affinity: TypeAffinity,
     pub primary_key: bool,
+    pub not_null: bool,
     pub collation: Collation,
+    /// The raw text of the DEFAULT expression.
+    pub default: Option<Vec<u8>>,
+}
+
+impl Column {
+    /// Parse the DEFAULT expression of the column.
+    ///
+    /// Returns [None] if the column has no DEFAULT clause.
+    pub fn default_expression(&self) -> anyhow::Result<Option<Expression>> {
+        let Some(default) = &self.default else {
+            return Ok(None);
+        };
+        let mut parser = Parser::new(default);
+        let expr =
+            parse_expr(&mut parser).map_err(|e| anyhow::anyhow!("parse default value: {:?}", e))?;
+        let expr = Expression::from(expr, &[], &UserFunctions::default())
+            .context("default value is not constant")?;
+        Ok(Some(expr))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -338,6 +615,63 @@ pub fn calc_type_affinity(type_name: &[MaybeQuotedBytes]) -> TypeAffinity {
     affinity
 }
 
+/// Convert the declared type of a column in a STRICT table to the datatype
+/// which the stored values must have.
+///
+/// Returns [None] for ANY and the types not allowed in STRICT tables.
+fn calc_datatype(declared_type: &[u8]) -> Option<TypeAffinity> {
+    match declared_type {
+        b"INT" | b"INTEGER" => Some(TypeAffinity::Integer),
+        b"REAL" => Some(TypeAffinity::Real),
+        b"TEXT" => Some(TypeAffinity::Text),
+        b"BLOB" => Some(TypeAffinity::Blob),
+        _ => None,
+    }
+}
+
+/// Normalize the raw type name text in the same way as SQLite.
+///
+/// The standard type names are converted to upper case. If the type name
+/// starts with a quoted identifier, only the dequoted identifier remains.
+pub fn calc_declared_type(type_text: &[u8]) -> Vec<u8> {
+    let is_quote = |c: &u8| matches!(c, b'\'' | b'"' | b'`' | b'[');
+    let mut standard_type = type_text;
+    if type_text.len() >= 2
+        && is_quote(&type_text[0])
+        && !type_text[1..type_text.len() - 1].iter().any(is_quote)
+    {
+        standard_type = &type_text[1..type_text.len() - 1];
+    }
+    let case_insensitive_type = CaseInsensitiveBytes::from(standard_type);
+    for standard_type in [
+        b"any".as_slice(),
+        b"blob",
+        b"int",
+        b"integer",
+        b"real",
+        b"text",
+    ] {
+        if case_insensitive_type.equal_to_lower_bytes(standard_type) {
+            return standard_type.to_ascii_uppercase();
+        }
+    }
+
+    let Some(quote) = type_text.first().filter(|c| is_quote(c)) else {
+        return type_text.to_vec();
+    };
+    let quote = if *quote == b'[' { b']' } else { *quote };
+    let mut result = Vec::with_capacity(type_text.len());
+    let mut iter = type_text[1..].iter().peekable();
+    while let Some(&c) = iter.next() {
+        // A doubled quote is an escaped quote.
+        if c == quote && iter.next_if_eq(&&quote).is_none() {
+            break;
+        }
+        result.push(c);
+    }
+    result
+}
+
 /// Parse the collation name to [Collation].
 ///
 /// This now supports BINARY, NOCASE, and RTRIM only.
@@ -363,6 +697,74 @@ pub struct Table {
     pub root_page_id: PageId,
     pub columns: Vec<Column>,
     pub indexes: Option<Rc<Index>>,
+    /// Keys of the autoindexes for UNIQUE and PRIMARY KEY constraints in the
+    /// order of the constraints.
+    pub autoindexes: Vec<ConstraintKey>,
+    /// Indexes of the PRIMARY KEY columns in the order of the key.
+    pub primary_key: Vec<usize>,
+    /// The position of the PRIMARY KEY in [Self::autoindexes]. [None] if there
+    /// is no PRIMARY KEY or it is the alias of the rowid.
+    pub primary_key_autoindex: Option<usize>,
+    /// The table is a b-tree keyed by the primary key without rowid.
+    pub without_rowid: bool,
+    /// Rowids are never reused. The largest rowid ever used is recorded in
+    /// sqlite_sequence.
+    pub autoincrement: bool,
+    /// The values must conform to the declared types of the columns.
+    ///
+    /// https://www.sqlite.org/stricttables.html
+    pub strict: bool,
+    /// The raw texts of the CHECK constraints.
+    pub checks: Vec<Vec<u8>>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// A key column of a PRIMARY KEY or UNIQUE constraint while parsing: the index
+/// of the column, the collation if it is not the one of the column and whether
+/// it is descending.
+type KeyColumn = (usize, Option<Collation>, bool);
+
+/// The key of a PRIMARY KEY or UNIQUE constraint.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConstraintKey {
+    /// The key columns and their collations.
+    pub columns: Vec<(ColumnNumber, Collation)>,
+    /// Whether each key column is sorted in descending order.
+    pub descending: Vec<bool>,
+}
+
+/// A FOREIGN KEY constraint of a child table.
+///
+/// https://www.sqlite.org/foreignkeys.html
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ForeignKey {
+    /// Indexes of the child key columns.
+    pub columns: Vec<usize>,
+    /// The dequoted name of the parent table.
+    pub parent_table: Vec<u8>,
+    /// The dequoted names of the parent key columns. Empty if the parent key
+    /// is the primary key of the parent table.
+    pub parent_columns: Vec<Vec<u8>>,
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+impl ForeignKey {
+    fn new(columns: Vec<usize>, clause: &ForeignKeyClause) -> anyhow::Result<Self> {
+        if !clause.columns.is_empty() && clause.columns.len() != columns.len() {
+            bail!(
+                "number of columns in foreign key does not match the number of columns in the referenced table: {:?}",
+                clause.foreign_table
+            );
+        }
+        Ok(Self {
+            columns,
+            parent_table: clause.foreign_table.dequote(),
+            parent_columns: clause.columns.iter().map(|name| name.dequote()).collect(),
+            on_delete: clause.on_delete,
+            on_update: clause.on_update,
+        })
+    }
 }
 
 impl Table {
@@ -376,9 +778,22 @@ impl Table {
                 sql
             );
         }
+        Self::new(create_table, root_page_id)
+    }
+
+    /// Build the table from the parsed CREATE TABLE statement.
+    ///
+    /// Returns the dequoted table name and the table.
+    pub fn new(create_table: CreateTable, root_page_id: PageId) -> anyhow::Result<(Vec<u8>, Self)> {
         let table_name = create_table.table_name.dequote();
         let mut columns: Vec<Column> = Vec::with_capacity(create_table.columns.len());
-        let mut has_primary_key = false;
+        // PRIMARY KEY and UNIQUE constraints in the order of declaration.
+        let mut key_constraints: Vec<(bool, Vec<KeyColumn>)> = Vec::new();
+        // INTEGER PRIMARY KEY DESC on a column is not the alias of the rowid.
+        let mut column_primary_key_desc = false;
+        let mut autoincrement = false;
+        let mut checks = Vec::new();
+        let mut foreign_keys = Vec::new();
         for column_def in create_table.columns {
             let column_name = column_def.name.dequote();
             let case_insensitive_name = CaseInsensitiveBytes::from(&column_name);
@@ -389,39 +804,246 @@ impl Table {
                 }
             }
 
-            let primary_key = column_def
-                .constraints
-                .contains(&ColumnConstraint::PrinaryKey);
-            if primary_key {
-                if has_primary_key {
-                    bail!("multiple primary key");
+            let mut collation = DEFAULT_COLLATION.clone();
+            let mut default = None;
+            for constraint in &column_def.constraints {
+                match constraint {
+                    ColumnConstraint::Collate(collation_name) => {
+                        collation = calc_collation(collation_name)
+                            .ok_or_else(|| anyhow::anyhow!("collation is not found"))?;
+                    }
+                    ColumnConstraint::Default(text) => default = Some(text.to_vec()),
+                    ColumnConstraint::PrinaryKey {
+                        desc,
+                        autoincrement: column_autoincrement,
+                    } => {
+                        key_constraints.push((true, vec![(columns.len(), None, *desc)]));
+                        column_primary_key_desc = *desc;
+                        autoincrement |= column_autoincrement;
+                    }
+                    ColumnConstraint::Unique => {
+                        key_constraints.push((false, vec![(columns.len(), None, false)]));
+                    }
+                    ColumnConstraint::NotNull => {}
+                    ColumnConstraint::Check(text) => checks.push(text.to_vec()),
+                    ColumnConstraint::References(clause) => {
+                        foreign_keys.push(ForeignKey::new(vec![columns.len()], clause)?);
+                    }
                 }
-                has_primary_key = true;
             }
 
-            let mut collation = DEFAULT_COLLATION.clone();
-            for constraint in &column_def.constraints {
-                if let ColumnConstraint::Collate(collation_name) = constraint {
-                    collation = calc_collation(collation_name)
-                        .ok_or_else(|| anyhow::anyhow!("collation is not found"))?;
+            let declared_type = calc_declared_type(column_def.declared_type);
+            let mut type_affinity = calc_type_affinity(&column_def.type_name);
+            if create_table.strict {
+                match calc_datatype(&declared_type) {
+                    Some(_) => {}
+                    // ANY columns store values as is.
+                    None if declared_type == b"ANY" => type_affinity = TypeAffinity::Blob,
+                    None if declared_type.is_empty() => {
+                        bail!("missing datatype for {:?}", column_def.name);
+                    }
+                    None => bail!("unknown datatype for {:?}", column_def.name),
                 }
             }
 
             columns.push(Column {
                 name: column_name,
-                type_affinity: calc_type_affinity(&column_def.type_name),
-                primary_key,
+                declared_type,
+                type_affinity,
+                primary_key: false,
+                not_null: column_def.constraints.contains(&ColumnConstraint::NotNull),
                 collation,
+                default,
             });
         }
-        Ok((
-            table_name,
-            Table {
-                root_page_id,
-                columns,
-                indexes: None,
-            },
-        ))
+        let find_column = |name: &MaybeQuotedBytes| {
+            let name = name.dequote();
+            let name = CaseInsensitiveBytes::from(&name);
+            columns
+                .iter()
+                .position(|column| CaseInsensitiveBytes::from(&column.name) == name)
+        };
+        for constraint in &create_table.constraints {
+            let (primary_key, key) = match constraint {
+                TableConstraint::PrimaryKey(key) => (true, key),
+                TableConstraint::Unique(key) => (false, key),
+                TableConstraint::Check(text) => {
+                    checks.push(text.to_vec());
+                    continue;
+                }
+                TableConstraint::ForeignKey { columns, clause } => {
+                    let Some(key_columns) = columns.iter().map(find_column).collect() else {
+                        bail!("unknown column in foreign key: {:?}", columns);
+                    };
+                    foreign_keys.push(ForeignKey::new(key_columns, clause)?);
+                    continue;
+                }
+            };
+            let mut key_columns: Vec<KeyColumn> = Vec::with_capacity(key.len());
+            for column in key {
+                let Some(i) = find_column(&column.name) else {
+                    bail!("unknown column in table constraint: {:?}", constraint);
+                };
+                let collation = column
+                    .collation
+                    .as_ref()
+                    .map(|collation_name| {
+                        calc_collation(collation_name)
+                            .ok_or_else(|| anyhow::anyhow!("collation is not found"))
+                    })
+                    .transpose()?;
+                // Duplicated columns are ignored.
+                if !key_columns.iter().any(|(j, _, _)| *j == i) {
+                    key_columns.push((i, collation, column.desc));
+                }
+            }
+            key_constraints.push((primary_key, key_columns));
+        }
+
+        let mut primary_keys = key_constraints.iter().filter(|(pk, _)| *pk);
+        let primary_key: Vec<usize> = primary_keys
+            .next()
+            .map(|(_, key_columns)| key_columns.iter().map(|(i, _, _)| *i).collect())
+            .unwrap_or_default();
+        if primary_keys.next().is_some() {
+            bail!("multiple primary key");
+        } else if create_table.without_rowid && primary_key.is_empty() {
+            bail!("no primary key in without rowid table");
+        }
+        for i in &primary_key {
+            columns[*i].primary_key = true;
+        }
+        let is_rowid_alias = match primary_key.as_slice() {
+            [i] => {
+                !create_table.without_rowid
+                    && columns[*i].type_affinity == TypeAffinity::Integer
+                    && !column_primary_key_desc
+            }
+            _ => false,
+        };
+
+        // The keys of the autoindexes with the indexes of the columns.
+        let mut autoindex_columns: Vec<Vec<(usize, Collation)>> = Vec::new();
+        let mut autoindex_descending = Vec::new();
+        let mut primary_key_autoindex = None;
+        for (is_primary_key, key_columns) in key_constraints {
+            // The rowid alias does not need an index.
+            if is_primary_key && is_rowid_alias {
+                continue;
+            }
+            let (key_columns, descending): (Vec<_>, Vec<_>) = key_columns
+                .into_iter()
+                .map(|(i, collation, desc)| {
+                    (
+                        (i, collation.unwrap_or_else(|| columns[i].collation.clone())),
+                        desc,
+                    )
+                })
+                .unzip();
+            // SQLite does not create duplicated autoindexes which have the
+            // same columns and collations. The primary key of WITHOUT ROWID
+            // tables takes a number but the table itself is the index.
+            let n = match autoindex_columns.iter().position(|key| *key == key_columns) {
+                Some(n) => n,
+                None => {
+                    autoindex_columns.push(key_columns);
+                    autoindex_descending.push(descending);
+                    autoindex_columns.len() - 1
+                }
+            };
+            if is_primary_key {
+                primary_key_autoindex = Some(n);
+            }
+        }
+
+        let mut table = Table {
+            root_page_id,
+            columns,
+            indexes: None,
+            autoindexes: Vec::new(),
+            primary_key,
+            primary_key_autoindex,
+            without_rowid: create_table.without_rowid,
+            autoincrement,
+            strict: create_table.strict,
+            checks,
+            foreign_keys,
+        };
+        table
+            .check_expressions(&table_name)
+            .context("invalid check constraint")?;
+        if autoincrement && table.rowid_alias().is_none() {
+            bail!("AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY");
+        }
+        table.autoindexes = autoindex_columns
+            .into_iter()
+            .zip(autoindex_descending)
+            .map(|(key_columns, descending)| ConstraintKey {
+                columns: key_columns
+                    .into_iter()
+                    .map(|(i, collation)| (table.column_number(i), collation))
+                    .collect(),
+                descending,
+            })
+            .collect();
+        Ok((table_name, table))
+    }
+
+    /// The index of the column which is the alias of the rowid.
+    ///
+    /// The single INTEGER PRIMARY KEY column of a rowid table is the alias
+    /// unless it is declared as `PRIMARY KEY DESC` on the column, in which
+    /// case the key has an autoindex.
+    ///
+    /// https://www.sqlite.org/lang_createtable.html#rowid
+    pub fn rowid_alias(&self) -> Option<usize> {
+        match self.primary_key.as_slice() {
+            [i] if !self.without_rowid && self.primary_key_autoindex.is_none() => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Convert the index of the column to [ColumnNumber].
+    ///
+    /// The records of WITHOUT ROWID tables start with the primary key columns
+    /// followed by the other columns.
+    pub fn column_number(&self, column_idx: usize) -> ColumnNumber {
+        if self.rowid_alias() == Some(column_idx) {
+            ColumnNumber::RowId
+        } else if self.without_rowid {
+            let position = self
+                .primary_key
+                .iter()
+                .position(|i| *i == column_idx)
+                .unwrap_or_else(|| {
+                    self.primary_key.len()
+                        + (0..column_idx)
+                            .filter(|i| !self.primary_key.contains(i))
+                            .count()
+                });
+            ColumnNumber::Column(position)
+        } else {
+            ColumnNumber::Column(column_idx)
+        }
+    }
+
+    /// Convert [ColumnNumber] to the index of the column.
+    ///
+    /// Returns [None] for the rowid which has no alias column.
+    pub fn column_index(&self, column_number: &ColumnNumber) -> Option<usize> {
+        (0..self.columns.len()).find(|i| self.column_number(*i) == *column_number)
+    }
+
+    /// Whether the table has an index which can not be updated.
+    pub fn has_unsupported_index(&self) -> bool {
+        let mut next = self.indexes.as_ref();
+        while let Some(index) = next {
+            if index.unsupported {
+                return true;
+            }
+            next = index.next.as_ref();
+        }
+        false
     }
 
     pub fn get_column(&self, name: &[u8]) -> Option<(ColumnNumber, TypeAffinity, Collation)> {
@@ -432,18 +1054,12 @@ impl Table {
             .enumerate()
             .find(|(_, c)| CaseInsensitiveBytes::from(&c.name) == column)
         {
-            let column_number =
-                if column.primary_key && column.type_affinity == TypeAffinity::Integer {
-                    ColumnNumber::RowId
-                } else {
-                    ColumnNumber::Column(i)
-                };
             Some((
-                column_number,
+                self.column_number(i),
                 column.type_affinity,
                 column.collation.clone(),
             ))
-        } else if column.equal_to_lower_bytes(b"rowid".as_slice()) {
+        } else if !self.without_rowid && column.equal_to_lower_bytes(b"rowid".as_slice()) {
             Some((
                 ColumnNumber::RowId,
                 TypeAffinity::Integer,
@@ -454,23 +1070,91 @@ impl Table {
         }
     }
 
+    /// Parse the CHECK constraints of the table.
+    ///
+    /// `table_name` is the dequoted name of this table.
+    pub fn check_expressions(&self, table_name: &[u8]) -> anyhow::Result<Vec<Expression>> {
+        self.checks
+            .iter()
+            .map(|check| {
+                let mut parser = Parser::new(check);
+                let expr = parse_expr(&mut parser)
+                    .map_err(|e| anyhow::anyhow!("parse check expression: {:?}", e))?;
+                // Functions in the schema must be built-in ones.
+                Ok(Expression::from(
+                    expr,
+                    &[(table_name, self)],
+                    &UserFunctions::default(),
+                )?)
+            })
+            .collect()
+    }
+
+    /// The datatypes of the columns of a STRICT table. [None] for the ANY
+    /// columns and the rowid alias column.
+    ///
+    /// Returns an empty list if the table is not STRICT.
+    pub fn datatypes(&self) -> Vec<Option<TypeAffinity>> {
+        if !self.strict {
+            return Vec::new();
+        }
+        let rowid_alias = self.rowid_alias();
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                if rowid_alias == Some(i) {
+                    None
+                } else {
+                    calc_datatype(&column.declared_type)
+                }
+            })
+            .collect()
+    }
+
+    /// Indexes of the columns with NOT NULL constraint.
+    ///
+    /// The rowid alias column is excluded because NULL is replaced with a new
+    /// rowid.
+    pub fn not_null_columns(&self) -> Vec<usize> {
+        let rowid_alias = self.rowid_alias();
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(i, column)| column.not_null && rowid_alias != Some(*i))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The name of the column for the result column name.
+    pub fn get_column_name(&self, column_number: &ColumnNumber) -> &[u8] {
+        match column_number {
+            ColumnNumber::Column(_) if self.without_rowid => self
+                .columns
+                .iter()
+                .enumerate()
+                .find(|(i, _)| self.column_number(*i) == *column_number)
+                .map(|(_, column)| column.name.as_slice())
+                .expect("column number must be valid"),
+            ColumnNumber::Column(i) => &self.columns[*i].name,
+            ColumnNumber::RowId => self
+                .rowid_alias()
+                .map(|i| self.columns[i].name.as_slice())
+                .unwrap_or(b"rowid"),
+        }
+    }
+
     pub fn get_all_columns(
         &self,
     ) -> impl Iterator<Item = (ColumnNumber, TypeAffinity, Collation)> + '_ {
         self.columns.iter().enumerate().map(|(i, column)| {
-            if column.primary_key && column.type_affinity == TypeAffinity::Integer {
-                (
-                    ColumnNumber::RowId,
-                    TypeAffinity::Integer,
-                    column.collation.clone(),
-                )
+            let column_number = self.column_number(i);
+            let type_affinity = if column_number == ColumnNumber::RowId {
+                TypeAffinity::Integer
             } else {
-                (
-                    ColumnNumber::Column(i),
-                    column.type_affinity,
-                    column.collation.clone(),
-                )
-            }
+                column.type_affinity
+            };
+            (column_number, type_affinity, column.collation.clone())
         })
     }
 }
@@ -482,6 +1166,7 @@ mod tests {
     use super::*;
     use crate::query::QueryPlan;
     use crate::test_utils::*;
+    use crate::value::ConstantValue;
     use crate::Connection;
     use crate::Expression;
 
@@ -493,12 +1178,15 @@ mod tests {
             .map(Expression::Column)
             .collect::<Vec<_>>();
         Schema::generate(
+            &conn,
             SelectStatement::new(
-                &conn,
                 schema_table.root_page_id,
                 columns,
+                Rc::new([]),
                 Expression::one(),
                 QueryPlan::FullScan,
+                Vec::new(),
+                None,
             ),
             schema_table,
         )
@@ -596,42 +1284,68 @@ mod tests {
                 columns: vec![
                     Column {
                         name: b"col".to_vec(),
+                        declared_type: Vec::new(),
                         type_affinity: TypeAffinity::Blob,
                         primary_key: false,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                     Column {
                         name: b"col1".to_vec(),
+                        declared_type: b"INTEGER".to_vec(),
                         type_affinity: TypeAffinity::Integer,
                         primary_key: true,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                     Column {
                         name: b"col2".to_vec(),
+                        declared_type: b"TEXT".to_vec(),
                         type_affinity: TypeAffinity::Text,
                         primary_key: false,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                     Column {
                         name: b"co`l3".to_vec(),
+                        declared_type: b"BLOB".to_vec(),
                         type_affinity: TypeAffinity::Blob,
                         primary_key: false,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                     Column {
                         name: b"col4".to_vec(),
+                        declared_type: b"REAL".to_vec(),
                         type_affinity: TypeAffinity::Real,
                         primary_key: false,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                     Column {
                         name: b"col5".to_vec(),
+                        declared_type: b"other".to_vec(),
                         type_affinity: TypeAffinity::Numeric,
                         primary_key: false,
+                        not_null: false,
                         collation: Collation::Binary,
+                        default: None,
                     },
                 ],
                 indexes: None,
+                autoindexes: Vec::new(),
+                primary_key: vec![1],
+                primary_key_autoindex: None,
+                without_rowid: false,
+                autoincrement: false,
+                strict: false,
+                checks: Vec::new(),
+                foreign_keys: Vec::new(),
             }
         );
 
@@ -683,6 +1397,232 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn test_parse_table_not_null() {
+        let (_, table) = Table::parse(
+            b"create table example(id integer primary key not null, col1 not null, col2, col3 text NOT NULL collate nocase)",
+            PAGE_ID_1,
+        )
+        .unwrap();
+
+        assert!(table.columns[0].not_null);
+        assert!(table.columns[1].not_null);
+        assert!(!table.columns[2].not_null);
+        assert!(table.columns[3].not_null);
+        assert_eq!(table.columns[3].collation, Collation::NoCase);
+        // The rowid alias is excluded.
+        assert_eq!(table.not_null_columns(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_parse_table_default() {
+        let (_, table) = Table::parse(
+            b"create table example(id integer primary key default 1, col1 default -1, col2 default ('a' || 'b'), col3)",
+            PAGE_ID_1,
+        )
+        .unwrap();
+
+        assert_eq!(table.columns[0].default, Some(b"1".to_vec()));
+        assert_eq!(table.columns[1].default, Some(b"-1".to_vec()));
+        assert_eq!(table.columns[2].default, Some(b"'a' || 'b'".to_vec()));
+        assert_eq!(table.columns[3].default, None);
+        assert!(matches!(
+            table.columns[1].default_expression().unwrap(),
+            Some(Expression::Const(ConstantValue::Integer(-1)))
+        ));
+        assert!(table.columns[2].default_expression().unwrap().is_some());
+        assert!(table.columns[3].default_expression().unwrap().is_none());
+
+        let (_, table) = Table::parse(
+            b"create table example(col1, col2 default (col1))",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert!(table.columns[1].default_expression().is_err());
+    }
+
+    #[test]
+    fn test_parse_table_foreign_key() {
+        let (_, table) = Table::parse(
+            b"create table example(a references parent1, b references Parent2(x) on delete cascade, c, foreign key (c, A) references parent3 (y, z))",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert_eq!(
+            table.foreign_keys,
+            vec![
+                ForeignKey {
+                    columns: vec![0],
+                    parent_table: b"parent1".to_vec(),
+                    parent_columns: Vec::new(),
+                    on_delete: ForeignKeyAction::NoAction,
+                    on_update: ForeignKeyAction::NoAction,
+                },
+                ForeignKey {
+                    columns: vec![1],
+                    parent_table: b"Parent2".to_vec(),
+                    parent_columns: vec![b"x".to_vec()],
+                    on_delete: ForeignKeyAction::Cascade,
+                    on_update: ForeignKeyAction::NoAction,
+                },
+                ForeignKey {
+                    columns: vec![2, 0],
+                    parent_table: b"parent3".to_vec(),
+                    parent_columns: vec![b"y".to_vec(), b"z".to_vec()],
+                    on_delete: ForeignKeyAction::NoAction,
+                    on_update: ForeignKeyAction::NoAction,
+                },
+            ]
+        );
+
+        for sql in [
+            b"create table example(a references parent(x, y))".as_slice(),
+            b"create table example(a, foreign key (a) references parent(x, y))",
+            b"create table example(a, foreign key (b) references parent)",
+        ] {
+            assert!(Table::parse(sql, PAGE_ID_1).is_err(), "{:?}", sql);
+        }
+    }
+
+    fn autoindex_columns(table: &Table) -> Vec<Vec<ColumnNumber>> {
+        table
+            .autoindexes
+            .iter()
+            .map(|key| key.columns.iter().map(|(column, _)| *column).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_table_primary_key() {
+        let (_, table) = Table::parse(
+            b"create table example(a, b unique, c, primary key(a, c), unique(c, a), unique(b))",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert_eq!(table.primary_key, vec![0, 2]);
+        assert!(!table.without_rowid);
+        assert_eq!(table.rowid_alias(), None);
+        assert!(table.columns[0].primary_key);
+        assert!(!table.columns[1].primary_key);
+        assert_eq!(
+            autoindex_columns(&table),
+            vec![
+                vec![ColumnNumber::Column(1)],
+                vec![ColumnNumber::Column(0), ColumnNumber::Column(2)],
+                vec![ColumnNumber::Column(2), ColumnNumber::Column(0)],
+            ]
+        );
+
+        // Columns of WITHOUT ROWID tables are stored in the primary key order
+        // first.
+        let (_, table) = Table::parse(
+            b"create table example(a unique, b, c integer, d, primary key(c, b)) without rowid",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert!(table.without_rowid);
+        assert_eq!(table.rowid_alias(), None);
+        assert_eq!(table.column_number(0), ColumnNumber::Column(2));
+        assert_eq!(table.column_number(1), ColumnNumber::Column(1));
+        assert_eq!(table.column_number(2), ColumnNumber::Column(0));
+        assert_eq!(table.column_number(3), ColumnNumber::Column(3));
+        assert_eq!(
+            autoindex_columns(&table),
+            vec![
+                vec![ColumnNumber::Column(2)],
+                vec![ColumnNumber::Column(0), ColumnNumber::Column(1)],
+            ]
+        );
+
+        let (_, table) = Table::parse(
+            b"create table example(id integer primary key autoincrement, a)",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert!(table.autoincrement);
+        assert_eq!(table.rowid_alias(), Some(0));
+        assert!(Table::parse(
+            b"create table example(id text primary key autoincrement)",
+            PAGE_ID_1
+        )
+        .is_err());
+
+        // INTEGER PRIMARY KEY DESC on the column is not the alias of the rowid.
+        let (_, table) = Table::parse(
+            b"create table example(id integer primary key desc, a)",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert_eq!(table.rowid_alias(), None);
+        assert_eq!(table.primary_key_autoindex, Some(0));
+        assert_eq!(
+            table.autoindexes,
+            vec![ConstraintKey {
+                columns: vec![(ColumnNumber::Column(0), Collation::Binary)],
+                descending: vec![true],
+            }]
+        );
+        let (_, table) = Table::parse(
+            b"create table example(id integer, a, primary key(id desc))",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert_eq!(table.rowid_alias(), Some(0));
+        assert!(table.autoindexes.is_empty());
+        assert!(Table::parse(
+            b"create table example(id integer primary key desc autoincrement)",
+            PAGE_ID_1
+        )
+        .is_err());
+
+        // The collations and the orders of the keys.
+        let (_, table) = Table::parse(
+            b"create table example(a collate nocase, b, c, primary key(b desc, a), unique(a collate binary, c desc), unique(a, c)) without rowid",
+            PAGE_ID_1,
+        )
+        .unwrap();
+        assert_eq!(table.primary_key, vec![1, 0]);
+        assert_eq!(table.primary_key_autoindex, Some(0));
+        assert_eq!(
+            table.autoindexes,
+            vec![
+                ConstraintKey {
+                    columns: vec![
+                        (ColumnNumber::Column(0), Collation::Binary),
+                        (ColumnNumber::Column(1), Collation::NoCase)
+                    ],
+                    descending: vec![true, false],
+                },
+                ConstraintKey {
+                    columns: vec![
+                        (ColumnNumber::Column(1), Collation::Binary),
+                        (ColumnNumber::Column(2), Collation::Binary)
+                    ],
+                    descending: vec![false, true],
+                },
+                ConstraintKey {
+                    columns: vec![
+                        (ColumnNumber::Column(1), Collation::NoCase),
+                        (ColumnNumber::Column(2), Collation::Binary)
+                    ],
+                    descending: vec![false, false],
+                },
+            ]
+        );
+        assert!(Table::parse(
+            b"create table example(a, unique(a collate unknown))",
+            PAGE_ID_1
+        )
+        .is_err());
+
+        assert!(Table::parse(b"create table example(a) without rowid", PAGE_ID_1).is_err());
+        assert!(Table::parse(
+            b"create table example(a primary key, b, primary key(b))",
+            PAGE_ID_1
+        )
+        .is_err());
+    }
+
     #[test]
     fn get_table() {
         let file = create_sqlite_database(&[
@@ -700,11 +1640,22 @@ mod tests {
                 root_page_id: PageId::new(2).unwrap(),
                 columns: vec![Column {
                     name: b"col".to_vec(),
+                    declared_type: Vec::new(),
                     type_affinity: TypeAffinity::Blob,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 }],
                 indexes: None,
+                autoindexes: Vec::new(),
+                primary_key: Vec::new(),
+                primary_key_autoindex: None,
+                without_rowid: false,
+                autoincrement: false,
+                strict: false,
+                checks: Vec::new(),
+                foreign_keys: Vec::new(),
             }
         );
         assert_eq!(
@@ -712,15 +1663,21 @@ mod tests {
             vec![
                 Column {
                     name: b"col1".to_vec(),
+                    declared_type: b"null".to_vec(),
                     type_affinity: TypeAffinity::Numeric,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"col2".to_vec(),
+                    declared_type: b"INTEGER".to_vec(),
                     type_affinity: TypeAffinity::Integer,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 }
             ]
         );
@@ -729,27 +1686,39 @@ mod tests {
             vec![
                 Column {
                     name: b"COL1".to_vec(),
+                    declared_type: b"REAL".to_vec(),
                     type_affinity: TypeAffinity::Real,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"Col2".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: true,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"cOL3".to_vec(),
+                    declared_type: b"BLOB".to_vec(),
                     type_affinity: TypeAffinity::Blob,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"_".to_vec(),
+                    declared_type: Vec::new(),
                     type_affinity: TypeAffinity::Blob,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 }
             ]
         );
@@ -759,44 +1728,80 @@ mod tests {
             vec![
                 Column {
                     name: b"col".to_vec(),
+                    declared_type: Vec::new(),
                     type_affinity: TypeAffinity::Blob,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"col1".to_vec(),
+                    declared_type: b"INTEGER".to_vec(),
                     type_affinity: TypeAffinity::Integer,
                     primary_key: true,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"col2".to_vec(),
+                    declared_type: b"TEXT".to_vec(),
                     type_affinity: TypeAffinity::Text,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::NoCase,
+                    default: None,
                 },
                 Column {
                     name: b"co`l3".to_vec(),
+                    declared_type: b"BLOB".to_vec(),
                     type_affinity: TypeAffinity::Blob,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::RTrim,
+                    default: None,
                 },
                 Column {
                     name: b"col4".to_vec(),
+                    declared_type: b"REAL".to_vec(),
                     type_affinity: TypeAffinity::Real,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
                 Column {
                     name: b"col5".to_vec(),
+                    declared_type: b"other".to_vec(),
                     type_affinity: TypeAffinity::Numeric,
                     primary_key: false,
+                    not_null: false,
                     collation: Collation::Binary,
+                    default: None,
                 },
             ]
         );
     }
 
+    #[test]
+    fn test_calc_declared_type() {
+        assert_eq!(calc_declared_type(b""), b"");
+        assert_eq!(calc_declared_type(b"integer"), b"INTEGER");
+        assert_eq!(calc_declared_type(b"Int"), b"INT");
+        assert_eq!(calc_declared_type(b"\"text\""), b"TEXT");
+        assert_eq!(calc_declared_type(b"[Blob]"), b"BLOB");
+        assert_eq!(calc_declared_type(b"`any`"), b"ANY");
+        assert_eq!(calc_declared_type(b"null"), b"null");
+        assert_eq!(calc_declared_type(b"\"foo\""), b"foo");
+        assert_eq!(calc_declared_type(b"\"fo\"\"o\""), b"fo\"o");
+        assert_eq!(calc_declared_type(b"\"foo\"(10)"), b"foo");
+        assert_eq!(calc_declared_type(b"'a' b"), b"a");
+        assert_eq!(calc_declared_type(b"a \"b\""), b"a \"b\"");
+        assert_eq!(calc_declared_type(b"varchar ( 10 )"), b"varchar ( 10 )");
+        assert_eq!(calc_declared_type(b"long  real"), b"long  real");
+    }
+
     #[test]
     fn test_get_table_type_affinity() {
         let file = create_sqlite_database(&[
@@ -1022,13 +2027,28 @@ mod tests {
         let schema = generate_schema(file.path());
 
         let index1 = Rc::new(Index {
+            name: b"index1".to_vec(),
             root_page_id: PageId::new(3).unwrap(),
-            columns: vec![ColumnNumber::Column(0)],
+            columns: vec![(ColumnNumber::Column(0), Collation::Binary)],
+            expressions: vec![None],
+            descending: vec![false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: None,
         });
         let index2 = Rc::new(Index {
+            name: b"index2".to_vec(),
             root_page_id: PageId::new(4).unwrap(),
-            columns: vec![ColumnNumber::Column(0), ColumnNumber::Column(1)],
+            columns: vec![
+                (ColumnNumber::Column(0), Collation::Binary),
+                (ColumnNumber::Column(1), Collation::Binary),
+            ],
+            expressions: vec![None, None],
+            descending: vec![false, false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: Some(index1.clone()),
         });
         assert_eq!(schema.get_index(b"index1").unwrap(), &index1);
@@ -1045,13 +2065,28 @@ mod tests {
         let schema = generate_schema(file.path());
 
         let index1 = Rc::new(Index {
+            name: b"index1".to_vec(),
             root_page_id: PageId::new(3).unwrap(),
-            columns: vec![ColumnNumber::Column(0)],
+            columns: vec![(ColumnNumber::Column(0), Collation::Binary)],
+            expressions: vec![None],
+            descending: vec![false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: None,
         });
         let index2 = Rc::new(Index {
+            name: b"index2".to_vec(),
             root_page_id: PageId::new(4).unwrap(),
-            columns: vec![ColumnNumber::Column(0), ColumnNumber::Column(1)],
+            columns: vec![
+                (ColumnNumber::Column(0), Collation::Binary),
+                (ColumnNumber::Column(1), Collation::Binary),
+            ],
+            expressions: vec![None, None],
+            descending: vec![false, false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: Some(index1.clone()),
         });
         assert_eq!(schema.get_index(b"index1").unwrap(), &index1);
@@ -1070,18 +2105,66 @@ mod tests {
         let table = schema.get_table(b"example").unwrap();
 
         let index1 = Rc::new(Index {
+            name: b"index1".to_vec(),
             root_page_id: PageId::new(3).unwrap(),
-            columns: vec![ColumnNumber::Column(0)],
+            columns: vec![(ColumnNumber::Column(0), Collation::Binary)],
+            expressions: vec![None],
+            descending: vec![false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: None,
         });
         let index2 = Rc::new(Index {
+            name: b"index2".to_vec(),
             root_page_id: PageId::new(4).unwrap(),
-            columns: vec![ColumnNumber::Column(0), ColumnNumber::Column(1)],
+            columns: vec![
+                (ColumnNumber::Column(0), Collation::Binary),
+                (ColumnNumber::Column(1), Collation::Binary),
+            ],
+            expressions: vec![None, None],
+            descending: vec![false, false],
+            unique: false,
+            predicate: None,
+            unsupported: false,
             next: Some(index1.clone()),
         });
         assert_eq!(table.indexes, Some(index2));
     }
 
+    #[test]
+    fn get_autoindex() {
+        let file = create_sqlite_database(&[
+            "CREATE TABLE example(id integer primary key unique, col1 unique unique, col2 text, col3 unique);",
+            "CREATE UNIQUE INDEX index1 ON example(col3, col1);",
+        ]);
+        let schema = generate_schema(file.path());
+
+        let table = schema.get_table(b"example").unwrap();
+        assert_eq!(
+            autoindex_columns(table),
+            vec![
+                vec![ColumnNumber::RowId],
+                vec![ColumnNumber::Column(1)],
+                vec![ColumnNumber::Column(3)]
+            ]
+        );
+        let autoindex2 = schema.get_index(b"sqlite_autoindex_example_2").unwrap();
+        assert_eq!(
+            autoindex2.columns,
+            vec![(ColumnNumber::Column(1), Collation::Binary)]
+        );
+        assert!(autoindex2.unique);
+        let autoindex3 = schema.get_index(b"sqlite_autoindex_example_3").unwrap();
+        assert_eq!(
+            autoindex3.columns,
+            vec![(ColumnNumber::Column(3), Collation::Binary)]
+        );
+        assert!(autoindex3.unique);
+        let index1 = schema.get_index(b"index1").unwrap();
+        assert!(index1.unique);
+    }
+
     #[test]
     fn get_index_case_insensitive() {
         let file = create_sqlite_database(&[
@@ -1110,31 +2193,110 @@ mod tests {
     #[test]
     fn parse_index() {
         let (_, table) = Table::parse(
-            b"create table example(col1, id integer primary key, col2)",
+            b"create table example(col1, id integer primary key, col2 collate nocase)",
             PAGE_ID_1,
         )
         .unwrap();
         let page_id = PageId::new(3).unwrap();
-        let (index_name, table_name, index) = Index::parse(
-            b"create index index1 on example(id, col1, col2)",
+        let (table_name, index) = Index::parse(
+            b"create index index1 on example(id, col1 collate rtrim, col2)",
             page_id,
             &table,
         )
         .unwrap();
-        assert_eq!(index_name, b"index1");
         assert_eq!(table_name, b"example".as_slice().into());
         assert_eq!(
             index,
             Index {
+                name: b"index1".to_vec(),
                 root_page_id: page_id,
                 columns: vec![
-                    ColumnNumber::RowId,
-                    ColumnNumber::Column(0),
-                    ColumnNumber::Column(2)
+                    (ColumnNumber::RowId, Collation::Binary),
+                    (ColumnNumber::Column(0), Collation::RTrim),
+                    (ColumnNumber::Column(2), Collation::NoCase)
                 ],
+                expressions: vec![None, None, None],
+                descending: vec![false, false, false],
+                unique: false,
+                predicate: None,
+                unsupported: false,
                 next: None,
             }
         );
+        let (_, index) = Index::parse(
+            b"create index index2 on example(col1 desc, col2 asc, id)",
+            page_id,
+            &table,
+        )
+        .unwrap();
+        assert_eq!(index.descending, vec![true, false, false]);
+        assert!(index.predicate.is_none());
+        let (_, index) = Index::parse(
+            b"create index index3 on example(col1) where col2 > 10 and example.id is not null",
+            page_id,
+            &table,
+        )
+        .unwrap();
+        assert_eq!(
+            index.predicate.unwrap().sql,
+            b"col2 > 10 and example.id is not null"
+        );
+        let (_, index) = Index::parse(
+            b"create index index4 on example(abs(col1), col2, abs(col2) collate rtrim)",
+            page_id,
+            &table,
+        )
+        .unwrap();
+        assert_eq!(
+            index.columns,
+            vec![
+                (ColumnNumber::RowId, Collation::Binary),
+                (ColumnNumber::Column(2), Collation::NoCase),
+                (ColumnNumber::RowId, Collation::RTrim),
+            ]
+        );
+        assert_eq!(index.expressions[0].as_ref().unwrap().sql, b"abs(col1)");
+        assert!(index.expressions[1].is_none());
+        assert_eq!(
+            index.expressions[2].as_ref().unwrap().sql,
+            b"abs(col2) collate rtrim"
+        );
+        // unknown function
+        let (_, index) = Index::parse(
+            b"create index index5 on example(col1, substr(col2, 1) collate rtrim)",
+            page_id,
+            &table,
+        )
+        .unwrap();
+        assert!(index.unsupported);
+        assert_eq!(
+            index.columns,
+            vec![
+                (ColumnNumber::Column(0), Collation::Binary),
+                (ColumnNumber::RowId, Collation::RTrim),
+            ]
+        );
+        let (_, index) = Index::parse(
+            b"create index index6 on example(col1) where length(col2) > 1",
+            page_id,
+            &table,
+        )
+        .unwrap();
+        assert!(index.unsupported);
+        // unknown column in the key expression
+        assert!(Index::parse(
+            b"create index index1 on example(abs(invalid))",
+            page_id,
+            &table
+        )
+        .is_err());
+        // unknown column in the where clause
+        assert!(Index::parse(
+            b"create index index1 on example(col1) where invalid = 1",
+            page_id,
+            &table
+        )
+        .is_err());
         // unknown column
         assert!(Index::parse(
             b"create index index1 on example(col1, invalid)",
@@ -1142,8 +2304,15 @@ mod tests {
             &table
         )
         .is_err());
+        // unknown collation
+        assert!(Index::parse(
+            b"create index index1 on example(col1 collate invalid)",
+            page_id,
+            &table
+        )
+        .is_err());
         // unknown table
-        let (_, table_name, _) =
+        let (table_name, _) =
             Index::parse(b"create index index1 on invalid(col1)", page_id, &table).unwrap();
         assert_eq!(table_name, b"invalid".as_slice().into());
     }
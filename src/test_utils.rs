@@ -16,6 +16,7 @@ use std::fmt::Write;
 use std::fs::File;
 use std::os::unix::fs::FileExt;
 use std::path::Path;
+use std::rc::Rc;
 
 use tempfile::NamedTempFile;
 
@@ -97,12 +98,15 @@ pub fn find_table_page_id(table: &str, filepath: &Path) -> PageId {
         .map(Expression::Column)
         .collect::<Vec<_>>();
     let schema = Schema::generate(
+        &conn,
         SelectStatement::new(
-            &conn,
             schema_table.root_page_id,
             columns,
+            Rc::new([]),
             Expression::one(),
             QueryPlan::FullScan,
+            Vec::new(),
+            None,
         ),
         schema_table,
     )
@@ -118,12 +122,15 @@ pub fn find_index_page_id(index: &str, filepath: &Path) -> PageId {
         .map(Expression::Column)
         .collect::<Vec<_>>();
     let schema = Schema::generate(
+        &conn,
         SelectStatement::new(
-            &conn,
             schema_table.root_page_id,
             columns,
+            Rc::new([]),
             Expression::one(),
             QueryPlan::FullScan,
+            Vec::new(),
+            None,
         ),
         schema_table,
     )
@@ -31,11 +31,11 @@ static CHAR_LOOKUP_TABLE: [u8; 256] = [
     0xFF, b' ', b' ', 0xFF, b' ', b' ', 0xFF, 0xFF, // 0x08 - 0x0F
     0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0x10 - 0x17
     0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0x18 - 0x1F
-    b' ', b'!', 0x05, 0xFF, 0x04, 0xFF, 0xFF, 0x05, // 0x20 - 0x27
-    b'(', b')', b'*', b'+', b',', b'-', b'.', 0xFF, // 0x28 - 0x2F
+    b' ', b'!', 0x05, 0xFF, 0x04, b'%', b'&', 0x05, // 0x20 - 0x27
+    b'(', b')', b'*', b'+', b',', b'-', b'.', b'/', // 0x28 - 0x2F
     0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, // 0x30 - 0x37
-    0x03, 0x03, 0xFF, b';', b'<', b'=', b'>', 0xFF, // 0x38 - 0x3F
-    0xFF, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, // 0x40 - 0x47
+    0x03, 0x03, b':', b';', b'<', b'=', b'>', b'?', // 0x38 - 0x3F
+    b'@', 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, // 0x40 - 0x47
     0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, // 0x48 - 0x4F
     0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, // 0x50 - 0x57
     0x00, 0x01, 0x01, 0x06, 0xFF, 0xFF, 0xFF, 0x02, // 0x58 - 0x5F
@@ -64,25 +64,70 @@ static CHAR_LOOKUP_TABLE: [u8; 256] = [
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Token<'a> {
     // Keywords
+    All,
+    And,
     As,
+    Asc,
+    Autoincrement,
+    Between,
+    By,
+    Case,
     Cast,
+    Check,
     Collate,
     Create,
+    Default,
     Delete,
+    Desc,
+    Distinct,
+    Else,
+    End,
+    Escape,
+    Except,
+    Foreign,
     From,
+    Glob,
+    Group,
+    Having,
+    Ignore,
+    In,
     Index,
+    Inner,
     Insert,
+    Intersect,
     Into,
+    Is,
+    Isnull,
+    Join,
     Key,
+    Left,
+    Like,
+    Not,
+    Notnull,
     Null,
     On,
+    Or,
+    Order,
+    Outer,
+    Pragma,
     Primary,
+    References,
+    Replace,
     Select,
+    Set,
     Table,
+    Then,
+    Union,
+    Unique,
+    Update,
+    Vacuum,
     Values,
+    When,
     Where,
+    Without,
 
     // Symbols
+    /// Whitespace or a comment.
     Space,
     LeftParen,
     RightParen,
@@ -91,6 +136,8 @@ pub enum Token<'a> {
     Comma,
     Minus,
     Dot,
+    Slash,
+    Percent,
     Semicolon,
     Tilda,
 
@@ -107,7 +154,10 @@ pub enum Token<'a> {
     Lt,
     /// Less than or equal to
     Le,
+    BitAnd,
     BitOr,
+    ShiftLeft,
+    ShiftRight,
     Concat,
 
     // Literals
@@ -116,11 +166,15 @@ pub enum Token<'a> {
     Blob(HexedBytes<'a>),
     // Only contains 0-9 chars.
     Integer(&'a [u8]),
+    /// Hexadecimal digits without the `0x` prefix.
+    HexInteger(&'a [u8]),
     Float(&'a [u8]),
+    /// Parameter including the prefix (e.g. `?`, `?1`, `:name`).
+    Variable(&'a [u8]),
     Illegal,
 }
 
-pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
+pub fn get_token(input: &[u8]) -> Option<(usize, Token<'_>)> {
     if input.is_empty() {
         return None;
     }
@@ -140,30 +194,66 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
                 Some((1, Token::Illegal))
             }
         }
+        b'&' => Some((1, Token::BitAnd)),
         b'(' => Some((1, Token::LeftParen)),
         b')' => Some((1, Token::RightParen)),
         b'*' => Some((1, Token::Asterisk)),
         b'+' => Some((1, Token::Plus)),
         b',' => Some((1, Token::Comma)),
-        b'-' => Some((1, Token::Minus)),
+        b'-' => {
+            if input.len() >= 2 && input[1] == b'-' {
+                // The line comment does not include the newline.
+                let len = input
+                    .iter()
+                    .position(|&byte| byte == b'\n' || byte == 0)
+                    .unwrap_or(input.len());
+                Some((len, Token::Space))
+            } else {
+                Some((1, Token::Minus))
+            }
+        }
         b'.' => {
             if input.len() >= 2 && input[1].is_ascii_digit() {
                 let (len, valid) = len_float(input);
-                if valid {
-                    Some((len, Token::Float(&input[..len])))
+                if !valid {
+                    return Some((len, Token::Illegal));
+                }
+                let n_suffix = len_identifier_suffix(&input[len..]);
+                if n_suffix > 0 {
+                    Some((len + n_suffix, Token::Illegal))
                 } else {
-                    Some((len, Token::Illegal))
+                    Some((len, Token::Float(&input[..len])))
                 }
             } else {
                 Some((1, Token::Dot))
             }
         }
+        b'/' => {
+            // Like SQLite, "/*" at the end of the input is not a comment.
+            if input.len() >= 3 && input[1] == b'*' {
+                // An unterminated block comment continues to the end of the
+                // input.
+                let end = input[2..]
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .map_or(input.len(), |i| i + 2);
+                let len = input[2..end]
+                    .windows(2)
+                    .position(|window| window == b"*/")
+                    .map_or(end, |i| i + 4);
+                Some((len, Token::Space))
+            } else {
+                Some((1, Token::Slash))
+            }
+        }
+        b'%' => Some((1, Token::Percent)),
         b';' => Some((1, Token::Semicolon)),
         b'<' => {
             if input.len() >= 2 {
                 match input[1] {
                     b'=' => Some((2, Token::Le)),
                     b'>' => Some((2, Token::Ne)),
+                    b'<' => Some((2, Token::ShiftLeft)),
                     _ => Some((1, Token::Lt)),
                 }
             } else {
@@ -178,8 +268,12 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
             }
         }
         b'>' => {
-            if input.len() >= 2 && input[1] == b'=' {
-                Some((2, Token::Ge))
+            if input.len() >= 2 {
+                match input[1] {
+                    b'=' => Some((2, Token::Ge)),
+                    b'>' => Some((2, Token::ShiftRight)),
+                    _ => Some((1, Token::Gt)),
+                }
             } else {
                 Some((1, Token::Gt))
             }
@@ -192,6 +286,21 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
             }
         }
         b'~' => Some((1, Token::Tilda)),
+        b'?' => {
+            let len = 1 + input[1..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+            Some((len, Token::Variable(&input[..len])))
+        }
+        CHAR_DOLLAR | b':' | b'@' => {
+            let len = len_identifier(input);
+            if len == 1 {
+                Some((1, Token::Illegal))
+            } else {
+                Some((len, Token::Variable(&input[..len])))
+            }
+        }
         CHAR_X => {
             if input.len() >= 2 && input[1] == b'\'' {
                 let mut iter = input.iter().skip(2).enumerate();
@@ -233,31 +342,91 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
                     lower_id[i] = UPPER_TO_LOWER[byte as usize];
                 }
                 match &lower_id {
+                    b"all\0\0\0\0" => Some((len, Token::All)),
+                    b"and\0\0\0\0" => Some((len, Token::And)),
                     b"as\0\0\0\0\0" => Some((len, Token::As)),
+                    b"asc\0\0\0\0" => Some((len, Token::Asc)),
+                    b"between" => Some((len, Token::Between)),
+                    b"by\0\0\0\0\0" => Some((len, Token::By)),
+                    b"case\0\0\0" => Some((len, Token::Case)),
                     b"cast\0\0\0" => Some((len, Token::Cast)),
+                    b"check\0\0" => Some((len, Token::Check)),
                     b"collate" => Some((len, Token::Collate)),
                     b"create\0" => Some((len, Token::Create)),
+                    b"default" => Some((len, Token::Default)),
                     b"delete\0" => Some((len, Token::Delete)),
+                    b"desc\0\0\0" => Some((len, Token::Desc)),
+                    b"else\0\0\0" => Some((len, Token::Else)),
+                    b"end\0\0\0\0" => Some((len, Token::End)),
+                    b"escape\0" => Some((len, Token::Escape)),
+                    b"except\0" => Some((len, Token::Except)),
+                    b"foreign" => Some((len, Token::Foreign)),
                     b"from\0\0\0" => Some((len, Token::From)),
+                    b"glob\0\0\0" => Some((len, Token::Glob)),
+                    b"group\0\0" => Some((len, Token::Group)),
+                    b"having\0" => Some((len, Token::Having)),
+                    b"ignore\0" => Some((len, Token::Ignore)),
+                    b"in\0\0\0\0\0" => Some((len, Token::In)),
                     b"index\0\0" => Some((len, Token::Index)),
+                    b"inner\0\0" => Some((len, Token::Inner)),
                     b"insert\0" => Some((len, Token::Insert)),
                     b"into\0\0\0" => Some((len, Token::Into)),
+                    b"is\0\0\0\0\0" => Some((len, Token::Is)),
+                    b"isnull\0" => Some((len, Token::Isnull)),
+                    b"join\0\0\0" => Some((len, Token::Join)),
                     b"key\0\0\0\0" => Some((len, Token::Key)),
+                    b"left\0\0\0" => Some((len, Token::Left)),
+                    b"like\0\0\0" => Some((len, Token::Like)),
+                    b"not\0\0\0\0" => Some((len, Token::Not)),
+                    b"notnull" => Some((len, Token::Notnull)),
                     b"null\0\0\0" => Some((len, Token::Null)),
                     b"on\0\0\0\0\0" => Some((len, Token::On)),
+                    b"or\0\0\0\0\0" => Some((len, Token::Or)),
+                    b"order\0\0" => Some((len, Token::Order)),
+                    b"outer\0\0" => Some((len, Token::Outer)),
+                    b"pragma\0" => Some((len, Token::Pragma)),
                     b"primary" => Some((len, Token::Primary)),
+                    b"replace" => Some((len, Token::Replace)),
                     b"select\0" => Some((len, Token::Select)),
+                    b"set\0\0\0\0" => Some((len, Token::Set)),
                     b"table\0\0" => Some((len, Token::Table)),
+                    b"then\0\0\0" => Some((len, Token::Then)),
+                    b"union\0\0" => Some((len, Token::Union)),
+                    b"unique\0" => Some((len, Token::Unique)),
+                    b"update\0" => Some((len, Token::Update)),
+                    b"vacuum\0" => Some((len, Token::Vacuum)),
                     b"values\0" => Some((len, Token::Values)),
+                    b"when\0\0\0" => Some((len, Token::When)),
                     b"where\0\0" => Some((len, Token::Where)),
+                    b"without" => Some((len, Token::Without)),
                     _ => Some((len, Token::Identifier(id.into()))),
                 }
+            } else if id.eq_ignore_ascii_case(b"intersect") {
+                // Keywords longer than MAX_KEYWORD_LEN.
+                Some((len, Token::Intersect))
+            } else if id.eq_ignore_ascii_case(b"distinct") {
+                Some((len, Token::Distinct))
+            } else if id.eq_ignore_ascii_case(b"autoincrement") {
+                Some((len, Token::Autoincrement))
+            } else if id.eq_ignore_ascii_case(b"references") {
+                Some((len, Token::References))
             } else {
                 Some((len, Token::Identifier(id.into())))
             }
         }
         CHAR_DIGIT => {
-            // TODO: support hexadecimal.
+            if input[0] == b'0' && input.len() >= 2 && (input[1] == b'x' || input[1] == b'X') {
+                let len = 2 + input[2..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_hexdigit())
+                    .count();
+                // "0x" without hexadecimal digits is illegal.
+                let n_suffix = len_identifier_suffix(&input[len..]);
+                if len == 2 || n_suffix > 0 {
+                    return Some((len + n_suffix, Token::Illegal));
+                }
+                return Some((len, Token::HexInteger(&input[2..len])));
+            }
             let mut len = 1;
             for &byte in input.iter().skip(len) {
                 // NOTE: u8::is_ascii_digit() is faster than CHAR_LOOKUP_TABLE.
@@ -269,7 +438,11 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
             }
             let (l, valid) = len_float(&input[len..]);
             if !valid {
-                Some((len + l, Token::Illegal))
+                return Some((len + l, Token::Illegal));
+            }
+            let n_suffix = len_identifier_suffix(&input[len + l..]);
+            if n_suffix > 0 {
+                Some((len + l + n_suffix, Token::Illegal))
             } else if l == 0 {
                 Some((len, Token::Integer(&input[..len])))
             } else {
@@ -299,8 +472,9 @@ pub fn get_token(input: &[u8]) -> Option<(usize, Token)> {
         CHAR_QUOTE2 => {
             for (i, &byte) in input.iter().enumerate().skip(1) {
                 if byte == b']' {
-                    let unquoted_buf = &input[1..i];
-                    return Some((i + 1, Token::Identifier(unquoted_buf.into())));
+                    // Keep the brackets so that the content is not dequoted again.
+                    let quoted_buf = &input[..i + 1];
+                    return Some((i + 1, Token::Identifier(quoted_buf.into())));
                 }
             }
             Some((input.len(), Token::Illegal))
@@ -322,6 +496,15 @@ fn len_identifier(input_bytes: &[u8]) -> usize {
     input_bytes.len()
 }
 
+/// The length of the identifier characters which make the preceding numeric
+/// literal illegal (e.g. "12abc") as SQLite does.
+fn len_identifier_suffix(input: &[u8]) -> usize {
+    input
+        .iter()
+        .take_while(|&&byte| CHAR_LOOKUP_TABLE[byte as usize] <= CHAR_DOLLAR)
+        .count()
+}
+
 fn len_float(input: &[u8]) -> (usize, bool) {
     let mut len = 0;
     if !input.is_empty() && input[0] == b'.' {
@@ -379,6 +562,8 @@ mod tests {
             (',', Token::Comma),
             ('-', Token::Minus),
             ('.', Token::Dot),
+            ('/', Token::Slash),
+            ('%', Token::Percent),
             (';', Token::Semicolon),
             ('~', Token::Tilda),
         ] {
@@ -404,6 +589,29 @@ mod tests {
         assert_eq!(get_token(b"  \t\n\x0b\x0c\r\x0e"), Some((7, Token::Space)));
     }
 
+    #[test]
+    fn test_comment() {
+        assert_eq!(get_token(b"--"), Some((2, Token::Space)));
+        assert_eq!(get_token(b"-- comment"), Some((10, Token::Space)));
+        assert_eq!(get_token(b"-- comment\nSELECT"), Some((10, Token::Space)));
+        assert_eq!(get_token(b"--\r\n"), Some((3, Token::Space)));
+        assert_eq!(get_token(b"-- a\0b"), Some((4, Token::Space)));
+        assert_eq!(get_token(b"- -"), Some((1, Token::Minus)));
+
+        assert_eq!(get_token(b"/**/"), Some((4, Token::Space)));
+        assert_eq!(get_token(b"/* comment */a"), Some((13, Token::Space)));
+        assert_eq!(get_token(b"/* multi\nline */"), Some((16, Token::Space)));
+        assert_eq!(get_token(b"/* a /* b */ c */"), Some((12, Token::Space)));
+        assert_eq!(get_token(b"/* -- */a"), Some((8, Token::Space)));
+        assert_eq!(get_token(b"/***/"), Some((5, Token::Space)));
+        // Unterminated block comments continue to the end of the input.
+        assert_eq!(get_token(b"/* comment"), Some((10, Token::Space)));
+        assert_eq!(get_token(b"/*/"), Some((3, Token::Space)));
+        assert_eq!(get_token(b"/* a\0 */"), Some((4, Token::Space)));
+        assert_eq!(get_token(b"/*"), Some((1, Token::Slash)));
+        assert_eq!(get_token(b"/ *"), Some((1, Token::Slash)));
+    }
+
     #[test]
     fn test_integer() {
         let mut test_cases = Vec::new();
@@ -427,16 +635,60 @@ mod tests {
                 "literal: {}",
                 literal
             );
-            let input = format!("{literal}abc ");
+            let input = format!("{literal} abc");
             assert_eq!(
                 get_token(input.as_bytes()),
                 Some((literal.len(), Token::Integer(literal.as_bytes()))),
                 "input: {}",
                 input
             );
+            let input = format!("{literal}abc ");
+            assert_eq!(
+                get_token(input.as_bytes()),
+                Some((literal.len() + 3, Token::Illegal)),
+                "input: {}",
+                input
+            );
         }
     }
 
+    #[test]
+    fn test_hex_integer() {
+        for literal in [
+            "0x0",
+            "0X1f",
+            "0xaBcDeF",
+            "0x0123456789",
+            "0x10000000000000000",
+        ] {
+            assert_eq!(
+                get_token(literal.as_bytes()),
+                Some((literal.len(), Token::HexInteger(&literal.as_bytes()[2..]))),
+                "literal: {}",
+                literal
+            );
+            let input = format!("{literal}+1");
+            assert_eq!(
+                get_token(input.as_bytes()),
+                Some((literal.len(), Token::HexInteger(&literal.as_bytes()[2..]))),
+                "input: {}",
+                input
+            );
+            let input = format!("{literal}xyz ");
+            assert_eq!(
+                get_token(input.as_bytes()),
+                Some((literal.len() + 3, Token::Illegal)),
+                "input: {}",
+                input
+            );
+        }
+        // No hexadecimal digits after 0x.
+        assert_eq!(get_token(b"0x"), Some((2, Token::Illegal)));
+        assert_eq!(get_token(b"0X "), Some((2, Token::Illegal)));
+        assert_eq!(get_token(b"0xg"), Some((3, Token::Illegal)));
+        assert_eq!(get_token(b"00x1"), Some((4, Token::Illegal)));
+    }
+
     #[test]
     fn test_float() {
         for literal in [
@@ -467,13 +719,20 @@ mod tests {
                 "literal: {}",
                 literal
             );
-            let input = format!("{literal}abc ");
+            let input = format!("{literal}-1");
             assert_eq!(
                 get_token(input.as_bytes()),
                 Some((literal.len(), Token::Float(literal.as_bytes()))),
                 "input: {}",
                 input
             );
+            let input = format!("{literal}abc ");
+            assert_eq!(
+                get_token(input.as_bytes()),
+                Some((literal.len() + 3, Token::Illegal)),
+                "input: {}",
+                input
+            );
         }
         assert_eq!(get_token(b"0.1.2"), Some((3, Token::Float(b"0.1"))));
     }
@@ -487,6 +746,11 @@ mod tests {
         assert_eq!(get_token(b"0.1e-"), Some((5, Token::Illegal)));
         assert_eq!(get_token(b"0.1e-e"), Some((5, Token::Illegal)));
         assert_eq!(get_token(b".e1"), Some((1, Token::Dot)));
+        // Identifier characters right after a numeric literal.
+        assert_eq!(get_token(b"12_ "), Some((3, Token::Illegal)));
+        assert_eq!(get_token(b"12$ "), Some((3, Token::Illegal)));
+        assert_eq!(get_token(b"1.5e3x "), Some((6, Token::Illegal)));
+        assert_eq!(get_token(b".5a "), Some((3, Token::Illegal)));
         assert_eq!(
             get_token(b"e1"),
             Some((2, Token::Identifier(b"e1".as_slice().into())))
@@ -547,6 +811,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_variable() {
+        assert_eq!(get_token(b"?"), Some((1, Token::Variable(b"?"))));
+        assert_eq!(get_token(b"? "), Some((1, Token::Variable(b"?"))));
+        assert_eq!(get_token(b"?123,"), Some((4, Token::Variable(b"?123"))));
+        assert_eq!(get_token(b"?a"), Some((1, Token::Variable(b"?"))));
+        assert_eq!(
+            get_token(b":name_1 "),
+            Some((7, Token::Variable(b":name_1")))
+        );
+        assert_eq!(get_token(b"@a1)"), Some((3, Token::Variable(b"@a1"))));
+        assert_eq!(get_token(b"$a$b;"), Some((4, Token::Variable(b"$a$b"))));
+        assert_eq!(get_token(b":1"), Some((2, Token::Variable(b":1"))));
+        assert_eq!(get_token(b": "), Some((1, Token::Illegal)));
+        assert_eq!(get_token(b"@"), Some((1, Token::Illegal)));
+        assert_eq!(get_token(b"$"), Some((1, Token::Illegal)));
+    }
+
     #[test]
     fn test_quoted_identifier() {
         assert_eq!(
@@ -614,16 +896,16 @@ mod tests {
 
         assert_eq!(
             get_token(b"[hello]] "),
-            Some((7, Token::Identifier(b"hello".as_slice().into())))
+            Some((7, Token::Identifier(b"[hello]".as_slice().into())))
         );
         // Non-ASCII
         assert_eq!(
             get_token(b"[\xE3\x81\x82] "),
-            Some((5, Token::Identifier(b"\xE3\x81\x82".as_slice().into())))
+            Some((5, Token::Identifier(b"[\xE3\x81\x82]".as_slice().into())))
         );
         assert_eq!(
             get_token(b"[[he[llo[]]] "),
-            Some((10, Token::Identifier(b"[he[llo[".as_slice().into())))
+            Some((10, Token::Identifier(b"[[he[llo[]".as_slice().into())))
         );
 
         assert_eq!(get_token(b"`hello\" "), Some((8, Token::Illegal)));
@@ -680,23 +962,67 @@ mod tests {
     #[test]
     fn test_keywords() {
         for (keyword, token) in [
+            ("all", Token::All),
+            ("and", Token::And),
             ("as", Token::As),
+            ("asc", Token::Asc),
+            ("autoincrement", Token::Autoincrement),
+            ("between", Token::Between),
+            ("by", Token::By),
+            ("case", Token::Case),
             ("cast", Token::Cast),
+            ("check", Token::Check),
             ("collate", Token::Collate),
             ("create", Token::Create),
+            ("default", Token::Default),
             ("delete", Token::Delete),
+            ("desc", Token::Desc),
+            ("distinct", Token::Distinct),
+            ("else", Token::Else),
+            ("end", Token::End),
+            ("escape", Token::Escape),
+            ("except", Token::Except),
+            ("foreign", Token::Foreign),
             ("from", Token::From),
+            ("glob", Token::Glob),
+            ("group", Token::Group),
+            ("having", Token::Having),
+            ("ignore", Token::Ignore),
+            ("in", Token::In),
             ("index", Token::Index),
+            ("inner", Token::Inner),
             ("insert", Token::Insert),
+            ("intersect", Token::Intersect),
             ("into", Token::Into),
+            ("is", Token::Is),
+            ("isnull", Token::Isnull),
+            ("join", Token::Join),
             ("key", Token::Key),
+            ("left", Token::Left),
+            ("like", Token::Like),
+            ("not", Token::Not),
+            ("notnull", Token::Notnull),
             ("null", Token::Null),
             ("on", Token::On),
+            ("or", Token::Or),
+            ("order", Token::Order),
+            ("outer", Token::Outer),
+            ("pragma", Token::Pragma),
             ("primary", Token::Primary),
+            ("references", Token::References),
+            ("replace", Token::Replace),
             ("select", Token::Select),
+            ("set", Token::Set),
             ("table", Token::Table),
+            ("then", Token::Then),
+            ("union", Token::Union),
+            ("unique", Token::Unique),
+            ("update", Token::Update),
+            ("vacuum", Token::Vacuum),
             ("values", Token::Values),
+            ("when", Token::When),
             ("where", Token::Where),
+            ("without", Token::Without),
         ] {
             assert_eq!(get_token(keyword.as_bytes()), Some((keyword.len(), token)));
             let input = format!("{keyword} ");
@@ -742,6 +1068,11 @@ mod tests {
             get_token(b"X'' "),
             Some((3, Token::Blob(b"".as_slice().into())))
         );
+        // Mixed case
+        assert_eq!(
+            get_token(b"x'aBcDeF' "),
+            Some((9, Token::Blob(b"aBcDeF".as_slice().into())))
+        );
     }
 
     #[test]
@@ -799,10 +1130,13 @@ mod tests {
             ("<", Token::Lt),
             ("<=", Token::Le),
             ("<>", Token::Ne),
+            ("<<", Token::ShiftLeft),
             ("=", Token::Eq),
             ("==", Token::Eq),
             (">", Token::Gt),
             (">=", Token::Ge),
+            (">>", Token::ShiftRight),
+            ("&", Token::BitAnd),
             ("|", Token::BitOr),
             ("||", Token::Concat),
         ] {
@@ -941,7 +1275,7 @@ mod tests {
                     Token::Space,
                     Token::Where,
                     Token::Space,
-                    Token::Identifier(b"col1".as_slice().into()),
+                    Token::Identifier(b"[col1]".as_slice().into()),
                     Token::Eq,
                     Token::String(b"'hello world'".as_slice().into()),
                     Token::Semicolon,
@@ -13,12 +13,21 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
 use std::hash::Hash;
 use std::hash::Hasher;
 
 const VARINT_FLAG_MASK: u8 = 0b1000_0000;
 const VARINT_VAR_MASK: u64 = (!VARINT_FLAG_MASK) as u64;
 
+/// Generate a random number.
+///
+/// [RandomState] is seeded randomly and the seed changes on each call.
+pub fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
 /// Convert u64 representation to i64.
 ///
 /// For example: 0xffff_ffff_ffff_ffff -> -1
@@ -615,23 +624,22 @@ impl MaybeQuotedBytes<'_> {
                 }
                 result
             }
+            // The content in brackets is not escaped.
+            Some(&b'[') => {
+                assert!(self.0.len() >= 2);
+                assert_eq!(self.0[self.0.len() - 1], b']');
+                self.0[1..self.0.len() - 1].to_vec()
+            }
             _ => self.0.to_vec(),
         }
     }
 
     pub fn dequote_iter(&self) -> DequotedIter<'_> {
-        let delimiter = self
-            .0
-            .first()
-            .map(|v| match v {
-                b'\'' | b'"' | b'`' => *v,
-                _ => 0,
-            })
-            .unwrap_or(0);
-        let buf = if delimiter != 0 {
-            &self.0[1..self.0.len() - 1]
-        } else {
-            self.0
+        let (delimiter, buf) = match self.0.first() {
+            Some(&v @ (b'\'' | b'"' | b'`')) => (v, &self.0[1..self.0.len() - 1]),
+            // The content in brackets is not escaped.
+            Some(&b'[') => (0, &self.0[1..self.0.len() - 1]),
+            _ => (0, self.0),
         };
         DequotedIter {
             iter: buf.iter(),
@@ -683,13 +691,32 @@ pub fn hex_to_int(h: u8) -> u8 {
     h & 0x0f
 }
 
+/// Parse the hexadecimal digits of a hex integer literal.
+///
+/// The value is the 64-bit two's complement, e.g. `0xFFFFFFFFFFFFFFFF` is -1.
+/// Returns [None] if the value does not fit in 64 bits.
+///
+/// The input must contain only hexadecimal characters.
+pub fn parse_hex_integer(input: &[u8]) -> Option<i64> {
+    let n_leading_zeros = input.iter().take_while(|&&h| h == b'0').count();
+    let digits = &input[n_leading_zeros..];
+    if digits.len() > 16 {
+        return None;
+    }
+    let mut value = 0_u64;
+    for &h in digits {
+        value = (value << 4) | hex_to_int(h) as u64;
+    }
+    Some(value as i64)
+}
+
 /// A wrapper for bytes which is hexadecimal data.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct HexedBytes<'a>(&'a [u8]);
 
 impl HexedBytes<'_> {
     pub fn decode(&self) -> Vec<u8> {
-        assert!(self.0.len() % 2 == 0);
+        assert!(self.0.len().is_multiple_of(2));
         let mut result = Vec::with_capacity(self.0.len() / 2);
         let mut iter = self.0.iter();
         // TODO: Optimization to avoid bounds check.
@@ -704,7 +731,7 @@ impl HexedBytes<'_> {
 
 impl<'a> From<&'a [u8]> for HexedBytes<'a> {
     fn from(bytes: &'a [u8]) -> Self {
-        assert!(bytes.len() % 2 == 0);
+        assert!(bytes.len().is_multiple_of(2));
         Self(bytes)
     }
 }
@@ -1237,6 +1264,11 @@ mod tests {
             );
         }
 
+        assert_eq!(
+            &MaybeQuotedBytes::from(b"[a\"\"b`c]".as_slice()).dequote(),
+            b"a\"\"b`c"
+        );
+        assert_eq!(&MaybeQuotedBytes::from(b"[]".as_slice()).dequote(), b"");
         assert_eq!(&MaybeQuotedBytes::from(b"abc".as_slice()).dequote(), b"abc");
         assert_eq!(&MaybeQuotedBytes::from(b"".as_slice()).dequote(), b"");
     }
@@ -1321,6 +1353,24 @@ mod tests {
                 .len(),
             0
         );
+        assert_eq!(
+            MaybeQuotedBytes::from(b"[a\"\"b]".as_slice())
+                .dequote_iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            b"a\"\"b"
+        );
+    }
+
+    #[test]
+    fn test_parse_hex_integer() {
+        assert_eq!(parse_hex_integer(b"0"), Some(0));
+        assert_eq!(parse_hex_integer(b"fF"), Some(255));
+        assert_eq!(parse_hex_integer(b"7FFFFFFFFFFFFFFF"), Some(i64::MAX));
+        assert_eq!(parse_hex_integer(b"8000000000000000"), Some(i64::MIN));
+        assert_eq!(parse_hex_integer(b"FFFFFFFFFFFFFFFF"), Some(-1));
+        assert_eq!(parse_hex_integer(b"000000000000000000001"), Some(1));
+        assert_eq!(parse_hex_integer(b"10000000000000000"), None);
     }
 
     #[test]
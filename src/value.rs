@@ -16,6 +16,7 @@ use std::cmp::Ordering;
 use std::io::Write;
 use std::ops::Deref;
 
+use crate::utils::is_space;
 use crate::utils::parse_float;
 use crate::utils::parse_integer;
 use crate::utils::CaseInsensitiveBytes;
@@ -46,6 +47,8 @@ pub enum Collation {
     RTrim,
 }
 
+/// The buffer of a text or blob value which is either owned or borrowed from
+/// the page.
 #[derive(Debug, Clone)]
 pub enum Buffer<'a> {
     Owned(Vec<u8>),
@@ -100,10 +103,20 @@ pub enum Value<'a> {
 }
 
 impl<'a> Value<'a> {
+    /// Copy the borrowed buffer if any. The owned buffer is moved.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Integer(i) => Value::Integer(i),
+            Value::Real(d) => Value::Real(d),
+            Value::Text(buf) => Value::Text(Buffer::Owned(buf.into_vec())),
+            Value::Blob(buf) => Value::Blob(Buffer::Owned(buf.into_vec())),
+        }
+    }
+
     pub fn display<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
         match self {
             Value::Integer(i) => write!(w, "{i}"),
-            Value::Real(d) => write!(w, "{d}"),
+            Value::Real(d) => format_real(w, *d),
             Value::Blob(buf) => w.write_all(buf),
             Value::Text(buf) => w.write_all(buf),
         }
@@ -135,6 +148,29 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Convert the value to the datatype of a column in a STRICT table.
+    ///
+    /// The type affinity of the datatype is applied first. Returns [None] if
+    /// the value still does not conform to the datatype.
+    ///
+    /// https://www.sqlite.org/stricttables.html
+    pub fn apply_strict(self, datatype: TypeAffinity) -> Option<Self> {
+        // Blobs are never converted to other types.
+        if matches!(self, Value::Blob(_)) && datatype != TypeAffinity::Blob {
+            return None;
+        }
+        let value = self.apply_affinity(datatype);
+        let conform = matches!(
+            (&value, datatype),
+            (Value::Integer(_), TypeAffinity::Integer)
+                | (Value::Real(_), TypeAffinity::Real)
+                | (Value::Text(_), TypeAffinity::Text)
+                | (Value::Blob(_), TypeAffinity::Blob)
+                | (Value::Integer(_) | Value::Real(_), TypeAffinity::Numeric)
+        );
+        conform.then_some(value)
+    }
+
     /// Convert the text value to a numeric value if it is well-formed.
     /// Otherwise, return the original value.
     pub fn apply_numeric_affinity(self) -> Self {
@@ -186,9 +222,8 @@ impl<'a> Value<'a> {
                 Value::Text(Buffer::Owned(text_buf))
             }
             Value::Real(d) => {
-                // TODO: Use the same format as SQLite "%!.15g".
                 let mut text_buf = Vec::new();
-                write!(text_buf, "{}", d).unwrap();
+                format_real(&mut text_buf, d).unwrap();
                 Value::Text(Buffer::Owned(text_buf))
             }
             Value::Text(t) => Value::Text(t),
@@ -214,6 +249,35 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Convert the value to a real value if it is well-formed. otherwise,
+    /// convert it to 0.0.
+    pub fn as_real(&self) -> f64 {
+        match self {
+            Value::Integer(i) => *i as f64,
+            Value::Real(d) => *d,
+            Value::Text(buf) | Value::Blob(buf) => {
+                let (_, _, d) = parse_float(buf);
+                d
+            }
+        }
+    }
+
+    /// Convert the value to a boolean value.
+    ///
+    /// Text and blob values are converted to a real value first.
+    ///
+    /// This comes from sqlite3VdbeBooleanValue().
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Integer(i) => *i != 0,
+            Value::Real(d) => *d != 0.0,
+            Value::Text(buf) | Value::Blob(buf) => {
+                let (_, _, d) = parse_float(buf);
+                d != 0.0
+            }
+        }
+    }
+
     /// Convert the value to text and return the [Buffer].
     pub fn force_text_buffer(self) -> Buffer<'a> {
         match self {
@@ -224,9 +288,8 @@ impl<'a> Value<'a> {
                 Buffer::Owned(text_buf)
             }
             Value::Real(d) => {
-                // TODO: Use the same format as SQLite "%!.15g".
                 let mut text_buf = Vec::new();
-                write!(text_buf, "{}", d).unwrap();
+                format_real(&mut text_buf, d).unwrap();
                 Buffer::Owned(text_buf)
             }
             Value::Text(buf) => buf,
@@ -234,6 +297,33 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Convert the value to an integer or a real value for arithmetic even if
+    /// the conversion is lossy.
+    ///
+    /// Unlike CAST AS NUMERIC, text with a decimal point or an exponent is a
+    /// real value even if it has no fractional part. Text without any number
+    /// is 0.
+    ///
+    /// This comes from numericType() in vdbe.c of SQLite.
+    pub fn force_numeric(self) -> Self {
+        match self {
+            Value::Integer(i) => Value::Integer(i),
+            Value::Real(d) => Value::Real(d),
+            Value::Text(buf) | Value::Blob(buf) => {
+                let (_, pure_integer, d) = parse_float(&buf);
+                if !pure_integer && has_number(&buf) {
+                    return Value::Real(d);
+                }
+                let (_, parsed_int) = parse_integer(&buf);
+                match parsed_int {
+                    ParseIntegerResult::Integer(i) => Value::Integer(i),
+                    ParseIntegerResult::Empty => Value::Integer(0),
+                    _ => Value::Real(d),
+                }
+            }
+        }
+    }
+
     /// Convert the [Value] to the type of [TypeAffinity] even if the conversion
     /// is lossy.
     ///
@@ -286,6 +376,53 @@ impl<'a> Value<'a> {
     }
 }
 
+/// Whether the text starts with a number after optional spaces and a sign.
+fn has_number(text: &[u8]) -> bool {
+    let mut text = text.iter().skip_while(|&&b| is_space(b)).peekable();
+    text.next_if(|&&b| b == b'+' || b == b'-');
+    text.next_if_eq(&&b'.');
+    text.next().is_some_and(u8::is_ascii_digit)
+}
+
+/// Format a real value as SQLite does with "%!.15g".
+///
+/// The value is rounded to 15 significant digits and always has a digit after
+/// the decimal point. The exponent form is used if the exponent is less than
+/// -4 or not less than 15. Negative zero is formatted as "0.0".
+fn format_real<W: Write>(w: &mut W, d: f64) -> std::io::Result<()> {
+    if d.is_nan() {
+        return w.write_all(b"NaN");
+    } else if d.is_infinite() {
+        return w.write_all(if d > 0.0 { b"Inf" } else { b"-Inf" });
+    } else if d == 0.0 {
+        return w.write_all(b"0.0");
+    }
+    // Keeps at least one digit after the decimal point.
+    fn trim_zeros(text: &str) -> &str {
+        let trimmed = text.trim_end_matches('0');
+        if trimmed.ends_with('.') {
+            &text[..trimmed.len() + 1]
+        } else {
+            trimmed
+        }
+    }
+    // "-d.dddddddddddddde<exp>"
+    let text = format!("{:.14e}", d);
+    let (mantissa, exp) = text.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    if (-4..15).contains(&exp) {
+        let text = format!("{:.*}", (14 - exp) as usize, d);
+        if text.contains('.') {
+            w.write_all(trim_zeros(&text).as_bytes())
+        } else {
+            write!(w, "{}.0", text)
+        }
+    } else {
+        let sign = if exp < 0 { '-' } else { '+' };
+        write!(w, "{}e{}{:02}", trim_zeros(mantissa), sign, exp.abs())
+    }
+}
+
 /// sqlite3RealSameAsInt() in vdbemem.c of SQLite
 fn is_real_same_as_int(d: f64, i: i64) -> bool {
     let di = i as f64;
@@ -402,7 +539,7 @@ impl ConstantValue {
         }
     }
 
-    pub fn as_value(&self) -> Value {
+    pub fn as_value(&self) -> Value<'_> {
         match self {
             Self::Integer(i) => Value::Integer(*i),
             Self::Real(f) => Value::Real(*f),
@@ -416,6 +553,28 @@ impl ConstantValue {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_value_into_owned() {
+        let buf = b"abc".to_vec();
+        let value = Value::Text(buf.as_slice().into()).into_owned();
+        drop(buf);
+        assert!(matches!(&value, Value::Text(Buffer::Owned(_))));
+        assert_eq!(value, Value::Text(b"abc".to_vec().into()));
+        assert_eq!(Value::Integer(1).into_owned(), Value::Integer(1));
+        assert!(matches!(
+            Value::Blob(b"\x01".as_slice().into()).into_owned(),
+            Value::Blob(Buffer::Owned(_))
+        ));
+
+        // The owned buffer is not copied.
+        let buf = b"abc".to_vec();
+        let ptr = buf.as_ptr();
+        let Value::Text(Buffer::Owned(buf)) = Value::Text(buf.into()).into_owned() else {
+            panic!("not owned text");
+        };
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+
     #[test]
     fn test_value_compare() {
         assert_eq!(
@@ -828,6 +987,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_real() {
+        for (value, expected) in [
+            (0.0, "0.0"),
+            (-0.0, "0.0"),
+            (1.0, "1.0"),
+            (-2.0, "-2.0"),
+            (0.1, "0.1"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (100.0, "100.0"),
+            (0.0001, "0.0001"),
+            (0.00001, "1.0e-05"),
+            (123456789012345.0, "123456789012345.0"),
+            (999999999999999.9, "1.0e+15"),
+            (1e15, "1.0e+15"),
+            (1.5e15, "1.5e+15"),
+            (1e20, "1.0e+20"),
+            (-1e20, "-1.0e+20"),
+            (123456789012345678.0, "1.23456789012346e+17"),
+            (1.7976931348623157e308, "1.79769313486232e+308"),
+            (f64::INFINITY, "Inf"),
+            (f64::NEG_INFINITY, "-Inf"),
+        ] {
+            assert_eq!(
+                Value::Real(value).force_text_buffer().as_ref(),
+                expected.as_bytes(),
+                "{}",
+                value
+            );
+        }
+    }
+
     #[test]
     fn test_apply_blob_affinity() {
         assert_eq!(
@@ -848,6 +1039,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_apply_strict() {
+        let text = |s: &'static [u8]| Value::Text(s.into());
+        let blob = |s: &'static [u8]| Value::Blob(s.into());
+        for (value, datatype, expected) in [
+            (
+                Value::Integer(1),
+                TypeAffinity::Integer,
+                Some(Value::Integer(1)),
+            ),
+            (
+                Value::Real(2.0),
+                TypeAffinity::Integer,
+                Some(Value::Integer(2)),
+            ),
+            (text(b"3"), TypeAffinity::Integer, Some(Value::Integer(3))),
+            (Value::Real(1.5), TypeAffinity::Integer, None),
+            (text(b"abc"), TypeAffinity::Integer, None),
+            (blob(b"1"), TypeAffinity::Integer, None),
+            (
+                Value::Integer(1),
+                TypeAffinity::Real,
+                Some(Value::Real(1.0)),
+            ),
+            (text(b"1.5"), TypeAffinity::Real, Some(Value::Real(1.5))),
+            (text(b"abc"), TypeAffinity::Real, None),
+            (Value::Integer(1), TypeAffinity::Text, Some(text(b"1"))),
+            (text(b"abc"), TypeAffinity::Text, Some(text(b"abc"))),
+            (blob(b"abc"), TypeAffinity::Text, None),
+            (blob(b"abc"), TypeAffinity::Blob, Some(blob(b"abc"))),
+            (text(b"abc"), TypeAffinity::Blob, None),
+            (Value::Integer(1), TypeAffinity::Blob, None),
+        ] {
+            assert_eq!(
+                value.clone().apply_strict(datatype),
+                expected,
+                "{:?} {:?}",
+                value,
+                datatype
+            );
+        }
+    }
+
+    #[test]
+    fn test_force_numeric() {
+        for (text, expected) in [
+            ("12", Value::Integer(12)),
+            (" -12 ", Value::Integer(-12)),
+            ("12abc", Value::Integer(12)),
+            ("3.0", Value::Real(3.0)),
+            ("3.", Value::Real(3.0)),
+            (".5", Value::Real(0.5)),
+            ("-0.0", Value::Real(0.0)),
+            ("1e2", Value::Real(100.0)),
+            ("12e3x", Value::Real(12000.0)),
+            ("1e", Value::Integer(1)),
+            ("9223372036854775808", Value::Real(9223372036854775808.0)),
+            ("", Value::Integer(0)),
+            (".", Value::Integer(0)),
+            ("abc", Value::Integer(0)),
+        ] {
+            assert_eq!(
+                Value::Text(text.as_bytes().into()).force_numeric(),
+                expected,
+                "{:?}",
+                text
+            );
+        }
+        assert_eq!(
+            Value::Blob(b"3.0".as_slice().into()).force_numeric(),
+            Value::Real(3.0)
+        );
+        assert_eq!(Value::Real(3.0).force_numeric(), Value::Real(3.0));
+        assert_eq!(Value::Integer(3).force_numeric(), Value::Integer(3));
+    }
+
     #[test]
     fn test_force_apply_type_affinity_numeric() {
         assert_eq!(
@@ -1242,4 +1509,31 @@ mod tests {
             Value::Blob(b"12345".as_slice().into())
         );
     }
+
+    #[test]
+    fn test_as_real() {
+        assert_eq!(Value::Integer(-3).as_real(), -3.0);
+        assert_eq!(Value::Real(1.5).as_real(), 1.5);
+        assert_eq!(Value::Text(b" 1.5abc".as_slice().into()).as_real(), 1.5);
+        assert_eq!(Value::Text(b"abc".as_slice().into()).as_real(), 0.0);
+        assert_eq!(Value::Blob(b"2e1".as_slice().into()).as_real(), 20.0);
+    }
+
+    #[test]
+    fn test_as_bool() {
+        assert!(Value::Integer(1).as_bool());
+        assert!(Value::Integer(-1).as_bool());
+        assert!(!Value::Integer(0).as_bool());
+        assert!(Value::Real(0.1).as_bool());
+        assert!(!Value::Real(0.0).as_bool());
+        assert!(Value::Text(b"1".as_slice().into()).as_bool());
+        assert!(Value::Text(b"0.5".as_slice().into()).as_bool());
+        assert!(Value::Text(b" 1abc".as_slice().into()).as_bool());
+        assert!(!Value::Text(b"0".as_slice().into()).as_bool());
+        assert!(!Value::Text(b"0.0".as_slice().into()).as_bool());
+        assert!(!Value::Text(b"abc".as_slice().into()).as_bool());
+        assert!(!Value::Text(b"".as_slice().into()).as_bool());
+        assert!(Value::Blob(b"2".as_slice().into()).as_bool());
+        assert!(!Value::Blob(b"\x01".as_slice().into()).as_bool());
+    }
 }
@@ -27,6 +27,50 @@ pub fn create_sqlite_database(queries: &[&str]) -> NamedTempFile {
     file
 }
 
+/// Create a database whose pages have `reserved` bytes at the tail which
+/// SQLite does not use (e.g. for encryption extensions).
+#[allow(dead_code)]
+pub fn create_sqlite_database_with_reserved_bytes(
+    pagesize: u32,
+    reserved: u8,
+    queries: &[&str],
+) -> NamedTempFile {
+    let file = NamedTempFile::new().unwrap();
+    let usable_size = pagesize - reserved as u32;
+    let mut page1 = vec![0; pagesize as usize];
+    page1[..16].copy_from_slice(b"SQLite format 3\0");
+    // 65536 is stored as 1.
+    page1[16..18].copy_from_slice(&(pagesize as u16 | (pagesize >> 16) as u16).to_be_bytes());
+    page1[18] = 1;
+    page1[19] = 1;
+    page1[20] = reserved;
+    page1[21] = 64;
+    page1[22] = 32;
+    page1[23] = 32;
+    // File change counter.
+    page1[24..28].copy_from_slice(&1_u32.to_be_bytes());
+    // Number of pages.
+    page1[28..32].copy_from_slice(&1_u32.to_be_bytes());
+    // Schema format number.
+    page1[44..48].copy_from_slice(&4_u32.to_be_bytes());
+    // UTF-8.
+    page1[56..60].copy_from_slice(&1_u32.to_be_bytes());
+    // Version valid for.
+    page1[92..96].copy_from_slice(&1_u32.to_be_bytes());
+    page1[96..100].copy_from_slice(&3039000_u32.to_be_bytes());
+    // The empty table leaf page of sqlite_schema.
+    page1[100] = 0x0d;
+    page1[105..107].copy_from_slice(&(usable_size as u16).to_be_bytes());
+    std::fs::write(file.path(), page1).unwrap();
+
+    let conn = rusqlite::Connection::open(file.path()).unwrap();
+    for query in queries {
+        conn.execute(query, []).unwrap();
+    }
+    conn.close().unwrap();
+    file
+}
+
 #[allow(dead_code)]
 pub fn load_rowids(conn: &Connection, query: &str) -> Vec<i64> {
     let stmt = conn.prepare(query).unwrap();
@@ -55,6 +99,18 @@ pub fn load_test_rowids(conn: &rusqlite::Connection, query: &str) -> Vec<i64> {
     results
 }
 
+/// Assert that sqlite3 reports no corruption of the database file.
+#[allow(dead_code)]
+pub fn assert_integrity_check(test_conn: &rusqlite::Connection) {
+    let mut stmt = test_conn.prepare("PRAGMA integrity_check;").unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        results.push(row.get::<_, String>(0).unwrap());
+    }
+    assert_eq!(results, vec!["ok".to_string()]);
+}
+
 #[macro_export]
 macro_rules! assert_same_result_prsqlite {
     ($rows:ident, $result:expr, $msg:expr) => {
@@ -101,3 +157,41 @@ pub fn assert_same_results(
     }
     assert!(rows.next_row().unwrap().is_none());
 }
+
+/// Assert that prsqlite returns the same rows as sqlite3 in the same order.
+#[allow(dead_code)]
+pub fn assert_same_results_as_sqlite(
+    query: &str,
+    test_conn: &rusqlite::Connection,
+    conn: &Connection,
+) {
+    let mut test_stmt = test_conn.prepare(query).unwrap();
+    let n_columns = test_stmt.column_count();
+    let mut test_rows = test_stmt.query([]).unwrap();
+    let mut expected = Vec::new();
+    while let Some(row) = test_rows.next().unwrap() {
+        let mut columns = Vec::with_capacity(n_columns);
+        for i in 0..n_columns {
+            columns.push(match row.get::<_, rusqlite::types::Value>(i).unwrap() {
+                rusqlite::types::Value::Null => None,
+                rusqlite::types::Value::Integer(v) => Some(Value::Integer(v)),
+                rusqlite::types::Value::Real(v) => Some(Value::Real(v)),
+                rusqlite::types::Value::Text(v) => Some(Value::Text(v.into_bytes().into())),
+                rusqlite::types::Value::Blob(v) => Some(Value::Blob(v.into())),
+            });
+        }
+        expected.push(columns);
+    }
+
+    let stmt = conn.prepare(query).unwrap();
+    let mut rows = stmt.query().unwrap();
+    for (i, e) in expected.iter().enumerate() {
+        let row = rows.next_row().unwrap().unwrap();
+        let columns = row.parse().unwrap();
+        assert_eq!(columns.len(), n_columns, "i: {i}, query: {query}");
+        for (j, e) in e.iter().enumerate() {
+            assert_eq!(columns.get(j), e.as_ref(), "i: {i}, j: {j}, query: {query}");
+        }
+    }
+    assert!(rows.next_row().unwrap().is_none(), "query: {query}");
+}
@@ -0,0 +1,510 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::AccessMethod;
+use prsqlite::Connection;
+use prsqlite::Error;
+
+#[test]
+fn test_create_index() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2, col3);",
+    ];
+    let insert_queries = (0..1000)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2, col3) VALUES ({}, {}, x'{}');",
+                i % 10,
+                (i * 7919) % 1000,
+                "11".repeat(i % 50)
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("CREATE INDEX index1 ON example(col1, col2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    // Queries using the index return the same rows as full scans.
+    for i in 0..11 {
+        let mut expected = load_rowids(
+            &conn,
+            &format!("SELECT rowid FROM example WHERE +col1 = {};", i),
+        );
+        expected.sort();
+        let mut rowids = load_rowids(
+            &conn,
+            &format!("SELECT rowid FROM example WHERE col1 = {};", i),
+        );
+        rowids.sort();
+        assert_eq!(rowids, expected, "{}", i);
+    }
+
+    // New rows are added to the index.
+    let stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (3, 0);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT col1, col2 FROM example WHERE col1 = 3 ORDER BY col2;",
+        "SELECT col1, col2 FROM example WHERE col1 > 7 ORDER BY col1, col2;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT rowid FROM example INDEXED BY index1 WHERE col1 = 3 AND col2 = 0;"
+        ),
+        vec![1001]
+    );
+}
+
+#[test]
+fn test_create_index_collate() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 text collate nocase);",
+        "INSERT INTO example(col1, col2) VALUES ('a', 'a');",
+        "INSERT INTO example(col1, col2) VALUES ('A', 'A');",
+        "INSERT INTO example(col1, col2) VALUES ('b', 'b ');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        "CREATE INDEX index1 ON example(col1 COLLATE NOCASE);",
+        "CREATE INDEX index2 ON example(col2 collate rtrim, col1);",
+    ] {
+        assert_eq!(conn.prepare(query).unwrap().execute().unwrap(), 0);
+    }
+
+    // The indexes do not match the collations of the comparisons.
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col1 = 'a';"),
+        vec![1]
+    );
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col2 = 'b';"),
+        Vec::<i64>::new()
+    );
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col2 = 'A';"),
+        vec![1, 2]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT rowid FROM example INDEXED BY index1 WHERE col1 = 'a' COLLATE NOCASE;"
+        ),
+        vec![1, 2]
+    );
+}
+
+#[test]
+fn test_create_index_desc() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+    ];
+    let insert_queries = (0..500)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2) VALUES ({}, {});",
+                i % 20,
+                (i * 7919) % 500
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("CREATE INDEX index1 ON example(col1 DESC, col2 ASC);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    for query in [
+        "INSERT INTO example(col1, col2) VALUES (3, 1000);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 1001);",
+        "UPDATE example SET col1 = 100 WHERE col2 < 10;",
+        "DELETE FROM example WHERE col1 = 7;",
+    ] {
+        conn.prepare(query).unwrap().execute().unwrap();
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT col1, col2 FROM example WHERE col1 = 3 ORDER BY col2;",
+        "SELECT col1, col2 FROM example WHERE col1 > 15 ORDER BY col1, col2;",
+        "SELECT col1, col2 FROM example WHERE col1 IS NULL;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT rowid FROM example INDEXED BY index1 WHERE col1 = 3 AND col2 = 1000;"
+        ),
+        vec![501]
+    );
+
+    // VACUUM keeps the order of the index.
+    conn.prepare("VACUUM;").unwrap().execute().unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite(
+        "SELECT col1, col2 FROM example WHERE col1 < 5 ORDER BY col1, col2;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_create_partial_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1) WHERE col2 > 10;",
+        "INSERT INTO example(col1, col2) VALUES (1, 5);",
+        "INSERT INTO example(col1, col2) VALUES (1, 15);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        // Rows not matching the WHERE clause are not indexed.
+        "INSERT INTO example(col1, col2) VALUES (2, 1);",
+        "INSERT INTO example(col1, col2) VALUES (2, 11);",
+        "INSERT INTO example(col1, col2) VALUES (3, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (3, 20);",
+        // Move rows into and out of the index.
+        "UPDATE example SET col2 = 100 WHERE rowid = 1;",
+        "UPDATE example SET col2 = 0 WHERE rowid = 2;",
+        "UPDATE example SET col1 = 4 WHERE col1 = 3;",
+        "DELETE FROM example WHERE col1 = 2;",
+    ] {
+        conn.prepare(query).unwrap().execute().unwrap();
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        assert_integrity_check(&test_conn);
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT rowid FROM example INDEXED BY index1 WHERE col2 > 10 ORDER BY rowid;"
+        ),
+        vec![1, 6]
+    );
+    for query in [
+        "SELECT rowid, col1, col2 FROM example WHERE col1 = 4;",
+        "SELECT rowid, col1, col2 FROM example WHERE col1 = 4 AND col2 > 10;",
+        "SELECT rowid, col1, col2 FROM example WHERE col1 >= 1 AND col2 > 10;",
+        "PRAGMA index_list(example);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Only the existing rows matching the WHERE clause are indexed.
+    let stmt = conn
+        .prepare("CREATE INDEX index2 ON example(col2) WHERE col1 IS NOT NULL AND col1 < 4;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT rowid FROM example INDEXED BY index2 WHERE col1 < 4 ORDER BY rowid;"
+        ),
+        vec![1, 2]
+    );
+    for query in [
+        "SELECT rowid FROM example WHERE col2 = 100;",
+        "SELECT rowid FROM example WHERE col2 >= 0 AND col1 < 4;",
+        "SELECT rowid FROM example WHERE col2 = 20 AND col1 < 4;",
+        "SELECT rowid FROM example WHERE col2 = 20;",
+    ] {
+        let mut rowids = load_rowids(&conn, query);
+        rowids.sort();
+        let mut expected = load_test_rowids(&test_conn, query);
+        expected.sort();
+        assert_eq!(rowids, expected, "{}", query);
+    }
+
+    assert!(conn
+        .prepare("CREATE INDEX index3 ON example(col1) WHERE invalid = 1;")
+        .is_err());
+    assert!(conn
+        .prepare("CREATE INDEX index3 ON example(col1) WHERE col2 = ?;")
+        .is_err());
+}
+
+#[test]
+fn test_create_expression_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(abs(col1));",
+        "INSERT INTO example(col1, col2) VALUES (5, 1);",
+        "INSERT INTO example(col1, col2) VALUES (-5, 2);",
+        "INSERT INTO example(col1, col2) VALUES (3, 3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        "INSERT INTO example(col1, col2) VALUES (-3, 4);",
+        "INSERT INTO example(col1, col2) VALUES (5.0, 5);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 6);",
+        "UPDATE example SET col1 = -7 WHERE col2 = 3;",
+        "UPDATE example SET col1 = 5 WHERE col2 = 4;",
+        "DELETE FROM example WHERE col2 = 1;",
+    ] {
+        conn.prepare(query).unwrap().execute().unwrap();
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        assert_integrity_check(&test_conn);
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let root_page: u32 = test_conn
+        .query_row(
+            "SELECT rootpage FROM sqlite_schema WHERE name = 'index1';",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let plan = conn
+        .prepare("SELECT col2 FROM example WHERE abs(col1) = 5;")
+        .unwrap()
+        .explain()
+        .unwrap();
+    assert_eq!(
+        plan.tables[0].access,
+        AccessMethod::IndexSeek {
+            index_name: "index1".to_owned(),
+            index_root_page: root_page,
+            n_ranges: 1,
+            covering: false,
+        }
+    );
+    for query in [
+        "SELECT col2 FROM example WHERE abs(col1) = 5 ORDER BY col2;",
+        "SELECT col2 FROM example WHERE 5 = abs(col1) ORDER BY col2;",
+        "SELECT col2 FROM example WHERE abs(col1) > 4 ORDER BY col2;",
+        "SELECT col2 FROM example WHERE abs(col1) = '5' ORDER BY col2;",
+        "PRAGMA index_info(index1);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // The index is built for the existing rows.
+    let stmt = conn
+        .prepare("CREATE INDEX index2 ON example(col2 * 2, col1);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite(
+        "SELECT col1 FROM example WHERE col2 * 2 = 8;",
+        &test_conn,
+        &conn,
+    );
+
+    assert!(conn
+        .prepare("CREATE INDEX index3 ON example(abs(invalid));")
+        .is_err());
+    assert!(conn
+        .prepare("CREATE INDEX index3 ON example(abs(?));")
+        .is_err());
+}
+
+#[test]
+fn test_load_unsupported_expression_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE TABLE example2(col1, col2);",
+        "CREATE INDEX index1 ON example(lower(col1));",
+        "CREATE INDEX index2 ON example2(col1, substr(col2, 1, 1));",
+        "CREATE INDEX index3 ON example2(col2) WHERE length(col1) > 1;",
+        "INSERT INTO example(col1, col2) VALUES ('Abc', 1);",
+        "INSERT INTO example(col1, col2) VALUES ('ABC', 2);",
+        "INSERT INTO example(col1, col2) VALUES ('def', 3);",
+        "INSERT INTO example2(col1, col2) VALUES ('x', 'abc');",
+        "INSERT INTO example2(col1, col2) VALUES ('yy', 'def');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // The index on lower() is used.
+    let root_page: u32 = test_conn
+        .query_row(
+            "SELECT rootpage FROM sqlite_schema WHERE name = 'index1';",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let plan = conn
+        .prepare("SELECT col2 FROM example WHERE lower(col1) = 'abc';")
+        .unwrap()
+        .explain()
+        .unwrap();
+    assert_eq!(
+        plan.tables[0].access,
+        AccessMethod::IndexSeek {
+            index_name: "index1".to_owned(),
+            index_root_page: root_page,
+            n_ranges: 1,
+            covering: false,
+        }
+    );
+    for query in [
+        "SELECT col2 FROM example WHERE lower(col1) = 'abc' ORDER BY col2;",
+        "SELECT col2 FROM example WHERE upper(col1) = 'DEF';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    conn.prepare("INSERT INTO example(col1, col2) VALUES ('aBC', 4);")
+        .unwrap()
+        .execute()
+        .unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite(
+        "SELECT col2 FROM example WHERE lower(col1) = 'abc' ORDER BY col2;",
+        &test_conn,
+        &conn,
+    );
+
+    // The indexes with unsupported functions are not used but the table is
+    // still readable.
+    let plan = conn
+        .prepare("SELECT col2 FROM example2 WHERE col1 = 'x';")
+        .unwrap()
+        .explain()
+        .unwrap();
+    assert_eq!(plan.tables[0].access, AccessMethod::FullScan);
+    for query in [
+        "SELECT col1, col2 FROM example2;",
+        "SELECT col2 FROM example2 WHERE col1 = 'x';",
+        "SELECT col1 FROM example2 WHERE col2 = 'def';",
+        "PRAGMA index_list(example2);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    for query in [
+        "INSERT INTO example2(col1, col2) VALUES ('z', 'ghi');",
+        "UPDATE example2 SET col2 = 'ghi';",
+        "DELETE FROM example2 WHERE col1 = 'x';",
+    ] {
+        assert!(
+            matches!(conn.prepare(query), Err(Error::Unsupported(_))),
+            "{}",
+            query
+        );
+    }
+    assert!(conn
+        .prepare("CREATE INDEX index4 ON example2(substr(col2, 1, 1));")
+        .is_err());
+}
+
+#[test]
+fn test_create_unique_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (1, 1);",
+        "INSERT INTO example(col1, col2) VALUES (1, 2);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 3);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("CREATE UNIQUE INDEX index1 ON example(col1);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    assert!(conn.prepare("SELECT * FROM example;").is_ok());
+
+    // NULLs are distinct from each other.
+    let stmt = conn
+        .prepare("CREATE UNIQUE INDEX index2 ON example(col1, col2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    let stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (1, 2);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert!(test_conn
+        .prepare("SELECT * FROM example INDEXED BY index1;")
+        .is_err());
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        vec![1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn test_create_index_fail() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE TABLE example2(col1 primary key, col2) WITHOUT ROWID;",
+        "CREATE TABLE example3(id integer primary key autoincrement);",
+        "CREATE INDEX index1 ON example(col1);",
+        "ANALYZE;",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // The names conflict with the existing table or index.
+    for query in [
+        "CREATE INDEX INDEX1 ON example(col2);",
+        "CREATE INDEX example ON example(col2);",
+    ] {
+        let stmt = conn.prepare(query).unwrap();
+        assert!(stmt.execute().is_err(), "{}", query);
+    }
+    for query in [
+        "CREATE INDEX index2 ON invalid(col1);",
+        "CREATE INDEX index2 ON example(invalid);",
+        "CREATE INDEX index2 ON example(col1 COLLATE invalid);",
+        "CREATE INDEX index2 ON sqlite_schema(name);",
+        "CREATE INDEX index2 ON sqlite_sequence(name);",
+        "CREATE INDEX index2 ON SQLITE_STAT1(tbl);",
+        "CREATE INDEX sqlite_index ON example(col1);",
+    ] {
+        assert!(conn.prepare(query).is_err(), "{}", query);
+    }
+    assert!(matches!(
+        conn.prepare("CREATE INDEX index2 ON example2(col2);"),
+        Err(Error::Unsupported(_))
+    ));
+}
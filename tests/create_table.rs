@@ -0,0 +1,290 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::Value;
+
+#[test]
+fn test_create_table() {
+    let conn = Connection::open_memory().unwrap();
+
+    let stmt = conn
+        .prepare("CREATE TABLE example(id integer primary key, col1 text, col2 integer);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    let stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (1, '2'), ('a', 'b');")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let stmt = conn.prepare("SELECT * FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(
+        rows,
+        [
+            Some(&Value::Integer(1)),
+            Some(&Value::Text(b"1".as_slice().into())),
+            Some(&Value::Integer(2)),
+        ],
+        "row 1"
+    );
+    assert_same_result_prsqlite!(
+        rows,
+        [
+            Some(&Value::Integer(2)),
+            Some(&Value::Text(b"a".as_slice().into())),
+            Some(&Value::Text(b"b".as_slice().into())),
+        ],
+        "row 2"
+    );
+    assert!(rows.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_create_table_file() {
+    let file = create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("CREATE TABLE example2 (col1 real, [col2] unique, col3, UNIQUE(col3, col1));")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    let stmt = conn
+        .prepare("create table example3(col1, col2 primary key) without rowid;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    let mut stmt = conn
+        .prepare("INSERT INTO example2(col1, col2, col3) VALUES (?, ?, ?);")
+        .unwrap();
+    for i in 0..100 {
+        stmt.bind(1, Some(Value::Integer(i))).unwrap();
+        stmt.bind(2, Some(Value::Integer(i * 2))).unwrap();
+        stmt.bind(3, Some(Value::Blob(vec![i as u8; 20].into())))
+            .unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+    }
+    let stmt = conn
+        .prepare("INSERT INTO example2(col1, col2) VALUES (1, 2);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Text(b"table".as_slice().into())),
+                Some(&Value::Text(b"example2".as_slice().into())),
+                Some(&Value::Integer(3)),
+                Some(&Value::Text(
+                    b"CREATE TABLE example2 (col1 real, [col2] unique, col3, UNIQUE(col3, col1))"
+                        .as_slice()
+                        .into(),
+                )),
+            ],
+            &[
+                Some(&Value::Text(b"index".as_slice().into())),
+                Some(&Value::Text(
+                    b"sqlite_autoindex_example2_1".as_slice().into(),
+                )),
+                Some(&Value::Integer(4)),
+                None,
+            ],
+            &[
+                Some(&Value::Text(b"index".as_slice().into())),
+                Some(&Value::Text(
+                    b"sqlite_autoindex_example2_2".as_slice().into(),
+                )),
+                Some(&Value::Integer(5)),
+                None,
+            ],
+            &[
+                Some(&Value::Text(b"table".as_slice().into())),
+                Some(&Value::Text(b"example3".as_slice().into())),
+                Some(&Value::Integer(6)),
+                Some(&Value::Text(
+                    b"create table example3(col1, col2 primary key) without rowid"
+                        .as_slice()
+                        .into(),
+                )),
+            ],
+        ],
+        "SELECT type, name, rootpage, sql FROM sqlite_schema WHERE rowid > 1;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results_as_sqlite(
+        "SELECT col1, col2, col3 FROM example2 WHERE col2 >= 150;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results_as_sqlite("SELECT * FROM example3;", &test_conn, &conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example2 WHERE col2 = 10;"),
+        vec![6]
+    );
+}
+
+#[test]
+fn test_create_table_transaction() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("CREATE TABLE example2(col);").unwrap();
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    // The new table is visible in the transaction.
+    assert!(load_rowids(&conn, "SELECT rowid FROM example2;").is_empty());
+    txn.rollback();
+    assert!(conn.prepare("SELECT * FROM example2;").is_err());
+
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert!(conn.prepare("SELECT * FROM example2;").is_ok());
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert!(test_conn.prepare("SELECT * FROM example2;").is_ok());
+}
+
+#[test]
+fn test_create_table_fail() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "CREATE INDEX index1 ON example(col);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // The names conflict with the existing table or index.
+    for query in ["CREATE TABLE Example(col);", "CREATE TABLE index1(col);"] {
+        let stmt = conn.prepare(query).unwrap();
+        assert!(stmt.execute().is_err(), "{}", query);
+    }
+    for query in [
+        "CREATE TABLE example2;",
+        "CREATE TABLE example2(col, col);",
+        "CREATE TABLE example2(col1 primary key, col2 primary key);",
+        "CREATE TABLE example2(col) WITHOUT ROWID;",
+        "CREATE TABLE example2(col) STRICT;",
+        "CREATE TABLE example2(col VARCHAR(10)) STRICT;",
+        "CREATE TABLE sqlite_example(col);",
+        "CREATE TABLE \"SQLITE_SCHEMA\"(col);",
+    ] {
+        assert!(conn.prepare(query).is_err(), "{}", query);
+    }
+
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    let stmt = conn.prepare("CREATE TABLE example2(col);").unwrap();
+    assert!(stmt.execute().is_err());
+}
+
+#[test]
+fn test_schema_changed() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let select_stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+
+    // Rolled back schema changes do not invalidate the statements.
+    let txn = conn.begin().unwrap();
+    let stmt = conn.prepare("CREATE TABLE example2(col);").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    txn.rollback();
+    assert!(select_stmt.query().is_ok());
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+
+    let stmt = conn
+        .prepare("CREATE INDEX index1 ON example(col);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert!(select_stmt.query().is_err());
+    assert!(matches!(insert_stmt.execute(), Err(Error::SchemaChanged)));
+
+    // Statements prepared again see the new schema.
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (3);")
+        .unwrap();
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col >= 2;"),
+        vec![2, 3]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "PRAGMA schema_version;"),
+        vec![2]
+    );
+}
+
+#[test]
+fn test_schema_changed_by_other_connection() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let other_conn = Connection::open(file.path()).unwrap();
+    let select_stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1]);
+
+    // Data changes are visible without invalidating the statements.
+    other_conn
+        .execute_batch("INSERT INTO example(col) VALUES (3);")
+        .unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1, 2]);
+    assert!(select_stmt.query().is_ok());
+
+    other_conn
+        .execute_batch(
+            "CREATE TABLE example2(col); INSERT INTO example2(col) VALUES (4); CREATE INDEX \
+             index1 ON example(col);",
+        )
+        .unwrap();
+    assert!(matches!(select_stmt.query(), Err(Error::SchemaChanged)));
+    assert!(matches!(insert_stmt.execute(), Err(Error::SchemaChanged)));
+
+    // Statements prepared again see the new schema.
+    let insert_stmt = conn
+        .prepare("INSERT INTO example2(col) VALUES (5);")
+        .unwrap();
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example2;"),
+        vec![1, 2]
+    );
+    assert_eq!(
+        load_rowids(&other_conn, "SELECT rowid FROM example2;"),
+        vec![1, 2]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+}
@@ -360,3 +360,163 @@ fn test_delete_partial() {
         &conn,
     );
 }
+
+#[test]
+fn test_delete_partial_multiple_level() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2, col1);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, x'{}');",
+            i % 10,
+            format!("{:04}", i).repeat(20)
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Delete using index1.
+    let stmt = conn.prepare("DELETE FROM example WHERE col1 = 3;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 100);
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert_integrity_check(&rusqlite::Connection::open(file.path()).unwrap());
+
+    // Delete using index1 with IN.
+    let stmt = conn
+        .prepare("DELETE FROM example WHERE col1 IN (5, 3, 7, 5);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 200);
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert_integrity_check(&rusqlite::Connection::open(file.path()).unwrap());
+
+    // Delete with full scan.
+    let stmt = conn
+        .prepare("DELETE FROM example WHERE rowid >= 500;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 351);
+    assert_eq!(stmt.execute().unwrap(), 0);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+
+    // sqlite3 may scan index1 instead of the table.
+    let mut expected = load_test_rowids(&test_conn, "SELECT rowid FROM example;");
+    expected.sort();
+    assert_eq!(expected.len(), 349);
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), expected);
+    for i in 0..10 {
+        let query = format!("SELECT rowid FROM example WHERE col1 = {};", i);
+        assert_eq!(
+            load_rowids(&conn, &query),
+            load_test_rowids(&test_conn, &query),
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_delete_insert_reuse_free_pages() {
+    let file = create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn
+        .prepare(&format!(
+            "INSERT INTO example(col) VALUES (x'{}');",
+            "11".repeat(100)
+        ))
+        .unwrap();
+    for _ in 0..1000 {
+        assert_eq!(insert_stmt.execute().unwrap(), 1);
+    }
+    let file_size = file.as_file().metadata().unwrap().len();
+    let delete_stmt = conn
+        .prepare("DELETE FROM example WHERE rowid > 500;")
+        .unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(delete_stmt.execute().unwrap(), 500);
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        let n_freelist_pages: i64 = test_conn
+            .query_row("PRAGMA freelist_count;", [], |row| row.get(0))
+            .unwrap();
+        assert!(n_freelist_pages > 0);
+        drop(test_conn);
+
+        // The new pages are allocated from the freelist.
+        for _ in 0..500 {
+            assert_eq!(insert_stmt.execute().unwrap(), 1);
+        }
+        assert_eq!(file.as_file().metadata().unwrap().len(), file_size);
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    let sql = "SELECT rowid FROM example;";
+    let expected = (1..=1000).collect::<Vec<_>>();
+    assert_eq!(load_rowids(&conn, sql), expected);
+    assert_eq!(load_test_rowids(&test_conn, sql), expected);
+}
+
+#[test]
+fn test_delete_reopen_reuse_free_pages() {
+    let file = create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
+    let insert_sql = format!("INSERT INTO example(col) VALUES (x'{}');", "11".repeat(100));
+    let freelist_count = || {
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        assert_integrity_check(&test_conn);
+        test_conn
+            .query_row("PRAGMA freelist_count;", [], |row| row.get::<_, i64>(0))
+            .unwrap()
+    };
+
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn.prepare(&insert_sql).unwrap();
+    for _ in 0..1000 {
+        assert_eq!(insert_stmt.execute().unwrap(), 1);
+    }
+    let file_size = file.as_file().metadata().unwrap().len();
+    assert_eq!(freelist_count(), 0);
+
+    // The freelist of the rolled back transaction is discarded.
+    let txn = conn.begin().unwrap();
+    let delete_stmt = conn
+        .prepare("DELETE FROM example WHERE rowid > 500;")
+        .unwrap();
+    assert_eq!(delete_stmt.execute().unwrap(), 500);
+    txn.rollback();
+    assert_eq!(freelist_count(), 0);
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(delete_stmt.execute().unwrap(), 500);
+    txn.commit().unwrap();
+    let n_freelist_pages = freelist_count();
+    assert!(n_freelist_pages > 0);
+    drop(insert_stmt);
+    drop(delete_stmt);
+    drop(conn);
+
+    // A new connection reads the freelist from the header and reuses the pages.
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn.prepare(&insert_sql).unwrap();
+    for _ in 0..100 {
+        assert_eq!(insert_stmt.execute().unwrap(), 1);
+    }
+    let n_reused = n_freelist_pages - freelist_count();
+    assert!(n_reused > 0);
+    for _ in 0..400 {
+        assert_eq!(insert_stmt.execute().unwrap(), 1);
+    }
+    assert_eq!(file.as_file().metadata().unwrap().len(), file_size);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        (1..=1000).collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,181 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::Value;
+
+fn create_database() -> tempfile::NamedTempFile {
+    create_sqlite_database(&[
+        "CREATE TABLE artist(id INTEGER PRIMARY KEY, name TEXT UNIQUE);",
+        "CREATE TABLE track(id INTEGER PRIMARY KEY, artist_id INTEGER REFERENCES artist, artist_name TEXT, FOREIGN KEY (artist_name) REFERENCES artist(name));",
+        "INSERT INTO artist(id, name) VALUES (1, 'a');",
+        "INSERT INTO artist(id, name) VALUES (2, 'b');",
+        "INSERT INTO artist(id, name) VALUES (3, 'c');",
+        "INSERT INTO track(id, artist_id, artist_name) VALUES (1, 1, NULL);",
+        "INSERT INTO track(id, artist_id, artist_name) VALUES (2, NULL, 'b');",
+    ])
+}
+
+#[test]
+fn test_foreign_keys_pragma() {
+    let file = create_database();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (sql, expected) in [
+        ("PRAGMA foreign_keys;", 0),
+        ("PRAGMA foreign_keys = ON;", 1),
+        ("PRAGMA foreign_keys = off;", 0),
+        ("PRAGMA foreign_keys = 1;", 1),
+        ("PRAGMA foreign_keys = 'no';", 0),
+        ("PRAGMA foreign_keys = true;", 1),
+    ] {
+        if !sql.contains('=') {
+            assert_eq!(load_rowids(&conn, sql), vec![expected]);
+            continue;
+        }
+        conn.prepare(sql).unwrap().execute().unwrap();
+        assert_eq!(load_rowids(&conn, "PRAGMA foreign_keys;"), vec![expected]);
+    }
+}
+
+#[test]
+fn test_foreign_key_insert() {
+    let file = create_database();
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Foreign keys are not enforced by default.
+    let stmt = conn
+        .prepare("INSERT INTO track(artist_id) VALUES (10);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+    for sql in [
+        "INSERT INTO track(artist_id) VALUES (10);",
+        "INSERT INTO track(artist_id) VALUES ('x');",
+        "INSERT INTO track(artist_name) VALUES ('d');",
+        "INSERT INTO track(artist_name) VALUES ('A');",
+        "INSERT INTO track(artist_id) VALUES (1), (4);",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::ForeignKeyConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+    // The affinity of the parent key is applied. NULL refers to no parent.
+    let stmt = conn
+        .prepare("INSERT INTO track(id, artist_id, artist_name) VALUES (10, '3', 'a'), (11, NULL, NULL);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(3)), Some(&Value::Integer(10)), None],
+            &[
+                Some(&Value::Integer(10)),
+                Some(&Value::Integer(3)),
+                Some(&Value::Text(b"a"[..].into())),
+            ],
+            &[Some(&Value::Integer(11)), None, None],
+        ],
+        "SELECT * FROM track WHERE id >= 3;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_foreign_key_delete_parent() {
+    let file = create_database();
+    let conn = Connection::open(file.path()).unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+    for sql in [
+        "DELETE FROM artist WHERE id = 1;",
+        "DELETE FROM artist WHERE name = 'b';",
+        "DELETE FROM artist;",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::ForeignKeyConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+    let stmt = conn.prepare("DELETE FROM artist WHERE id = 3;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    // The parent is deletable after the children are deleted.
+    conn.execute_batch("DELETE FROM track; DELETE FROM artist;")
+        .unwrap();
+    assert!(load_rowids(&conn, "SELECT id FROM artist;").is_empty());
+}
+
+#[test]
+fn test_foreign_key_update() {
+    let file = create_database();
+    let conn = Connection::open(file.path()).unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+    for sql in [
+        "UPDATE track SET artist_id = 10 WHERE id = 1;",
+        "UPDATE artist SET name = 'd' WHERE id = 2;",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::ForeignKeyConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+    for sql in [
+        "UPDATE track SET artist_id = 3 WHERE id = 1;",
+        // The parent key is not changed.
+        "UPDATE artist SET name = 'b' WHERE id = 2;",
+        "UPDATE artist SET name = 'd' WHERE id = 1;",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1, "{}", sql);
+    }
+}
+
+#[test]
+fn test_foreign_key_mismatch() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE parent(a, b);",
+        "CREATE TABLE child(x REFERENCES parent(a), y REFERENCES unknown);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+    // The parent key has no UNIQUE index.
+    let stmt = conn.prepare("INSERT INTO child(x) VALUES (1);").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::Other(_))));
+    let stmt = conn.prepare("INSERT INTO child(y) VALUES (1);").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::Other(_))));
+    // NULL child keys are not checked.
+    let stmt = conn.prepare("INSERT INTO child(x) VALUES (NULL);").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn.prepare("INSERT INTO parent(a) VALUES (1);").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn.prepare("DELETE FROM parent;").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::Other(_))));
+}
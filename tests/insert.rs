@@ -82,6 +82,123 @@ fn test_insert() {
     )
 }
 
+#[test]
+fn test_insert_literals() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col1, col2);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT INTO example (col1, col2) VALUES ('it''s', x'aBcDeF'), ('''''', X''), ('\"a\"', x'00FF');")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Text(b"it's".as_slice().into())),
+                Some(&Value::Blob([0xab, 0xcd, 0xef].as_slice().into())),
+            ],
+            &[
+                Some(&Value::Text(b"''".as_slice().into())),
+                Some(&Value::Blob(b"".as_slice().into())),
+            ],
+            &[
+                Some(&Value::Text(b"\"a\"".as_slice().into())),
+                Some(&Value::Blob([0x00, 0xff].as_slice().into())),
+            ],
+        ],
+        "SELECT col1, col2 FROM example;",
+        &test_conn,
+        &conn,
+    );
+    for query in [
+        "SELECT rowid FROM example WHERE col1 = 'it''s';",
+        "SELECT rowid FROM example WHERE col1 = '''''';",
+        "SELECT rowid FROM example WHERE col2 = X'ABCDEF';",
+        "SELECT rowid FROM example WHERE col2 IN (x'00ff', x'');",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Invalid literals are parse errors.
+    for query in [
+        "INSERT INTO example (col1) VALUES (x'abc');",
+        "INSERT INTO example (col1) VALUES (x'0g');",
+        "INSERT INTO example (col1) VALUES ('it''s);",
+        "SELECT * FROM example WHERE col2 = x'1';",
+    ] {
+        assert!(
+            matches!(conn.prepare(query), Err(Error::Parse(_))),
+            "{}",
+            query
+        );
+        assert!(test_conn.prepare(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn test_insert_unary_operators() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 real, col3 integer, col4 text);",
+        "CREATE TABLE example2(id integer primary key, col);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT INTO example VALUES (-1, +2.5, NOT 0, -'3'), (- -9223372036854775808, -2, NOT 'a', ~5), (-0.5, -0x10, NOT NULL, -x'31');")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+    // The value for the rowid alias column is the rowid.
+    let stmt = conn
+        .prepare("INSERT INTO example2 VALUES (-5, -1.5), (NULL, +'a');")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(-1)),
+                Some(&Value::Real(2.5)),
+                Some(&Value::Integer(1)),
+                Some(&Value::Text(b"-3".as_slice().into())),
+            ],
+            &[
+                Some(&Value::Real(9223372036854775808.0)),
+                Some(&Value::Real(-2.0)),
+                Some(&Value::Integer(1)),
+                Some(&Value::Text(b"-6".as_slice().into())),
+            ],
+            &[
+                Some(&Value::Real(-0.5)),
+                Some(&Value::Real(-16.0)),
+                None,
+                Some(&Value::Text(b"-1".as_slice().into())),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(-5)), Some(&Value::Real(-1.5))],
+            &[
+                Some(&Value::Integer(-4)),
+                Some(&Value::Text(b"a".as_slice().into())),
+            ],
+        ],
+        "SELECT id, col FROM example2;",
+        &test_conn,
+        &conn,
+    );
+
+    assert!(conn
+        .prepare("INSERT INTO example VALUES (1, 2, 3);")
+        .is_err());
+}
+
 #[test]
 fn test_insert_per_pagesize() {
     // TODO: Other page sizes 512 ~ 65536.
@@ -108,6 +225,91 @@ fn test_insert_per_pagesize() {
     }
 }
 
+#[test]
+fn test_insert_reserved_bytes() {
+    for (pagesize, reserved) in [(512, 32), (4096, 1), (4096, 255), (65536, 100)] {
+        let file = create_sqlite_database_with_reserved_bytes(
+            pagesize,
+            reserved,
+            &[
+                "CREATE TABLE example(col1, col2);",
+                "CREATE INDEX index1 ON example(col2);",
+                "INSERT INTO example(col1, col2) VALUES (1, 'abc'), (2, zeroblob(100000));",
+            ],
+        );
+        // Mark the reserved bytes of the pages written by SQLite.
+        let mut buf = std::fs::read(file.path()).unwrap();
+        for page in buf.chunks_mut(pagesize as usize) {
+            page[(pagesize - reserved as u32) as usize..].fill(0xab);
+        }
+        std::fs::write(file.path(), &buf).unwrap();
+
+        let conn = Connection::open(file.path()).unwrap();
+        let msg = format!("pagesize: {pagesize}, reserved: {reserved}");
+        assert_eq!(
+            load_rowids(&conn, "SELECT col1 FROM example WHERE col2 = 'abc';"),
+            vec![1],
+            "{}",
+            msg
+        );
+
+        let long_text = "a".repeat(pagesize as usize * 3);
+        let stmt = conn
+            .prepare(&format!(
+                "INSERT INTO example(col1, col2) VALUES (3, '{long_text}');"
+            ))
+            .unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+        let mut stmt = conn
+            .prepare(
+                "INSERT INTO example(col1, col2) VALUES (?, 'hello world hello world hello world');",
+            )
+            .unwrap();
+        let txn = conn.begin().unwrap();
+        for i in 4..1000 {
+            stmt.bind(1, Some(Value::Integer(i))).unwrap();
+            assert_eq!(stmt.execute().unwrap(), 1);
+        }
+        txn.commit().unwrap();
+        assert_eq!(
+            conn.prepare("DELETE FROM example WHERE col1 % 3 = 0;")
+                .unwrap()
+                .execute()
+                .unwrap(),
+            333
+        );
+        assert_eq!(
+            conn.prepare("UPDATE example SET col2 = col1 || col2 WHERE col1 % 3 = 1;")
+                .unwrap()
+                .execute()
+                .unwrap(),
+            333
+        );
+
+        // Each page keeps the reserved bytes written by SQLite, or has them zeroed
+        // as a page newly added to the file.
+        let buf = std::fs::read(file.path()).unwrap();
+        for (i, page) in buf.chunks(pagesize as usize).enumerate() {
+            let reserved_bytes = &page[(pagesize - reserved as u32) as usize..];
+            assert!(
+                reserved_bytes.iter().all(|b| *b == 0xab) || reserved_bytes.iter().all(|b| *b == 0),
+                "page {}, {}",
+                i + 1,
+                msg
+            );
+        }
+
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        assert_integrity_check(&test_conn);
+        for query in [
+            "SELECT col1, col2 FROM example;",
+            "SELECT col1 FROM example WHERE col2 >= 'a' ORDER BY col2;",
+        ] {
+            assert_same_results_as_sqlite(query, &test_conn, &conn);
+        }
+    }
+}
+
 #[test]
 fn test_insert_with_rowid() {
     let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
@@ -153,6 +355,75 @@ fn test_insert_with_rowid() {
     )
 }
 
+#[test]
+fn test_insert_last_insert_rowid() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col unique);",
+        "INSERT INTO example(id, col) VALUES (5, 0);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(conn.last_insert_rowid(), 0);
+    assert_eq!(conn.changes(), 0);
+    assert_eq!(conn.total_changes(), 0);
+
+    let stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (1);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    assert_eq!(conn.last_insert_rowid(), 6);
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example WHERE col = 1;"),
+        vec![6]
+    );
+
+    let stmt = conn
+        .prepare("INSERT INTO example(id, col) VALUES (-3, 2), (100, 3);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+    assert_eq!(conn.last_insert_rowid(), 100);
+    assert_eq!(conn.changes(), 2);
+    assert_eq!(conn.total_changes(), 3);
+
+    let stmt = conn
+        .prepare("INSERT INTO example(rowid, col) VALUES (NULL, 4), (-5, 5);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+    assert_eq!(conn.last_insert_rowid(), -5);
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example WHERE col >= 4;"),
+        vec![101, -5]
+    );
+
+    // Ignored and failed inserts keep the value.
+    let stmt = conn
+        .prepare("INSERT OR IGNORE INTO example(col) VALUES (1);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert_eq!(conn.last_insert_rowid(), -5);
+    assert_eq!(conn.changes(), 0);
+    let stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (6), (1);")
+        .unwrap();
+    assert!(stmt.execute().is_err());
+    assert_eq!(conn.last_insert_rowid(), -5);
+    assert_eq!(conn.total_changes(), 5);
+
+    // UPDATE and DELETE change the number of changes only.
+    let stmt = conn
+        .prepare("UPDATE example SET col = col + 10 WHERE col < 3;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+    assert_eq!(conn.changes(), 3);
+    let stmt = conn.prepare("DELETE FROM example WHERE id > 0;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 4);
+    assert_eq!(conn.changes(), 4);
+    let stmt = conn.prepare("DELETE FROM example;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+    assert_eq!(conn.changes(), 2);
+    assert_eq!(conn.total_changes(), 14);
+    assert_eq!(conn.last_insert_rowid(), -5);
+}
+
 #[test]
 fn test_insert_into_existing_table() {
     let file = create_sqlite_database(&[
@@ -238,6 +509,312 @@ fn test_insert_rowid_conflict() {
     )
 }
 
+#[test]
+fn test_insert_many_auto_rowids() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example (rowid, col) VALUES (10, 0);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let n = 50000;
+    let query = format!(
+        "INSERT INTO example (col) VALUES {};",
+        (1..=n)
+            .map(|i| format!("({})", i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    assert_eq!(conn.prepare(&query).unwrap().execute().unwrap(), n);
+    let rowids = load_rowids(&conn, "SELECT rowid FROM example WHERE col > 0;");
+    assert_eq!(rowids, (11..11 + n as i64).collect::<Vec<_>>());
+
+    // Explicit rowids larger than the largest one move the next rowid.
+    let stmt = conn
+        .prepare("INSERT INTO example (rowid, col) VALUES (NULL, -1), (100000, -2), (NULL, -3), (5, -4), (NULL, -5);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 5);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col < 0;"),
+        vec![5, n as i64 + 11, 100000, 100001, 100002]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+}
+
+#[test]
+fn test_insert_max_rowid() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example (rowid, col) VALUES (9223372036854775807, 0);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // An unused rowid is chosen randomly.
+    let stmt = conn
+        .prepare("INSERT INTO example (col) VALUES (1), (2), (3);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let mut rowids = load_rowids(&conn, "SELECT rowid FROM example WHERE col > 0;");
+    assert_eq!(rowids.len(), 3);
+    for rowid in &rowids {
+        assert!(*rowid > 0 && *rowid <= (i64::MAX >> 1) + 1, "{}", rowid);
+    }
+    rowids.dedup();
+    assert_eq!(rowids.len(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        load_rowids(&conn, "SELECT rowid FROM example;")
+    );
+}
+
+#[test]
+fn test_insert_autoincrement() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col);",
+        "CREATE TABLE example2(id integer primary key autoincrement, col);",
+        "INSERT INTO example2(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for table in ["example", "example2"] {
+        for query in [
+            format!("INSERT INTO {table}(col) VALUES (2), (3);"),
+            format!("DELETE FROM {table} WHERE col = 3;"),
+            format!("INSERT INTO {table}(col) VALUES (4);"),
+        ] {
+            conn.prepare(&query).unwrap().execute().unwrap();
+        }
+    }
+    // The rowid of the deleted last row is reused only by the plain rowid table.
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example WHERE col = 4;"),
+        vec![2]
+    );
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example2 WHERE col = 4;"),
+        vec![4]
+    );
+
+    // Explicit rowids larger than the recorded one are recorded.
+    let stmt = conn
+        .prepare("INSERT INTO example2(id, col) VALUES (10, 5);")
+        .unwrap();
+    stmt.execute().unwrap();
+    let stmt = conn.prepare("DELETE FROM example2 WHERE id = 10;").unwrap();
+    stmt.execute().unwrap();
+    let stmt = conn
+        .prepare("INSERT INTO example2(col) VALUES (6);")
+        .unwrap();
+    stmt.execute().unwrap();
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example2 WHERE col = 6;"),
+        vec![11]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite("SELECT * FROM example2;", &test_conn, &conn);
+    assert_same_results_as_sqlite("SELECT * FROM sqlite_sequence;", &test_conn, &conn);
+
+    // The first AUTOINCREMENT table creates sqlite_sequence.
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    for query in [
+        "CREATE TABLE example2(id integer primary key autoincrement, col);",
+        "CREATE TABLE example3(id integer primary key autoincrement);",
+        "INSERT INTO example2(col) VALUES (1), (2);",
+        "INSERT INTO example3(id) VALUES (100);",
+        "DELETE FROM example3;",
+        "INSERT INTO example3(id) VALUES (NULL);",
+    ] {
+        conn.prepare(query).unwrap().execute().unwrap();
+    }
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite(
+        "SELECT name, tbl_name, sql FROM sqlite_schema;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results_as_sqlite("SELECT * FROM sqlite_sequence;", &test_conn, &conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT id FROM example3;"),
+        vec![101]
+    );
+    // SQLite keeps using the recorded largest rowid.
+    test_conn
+        .execute("INSERT INTO example3(id) VALUES (NULL);", [])
+        .unwrap();
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT id FROM example3;"),
+        vec![101, 102]
+    );
+
+    assert!(conn
+        .prepare("CREATE TABLE example4(id text primary key autoincrement);")
+        .is_err());
+}
+
+#[test]
+fn test_insert_not_null() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key not null, col1 not null, col2);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for sql in [
+        "INSERT INTO example (col1, col2) VALUES (NULL, 1);",
+        "INSERT INTO example (col2) VALUES (1);",
+        "INSERT INTO example (col1) VALUES (1 + NULL);",
+        "INSERT INTO example (col1) VALUES (1), (NULL);",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::NotNullConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+    let mut stmt = conn
+        .prepare("INSERT INTO example (col1) VALUES (?);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::NotNullConstraintViolation)
+    ));
+    stmt.bind(1, Some(Value::Integer(1))).unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    // NULL for the rowid alias generates a new rowid.
+    let stmt = conn
+        .prepare("INSERT INTO example (id, col1) VALUES (NULL, 2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert!(test_conn
+        .execute("INSERT INTO example (col2) VALUES (1);", [])
+        .is_err());
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(1)), Some(&Value::Integer(1)), None],
+            &[Some(&Value::Integer(2)), Some(&Value::Integer(2)), None],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_insert_default() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 DEFAULT 10, col2 text DEFAULT -1, col3 DEFAULT 'abc', col4 integer DEFAULT ('2' || '0'), col5 not null DEFAULT x'ff', col6 DEFAULT NULL, col7);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT INTO example (col7) VALUES (1), (2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+    // Explicit values override the defaults, even NULL.
+    let stmt = conn
+        .prepare("INSERT INTO example (col1, col3, col7) VALUES (NULL, 'def', 3);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn
+        .prepare("INSERT INTO example (col5) VALUES (NULL);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::NotNullConstraintViolation)
+    ));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let col2 = Value::Text(b"-1"[..].into());
+    let col3 = Value::Text(b"abc"[..].into());
+    let col5 = Value::Blob(b"\xff"[..].into());
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(10)),
+                Some(&col2),
+                Some(&col3),
+                Some(&Value::Integer(20)),
+                Some(&col5),
+                None,
+                Some(&Value::Integer(1)),
+            ],
+            &[
+                Some(&Value::Integer(10)),
+                Some(&col2),
+                Some(&col3),
+                Some(&Value::Integer(20)),
+                Some(&col5),
+                None,
+                Some(&Value::Integer(2)),
+            ],
+            &[
+                None,
+                Some(&col2),
+                Some(&Value::Text(b"def"[..].into())),
+                Some(&Value::Integer(20)),
+                Some(&col5),
+                None,
+                Some(&Value::Integer(3)),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_insert_default_current_time() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 DEFAULT CURRENT_TIME, col2 DEFAULT current_date, col3 DEFAULT CURRENT_TIMESTAMP, col4);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT INTO example (col4) VALUES (1);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let stmt = conn
+        .prepare("SELECT col1, col2, col3 FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    let texts = (0..3)
+        .map(|i| match columns.get(i) {
+            Some(Value::Text(text)) => text.to_vec(),
+            v => panic!("unexpected value: {:?}", v),
+        })
+        .collect::<Vec<_>>();
+    for (text, format) in texts
+        .iter()
+        .zip(["00:00:00", "0000-00-00", "0000-00-00 00:00:00"])
+    {
+        assert_eq!(text.len(), format.len());
+        for (c, f) in text.iter().zip(format.bytes()) {
+            if f == b'0' {
+                assert!(c.is_ascii_digit());
+            } else {
+                assert_eq!(*c, f);
+            }
+        }
+    }
+}
+
 #[test]
 fn test_insert_multiple_statements() {
     let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
@@ -344,6 +921,40 @@ fn test_insert_overflow() {
     )
 }
 
+#[test]
+fn test_insert_overflow_same_as_sqlite() {
+    let blob = (0..10000)
+        .map(|i| format!("{:02x}", i % 251))
+        .collect::<String>();
+    let query = format!("INSERT INTO example (col) VALUES (x'{blob}');");
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let expected_file = create_sqlite_database(&["CREATE TABLE example(col);", query.as_str()]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    assert_eq!(conn.prepare(&query).unwrap().execute().unwrap(), 1);
+
+    let stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    let Some(Value::Blob(value)) = columns.get(0) else {
+        panic!("not a blob");
+    };
+    assert_eq!(value.len(), 10000);
+    assert!(value.iter().enumerate().all(|(i, v)| *v == (i % 251) as u8));
+    drop(columns);
+    drop(row);
+    drop(rows);
+
+    // The table page and the overflow pages are the same as what SQLite writes.
+    let page_size = 4096;
+    let content = std::fs::read(file.path()).unwrap();
+    let expected_content = std::fs::read(expected_file.path()).unwrap();
+    assert_eq!(content.len(), expected_content.len());
+    assert_eq!(content.len(), 4 * page_size);
+    assert!(content[page_size..] == expected_content[page_size..]);
+}
+
 #[test]
 fn test_insert_1_cell_per_table_page_no_overflow() {
     let file = create_sqlite_database(&["PRAGMA page_size = 512;", "CREATE TABLE example(col);"]);
@@ -472,6 +1083,54 @@ fn test_insert_freeblock() {
     )
 }
 
+#[test]
+fn test_insert_delete_interleaved_in_single_page() {
+    let file = create_sqlite_database(&["PRAGMA page_size = 1024;", "CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let blob = |rowid: i64| vec![rowid as u8; (rowid as usize * 37) % 50 + 1];
+
+    for rowid in 0..200 {
+        let stmt = conn
+            .prepare(&format!(
+                "INSERT INTO example (rowid, col) VALUES ({}, x'{}');",
+                rowid,
+                format!("{:02x}", rowid as u8).repeat(blob(rowid).len())
+            ))
+            .unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+        if rowid >= 8 {
+            // The cell of the deleted row is in the middle of the cell content area.
+            let stmt = conn
+                .prepare(&format!("DELETE FROM example WHERE rowid = {};", rowid - 8))
+                .unwrap();
+            assert_eq!(stmt.execute().unwrap(), 1);
+        }
+        if rowid % 20 == 19 {
+            let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+            assert_integrity_check(&test_conn);
+        }
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    // All the rows fit in the root page of the table.
+    let n_pages: i64 = test_conn
+        .query_row("PRAGMA page_count;", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(n_pages, 2);
+    let rowids = load_test_rowids(&test_conn, "SELECT rowid FROM example;");
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), rowids);
+    for rowid in rowids {
+        let v = Value::Blob(blob(rowid).into());
+        assert_same_results(
+            &[&[Some(&v)]],
+            &format!("SELECT col FROM example WHERE rowid = {};", rowid),
+            &test_conn,
+            &conn,
+        );
+    }
+}
+
 #[test]
 fn test_insert_split() {
     let file = create_sqlite_database(&[
@@ -577,6 +1236,78 @@ fn test_insert_split() {
     }
 }
 
+#[test]
+fn test_insert_split_random_order() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+    ];
+    // Leave free pages in the freelist.
+    let fill_query = format!(
+        "INSERT INTO example (col1, col2) VALUES {};",
+        (0..500)
+            .map(|i| format!("({}, x'{}')", i, "ff".repeat(100)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    queries.push(&fill_query);
+    queries.push("DELETE FROM example;");
+    let file = create_sqlite_database(&queries);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let n_freelist_pages: i64 = test_conn
+        .query_row("PRAGMA freelist_count;", [], |row| row.get(0))
+        .unwrap();
+    assert!(n_freelist_pages > 0);
+    drop(test_conn);
+    let file_size = file.as_file().metadata().unwrap().len();
+
+    let conn = Connection::open(file.path()).unwrap();
+    let n = 3000;
+    for i in 0..n {
+        // 7919 is a prime number. This visits all rowids in a random order.
+        let rowid = (i * 7919) % n;
+        conn.prepare(&format!(
+            "INSERT INTO example (rowid, col1, col2) VALUES ({}, {}, '{}');",
+            rowid,
+            n - rowid,
+            rowid
+        ))
+        .unwrap()
+        .execute()
+        .unwrap();
+    }
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    let n_freelist_pages: i64 = test_conn
+        .query_row("PRAGMA freelist_count;", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(n_freelist_pages, 0);
+    assert!(file.as_file().metadata().unwrap().len() > file_size);
+
+    let sql = "SELECT rowid, col1, col2 FROM example;";
+    let stmt = conn.prepare(sql).unwrap();
+    let mut rows = stmt.query().unwrap();
+    for rowid in 0..n {
+        let row = rows.next_row().unwrap().unwrap();
+        let columns = row.parse().unwrap();
+        assert_eq!(columns.get(0), Some(&Value::Integer(rowid)));
+        assert_eq!(columns.get(1), Some(&Value::Integer(n - rowid)));
+        assert_eq!(
+            columns.get(2),
+            Some(&Value::Text(rowid.to_string().as_bytes().into()))
+        );
+    }
+    assert!(rows.next_row().unwrap().is_none());
+
+    for col1 in [1, 2, n / 2, n - 1, n] {
+        let sql = format!("SELECT rowid FROM example WHERE col1 = {};", col1);
+        assert_eq!(&load_rowids(&conn, &sql), &[n - col1], "{}", sql);
+        assert_eq!(&load_test_rowids(&test_conn, &sql), &[n - col1], "{}", sql);
+    }
+}
+
 #[test]
 fn test_insert_index() {
     let file = create_sqlite_database(&[
@@ -626,6 +1357,211 @@ fn test_insert_index() {
     assert_eq!(&load_test_rowids(&test_conn, sql), &rowids, "{}", sql);
 }
 
+#[test]
+fn test_insert_index_many_rows() {
+    let file = create_sqlite_database(&[
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE UNIQUE INDEX index2 ON example(col2, col1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let n = 3000;
+    // 7919 is a prime number. The index keys are inserted in a random order.
+    let query = format!(
+        "INSERT INTO example (col1, col2) VALUES {};",
+        (0..n)
+            .map(|i| format!("({}, 'abcdefghij{}')", (i * 7919) % n, i % 100))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    assert_eq!(conn.prepare(&query).unwrap().execute().unwrap(), n);
+    // Conflicts in the middle of the rows replace entries of the reused cursors.
+    let query = format!(
+        "INSERT OR REPLACE INTO example (col1, col2) VALUES {};",
+        (0..n)
+            .step_by(7)
+            .map(|i| format!("({}, 'abcdefghij{}')", (i * 7919) % n, i % 100))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    conn.prepare(&query).unwrap().execute().unwrap();
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT count(*) FROM example INDEXED BY index1 WHERE col1 >= 0;",
+        "SELECT count(*) FROM example INDEXED BY index2 WHERE col2 >= '';",
+    ] {
+        let count: i64 = test_conn.query_row(query, [], |row| row.get(0)).unwrap();
+        assert_eq!(count, n as i64, "{}", query);
+    }
+    for i in (0..n).step_by(101) {
+        assert_same_results_as_sqlite(
+            &format!("SELECT rowid, col2 FROM example WHERE col1 = {};", i),
+            &test_conn,
+            &conn,
+        );
+    }
+}
+
+#[test]
+fn test_insert_unique() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 unique, col2 text primary key, col3 collate nocase unique, col4, col5);",
+        "CREATE UNIQUE INDEX index1 ON example(col4, col5);",
+        "INSERT INTO example (col1, col2, col3, col4, col5) VALUES (1, 'a', 'a', 1, 1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for sql in [
+        "INSERT INTO example (col1) VALUES (1);",
+        "INSERT INTO example (col2) VALUES ('a');",
+        "INSERT INTO example (col3) VALUES ('A');",
+        "INSERT INTO example (col4, col5) VALUES (1, 1);",
+        "INSERT INTO example (col1) VALUES (2), (2);",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::UniqueConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+
+    // NULLs are distinct from each other.
+    let stmt = conn
+        .prepare("INSERT INTO example (col1, col3, col4, col5) VALUES (NULL, NULL, 1, NULL), (NULL, NULL, 1, NULL), (2, 'b', 1, 2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for (sql, rowids) in [
+        ("SELECT rowid FROM example WHERE col1 = 2;", vec![4]),
+        ("SELECT rowid FROM example WHERE col3 = 'B';", vec![4]),
+        (
+            "SELECT rowid FROM example WHERE col4 = 1;",
+            vec![1, 2, 3, 4],
+        ),
+    ] {
+        // The order depends on the chosen index.
+        let mut actual = load_rowids(&conn, sql);
+        actual.sort();
+        let mut expected = load_test_rowids(&test_conn, sql);
+        expected.sort();
+        assert_eq!(actual, rowids, "{}", sql);
+        assert_eq!(expected, rowids, "{}", sql);
+    }
+}
+
+#[test]
+fn test_insert_or_ignore() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1 unique, col2);",
+        "CREATE INDEX index1 ON example(col2);",
+        "INSERT INTO example (id, col1, col2) VALUES (1, 10, 100);",
+        "INSERT INTO example (id, col1, col2) VALUES (2, 20, 200);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT OR IGNORE INTO example (id, col1, col2) VALUES (1, 30, 300), (3, 20, 400), (4, 40, 500), (5, NULL, 600), (6, NULL, 700);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(1)),
+                Some(&Value::Integer(10)),
+                Some(&Value::Integer(100)),
+            ],
+            &[
+                Some(&Value::Integer(2)),
+                Some(&Value::Integer(20)),
+                Some(&Value::Integer(200)),
+            ],
+            &[
+                Some(&Value::Integer(4)),
+                Some(&Value::Integer(40)),
+                Some(&Value::Integer(500)),
+            ],
+            &[Some(&Value::Integer(5)), None, Some(&Value::Integer(600))],
+            &[Some(&Value::Integer(6)), None, Some(&Value::Integer(700))],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_insert_or_replace() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1 unique, col2 unique);",
+        "CREATE INDEX index1 ON example(col2, col1);",
+        "INSERT INTO example (id, col1, col2) VALUES (1, 10, 100);",
+        "INSERT INTO example (id, col1, col2) VALUES (2, 20, 200);",
+        "INSERT INTO example (id, col1, col2) VALUES (3, 30, 300);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Replaces the row with the same rowid.
+    let stmt = conn
+        .prepare("INSERT OR REPLACE INTO example (id, col1, col2) VALUES (1, 11, 101);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    // Replaces the rows with the same unique keys.
+    let stmt = conn
+        .prepare("INSERT OR REPLACE INTO example (id, col1, col2) VALUES (4, 20, 300);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn
+        .prepare("INSERT INTO example (id, col1, col2) VALUES (5, 20, 500);")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(1)),
+                Some(&Value::Integer(11)),
+                Some(&Value::Integer(101)),
+            ],
+            &[
+                Some(&Value::Integer(4)),
+                Some(&Value::Integer(20)),
+                Some(&Value::Integer(300)),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+    for sql in [
+        "SELECT rowid FROM example WHERE col1 = 10;",
+        "SELECT rowid FROM example WHERE col1 = 20;",
+        "SELECT rowid FROM example WHERE col2 = 200;",
+        "SELECT rowid FROM example WHERE col2 = 300;",
+    ] {
+        assert_eq!(
+            load_rowids(&conn, sql),
+            load_test_rowids(&test_conn, sql),
+            "{}",
+            sql
+        );
+    }
+}
+
 #[test]
 fn test_insert_rowid_type_affinity() {
     let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
@@ -755,7 +1691,7 @@ fn test_insert_type_affinity() {
                 Some(&Value::Text(b"500.0".as_slice().into())),
             ],
             &[
-                Some(&Value::Text(b"500".as_slice().into())),
+                Some(&Value::Text(b"500.0".as_slice().into())),
                 Some(&Value::Integer(500)),
                 Some(&Value::Integer(500)),
                 Some(&Value::Real(500.0)),
@@ -782,3 +1718,227 @@ fn test_insert_type_affinity() {
         &conn,
     )
 }
+
+#[test]
+fn test_insert_parameters() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col1 integer, col2, col3);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let mut stmt = conn
+        .prepare("INSERT INTO example (rowid, col1, col2, col3) VALUES (?, ?, :value, :value);")
+        .unwrap();
+    assert_eq!(stmt.parameter_count(), 3);
+    assert_eq!(stmt.parameter_index(":value"), Some(3));
+    assert_eq!(stmt.parameter_index("value"), None);
+
+    let values = [
+        Some(Value::Integer(10)),
+        Some(Value::Real(1.5)),
+        Some(Value::Text(b"abc"[..].into())),
+        Some(Value::Blob(b"\x00\x01"[..].into())),
+        None,
+    ];
+    for (i, value) in values.iter().enumerate() {
+        stmt.bind(1, Some(Value::Integer(i as i64 + 1))).unwrap();
+        // Type affinity of the column is applied.
+        stmt.bind(2, Some(Value::Text(b"100"[..].into()))).unwrap();
+        stmt.bind_named(":value", value.clone()).unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+    }
+    // Unbound parameters are NULL.
+    let stmt = conn
+        .prepare("INSERT INTO example (col1, col2) VALUES (?, ?2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let expected = values
+        .iter()
+        .map(|v| [Some(&Value::Integer(100)), v.as_ref(), v.as_ref()])
+        .chain([[None, None, None]])
+        .collect::<Vec<_>>();
+    assert_same_results(
+        &expected
+            .iter()
+            .map(|row| row.as_slice())
+            .collect::<Vec<_>>(),
+        "SELECT col1, col2, col3 FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_insert_computed_values() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col1 integer, col2, col3);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("INSERT INTO example (rowid, col1, col2, col3) VALUES (1 + 1, '7' / 2, 'a' || 2, 1 / 0);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let mut stmt = conn
+        .prepare("INSERT INTO example (col1, col2, col3) VALUES (? * 2, ? - 0.5, ? % 3);")
+        .unwrap();
+    stmt.bind(1, Some(Value::Integer(21))).unwrap();
+    stmt.bind(2, Some(Value::Text(b"3"[..].into()))).unwrap();
+    stmt.bind(3, Some(Value::Integer(-7))).unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(2)),
+                Some(&Value::Integer(3)),
+                Some(&Value::Text(b"a2"[..].into())),
+                None,
+            ],
+            &[
+                Some(&Value::Integer(3)),
+                Some(&Value::Integer(42)),
+                Some(&Value::Real(2.5)),
+                Some(&Value::Integer(-1)),
+            ],
+        ],
+        "SELECT rowid, col1, col2, col3 FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_insert_parameters_fail() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col1, col2);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let mut stmt = conn
+        .prepare("INSERT INTO example (col1, col2) VALUES (?, @name);")
+        .unwrap();
+    assert!(stmt.bind(0, Some(Value::Integer(1))).is_err());
+    assert!(stmt.bind(3, Some(Value::Integer(1))).is_err());
+    assert!(stmt.bind_named(":name", Some(Value::Integer(1))).is_err());
+    assert!(stmt.bind_named("@name", Some(Value::Integer(1))).is_ok());
+
+    assert!(matches!(
+        conn.prepare("INSERT INTO example (col1, col2) VALUES (?0, 1);"),
+        Err(Error::Parse(_))
+    ));
+    assert!(matches!(
+        conn.prepare("INSERT INTO example (col1, col2) VALUES (:, 1);"),
+        Err(Error::Parse(_))
+    ));
+}
+
+#[test]
+fn test_insert_strict_table() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id INTEGER PRIMARY KEY, col1 INTEGER, col2 REAL, col3 TEXT, col4 BLOB, col5 ANY) STRICT;",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for sql in [
+        "INSERT INTO example (col1) VALUES ('abc');",
+        "INSERT INTO example (col1) VALUES (1.5);",
+        "INSERT INTO example (col1) VALUES (x'01');",
+        "INSERT INTO example (col2) VALUES ('abc');",
+        "INSERT INTO example (col3) VALUES (x'01');",
+        "INSERT INTO example (col4) VALUES (1);",
+        "INSERT INTO example (col1) VALUES (1), ('abc');",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::DataTypeConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+
+    // Values which can be converted losslessly are accepted. NULL is always
+    // accepted.
+    let stmt = conn
+        .prepare("INSERT INTO example (col1, col2, col3, col4, col5) VALUES (1, 2, 3, x'04', '5'), ('6', '7.5', 'a', NULL, 8.5), (NULL, NULL, NULL, NULL, NULL);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert!(test_conn
+        .execute("INSERT INTO example (col1) VALUES ('abc');", [])
+        .is_err());
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(1)),
+                Some(&Value::Integer(1)),
+                Some(&Value::Real(2.0)),
+                Some(&Value::Text(b"3"[..].into())),
+                Some(&Value::Blob(b"\x04"[..].into())),
+                Some(&Value::Text(b"5"[..].into())),
+            ],
+            &[
+                Some(&Value::Integer(2)),
+                Some(&Value::Integer(6)),
+                Some(&Value::Real(7.5)),
+                Some(&Value::Text(b"a"[..].into())),
+                None,
+                Some(&Value::Real(8.5)),
+            ],
+            &[Some(&Value::Integer(3)), None, None, None, None, None],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+    assert_integrity_check(&test_conn);
+}
+
+#[test]
+fn test_insert_check_constraint() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id INTEGER PRIMARY KEY CHECK(id < 100), name TEXT, age INTEGER CHECK(age >= 0), CHECK(name != 'invalid'));",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    for sql in [
+        "INSERT INTO example (name, age) VALUES ('a', -1);",
+        "INSERT INTO example (name, age) VALUES ('invalid', 1);",
+        "INSERT INTO example (id, name, age) VALUES (100, 'a', 1);",
+        "INSERT INTO example (name, age) VALUES ('a', 1), ('b', -1);",
+    ] {
+        let stmt = conn.prepare(sql).unwrap();
+        assert!(
+            matches!(stmt.execute(), Err(Error::CheckConstraintViolation)),
+            "{}",
+            sql
+        );
+    }
+
+    // NULL results of the CHECK expressions pass.
+    let stmt = conn
+        .prepare("INSERT INTO example (name, age) VALUES ('a', 20), (NULL, NULL);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert!(test_conn
+        .execute("INSERT INTO example (age) VALUES (-1);", [])
+        .is_err());
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(1)),
+                Some(&Value::Text(b"a"[..].into())),
+                Some(&Value::Integer(20)),
+            ],
+            &[Some(&Value::Integer(2)), None, None],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+
+    // Invalid CHECK constraints are rejected.
+    assert!(conn
+        .prepare("CREATE TABLE example2(col CHECK(unknown > 0));")
+        .is_err());
+}
@@ -15,7 +15,12 @@
 mod common;
 
 use common::*;
+use prsqlite::AccessMethod;
 use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::PlanDescription;
+use prsqlite::Rows;
+use prsqlite::TablePlan;
 use prsqlite::Value;
 
 #[test]
@@ -74,6 +79,199 @@ fn test_select_all_from_table() {
     assert!(rows.next_row().unwrap().is_none());
 }
 
+#[test]
+fn test_open_readonly() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "CREATE INDEX index1 ON example(col);",
+        "INSERT INTO example(col) VALUES (1), (2);",
+    ]);
+    let mut permissions = file.as_file().metadata().unwrap().permissions();
+    permissions.set_readonly(true);
+    file.as_file().set_permissions(permissions).unwrap();
+    let original = std::fs::read(file.path()).unwrap();
+
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    let stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(1))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(2))], "");
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    for query in [
+        "INSERT INTO example(col) VALUES (3);",
+        "DELETE FROM example WHERE col = 1;",
+        "DELETE FROM example;",
+    ] {
+        let stmt = conn.prepare(query).unwrap();
+        assert!(matches!(stmt.execute(), Err(Error::ReadOnly)), "{}", query);
+    }
+
+    // Failed writes do not block following reads.
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(1))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(2))], "");
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    assert_eq!(std::fs::read(file.path()).unwrap(), original);
+}
+
+#[test]
+fn test_open_fail() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let path = file.path().with_extension("notfound");
+    assert!(matches!(
+        Connection::open(&path),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+
+    std::fs::write(file.path(), [0; 4096]).unwrap();
+    assert!(matches!(
+        Connection::open(file.path()),
+        Err(Error::NotADatabase)
+    ));
+    assert!(matches!(
+        Connection::open_readonly(file.path()),
+        Err(Error::NotADatabase)
+    ));
+    std::fs::write(file.path(), b"SQLite format 3\0").unwrap();
+    assert!(matches!(
+        Connection::open(file.path()),
+        Err(Error::NotADatabase)
+    ));
+
+    // Invalid page size.
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let mut buf = std::fs::read(file.path()).unwrap();
+    buf[16..18].copy_from_slice(&1000_u16.to_be_bytes());
+    std::fs::write(file.path(), buf).unwrap();
+    assert!(matches!(
+        Connection::open(file.path()),
+        Err(Error::Corrupt(_))
+    ));
+}
+
+#[test]
+fn test_open_empty_file() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    assert!(matches!(
+        Connection::open_readonly(file.path()),
+        Err(Error::NotADatabase)
+    ));
+    assert_eq!(std::fs::read(file.path()).unwrap().len(), 0);
+
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(std::fs::read(file.path()).unwrap().len(), 4096);
+    assert!(load_rowids(&conn, "SELECT rootpage FROM sqlite_schema;").is_empty());
+    conn.execute_batch("CREATE TABLE example(col); INSERT INTO example(col) VALUES (1), (2);")
+        .unwrap();
+    drop(conn);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example;"),
+        vec![1, 2]
+    );
+    drop(test_conn);
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![1, 2]);
+}
+
+#[test]
+fn test_create_page_size() {
+    for pagesize in [512, 4096, 65536] {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let conn = Connection::create(&path)
+            .page_size(pagesize)
+            .open()
+            .unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap().len(),
+            pagesize as usize,
+            "{}",
+            pagesize
+        );
+        assert_eq!(
+            load_rowids(&conn, "PRAGMA page_size;"),
+            vec![pagesize as i64]
+        );
+
+        conn.execute_batch(
+            "CREATE TABLE example(col1, col2); CREATE INDEX index1 ON example(col2);",
+        )
+        .unwrap();
+        let mut stmt = conn
+            .prepare("INSERT INTO example(col1, col2) VALUES (?, ?);")
+            .unwrap();
+        let txn = conn.begin().unwrap();
+        for i in 0..1000 {
+            // Every 100th row overflows.
+            let padding = if i % 100 == 0 {
+                pagesize as usize * 2
+            } else {
+                100
+            };
+            let text = format!("{i:04}{}", "x".repeat(padding));
+            stmt.bind(1, Some(Value::Integer(i))).unwrap();
+            stmt.bind(2, Some(Value::Text(text.as_bytes().into())))
+                .unwrap();
+            assert_eq!(stmt.execute().unwrap(), 1);
+        }
+        txn.commit().unwrap();
+        drop(stmt);
+        drop(conn);
+
+        let test_conn = rusqlite::Connection::open(&path).unwrap();
+        assert_integrity_check(&test_conn);
+        assert_eq!(
+            load_test_rowids(&test_conn, "PRAGMA page_size;"),
+            vec![pagesize as i64]
+        );
+        let conn = Connection::open(&path).unwrap();
+        for query in [
+            "SELECT col1, col2 FROM example;",
+            "SELECT col1 FROM example WHERE col2 >= '0500';",
+        ] {
+            assert_same_results_as_sqlite(query, &test_conn, &conn);
+        }
+    }
+}
+
+#[test]
+fn test_create_fail() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    for pagesize in [0, 256, 511, 1000, 4095, 131072] {
+        assert!(
+            matches!(
+                Connection::create(&path).page_size(pagesize).open(),
+                Err(Error::Unsupported(_))
+            ),
+            "{}",
+            pagesize
+        );
+    }
+    assert!(!path.exists());
+
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let original = std::fs::read(file.path()).unwrap();
+    assert!(matches!(
+        Connection::create(file.path()).page_size(512).open(),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::AlreadyExists
+    ));
+    assert_eq!(std::fs::read(file.path()).unwrap(), original);
+
+    let conn = Connection::create(std::path::Path::new(":memory:"))
+        .page_size(1024)
+        .open()
+        .unwrap();
+    assert_eq!(load_rowids(&conn, "PRAGMA page_size;"), vec![1024]);
+}
+
 #[test]
 fn test_select_reuse_statement() {
     let file = create_sqlite_database(&[
@@ -93,6 +291,193 @@ fn test_select_reuse_statement() {
     assert_same_result_prsqlite!(rows, [Some(&Value::Integer(1))], "");
 }
 
+#[test]
+fn test_select_rerun_with_parameters() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1), (2), (3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let mut stmt = conn
+        .prepare("SELECT rowid FROM example WHERE col >= ?;")
+        .unwrap();
+
+    stmt.bind(1, Some(Value::Integer(2))).unwrap();
+    let rows = stmt
+        .query()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(2)));
+
+    stmt.bind(1, Some(Value::Integer(3))).unwrap();
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(3))], "");
+    assert!(rows.next_row().unwrap().is_none());
+
+    // The read transaction is held until the rows are dropped.
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (4);")
+        .unwrap();
+    assert!(matches!(insert_stmt.execute(), Err(Error::Busy)));
+    drop(rows);
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+
+    // The query restarts from the beginning even if the previous rows are
+    // not consumed.
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(3))], "");
+    drop(rows);
+    let mut rows = stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(3))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(4))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(5))], "");
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    // Reset keeps the bound parameters and does not end the read transaction
+    // of the rows alive.
+    let rows = stmt.query().unwrap();
+    stmt.reset();
+    assert!(matches!(insert_stmt.execute(), Err(Error::Busy)));
+    drop(rows);
+    assert_eq!(stmt.query().unwrap().count(), 3);
+
+    stmt.clear_bindings();
+    assert!(stmt.query().unwrap().next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_rows_outlive_statement() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "CREATE INDEX index1 ON example(col);",
+        "INSERT INTO example(col) VALUES (1), (2), (3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let mut stmt = conn
+        .prepare("SELECT col FROM example WHERE col >= ?;")
+        .unwrap();
+    stmt.bind(1, Some(Value::Integer(2))).unwrap();
+    let mut rows1 = stmt.query().unwrap();
+
+    // Rebinding while the rows are alive does not affect them.
+    stmt.bind(1, Some(Value::Integer(3))).unwrap();
+    let mut rows2 = stmt.query().unwrap();
+    drop(stmt);
+
+    assert_same_result_prsqlite!(rows1, [Some(&Value::Integer(2))], "");
+    assert_same_result_prsqlite!(rows2, [Some(&Value::Integer(3))], "");
+    assert_same_result_prsqlite!(rows1, [Some(&Value::Integer(3))], "");
+    assert!(rows1.next_row().unwrap().is_none());
+    assert!(rows2.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_rows_iterator() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(100);
+    for i in 0..100 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, '{}');",
+            i,
+            "a".repeat(i)
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("SELECT col1, col2 FROM example;").unwrap();
+
+    // Rows outlive the iteration step and span multiple pages.
+    let rows = stmt
+        .query()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 100);
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row.len(), 2);
+        assert_eq!(row.get(0), Some(&Value::Integer(i as i64)));
+        assert_eq!(
+            row.get(1),
+            Some(&Value::Text("a".repeat(i).into_bytes().into()))
+        );
+    }
+
+    let stmt = conn
+        .prepare("SELECT col1 FROM example WHERE col1 < 3 ORDER BY col1 DESC;")
+        .unwrap();
+    let mut values = Vec::new();
+    for row in stmt.query().unwrap() {
+        values.push(row.unwrap().get(0).cloned());
+    }
+    assert_eq!(
+        values,
+        vec![
+            Some(Value::Integer(2)),
+            Some(Value::Integer(1)),
+            Some(Value::Integer(0))
+        ]
+    );
+}
+
+#[test]
+fn test_select_owned_rows() {
+    let file = create_sqlite_database(&[
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES ('abc', x'01');",
+        // The payload spans overflow pages.
+        &format!(
+            "INSERT INTO example(col1, col2) VALUES ('{}', x'{}');",
+            "b".repeat(1000),
+            "02".repeat(1000)
+        ),
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn
+        .prepare("SELECT col1, col2, col1 || 'd' AS col3 FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let mut owned_rows = Vec::new();
+    while let Some(row) = rows.next_row().unwrap() {
+        owned_rows.push(row.parse().unwrap().into_owned());
+    }
+    drop(rows);
+    drop(stmt);
+    drop(conn);
+
+    assert_eq!(owned_rows.len(), 2);
+    assert_eq!(owned_rows[0].column_names(), &["col1", "col2", "col3"]);
+    assert_eq!(
+        owned_rows[0].get(0),
+        Some(&Value::Text(b"abc".as_slice().into()))
+    );
+    assert_eq!(
+        owned_rows[0].get(1),
+        Some(&Value::Blob(b"\x01".as_slice().into()))
+    );
+    assert_eq!(
+        owned_rows[0].get_by_name("col3"),
+        Some(&Value::Text(b"abcd".as_slice().into()))
+    );
+    assert_eq!(
+        owned_rows[1].get(0),
+        Some(&Value::Text("b".repeat(1000).into_bytes().into()))
+    );
+    assert_eq!(
+        owned_rows[1].get(1),
+        Some(&Value::Blob(vec![2; 1000].into()))
+    );
+}
+
 #[test]
 fn test_select_multiple_statements() {
     let file = create_sqlite_database(&[
@@ -122,6 +507,70 @@ fn test_select_multiple_statements() {
     assert!(rows4.next_row().unwrap().is_none());
 }
 
+#[test]
+fn test_select_read_snapshot() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1), (2);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let select_stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let filter_stmt = conn
+        .prepare("SELECT col FROM example WHERE col = 3;")
+        .unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (3);")
+        .unwrap();
+
+    let snapshot = conn.begin_read().unwrap();
+
+    let mut rows = select_stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(1))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(2))], "");
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    // Writes are blocked while the snapshot is alive even if no query is running.
+    assert!(matches!(insert_stmt.execute(), Err(Error::Busy)));
+
+    let mut rows = filter_stmt.query().unwrap();
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+    let mut rows = select_stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(1))], "");
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(2))], "");
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    drop(snapshot);
+
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    let mut rows = filter_stmt.query().unwrap();
+    assert_same_result_prsqlite!(rows, [Some(&Value::Integer(3))], "");
+    assert!(rows.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_multiple_read_snapshots() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+
+    let snapshot1 = conn.begin_read().unwrap();
+    let snapshot2 = conn.begin_read().unwrap();
+    drop(snapshot1);
+    assert!(matches!(insert_stmt.execute(), Err(Error::Busy)));
+    drop(snapshot2);
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+}
+
 #[test]
 fn test_select_partial() {
     let file = create_sqlite_database(&[
@@ -211,6 +660,206 @@ fn test_select_rowid() {
     assert!(rows.next_row().unwrap().is_none());
 }
 
+#[test]
+fn test_query_rowid_range() {
+    let mut stmts = vec!["CREATE TABLE example(col1, col2);"];
+    let mut insert_stmts = Vec::new();
+    for i in 1..=2000 {
+        // Leave gaps in the rowids.
+        if i % 7 == 0 {
+            continue;
+        }
+        insert_stmts.push(format!(
+            "INSERT INTO example(rowid, col1, col2) VALUES ({}, {}, '{}');",
+            i,
+            i * 3,
+            "a".repeat(100)
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let load = |rows: Rows| {
+        rows.map(|row| {
+            let row = row.unwrap();
+            (0..row.len())
+                .map(|i| row.get(i).cloned())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+    };
+
+    for (lo, hi) in [
+        (1, 2000),
+        (1500, 1600),
+        (7, 7),
+        (8, 8),
+        (14, 20),
+        (-100, 5),
+        (1990, i64::MAX),
+        (i64::MIN, i64::MAX),
+        (2001, 3000),
+        (100, 99),
+    ] {
+        let expected = load(
+            conn.prepare(&format!(
+                "SELECT * FROM example WHERE rowid BETWEEN {lo} AND {hi};"
+            ))
+            .unwrap()
+            .query()
+            .unwrap(),
+        );
+        let rows = load(conn.query_rowid_range("example", lo..=hi).unwrap());
+        assert_eq!(rows, expected, "range: {lo}..={hi}");
+    }
+
+    let row = conn
+        .query_rowid_range("example", 1500..=1600)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(row.column_names(), ["col1", "col2"]);
+
+    // The range scan does not read the pages of the rows below the range.
+    let full_scan = conn
+        .prepare("SELECT * FROM example WHERE rowid BETWEEN 1900 AND 1910;")
+        .unwrap();
+    let stats = conn.cache_stats();
+    let n_rows = load(full_scan.query().unwrap()).len();
+    let stats_after = conn.cache_stats();
+    let n_full_scan_lookups = stats_after.hits + stats_after.misses - stats.hits - stats.misses;
+    let stats = conn.cache_stats();
+    let rows = load(conn.query_rowid_range("example", 1900..=1910).unwrap());
+    let stats_after = conn.cache_stats();
+    let n_lookups = stats_after.hits + stats_after.misses - stats.hits - stats.misses;
+    assert_eq!(rows.len(), n_rows);
+    assert_eq!(rows[0][0], Some(Value::Integer(1900 * 3)));
+    assert!(
+        n_lookups * 10 < n_full_scan_lookups,
+        "range scan: {}, full scan: {}",
+        n_lookups,
+        n_full_scan_lookups
+    );
+
+    assert!(conn.query_rowid_range("invalid", 1..=2).is_err());
+}
+
+#[test]
+fn test_prepare_with_comments() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (1, 10), (2, 20), (3, 30);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "-- Load the rows.\nSELECT col1 FROM example;",
+        "/* Load the rows. */ SELECT col1 FROM example;",
+        "SELECT /* the first column */ col1 -- col2\nFROM example;",
+        "SELECT col1 FROM example WHERE col2 > 10 -- skip the first row\n;",
+        "SELECT col1 FROM example WHERE col2 >/**/10;",
+        "SELECT col1 FROM example WHERE col2 - -10 > 25;",
+        "SELECT col1 FROM example WHERE col2 / 10 >= 2;",
+        "\t\r\n\x0cSELECT\tcol1\r\nFROM\x0cexample;",
+        "SELECT col1 FROM example; -- trailing comment",
+        "SELECT col1 FROM example; /* unterminated comment",
+        "SELECT col1 FROM example /*;*/",
+        "SELECT col1 FROM example --;",
+        "SELECT col1 FROM example -- trailing comment without semicolon",
+        "SELECT col1 FROM example /* unterminated comment without semicolon",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    assert!(conn.prepare("SELECT col1 FROM example; /").is_err());
+    assert!(conn.prepare("SELECT col1 FROM example /").is_err());
+}
+
+#[test]
+fn test_select_column_names() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(Col1, \"col 2\", id integer primary key);",
+        "CREATE TABLE example2(col, roWid);",
+        "INSERT INTO example(col1, \"col 2\") VALUES (10, 20);",
+        "INSERT INTO example2(col, rowid) VALUES (30, 100);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT * FROM example;",
+        "SELECT col1, COL1, \"col1\", \"col 2\", id FROM example;",
+        "SELECT rowid, RowID FROM example;",
+        "SELECT rowid, col, * FROM example2;",
+        "SELECT - col1, col1||'a',  col1   =  10 , 'text', count(*) FROM example;",
+    ] {
+        let stmt = conn.prepare(query).unwrap();
+        let test_stmt = test_conn.prepare(query).unwrap();
+        assert_eq!(stmt.column_names(), test_stmt.column_names(), "{}", query);
+    }
+
+    let stmt = conn
+        .prepare("SELECT col1, \"col 2\", -col1 FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.column_names(), stmt.column_names());
+    assert_eq!(columns.get_by_name("col1"), Some(&Value::Integer(10)));
+    assert_eq!(columns.get_by_name("COL1"), Some(&Value::Integer(10)));
+    assert_eq!(columns.get_by_name("col 2"), Some(&Value::Integer(20)));
+    assert_eq!(columns.get_by_name("-col1"), Some(&Value::Integer(-10)));
+    assert_eq!(columns.get_by_name("id"), None);
+
+    // The first column wins if names are duplicated.
+    let stmt = conn
+        .prepare("SELECT \"col 2\", col1, * FROM example;")
+        .unwrap();
+    let row = stmt.query().unwrap().next().unwrap().unwrap();
+    assert_eq!(row.get_by_name("col1"), Some(&Value::Integer(10)));
+    assert_eq!(row.get_by_name("id"), Some(&Value::Integer(1)));
+
+    let stmt = conn
+        .prepare("INSERT INTO example(col1) VALUES (1);")
+        .unwrap();
+    assert!(stmt.column_names().is_empty());
+}
+
+#[test]
+fn test_select_column_alias() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (10, 20);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col1 AS foo, col2 bar FROM example;",
+        "SELECT col1 AS \"foo bar\", col1 AS `baz` FROM example;",
+        "SELECT col1 = 10 AS c, -col2 AS col1, * FROM example;",
+        "SELECT count(*) AS n, col1 FROM example;",
+    ] {
+        let stmt = conn.prepare(query).unwrap();
+        let test_stmt = test_conn.prepare(query).unwrap();
+        assert_eq!(stmt.column_names(), test_stmt.column_names(), "{}", query);
+    }
+
+    // The alias does not affect the values.
+    let stmt = conn
+        .prepare("SELECT col2 AS col1, col1 AS col2 FROM example;")
+        .unwrap();
+    let row = stmt.query().unwrap().next().unwrap().unwrap();
+    assert_eq!(row.get(0), Some(&Value::Integer(20)));
+    assert_eq!(row.get_by_name("col1"), Some(&Value::Integer(20)));
+    assert_eq!(row.get_by_name("col2"), Some(&Value::Integer(10)));
+}
+
 #[test]
 fn test_select_column_name_and_all() {
     let file = create_sqlite_database(&[
@@ -274,6 +923,12 @@ fn test_select_expression() {
 
     assert_same_results(&[&[None]], "SELECT NULL FROM example;", &test_conn, &conn);
     assert_same_results(&[&[None]], "SELECT ~NULL FROM example;", &test_conn, &conn);
+    assert_same_results(
+        &[&[None, None]],
+        "SELECT CAST(NULL AS INTEGER), CAST(NULL AS TEXT) FROM example;",
+        &test_conn,
+        &conn,
+    );
 
     for (expected, expr) in [
         // Literals
@@ -326,6 +981,25 @@ fn test_select_expression() {
         (Value::Integer(10), "CAST('10e.1' AS INTEGER)"),
         (Value::Integer(0), "CAST('a3' AS INTEGER)"),
         (Value::Integer(10), "CAST('10.12e1' AS INTEGER)"),
+        (Value::Integer(-4), "CAST(-4.5 AS INTEGER)"),
+        (Value::Integer(9223372036854775807), "CAST(1e20 AS INTEGER)"),
+        (
+            Value::Integer(-9223372036854775808),
+            "CAST(-1e20 AS INTEGER)",
+        ),
+        (
+            Value::Integer(9223372036854775807),
+            "CAST('9223372036854775808' AS INTEGER)",
+        ),
+        (
+            Value::Integer(-9223372036854775808),
+            "CAST('-9223372036854775809' AS INTEGER)",
+        ),
+        (Value::Integer(0), "CAST('' AS INTEGER)"),
+        (Value::Integer(0), "CAST('-' AS INTEGER)"),
+        (Value::Integer(0), "CAST('0x10' AS INTEGER)"),
+        (Value::Integer(-12), "CAST('-12-3' AS INTEGER)"),
+        (Value::Integer(12), "CAST(x'3132' AS INTEGER)"),
         // Cast to float
         (Value::Real(1.0), "CAST(col1 AS FLOAT)"),
         (Value::Real(4.5), "CAST(col4 AS FLOAT)"),
@@ -338,6 +1012,7 @@ fn test_select_expression() {
         (Value::Text(b"1".to_vec().into()), "CAST(col1 AS TEXT)"),
         (Value::Text(b"-3".to_vec().into()), "CAST(col3 AS TEXT)"),
         (Value::Text(b"4.5".to_vec().into()), "CAST(col4 AS TEXT)"),
+        (Value::Text(b"-4.5".to_vec().into()), "CAST(-4.5 AS TEXT)"),
         (
             Value::Text(b" 10 ".as_slice().into()),
             "CAST(' 10 ' AS TEXT)",
@@ -405,32 +1080,668 @@ fn test_select_expression_operators() {
         (Value::Integer(0), "1 = 'a' = 'a'"),
         (Value::Integer(1), "1 < 2 = 1"),
         (Value::Integer(0), "1 = 2 <= 1"),
+        (Value::Integer(1), "1 OR 0 AND 0"),
+        (Value::Integer(0), "NOT 1 AND 1"),
+        (Value::Integer(1), "NOT 0 OR 0"),
     ] {
         let query = format!("SELECT {} FROM example;", expr);
         assert_same_results(&[&[Some(&expected)]], &query, &test_conn, &conn);
     }
+
+    // The right operand is not evaluated. SQLite fails with the invalid escape here.
+    for (expected, expr) in [
+        (Value::Integer(0), "0 AND 'a' LIKE 'a' ESCAPE 'ab'"),
+        (Value::Integer(1), "1 OR 'a' LIKE 'a' ESCAPE 'ab'"),
+    ] {
+        let query = format!("SELECT {} FROM example;", expr);
+        let stmt = conn.prepare(&query).unwrap();
+        let mut rows = stmt.query().unwrap();
+        let row = rows.next_row().unwrap().unwrap();
+        assert_eq!(row.parse().unwrap().get(0), Some(&expected), "{}", query);
+    }
 }
 
 #[test]
-fn test_select_primary_key() {
+fn test_select_scalar_functions() {
     let file = create_sqlite_database(&[
-        "CREATE TABLE example(id integer primary key, col text);",
-        "INSERT INTO example(id, col) VALUES (1, '10');",
-        "INSERT INTO example(id, col) VALUES (5, '20');",
-        "INSERT INTO example(id, col) VALUES (3, '30');",
+        "CREATE TABLE example(col1, col2 COLLATE NOCASE, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (-3, 'abc', NULL);",
     ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
     let conn = Connection::open(file.path()).unwrap();
 
-    let stmt = conn.prepare("SELECT * FROM example;").unwrap();
-    stmt.query().unwrap();
-    let mut rows = stmt.query().unwrap();
+    for (expected, expr) in [
+        (Some(Value::Integer(3)), "abs(col1)"),
+        (
+            Some(Value::Integer(9223372036854775807)),
+            "abs(-9223372036854775807)",
+        ),
+        (Some(Value::Real(1.5)), "abs(-1.5)"),
+        (Some(Value::Real(12.0)), "abs('-12')"),
+        (Some(Value::Real(0.0)), "abs('abc')"),
+        (None, "abs(col3)"),
+        // Halfway cases are rounded away from zero, not to even.
+        (Some(Value::Real(3.0)), "round(2.5)"),
+        (Some(Value::Real(-3.0)), "round(-2.5)"),
+        (Some(Value::Real(4.0)), "round(3.5)"),
+        (Some(Value::Real(0.13)), "round(0.125, 2)"),
+        (Some(Value::Real(-0.13)), "round(-0.125, 2)"),
+        (Some(Value::Real(1.01)), "round(1.005, 2)"),
+        (Some(Value::Real(10.0)), "round(9.96, 1)"),
+        (Some(Value::Real(-3.0)), "round(col1)"),
+        (Some(Value::Real(1.235)), "round('1.23456', 3)"),
+        (Some(Value::Real(123.0)), "round(123.456, -1)"),
+        (Some(Value::Real(1.25)), "round(1.25, 100)"),
+        (Some(Value::Real(0.0)), "round(-0.4)"),
+        (Some(Value::Real(0.0)), "round(-0.004, 2)"),
+        // Negative zero would be rendered with "-".
+        (Some(Value::Integer(0)), "round(-0.4) || '' LIKE '-%'"),
+        (Some(Value::Integer(0)), "round(-0.004, 2) || '' LIKE '-%'"),
+        (None, "round(col3)"),
+        (None, "round(1.5, col3)"),
+        (Some(Value::Integer(-3)), "coalesce(col3, col1)"),
+        (Some(Value::Integer(1)), "coalesce(NULL, NULL, 1, col1)"),
+        (None, "coalesce(col3, NULL)"),
+        (Some(Value::Integer(-3)), "ifnull(col3, col1)"),
+        (Some(Value::Integer(-3)), "ifnull(col1, 1)"),
+        (None, "ifnull(col3, NULL)"),
+        (None, "nullif(col1, -3)"),
+        (Some(Value::Integer(-3)), "nullif(col1, '-3')"),
+        (Some(Value::Integer(-3)), "nullif(col1, col3)"),
+        (None, "nullif(col3, 1)"),
+        (None, "nullif(col2, 'ABC')"),
+        (
+            Some(Value::Text(b"abc".as_slice().into())),
+            "nullif('abc', 'ABC')",
+        ),
+        (None, "nullif('abc', col2)"),
+        (None, "IFNULL(NULLIF(col1, -3), Coalesce(col3, NULL))"),
+    ] {
+        let query = format!("SELECT {} FROM example;", expr);
+        assert_same_results(&[&[expected.as_ref()]], &query, &test_conn, &conn);
+    }
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 2);
-    assert_eq!(columns.get(0), Some(&Value::Integer(1)));
-    assert_eq!(columns.get(1), Some(&Value::Text(b"10".as_slice().into())));
-    drop(row);
+    // Later arguments are not evaluated once a non-NULL value is found.
+    for (expected, expr) in [
+        (
+            Value::Integer(1),
+            "coalesce(NULL, 1, 'a' LIKE 'a' ESCAPE 'ab')",
+        ),
+        (Value::Integer(1), "ifnull(1, 'a' LIKE 'a' ESCAPE 'ab')"),
+    ] {
+        let query = format!("SELECT {} FROM example;", expr);
+        let stmt = conn.prepare(&query).unwrap();
+        let mut rows = stmt.query().unwrap();
+        let row = rows.next_row().unwrap().unwrap();
+        assert_eq!(row.parse().unwrap().get(0), Some(&expected), "{}", query);
+    }
+
+    let stmt = conn
+        .prepare("SELECT abs(-9223372036854775808) FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    assert!(row.parse().is_err());
+
+    for query in [
+        "SELECT abs() FROM example;",
+        "SELECT abs(1, 2) FROM example;",
+        "SELECT round(1, 2, 3) FROM example;",
+        "SELECT coalesce(1) FROM example;",
+        "SELECT ifnull(1, 2, 3) FROM example;",
+        "SELECT nullif(1) FROM example;",
+    ] {
+        assert!(conn.prepare(query).is_err(), "{}", query);
+        assert!(test_conn.prepare(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn test_select_user_function() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (1, 'a');",
+        "INSERT INTO example(col1, col2) VALUES (2, 'b');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 'c');",
+        "INSERT INTO example(col1, col2) VALUES (4, 'd');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    assert!(conn.prepare("SELECT double(col1) FROM example;").is_err());
+
+    conn.create_function("DOUBLE", 1, |args| match &args[0] {
+        None => Ok(None),
+        Some(Value::Integer(i)) => Ok(Some(Value::Integer(i * 2))),
+        Some(Value::Real(d)) => Ok(Some(Value::Real(d * 2.0))),
+        Some(_) => anyhow::bail!("not a number"),
+    })
+    .unwrap();
+
+    let stmt = conn.prepare("SELECT double(col1) FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    for expected in [Some(Value::Integer(2)), Some(Value::Integer(4)), None] {
+        let row = rows.next_row().unwrap().unwrap();
+        assert_eq!(row.parse().unwrap().get(0), expected.as_ref());
+    }
+    drop(rows);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE double(col1) > 3;"),
+        vec![2, 4]
+    );
+    assert_eq!(
+        load_rowids(
+            &conn,
+            "SELECT double(double(col1) + 1) FROM example WHERE col1 = 1;"
+        ),
+        vec![6]
+    );
+    let mut stmt = conn
+        .prepare("SELECT rowid FROM example WHERE double(?) = col1;")
+        .unwrap();
+    stmt.bind(1, Some(Value::Integer(2))).unwrap();
+    let rows = stmt
+        .query()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(4)));
+
+    // Arguments are validated.
+    assert!(conn
+        .prepare("SELECT double(col1, 1) FROM example;")
+        .is_err());
+    let stmt = conn.prepare("SELECT double(col2) FROM example;").unwrap();
+    assert!(stmt
+        .query()
+        .unwrap()
+        .next_row()
+        .unwrap()
+        .unwrap()
+        .parse()
+        .is_err());
+
+    // User defined functions override built-in functions.
+    let stmt = conn
+        .prepare("SELECT abs(-1) FROM example WHERE rowid = 1;")
+        .unwrap();
+    conn.create_function("abs", 1, |_| Ok(Some(Value::Integer(100))))
+        .unwrap();
+    assert_eq!(
+        load_rowids(&conn, "SELECT abs(-1) FROM example WHERE rowid = 1;"),
+        vec![100]
+    );
+    let rows = stmt
+        .query()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(1)));
+
+    // The function can be used to insert values.
+    let stmt = conn
+        .prepare("INSERT INTO example(col1) VALUES (double(10));")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    assert_eq!(
+        load_rowids(&conn, "SELECT col1 FROM example WHERE rowid = 5;"),
+        vec![20]
+    );
+
+    assert!(conn.create_function("", 1, |_| Ok(None)).is_err());
+    assert!(conn.create_function("f", 128, |_| Ok(None)).is_err());
+}
+
+#[test]
+fn test_select_typeof() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 INTEGER, col2 TEXT, col3 BLOB, col4 REAL);",
+        "INSERT INTO example(col1, col2, col3, col4) VALUES (1, 2, 3, 4);",
+        "INSERT INTO example(col1, col2, col3, col4) VALUES ('abc', 'def', 'ghi', '5.5');",
+        "INSERT INTO example(col1, col2, col3, col4) VALUES ('10', x'01', 2.5, NULL);",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    // A column declared INTEGER can store text which does not look like a number.
+    assert_same_results_as_sqlite(
+        "SELECT typeof(col1), typeof(col2), typeof(col3), typeof(col4) FROM example;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results_as_sqlite(
+        "SELECT typeof(NULL), TYPEOF(1), typeof(1.5), typeof('a'), typeof(x'01'), typeof(CAST(1 AS TEXT)), typeof(col1 || 1) FROM example;",
+        &test_conn,
+        &conn,
+    );
+    for (query, expected) in [
+        (
+            "SELECT rowid FROM example WHERE typeof(col1) = 'integer';",
+            vec![1, 3],
+        ),
+        (
+            "SELECT rowid FROM example WHERE typeof(col1) = 'text';",
+            vec![2],
+        ),
+        (
+            "SELECT rowid FROM example WHERE typeof(col4) != 'real';",
+            vec![3],
+        ),
+    ] {
+        assert_eq!(load_rowids(&conn, query), expected, "{}", query);
+        assert_eq!(load_test_rowids(&test_conn, query), expected, "{}", query);
+    }
+}
+
+#[test]
+fn test_select_quote_hex_char() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (1, x'27616227');",
+        "INSERT INTO example(col1, col2) VALUES ('it''s', x'00ff10277f');",
+        "INSERT INTO example(col1, col2) VALUES (2.5, 'a''b''');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, -3);",
+        "INSERT INTO example(col1, col2) VALUES ('é', x'');",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT quote(col1), quote(col2), hex(col1), hex(col2) FROM example;",
+        "SELECT quote(NULL), quote(-12), quote(1.5), quote(''), quote(x'01ab'), quote('a' || char(0) || 'b') FROM example WHERE rowid = 1;",
+        "SELECT hex(NULL), hex(''), hex(-12), hex(1.5), hex('a' || char(0) || 'b') FROM example WHERE rowid = 1;",
+        "SELECT char(), char(65, 66, 67), char(0x10ffff, 0xe9, 0x7ff), char(-1, 0x110000), char('66', 67.9) FROM example WHERE rowid = 1;",
+        "SELECT hex(char(0xd800)), hex(char(NULL)), hex(char(65, 0, 66)) FROM example WHERE rowid = 1;",
+        "SELECT rowid FROM example WHERE hex(col2) LIKE '%27%';",
+        "SELECT rowid FROM example WHERE quote(col1) = 'NULL';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // unhex() is not available in the SQLite linked by the tests.
+    let blob = |buf: &[u8]| Some(Value::Blob(buf.to_vec().into()));
+    for (expr, expected) in [
+        ("unhex('4142')", blob(b"AB")),
+        ("unhex('00ff7F')", blob(b"\x00\xff\x7f")),
+        ("unhex('')", blob(b"")),
+        ("unhex('414')", None),
+        ("unhex('41 42')", None),
+        ("unhex('41 42', ' ')", blob(b"AB")),
+        ("unhex('-41-42-', '-')", blob(b"AB")),
+        ("unhex('4 1', ' ')", None),
+        ("unhex(NULL)", None),
+        ("unhex('41', NULL)", None),
+        ("unhex(41)", blob(b"A")),
+        ("unhex(hex(x'00ff10277f'))", blob(b"\x00\xff\x10\x27\x7f")),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        let stmt = conn.prepare(&query).unwrap();
+        let mut rows = stmt.query().unwrap();
+        let row = rows.next_row().unwrap().unwrap();
+        let columns = row.parse().unwrap();
+        assert_eq!(columns.get(0).cloned(), expected, "{}", query);
+    }
+}
+
+#[test]
+fn test_select_lower_upper() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES ('Hello, World', x'41');",
+        "INSERT INTO example(col1, col2) VALUES ('ÉtÉ', 1.5);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, -3);",
+        "INSERT INTO example(col1, col2) VALUES ('a' || char(0) || 'B', 'aBc');",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT lower(col1), upper(col1), lower(col2), upper(col2) FROM example;",
+        "SELECT typeof(lower(col2)), typeof(upper(col1)) FROM example;",
+        "SELECT hex(lower(col1)), hex(upper(col1)) FROM example;",
+        "SELECT rowid FROM example WHERE upper(col2) = 'ABC';",
+        "SELECT rowid FROM example WHERE LOWER(col1) = 'hello, world';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_case() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 INTEGER, col2 TEXT COLLATE NOCASE, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'abc', 10);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'def', NULL);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'ABC', 30);",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for query in [
+        // Fall through to ELSE.
+        "SELECT CASE WHEN col1 = 1 THEN 'one' WHEN col1 = 2 THEN 'two' ELSE 'other' END FROM example;",
+        // No ELSE.
+        "SELECT CASE WHEN col1 = 1 THEN 'one' END FROM example;",
+        // NULL conditions are false.
+        "SELECT CASE WHEN col3 THEN 'true' WHEN NOT col3 THEN 'false' ELSE 'null' END FROM example;",
+        "SELECT CASE WHEN NULL THEN 1 ELSE 2 END FROM example;",
+        // The first matching branch wins.
+        "SELECT CASE WHEN col3 > 0 THEN 'a' WHEN col3 > 5 THEN 'b' END FROM example;",
+        // Simple form. NULL operand never matches.
+        "SELECT CASE col1 WHEN 1 THEN 'one' WHEN 2 THEN 'two' ELSE 'other' END FROM example;",
+        "SELECT CASE col1 WHEN NULL THEN 'null' ELSE 'other' END FROM example;",
+        "SELECT CASE NULL WHEN NULL THEN 'null' ELSE 'other' END FROM example;",
+        // Affinity and collation are applied to the comparison.
+        "SELECT CASE col1 WHEN '1' THEN 'one' WHEN '2.0' THEN 'two' END FROM example;",
+        "SELECT CASE '1' WHEN 1 THEN 'one' ELSE 'other' END FROM example;",
+        "SELECT CASE col2 WHEN 'abc' THEN 'abc' ELSE 'other' END FROM example;",
+        "SELECT CASE 'abc' WHEN col2 THEN 'abc' ELSE 'other' END FROM example;",
+        "SELECT CASE col2 COLLATE BINARY WHEN 'abc' THEN 'abc' ELSE 'other' END FROM example;",
+        // Nested CASE.
+        "SELECT CASE WHEN col1 IS NULL THEN CASE col2 WHEN 'abc' THEN 'nested' END ELSE CASE WHEN col3 IS NULL THEN col2 ELSE col3 END END FROM example;",
+        "SELECT CASE CASE col1 WHEN 1 THEN 2 ELSE 1 END WHEN 2 THEN 'two' ELSE 'one' END FROM example;",
+        "SELECT rowid FROM example WHERE CASE WHEN col1 IS NULL THEN 1 ELSE col1 = 2 END;",
+        "SELECT CASE WHEN count(*) > 2 THEN 'many' ELSE 'few' END FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // The other branches are not evaluated.
+    let stmt = conn
+        .prepare("SELECT CASE WHEN 1 THEN 1 ELSE 'a' LIKE 'a' ESCAPE 'ab' END FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    assert_eq!(row.parse().unwrap().get(0), Some(&Value::Integer(1)));
+}
+
+#[test]
+fn test_select_arithmetic() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (5, 2, NULL);",
+        "INSERT INTO example(col1, col2, col3) VALUES (-7, 0, 1.5);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('12', '3.5', 'abc');",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (expected, expr) in [
+        (Some(Value::Integer(2)), "5/2"),
+        (Some(Value::Real(2.5)), "5.0/2"),
+        (None, "1/0"),
+        (None, "1.5/0.0"),
+        (None, "1%0"),
+        (Some(Value::Text(b"a1".as_slice().into())), "'a' || 1"),
+        (None, "'a' || NULL"),
+        (Some(Value::Integer(-2)), "-5/2"),
+        (Some(Value::Integer(-1)), "-5%2"),
+        (Some(Value::Integer(1)), "5%-2"),
+        (Some(Value::Real(1.0)), "5.5%2"),
+        (Some(Value::Integer(0)), "-9223372036854775808 % -1"),
+        (Some(Value::Integer(7)), "1 + 2 * 3"),
+        (Some(Value::Integer(-1)), "1 - 2"),
+        (Some(Value::Integer(0)), "1 - 2 + 1"),
+        (Some(Value::Real(3.5)), "1 + 2.5"),
+        (
+            Some(Value::Real(9223372036854775808.0)),
+            "9223372036854775807 + 1",
+        ),
+        (
+            Some(Value::Real(-9223372036854775808.0)),
+            "-9223372036854775807 - 2",
+        ),
+        (
+            Some(Value::Real(1.8446744073709552e19)),
+            "9223372036854775807 * 2",
+        ),
+        (
+            Some(Value::Real(9223372036854775808.0)),
+            "-9223372036854775808 / -1",
+        ),
+        (
+            Some(Value::Real(9223372036854775808.0)),
+            "- -9223372036854775808",
+        ),
+        (Some(Value::Integer(5)), "'2' + '3'"),
+        (Some(Value::Real(5.5)), "'2.5' + 3"),
+        (Some(Value::Integer(3)), "'abc' + 3"),
+        (Some(Value::Integer(-5)), "-'5'"),
+        (Some(Value::Real(-1.5)), "-'1.5'"),
+        (Some(Value::Integer(0)), "-'abc'"),
+        (Some(Value::Integer(6)), "x'33' * 2"),
+        // Text with a decimal point or an exponent is a real value.
+        (Some(Value::Real(4.0)), "'3.0' + 1"),
+        (Some(Value::Real(100.0)), "'1e2' + 0"),
+        (Some(Value::Real(-3.0)), "-'3.0'"),
+        (Some(Value::Real(2.5)), "'1.5x' + 1"),
+        (Some(Value::Integer(12)), "'12abc' + 0"),
+        (Some(Value::Integer(1)), "'1e' + 0"),
+        (None, "NULL + 1"),
+        (None, "1 * NULL"),
+        // || binds tighter than * and +.
+        (Some(Value::Integer(47)), "1 + 2 || 3 * 2"),
+        (Some(Value::Integer(1)), "1 + 1 = 2"),
+        // Parentheses override the precedence.
+        (Some(Value::Integer(9)), "(1 + 2) * 3"),
+        (Some(Value::Integer(-1)), "1 - (1 + 1)"),
+        (Some(Value::Integer(3)), "(7 - 1) / (4 - 2)"),
+        (Some(Value::Integer(66)), "(1 + 2) || 3 * 2"),
+        (Some(Value::Integer(-6)), "-(2 * (1 + 2))"),
+        (Some(Value::Integer(0)), "((1 + 1) = 2) = 0"),
+        (None, "(1 + NULL) * 2"),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        assert_same_results(&[&[expected.as_ref()]], &query, &test_conn, &conn);
+    }
+
+    // Real values are converted to text with 15 significant digits.
+    for (expected, expr) in [
+        ("1.0e+20", "1e20 || ''"),
+        ("-1.0e+20", "-1e20 || ''"),
+        ("2.0", "2.0 || ''"),
+        ("0.0", "round(-0.4) || ''"),
+        ("x0.5", "'x' || 0.5"),
+        ("0.333333333333333", "(1.0 / 3) || ''"),
+        ("1.23456789012346e+17", "123456789012345678.0 || ''"),
+        ("123456789012345.0", "123456789012345.0 || ''"),
+        ("1.0e+15", "1e15 || ''"),
+        ("1.0e-05", "0.00001 || ''"),
+        ("0.0001", "0.0001 || ''"),
+        ("1.5e+15", "CAST(1.5e15 AS TEXT)"),
+        ("9.22337203685478e+18", "(9223372036854775807 + 1) || ''"),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        let expected = Value::Text(expected.as_bytes().into());
+        assert_same_results(&[&[Some(&expected)]], &query, &test_conn, &conn);
+    }
+
+    for query in [
+        "SELECT col1 + col2, col1 - col2, col1 * col2, col1 / col2, col1 % col2 FROM example;",
+        "SELECT col1 + col3, col1 / col3, col3 * 2, -col3, col2 / 2 FROM example;",
+        "SELECT rowid FROM example WHERE col1 * 2 > 10;",
+        "SELECT rowid FROM example WHERE col1 % 2 = 1 OR col2 / 0 IS NULL;",
+        "SELECT (col1 + col2) * col3, col1 * (col2 - col3), (col1 || col2) + 1 FROM example;",
+        "SELECT rowid FROM example WHERE (col1 + col2) * 2 > 10;",
+        "SELECT rowid FROM example WHERE -(col1 - col2) < 0 AND (col3 IS NULL OR col3 > 1);",
+        "SELECT col2 + 0, col2 * 2, col3 || '', col1 * 1.0 || '', col1 / 3.0 || '' FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_numeric_literals() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (255), (1500), (-1), (0.5);",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (expected, expr) in [
+        (Value::Integer(255), "0xFF"),
+        (Value::Integer(31), "0X1f"),
+        (Value::Integer(i64::MAX), "0x7FFFFFFFFFFFFFFF"),
+        (Value::Integer(i64::MIN), "0x8000000000000000"),
+        (Value::Integer(-1), "0xFFFFFFFFFFFFFFFF"),
+        (Value::Integer(1), "0x000000000000000000001"),
+        (Value::Integer(-16), "-0x10"),
+        (Value::Integer(257), "0x100 + 1"),
+        (Value::Real(1500.0), "1.5e3"),
+        (Value::Real(1500.0), "1.5E+3"),
+        (Value::Real(0.015), "1.5e-2"),
+        (Value::Real(0.5), ".5"),
+        (Value::Real(50.0), ".5e2"),
+        (Value::Real(1.0), "1."),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        assert_same_results(&[&[Some(&expected)]], &query, &test_conn, &conn);
+    }
+
+    for query in [
+        "SELECT rowid FROM example WHERE col = 0xFF;",
+        "SELECT rowid FROM example WHERE col = 1.5e3;",
+        "SELECT rowid FROM example WHERE col = -0x1;",
+        "SELECT rowid FROM example WHERE col = .5;",
+        "SELECT rowid FROM example WHERE col IN (0xff, 0xFFFFFFFFFFFFFFFF);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Hex literals wider than 64 bits are errors.
+    let query = "SELECT 0x10000000000000000 FROM example;";
+    assert!(test_conn.prepare(query).is_err());
+    assert!(conn.prepare(query).is_err());
+
+    // 0x without hexadecimal digits is not 0 followed by an alias.
+    for query in [
+        "SELECT 0x FROM example;",
+        "SELECT 0X FROM example;",
+        "SELECT 0xg FROM example;",
+    ] {
+        assert!(test_conn.prepare(query).is_err(), "{}", query);
+        assert!(conn.prepare(query).is_err(), "{}", query);
+    }
+    // Newer SQLite also rejects identifier characters after hexadecimal digits.
+    assert!(conn.prepare("SELECT 0x1g FROM example;").is_err());
+
+    // An identifier right after a numeric literal is not an alias.
+    for query in [
+        "SELECT 12abc FROM example;",
+        "SELECT 1.5x FROM example;",
+        "SELECT 1e3e FROM example;",
+        "SELECT .5_ FROM example;",
+        "SELECT col FROM example WHERE col = 255x;",
+    ] {
+        assert!(test_conn.prepare(query).is_err(), "{}", query);
+        assert!(conn.prepare(query).is_err(), "{}", query);
+    }
+    assert_same_results_as_sqlite("SELECT 12 abc FROM example;", &test_conn, &conn);
+}
+
+#[test]
+fn test_select_bitwise() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example(col1, col2) VALUES (12, 10);",
+        "INSERT INTO example(col1, col2) VALUES (-8, 2);",
+        "INSERT INTO example(col1, col2) VALUES ('7', NULL);",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (expected, expr) in [
+        (Some(Value::Integer(8)), "1 << 3"),
+        (Some(Value::Integer(-1)), "~0"),
+        (Some(Value::Integer(2)), "8 >> 2"),
+        (Some(Value::Integer(2)), "8 << -2"),
+        (Some(Value::Integer(32)), "8 >> -2"),
+        (Some(Value::Integer(-2)), "-8 >> 2"),
+        (Some(Value::Integer(-2)), "-8 << -2"),
+        (Some(Value::Integer(0)), "1 << 64"),
+        (Some(Value::Integer(i64::MIN)), "1 << 63"),
+        (Some(Value::Integer(0)), "-1 << 64"),
+        (Some(Value::Integer(0)), "1 >> 64"),
+        (Some(Value::Integer(-1)), "-1 >> 64"),
+        (Some(Value::Integer(-1)), "-5 >> 100"),
+        (Some(Value::Integer(-1)), "-5 << -9223372036854775808"),
+        (Some(Value::Integer(0)), "5 >> -9223372036854775808"),
+        (Some(Value::Integer(8)), "12 & 10"),
+        (Some(Value::Integer(14)), "12 | 10"),
+        (Some(Value::Integer(2)), "'2.9' & 3"),
+        (Some(Value::Integer(3)), "1.5 | x'32'"),
+        (Some(Value::Integer(0)), "'abc' | 0"),
+        (None, "NULL & 1"),
+        (None, "1 << NULL"),
+        // Bitwise operators bind looser than + and tighter than <.
+        (Some(Value::Integer(32)), "1 << 2 + 3"),
+        (Some(Value::Integer(1)), "1 | 2 < 4"),
+        (Some(Value::Integer(2)), "6 & 3 | 0"),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        assert_same_results(&[&[expected.as_ref()]], &query, &test_conn, &conn);
+    }
+
+    for query in [
+        "SELECT col1 & col2, col1 | col2, col1 << col2, col1 >> col2, ~col1 FROM example;",
+        "SELECT rowid FROM example WHERE col1 & 4;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_expression_three_valued_logic() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1);",
+        "INSERT INTO example(col1) VALUES (1);",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for left in ["NULL", "0", "1", "0.5", "'abc'"] {
+        for right in ["NULL", "0", "1", "0.5", "'abc'"] {
+            for op in ["AND", "OR"] {
+                let query = format!("SELECT {left} {op} {right} FROM example;");
+                let mut stmt = test_conn.prepare(&query).unwrap();
+                let expected = stmt
+                    .query_row([], |row| row.get::<_, Option<i64>>(0))
+                    .unwrap()
+                    .map(Value::Integer);
+                assert_same_results(&[&[expected.as_ref()]], &query, &test_conn, &conn);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_select_primary_key() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col text);",
+        "INSERT INTO example(id, col) VALUES (1, '10');",
+        "INSERT INTO example(id, col) VALUES (5, '20');",
+        "INSERT INTO example(id, col) VALUES (3, '30');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn.prepare("SELECT * FROM example;").unwrap();
+    stmt.query().unwrap();
+    let mut rows = stmt.query().unwrap();
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(columns.get(0), Some(&Value::Integer(1)));
+    assert_eq!(columns.get(1), Some(&Value::Text(b"10".as_slice().into())));
+    drop(row);
 
     let row = rows.next_row().unwrap().unwrap();
     let columns = row.parse().unwrap();
@@ -975,229 +2286,2770 @@ fn test_select_filter_compare() {
 }
 
 #[test]
-fn test_select_filter_with_rowid() {
+fn test_select_filter_not_in() {
     let file = create_sqlite_database(&[
-        "CREATE TABLE example(col);",
-        "INSERT INTO example(col) VALUES (10);",
-        "INSERT INTO example(col) VALUES (20);",
-        "INSERT INTO example(col) VALUES (30);",
-        "INSERT INTO example(col) VALUES (40);",
+        "CREATE TABLE example(col1, col2 integer, col3 text);",
+        "INSERT INTO example(col1, col2, col3) VALUES (null, null, null);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 1, 1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 2, 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('1', '1', '1');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('abc', 'abc', 'abc');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('ABC', 'ABC', 'ABC');",
+        "INSERT INTO example(col1, col2, col3) VALUES (1.5, 1.5, 1.5);",
+        "INSERT INTO example(col1, col2, col3) VALUES (x'01', x'01', x'01');",
     ]);
 
     let conn = Connection::open(file.path()).unwrap();
-    let stmt = conn
-        .prepare("SELECT col, RoWid FROM example WHERE rowid = 2;")
-        .unwrap();
-    let mut rows = stmt.query().unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 2);
-    assert_eq!(columns.get(0), Some(&Value::Integer(20)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(2)));
-    drop(row);
+    for column in ["col1", "col2", "col3"] {
+        for list in [
+            "()",
+            "(null)",
+            "(1)",
+            "(1, null)",
+            "(1, 2)",
+            "(1, 2, null)",
+            "('1')",
+            "('1', null)",
+            "('abc', 1.5)",
+            "('abc' collate nocase)",
+            "(x'01', 3)",
+            "(col1, 2)",
+            "(col3)",
+        ] {
+            for op in ["IN", "NOT IN"] {
+                let query = format!("SELECT rowid FROM example WHERE {column} {op} {list};");
+                assert_eq!(
+                    load_rowids(&conn, &query),
+                    load_test_rowids(&test_conn, &query),
+                    "query: {}",
+                    query
+                );
+            }
+        }
+    }
 
-    assert!(rows.next_row().unwrap().is_none());
+    // NOT IN with a list containing NULL never be true.
+    assert!(load_rowids(
+        &conn,
+        "SELECT rowid FROM example WHERE col1 NOT IN (1, null);"
+    )
+    .is_empty());
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col1 NOT IN (1);"),
+        vec![3, 4, 5, 6, 7, 8]
+    );
+    // x NOT IN () is true even if x is NULL.
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col1 NOT IN ();"),
+        vec![1, 2, 3, 4, 5, 6, 7, 8]
+    );
 
-    // TODO: Test with rowid = '2'
+    for (expected, expr) in [
+        (None, "1 NOT IN (2, null)"),
+        (Some(Value::Integer(0)), "1 NOT IN (1, null)"),
+        (Some(Value::Integer(1)), "1 NOT IN (2, 3)"),
+        (None, "null NOT IN (1)"),
+        (Some(Value::Integer(1)), "null NOT IN ()"),
+        (None, "1 IN (2, null)"),
+        (Some(Value::Integer(1)), "1 IN (1, null)"),
+        (Some(Value::Integer(0)), "null IN ()"),
+    ] {
+        let query = format!("SELECT {} FROM example WHERE rowid = 1;", expr);
+        assert_same_results(&[&[expected.as_ref()]], &query, &test_conn, &conn);
+    }
 }
 
 #[test]
-fn test_select_filter_with_primary_key() {
+fn test_select_filter_is() {
     let file = create_sqlite_database(&[
-        "CREATE TABLE example(id integer primary key, col text);",
-        "INSERT INTO example(id, col) VALUES (1, '10');",
-        "INSERT INTO example(id, col) VALUES (3, '20');",
-        "INSERT INTO example(id, col) VALUES (5, '30');",
-        "INSERT INTO example(id, col) VALUES (6, '40');",
+        "CREATE TABLE example(col1, col2 integer, col3);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "INSERT INTO example VALUES (null, null, null);",
+        "INSERT INTO example VALUES (1, 1, 1);",
+        "INSERT INTO example VALUES ('1', '1', '1');",
+        "INSERT INTO example VALUES (null, null, null);",
+        "INSERT INTO example VALUES ('abc', 'abc', 'abc');",
+        "INSERT INTO example VALUES (1.5, 1.5, 1.5);",
     ]);
+
     let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // col3 is not indexed.
+    for column in ["col1", "col2", "col3"] {
+        for filter in [
+            "{} = NULL",
+            "{} != NULL",
+            "{} IS NULL",
+            "{} IS NOT NULL",
+            "{} ISNULL",
+            "{} NOTNULL",
+            "{} NOT NULL",
+            "NULL IS {}",
+            "{} IS 1",
+            "{} IS '1'",
+            "{} IS NOT 1",
+            "{} IS 1.5",
+            "{} = NULL IS NULL",
+            "{} IS NULL = 0",
+        ] {
+            let filter = filter.replace("{}", column);
+            let query = format!("SELECT rowid FROM example WHERE {filter};");
+            // SQLite may scan a covering index and return rows in a different order.
+            let mut rowids = load_rowids(&conn, &query);
+            rowids.sort();
+            let mut expected = load_test_rowids(&test_conn, &query);
+            expected.sort();
+            assert_eq!(rowids, expected, "query: {}", query);
+        }
+    }
 
+    // IS returns 0 or 1 instead of NULL.
     let stmt = conn
-        .prepare("SELECT col, RoWid FROM example WHERE id = 3;")
+        .prepare("SELECT col1 = NULL, col1 IS NULL, col1 IS NOT NULL, NULL IS NULL FROM example;")
         .unwrap();
     let mut rows = stmt.query().unwrap();
     let row = rows.next_row().unwrap().unwrap();
     let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 2);
-    assert_eq!(columns.get(0), Some(&Value::Text(b"20".as_slice().into())));
-    assert_eq!(columns.get(1), Some(&Value::Integer(3)));
+    assert_eq!(columns.get(0), None);
+    assert_eq!(columns.get(1), Some(&Value::Integer(1)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(0)));
+    assert_eq!(columns.get(3), Some(&Value::Integer(1)));
     drop(row);
-    assert!(rows.next_row().unwrap().is_none());
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.get(0), None);
+    assert_eq!(columns.get(1), Some(&Value::Integer(0)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(1)));
+    assert_eq!(columns.get(3), Some(&Value::Integer(1)));
+}
 
-    let stmt = conn
-        .prepare("SELECT col, RoWid FROM example WHERE id = 4;")
-        .unwrap();
-    let mut rows = stmt.query().unwrap();
-    assert!(rows.next_row().unwrap().is_none());
+#[test]
+fn test_select_filter_in_with_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 integer, col3 text COLLATE NOCASE);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "CREATE INDEX index3 ON example(col3, col1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (null, null, null);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 2, 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 1, 1);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('1', '1', '1');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('abc', 'abc', 'abc');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('ABC', 'ABC', 'ABC');",
+        "INSERT INTO example(col1, col2, col3) VALUES (1.5, 1.5, 1.5);",
+        "INSERT INTO example(col1, col2, col3) VALUES (x'01', x'01', x'01');",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 2, 2);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for column in ["col1", "col2", "col3"] {
+        for list in [
+            "()",
+            "(null)",
+            "(1)",
+            "(2, 1)",
+            "(1, null, 2)",
+            "(1, 1, '1')",
+            "('1', 1.5)",
+            "('abc', 'ABC', 3)",
+            "(x'01', 'abc', 2, 1.5, null)",
+        ] {
+            let query = format!("SELECT rowid FROM example WHERE {column} IN {list};");
+            assert_eq!(
+                load_rowids(&conn, &query),
+                load_test_rowids(&test_conn, &query),
+                "query: {}",
+                query
+            );
+        }
+    }
 }
 
 #[test]
-fn test_select_with_index() {
+fn test_select_filter_compare_with_index() {
     let file = create_sqlite_database(&[
-        "CREATE TABLE example(col1, col2, col3);",
-        "CREATE INDEX index1 ON example(col2, col3);",
-        "CREATE INDEX index2 ON example(col3);",
-        "INSERT INTO example(col1, col2, col3) VALUES (1, 2, 3);",
-        "INSERT INTO example(col1, col2, col3) VALUES (4, 5, 6);",
-        "INSERT INTO example(col1, col2, col3) VALUES (7, 8, 9);",
-        "INSERT INTO example(col1, col2, col3) VALUES (10, 5, 2);",
-        "INSERT INTO example(col1, col2, col3) VALUES (3, 3, 3);",
+        "CREATE TABLE example(col1, col2 integer, col3 text COLLATE NOCASE, col4);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "CREATE INDEX index3 ON example(col3, col1);",
+        "INSERT INTO example VALUES (null, null, null, null);",
+        "INSERT INTO example VALUES (2, 2, 2, 2);",
+        "INSERT INTO example VALUES (1, 1, 1, 1);",
+        "INSERT INTO example VALUES ('1', '1', '1', '1');",
+        "INSERT INTO example VALUES ('abc', 'abc', 'abc', 'abc');",
+        "INSERT INTO example VALUES ('ABC', 'ABC', 'ABC', 'ABC');",
+        "INSERT INTO example VALUES ('abd', 'abd', 'abd', 'abd');",
+        "INSERT INTO example VALUES (1.5, 1.5, 1.5, 1.5);",
+        "INSERT INTO example VALUES (x'01', x'01', x'01', x'01');",
+        "INSERT INTO example VALUES (2, 2, 2, 2);",
     ]);
 
     let conn = Connection::open(file.path()).unwrap();
-    let stmt = conn
-        .prepare("SELECT * FROM example WHERE col2 == 5;")
-        .unwrap();
-    let mut rows = stmt.query().unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 3);
-    assert_eq!(columns.get(0), Some(&Value::Integer(10)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
-    assert_eq!(columns.get(2), Some(&Value::Integer(2)));
-    drop(row);
+    // col4 is not indexed and falls back to a full scan.
+    for column in ["col1", "col2", "col3", "col4"] {
+        for op in ["<", "<=", ">", ">="] {
+            for value in [
+                "1", "1.5", "'1'", "'ABC'", "'abd'", "x'00'", "x'01'", "null",
+            ] {
+                for query in [
+                    format!("SELECT rowid FROM example WHERE {column} {op} {value};"),
+                    format!("SELECT rowid FROM example WHERE {value} {op} {column};"),
+                ] {
+                    assert_eq!(
+                        load_rowids(&conn, &query),
+                        load_test_rowids(&test_conn, &query),
+                        "query: {}",
+                        query
+                    );
+                }
+            }
+        }
+    }
+}
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 3);
-    assert_eq!(columns.get(0), Some(&Value::Integer(4)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
-    assert_eq!(columns.get(2), Some(&Value::Integer(6)));
-    drop(row);
+#[test]
+fn test_select_filter_folded_constant() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 integer, col3 text COLLATE NOCASE);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "CREATE INDEX index3 ON example(col3);",
+        "INSERT INTO example VALUES (null, null, null);",
+        "INSERT INTO example VALUES (5, 5, 5);",
+        "INSERT INTO example VALUES ('5', '5', '5');",
+        "INSERT INTO example VALUES (5.5, 5.5, 5.5);",
+        "INSERT INTO example VALUES ('ab', 'ab', 'ab');",
+        "INSERT INTO example VALUES ('AB', 'AB', 'AB');",
+        "INSERT INTO example VALUES (-1, -1, -1);",
+        "INSERT INTO example VALUES (0, 0, 0);",
+    ]);
 
-    assert!(rows.next_row().unwrap().is_none());
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    let stmt = conn
-        .prepare("SELECT * FROM example WHERE col3 == 6;")
-        .unwrap();
-    let mut rows = stmt.query().unwrap();
+    for column in ["col1", "col2", "col3"] {
+        for value in [
+            "2 + 3",
+            "'2' + 3",
+            "11 / 2",
+            "11.0 / 2",
+            "1 / 0",
+            "-1",
+            "- -5",
+            "~0",
+            "'a' || 'b'",
+            "'A' || 'B'",
+            "CAST(5 AS TEXT)",
+            "abs(-5)",
+            "coalesce(NULL, 5)",
+            "1 = 1",
+            "NULL + 1",
+            "CASE WHEN 1 THEN 5 ELSE 0 END",
+        ] {
+            for query in [
+                format!("SELECT rowid FROM example WHERE {column} = {value};"),
+                format!("SELECT rowid FROM example WHERE {column} >= {value};"),
+                format!("SELECT rowid FROM example WHERE {column} IN ({value}, 0);"),
+                format!("SELECT rowid FROM example WHERE {column} BETWEEN {value} AND 10;"),
+            ] {
+                // The order depends on whether an index is used.
+                let mut rowids = load_rowids(&conn, &query);
+                rowids.sort();
+                let mut expected = load_test_rowids(&test_conn, &query);
+                expected.sort();
+                assert_eq!(rowids, expected, "query: {}", query);
+            }
+        }
+    }
+}
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 3);
-    assert_eq!(columns.get(0), Some(&Value::Integer(4)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
-    assert_eq!(columns.get(2), Some(&Value::Integer(6)));
-    drop(row);
+#[test]
+fn test_select_filter_compare_with_index_multiple_pages() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, {});",
+            (i * 7) % 500,
+            i
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
 
-    assert!(rows.next_row().unwrap().is_none());
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
+    // Rows are returned in the index order.
     let stmt = conn
-        .prepare("SELECT * FROM example WHERE col3 == 3;")
+        .prepare("SELECT col1 FROM example WHERE col1 > 100;")
         .unwrap();
     let mut rows = stmt.query().unwrap();
+    let mut values = Vec::new();
+    while let Some(row) = rows.next_row().unwrap() {
+        let columns = row.parse().unwrap();
+        let Some(Value::Integer(value)) = columns.get(0) else {
+            panic!("unexpected value");
+        };
+        values.push(*value);
+    }
+    let expected = (101..500).flat_map(|v| [v, v]).collect::<Vec<_>>();
+    assert_eq!(values, expected);
+
+    for filter in [
+        "col1 > 100",
+        "col1 >= 499",
+        "col1 < 10",
+        "col1 <= -1",
+        "250 < col1",
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {filter};");
+        assert_eq!(
+            load_rowids(&conn, &query),
+            load_test_rowids(&test_conn, &query),
+            "query: {}",
+            query
+        );
+    }
+}
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 3);
-    assert_eq!(columns.get(0), Some(&Value::Integer(1)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(2)));
-    assert_eq!(columns.get(2), Some(&Value::Integer(3)));
-    drop(row);
+#[test]
+fn test_select_filter_with_desc_index() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1 DESC);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, -1);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, -2);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, {});",
+            (i * 7) % 500,
+            i
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
 
-    let row = rows.next_row().unwrap().unwrap();
-    let columns = row.parse().unwrap();
-    assert_eq!(columns.len(), 3);
-    assert_eq!(columns.get(0), Some(&Value::Integer(3)));
-    assert_eq!(columns.get(1), Some(&Value::Integer(3)));
-    assert_eq!(columns.get(2), Some(&Value::Integer(3)));
-    drop(row);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    assert!(rows.next_row().unwrap().is_none());
+    // Rows are returned in the descending index order.
+    let stmt = conn
+        .prepare("SELECT col1 FROM example WHERE col1 > 100;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let mut values = Vec::new();
+    while let Some(row) = rows.next_row().unwrap() {
+        let columns = row.parse().unwrap();
+        let Some(Value::Integer(value)) = columns.get(0) else {
+            panic!("unexpected value");
+        };
+        values.push(*value);
+    }
+    let expected = (101..500).rev().flat_map(|v| [v, v]).collect::<Vec<_>>();
+    assert_eq!(values, expected);
+
+    for filter in [
+        "col1 = 100",
+        "col1 = 500",
+        "col1 > 100",
+        "col1 >= 499",
+        "col1 < 10",
+        "col1 <= -1",
+        "250 < col1",
+        "col1 BETWEEN 10 AND 20",
+        "col1 IN (3, 400, 3, 1000)",
+        "col1 = 5 OR col1 > 490 OR col1 IS NULL",
+        "col1 IS NULL",
+        "col1 IS NOT NULL",
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {filter};");
+        let mut rowids = load_rowids(&conn, &query);
+        rowids.sort();
+        let mut expected = load_test_rowids(&test_conn, &query);
+        expected.sort();
+        assert_eq!(rowids, expected, "query: {}", query);
+    }
+    assert_same_results_as_sqlite(
+        "SELECT col1, col2 FROM example WHERE col1 >= 490 ORDER BY col1, col2;",
+        &test_conn,
+        &conn,
+    );
 }
 
 #[test]
-fn test_select_with_index_multi_type() {
+fn test_select_filter_between_with_index() {
     let file = create_sqlite_database(&[
-        "CREATE TABLE example(col1, col2 INTEGER, col3 TEXT);",
+        "CREATE TABLE example(col1, col2 integer, col3 text COLLATE NOCASE, col4);",
         "CREATE INDEX index1 ON example(col1);",
         "CREATE INDEX index2 ON example(col2);",
-        "CREATE INDEX index3 ON example(col3);",
-        "INSERT INTO example(col1, col2, col3) VALUES ('abc', 'abc', 'abc');",
-        "INSERT INTO example(col1, col2, col3) VALUES ('1234', '1234', '1234');",
-        "INSERT INTO example(col1, col2, col3) VALUES (x'31323334', x'31323334', x'31323334');",
-        "INSERT INTO example(col1, col2, col3) VALUES (1234, 1234, 1234);",
-        "INSERT INTO example(col1, col2, col3) VALUES (1234.5, 1234.5, 1234.5);",
-        "INSERT INTO example(col1, col2, col3) VALUES (NULL, NULL, NULL);",
-        "INSERT INTO example(col1, col2, col3) VALUES (1234, 1234, 1234);",
-        "INSERT INTO example(col1, col2, col3) VALUES ('1234', '1234', '1234');",
-        "INSERT INTO example(col1, col2, col3) VALUES ('01234', '01234', '01234');",
+        "CREATE INDEX index3 ON example(col3, col1);",
+        "INSERT INTO example VALUES (null, null, null, null);",
+        "INSERT INTO example VALUES (2, 2, 2, 2);",
+        "INSERT INTO example VALUES (1, 1, 1, 1);",
+        "INSERT INTO example VALUES ('1', '1', '1', '1');",
+        "INSERT INTO example VALUES ('abc', 'abc', 'abc', 'abc');",
+        "INSERT INTO example VALUES ('ABC', 'ABC', 'ABC', 'ABC');",
+        "INSERT INTO example VALUES ('abd', 'abd', 'abd', 'abd');",
+        "INSERT INTO example VALUES (1.5, 1.5, 1.5, 1.5);",
+        "INSERT INTO example VALUES (x'01', x'01', x'01', x'01');",
+        "INSERT INTO example VALUES (2, 2, 2, 2);",
     ]);
 
+    let conn = Connection::open(file.path()).unwrap();
     let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // col4 is not indexed and falls back to a full scan.
+    for column in ["col1", "col2", "col3", "col4"] {
+        for range in [
+            "1 AND 2",
+            "1 AND 1",
+            "2 AND 1",
+            "1.5 AND 3",
+            "'1' AND '2'",
+            "0 AND 'abc'",
+            "'ABC' AND 'abd'",
+            "'abc' AND x'00'",
+            "x'00' AND x'02'",
+            "null AND 2",
+            "1 AND null",
+        ] {
+            let query = format!("SELECT rowid FROM example WHERE {column} BETWEEN {range};");
+            assert_eq!(
+                load_rowids(&conn, &query),
+                load_test_rowids(&test_conn, &query),
+                "query: {}",
+                query
+            );
+        }
+    }
+}
+
+#[test]
+fn test_select_filter_between_with_index_multiple_pages() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, {});",
+            (i * 7) % 500,
+            (i * 7) % 500
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
+
     let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    for (expected, query) in [
-        (vec![4, 7], "col1 = 1234"),
-        (vec![2, 4, 7, 8, 9], "col2 = 1234"),
-        (vec![2, 4, 7, 8], "col3 = 1234"),
-        (vec![], "col1 = 0"),
-        (vec![], "col2 = 0"),
-        (vec![], "col3 = 0"),
-        (vec![2, 8], "col1 = '1234'"),
-        (vec![2, 4, 7, 8, 9], "col2 = '1234'"),
-        (vec![2, 4, 7, 8], "col3 = '1234'"),
-        (vec![9], "col1 = '01234'"),
-        (vec![2, 4, 7, 8, 9], "col2 = '01234'"),
-        (vec![9], "col3 = '01234'"),
-        (vec![], "col1 = '0'"),
-        (vec![], "col2 = '0'"),
-        (vec![], "col3 = '0'"),
-        (vec![5], "col1 = 1234.5"),
-        (vec![5], "col2 = 1234.5"),
-        (vec![5], "col3 = 1234.5"),
-        (vec![3], "col1 = x'31323334'"),
-        (vec![3], "col2 = x'31323334'"),
-        (vec![3], "col3 = x'31323334'"),
+    for range in [
+        "0 AND 499",
+        "-10 AND 10",
+        "100 AND 250",
+        "250 AND 260",
+        "490 AND 1000",
     ] {
-        let query = format!("SELECT rowid FROM example WHERE {};", query);
-        let mut stmt = test_conn.prepare(&query).unwrap();
-        let rows = stmt.query([]).unwrap();
-        let rowids: Vec<i64> = rows.mapped(|r| r.get(0)).map(|v| v.unwrap()).collect();
-        assert_eq!(rowids, expected, "query: {}", query);
+        let query = format!("SELECT rowid FROM example WHERE col1 BETWEEN {range};");
+        let rowids = load_rowids(&conn, &query);
+        assert_eq!(
+            rowids,
+            load_test_rowids(&test_conn, &query),
+            "query: {}",
+            query
+        );
+        let query = format!("SELECT rowid FROM example WHERE col2 BETWEEN {range};");
+        let mut full_scan_rowids = load_rowids(&conn, &query);
+        assert_eq!(
+            full_scan_rowids,
+            load_test_rowids(&test_conn, &query),
+            "query: {}",
+            query
+        );
+        let mut rowids = rowids;
+        rowids.sort();
+        full_scan_rowids.sort();
+        assert_eq!(rowids, full_scan_rowids, "range: {}", range);
+    }
+}
 
-        let results = load_rowids(&conn, &query);
+#[test]
+fn test_select_filter_pattern_with_index() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1 text, col2 text, col3 text);",
+        "CREATE INDEX index1 ON example(col1 COLLATE NOCASE);",
+        "CREATE INDEX index2 ON example(col2);",
+        "INSERT INTO example VALUES (NULL, NULL, NULL);",
+        "INSERT INTO example VALUES (x'616263', x'616263', x'616263');",
+        "INSERT INTO example VALUES ('ab\u{3042}', 'ab\u{3042}', 'ab\u{3042}');",
+        "INSERT INTO example VALUES ('abz\u{ff}', 'abz\u{ff}', 'abz\u{ff}');",
+        "INSERT INTO example VALUES ('AB[', 'AB[', 'AB[');",
+        "INSERT INTO example VALUES ('ab_c', 'ab_c', 'ab_c');",
+        "INSERT INTO example VALUES (10, 10, 10);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        let value = format!("'{}{}'", ["abc", "ABD", "aBz", "xyz", "ab"][i % 5], i);
+        insert_stmts.push(format!(
+            "INSERT INTO example VALUES ({value}, {value}, {value});"
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
 
-        assert_eq!(results, expected, "query: {}", query);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for pattern in [
+        "LIKE 'abc%'",
+        "LIKE 'ABC1%'",
+        "LIKE 'ab%'",
+        "LIKE 'abz%'",
+        "LIKE 'AB[%'",
+        "LIKE 'ab_c%'",
+        "LIKE 'ab\u{3042}%'",
+        "LIKE '1%'",
+        "LIKE 'abc'",
+        "LIKE 'x_z9%9'",
+        "GLOB 'abc*'",
+        "GLOB 'aBz1*'",
+        "GLOB 'AB*'",
+        "GLOB 'ab?c'",
+        "GLOB 'ab[_]*'",
+        "GLOB 'ab\u{3042}*'",
+        "GLOB '1*'",
+    ] {
+        // SQLite may be built not to match BLOBs by LIKE and GLOB.
+        let query =
+            format!("SELECT rowid FROM example WHERE col3 {pattern} AND typeof(col3) != 'blob';");
+        assert_eq!(
+            load_rowids(&conn, &query),
+            load_test_rowids(&test_conn, &query),
+            "query: {}",
+            query
+        );
+        // col3 is not indexed.
+        let query = format!("SELECT rowid FROM example WHERE col3 {pattern};");
+        let full_scan_rowids = load_rowids(&conn, &query);
+        for column in ["col1", "col2"] {
+            let query = format!("SELECT rowid FROM example WHERE {column} {pattern};");
+            let mut rowids = load_rowids(&conn, &query);
+            rowids.sort();
+            assert_eq!(rowids, full_scan_rowids, "query: {}", query);
+        }
     }
 }
 
 #[test]
-fn test_select_with_index_same_items() {
-    let mut queries = vec![
+fn test_select_filter_or_with_index() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
         "CREATE TABLE example(col1, col2, col3);",
-        "CREATE INDEX index1 ON example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1);",
     ];
-    queries.resize(
-        queries.len() + 100,
-        "INSERT INTO example(col1, col2, col3) VALUES ('abc', NULL, NULL);",
-    );
-    queries.resize(
-        queries.len() + 100,
-        "INSERT INTO example(col1, col2, col3) VALUES (123, NULL, NULL);",
-    );
-    queries.resize(
-        queries.len() + 100,
-        "INSERT INTO example(col1, col2, col3) VALUES (NULL, NULL, NULL);",
-    );
-    let file = create_sqlite_database(&queries);
+    let mut insert_stmts = Vec::with_capacity(1000);
+    for i in 0..1000 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2, col3) VALUES ({}, {}, '{}');",
+            (i * 7) % 500,
+            (i * 7) % 500,
+            // Make the table spread over many pages.
+            "a".repeat(100)
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    stmts.push("INSERT INTO example(col1, col2) VALUES (NULL, NULL);");
+    let file = create_sqlite_database(&stmts);
 
-    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
     let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
 
-    for (expected, query) in [
-        ((1..=100).collect::<Vec<i64>>(), "col1 = 'abc'"),
-        ((101..=200).collect::<Vec<i64>>(), "col1 = 123"),
+    for filter in [
+        "{} = 1 OR {} = 2",
+        "{} = 2 OR {} = 1 OR {} = 2",
+        "{} = 1 OR {} IN (3, 1, 5)",
+        "{} < 5 OR {} > 495",
+        "{} BETWEEN 10 AND 15 OR {} BETWEEN 13 AND 18 OR {} = 30",
+        "{} IS NULL OR {} = 0",
+        "{} = 1 OR {} = 2 AND rowid > 100",
+        "{} >= 499 OR {} = 499.0 OR {} = '499'",
+        "({} = 1 OR {} = 2) AND rowid > 100",
+        "({} < 5 OR {} > 495) AND (rowid < 100 OR rowid > 900)",
     ] {
-        let query = format!("SELECT rowid FROM example WHERE {};", query);
-        let mut stmt = test_conn.prepare(&query).unwrap();
-        let rows = stmt.query([]).unwrap();
-        let rowids: Vec<i64> = rows.mapped(|r| r.get(0)).map(|v| v.unwrap()).collect();
-        assert_eq!(rowids, expected, "query: {}", query);
+        let query = format!(
+            "SELECT rowid FROM example WHERE {};",
+            filter.replace("{}", "col1")
+        );
+        let stats = conn.cache_stats();
+        let rowids = load_rowids(&conn, &query);
+        let stats_after = conn.cache_stats();
+        let n_lookups = stats_after.hits + stats_after.misses - stats.hits - stats.misses;
+        assert!(!rowids.is_empty(), "query: {}", query);
+        let mut expected = load_test_rowids(&test_conn, &query);
+        expected.sort();
+        let mut sorted_rowids = rowids.clone();
+        sorted_rowids.sort();
+        assert_eq!(sorted_rowids, expected, "query: {}", query);
+
+        // col2 is not indexed and falls back to a full scan.
+        let query = format!(
+            "SELECT rowid FROM example WHERE {};",
+            filter.replace("{}", "col2")
+        );
+        let stats = conn.cache_stats();
+        let mut full_scan_rowids = load_rowids(&conn, &query);
+        let stats_after = conn.cache_stats();
+        let n_full_scan_lookups = stats_after.hits + stats_after.misses - stats.hits - stats.misses;
+        full_scan_rowids.sort();
+        assert_eq!(full_scan_rowids, expected, "query: {}", query);
+
+        // The index scan visits only the index and the matching rows.
+        assert!(
+            n_lookups * 2 < n_full_scan_lookups,
+            "query: {}, index: {}, full scan: {}",
+            query,
+            n_lookups,
+            n_full_scan_lookups
+        );
+    }
+}
 
-        let results = load_rowids(&conn, &query);
-        assert_eq!(results, expected, "query: {}", query);
+#[test]
+fn test_select_filter_not_between() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 integer, col3 text);",
+        "INSERT INTO example(col1, col2, col3) VALUES (null, null, null);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 1, 1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 2, 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 3, 3);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('2', '2', '2');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('abc', 'abc', 'abc');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('ABD', 'ABD', 'ABD');",
+        "INSERT INTO example(col1, col2, col3) VALUES (2.5, 2.5, 2.5);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for column in ["col1", "col2", "col3"] {
+        for range in [
+            "1 AND 2",
+            "2 AND 1",
+            "'1' AND '2'",
+            "null AND 2",
+            "1 AND null",
+            "5 AND null",
+            "null AND 0",
+            "'abc' AND 'abd'",
+            "'abc' AND 'abd' COLLATE nocase",
+            "col1 AND 3",
+            "1 = 1 AND 2",
+            "NOT 0 AND 2",
+        ] {
+            for op in ["BETWEEN", "NOT BETWEEN"] {
+                let query = format!("SELECT rowid FROM example WHERE {column} {op} {range};");
+                assert_eq!(
+                    load_rowids(&conn, &query),
+                    load_test_rowids(&test_conn, &query),
+                    "query: {}",
+                    query
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_select_filter_not_like() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (null);",
+        "INSERT INTO example(col) VALUES ('hello');",
+        "INSERT INTO example(col) VALUES ('Hello World');",
+        "INSERT INTO example(col) VALUES ('world');",
+        "INSERT INTO example(col) VALUES ('');",
+        "INSERT INTO example(col) VALUES (123);",
+        "INSERT INTO example(col) VALUES (1.5);",
+        "INSERT INTO example(col) VALUES ('h_llo%');",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for pattern in [
+        "null", "''", "'%'", "'_'", "'hello'", "'HELLO'", "'h%'", "'H%'", "'%o'", "'%o%'",
+        "'h_llo'", "'1%'", "'%.5'", "'12_'", "'*'", "'h*'", "'H*'", "'?ello'", "'[hw]*'",
+        "'[^h]*'", "'[a-z]*'", "'*[0-9]'",
+    ] {
+        for op in ["LIKE", "NOT LIKE", "GLOB", "NOT GLOB"] {
+            let query = format!("SELECT rowid FROM example WHERE col {op} {pattern};");
+            assert_eq!(
+                load_rowids(&conn, &query),
+                load_test_rowids(&test_conn, &query),
+                "query: {}",
+                query
+            );
+        }
     }
 }
+
+#[test]
+fn test_select_filter_like_escape() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (null);",
+        "INSERT INTO example(col) VALUES ('100%');",
+        "INSERT INTO example(col) VALUES ('100 percent');",
+        "INSERT INTO example(col) VALUES ('a_b');",
+        "INSERT INTO example(col) VALUES ('axb');",
+        "INSERT INTO example(col) VALUES ('a!b');",
+        "INSERT INTO example(col) VALUES ('A_B');",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for (pattern, escape) in [
+        ("'%!%'", "'!'"),
+        ("'%\\%'", "'\\'"),
+        ("'a!_b'", "'!'"),
+        ("'a!!b'", "'!'"),
+        ("'a_b'", "'!'"),
+        ("'a__b'", "'_'"),
+        ("'%!%'", "null"),
+        ("null", "'!'"),
+    ] {
+        for op in ["LIKE", "NOT LIKE"] {
+            let query =
+                format!("SELECT rowid FROM example WHERE col {op} {pattern} ESCAPE {escape};");
+            assert_eq!(
+                load_rowids(&conn, &query),
+                load_test_rowids(&test_conn, &query),
+                "query: {}",
+                query
+            );
+        }
+    }
+
+    for escape in ["''", "'!!'"] {
+        let query = format!("SELECT rowid FROM example WHERE col LIKE '%' ESCAPE {escape};");
+        let stmt = conn.prepare(&query).unwrap();
+        let mut rows = stmt.query().unwrap();
+        assert!(rows.next_row().is_err(), "query: {}", query);
+    }
+    assert!(conn
+        .prepare("SELECT rowid FROM example WHERE col GLOB '*' ESCAPE '!';")
+        .is_err());
+}
+
+#[test]
+fn test_select_filter_not() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (null);",
+        "INSERT INTO example(col) VALUES (0);",
+        "INSERT INTO example(col) VALUES (1);",
+        "INSERT INTO example(col) VALUES (-1);",
+        "INSERT INTO example(col) VALUES (0.0);",
+        "INSERT INTO example(col) VALUES (0.5);",
+        "INSERT INTO example(col) VALUES ('0');",
+        "INSERT INTO example(col) VALUES ('1abc');",
+        "INSERT INTO example(col) VALUES ('abc');",
+        "INSERT INTO example(col) VALUES (x'31');",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for filter in [
+        "col",
+        "NOT col",
+        "NOT NOT col",
+        "NOT col = 1",
+        "NOT col NOT IN (1)",
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {filter};");
+        assert_eq!(
+            load_rowids(&conn, &query),
+            load_test_rowids(&test_conn, &query),
+            "query: {}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_select_filter_and_or() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "INSERT INTO example VALUES (null, null, null);",
+        "INSERT INTO example VALUES (1, 1, 1);",
+        "INSERT INTO example VALUES (1, 2, 3);",
+        "INSERT INTO example VALUES (2, 1, null);",
+        "INSERT INTO example VALUES (2, 2, 2);",
+        "INSERT INTO example VALUES (3, null, 1);",
+        "INSERT INTO example VALUES (1, 1, 0);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // col3 is not indexed.
+    for filter in [
+        "col1 = 1 AND col3 = 1",
+        "col3 = 1 AND col1 = 1",
+        "col3 >= 1 AND col1 > 1",
+        "col1 = 1 AND col2 = 1",
+        "col1 = 1 AND col2 = 1 AND col3 = 0",
+        "col1 IN (1, 2) AND col3 IS NULL",
+        "col1 BETWEEN 1 AND 2 AND col2 = 2",
+        "col1 = 1 AND col3",
+        "col1 = 1 AND NOT col3",
+        "col1 = 1 OR col3 = 1",
+        "col1 = 1 OR col2 = 2 AND col3 = 2",
+        "col3 OR col3 IS NULL",
+        "col3 IS NOT NULL AND rowid > 3",
+        "rowid = 2 AND col1 = 1",
+        "rowid = 2 AND col1 = 2",
+        "col3 AND col2",
+        "col3 OR col2",
+        "(col1 = 1 OR col2 = 2) AND col3 = 2",
+        "(col1 = 1 OR col1 = 2) AND (col2 = 1 OR col3 = 1)",
+        "col1 = 1 AND (col2 = 2 OR col3 = 0)",
+        "NOT (col1 = 1 OR col2 = 1)",
+        "((col1 = 2)) OR (col2 IS NULL AND (col3 = 1))",
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {filter};");
+        // SQLite may choose another index and return rows in a different order.
+        let mut rowids = load_rowids(&conn, &query);
+        rowids.sort();
+        let mut expected = load_test_rowids(&test_conn, &query);
+        expected.sort();
+        assert_eq!(rowids, expected, "query: {}", query);
+    }
+}
+
+#[test]
+fn test_select_filter_with_rowid() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (10);",
+        "INSERT INTO example(col) VALUES (20);",
+        "INSERT INTO example(col) VALUES (30);",
+        "INSERT INTO example(col) VALUES (40);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn
+        .prepare("SELECT col, RoWid FROM example WHERE rowid = 2;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(columns.get(0), Some(&Value::Integer(20)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(2)));
+    drop(row);
+
+    assert!(rows.next_row().unwrap().is_none());
+
+    // The rowid has INTEGER affinity.
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    for query in [
+        "SELECT col, rowid FROM example WHERE rowid = '2';",
+        "SELECT col, rowid FROM example WHERE rowid = 2.0;",
+        "SELECT col, rowid FROM example WHERE rowid = '2.0';",
+        "SELECT col, rowid FROM example WHERE '3' = rowid;",
+        "SELECT col, rowid FROM example WHERE rowid IS 3.0;",
+        "SELECT col, rowid FROM example WHERE rowid = 2.5;",
+        "SELECT col, rowid FROM example WHERE rowid = '2.5';",
+        "SELECT col, rowid FROM example WHERE rowid = 'abc';",
+        "SELECT col, rowid FROM example WHERE rowid = x'32';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_filter_with_primary_key() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col text);",
+        "INSERT INTO example(id, col) VALUES (1, '10');",
+        "INSERT INTO example(id, col) VALUES (3, '20');",
+        "INSERT INTO example(id, col) VALUES (5, '30');",
+        "INSERT INTO example(id, col) VALUES (6, '40');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("SELECT col, RoWid FROM example WHERE id = 3;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(columns.get(0), Some(&Value::Text(b"20".as_slice().into())));
+    assert_eq!(columns.get(1), Some(&Value::Integer(3)));
+    drop(row);
+    assert!(rows.next_row().unwrap().is_none());
+
+    let stmt = conn
+        .prepare("SELECT col, RoWid FROM example WHERE id = 4;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    assert!(rows.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_with_covering_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1, col2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 10, 100);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 20.5, 200);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, NULL, 300);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 'abc', 400);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 50, 500);",
+        "INSERT INTO example(col1, col2, col3) VALUES (4, x'0102', 600);",
+    ]);
+    let mut long_text = "a".repeat(10000);
+    long_text.push('b');
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    test_conn
+        .execute(
+            "INSERT INTO example(col1, col2, col3) VALUES (5, ?, 700);",
+            [&long_text],
+        )
+        .unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    let queries = [
+        "SELECT col1, col2 FROM example WHERE col1 = 2;",
+        "SELECT rowid, id, col2 FROM example WHERE col1 >= 2;",
+        "SELECT col2 FROM example WHERE col1 IS NULL;",
+        "SELECT col1 FROM example WHERE col1 > 0 AND col2 LIKE '%b';",
+        "SELECT col1, count(*), max(col2) FROM example WHERE col1 BETWEEN 2 AND 4 GROUP BY col1;",
+        "SELECT col1 FROM example WHERE col1 IN (1, 3, 5) ORDER BY col2;",
+    ];
+    let load = |conn: &Connection, query: &str| {
+        conn.prepare(query)
+            .unwrap()
+            .query()?
+            .map(|row| {
+                let row = row?;
+                Ok((0..row.len())
+                    .map(|i| row.get(i).cloned())
+                    .collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    };
+    let mut expected = Vec::new();
+    for query in queries {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+        expected.push(load(&conn, query).unwrap());
+    }
+    drop(conn);
+
+    // Break the table b-tree so that queries reading the table fail.
+    let root_page_id = load_test_rowids(
+        &test_conn,
+        "SELECT rootpage FROM sqlite_schema WHERE name = 'example';",
+    )[0];
+    let page_size = load_test_rowids(&test_conn, "PRAGMA page_size;")[0];
+    drop(test_conn);
+    let mut buf = std::fs::read(file.path()).unwrap();
+    let offset = ((root_page_id - 1) * page_size) as usize;
+    buf[offset..offset + page_size as usize].fill(0xff);
+    std::fs::write(file.path(), buf).unwrap();
+
+    let conn = Connection::open(file.path()).unwrap();
+    for (query, expected) in queries.iter().zip(expected) {
+        assert_eq!(load(&conn, query).unwrap(), expected, "{}", query);
+    }
+    assert!(matches!(
+        load(&conn, "SELECT col3 FROM example WHERE col1 = 2;"),
+        Err(Error::Corrupt(_))
+    ));
+}
+
+#[test]
+fn test_select_with_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col2, col3);",
+        "CREATE INDEX index2 ON example(col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 2, 3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (4, 5, 6);",
+        "INSERT INTO example(col1, col2, col3) VALUES (7, 8, 9);",
+        "INSERT INTO example(col1, col2, col3) VALUES (10, 5, 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 3, 3);",
+    ]);
+
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn
+        .prepare("SELECT * FROM example WHERE col2 == 5;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.get(0), Some(&Value::Integer(10)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(2)));
+    drop(row);
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.get(0), Some(&Value::Integer(4)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(6)));
+    drop(row);
+
+    assert!(rows.next_row().unwrap().is_none());
+
+    let stmt = conn
+        .prepare("SELECT * FROM example WHERE col3 == 6;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.get(0), Some(&Value::Integer(4)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(5)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(6)));
+    drop(row);
+
+    assert!(rows.next_row().unwrap().is_none());
+
+    let stmt = conn
+        .prepare("SELECT * FROM example WHERE col3 == 3;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.get(0), Some(&Value::Integer(1)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(2)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(3)));
+    drop(row);
+
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.get(0), Some(&Value::Integer(3)));
+    assert_eq!(columns.get(1), Some(&Value::Integer(3)));
+    assert_eq!(columns.get(2), Some(&Value::Integer(3)));
+    drop(row);
+
+    assert!(rows.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_with_index_multi_type() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 INTEGER, col3 TEXT);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "CREATE INDEX index3 ON example(col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('abc', 'abc', 'abc');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('1234', '1234', '1234');",
+        "INSERT INTO example(col1, col2, col3) VALUES (x'31323334', x'31323334', x'31323334');",
+        "INSERT INTO example(col1, col2, col3) VALUES (1234, 1234, 1234);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1234.5, 1234.5, 1234.5);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, NULL, NULL);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1234, 1234, 1234);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('1234', '1234', '1234');",
+        "INSERT INTO example(col1, col2, col3) VALUES ('01234', '01234', '01234');",
+    ]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (expected, query) in [
+        (vec![4, 7], "col1 = 1234"),
+        (vec![2, 4, 7, 8, 9], "col2 = 1234"),
+        (vec![2, 4, 7, 8], "col3 = 1234"),
+        (vec![], "col1 = 0"),
+        (vec![], "col2 = 0"),
+        (vec![], "col3 = 0"),
+        (vec![2, 8], "col1 = '1234'"),
+        (vec![2, 4, 7, 8, 9], "col2 = '1234'"),
+        (vec![2, 4, 7, 8], "col3 = '1234'"),
+        (vec![9], "col1 = '01234'"),
+        (vec![2, 4, 7, 8, 9], "col2 = '01234'"),
+        (vec![9], "col3 = '01234'"),
+        (vec![], "col1 = '0'"),
+        (vec![], "col2 = '0'"),
+        (vec![], "col3 = '0'"),
+        (vec![5], "col1 = 1234.5"),
+        (vec![5], "col2 = 1234.5"),
+        (vec![5], "col3 = 1234.5"),
+        (vec![3], "col1 = x'31323334'"),
+        (vec![3], "col2 = x'31323334'"),
+        (vec![3], "col3 = x'31323334'"),
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {};", query);
+        let mut stmt = test_conn.prepare(&query).unwrap();
+        let rows = stmt.query([]).unwrap();
+        let rowids: Vec<i64> = rows.mapped(|r| r.get(0)).map(|v| v.unwrap()).collect();
+        assert_eq!(rowids, expected, "query: {}", query);
+
+        let results = load_rowids(&conn, &query);
+
+        assert_eq!(results, expected, "query: {}", query);
+    }
+}
+
+#[test]
+fn test_select_with_index_same_items() {
+    let mut queries = vec![
+        "CREATE TABLE example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1, col2, col3);",
+    ];
+    queries.resize(
+        queries.len() + 100,
+        "INSERT INTO example(col1, col2, col3) VALUES ('abc', NULL, NULL);",
+    );
+    queries.resize(
+        queries.len() + 100,
+        "INSERT INTO example(col1, col2, col3) VALUES (123, NULL, NULL);",
+    );
+    queries.resize(
+        queries.len() + 100,
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, NULL, NULL);",
+    );
+    let file = create_sqlite_database(&queries);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    for (expected, query) in [
+        ((1..=100).collect::<Vec<i64>>(), "col1 = 'abc'"),
+        ((101..=200).collect::<Vec<i64>>(), "col1 = 123"),
+    ] {
+        let query = format!("SELECT rowid FROM example WHERE {};", query);
+        let mut stmt = test_conn.prepare(&query).unwrap();
+        let rows = stmt.query([]).unwrap();
+        let rowids: Vec<i64> = rows.mapped(|r| r.get(0)).map(|v| v.unwrap()).collect();
+        assert_eq!(rowids, expected, "query: {}", query);
+
+        let results = load_rowids(&conn, &query);
+        assert_eq!(results, expected, "query: {}", query);
+    }
+}
+
+#[test]
+fn test_select_order_by() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 COLLATE NOCASE, col3);",
+        "CREATE INDEX index1 ON example(col1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 'b', 1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'A', 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'a', 3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'C', 4);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('x', NULL, 5);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1.5, 'B', 6);",
+        "INSERT INTO example(col1, col2, col3) VALUES (x'00', 'c', 7);",
+        "INSERT INTO example(col1, col2, col3) VALUES (0, 'b', 8);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT * FROM example ORDER BY col1;",
+        "SELECT * FROM example ORDER BY col1 ASC;",
+        "SELECT * FROM example ORDER BY col1 DESC;",
+        "SELECT * FROM example ORDER BY col2, col3;",
+        "SELECT * FROM example ORDER BY col2 DESC, col3 DESC;",
+        "SELECT * FROM example ORDER BY col2 COLLATE BINARY, col3;",
+        "SELECT * FROM example ORDER BY col2 COLLATE BINARY DESC, col3;",
+        "SELECT * FROM example ORDER BY col1, col2 DESC;",
+        "SELECT col3 FROM example ORDER BY col2 || col1, col3;",
+        "SELECT col3, col1 FROM example ORDER BY 2, 1 DESC;",
+        "SELECT col3 FROM example ORDER BY rowid;",
+        "SELECT col3 FROM example ORDER BY rowid DESC;",
+        "SELECT col3 FROM example WHERE col1 = 1 ORDER BY col2 DESC;",
+        "SELECT col3 FROM example WHERE col1 = 100 ORDER BY col2;",
+        "SELECT col3 FROM example WHERE col3 >= 4 ORDER BY col1 DESC;",
+        "SELECT * FROM example ORDER BY col1 NULLS FIRST;",
+        "SELECT * FROM example ORDER BY col1 ASC NULLS LAST;",
+        "SELECT * FROM example ORDER BY col1 DESC NULLS FIRST;",
+        "SELECT * FROM example ORDER BY col1 DESC NULLS LAST;",
+        "SELECT * FROM example ORDER BY col2 ASC NULLS LAST, col1 DESC NULLS FIRST;",
+        "SELECT * FROM example ORDER BY col1 DESC NULLS FIRST, col2 ASC NULLS LAST;",
+        "SELECT * FROM example ORDER BY col1 NULLS LAST, col3 DESC;",
+        "SELECT col3 FROM example ORDER BY rowid NULLS LAST;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    assert!(conn
+        .prepare("SELECT col1 FROM example ORDER BY 0;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example ORDER BY 2;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example ORDER BY invalid;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example ORDER BY col1 NULLS;")
+        .is_err());
+}
+
+#[test]
+fn test_select_order_by_alias() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 'b', 1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'a', 2);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'a', 3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'c', 4);",
+        "INSERT INTO example(col1, col2, col3) VALUES (0, 'b', 5);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col3 AS c, col1 FROM example ORDER BY c DESC;",
+        "SELECT col1 + col3 AS total, col2 FROM example ORDER BY total, col2;",
+        "SELECT col3 AS \"Value\" FROM example ORDER BY value DESC;",
+        // The alias takes precedence over the column of the table.
+        "SELECT col3 AS col1 FROM example ORDER BY col1 DESC;",
+        "SELECT col2 AS name, count(*) AS n FROM example GROUP BY col2 ORDER BY n DESC, name;",
+        "SELECT col2 AS name, col3 FROM example ORDER BY 2 DESC;",
+        "SELECT col2 AS name, col3 FROM example ORDER BY name, 2 DESC;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_order_by_multiple_pages() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+    ];
+    let insert_queries = (0..1000)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2) VALUES ({}, x'{}');",
+                (i * 7919) % 1000,
+                "11".repeat(i % 50)
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    assert_same_results_as_sqlite(
+        "SELECT col1, col2 FROM example ORDER BY col1;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results_as_sqlite(
+        "SELECT rowid, col2 FROM example ORDER BY col2 DESC, col1;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_select_union_all() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+        "INSERT INTO example(col1, col2) VALUES (3, 'a');",
+        "INSERT INTO example(col1, col2) VALUES (1, 'b');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 'c');",
+        "INSERT INTO example(col1, col2) VALUES (2, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (1, 'e');",
+        "CREATE TABLE example2(id INTEGER PRIMARY KEY, name);",
+        "INSERT INTO example2(id, name) VALUES (10, 'x');",
+        "INSERT INTO example2(id, name) VALUES (20, 'y');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col2 FROM example WHERE col1 = 1 UNION ALL SELECT col2 FROM example WHERE col1 >= 2;",
+        "SELECT * FROM example WHERE col2 < 'c' UNION ALL SELECT * FROM example WHERE col2 IS NULL;",
+        "SELECT col1, col2 FROM example UNION ALL SELECT id, name FROM example2;",
+        "SELECT id AS a, name FROM example2 UNION ALL SELECT * FROM example UNION ALL SELECT rowid, name FROM example2 WHERE id = 20;",
+        "SELECT col1 FROM example WHERE col1 = 100 UNION ALL SELECT name FROM example2;",
+        "SELECT count(*) FROM example UNION ALL SELECT max(id) FROM example2;",
+        "SELECT col2 FROM example UNION ALL SELECT col2 FROM example ORDER BY 1;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    let stmt = conn
+        .prepare("SELECT id AS a, name FROM example2 UNION ALL SELECT col1, col2 FROM example;")
+        .unwrap();
+    assert!(stmt.is_query());
+    assert_eq!(stmt.column_names(), &["a", "name"]);
+    assert_eq!(stmt.query().unwrap().count(), 7);
+
+    // Parameters are numbered across the SELECTs.
+    let mut stmt = conn
+        .prepare("SELECT col2 FROM example WHERE col1 = ? UNION ALL SELECT name FROM example2 WHERE id = ?;")
+        .unwrap();
+    assert_eq!(stmt.parameter_count(), 2);
+    stmt.bind(1, Some(Value::Integer(3))).unwrap();
+    stmt.bind(2, Some(Value::Integer(20))).unwrap();
+    let values = stmt
+        .query()
+        .unwrap()
+        .map(|row| row.unwrap().get(0).cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            Some(Value::Text(b"a".as_slice().into())),
+            Some(Value::Text(b"y".as_slice().into()))
+        ]
+    );
+
+    assert!(conn
+        .prepare("SELECT col1 FROM example UNION ALL SELECT id, name FROM example2;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example UNION ALL SELECT col1 FROM invalid;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example ORDER BY col1 UNION ALL SELECT id FROM example2;")
+        .is_err());
+}
+
+#[test]
+fn test_select_union_intersect_except() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 COLLATE NOCASE);",
+        "INSERT INTO example(col1, col2) VALUES (3, 'a');",
+        "INSERT INTO example(col1, col2) VALUES (1, 'b');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (2, 'c');",
+        "INSERT INTO example(col1, col2) VALUES (1, 'b');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (x'01', 'd');",
+        "CREATE TABLE example2(col1, col2);",
+        "INSERT INTO example2(col1, col2) VALUES (1, 'B');",
+        "INSERT INTO example2(col1, col2) VALUES (NULL, NULL);",
+        "INSERT INTO example2(col1, col2) VALUES (4, 'e');",
+        "INSERT INTO example2(col1, col2) VALUES ('3', 'a');",
+        "INSERT INTO example2(col1, col2) VALUES (4, 'e');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for (query, n_rows) in [
+        ("SELECT * FROM example UNION SELECT * FROM example;", 5),
+        ("SELECT col1 FROM example UNION SELECT col1 FROM example2;", 7),
+        ("SELECT * FROM example UNION SELECT * FROM example2;", 7),
+        ("SELECT * FROM example2 UNION SELECT * FROM example2;", 4),
+        ("SELECT * FROM example INTERSECT SELECT * FROM example2;", 2),
+        ("SELECT col1 FROM example INTERSECT SELECT col1 FROM example2;", 2),
+        ("SELECT * FROM example2 INTERSECT SELECT * FROM example;", 1),
+        ("SELECT * FROM example EXCEPT SELECT * FROM example2;", 3),
+        ("SELECT * FROM example2 EXCEPT SELECT * FROM example;", 3),
+        ("SELECT col1 FROM example EXCEPT SELECT col1 FROM example2 WHERE col1 = 1;", 4),
+        ("SELECT col1 FROM example WHERE col1 = 100 UNION SELECT col1 FROM example;", 5),
+        ("SELECT col1 FROM example WHERE col1 = 100 INTERSECT SELECT col1 FROM example;", 0),
+        ("SELECT col1 FROM example EXCEPT SELECT col1 FROM example;", 0),
+        (
+            "SELECT col1 FROM example UNION SELECT col1 FROM example2 EXCEPT SELECT col1 FROM example WHERE col1 >= 2;",
+            4,
+        ),
+        (
+            "SELECT col1 FROM example UNION ALL SELECT col1 FROM example2 INTERSECT SELECT col1 FROM example2;",
+            4,
+        ),
+        (
+            "SELECT col1 FROM example INTERSECT SELECT col1 FROM example2 UNION ALL SELECT col1 FROM example2;",
+            7,
+        ),
+        (
+            "SELECT col1 FROM example2 UNION SELECT col1 FROM example2 UNION ALL SELECT col1 FROM example2 WHERE col1 = 4 UNION ALL SELECT col1 FROM example WHERE col1 = 3;",
+            7,
+        ),
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    // The collation of the first SELECT is used to compare rows.
+    for (query, n_rows) in [
+        (
+            "SELECT col2 FROM example UNION SELECT col2 FROM example2;",
+            6,
+        ),
+        (
+            "SELECT col2 FROM example2 UNION SELECT col2 FROM example;",
+            7,
+        ),
+        (
+            "SELECT col2 FROM example INTERSECT SELECT col2 FROM example2;",
+            3,
+        ),
+        (
+            "SELECT col2 FROM example2 INTERSECT SELECT col2 FROM example;",
+            2,
+        ),
+        (
+            "SELECT col2 COLLATE NOCASE FROM example2 EXCEPT SELECT col2 FROM example;",
+            1,
+        ),
+    ] {
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+        assert_eq!(
+            test_conn
+                .prepare(query)
+                .unwrap()
+                .query_map([], |_| Ok(()))
+                .unwrap()
+                .count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    let stmt = conn
+        .prepare("SELECT col1 AS a FROM example EXCEPT SELECT col2 FROM example2;")
+        .unwrap();
+    assert_eq!(stmt.column_names(), &["a"]);
+    assert!(conn
+        .prepare("SELECT col1 FROM example INTERSECT SELECT col1, col2 FROM example2;")
+        .is_err());
+}
+
+#[test]
+fn test_select_compound_order_by() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 COLLATE NOCASE);",
+        "INSERT INTO example(col1, col2) VALUES (3, 'a');",
+        "INSERT INTO example(col1, col2) VALUES (1, 'B');",
+        "INSERT INTO example(col1, col2) VALUES (NULL, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (2, 'c');",
+        "CREATE TABLE example2(id INTEGER PRIMARY KEY, name);",
+        "INSERT INTO example2(id, name) VALUES (10, 'A');",
+        "INSERT INTO example2(id, name) VALUES (0, 'b');",
+        "INSERT INTO example2(id, name) VALUES (5, 'D');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col1, col2 FROM example UNION ALL SELECT id, name FROM example2 ORDER BY 1;",
+        "SELECT col1, col2 FROM example UNION ALL SELECT id, name FROM example2 ORDER BY 1 DESC;",
+        "SELECT col1, col2 FROM example UNION ALL SELECT id, name FROM example2 ORDER BY col1 NULLS LAST;",
+        // The aliases of the first SELECT name the result columns.
+        "SELECT col1 AS k, col2 AS v FROM example UNION ALL SELECT id, name FROM example2 ORDER BY k DESC;",
+        "SELECT col1 AS k, col2 AS v FROM example UNION SELECT id, name FROM example2 ORDER BY v, k;",
+        // The names of the later SELECTs also refer to the result columns.
+        "SELECT col1, col2 FROM example UNION SELECT id, name FROM example2 ORDER BY name DESC, id;",
+        "SELECT col1, col2 FROM example EXCEPT SELECT id, name FROM example2 ORDER BY col1 DESC;",
+        "SELECT col1, col2 FROM example INTERSECT SELECT col1, col2 FROM example ORDER BY col2;",
+        // The collation of the first SELECT is used unless COLLATE is specified.
+        "SELECT col2 FROM example UNION ALL SELECT name FROM example2 ORDER BY col2, 1 COLLATE BINARY;",
+        "SELECT name FROM example2 UNION ALL SELECT col2 FROM example ORDER BY 1;",
+        "SELECT name FROM example2 UNION ALL SELECT col2 FROM example ORDER BY name COLLATE NOCASE DESC;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    let stmt = conn
+        .prepare("SELECT col1 AS k FROM example UNION ALL SELECT id FROM example2 ORDER BY k;")
+        .unwrap();
+    assert_eq!(stmt.column_names(), &["k"]);
+    assert!(conn
+        .prepare("SELECT col1 FROM example UNION SELECT id FROM example2 ORDER BY 2;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example UNION SELECT id FROM example2 ORDER BY col2;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example UNION SELECT id FROM example2 ORDER BY col1 + 1;")
+        .is_err());
+}
+
+#[test]
+fn test_select_inner_join() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE parent(id INTEGER PRIMARY KEY, name TEXT COLLATE NOCASE);",
+        "CREATE TABLE child(id INTEGER PRIMARY KEY, parent_id INTEGER, value);",
+        "CREATE TABLE child2(id INTEGER PRIMARY KEY, parent_id INTEGER, value);",
+        "CREATE INDEX index1 ON child2(parent_id);",
+        "CREATE TABLE child3(parent_key TEXT, value);",
+        "CREATE INDEX index2 ON child3(parent_key);",
+    ];
+    let insert_queries = (0..100)
+        .map(|i| {
+            format!(
+                "INSERT INTO parent(id, name) VALUES ({}, 'P{}');",
+                i,
+                i % 30
+            )
+        })
+        .chain((0..300).flat_map(|i| {
+            let parent_id = if i % 50 == 0 {
+                "NULL".to_string()
+            } else {
+                ((i * 7) % 120).to_string()
+            };
+            [
+                format!(
+                    "INSERT INTO child(parent_id, value) VALUES ({}, {});",
+                    parent_id, i
+                ),
+                format!(
+                    "INSERT INTO child2(parent_id, value) VALUES ({}, {});",
+                    parent_id, i
+                ),
+                format!(
+                    "INSERT INTO child3(parent_key, value) VALUES ('{}', {});",
+                    parent_id, i
+                ),
+            ]
+        }))
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for (query, n_rows) in [
+        // Without index on the join key.
+        (
+            "SELECT parent.name, child.value FROM parent JOIN child ON parent.id = child.parent_id ORDER BY child.id;",
+            247,
+        ),
+        // With index on the join key.
+        (
+            "SELECT parent.name, child2.value FROM parent JOIN child2 ON parent.id = child2.parent_id ORDER BY child2.id;",
+            247,
+        ),
+        (
+            "SELECT parent.name, child2.value FROM parent INNER JOIN child2 ON child2.parent_id = parent.id ORDER BY child2.id;",
+            247,
+        ),
+        // Look up the rowid of the inner table.
+        (
+            "SELECT child.value, name FROM child JOIN parent ON child.parent_id = parent.id ORDER BY child.id;",
+            247,
+        ),
+        // The text keys are compared as numbers.
+        (
+            "SELECT parent.id, child3.value FROM parent JOIN child3 ON parent.id = child3.parent_key ORDER BY child3.rowid;",
+            247,
+        ),
+        (
+            "SELECT parent.id, child3.value FROM child3 JOIN parent ON child3.parent_key = parent.id ORDER BY child3.rowid;",
+            247,
+        ),
+        (
+            "SELECT * FROM parent JOIN child2 ON parent.id = child2.parent_id WHERE parent.name = 'p7' ORDER BY child2.id;",
+            10,
+        ),
+        (
+            "SELECT child2.*, parent.* FROM parent JOIN child2 ON parent.id = child2.parent_id AND child2.value < 100 WHERE parent.id > 50 ORDER BY 1;",
+            42,
+        ),
+        (
+            "SELECT parent.id, child.id FROM parent JOIN child ON parent.id < child.parent_id AND child.value < 3 ORDER BY 1, 2;",
+            21,
+        ),
+        (
+            "SELECT parent_id, value FROM child JOIN parent ON parent.id = 3 ORDER BY child.id;",
+            300,
+        ),
+        (
+            "SELECT parent.name FROM parent JOIN child2 ON parent.id = child2.parent_id WHERE child2.value = 1000;",
+            0,
+        ),
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    // The rows are returned without sorting.
+    for (query, n_rows) in [
+        (
+            "SELECT * FROM parent JOIN child ON parent.id = child.parent_id;",
+            247,
+        ),
+        (
+            "SELECT * FROM child2 JOIN parent ON parent.id = child2.parent_id;",
+            247,
+        ),
+        (
+            "SELECT * FROM parent JOIN child3 ON child3.parent_key = parent.id;",
+            247,
+        ),
+    ] {
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    let stmt = conn
+        .prepare("SELECT * FROM parent JOIN child ON parent.id = child.parent_id;")
+        .unwrap();
+    assert_eq!(
+        stmt.column_names(),
+        &["id", "name", "id", "parent_id", "value"]
+    );
+    // "id" is in both tables.
+    assert!(conn
+        .prepare("SELECT id FROM parent JOIN child ON parent.id = child.parent_id;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT child.name FROM parent JOIN child ON parent.id = child.parent_id;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT invalid.* FROM parent JOIN child ON parent.id = child.parent_id;")
+        .is_err());
+    assert!(matches!(
+        conn.prepare("SELECT count(*) FROM parent JOIN child ON parent.id = child.parent_id;"),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_select_left_join() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE parent(id INTEGER PRIMARY KEY, name TEXT);",
+        "CREATE TABLE child(id INTEGER PRIMARY KEY, parent_id INTEGER, value);",
+        "CREATE TABLE child2(id INTEGER PRIMARY KEY, parent_id INTEGER, value);",
+        "CREATE INDEX index1 ON child2(parent_id);",
+    ];
+    let insert_queries = (0..100)
+        .map(|i| {
+            format!(
+                "INSERT INTO parent(id, name) VALUES ({}, 'P{}');",
+                i,
+                i % 30
+            )
+        })
+        .chain((0..200).flat_map(|i| {
+            let parent_id = if i % 50 == 0 {
+                "NULL".to_string()
+            } else {
+                ((i * 7) % 130).to_string()
+            };
+            [
+                format!(
+                    "INSERT INTO child(parent_id, value) VALUES ({}, {});",
+                    parent_id, i
+                ),
+                format!(
+                    "INSERT INTO child2(parent_id, value) VALUES ({}, {});",
+                    parent_id, i
+                ),
+            ]
+        }))
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for (query, n_rows) in [
+        // Without index on the join key.
+        (
+            "SELECT parent.id, child.id, child.value FROM parent LEFT JOIN child ON parent.id = child.parent_id ORDER BY 1, 2;",
+            154,
+        ),
+        // With index on the join key.
+        (
+            "SELECT parent.id, child2.id, child2.value FROM parent LEFT OUTER JOIN child2 ON parent.id = child2.parent_id ORDER BY 1, 2;",
+            154,
+        ),
+        // Look up the rowid of the inner table.
+        (
+            "SELECT * FROM child LEFT JOIN parent ON child.parent_id = parent.id ORDER BY child.id;",
+            200,
+        ),
+        // The outer rows without any matching inner row.
+        (
+            "SELECT parent.* FROM parent LEFT JOIN child2 ON parent.id = child2.parent_id WHERE child2.id IS NULL ORDER BY 1;",
+            1,
+        ),
+        (
+            "SELECT child.id, parent.name FROM child LEFT JOIN parent ON child.parent_id = parent.id WHERE parent.name IS NULL ORDER BY 1;",
+            47,
+        ),
+        // ON filters the inner rows to join while WHERE filters the joined rows.
+        (
+            "SELECT parent.id, child2.value FROM parent LEFT JOIN child2 ON parent.id = child2.parent_id AND child2.value < 50 ORDER BY 1, 2;",
+            100,
+        ),
+        (
+            "SELECT parent.id, child2.value FROM parent LEFT JOIN child2 ON parent.id = child2.parent_id WHERE child2.value < 50 ORDER BY 1, 2;",
+            40,
+        ),
+        (
+            "SELECT parent.id, child.value FROM parent LEFT JOIN child ON parent.id = child.parent_id AND parent.id < 10 ORDER BY 1, 2;",
+            105,
+        ),
+        (
+            "SELECT parent.id, child.value IS NULL, coalesce(child.value, -1) FROM parent LEFT JOIN child ON 0 WHERE parent.id < 10 ORDER BY 1;",
+            10,
+        ),
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    // The rows are returned without sorting.
+    for (query, n_rows) in [
+        (
+            "SELECT * FROM parent LEFT JOIN child ON parent.id = child.parent_id;",
+            154,
+        ),
+        (
+            "SELECT * FROM parent LEFT JOIN child2 ON parent.id = child2.parent_id;",
+            154,
+        ),
+    ] {
+        assert_eq!(
+            conn.prepare(query).unwrap().query().unwrap().count(),
+            n_rows,
+            "{}",
+            query
+        );
+    }
+
+    // The inner columns of the outer rows without any matching inner row are NULL.
+    let stmt = conn
+        .prepare("SELECT parent.name, child.* FROM parent LEFT JOIN child ON parent.id = child.parent_id WHERE parent.id = 50;")
+        .unwrap();
+    assert_eq!(stmt.column_names(), &["name", "id", "parent_id", "value"]);
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.get(0), Some(&Value::Text(b"P20".as_slice().into())));
+    assert_eq!(columns.get(1), None);
+    assert_eq!(columns.get(2), None);
+    assert_eq!(columns.get(3), None);
+    drop(row);
+    assert!(rows.next_row().unwrap().is_none());
+}
+
+#[test]
+fn test_select_subquery() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1 integer, col2 text COLLATE NOCASE);",
+        "CREATE TABLE example2(col1, col2 text);",
+        "INSERT INTO example(col1, col2) VALUES (1, 'a'), (2, 'B'), (3, 'c'), (NULL, 'd'), (5, NULL), (6, 'abc');",
+        "INSERT INTO example2(col1, col2) VALUES (1, 'A'), ('2', 'b'), (NULL, '3'), (6, 'x'), (7.0, '5');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT * FROM example WHERE col1 = (SELECT max(col1) FROM example2);",
+        "SELECT * FROM example WHERE col1 > (SELECT col1 FROM example2 WHERE rowid = 2);",
+        "SELECT * FROM example WHERE (SELECT count(*) FROM example2) = 5;",
+        "SELECT * FROM example WHERE col1 = (SELECT col1 FROM example2 WHERE col1 = 100);",
+        "SELECT * FROM example WHERE (SELECT col1 FROM example2 WHERE col1 = 100) IS NULL;",
+        "SELECT * FROM example WHERE col1 IN (SELECT col1 FROM example2);",
+        "SELECT * FROM example WHERE col1 NOT IN (SELECT col1 FROM example2);",
+        "SELECT * FROM example WHERE col1 NOT IN (SELECT col1 FROM example2 WHERE col1 IS NOT NULL);",
+        "SELECT * FROM example WHERE col1 IN (SELECT col1 FROM example2 WHERE col1 = 100);",
+        "SELECT * FROM example WHERE col1 NOT IN (SELECT col1 FROM example2 WHERE col1 = 100);",
+        // Affinity of the left operand.
+        "SELECT * FROM example WHERE col1 IN (SELECT col2 FROM example2);",
+        "SELECT * FROM example2 WHERE col2 IN (SELECT col1 FROM example);",
+        // Collation of the left operand, or the explicit one.
+        "SELECT * FROM example WHERE col2 IN (SELECT col2 FROM example2);",
+        "SELECT * FROM example2 WHERE col2 IN (SELECT col2 FROM example);",
+        "SELECT * FROM example2 WHERE col2 IN (SELECT col2 COLLATE BINARY FROM example);",
+        "SELECT * FROM example WHERE col2 = (SELECT col2 FROM example2 WHERE col1 = 1);",
+        // Nested subqueries.
+        "SELECT * FROM example WHERE col1 IN (SELECT col1 FROM example2 WHERE col1 < (SELECT max(col1) FROM example));",
+        "SELECT id FROM example WHERE col1 IN (SELECT col1 FROM example2) AND col2 IN (SELECT col2 FROM example2);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Parameters in the subquery.
+    let mut stmt = conn
+        .prepare("SELECT id FROM example WHERE col1 IN (SELECT col1 FROM example2 WHERE rowid <= ?) AND id > ?;")
+        .unwrap();
+    stmt.bind(1, Some(Value::Integer(4))).unwrap();
+    stmt.bind(2, Some(Value::Integer(1))).unwrap();
+    let rows = stmt
+        .query()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(2)));
+    assert_eq!(rows[1].get(0), Some(&Value::Integer(6)));
+    stmt.bind(1, Some(Value::Integer(1))).unwrap();
+    stmt.bind(2, Some(Value::Integer(0))).unwrap();
+    assert_eq!(stmt.query().unwrap().count(), 1);
+
+    // The subquery returns more than one row.
+    let stmt = conn
+        .prepare("SELECT * FROM example WHERE col1 = (SELECT col1 FROM example2);")
+        .unwrap();
+    assert!(stmt.query().is_err());
+    // The subquery returns more than one column.
+    assert!(conn
+        .prepare("SELECT * FROM example WHERE col1 = (SELECT col1, col2 FROM example2);")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT * FROM example WHERE col1 IN (SELECT * FROM example2);")
+        .is_err());
+    // Correlated subqueries are not supported.
+    assert!(conn
+        .prepare("SELECT * FROM example WHERE col1 IN (SELECT col1 FROM example2 WHERE col2 = example.col2);")
+        .is_err());
+    // Subqueries are supported only in WHERE.
+    assert!(conn
+        .prepare("SELECT (SELECT max(col1) FROM example2) FROM example;")
+        .is_err());
+}
+
+#[test]
+fn test_explain() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE TABLE example2(col1, col2);",
+        "CREATE INDEX index2 ON example2(col2);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let root_page = |name: &str| -> u32 {
+        test_conn
+            .query_row(
+                "SELECT rootpage FROM sqlite_schema WHERE name = ?;",
+                [name],
+                |row| row.get(0),
+            )
+            .unwrap()
+    };
+    let table_plan = |table_name: &str, access: AccessMethod| TablePlan {
+        table_name: table_name.to_owned(),
+        root_page: root_page(table_name),
+        access,
+    };
+    let index_seek = |index_name: &str, n_ranges: usize, covering: bool| AccessMethod::IndexSeek {
+        index_name: index_name.to_owned(),
+        index_root_page: root_page(index_name),
+        n_ranges,
+        covering,
+    };
+
+    let explain = |query: &str| conn.prepare(query).unwrap().explain().unwrap();
+
+    let plan = explain("SELECT * FROM example WHERE col1 = 1;");
+    assert_eq!(
+        plan,
+        PlanDescription {
+            tables: vec![table_plan("example", index_seek("index1", 1, false))],
+            sort: false,
+            aggregate: false,
+            subqueries: Vec::new(),
+        }
+    );
+    let plan = explain("SELECT * FROM example WHERE col2 = 1;");
+    assert_eq!(
+        plan.tables,
+        vec![table_plan("example", AccessMethod::FullScan)]
+    );
+    let plan = explain("SELECT * FROM example WHERE id = 1;");
+    assert_eq!(
+        plan.tables,
+        vec![table_plan("example", AccessMethod::RowId)]
+    );
+    let plan = explain("SELECT id FROM example WHERE col1 IN (1, 2, 3) ORDER BY col2;");
+    assert_eq!(
+        plan.tables,
+        vec![table_plan("example", index_seek("index1", 3, false))]
+    );
+    assert!(plan.sort);
+    let plan = explain("SELECT count(*) FROM example2;");
+    assert_eq!(
+        plan.tables,
+        vec![table_plan("example2", AccessMethod::FullScan)]
+    );
+    assert!(plan.aggregate);
+    assert!(!plan.sort);
+
+    let plan = explain(
+        "SELECT * FROM example JOIN example2 ON example2.col2 = example.col2 WHERE example.col1 = 1;",
+    );
+    assert_eq!(
+        plan.tables,
+        vec![
+            table_plan("example", AccessMethod::FullScan),
+            table_plan("example2", index_seek("index2", 1, false)),
+        ]
+    );
+    let plan = explain("SELECT * FROM example2 JOIN example ON example.id = example2.col1;");
+    assert_eq!(
+        plan.tables,
+        vec![
+            table_plan("example2", AccessMethod::FullScan),
+            table_plan("example", AccessMethod::RowId),
+        ]
+    );
+
+    let plan =
+        explain("SELECT * FROM example2 WHERE col1 IN (SELECT col1 FROM example WHERE col1 > 1);");
+    assert_eq!(
+        plan.tables,
+        vec![table_plan("example2", AccessMethod::FullScan)]
+    );
+    assert_eq!(plan.subqueries.len(), 1);
+    assert_eq!(
+        plan.subqueries[0].tables,
+        vec![table_plan("example", index_seek("index1", 1, true))]
+    );
+
+    assert!(matches!(
+        conn.prepare("INSERT INTO example2(col1, col2) VALUES (1, 2);")
+            .unwrap()
+            .explain(),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_quoted_identifiers() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE \"Example Table\"(\"select\" integer, [my col] text, \"a\"\"b\", `c``d`, [\"x\"], [current_time]);",
+        "INSERT INTO \"Example Table\" VALUES (1, 'a', 'b', 'c', 'd', 'e'), (2, 'A', 'B', 'C', 'D', 'E'), (3, 'z', NULL, 1, 2, 3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    let stmt = conn.prepare("SELECT * FROM [Example Table];").unwrap();
+    assert_eq!(
+        stmt.column_names(),
+        &["select", "my col", "a\"b", "c`d", "\"x\"", "current_time"]
+    );
+    for query in [
+        "SELECT * FROM \"Example Table\";",
+        "SELECT \"select\", [my col], \"a\"\"b\", `c``d` FROM `Example Table` WHERE \"select\" > 1;",
+        "SELECT [select], `my col` FROM [example table] WHERE \"my col\" = 'a';",
+        "SELECT [a\"b], \"C`D\" FROM \"Example Table\" ORDER BY [my col] DESC;",
+        // The quotes in brackets are part of the name.
+        "SELECT [\"x\"], \"\"\"x\"\"\", `\"x\"` FROM \"Example Table\";",
+        // Quoted keywords are names.
+        "SELECT [current_time], \"current_time\" FROM \"Example Table\";",
+        "SELECT \"Example Table\".[select] FROM \"Example Table\";",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Write with quoted identifiers.
+    conn.prepare("INSERT INTO [Example Table]([select], \"my col\", `a\"b`) VALUES (4, 'y', 'x');")
+        .unwrap()
+        .execute()
+        .unwrap();
+    conn.prepare("CREATE TABLE [new table](\"from\" integer primary key, [a b] text);")
+        .unwrap()
+        .execute()
+        .unwrap();
+    conn.prepare("INSERT INTO \"new table\"(`from`, \"a b\") VALUES (1, 'v');")
+        .unwrap()
+        .execute()
+        .unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    for query in [
+        "SELECT * FROM \"Example Table\" WHERE [select] = 4;",
+        "SELECT [from], \"a b\" FROM [new table];",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        conn.prepare("SELECT * FROM [new table];")
+            .unwrap()
+            .column_names(),
+        &["from", "a b"]
+    );
+}
+
+#[test]
+fn test_select_without_rowid() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2, col3 integer, PRIMARY KEY(col3, col1)) WITHOUT ROWID;",
+        "CREATE TABLE example2(id integer primary key, col) WITHOUT ROWID;",
+        "INSERT INTO example2(id, col) VALUES (3, 'c');",
+        "INSERT INTO example2(id, col) VALUES (1, 'a');",
+        "INSERT INTO example2(id, col) VALUES (2, 'b');",
+    ];
+    let insert_queries = (0..1000)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2, col3) VALUES ({}, x'{}', {});",
+                i,
+                "11".repeat(i % 50),
+                (i * 7919) % 100
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT * FROM example;",
+        "SELECT col2, col1 FROM example WHERE col3 = 7;",
+        "SELECT col1 FROM example WHERE col1 >= 990;",
+        "SELECT * FROM example2;",
+        "SELECT id FROM example2 WHERE col = 'b';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    assert!(conn.prepare("SELECT rowid FROM example;").is_err());
+    for query in [
+        "INSERT INTO example(col1, col3) VALUES (1, 1);",
+        "UPDATE example SET col2 = 1;",
+        "DELETE FROM example;",
+    ] {
+        assert!(
+            matches!(conn.prepare(query), Err(prsqlite::Error::Unsupported(_))),
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_select_without_rowid_desc_key() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2 text, col3 integer, CONSTRAINT pk PRIMARY KEY(col3 DESC, col2 COLLATE NOCASE) ON CONFLICT REPLACE) WITHOUT ROWID;",
+    ];
+    let insert_queries = (0..500)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2, col3) VALUES ({}, '{}{}', {});",
+                i,
+                if i % 2 == 0 { "a" } else { "B" },
+                i,
+                (i * 7919) % 50
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT * FROM example;",
+        "SELECT col1, col2 FROM example WHERE col3 = 7;",
+        "SELECT col3, count(*) FROM example GROUP BY col3 ORDER BY col3;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    conn.prepare("VACUUM;").unwrap().execute().unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite("SELECT * FROM example;", &test_conn, &conn);
+}
+
+#[test]
+fn test_insert_constraint_options() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id INTEGER CONSTRAINT pk PRIMARY KEY DESC, col1 text NOT NULL ON CONFLICT ABORT, col2, UNIQUE(col1 COLLATE NOCASE, col2 DESC));",
+        "INSERT INTO example(id, col1, col2) VALUES (10, 'a', 1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // INTEGER PRIMARY KEY DESC is not the alias of the rowid.
+    assert_same_results(
+        &[&[Some(&Value::Integer(1)), Some(&Value::Integer(10))]],
+        "SELECT rowid, id FROM example;",
+        &test_conn,
+        &conn,
+    );
+    conn.execute_batch(
+        "INSERT INTO example(id, col1, col2) VALUES (20, 'b', 1); INSERT INTO example(id, col1, \
+         col2) VALUES (5, 'a', 2);",
+    )
+    .unwrap();
+    // The keys are compared with the collation of the constraint.
+    assert!(conn
+        .execute_batch("INSERT INTO example(id, col1, col2) VALUES (30, 'A', 1);")
+        .is_err());
+    assert!(conn
+        .execute_batch("INSERT INTO example(id, col1, col2) VALUES (10, 'c', 1);")
+        .is_err());
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite(
+        "SELECT rowid, id, col1, col2 FROM example WHERE col1 = 'a';",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_select_group_by() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'a', 10);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'b', 50);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'a', 20);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'a', 60);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'b', 30);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'a', NULL);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'a', 70);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 'c', 1.5);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col1, col2, count(*) FROM example GROUP BY col1, col2;",
+        "SELECT col1, col2, count(*) FROM example GROUP BY col1, col2 HAVING count(*) > 1;",
+        "SELECT col2, col1 FROM example GROUP BY col2, col1 HAVING count(*) > 1;",
+        "SELECT col1, count(col3), sum(col3), total(col3), avg(col3), min(col3), max(col3) FROM example GROUP BY col1;",
+        "SELECT count(*), sum(col1), min(col2), max(col2) FROM example;",
+        "SELECT count(*), sum(col3), avg(col3), max(col3) FROM example WHERE col1 = 100;",
+        "SELECT col1 FROM example GROUP BY col1 HAVING sum(col3) > 20 ORDER BY count(*) DESC, col1;",
+        "SELECT col2, max(col1) FROM example GROUP BY col2 ORDER BY 2 DESC, 1;",
+        "SELECT count(*) FROM example GROUP BY col1 || col2;",
+        "SELECT col2, sum(col3) FROM example WHERE col1 = 1 GROUP BY col2;",
+        "SELECT col2, COUNT(*) FROM example GROUP BY col2 HAVING max(col3) < 60;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    assert!(conn
+        .prepare("SELECT col1 FROM example WHERE count(*) > 1;")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example GROUP BY count(*);")
+        .is_err());
+    assert!(conn
+        .prepare("SELECT col1 FROM example HAVING col1 > 1;")
+        .is_err());
+    assert!(conn.prepare("SELECT sum(max(col1)) FROM example;").is_err());
+    assert!(conn.prepare("SELECT invalid(col1) FROM example;").is_err());
+}
+
+#[test]
+fn test_select_group_by_result_column() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'a', 10);",
+        "INSERT INTO example(col1, col2, col3) VALUES (2, 'B', 50);",
+        "INSERT INTO example(col1, col2, col3) VALUES (1, 'A', 20);",
+        "INSERT INTO example(col1, col2, col3) VALUES (NULL, 'b', 60);",
+        "INSERT INTO example(col1, col2, col3) VALUES (3, 'c', 30);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for query in [
+        "SELECT col1, count(*) FROM example GROUP BY 1 ORDER BY 1;",
+        "SELECT count(*), upper(col2) FROM example GROUP BY 2 ORDER BY 2;",
+        "SELECT col1 % 2 AS parity, sum(col3) FROM example GROUP BY parity ORDER BY parity;",
+        "SELECT upper(col2) AS u, count(*) FROM example GROUP BY u HAVING count(*) > 1 ORDER BY u;",
+        // The table column takes precedence over the alias.
+        "SELECT col3 AS col1, count(*) FROM example GROUP BY col1 ORDER BY 2, 1;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    for query in [
+        "SELECT col1 FROM example GROUP BY 0;",
+        "SELECT col1 FROM example GROUP BY 2;",
+        "SELECT col1, count(*) FROM example GROUP BY 2;",
+        "SELECT count(*) AS n FROM example GROUP BY n;",
+    ] {
+        assert!(conn.prepare(query).is_err(), "{}", query);
+        assert!(test_conn.prepare(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn test_select_aggregate_real_column() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2 REAL);",
+        "INSERT INTO example(col1, col2) VALUES (1, 1.0);",
+        "INSERT INTO example(col1, col2) VALUES (1, 3);",
+        "INSERT INTO example(col1, col2) VALUES (2, NULL);",
+        "INSERT INTO example(col1, col2) VALUES (2, 2.5);",
+        "INSERT INTO example(col1, col2) VALUES (3, NULL);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // The integral values stored in the REAL column are summed as reals.
+    assert_same_results(
+        &[&[Some(&Value::Real(6.5))]],
+        "SELECT sum(col2) FROM example;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(1)), Some(&Value::Real(4.0))],
+            &[Some(&Value::Integer(2)), Some(&Value::Real(2.5))],
+            &[Some(&Value::Integer(3)), None],
+        ],
+        "SELECT col1, sum(col2) FROM example GROUP BY col1;",
+        &test_conn,
+        &conn,
+    );
+    for query in [
+        "SELECT col1, sum(col2), total(col2), avg(col2), min(col2), max(col2) FROM example GROUP BY col1;",
+        "SELECT sum(col2 + 1), sum(col1) FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_mixed_storage_classes() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3 INTEGER, col4 TEXT COLLATE NOCASE);",
+        "INSERT INTO example VALUES (1, 10, 1, 'b');",
+        "INSERT INTO example VALUES (1, -2.5, '10', 'B');",
+        "INSERT INTO example VALUES (1, 'abc', 'abc', 'a');",
+        "INSERT INTO example VALUES (1, '', x'', 'C');",
+        "INSERT INTO example VALUES (1, x'00', -7, NULL);",
+        "INSERT INTO example VALUES (1, x'', 2.5, 'c');",
+        "INSERT INTO example VALUES (1, NULL, NULL, 'A');",
+        "INSERT INTO example VALUES (2, 'abc', 5, 'x');",
+        "INSERT INTO example VALUES (2, 3, 4, 10);",
+        "INSERT INTO example VALUES (3, x'ff', x'00', x'01');",
+        "INSERT INTO example VALUES (4, NULL, NULL, NULL);",
+        "INSERT INTO example VALUES (5, 9223372036854775807, -9223372036854775808, 1e300);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // NULL < INTEGER and REAL < TEXT < BLOB.
+    for query in [
+        "SELECT min(col2), max(col2), min(col3), max(col3), min(col4), max(col4) FROM example;",
+        "SELECT col1, min(col2), max(col2), min(col3), max(col3) FROM example GROUP BY col1;",
+        "SELECT col1, min(col4), max(col4) FROM example GROUP BY col1;",
+        "SELECT min(col2) FROM example WHERE typeof(col2) != 'integer';",
+        "SELECT max(col2) FROM example WHERE typeof(col2) != 'blob';",
+        "SELECT rowid, col2 FROM example ORDER BY col2, rowid;",
+        "SELECT rowid, col2 FROM example ORDER BY col2 DESC, rowid;",
+        "SELECT rowid, col4 FROM example ORDER BY col4, rowid;",
+        "SELECT rowid FROM example WHERE col2 < x'';",
+        "SELECT rowid FROM example WHERE col2 >= '';",
+        "SELECT rowid FROM example WHERE col2 > 1000000;",
+        "SELECT rowid FROM example WHERE col3 < 'a';",
+        "SELECT rowid FROM example WHERE col2 BETWEEN 0 AND x'00';",
+        "SELECT abs(col2), abs(col3) FROM example WHERE rowid < 11;",
+        "SELECT min(col2, col3), max(col2, col3) FROM example;",
+        "SELECT min(col2, col3, col4), max(col4, col2) FROM example;",
+        "SELECT min(col4, 'B'), max('B', col4), max('B' COLLATE BINARY, col4) FROM example;",
+        "SELECT max(col1, min(col2, 5)) FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    assert!(conn.prepare("SELECT min() FROM example;").is_err());
+}
+
+#[test]
+fn test_select_distinct() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 COLLATE NOCASE, col2, col3);",
+        "CREATE TABLE example2(col1, col2);",
+        "INSERT INTO example VALUES ('abc', 1, 10);",
+        "INSERT INTO example VALUES ('ABC', 1.0, 20);",
+        "INSERT INTO example VALUES ('def', '1', 10);",
+        "INSERT INTO example VALUES (NULL, NULL, 30);",
+        "INSERT INTO example VALUES ('aBc', 2, NULL);",
+        "INSERT INTO example VALUES (NULL, 2, 20);",
+        "INSERT INTO example VALUES ('Def', x'31', 10);",
+        "INSERT INTO example2 VALUES (1, 'x');",
+        "INSERT INTO example2 VALUES (2, 'x');",
+        "INSERT INTO example2 VALUES (1, 'y');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // 'abc', 'ABC' and 'aBc' are the same by NOCASE.
+    assert_eq!(
+        load_rowids(&conn, "SELECT count(DISTINCT col1) FROM example;"),
+        vec![2]
+    );
+    assert_eq!(
+        load_rowids(
+            &conn,
+            "SELECT count(DISTINCT col1 COLLATE BINARY) FROM example;"
+        ),
+        vec![5]
+    );
+
+    for query in [
+        "SELECT DISTINCT col1 FROM example ORDER BY col1;",
+        "SELECT DISTINCT col3 FROM example ORDER BY col3;",
+        "SELECT DISTINCT col2 FROM example ORDER BY col3;",
+        "SELECT DISTINCT col1, col3 FROM example ORDER BY 1, 2;",
+        "SELECT DISTINCT col1 COLLATE BINARY FROM example ORDER BY 1;",
+        "SELECT DISTINCT col3 * 0 FROM example ORDER BY 1;",
+        "SELECT DISTINCT * FROM example2 ORDER BY col2, col1;",
+        "SELECT DISTINCT col2 FROM example2 ORDER BY col2 DESC;",
+        "SELECT DISTINCT count(*) FROM example GROUP BY col3 ORDER BY 1;",
+        "SELECT ALL col3 FROM example ORDER BY col3;",
+        "SELECT count(DISTINCT col1), count(DISTINCT col2), count(DISTINCT col3) FROM example;",
+        "SELECT sum(DISTINCT col3), total(DISTINCT col3), avg(DISTINCT col3) FROM example;",
+        "SELECT min(DISTINCT col1), max(DISTINCT col1) FROM example;",
+        "SELECT count(ALL col3), sum(ALL col3) FROM example;",
+        "SELECT col3, count(DISTINCT col1) FROM example GROUP BY col3;",
+        "SELECT col1, count(DISTINCT col3), sum(DISTINCT col3) FROM example GROUP BY col1;",
+        "SELECT count(DISTINCT col1) FROM example WHERE col1 IS NULL;",
+        "SELECT abs(DISTINCT col3) FROM example;",
+        "SELECT col1 FROM example2 WHERE col1 IN (SELECT DISTINCT col2 FROM example);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // Rows without ORDER BY keep the order of their first occurrences.
+    for query in [
+        "SELECT DISTINCT col3 FROM example;",
+        "SELECT DISTINCT col1 FROM example2;",
+        "SELECT DISTINCT col2, col1 + 1 FROM example2;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    for query in [
+        "SELECT count(DISTINCT *) FROM example;",
+        "SELECT count(DISTINCT) FROM example;",
+        "SELECT count(DISTINCT col1, col2) FROM example;",
+        "SELECT DISTINCT FROM example;",
+    ] {
+        assert!(conn.prepare(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn test_select_min_max_bare_columns() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id INTEGER PRIMARY KEY, grp, c, name COLLATE NOCASE);",
+        "INSERT INTO example VALUES (1, 1, 5, 'abc');",
+        "INSERT INTO example VALUES (2, 1, 3, 'ABC');",
+        "INSERT INTO example VALUES (3, 1, 3, 'aBc');",
+        "INSERT INTO example VALUES (4, 1, 7, 'Abc');",
+        "INSERT INTO example VALUES (5, 2, NULL, 'x');",
+        "INSERT INTO example VALUES (6, 2, NULL, 'y');",
+        "INSERT INTO example VALUES (7, 2, 4, 'z');",
+        "INSERT INTO example VALUES (8, 2, NULL, 'w');",
+        "INSERT INTO example VALUES (9, 3, NULL, 'v');",
+        "INSERT INTO example VALUES (10, 3, NULL, 'u');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // Bare columns come from the row holding the minimum or the maximum.
+    assert_same_results(
+        &[&[Some(&Value::Integer(2)), Some(&Value::Integer(3))]],
+        "SELECT id, min(c) FROM example;",
+        &test_conn,
+        &conn,
+    );
+    assert_same_results(
+        &[&[Some(&Value::Integer(4)), Some(&Value::Integer(7))]],
+        "SELECT id, max(c) FROM example;",
+        &test_conn,
+        &conn,
+    );
+
+    for query in [
+        "SELECT min(c), id FROM example;",
+        "SELECT max(c), id, count(*) FROM example;",
+        "SELECT grp, min(c), id FROM example GROUP BY grp;",
+        "SELECT grp, max(c), id, name FROM example GROUP BY grp;",
+        "SELECT max(id), c FROM example WHERE c IS NULL;",
+        "SELECT min(c), id FROM example WHERE grp = 3;",
+        "SELECT name, max(id) FROM example GROUP BY name;",
+        "SELECT name, min(c) FROM example GROUP BY name;",
+        "SELECT id, sum(c) FROM example GROUP BY grp;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_group_by_collation() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 COLLATE NOCASE, col2 COLLATE RTRIM, col3);",
+        "INSERT INTO example VALUES ('abc', 'abc', 1);",
+        "INSERT INTO example VALUES ('ABC', 'abc  ', 2);",
+        "INSERT INTO example VALUES ('aBc', 'ABC', 3);",
+        "INSERT INTO example VALUES ('abd', 'abc', 4);",
+        "INSERT INTO example VALUES ('ABD ', 'abd', 5);",
+        "INSERT INTO example VALUES (NULL, NULL, 6);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // 'abc', 'ABC' and 'aBc' collapse into one group.
+    assert_eq!(
+        load_rowids(&conn, "SELECT count(*) FROM example GROUP BY col1;"),
+        vec![1, 3, 1, 1]
+    );
+    assert_eq!(
+        load_rowids(&conn, "SELECT sum(col3) FROM example GROUP BY col1;"),
+        vec![6, 6, 4, 5]
+    );
+    // 'abc' and 'abc  ' are in the same group by RTRIM.
+    assert_eq!(
+        load_rowids(&conn, "SELECT count(*) FROM example GROUP BY col2;"),
+        vec![1, 1, 3, 1]
+    );
+
+    for query in [
+        "SELECT count(*), sum(col3) FROM example GROUP BY col1;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col2;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col1, col2;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col3 = 1, col1;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col2 COLLATE NOCASE;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col1 COLLATE BINARY;",
+        "SELECT count(*), sum(col3) FROM example GROUP BY col3 || '';",
+        "SELECT count(*) FROM example GROUP BY col1 HAVING count(*) > 1;",
+        "SELECT col1, col2, count(*) FROM example GROUP BY col1;",
+        "SELECT max(col1), min(col2) FROM example GROUP BY col2;",
+        "SELECT col1 FROM example UNION SELECT col2 FROM example;",
+        "SELECT col2 FROM example UNION SELECT col1 FROM example;",
+        "SELECT col1 FROM example INTERSECT SELECT col2 FROM example;",
+        "SELECT col1 FROM example EXCEPT SELECT col3 FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+}
+
+#[test]
+fn test_select_group_by_multiple_pages() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2, col3);",
+    ];
+    let insert_queries = (0..1000)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2, col3) VALUES ({}, {}, {});",
+                (i * 7919) % 37,
+                i % 3,
+                i
+            )
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    assert_same_results_as_sqlite(
+        "SELECT col1, col2, count(*), sum(col3) FROM example GROUP BY col1, col2 HAVING count(*) > 9;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_select_count_all() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE TABLE example2(col1 PRIMARY KEY, col2) WITHOUT ROWID;",
+        "CREATE TABLE empty(col1);",
+    ];
+    let insert_queries = (0..2000)
+        .flat_map(|i| {
+            [
+                format!(
+                    "INSERT INTO example(col1, col2) VALUES ({}, '{}');",
+                    i,
+                    "a".repeat(i % 100)
+                ),
+                format!(
+                    "INSERT INTO example2(col1, col2) VALUES ({}, '{}');",
+                    i,
+                    "a".repeat(i % 100)
+                ),
+            ]
+        })
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    queries.push("DELETE FROM example WHERE col1 % 3 = 0;");
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    for table in ["example", "example2", "empty"] {
+        // count(col1) scans the rows.
+        let scan_count = load_rowids(&conn, &format!("SELECT count(col1) FROM {table};"));
+        for query in [
+            format!("SELECT count(*) FROM {table};"),
+            format!("SELECT count(*) FROM {table} WHERE 1;"),
+            format!("SELECT count(*), count(*) + 1 AS n FROM {table};"),
+        ] {
+            assert_same_results_as_sqlite(&query, &test_conn, &conn);
+        }
+        assert_eq!(
+            load_rowids(&conn, &format!("SELECT count(*) FROM {table};")),
+            scan_count,
+            "table: {}",
+            table
+        );
+    }
+    for query in [
+        "SELECT count(*) FROM example WHERE col1 > 1000;",
+        "SELECT count(*) FROM example GROUP BY col1 % 2;",
+        "SELECT count(*) FROM example HAVING count(*) > 10;",
+        "SELECT count(*), count(col2) FROM example;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // The count reflects the changes in the transaction.
+    let txn = conn.begin().unwrap();
+    conn.prepare("DELETE FROM example WHERE col1 < 100;")
+        .unwrap()
+        .execute()
+        .unwrap();
+    assert_eq!(
+        load_rowids(&conn, "SELECT count(*) FROM example;"),
+        load_rowids(&conn, "SELECT count(col1) FROM example;")
+    );
+    drop(txn);
+    assert_eq!(
+        load_rowids(&conn, "SELECT count(*) FROM example;"),
+        load_test_rowids(&test_conn, "SELECT count(*) FROM example;")
+    );
+}
+
+#[test]
+fn test_select_parameters() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+        "INSERT INTO example(col1, col2) VALUES (1, 'a');",
+        "INSERT INTO example(col1, col2) VALUES ('b', 2);",
+        "INSERT INTO example(col1, col2) VALUES (x'0102', 3.5);",
+        "INSERT INTO example(col1, col2) VALUES (NULL, 4);",
+        "INSERT INTO example(col1, col2) VALUES (1, 'c');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let mut stmt = conn
+        .prepare("SELECT rowid, col2, ?2 FROM example WHERE col1 = ?1;")
+        .unwrap();
+    assert_eq!(stmt.parameter_count(), 2);
+    for (value, expected) in [
+        (Some(Value::Integer(1)), vec![1, 5]),
+        (Some(Value::Text(b"b"[..].into())), vec![2]),
+        (Some(Value::Blob(b"\x01\x02"[..].into())), vec![3]),
+        (Some(Value::Integer(100)), vec![]),
+        // col1 = NULL is always NULL.
+        (None, vec![]),
+    ] {
+        stmt.bind(1, value).unwrap();
+        stmt.bind(2, Some(Value::Text(b"x"[..].into()))).unwrap();
+        let mut rows = stmt.query().unwrap();
+        let mut rowids = Vec::new();
+        while let Some(row) = rows.next_row().unwrap() {
+            let columns = row.parse().unwrap();
+            assert_eq!(columns.get(2), Some(&Value::Text(b"x"[..].into())));
+            let Some(Value::Integer(rowid)) = columns.get(0) else {
+                panic!("rowid is not integer");
+            };
+            rowids.push(*rowid);
+        }
+        assert_eq!(rowids, expected);
+    }
+
+    // Unbound parameters are NULL.
+    let stmt = conn
+        .prepare("SELECT col2, :name FROM example WHERE rowid = 4;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+    assert_eq!(columns.get(0), Some(&Value::Integer(4)));
+    assert_eq!(columns.get(1), None);
+}
+
+#[test]
+fn test_page_cache() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;".to_string(),
+        "CREATE TABLE example(col);".to_string(),
+    ];
+    for i in 0..500 {
+        stmts.push(format!("INSERT INTO example(col) VALUES ({});", i));
+    }
+    let file = create_sqlite_database(&stmts.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+    let conn = Connection::open(file.path()).unwrap();
+    // Load the schema first.
+    conn.prepare("SELECT col FROM example;").unwrap();
+
+    let stats = conn.cache_stats();
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;").len(), 500);
+    let first = conn.cache_stats();
+    assert!(first.misses - stats.misses > 1);
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;").len(), 500);
+    let second = conn.cache_stats();
+    assert_eq!(second.misses, first.misses);
+    assert!(second.hits > first.hits);
+
+    // Pages are read again if the cache is too small.
+    conn.set_cache_size(2);
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;").len(), 500);
+    assert!(conn.cache_stats().misses > second.misses);
+    assert!(conn.cache_stats().hit_rate() < second.hit_rate());
+}
+
+#[test]
+fn test_readahead() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;".to_string(),
+        "CREATE TABLE example(col);".to_string(),
+    ];
+    for i in 0..2000 {
+        stmts.push(format!("INSERT INTO example(col) VALUES ({});", i));
+    }
+    let file = create_sqlite_database(&stmts.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+
+    let mut misses = Vec::new();
+    for readahead in [0, 16] {
+        let conn = Connection::open(file.path()).unwrap();
+        conn.set_readahead(readahead);
+        // Load the schema first.
+        conn.prepare("SELECT col FROM example;").unwrap();
+
+        let stats = conn.cache_stats();
+        assert_eq!(
+            load_rowids(&conn, "SELECT rowid FROM example;"),
+            (1..=2000).collect::<Vec<_>>()
+        );
+        misses.push(conn.cache_stats().misses - stats.misses);
+    }
+    // Leaf pages prefetched in batches are not read on demand.
+    assert!(misses[1] * 4 < misses[0], "misses: {:?}", misses);
+}
+
+#[test]
+fn test_statement_cache() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+        "INSERT INTO example(col) VALUES (2);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let sql = "SELECT rowid FROM example WHERE col = ?;";
+
+    let mut stmt = conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats().misses, 1);
+    assert_eq!(conn.statement_cache_stats().hits, 0);
+    stmt.bind(1, Some(Value::Integer(2))).unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1, 2]);
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    assert_eq!(row.parse().unwrap().get(0), Some(&Value::Integer(2)));
+    drop(row);
+    drop(rows);
+
+    // The cached plan is reused without the parameters bound to the other
+    // statement.
+    let stmt = conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats().hits, 1);
+    let mut rows = stmt.query().unwrap();
+    assert!(rows.next_row().unwrap().is_none());
+    drop(rows);
+
+    // Schema changes invalidate the cached plans.
+    conn.execute_batch("CREATE TABLE example2(col);").unwrap();
+    let stats = conn.statement_cache_stats();
+    conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats().misses, stats.misses + 1);
+    conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats().hits, stats.hits + 1);
+
+    conn.clear_statement_cache();
+    let stats = conn.statement_cache_stats();
+    conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats().misses, stats.misses + 1);
+
+    // The least recently used statement is evicted.
+    conn.set_statement_cache_size(1);
+    conn.prepare("SELECT col FROM example;").unwrap();
+    let stats = conn.statement_cache_stats();
+    conn.prepare(sql).unwrap();
+    conn.prepare("SELECT col FROM example;").unwrap();
+    assert_eq!(conn.statement_cache_stats().misses, stats.misses + 2);
+
+    // Disabled cache.
+    conn.set_statement_cache_size(0);
+    let stats = conn.statement_cache_stats();
+    conn.prepare(sql).unwrap();
+    conn.prepare(sql).unwrap();
+    assert_eq!(conn.statement_cache_stats(), stats);
+}
@@ -0,0 +1,79 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::path::Path;
+
+use common::*;
+use prsqlite::Connection;
+
+#[test]
+fn test_open_memory() {
+    for conn in [
+        Connection::open_memory().unwrap(),
+        Connection::open(Path::new(":memory:")).unwrap(),
+    ] {
+        assert!(load_rowids(&conn, "SELECT rowid FROM sqlite_schema;").is_empty());
+        assert!(conn.prepare("SELECT * FROM example;").is_err());
+    }
+}
+
+#[test]
+fn test_memory_write() {
+    let conn = Connection::open_memory().unwrap();
+    // Views are not loaded into the schema yet, so they can be inserted as
+    // plain rows.
+    let insert_stmt = conn
+        .prepare(&format!(
+            "INSERT INTO sqlite_schema(type, name, tbl_name, rootpage, sql) VALUES ('view', 'v', 'v', 0, '{}');",
+            "a".repeat(100)
+        ))
+        .unwrap();
+
+    let txn = conn.begin().unwrap();
+    for _ in 0..200 {
+        assert_eq!(insert_stmt.execute().unwrap(), 1);
+    }
+    txn.commit().unwrap();
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM sqlite_schema;"),
+        (1..=200).collect::<Vec<_>>()
+    );
+
+    let txn = conn.begin().unwrap();
+    let stmt = conn
+        .prepare("DELETE FROM sqlite_schema WHERE rowid > 10;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 190);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM sqlite_schema;"),
+        (1..=10).collect::<Vec<_>>()
+    );
+    txn.rollback();
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM sqlite_schema;").len(),
+        200
+    );
+
+    // Statements outside of a transaction commit on their own.
+    let stmt = conn
+        .prepare("DELETE FROM sqlite_schema WHERE rowid > 10;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 190);
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM sqlite_schema;"),
+        (1..=10).collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,355 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::SchemaEntry;
+use prsqlite::TextEncoding;
+use prsqlite::Value;
+
+#[test]
+fn test_user_version() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);", "PRAGMA user_version = 3;"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let select_stmt = conn.prepare("PRAGMA user_version;").unwrap();
+    assert!(select_stmt.is_query());
+    assert_eq!(select_stmt.column_names(), &["user_version"]);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results_as_sqlite("PRAGMA user_version;", &test_conn, &conn);
+    drop(test_conn);
+
+    let stmt = conn.prepare("PRAGMA user_version = 10;").unwrap();
+    assert!(!stmt.is_query());
+    assert_eq!(stmt.execute().unwrap(), 0);
+    // The prepared statement reads the current value.
+    let mut rows = select_stmt.query().unwrap();
+    assert_eq!(
+        rows.next().unwrap().unwrap().get(0),
+        Some(&prsqlite::Value::Integer(10))
+    );
+    assert!(rows.next().is_none());
+    drop(rows);
+
+    // The value is kept across reopen.
+    drop(select_stmt);
+    drop(stmt);
+    drop(conn);
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(load_rowids(&conn, "pragma USER_VERSION;"), vec![10]);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_eq!(
+        load_test_rowids(&test_conn, "PRAGMA user_version;"),
+        vec![10]
+    );
+    drop(test_conn);
+
+    for (query, expected) in [
+        ("PRAGMA user_version = -5;", -5),
+        ("PRAGMA user_version = '7';", 7),
+        ("PRAGMA user_version = 4294967297;", 1),
+        ("PRAGMA user_version(2147483647);", i32::MAX as i64),
+    ] {
+        assert_eq!(conn.prepare(query).unwrap().execute().unwrap(), 0);
+        assert_eq!(
+            load_rowids(&conn, "PRAGMA user_version;"),
+            vec![expected],
+            "{}",
+            query
+        );
+        let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+        assert_eq!(
+            load_test_rowids(&test_conn, "PRAGMA user_version;"),
+            vec![expected],
+            "{}",
+            query
+        );
+    }
+
+    // Rolled back with the transaction.
+    let txn = conn.begin().unwrap();
+    let stmt = conn.prepare("PRAGMA user_version = 100;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert_eq!(load_rowids(&conn, "PRAGMA user_version;"), vec![100]);
+    txn.rollback();
+    assert_eq!(
+        load_rowids(&conn, "PRAGMA user_version;"),
+        vec![i32::MAX as i64]
+    );
+}
+
+#[test]
+fn test_page_size() {
+    let file = create_sqlite_database(&["PRAGMA page_size = 1024;", "CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results_as_sqlite("PRAGMA page_size;", &test_conn, &conn);
+    assert_eq!(load_rowids(&conn, "PRAGMA page_size;"), vec![1024]);
+
+    assert!(matches!(
+        conn.prepare("PRAGMA page_size = 4096;"),
+        Err(Error::Unsupported(_))
+    ));
+
+    let conn = Connection::open_memory().unwrap();
+    assert_eq!(load_rowids(&conn, "PRAGMA page_size;"), vec![4096]);
+}
+
+#[test]
+fn test_header_metadata() {
+    let file = create_sqlite_database(&[
+        "PRAGMA application_id = 1095258695;",
+        "CREATE TABLE example(col);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_eq!(conn.application_id().unwrap(), 1095258695);
+    assert_eq!(conn.text_encoding().unwrap(), TextEncoding::Utf8);
+    let version: i64 = test_conn
+        .query_row("SELECT sqlite_version();", [], |row| {
+            let version: String = row.get(0)?;
+            let mut parts = version.split('.').map(|v| v.parse::<i64>().unwrap());
+            Ok(parts.next().unwrap() * 1000000
+                + parts.next().unwrap() * 1000
+                + parts.next().unwrap())
+        })
+        .unwrap();
+    assert_eq!(conn.sqlite_version_number().unwrap() as i64, version);
+
+    let file =
+        create_sqlite_database(&["PRAGMA application_id = -1;", "CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(conn.application_id().unwrap(), -1);
+
+    let conn = Connection::open_memory().unwrap();
+    assert_eq!(conn.application_id().unwrap(), 0);
+    assert_eq!(conn.text_encoding().unwrap(), TextEncoding::Utf8);
+    assert_eq!(conn.sqlite_version_number().unwrap(), 3041002);
+}
+
+#[test]
+fn test_pragma_fail() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    assert!(matches!(
+        conn.prepare("PRAGMA invalid;"),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(conn.prepare("PRAGMA user_version = ;").is_err());
+    assert!(conn
+        .prepare("PRAGMA user_version;")
+        .unwrap()
+        .execute()
+        .is_err());
+
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    assert_eq!(load_rowids(&conn, "PRAGMA user_version;"), vec![0]);
+    let stmt = conn.prepare("PRAGMA user_version = 1;").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::ReadOnly)));
+}
+
+#[test]
+fn test_table_info() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1 varchar ( 10 ) not null, \"col2\" text default 'abc', col3 long  real default -1, col4);",
+        "CREATE TABLE example2(col1, col2 \"int\" not null, col3 blob, primary key(col3, col1)) WITHOUT ROWID;",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("PRAGMA table_info(example);").unwrap();
+    assert!(stmt.is_query());
+    assert_eq!(
+        stmt.column_names(),
+        &["cid", "name", "type", "notnull", "dflt_value", "pk"]
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    for query in [
+        "PRAGMA table_info(example);",
+        "PRAGMA table_info('EXAMPLE');",
+        "PRAGMA table_info(example2);",
+        "PRAGMA table_info(sqlite_schema);",
+        "PRAGMA table_info(invalid);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(0)),
+                Some(&Value::Text(b"col1".as_slice().into())),
+                Some(&Value::Text(b"".as_slice().into())),
+                Some(&Value::Integer(1)),
+                None,
+                Some(&Value::Integer(2)),
+            ],
+            &[
+                Some(&Value::Integer(1)),
+                Some(&Value::Text(b"col2".as_slice().into())),
+                Some(&Value::Text(b"INT".as_slice().into())),
+                Some(&Value::Integer(1)),
+                None,
+                Some(&Value::Integer(0)),
+            ],
+            &[
+                Some(&Value::Integer(2)),
+                Some(&Value::Text(b"col3".as_slice().into())),
+                Some(&Value::Text(b"BLOB".as_slice().into())),
+                Some(&Value::Integer(1)),
+                None,
+                Some(&Value::Integer(1)),
+            ],
+        ],
+        "PRAGMA table_info(example2);",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_index_list_and_index_info() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 text primary key, col2 unique, col3, UNIQUE(col3, col2));",
+        "CREATE INDEX index1 ON example(col3 COLLATE NOCASE, col1);",
+        "CREATE UNIQUE INDEX \"Index2\" ON example(col2);",
+        "CREATE TABLE example2(id integer primary key, col);",
+        "CREATE INDEX index3 ON example2(col, id);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("PRAGMA index_list(example);").unwrap();
+    assert_eq!(
+        stmt.column_names(),
+        &["seq", "name", "unique", "origin", "partial"]
+    );
+    let stmt = conn.prepare("PRAGMA index_info(index1);").unwrap();
+    assert_eq!(stmt.column_names(), &["seqno", "cid", "name"]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    for query in [
+        "PRAGMA index_list(example);",
+        "PRAGMA index_list(example2);",
+        "PRAGMA index_list(sqlite_schema);",
+        "PRAGMA index_list(invalid);",
+        "PRAGMA index_info(index1);",
+        "PRAGMA index_info(INDEX2);",
+        "PRAGMA index_info('index3');",
+        "PRAGMA index_info(sqlite_autoindex_example_1);",
+        "PRAGMA index_info(sqlite_autoindex_example_3);",
+        "PRAGMA index_info(example);",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // The prepared statement is invalidated by schema changes.
+    let stmt = conn.prepare("PRAGMA index_list(example2);").unwrap();
+    assert_eq!(
+        conn.prepare("CREATE INDEX index4 ON example2(col);")
+            .unwrap()
+            .execute()
+            .unwrap(),
+        0
+    );
+    assert!(stmt.query().is_err());
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results_as_sqlite("PRAGMA index_list(example2);", &test_conn, &conn);
+}
+
+#[test]
+fn test_tables_and_indexes() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 text primary key, col2);",
+        "CREATE INDEX index1 ON example(col2);",
+        "CREATE TABLE \"Example2\"(id integer primary key, col);",
+        "CREATE UNIQUE INDEX index2 ON Example2(col, id);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    let mut stmt = test_conn
+        .prepare("SELECT type, name, tbl_name, rootpage, sql FROM sqlite_schema;")
+        .unwrap();
+    let expected = stmt
+        .query_map([], |row| {
+            Ok(SchemaEntry {
+                type_: row.get(0)?,
+                name: row.get(1)?,
+                table_name: row.get(2)?,
+                root_page: row.get(3)?,
+                sql: row.get(4)?,
+            })
+        })
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    drop(stmt);
+
+    let tables = conn.tables().unwrap();
+    assert_eq!(
+        tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+        ["example", "Example2"]
+    );
+    assert_eq!(
+        tables[0].sql.as_deref(),
+        Some("CREATE TABLE example(col1 text primary key, col2)")
+    );
+    assert_eq!(
+        tables,
+        expected
+            .iter()
+            .filter(|e| e.type_ == "table")
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+
+    let indexes = conn.indexes().unwrap();
+    assert_eq!(
+        indexes
+            .iter()
+            .map(|i| (i.name.as_str(), i.table_name.as_str(), i.sql.as_deref()))
+            .collect::<Vec<_>>(),
+        [
+            ("sqlite_autoindex_example_1", "example", None),
+            (
+                "index1",
+                "example",
+                Some("CREATE INDEX index1 ON example(col2)")
+            ),
+            (
+                "index2",
+                "Example2",
+                Some("CREATE UNIQUE INDEX index2 ON Example2(col, id)")
+            ),
+        ]
+    );
+    assert_eq!(
+        indexes,
+        expected
+            .iter()
+            .filter(|e| e.type_ == "index")
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+
+    // Schema changes are reflected.
+    conn.prepare("CREATE INDEX index3 ON example(col1, col2);")
+        .unwrap()
+        .execute()
+        .unwrap();
+    assert_eq!(conn.indexes().unwrap().len(), 4);
+    conn.prepare("CREATE TABLE example3(col);")
+        .unwrap()
+        .execute()
+        .unwrap();
+    assert_eq!(conn.tables().unwrap().len(), 3);
+}
@@ -0,0 +1,131 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct User {
+    id: i64,
+    name: String,
+    age: Option<u8>,
+    score: Option<f64>,
+    icon: Vec<u8>,
+    active: bool,
+}
+
+#[test]
+fn test_deserialize_struct() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE user(id integer primary key, name text, age integer, score real, icon blob, active integer);",
+        "INSERT INTO user(name, age, score, icon, active) VALUES ('alice', 20, 1.5, x'0102', 1);",
+        "INSERT INTO user(name, age, score, icon, active) VALUES ('bob', NULL, NULL, x'', 0);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("SELECT * FROM user;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    let mut users = Vec::new();
+    while let Some(row) = rows.next_row().unwrap() {
+        users.push(row.deserialize::<User>().unwrap());
+    }
+    assert_eq!(
+        users,
+        vec![
+            User {
+                id: 1,
+                name: "alice".to_string(),
+                age: Some(20),
+                score: Some(1.5),
+                icon: vec![1, 2],
+                active: true,
+            },
+            User {
+                id: 2,
+                name: "bob".to_string(),
+                age: None,
+                score: None,
+                icon: Vec::new(),
+                active: false,
+            },
+        ]
+    );
+
+    // The fields are looked up by the column names.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Renamed {
+        #[serde(rename = "user_name")]
+        name: String,
+        missing: Option<i64>,
+    }
+    let stmt = conn
+        .prepare("SELECT id, name AS user_name FROM user WHERE id = 2;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    assert_eq!(
+        row.deserialize::<Renamed>().unwrap(),
+        Renamed {
+            name: "bob".to_string(),
+            missing: None,
+        }
+    );
+}
+
+#[test]
+fn test_deserialize_borrowed_and_tuple() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "INSERT INTO example(col1, col2, col3) VALUES ('hello', x'ff', 3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn
+        .prepare("SELECT col1, col2, col3 FROM example;")
+        .unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Borrowed<'a> {
+        col1: &'a str,
+        col2: &'a [u8],
+    }
+    assert_eq!(
+        columns.deserialize::<Borrowed>().unwrap(),
+        Borrowed {
+            col1: "hello",
+            col2: &[0xff],
+        }
+    );
+    assert_eq!(
+        columns.deserialize::<(String, Vec<u8>, i32)>().unwrap(),
+        ("hello".to_string(), vec![0xff], 3)
+    );
+
+    // Type mismatches fail.
+    assert!(columns
+        .deserialize::<std::collections::HashMap<String, i64>>()
+        .is_err());
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Invalid {
+        col1: i64,
+    }
+    assert!(row.deserialize::<Invalid>().is_err());
+}
@@ -0,0 +1,176 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use common::*;
+use prsqlite::Error;
+use prsqlite::SharedConnection;
+
+#[test]
+fn test_shared_connection_threads() {
+    let mut queries = vec!["CREATE TABLE example(col);"];
+    let insert_queries = (0..1000)
+        .map(|i| format!("INSERT INTO example(col) VALUES ({});", i))
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let shared = Arc::new(SharedConnection::open(file.path()).unwrap());
+
+    let handles = (0..8)
+        .map(|i| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    let rowids = shared
+                        .with(|conn| {
+                            load_rowids(
+                                conn,
+                                &format!("SELECT rowid FROM example WHERE col % 8 = {};", i),
+                            )
+                        })
+                        .unwrap();
+                    assert_eq!(rowids.len(), 125);
+                    assert!(rowids.iter().all(|rowid| (rowid - 1) % 8 == i));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // The handle is read-only.
+    let result = shared.with(|conn| {
+        conn.prepare("INSERT INTO example(col) VALUES (1000);")
+            .unwrap()
+            .execute()
+            .is_err()
+    });
+    assert!(result.unwrap());
+}
+
+#[test]
+fn test_shared_connection_open_fail() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    assert!(matches!(
+        SharedConnection::open(file.path()),
+        Err(Error::NotADatabase)
+    ));
+}
+
+#[test]
+fn test_shared_connection_close() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let shared = SharedConnection::open(file.path()).unwrap();
+    let moved_path = file.path().with_extension("moved");
+
+    // The open connection keeps working after the file is moved.
+    std::fs::rename(file.path(), &moved_path).unwrap();
+    let rowids = shared
+        .with(|conn| load_rowids(conn, "SELECT rowid FROM example;"))
+        .unwrap();
+    assert_eq!(rowids, vec![1]);
+
+    // The closed connection is reopened by the next use.
+    shared.close();
+    assert!(matches!(
+        shared.with(|conn| load_rowids(conn, "SELECT rowid FROM example;")),
+        Err(Error::Io(_))
+    ));
+    std::fs::rename(&moved_path, file.path()).unwrap();
+    let rowids = shared
+        .with(|conn| load_rowids(conn, "SELECT rowid FROM example;"))
+        .unwrap();
+    assert_eq!(rowids, vec![1]);
+
+    // Closing the connection of another thread does not affect this thread.
+    let shared = Arc::new(shared);
+    thread::spawn({
+        let shared = shared.clone();
+        move || {
+            shared.with(|_| ()).unwrap();
+            shared.close();
+        }
+    })
+    .join()
+    .unwrap();
+    std::fs::rename(file.path(), &moved_path).unwrap();
+    let rowids = shared
+        .with(|conn| load_rowids(conn, "SELECT rowid FROM example;"))
+        .unwrap();
+    assert_eq!(rowids, vec![1]);
+    std::fs::rename(&moved_path, file.path()).unwrap();
+}
+
+/// The number of the file descriptors of the process opening the file.
+fn count_open_files(path: &Path) -> usize {
+    let path = path.canonicalize().unwrap();
+    std::fs::read_dir("/proc/self/fd")
+        .unwrap()
+        .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+        .filter(|target| *target == path)
+        .count()
+}
+
+#[test]
+fn test_shared_connection_pruned_on_other_thread() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let other_file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    // The temporary file itself is kept open.
+    let n_open_files = count_open_files(file.path());
+    let shared = Arc::new(SharedConnection::open(file.path()).unwrap());
+    let other_shared = Arc::new(SharedConnection::open(other_file.path()).unwrap());
+
+    let (dropped_tx, dropped_rx) = mpsc::channel();
+    let (used_tx, used_rx) = mpsc::channel();
+    let handle = thread::spawn({
+        let shared = shared.clone();
+        let other_shared = other_shared.clone();
+        move || {
+            shared.with(|_| ()).unwrap();
+            other_shared.with(|_| ()).unwrap();
+            drop(shared);
+            used_tx.send(()).unwrap();
+            dropped_rx.recv().unwrap();
+            // Using the connection already open for another handle closes the
+            // connection of the dropped handle.
+            other_shared.with(|_| ()).unwrap();
+            used_tx.send(()).unwrap();
+            dropped_rx.recv().unwrap();
+        }
+    });
+
+    used_rx.recv().unwrap();
+    // Both this thread and the other thread open the file.
+    assert_eq!(count_open_files(file.path()), n_open_files + 2);
+    drop(shared);
+    assert_eq!(count_open_files(file.path()), n_open_files + 1);
+    dropped_tx.send(()).unwrap();
+    used_rx.recv().unwrap();
+    assert_eq!(count_open_files(file.path()), n_open_files);
+    dropped_tx.send(()).unwrap();
+    handle.join().unwrap();
+}
@@ -0,0 +1,496 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::Value;
+
+#[test]
+fn test_transaction_commit() {
+    let file = create_sqlite_database(&[
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    let mut stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (?, ?);")
+        .unwrap();
+    let txn = conn.begin().unwrap();
+    for i in 0..1000 {
+        stmt.bind(1, Some(Value::Integer(i % 10))).unwrap();
+        stmt.bind(2, Some(Value::Integer(i))).unwrap();
+        assert_eq!(stmt.execute().unwrap(), 1);
+    }
+    let stmt = conn
+        .prepare("UPDATE example SET col2 = -1 WHERE col1 = 3;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 100);
+    let stmt = conn.prepare("DELETE FROM example WHERE col1 = 4;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 100);
+
+    // The changes are visible in the transaction.
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example WHERE col2 = -1;").len(),
+        100
+    );
+    // The changes are not written to the file yet.
+    assert!(load_test_rowids(&test_conn, "SELECT rowid FROM example;").is_empty());
+
+    txn.commit().unwrap();
+
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT rowid FROM example;",
+        "SELECT rowid FROM example WHERE col2 = -1;",
+        "SELECT rowid FROM example WHERE col1 = 4;",
+    ] {
+        // SQLite may scan the index instead.
+        let mut expected = load_test_rowids(&test_conn, query);
+        expected.sort();
+        assert_eq!(load_rowids(&conn, query), expected, "{}", query);
+    }
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;").len(),
+        900
+    );
+
+    // Statements commit on their own after the transaction.
+    let stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (100, 100);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    // test_conn sees the change by the file change counter.
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example WHERE col1 = 100;"),
+        vec![1001]
+    );
+}
+
+#[test]
+fn test_transaction_rollback() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+    let delete_stmt = conn.prepare("DELETE FROM example;").unwrap();
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    assert_eq!(delete_stmt.execute().unwrap(), 2);
+    assert!(load_rowids(&conn, "SELECT rowid FROM example;").is_empty());
+    txn.rollback();
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1]);
+
+    // Dropping the transaction also discards the changes.
+    let txn = conn.begin().unwrap();
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    drop(txn);
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1]);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        vec![1]
+    );
+}
+
+#[test]
+fn test_transaction_statement_failure() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (1);")
+        .unwrap();
+    let conflict_stmt = conn
+        .prepare("INSERT INTO example(rowid, col) VALUES (1, 2);")
+        .unwrap();
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    assert!(matches!(
+        conflict_stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    // The transaction is aborted.
+    assert!(matches!(
+        insert_stmt.execute(),
+        Err(Error::TransactionAborted)
+    ));
+    assert!(matches!(txn.commit(), Err(Error::TransactionAborted)));
+
+    assert!(load_rowids(&conn, "SELECT rowid FROM example;").is_empty());
+    assert_eq!(insert_stmt.execute().unwrap(), 1);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        vec![1]
+    );
+}
+
+#[test]
+fn test_transaction_fail() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let txn = conn.begin().unwrap();
+    assert!(matches!(conn.begin(), Err(Error::Busy)));
+    assert!(conn.begin_read().is_ok());
+    drop(txn);
+
+    let snapshot = conn.begin_read().unwrap();
+    assert!(matches!(conn.begin(), Err(Error::Busy)));
+    drop(snapshot);
+
+    let stmt = conn.prepare("SELECT col FROM example;").unwrap();
+    let txn = conn.begin().unwrap();
+    let rows = stmt.query().unwrap();
+    assert!(matches!(txn.commit(), Err(Error::Busy)));
+    drop(rows);
+    assert!(conn.begin().is_ok());
+
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    assert!(matches!(conn.begin(), Err(Error::ReadOnly)));
+}
+
+#[test]
+fn test_execute_batch() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    conn.execute_batch(
+        "CREATE TABLE example2(id integer primary key, col);
+        INSERT INTO example2(col) VALUES (10);
+        INSERT INTO example2(col) VALUES (20), (30);
+        SELECT * FROM example2;
+        CREATE INDEX index1 ON example2(col);
+        INSERT INTO example2(id, col) VALUES (5, 50);
+        
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example2;"),
+        vec![1, 2, 3, 5]
+    );
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example2 WHERE col > 15;"),
+        vec![20, 30, 50]
+    );
+
+    // Empty batch.
+    conn.execute_batch("").unwrap();
+    conn.execute_batch("  \n ").unwrap();
+}
+
+#[test]
+fn test_execute_batch_comments() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    conn.execute_batch(
+        "-- Migration 2: add example2.
+        /*
+         * example2 keeps the values; of example.
+         */
+        CREATE TABLE example2(
+            id integer primary key, -- the rowid
+            col /* any type */
+        );
+        INSERT INTO example2(col) VALUES (10); -- first
+        INSERT INTO example2(col) /* ; */ VALUES (20);
+        -- INSERT INTO example2(col) VALUES (30);
+        /* the last comment is not terminated",
+    )
+    .unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT id FROM example2;"), vec![1, 2]);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example2;"),
+        vec![10, 20]
+    );
+
+    // Comments only.
+    conn.execute_batch("-- nothing to do").unwrap();
+    conn.execute_batch("/* nothing */ -- to do\n").unwrap();
+
+    // The last statement may end with a comment instead of a semicolon.
+    conn.execute_batch("INSERT INTO example2(col) VALUES (30) -- no semicolon")
+        .unwrap();
+    conn.execute_batch("INSERT INTO example2(col) VALUES (40) /* no semicolon")
+        .unwrap();
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example2;"),
+        vec![10, 20, 30, 40]
+    );
+}
+
+#[test]
+fn test_execute_batch_rollback() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col UNIQUE);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Fails on the constraint violation.
+    assert!(matches!(
+        conn.execute_batch(
+            "CREATE TABLE example2(col);
+            INSERT INTO example(col) VALUES (2);
+            INSERT INTO example(col) VALUES (1);
+            INSERT INTO example(col) VALUES (3);"
+        ),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![1]);
+    assert!(conn.prepare("SELECT * FROM example2;").is_err());
+
+    // Fails on the parse error and the missing semicolon.
+    for batch in [
+        "INSERT INTO example(col) VALUES (2); INSERT INTO;",
+        "INSERT INTO example(col) VALUES (2); INSERT INTO example(col) VALUES (3)",
+        "INSERT INTO example(col) VALUES (2); SELECT * FROM invalid;",
+    ] {
+        assert!(conn.execute_batch(batch).is_err(), "{}", batch);
+        assert_eq!(
+            load_rowids(&conn, "SELECT col FROM example;"),
+            vec![1],
+            "{}",
+            batch
+        );
+    }
+
+    // A failed write aborts the running transaction.
+    let txn = conn.begin().unwrap();
+    assert!(matches!(
+        conn.execute_batch(
+            "INSERT INTO example(col) VALUES (2); INSERT INTO example(col) VALUES (1);"
+        ),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    assert!(matches!(
+        conn.execute_batch("INSERT INTO example(col) VALUES (3);"),
+        Err(Error::TransactionAborted)
+    ));
+    assert!(matches!(txn.commit(), Err(Error::TransactionAborted)));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example;"),
+        vec![1]
+    );
+}
+
+#[test]
+fn test_execute_batch_in_transaction() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // The batch runs in the running transaction and is committed with it.
+    let txn = conn.begin().unwrap();
+    conn.prepare("INSERT INTO example(col) VALUES (1);")
+        .unwrap()
+        .execute()
+        .unwrap();
+    conn.execute_batch(
+        "CREATE TABLE example2(col); INSERT INTO example2(col) VALUES (10); INSERT INTO \
+         example(col) VALUES (2);",
+    )
+    .unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![1, 2]);
+    assert!(load_test_rowids(&test_conn, "SELECT col FROM example;").is_empty());
+    txn.commit().unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example;"),
+        vec![1, 2]
+    );
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example2;"),
+        vec![10]
+    );
+
+    // The batch is rolled back with the running transaction.
+    let txn = conn.begin().unwrap();
+    conn.execute_batch("INSERT INTO example(col) VALUES (3);")
+        .unwrap();
+    txn.rollback();
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![1, 2]);
+
+    // The first error is returned and the statements before it stay in the
+    // transaction.
+    let txn = conn.begin().unwrap();
+    assert!(conn
+        .execute_batch("INSERT INTO example(col) VALUES (4); SELECT * FROM invalid;")
+        .is_err());
+    txn.commit().unwrap();
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example;"),
+        vec![1, 2, 4]
+    );
+}
+
+#[test]
+fn test_transaction_changes() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let execute = |sql: &str| conn.prepare(sql).unwrap().execute();
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(
+        execute("INSERT INTO example(col) VALUES (1), (2), (3);").unwrap(),
+        3
+    );
+    assert_eq!(
+        execute("UPDATE example SET col = col + 1 WHERE col > 1;").unwrap(),
+        2
+    );
+    assert_eq!(execute("DELETE FROM example WHERE col = 1;").unwrap(), 1);
+    assert_eq!(conn.changes(), 1);
+    assert_eq!(conn.total_changes(), 6);
+    txn.commit().unwrap();
+    assert_eq!(conn.changes(), 1);
+    assert_eq!(conn.total_changes(), 6);
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(
+        execute("INSERT INTO example(col) VALUES (4), (5);").unwrap(),
+        2
+    );
+    assert_eq!(execute("DELETE FROM example WHERE col > 2;").unwrap(), 4);
+    assert_eq!(conn.changes(), 4);
+    assert_eq!(conn.total_changes(), 12);
+    txn.rollback();
+    assert_eq!(conn.changes(), 1);
+    assert_eq!(conn.total_changes(), 6);
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![3, 4]);
+
+    // The changes are discarded if the transaction is aborted by a failure.
+    let txn = conn.begin().unwrap();
+    assert_eq!(execute("INSERT INTO example(col) VALUES (6);").unwrap(), 1);
+    assert_eq!(conn.total_changes(), 7);
+    assert!(execute("INSERT INTO example(rowid) VALUES (2);").is_err());
+    assert!(txn.commit().is_err());
+    assert_eq!(conn.changes(), 1);
+    assert_eq!(conn.total_changes(), 6);
+
+    assert_eq!(execute("INSERT INTO example(col) VALUES (7);").unwrap(), 1);
+    assert_eq!(conn.changes(), 1);
+    assert_eq!(conn.total_changes(), 7);
+}
+
+#[test]
+fn test_change_counter() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let read_header = || {
+        let buf = std::fs::read(file.path()).unwrap();
+        (
+            u32::from_be_bytes(buf[24..28].try_into().unwrap()),
+            u32::from_be_bytes(buf[92..96].try_into().unwrap()),
+        )
+    };
+    let (counter, version_valid_for) = read_header();
+    assert_eq!(version_valid_for, counter);
+    assert_eq!(conn.change_counter().unwrap(), counter);
+
+    let stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (1);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    assert_eq!(read_header(), (counter + 1, counter + 1));
+
+    let txn = conn.begin().unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    assert_eq!(stmt.execute().unwrap(), 1);
+    txn.commit().unwrap();
+    assert_eq!(read_header(), (counter + 2, counter + 2));
+    assert_eq!(conn.change_counter().unwrap(), counter + 2);
+
+    // Nothing is written by a statement which changes no row or a transaction
+    // rolled back.
+    let stmt = conn.prepare("DELETE FROM example WHERE col = 2;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+    assert_eq!(read_header(), (counter + 2, counter + 2));
+    let txn = conn.begin().unwrap();
+    conn.prepare("DELETE FROM example;")
+        .unwrap()
+        .execute()
+        .unwrap();
+    txn.rollback();
+    assert_eq!(read_header(), (counter + 2, counter + 2));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    test_conn
+        .execute("INSERT INTO example(col) VALUES (3);", [])
+        .unwrap();
+    drop(stmt);
+    drop(conn);
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(conn.change_counter().unwrap(), counter + 3);
+}
+
+#[test]
+fn test_legacy_database_size() {
+    let file = create_sqlite_database(&[
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    // A legacy writer leaves the version-valid-for number and the database
+    // size stale.
+    let mut buf = std::fs::read(file.path()).unwrap();
+    let n_pages = u32::from_be_bytes(buf[28..32].try_into().unwrap());
+    buf[28..32].copy_from_slice(&1_u32.to_be_bytes());
+    buf[92..96].copy_from_slice(&0_u32.to_be_bytes());
+    std::fs::write(file.path(), &buf).unwrap();
+
+    let conn = Connection::open(file.path()).unwrap();
+    assert_eq!(load_rowids(&conn, "SELECT col FROM example;"), vec![1]);
+    let stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    // The database size is written back with the valid version-valid-for.
+    let buf = std::fs::read(file.path()).unwrap();
+    assert_eq!(u32::from_be_bytes(buf[28..32].try_into().unwrap()), n_pages);
+    assert_eq!(buf[24..28], buf[92..96]);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col FROM example;"),
+        vec![1, 2]
+    );
+}
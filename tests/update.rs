@@ -0,0 +1,457 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+use prsqlite::Value;
+
+#[test]
+fn test_update() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example (col1, col2) VALUES (10, 'a');",
+        "INSERT INTO example (col1, col2) VALUES (20, 'b');",
+        "INSERT INTO example (col1, col2) VALUES (30, 'c');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn.prepare("UPDATE example SET col1 = 0;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Integer(0)),
+                Some(&Value::Text(b"a"[..].into())),
+            ],
+            &[
+                Some(&Value::Integer(0)),
+                Some(&Value::Text(b"b"[..].into())),
+            ],
+            &[
+                Some(&Value::Integer(0)),
+                Some(&Value::Text(b"c"[..].into())),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_where() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        "INSERT INTO example (col1, col2) VALUES (10, 'a');",
+        "INSERT INTO example (col1, col2) VALUES (20, 'b');",
+        "INSERT INTO example (col1, col2) VALUES (30, 'c');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Assigned values are computed from the original row.
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = col2, col2 = col2 || col1 WHERE col1 >= 20;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let stmt = conn
+        .prepare("UPDATE example SET col2 = NULL WHERE rowid = 1;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let stmt = conn
+        .prepare("UPDATE example SET col2 = 'd' WHERE col1 = 100;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(10)), None],
+            &[
+                Some(&Value::Text(b"b"[..].into())),
+                Some(&Value::Text(b"b20"[..].into())),
+            ],
+            &[
+                Some(&Value::Text(b"c"[..].into())),
+                Some(&Value::Text(b"c30"[..].into())),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_type_affinity() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 integer, col2 text);",
+        "INSERT INTO example (col1, col2) VALUES (10, 'a');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = '20', col2 = 30;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[&[
+            Some(&Value::Integer(20)),
+            Some(&Value::Text(b"30"[..].into())),
+        ]],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_not_null() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 not null, col2);",
+        "INSERT INTO example (col1, col2) VALUES (1, 1);",
+        "INSERT INTO example (col1, col2) VALUES (2, 2);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = NULL WHERE col2 = 2;")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::NotNullConstraintViolation)
+    ));
+    let stmt = conn.prepare("UPDATE example SET col2 = NULL;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(1)), None],
+            &[Some(&Value::Integer(2)), None],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_check_constraint() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(name, age CHECK(age >= 0));",
+        "INSERT INTO example (name, age) VALUES ('a', 1);",
+        "INSERT INTO example (name, age) VALUES ('b', 2);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // No row is updated if any row violates the constraint.
+    let stmt = conn.prepare("UPDATE example SET age = age - 2;").unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::CheckConstraintViolation)
+    ));
+    let stmt = conn.prepare("UPDATE example SET age = age - 1;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[
+            &[
+                Some(&Value::Text(b"a"[..].into())),
+                Some(&Value::Integer(0)),
+            ],
+            &[
+                Some(&Value::Text(b"b"[..].into())),
+                Some(&Value::Integer(1)),
+            ],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_strict_table() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 INTEGER, col2 ANY) STRICT;",
+        "INSERT INTO example (col1, col2) VALUES (1, 1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn.prepare("UPDATE example SET col1 = 'abc';").unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::DataTypeConstraintViolation)
+    ));
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = '2', col2 = 'abc';")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_same_results(
+        &[&[
+            Some(&Value::Integer(2)),
+            Some(&Value::Text(b"abc"[..].into())),
+        ]],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_index() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2, col3);",
+        "CREATE INDEX index1 ON example(col1);",
+        "CREATE INDEX index2 ON example(col2);",
+        "INSERT INTO example (col1, col2, col3) VALUES (1, 10, 100);",
+        "INSERT INTO example (col1, col2, col3) VALUES (2, 20, 200);",
+        "INSERT INTO example (col1, col2, col3) VALUES (3, 30, 300);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // The filter uses index1 which is updated.
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = 3, col3 = 0 WHERE col1 = 2;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = 2 WHERE col1 = 3;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 2);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for (query, expected) in [
+        ("SELECT rowid FROM example WHERE col1 = 1;", vec![1]),
+        ("SELECT rowid FROM example WHERE col1 = 2;", vec![2, 3]),
+        ("SELECT rowid FROM example WHERE col1 = 3;", vec![]),
+        ("SELECT rowid FROM example WHERE col2 = 20;", vec![2]),
+        ("SELECT rowid FROM example WHERE col3 = 0;", vec![2]),
+    ] {
+        assert_eq!(load_rowids(&conn, query), expected, "{}", query);
+        assert_eq!(load_test_rowids(&test_conn, query), expected, "{}", query);
+    }
+}
+
+#[test]
+fn test_update_unique() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1 unique, col2);",
+        "INSERT INTO example (col1, col2) VALUES (1, 1);",
+        "INSERT INTO example (col1, col2) VALUES (2, 2);",
+        "INSERT INTO example (col1, col2) VALUES (NULL, 3);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = 1 WHERE col2 = 2;")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    // Updating a row to its own value is not a conflict.
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = 1 WHERE col2 = 1;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = NULL WHERE col2 = 2;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results(
+        &[
+            &[Some(&Value::Integer(1)), Some(&Value::Integer(1))],
+            &[None, Some(&Value::Integer(2))],
+            &[None, Some(&Value::Integer(3))],
+        ],
+        "SELECT * FROM example;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_multiple_level() {
+    let mut stmts = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "CREATE INDEX index1 ON example(col1);",
+    ];
+    let mut insert_stmts = Vec::with_capacity(500);
+    for i in 0..500 {
+        insert_stmts.push(format!(
+            "INSERT INTO example(col1, col2) VALUES ({}, x'{}');",
+            i % 10,
+            "11".repeat(i % 100)
+        ));
+    }
+    stmts.extend(insert_stmts.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&stmts);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Grow and shrink records.
+    let stmt = conn
+        .prepare("UPDATE example SET col1 = 10, col2 = x'2222222222222222222222222222222222222222222222222222222222222222' WHERE col1 = 3;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 50);
+    let stmt = conn
+        .prepare("UPDATE example SET col2 = NULL WHERE col1 = 4;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 50);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for i in 0..11 {
+        let query = format!("SELECT rowid FROM example WHERE col1 = {};", i);
+        assert_eq!(
+            load_rowids(&conn, &query),
+            load_test_rowids(&test_conn, &query),
+            "{}",
+            query
+        );
+    }
+    let v = Value::Blob(vec![0x22; 32].into());
+    let expected = vec![[Some(&Value::Integer(10)), Some(&v)]; 50];
+    assert_same_results(
+        &expected
+            .iter()
+            .map(|row| row.as_slice())
+            .collect::<Vec<_>>(),
+        "SELECT col1, col2 FROM example WHERE col1 = 10;",
+        &test_conn,
+        &conn,
+    );
+    let expected = vec![[Some(&Value::Integer(4)), None]; 50];
+    assert_same_results(
+        &expected
+            .iter()
+            .map(|row| row.as_slice())
+            .collect::<Vec<_>>(),
+        "SELECT col1, col2 FROM example WHERE col1 = 4;",
+        &test_conn,
+        &conn,
+    );
+}
+
+#[test]
+fn test_update_fail() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col);",
+        "INSERT INTO example (id, col) VALUES (1, 10);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    assert!(conn.prepare("UPDATE example SET invalid = 2;").is_err());
+    assert!(conn.prepare("UPDATE invalid SET col = 2;").is_err());
+}
+
+#[test]
+fn test_update_rowid() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col1, col2 unique);",
+        "CREATE INDEX index1 ON example(col1);",
+        "INSERT INTO example (id, col1, col2) VALUES (1, 10, 'a');",
+        "INSERT INTO example (id, col1, col2) VALUES (2, 20, 'b');",
+        "INSERT INTO example (id, col1, col2) VALUES (3, 30, 'c');",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn.prepare("UPDATE example SET id = id + 10;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 3);
+    let stmt = conn
+        .prepare("UPDATE example SET rowid = ' 7 ', col1 = 70 WHERE col2 = 'b';")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let mut stmt = conn
+        .prepare("UPDATE example SET id = ? WHERE id = ?;")
+        .unwrap();
+    stmt.bind(1, Some(Value::Integer(1))).unwrap();
+    stmt.bind(2, Some(Value::Integer(11))).unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    // Updating a row to its own rowid is not a conflict.
+    let stmt = conn
+        .prepare("UPDATE example SET id = 13 WHERE id = 13;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT * FROM example;",
+        "SELECT rowid, col1 FROM example WHERE col1 = 70;",
+        "SELECT rowid, col2 FROM example WHERE col2 = 'c';",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        load_rowids(&conn, "SELECT rowid FROM example;"),
+        vec![1, 7, 13]
+    );
+}
+
+#[test]
+fn test_update_rowid_fail() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key, col);",
+        "INSERT INTO example (id, col) VALUES (1, 10);",
+        "INSERT INTO example (id, col) VALUES (2, 20);",
+        "INSERT INTO example (id, col) VALUES (3, 30);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+
+    // Rows are moved one by one, so the new rowid of the first row conflicts with
+    // the second row.
+    let stmt = conn.prepare("UPDATE example SET id = id + 1;").unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    let stmt = conn
+        .prepare("UPDATE example SET rowid = 3 WHERE id = 1;")
+        .unwrap();
+    assert!(matches!(
+        stmt.execute(),
+        Err(Error::UniqueConstraintViolation)
+    ));
+    let stmt = conn.prepare("UPDATE example SET id = NULL;").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::DataTypeMismatch)));
+    let stmt = conn.prepare("UPDATE example SET id = 'x';").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::DataTypeMismatch)));
+    let stmt = conn.prepare("UPDATE example SET id = 1.5;").unwrap();
+    assert!(matches!(stmt.execute(), Err(Error::DataTypeMismatch)));
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT rowid FROM example;"),
+        vec![1, 2, 3]
+    );
+}
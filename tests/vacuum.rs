@@ -0,0 +1,210 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::*;
+use prsqlite::Connection;
+use prsqlite::Error;
+
+#[test]
+fn test_vacuum() {
+    let mut queries = vec![
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2 text collate nocase, col3);",
+        "CREATE INDEX index1 ON example(col2);",
+        "CREATE TABLE example2(col1 primary key, col2) WITHOUT ROWID;",
+        "CREATE VIEW view1 AS SELECT col1 FROM example;",
+    ];
+    let insert_queries = (0..1000)
+        .map(|i| {
+            format!(
+                "INSERT INTO example(col1, col2, col3) VALUES ({}, '{}', x'{}');",
+                i,
+                if i % 2 == 0 { "a" } else { "B" }.repeat(i % 10),
+                "11".repeat(i % 1000),
+            )
+        })
+        .chain((0..20).map(|i| {
+            format!(
+                "INSERT INTO example2(col1, col2) VALUES ({}, '{}');",
+                i,
+                "c".repeat(i)
+            )
+        }))
+        .collect::<Vec<_>>();
+    queries.extend(insert_queries.iter().map(|s| s.as_str()));
+    let file = create_sqlite_database(&queries);
+    let conn = Connection::open(file.path()).unwrap();
+
+    let stmt = conn
+        .prepare("DELETE FROM example WHERE col1 % 10 != 4;")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 900);
+    let size_before = file.as_file().metadata().unwrap().len();
+
+    let stmt = conn.prepare("VACUUM;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    let size_after = file.as_file().metadata().unwrap().len();
+    assert!(
+        size_after < size_before / 2,
+        "{} {}",
+        size_before,
+        size_after
+    );
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(&test_conn, "PRAGMA freelist_count;"),
+        vec![0]
+    );
+    assert_eq!(
+        load_test_rowids(&test_conn, "PRAGMA page_count;"),
+        vec![size_after as i64 / 512]
+    );
+    for query in [
+        "SELECT rowid, col1, col2, col3 FROM example;",
+        "SELECT rowid, col2 FROM example WHERE col2 = 'AAAA';",
+        "SELECT col1, col2 FROM example2;",
+        "SELECT name, tbl_name, sql FROM sqlite_schema;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        load_test_rowids(&test_conn, "SELECT col1 FROM view1;").len(),
+        100
+    );
+
+    // The database is writable after vacuum.
+    let stmt = conn
+        .prepare("INSERT INTO example(col1, col2) VALUES (1000, 'AAAA');")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_eq!(
+        load_test_rowids(
+            &test_conn,
+            "SELECT count(*) FROM example INDEXED BY index1 WHERE col2 = 'aaaa';"
+        ),
+        vec![101]
+    );
+}
+
+#[test]
+fn test_vacuum_large_table() {
+    let file = create_sqlite_database(&[
+        "PRAGMA page_size = 512;",
+        "CREATE TABLE example(col1, col2);",
+        "WITH RECURSIVE t(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM t WHERE i < 30000) INSERT INTO example(col1, col2) SELECT i, hex(randomblob(i % 50)) FROM t;",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let size_before = file.as_file().metadata().unwrap().len();
+
+    assert_eq!(conn.prepare("VACUUM;").unwrap().execute().unwrap(), 0);
+
+    // The table is bulk loaded with the leaf pages filled to capacity as
+    // SQLite does for appended rows.
+    let size_after = file.as_file().metadata().unwrap().len();
+    assert!(size_after <= size_before, "{} {}", size_before, size_after);
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite("SELECT rowid, col1, col2 FROM example;", &test_conn, &conn);
+}
+
+#[test]
+fn test_vacuum_invalidates_statements() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col);",
+        "INSERT INTO example(col) VALUES (1);",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let insert_stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (2);")
+        .unwrap();
+
+    assert_eq!(conn.prepare("VACUUM;").unwrap().execute().unwrap(), 0);
+    assert!(matches!(insert_stmt.execute(), Err(Error::SchemaChanged)));
+    assert_eq!(load_rowids(&conn, "SELECT rowid FROM example;"), vec![1]);
+}
+
+#[test]
+fn test_vacuum_fail() {
+    let file = create_sqlite_database(&["CREATE TABLE example(col);"]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("VACUUM;").unwrap();
+
+    let txn = conn.begin().unwrap();
+    assert!(stmt.execute().is_err());
+    drop(txn);
+
+    let conn = Connection::open_readonly(file.path()).unwrap();
+    let stmt = conn.prepare("VACUUM;").unwrap();
+    assert!(stmt.execute().is_err());
+}
+
+#[test]
+fn test_vacuum_internal_tables() {
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(id integer primary key autoincrement, col);",
+        "CREATE INDEX index1 ON example(col);",
+        "INSERT INTO example(col) VALUES (1), (2), (3), (2);",
+        "DELETE FROM example WHERE id >= 3;",
+        "ANALYZE;",
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+
+    // The internal tables are readable as ordinary tables.
+    for query in [
+        "SELECT * FROM sqlite_sequence;",
+        "SELECT name, seq FROM SQLITE_SEQUENCE WHERE name = 'example';",
+        "SELECT * FROM sqlite_stat1;",
+        "SELECT type, name, tbl_name, sql FROM sqlite_schema;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+    assert_eq!(
+        load_rowids(&conn, "SELECT seq FROM sqlite_sequence;"),
+        vec![4]
+    );
+
+    let stmt = conn.prepare("VACUUM;").unwrap();
+    assert_eq!(stmt.execute().unwrap(), 0);
+
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    for query in [
+        "SELECT * FROM sqlite_sequence;",
+        "SELECT * FROM sqlite_stat1;",
+        "SELECT type, name, tbl_name, sql FROM sqlite_schema;",
+    ] {
+        assert_same_results_as_sqlite(query, &test_conn, &conn);
+    }
+
+    // AUTOINCREMENT continues from the sequence kept by VACUUM.
+    let stmt = conn
+        .prepare("INSERT INTO example(col) VALUES (5);")
+        .unwrap();
+    assert_eq!(stmt.execute().unwrap(), 1);
+    assert_eq!(
+        load_rowids(&conn, "SELECT id FROM example WHERE col = 5;"),
+        vec![5]
+    );
+    let test_conn = rusqlite::Connection::open(file.path()).unwrap();
+    assert_integrity_check(&test_conn);
+    assert_same_results_as_sqlite("SELECT * FROM sqlite_sequence;", &test_conn, &conn);
+}
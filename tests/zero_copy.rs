@@ -0,0 +1,98 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+use common::*;
+use prsqlite::Buffer;
+use prsqlite::Connection;
+use prsqlite::Value;
+
+/// Counts the bytes allocated by the current thread so that tests running in
+/// parallel do not affect each other.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.with(|allocated| allocated.set(allocated.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocated() -> usize {
+    ALLOCATED.with(|allocated| allocated.get())
+}
+
+#[test]
+fn test_get_bytes_local_payload() {
+    let text = "a".repeat(1000);
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        &format!("INSERT INTO example(col1, col2) VALUES ('{text}', x'0102');"),
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("SELECT col1, col2 FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+
+    let before = allocated();
+    let columns = row.parse().unwrap();
+    let col1 = columns.get_bytes(0).unwrap();
+    let col2 = columns.get_bytes(1).unwrap();
+    // Only the list of the values is allocated.
+    assert!(allocated() - before < text.len());
+
+    assert_eq!(col1, text.as_bytes());
+    assert_eq!(col2, &[1, 2]);
+    assert!(matches!(columns.get(0), Some(Value::Text(Buffer::Ref(_)))));
+    assert!(matches!(columns.get(1), Some(Value::Blob(Buffer::Ref(_)))));
+
+    let before = allocated();
+    assert_eq!(columns.get_bytes(0).unwrap().len(), text.len());
+    assert_eq!(allocated(), before);
+}
+
+#[test]
+fn test_get_bytes_overflow_payload() {
+    let text = "a".repeat(10000);
+    let file = create_sqlite_database(&[
+        "CREATE TABLE example(col1, col2);",
+        &format!("INSERT INTO example(col1, col2) VALUES ('{text}', 10);"),
+    ]);
+    let conn = Connection::open(file.path()).unwrap();
+    let stmt = conn.prepare("SELECT col1, col2 FROM example;").unwrap();
+    let mut rows = stmt.query().unwrap();
+    let row = rows.next_row().unwrap().unwrap();
+    let columns = row.parse().unwrap();
+
+    assert_eq!(columns.get_bytes(0).unwrap(), text.as_bytes());
+    assert_eq!(columns.get_bytes(1), None);
+    assert_eq!(columns.get_bytes(2), None);
+}